@@ -0,0 +1,91 @@
+//! Reads programs in the textual assembly format, then compiles and runs
+//! their `main` functions
+//!
+//! Each program is ended by a line with just `.` on it, or the end of the
+//! input. Any arguments given are passed to `main`.
+//!
+//! ```text
+//! $ cargo run --example asm_repl 4
+//! > function main(%x: int) -> int {
+//! >     %y = mul %x, %x
+//! >     ret %y
+//! > }
+//! > .
+//! 16
+//! ```
+extern crate jit;
+use jit::*;
+use jit::interp::Value;
+use std::env;
+use std::io::prelude::*;
+use std::io;
+
+static PROMPT:&'static str = "> ";
+
+fn argument(text: &str, ty: &str) -> Result<Value, String> {
+    let bad = |_| format!("`{}` is not a valid {}", text, ty);
+    Ok(match ty {
+        "sbyte" | "short" | "int" => Value::Int(try!(text.parse().map_err(bad))),
+        "ubyte" | "ushort" | "uint" => Value::UInt(try!(text.parse().map_err(bad))),
+        "long" | "nint" => Value::Long(try!(text.parse().map_err(bad))),
+        "ulong" | "nuint" | "ptr" => Value::ULong(try!(text.parse().map_err(bad))),
+        "float32" => Value::Float32(try!(text.parse().map_err(bad))),
+        "float64" | "nfloat" => Value::Float64(try!(text.parse().map_err(bad))),
+        _ => return Err(format!("can't pass a {} to main", ty))
+    })
+}
+
+fn run(text: &str, args: &[String]) -> Result<Value, String> {
+    let program = try!(asm::parse(text).map_err(|error| error.to_string()));
+    let main = match program.functions.iter().find(|func| func.name == "main") {
+        Some(main) => main,
+        None => return Err("there is no main function".to_string())
+    };
+    if main.params.len() != args.len() {
+        return Err(format!("main takes {} arguments, but {} were given", main.params.len(), args.len()))
+    }
+    let mut values = Vec::new();
+    for (arg, &(_, ref ty)) in args.iter().zip(main.params.iter()) {
+        values.push(try!(argument(arg, ty)));
+    }
+    let mut ctx = Context::<()>::new();
+    let funcs = try!(program.build(&mut ctx).map_err(|error| error.to_string()));
+    let mut compiled = Vec::new();
+    for (name, func) in funcs {
+        compiled.push((name, try!(func.try_compile().map_err(|error| error.to_string()))));
+    }
+    let &(_, ref main) = compiled.iter().find(|&&(ref name, _)| name == "main").unwrap();
+    interp::call(main, &values).map_err(|error| error.to_string())
+}
+
+fn main() {
+    let args:Vec<String> = env::args().skip(1).collect();
+    let input = io::stdin();
+    let mut output = io::stdout();
+    let mut text = String::new();
+    let mut lines = input.lock().lines();
+    loop {
+        output.write(PROMPT.as_bytes()).unwrap();
+        output.flush().unwrap();
+        let line = lines.next().map(|line| line.unwrap());
+        let done = match line {
+            Some(ref line) if line.trim() == "." => true,
+            Some(ref line) => {
+                text.push_str(line);
+                text.push('\n');
+                false
+            },
+            None => true
+        };
+        if done && !text.trim().is_empty() {
+            match run(&text, &args) {
+                Ok(value) => println!("{}", value),
+                Err(error) => println!("error: {}", error)
+            }
+            text.clear();
+        }
+        if line.is_none() {
+            break
+        }
+    }
+}
@@ -49,7 +49,8 @@ fn compile<'a>(func: &UncompiledFunction<'a>, code: &str) {
     let ubyte = typecs::get_ubyte();
     let putchar_sig = get::<fn(u8)>();
     let readchar_sig = get::<fn() -> u8>();
-    let ref data = func[0];
+    let data_var = func.param_lvalue(0);
+    let data = data_var.get_local().unwrap();
     let mut current_loop = None;
     let mut code = code.chars().peekable();
     while let Some(c) = code.next() {
@@ -58,12 +59,12 @@ fn compile<'a>(func: &UncompiledFunction<'a>, code: &str) {
             '>' => {
                 let amount = count(func, &mut code, c);
                 let new_value = data + amount;
-                func.insn_store(data, new_value);
+                func.insn_store(&data_var, new_value);
             },
             '<' => {
                 let amount = count(func, &mut code, c);
                 let new_value = data - amount;
-                func.insn_store(data, new_value);
+                func.insn_store(&data_var, new_value);
             },
             '+' => {
                 let amount = count(func, &mut code, c);
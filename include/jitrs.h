@@ -0,0 +1,42 @@
+/* C interface to jit.rs, available when it is built with the `capi` feature */
+#ifndef JITRS_H
+#define JITRS_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#define JITRS_CONST 0
+#define JITRS_PARAM 1
+#define JITRS_ADD 2
+#define JITRS_SUB 3
+#define JITRS_MUL 4
+#define JITRS_DIV 5
+#define JITRS_REM 6
+#define JITRS_NEG 7
+#define JITRS_AND 8
+#define JITRS_OR 9
+#define JITRS_XOR 10
+#define JITRS_SHL 11
+#define JITRS_SHR 12
+#define JITRS_LT 13
+#define JITRS_EQ 14
+#define JITRS_RETURN 15
+
+#define JITRS_OK 0
+#define JITRS_BAD_OPCODE 1
+#define JITRS_MISSING_OPERAND 2
+#define JITRS_BAD_PARAM 3
+#define JITRS_STACK_UNDERFLOW 4
+#define JITRS_BAD_RETURN 5
+#define JITRS_NULL 6
+#define JITRS_COMPILE_FAILED 7
+
+typedef struct jitrs_context jitrs_context;
+
+jitrs_context* jitrs_context_new(void);
+void jitrs_context_free(jitrs_context* ctx);
+const char* jitrs_error_message(int error);
+void* jitrs_compile(jitrs_context* ctx, unsigned int params,
+                    const int64_t* code, size_t len, int* error);
+
+#endif
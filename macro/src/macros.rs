@@ -220,7 +220,7 @@ fn expand_derive_compile(cx: &mut ExtCtxt, sp: Span, _meta: &MetaItem, item: &An
                 let names = cx.expr_vec(sp, names);
                 type_expr = quote_expr!(cx, {
                     let mut ty: Type = $type_expr;
-                    ty.set_names(&$names);
+                    ty.set_names(&$names).unwrap();
                     ty.into()
                 })
             }
@@ -508,7 +508,7 @@ macro_rules! jit_struct(
         let mut ty = Type::new_struct(&mut [
             $(&get::<$ty>()),*
         ]);
-        ty.set_names(&[$(stringify!($name)),*]);
+        ty.set_names(&[$(stringify!($name)),*]).unwrap();
         ty
     });
     ($($ty:ty),+ ) => (
@@ -525,7 +525,7 @@ macro_rules! jit_union(
         let union = Type::new_union(&mut [
             $(&get::<$ty>()),*
         ]);
-        union.set_names(&[$(stringify!($name)),*]);
+        union.set_names(&[$(stringify!($name)),*]).unwrap();
         union
     });
     ($($ty:ty),+ ) => (
@@ -560,16 +560,16 @@ macro_rules! jit(
         $func.insn_return(jit!($func, $($t)+))
     );
     ($func:ident, $var:ident += $($t:tt)+) => (
-        $func.insn_store($var, &$func.insn_add($var, jit!($func, $($t)+)));
+        $func.insn_store(&$var, $func.insn_add($func.insn_read(&$var).get(), jit!($func, $($t)+)));
     );
     ($func:ident, $var:ident -= $($t:tt)+) => (
-        $func.insn_store($var, &$func.insn_sub($var, jit!($func, $($t)+)));
+        $func.insn_store(&$var, $func.insn_sub($func.insn_read(&$var).get(), jit!($func, $($t)+)));
     );
     ($func:ident, $var:ident *= $($t:tt)+) => (
-        $func.insn_store($var, &$func.insn_mul($var, jit!($func, $($t)+)));
+        $func.insn_store(&$var, $func.insn_mul($func.insn_read(&$var).get(), jit!($func, $($t)+)));
     );
     ($func:ident, $var:ident /= $($t:tt)+) => (
-        $func.insn_store($var, &$func.insn_div($var, jit!($func, $($t)+)));
+        $func.insn_store(&$var, $func.insn_div($func.insn_read(&$var).get(), jit!($func, $($t)+)));
     );
     ($func:ident, $($a:tt)+ + $($b:tt)+) => (
         $func.insn_add(jit!($func, $($a)+), jit!($func, $($b)+))
@@ -590,7 +590,7 @@ macro_rules! jit(
         $func.insn_sqrt(&jit!($func, $($t)+))
     );
     ($func:ident, $var:ident = $($t:tt)+) => (
-        $func.insn_store($var, jit!($func, $val));
+        $func.insn_store(&$var, jit!($func, $($t)+));
     );
     ($func:ident, *$var:ident) => (
         $func.insn_load($var)
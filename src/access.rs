@@ -0,0 +1,86 @@
+use types::{Type, Ty};
+use value::Val;
+
+/// Finds the named fields of a struct type, so they can be loaded and stored
+/// through a pointer without working out their offsets by hand
+///
+/// ```rust
+/// use jit::*;
+/// let mut point_t = Type::new_struct(&mut [&get::<i32>(), &get::<f64>()]);
+/// point_t.set_names(&["id", "weight"]).unwrap();
+/// let point = StructAccess::new(&point_t);
+/// let mut ctx = Context::<()>::new();
+/// let func = UncompiledFunction::new(&mut ctx, &get::<fn(&'static u8) -> f64>());
+/// let weight = point.field(&func[0], "weight");
+/// assert_eq!(weight.get_offset(), 8);
+/// weight.store(func.insn_mul(weight.load(), func.insn_of(2.0f64)));
+/// func.insn_return(weight.load());
+/// ```
+pub struct StructAccess<'t> {
+    ty: &'t Ty
+}
+impl<'t> StructAccess<'t> {
+    /// Make an accessor for the fields of the struct or union type given
+    pub fn new(ty: &'t Ty) -> StructAccess<'t> {
+        if !ty.is_struct() && !ty.is_union() {
+            panic!("StructAccess needs a struct or union type, got {:?}", ty)
+        }
+        StructAccess {
+            ty: ty
+        }
+    }
+    /// Get the type the fields are found in
+    pub fn get_type(&self) -> &'t Ty {
+        self.ty
+    }
+    /// Get the field named `name` of the struct that `ptr` points to
+    pub fn field<'a>(&self, ptr: &'a Val, name: &str) -> FieldRef<'a> {
+        FieldRef::find(ptr, 0, self.ty, name)
+    }
+}
+
+/// A field of a struct behind a pointer, which can be loaded and stored
+pub struct FieldRef<'a> {
+    ptr: &'a Val,
+    offset: usize,
+    ty: Type
+}
+impl<'a> FieldRef<'a> {
+    fn find(ptr: &'a Val, base: usize, ty: &Ty, name: &str) -> FieldRef<'a> {
+        match ty.get_field(name) {
+            Some(field) => FieldRef {
+                ptr: ptr,
+                offset: base + field.get_offset(),
+                ty: field.get_type().to_owned()
+            },
+            None => panic!("{:?} has no field named {}", ty, name)
+        }
+    }
+    /// Get the offset of the field from the pointer
+    pub fn get_offset(&self) -> usize {
+        self.offset
+    }
+    /// Get the type of the field
+    pub fn get_type(&self) -> &Ty {
+        &self.ty
+    }
+    /// Get the field named `name` of this field, which must be a struct
+    pub fn field(&self, name: &str) -> FieldRef<'a> {
+        FieldRef::find(self.ptr, self.offset, &self.ty, name)
+    }
+    /// Make an instruction that loads the value of the field
+    pub fn load(&self) -> &'a Val {
+        self.ptr.get_function().insn_load_relative(self.ptr, self.offset, &self.ty)
+    }
+    /// Make instructions that store `value` in the field, converting it to
+    /// the field's type first, so it doesn't spill into the fields after it
+    pub fn store(&self, value: &'a Val) {
+        let func = self.ptr.get_function();
+        let value = if value.get_type() == &*self.ty { value } else { func.insn_convert(value, &self.ty, false) };
+        func.insn_store_relative(self.ptr, self.offset, value)
+    }
+    /// Make an instruction that gets the address of the field
+    pub fn address(&self) -> &'a Val {
+        self.ptr.get_function().insn_add_relative(self.ptr, self.offset)
+    }
+}
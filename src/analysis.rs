@@ -0,0 +1,320 @@
+//! Analyses of the graph of blocks in a function, for passes over its
+//! instructions
+//!
+//! A `BlockGraph` finds where each block of a function that hasn't been
+//! compiled yet can go next, and from that its dominators, its loops and how
+//! deeply they nest, and whether its control flow is reducible. Blocks are
+//! numbered in the order `UncompiledFunction::blocks` gives them, from the
+//! entry block at 0. Building instructions in the function after making a
+//! graph leaves the graph out of date.
+//!
+//! ```rust
+//! use jit::*;
+//! use jit::analysis::BlockGraph;
+//! let mut ctx = Context::<()>::new();
+//! let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+//! let total_var = func.new_lvalue(&get::<i32>());
+//! let total = total_var.get_local().unwrap();
+//! func.insn_store(&total_var, func.insn_of(0i32));
+//! func.insn_for_range(func.insn_of(0i32), &func[0], |index| {
+//!     func.insn_store(&total_var, func.insn_add(total, index));
+//! });
+//! func.insn_return(total);
+//! let graph = BlockGraph::new(&func);
+//! assert!(graph.is_reducible());
+//! let loops = graph.loops();
+//! assert_eq!(loops.get_loops().len(), 1);
+//! let header = loops.get_loops()[0].header;
+//! assert_eq!(loops.get_depth(header), 1);
+//! assert!(graph.dominators().dominates(0, header));
+//! ```
+use raw::*;
+use function::Func;
+use insn::{Block, Blocks};
+use ssa;
+use std::collections::HashMap;
+
+/// Marks a block the depth-first search from the entry never reached
+const UNVISITED: usize = !0;
+
+/// The blocks of a function and the edges between them
+pub struct BlockGraph<'a> {
+    blocks: Vec<Block<'a>>,
+    branches: Vec<Vec<usize>>,
+    falls_through: Vec<bool>,
+    preds: Vec<Vec<usize>>
+}
+impl<'a> BlockGraph<'a> {
+    /// Find the blocks of `func` and where each can go next
+    pub fn new(func: &Func) -> BlockGraph<'a> {
+        let raw: jit_function_t = func.into();
+        let blocks: Vec<Block<'a>> = Blocks::new(raw).collect();
+        let index: HashMap<jit_block_t, usize> = blocks.iter().enumerate()
+            .map(|(i, &block)| (block.into(), i))
+            .collect();
+        let branches: Vec<Vec<usize>> = blocks.iter().map(|&block| match block.iter().last() {
+            Some(insn) => ssa::targets(raw, insn).iter().filter_map(|target| index.get(target).cloned()).collect(),
+            None => Vec::new()
+        }).collect();
+        let falls_through: Vec<bool> = blocks.iter().enumerate()
+            .map(|(i, block)| !block.ends_in_dead() && i + 1 < blocks.len())
+            .collect();
+        let mut preds = vec![Vec::new(); blocks.len()];
+        for (i, targets) in branches.iter().enumerate() {
+            if falls_through[i] {
+                preds[i + 1].push(i);
+            }
+            for &target in targets {
+                preds[target].push(i);
+            }
+        }
+        BlockGraph {
+            blocks: blocks,
+            branches: branches,
+            falls_through: falls_through,
+            preds: preds
+        }
+    }
+    /// Get the blocks, in order
+    pub fn get_blocks(&self) -> &[Block<'a>] {
+        &self.blocks
+    }
+    /// Get the number of blocks
+    pub fn len(&self) -> usize {
+        self.blocks.len()
+    }
+    /// Get the blocks the instruction at the end of `block` can branch to
+    pub fn get_branch_targets(&self, block: usize) -> &[usize] {
+        &self.branches[block]
+    }
+    /// Check if `block` can carry on into the block after it
+    pub fn falls_through(&self, block: usize) -> bool {
+        self.falls_through[block]
+    }
+    /// Get every block `block` can go to next
+    pub fn get_successors(&self, block: usize) -> Vec<usize> {
+        let mut succs = self.branches[block].clone();
+        if self.falls_through[block] {
+            succs.push(block + 1);
+        }
+        succs
+    }
+    /// Get every block that can go to `block` next
+    pub fn get_predecessors(&self, block: usize) -> &[usize] {
+        &self.preds[block]
+    }
+    /// Search the graph depth first from the entry, giving when each block
+    /// was first and last visited, with `UNVISITED` for blocks it never
+    /// reaches, and the blocks it reached in reverse postorder
+    fn search(&self) -> (Vec<usize>, Vec<usize>, Vec<usize>) {
+        let (mut pre, mut post) = (vec![UNVISITED; self.len()], vec![UNVISITED; self.len()]);
+        let mut postorder = Vec::new();
+        if self.blocks.is_empty() {
+            return (pre, post, postorder)
+        }
+        let (mut next_pre, mut next_post) = (0, 0);
+        let mut stack = vec![(0, self.get_successors(0))];
+        pre[0] = next_pre;
+        next_pre += 1;
+        while !stack.is_empty() {
+            let next = stack.last_mut().unwrap().1.pop();
+            match next {
+                Some(succ) if pre[succ] == UNVISITED => {
+                    pre[succ] = next_pre;
+                    next_pre += 1;
+                    stack.push((succ, self.get_successors(succ)));
+                },
+                Some(_) => (),
+                None => {
+                    let (block, _) = stack.pop().unwrap();
+                    post[block] = next_post;
+                    next_post += 1;
+                    postorder.push(block);
+                }
+            }
+        }
+        postorder.reverse();
+        (pre, post, postorder)
+    }
+    /// Work out which blocks dominate which
+    pub fn dominators(&self) -> Dominators {
+        let (_, _, order) = self.search();
+        let mut number = vec![UNVISITED; self.len()];
+        for (n, &block) in order.iter().enumerate() {
+            number[block] = n;
+        }
+        let mut idom: Vec<Option<usize>> = vec![None; self.len()];
+        if let Some(&entry) = order.first() {
+            idom[entry] = Some(entry);
+        }
+        // Cooper, Harvey and Kennedy's iterative algorithm
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for &block in order.iter().skip(1) {
+                let mut new_idom = None;
+                for &pred in &self.preds[block] {
+                    if idom[pred].is_none() {
+                        continue
+                    }
+                    new_idom = Some(match new_idom {
+                        None => pred,
+                        Some(mut other) => {
+                            let mut finger = pred;
+                            while finger != other {
+                                while number[finger] > number[other] {
+                                    finger = idom[finger].unwrap();
+                                }
+                                while number[other] > number[finger] {
+                                    other = idom[other].unwrap();
+                                }
+                            }
+                            finger
+                        }
+                    });
+                }
+                if new_idom != idom[block] {
+                    idom[block] = new_idom;
+                    changed = true;
+                }
+            }
+        }
+        Dominators {
+            idom: idom
+        }
+    }
+    /// Find the natural loops, each made of the blocks that can reach a
+    /// branch back to a block that dominates it without going through that
+    /// block, and how deeply they nest
+    ///
+    /// Loops with the same header are merged into one.
+    pub fn loops(&self) -> LoopNest {
+        let doms = self.dominators();
+        let mut bodies: Vec<(usize, Vec<bool>)> = Vec::new();
+        for block in 0..self.len() {
+            for &header in self.get_successors(block).iter().filter(|&&header| doms.dominates(header, block)) {
+                let posn = match bodies.iter().position(|&(other, _)| other == header) {
+                    Some(posn) => posn,
+                    None => {
+                        let mut body = vec![false; self.len()];
+                        body[header] = true;
+                        bodies.push((header, body));
+                        bodies.len() - 1
+                    }
+                };
+                let body = &mut bodies[posn].1;
+                let mut work = vec![block];
+                while let Some(next) = work.pop() {
+                    if !body[next] {
+                        body[next] = true;
+                        work.extend(self.preds[next].iter().cloned());
+                    }
+                }
+            }
+        }
+        let mut loops: Vec<NaturalLoop> = bodies.into_iter().map(|(header, body)| NaturalLoop {
+            header: header,
+            blocks: (0..body.len()).filter(|&i| body[i]).collect(),
+            parent: None
+        }).collect();
+        loops.sort_by(|a, b| (b.blocks.len(), a.header).cmp(&(a.blocks.len(), b.header)));
+        let mut depth = vec![0; self.len()];
+        for index in 0..loops.len() {
+            let header = loops[index].header;
+            loops[index].parent = (0..index).rev().find(|&outer| loops[outer].contains(header));
+            for &block in &loops[index].blocks {
+                depth[block] += 1;
+            }
+        }
+        LoopNest {
+            loops: loops,
+            depth: depth
+        }
+    }
+    /// Check if every loop can only be entered through its header, so a
+    /// branch back to a block visited earlier always goes to a block that
+    /// dominates it
+    pub fn is_reducible(&self) -> bool {
+        let (pre, post, _) = self.search();
+        let doms = self.dominators();
+        (0..self.len()).filter(|&block| pre[block] != UNVISITED).all(|block| {
+            self.get_successors(block).into_iter().all(|succ| {
+                let retreating = pre[succ] <= pre[block] && post[succ] >= post[block];
+                !retreating || doms.dominates(succ, block)
+            })
+        })
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+/// Which blocks dominate which, meaning every path from the entry to one
+/// goes through the other
+pub struct Dominators {
+    idom: Vec<Option<usize>>
+}
+impl Dominators {
+    /// Check if the entry can reach `block`
+    pub fn is_reachable(&self, block: usize) -> bool {
+        self.idom[block].is_some()
+    }
+    /// Get the closest block that dominates `block` other than itself, if it
+    /// is reachable and isn't the entry
+    pub fn get_immediate(&self, block: usize) -> Option<usize> {
+        self.idom[block].and_then(|idom| if idom == block { None } else { Some(idom) })
+    }
+    /// Check if `a` dominates `b`, which every reachable block does to itself
+    pub fn dominates(&self, a: usize, b: usize) -> bool {
+        if !self.is_reachable(b) {
+            return false
+        }
+        let mut block = b;
+        loop {
+            if block == a {
+                return true
+            }
+            match self.get_immediate(block) {
+                Some(idom) => block = idom,
+                None => return false
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+/// A natural loop
+pub struct NaturalLoop {
+    /// The block every way into the loop goes through
+    pub header: usize,
+    /// The blocks in the loop, in order, including the header
+    pub blocks: Vec<usize>,
+    /// The index of the innermost loop this one is inside, if any
+    pub parent: Option<usize>
+}
+impl NaturalLoop {
+    /// Check if `block` is in this loop
+    pub fn contains(&self, block: usize) -> bool {
+        self.blocks.binary_search(&block).is_ok()
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+/// The natural loops of a function and how deeply each block is nested in
+/// them
+pub struct LoopNest {
+    loops: Vec<NaturalLoop>,
+    depth: Vec<usize>
+}
+impl LoopNest {
+    /// Get the loops, with each one before the loops inside it
+    pub fn get_loops(&self) -> &[NaturalLoop] {
+        &self.loops
+    }
+    /// Get how many loops `block` is inside
+    pub fn get_depth(&self, block: usize) -> usize {
+        self.depth[block]
+    }
+    /// Get the index of the innermost loop `block` is inside, if any
+    pub fn get_innermost(&self, block: usize) -> Option<usize> {
+        (0..self.loops.len()).rev().find(|&index| self.loops[index].contains(block))
+    }
+}
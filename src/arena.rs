@@ -0,0 +1,45 @@
+use libc::c_char;
+use std::cmp;
+
+/// The number of bytes in each chunk of an arena, unless something bigger is
+/// put in it
+const CHUNK_SIZE: usize = 4096;
+
+/// A bump allocator that copies bytes into big chunks, so lots of small
+/// things can be kept alive without an allocation each
+///
+/// Chunks are never grown, so pointers into them stay valid until the arena
+/// is dropped.
+pub struct Arena {
+    chunks: Vec<Vec<u8>>
+}
+impl Arena {
+    /// Make an empty arena, which doesn't allocate until something is put in it
+    pub fn new() -> Arena {
+        Arena {
+            chunks: Vec::new()
+        }
+    }
+    /// Copy `bytes` into the arena and return a pointer to the copy
+    pub fn alloc_bytes(&mut self, bytes: &[u8]) -> *const u8 {
+        let fits = match self.chunks.last() {
+            Some(chunk) => chunk.capacity() - chunk.len() >= bytes.len(),
+            None => false
+        };
+        if !fits {
+            self.chunks.push(Vec::with_capacity(cmp::max(CHUNK_SIZE, bytes.len())));
+        }
+        let chunk = self.chunks.last_mut().unwrap();
+        let start = chunk.len();
+        chunk.extend(bytes.iter().cloned());
+        chunk[start..].as_ptr()
+    }
+    /// Copy `text` into the arena with a null terminator and return a pointer
+    /// to the copy
+    pub fn alloc_str(&mut self, text: &str) -> *const c_char {
+        let mut bytes = Vec::with_capacity(text.len() + 1);
+        bytes.extend(text.bytes().take_while(|&byte| byte != 0));
+        bytes.push(0);
+        self.alloc_bytes(&bytes) as *const c_char
+    }
+}
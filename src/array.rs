@@ -0,0 +1,133 @@
+use exception;
+use function::flags;
+use label::Label;
+use types::{consts, get, Type, Ty};
+use value::Val;
+
+/// The layout of an array with any number of dimensions behind a pointer, so
+/// its elements can be loaded and stored by their indices without working
+/// out their offsets by hand
+///
+/// The extents, strides and indices can be values of any integer type, and
+/// are converted to `nint` before they are multiplied. Strides are counted in
+/// elements, not bytes.
+///
+/// ```rust
+/// use jit::*;
+/// let mut ctx = Context::<()>::new();
+/// let func = UncompiledFunction::new(&mut ctx, &get::<fn(&'static f64, i32, i32) -> f64>());
+/// // a 3 by 4 matrix, with rows one after another
+/// let matrix = ArrayView::new(&func[0], &get::<f64>(), &[func.insn_of(3i32), func.insn_of(4i32)]);
+/// func.insn_return(matrix.load(&[&func[1], &func[2]]));
+/// let at: extern fn(*const f64, i32, i32) -> f64 = func.compile().closure();
+/// let values: Vec<f64> = (0..12).map(|x| x as f64).collect();
+/// assert_eq!(at(values.as_ptr(), 2, 1), 9.0);
+/// ```
+pub struct ArrayView<'a> {
+    base: &'a Val,
+    elem: Type,
+    extents: Vec<&'a Val>,
+    strides: Vec<&'a Val>,
+    checked: bool
+}
+impl<'a> ArrayView<'a> {
+    /// Describe an array of `elem` values that `base` points to, with the
+    /// `extents` of its dimensions given from the outermost, laid out with
+    /// the last index changing fastest
+    pub fn new(base: &'a Val, elem: &Ty, extents: &[&'a Val]) -> ArrayView<'a> {
+        let func = base.get_function();
+        let nint = consts::get_nint();
+        let mut strides = Vec::with_capacity(extents.len());
+        let mut stride = func.insn_of(1isize);
+        for &extent in extents.iter().rev() {
+            strides.push(stride);
+            stride = func.insn_mul(stride, func.insn_convert(extent, &nint, false));
+        }
+        strides.reverse();
+        ArrayView::strided(base, elem, extents, &strides)
+    }
+    /// Describe an array of `elem` values that `base` points to, with the
+    /// `extents` of its dimensions and how many elements apart consecutive
+    /// indices of each are, given from the outermost
+    ///
+    /// This can describe a column-major array, or a window onto a bigger
+    /// array, like a region of an image whose rows are further apart than it
+    /// is wide.
+    pub fn strided(base: &'a Val, elem: &Ty, extents: &[&'a Val], strides: &[&'a Val]) -> ArrayView<'a> {
+        if !base.get_type().is_pointer() {
+            panic!("The base of an array view should be a pointer, got {:?}", base.get_type())
+        }
+        if extents.len() != strides.len() {
+            panic!("An array view was given {} extents but {} strides", extents.len(), strides.len())
+        }
+        let func = base.get_function();
+        let nint = consts::get_nint();
+        let to_nint = |value: &'a Val| if value.get_type().get_kind() == nint.get_kind() {
+            value
+        } else {
+            func.insn_convert(value, &nint, false)
+        };
+        ArrayView {
+            base: base,
+            elem: elem.to_owned(),
+            extents: extents.iter().map(|&extent| to_nint(extent)).collect(),
+            strides: strides.iter().map(|&stride| to_nint(stride)).collect(),
+            checked: false
+        }
+    }
+    /// Set if indices are checked against the extents, raising
+    /// `BuiltinException::OutOfBounds` if any is negative or too big
+    pub fn set_checked(&mut self, checked: bool) {
+        self.checked = checked;
+    }
+    /// Check if indices are checked against the extents
+    pub fn is_checked(&self) -> bool {
+        self.checked
+    }
+    /// Get the number of dimensions
+    pub fn get_rank(&self) -> usize {
+        self.extents.len()
+    }
+    /// Get the type of the elements
+    pub fn get_elem_type(&self) -> &Ty {
+        &self.elem
+    }
+    /// Make instructions that get the address of the element at `indices`,
+    /// checking them first if the view is checked
+    pub fn address(&self, indices: &[&'a Val]) -> &'a Val {
+        if indices.len() != self.extents.len() {
+            panic!("An array view with {} dimensions was given {} indices", self.extents.len(), indices.len())
+        }
+        let func = self.base.get_function();
+        let (nint, nuint) = (consts::get_nint(), consts::get_nuint());
+        let mut offset: Option<&'a Val> = None;
+        for ((&index, &extent), &stride) in indices.iter().zip(self.extents.iter()).zip(self.strides.iter()) {
+            let index = if index.get_type().get_kind() == nint.get_kind() { index } else { func.insn_convert(index, &nint, false) };
+            if self.checked {
+                // a negative index is too big once it is unsigned
+                let inside = func.insn_lt(func.insn_convert(index, &nuint, false), func.insn_convert(extent, &nuint, false));
+                let mut ok = Label::new(&func);
+                func.insn_branch_if(inside, &mut ok);
+                func.insn_call_native0(Some("out_of_bounds"), exception::out_of_bounds, &get::<fn()>(), flags::CallFlags::empty());
+                func.insn_label(&mut ok);
+            }
+            let term = func.insn_mul(index, stride);
+            offset = Some(match offset {
+                Some(offset) => func.insn_add(offset, term),
+                None => term
+            });
+        }
+        match offset {
+            Some(offset) => func.insn_load_elem_address(self.base, offset, &self.elem),
+            None => self.base
+        }
+    }
+    /// Make instructions that load the element at `indices`
+    pub fn load(&self, indices: &[&'a Val]) -> &'a Val {
+        self.base.get_function().insn_load_relative(self.address(indices), 0, &self.elem)
+    }
+    /// Make instructions that store `value` as the element at `indices`
+    pub fn store(&self, indices: &[&'a Val], value: &'a Val) {
+        self.base.get_function().insn_store_relative(self.address(indices), 0, value)
+    }
+}
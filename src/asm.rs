@@ -0,0 +1,634 @@
+//! A textual assembly format for functions
+//!
+//! Each function has a header giving its name, parameters and return type,
+//! followed by one instruction or label per line, and a closing brace.
+//! Values are written `%name`, labels `@name`, and comments start with `;`.
+//!
+//! ```rust
+//! use jit::*;
+//! let text = "
+//! function sum(%n: int) -> int {
+//!     %total = local int
+//!     %zero = const int 0
+//!     %one = const int 1
+//!     store %total, %zero
+//! @loop:
+//!     %done = leq %n, %zero
+//!     brif %done, @end
+//!     %next = add %total, %n
+//!     store %total, %next
+//!     %rest = sub %n, %one
+//!     store %n, %rest
+//!     br @loop
+//! @end:
+//!     ret %total
+//! }";
+//! let mut ctx = Context::<()>::new();
+//! let funcs = asm::assemble(&mut ctx, text).unwrap();
+//! funcs[0].1.with(|sum: extern fn(i32) -> i32| assert_eq!(sum(4), 10));
+//! ```
+//!
+//! The instructions are:
+//!
+//! + `%x = const TYPE NUMBER` and `%x = local TYPE`
+//! + `store %dest, %value`, `%x = load %ptr, OFFSET, TYPE` and
+//! `store_relative %ptr, OFFSET, %value`
+//! + `%x = convert %value, TYPE` and `%x = convert_ovf %value, TYPE`
+//! + `%x = OP %a, %b` where `OP` is one of `add`, `sub`, `mul`, `div`, `rem`,
+//! `add_ovf`, `sub_ovf`, `mul_ovf`, `and`, `or`, `xor`, `shl`, `shr`, `eq`,
+//! `neq`, `lt`, `leq`, `gt`, `geq`, `min`, `max` or `pow`
+//! + `%x = OP %a` where `OP` is one of `neg`, `not`, `abs`, `sign`, `sqrt`,
+//! `floor`, `ceil`, `dup` or `address_of`
+//! + `br @label`, `brif %cond, @label` and `brifnot %cond, @label`
+//! + `%x = call NAME, %args...` to call another function in the program
+//! + `ret %value` and `ret`
+//!
+//! Instructions are checked like they are when built from Rust, so when
+//! validation is on, operands of the wrong types give an `AsmError` with the
+//! line they are on.
+use raw::*;
+use context::Context;
+use data::ContextData;
+use function::{flags, Abi, CompiledFunction, UncompiledFunction};
+use label::Label;
+use lvalue;
+use types::{consts, Type, TypeKind, Ty};
+use value::Val;
+use util::from_ptr;
+use libc::c_uint;
+use std::collections::HashMap;
+use std::collections::hash_map::Entry;
+use std::error::Error;
+use std::{fmt, mem};
+
+#[derive(Clone, Debug, PartialEq)]
+/// An error in a program's text, or from building it
+pub struct AsmError {
+    /// The line the error is on, starting from 1, or 0 if it isn't known
+    pub line: usize,
+    /// What went wrong
+    pub message: String
+}
+impl AsmError {
+    fn new<S: Into<String>>(line: usize, message: S) -> AsmError {
+        AsmError {
+            line: line,
+            message: message.into()
+        }
+    }
+}
+impl fmt::Display for AsmError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "line {}: {}", self.line, self.message)
+    }
+}
+impl Error for AsmError {
+    fn description(&self) -> &str {
+        &self.message
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+/// An operand of an instruction
+pub enum Operand {
+    /// A value, written `%name`
+    Value(String),
+    /// A label, written `@name`
+    Label(String),
+    /// A type or function name
+    Name(String),
+    /// An integer literal
+    Int(i64),
+    /// A floating-point literal
+    Float(f64)
+}
+impl fmt::Display for Operand {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Operand::Value(ref name) => write!(fmt, "%{}", name),
+            Operand::Label(ref name) => write!(fmt, "@{}", name),
+            Operand::Name(ref name) => fmt.write_str(name),
+            Operand::Int(value) => write!(fmt, "{}", value),
+            Operand::Float(value) => {
+                let text = format!("{:?}", value);
+                if text.contains(|c: char| c == '.' || c == 'e' || c == 'E' || c.is_alphabetic()) {
+                    fmt.write_str(&text)
+                } else {
+                    write!(fmt, "{}.0", text)
+                }
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+/// A single instruction
+pub struct Insn {
+    /// The value the result is given to, if any
+    pub dest: Option<String>,
+    /// The name of the instruction
+    pub op: String,
+    /// The operands of the instruction
+    pub args: Vec<Operand>
+}
+impl fmt::Display for Insn {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(ref dest) = self.dest {
+            try!(write!(fmt, "%{} = ", dest));
+        }
+        try!(fmt.write_str(&self.op));
+        for (index, arg) in self.args.iter().enumerate() {
+            try!(write!(fmt, "{}{}", if index == 0 { " " } else { ", " }, arg));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+/// A line in the body of a function
+pub enum Stmt {
+    /// A label, which branches can go to
+    Label(String),
+    /// An instruction
+    Insn(Insn)
+}
+impl fmt::Display for Stmt {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Stmt::Label(ref name) => write!(fmt, "@{}:", name),
+            Stmt::Insn(ref insn) => write!(fmt, "    {}", insn)
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+/// The definition of a function
+pub struct FunctionDef {
+    /// The line the function starts on
+    pub line: usize,
+    /// The name of the function
+    pub name: String,
+    /// The names and types of the parameters
+    pub params: Vec<(String, String)>,
+    /// The return type
+    pub ret: String,
+    /// The labels and instructions, with the lines they're on
+    pub body: Vec<(usize, Stmt)>
+}
+impl fmt::Display for FunctionDef {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        try!(write!(fmt, "function {}(", self.name));
+        for (index, &(ref name, ref ty)) in self.params.iter().enumerate() {
+            try!(write!(fmt, "{}%{}: {}", if index == 0 { "" } else { ", " }, name, ty));
+        }
+        try!(writeln!(fmt, ") -> {} {{", self.ret));
+        for &(_, ref stmt) in &self.body {
+            try!(writeln!(fmt, "{}", stmt));
+        }
+        fmt.write_str("}\n")
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+/// A parsed program, made of functions
+///
+/// Displaying a program gives text that parses back into the same program,
+/// apart from the line numbers.
+pub struct Program {
+    /// The functions in the program
+    pub functions: Vec<FunctionDef>
+}
+impl fmt::Display for Program {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        for (index, func) in self.functions.iter().enumerate() {
+            if index > 0 {
+                try!(fmt.write_str("\n"));
+            }
+            try!(write!(fmt, "{}", func));
+        }
+        Ok(())
+    }
+}
+impl Program {
+    /// Build the functions of the program in the context given, in the order
+    /// they were defined, without compiling them
+    pub fn build<'a, T>(&self, ctx: &'a mut Context<T>) -> Result<Vec<(String, UncompiledFunction<'a>)>, AsmError> {
+        // operands of the wrong types poison the functions instead of
+        // panicking while they are built, so the error can give the line
+        let raw: jit_context_t = (&*ctx).into();
+        let deferred = ContextData::with(raw, |data| mem::replace(&mut data.options.defer_errors, true));
+        let built = self.build_deferring(ctx);
+        ContextData::with(raw, |data| data.options.defer_errors = deferred);
+        built
+    }
+    fn build_deferring<'a, T>(&self, ctx: &'a mut Context<T>) -> Result<Vec<(String, UncompiledFunction<'a>)>, AsmError> {
+        let mut ctx = Some(ctx);
+        let mut funcs:Vec<(String, UncompiledFunction<'a>)> = Vec::new();
+        for def in &self.functions {
+            if funcs.iter().any(|&(ref name, _)| *name == def.name) {
+                return Err(AsmError::new(def.line, format!("function {} is defined twice", def.name)))
+            }
+            let mut params:Vec<&Ty> = Vec::new();
+            for &(_, ref ty) in &def.params {
+                params.push(try!(type_named(ty, def.line)));
+            }
+            let ret = try!(type_named(&def.ret, def.line));
+            let sig = Type::new_signature(Abi::CDecl, ret, &mut params);
+            let func = match funcs.first() {
+                Some(&(_, ref first)) => first.sibling(&sig),
+                None => UncompiledFunction::new(ctx.take().unwrap(), &sig)
+            };
+            funcs.push((def.name.clone(), func));
+        }
+        for (def, &(_, ref func)) in self.functions.iter().zip(funcs.iter()) {
+            try!(build_body(def, func, &funcs));
+        }
+        Ok(funcs)
+    }
+}
+
+/// Parse the text of a program
+pub fn parse(text: &str) -> Result<Program, AsmError> {
+    let mut functions = Vec::new();
+    let mut current:Option<FunctionDef> = None;
+    for (index, line) in text.lines().enumerate() {
+        let number = index + 1;
+        let line = line.splitn(2, ';').next().unwrap().trim();
+        if line.is_empty() {
+            continue
+        }
+        let stmt = if current.is_none() {
+            current = Some(try!(parse_header(line, number)));
+            continue
+        } else if line == "}" {
+            functions.push(current.take().unwrap());
+            continue
+        } else if line.starts_with('@') && line.ends_with(':') {
+            let name = &line[1..line.len() - 1];
+            try!(check_name(name, number));
+            Stmt::Label(name.to_string())
+        } else {
+            Stmt::Insn(try!(parse_insn(line, number)))
+        };
+        current.as_mut().unwrap().body.push((number, stmt));
+    }
+    match current {
+        Some(func) => Err(AsmError::new(func.line, format!("function {} is never closed", func.name))),
+        None => Ok(Program {
+            functions: functions
+        })
+    }
+}
+/// Parse the text of a program, build it in the context given and compile it
+pub fn assemble<'a, T>(ctx: &'a mut Context<T>, text: &str) -> Result<Vec<(String, CompiledFunction<'a>)>, AsmError> {
+    let program = try!(parse(text));
+    let funcs = try!(program.build(ctx));
+    let mut compiled = Vec::with_capacity(funcs.len());
+    for (name, func) in funcs {
+        match func.try_compile() {
+            Ok(func) => compiled.push((name, func)),
+            Err(error) => return Err(AsmError::new(0, format!("{}: {}", name, error)))
+        }
+    }
+    Ok(compiled)
+}
+
+fn check_name(name: &str, line: usize) -> Result<(), AsmError> {
+    if !name.is_empty() && name.chars().all(|c| c.is_alphanumeric() || c == '_' || c == '.') {
+        Ok(())
+    } else {
+        Err(AsmError::new(line, format!("`{}` is not a valid name", name)))
+    }
+}
+fn type_named(name: &str, line: usize) -> Result<&'static Ty, AsmError> {
+    Ok(match name {
+        "void" => consts::get_void(),
+        "sbyte" => consts::get_sbyte(),
+        "ubyte" => consts::get_ubyte(),
+        "short" => consts::get_short(),
+        "ushort" => consts::get_ushort(),
+        "int" => consts::get_int(),
+        "uint" => consts::get_uint(),
+        "nint" => consts::get_nint(),
+        "nuint" => consts::get_nuint(),
+        "long" => consts::get_long(),
+        "ulong" => consts::get_ulong(),
+        "float32" => consts::get_float32(),
+        "float64" => consts::get_float64(),
+        "nfloat" => consts::get_nfloat(),
+        "ptr" => consts::get_void_ptr(),
+        _ => return Err(AsmError::new(line, format!("unknown type `{}`", name)))
+    })
+}
+fn parse_header(line: &str, number: usize) -> Result<FunctionDef, AsmError> {
+    let expected = "expected `function NAME(%PARAM: TYPE, ...) -> TYPE {`";
+    if !line.starts_with("function ") || !line.ends_with('{') {
+        return Err(AsmError::new(number, expected))
+    }
+    let line = line["function ".len()..line.len() - 1].trim();
+    let (open, close) = match (line.find('('), line.rfind(')')) {
+        (Some(open), Some(close)) if open < close => (open, close),
+        _ => return Err(AsmError::new(number, expected))
+    };
+    let name = line[..open].trim();
+    try!(check_name(name, number));
+    let mut params = Vec::new();
+    for param in line[open + 1..close].split(',').map(|param| param.trim()).filter(|param| !param.is_empty()) {
+        let mut parts = param.splitn(2, ':');
+        let param_name = parts.next().unwrap().trim();
+        let ty = parts.next().unwrap_or("").trim();
+        if !param_name.starts_with('%') || ty.is_empty() {
+            return Err(AsmError::new(number, format!("expected `%NAME: TYPE`, not `{}`", param)))
+        }
+        try!(check_name(&param_name[1..], number));
+        try!(type_named(ty, number));
+        params.push((param_name[1..].to_string(), ty.to_string()));
+    }
+    let rest = line[close + 1..].trim();
+    let ret = if rest.is_empty() {
+        "void"
+    } else if rest.starts_with("->") {
+        rest[2..].trim()
+    } else {
+        return Err(AsmError::new(number, expected))
+    };
+    try!(type_named(ret, number));
+    Ok(FunctionDef {
+        line: number,
+        name: name.to_string(),
+        params: params,
+        ret: ret.to_string(),
+        body: Vec::new()
+    })
+}
+fn parse_insn(line: &str, number: usize) -> Result<Insn, AsmError> {
+    let (dest, rest) = if line.starts_with('%') {
+        let equals = match line.find('=') {
+            Some(equals) => equals,
+            None => return Err(AsmError::new(number, "expected `=` after the value being defined"))
+        };
+        let dest = line[1..equals].trim();
+        try!(check_name(dest, number));
+        (Some(dest.to_string()), line[equals + 1..].trim())
+    } else {
+        (None, line)
+    };
+    let (op, args) = match rest.find(|c: char| c.is_whitespace()) {
+        Some(space) => (&rest[..space], rest[space..].trim()),
+        None => (rest, "")
+    };
+    try!(check_name(op, number));
+    let mut operands = Vec::new();
+    if !args.is_empty() {
+        for arg in args.split(',') {
+            operands.push(try!(parse_operand(arg.trim(), number)));
+        }
+    }
+    Ok(Insn {
+        dest: dest,
+        op: op.to_string(),
+        args: operands
+    })
+}
+fn parse_operand(text: &str, number: usize) -> Result<Operand, AsmError> {
+    if text.starts_with('%') {
+        try!(check_name(&text[1..], number));
+        Ok(Operand::Value(text[1..].to_string()))
+    } else if text.starts_with('@') {
+        try!(check_name(&text[1..], number));
+        Ok(Operand::Label(text[1..].to_string()))
+    } else if text.starts_with(|c: char| c.is_digit(10) || c == '-' || c == '+') {
+        if let Ok(value) = text.parse::<i64>() {
+            Ok(Operand::Int(value))
+        } else if let Ok(value) = text.parse::<f64>() {
+            Ok(Operand::Float(value))
+        } else {
+            Err(AsmError::new(number, format!("`{}` is not a valid number", text)))
+        }
+    } else {
+        try!(check_name(text, number));
+        Ok(Operand::Name(text.to_string()))
+    }
+}
+
+fn build_body<'a>(def: &FunctionDef, func: &UncompiledFunction<'a>,
+                  funcs: &[(String, UncompiledFunction<'a>)]) -> Result<(), AsmError> {
+    let mut values:HashMap<&str, &'a Val> = HashMap::new();
+    for (index, &(ref name, _)) in def.params.iter().enumerate() {
+        let param = unsafe { from_ptr(jit_value_get_param(func.into(), index as c_uint)) };
+        if values.insert(&**name, param).is_some() {
+            return Err(AsmError::new(def.line, format!("parameter %{} is defined twice", name)))
+        }
+    }
+    let mut labels:HashMap<&str, (Label<'a>, bool)> = HashMap::new();
+    for &(line, ref stmt) in &def.body {
+        match *stmt {
+            Stmt::Label(ref name) => {
+                let label = get_label(&mut labels, func, &**name);
+                if label.1 {
+                    return Err(AsmError::new(line, format!("label @{} is placed twice", name)))
+                }
+                label.1 = true;
+                func.insn_label(&mut label.0);
+            },
+            Stmt::Insn(ref insn) => {
+                let result = try!(build_insn(insn, line, func, funcs, &values, &mut labels));
+                if let Some(error) = func.get_poison() {
+                    return Err(AsmError::new(line, error.message))
+                }
+                match (insn.dest.as_ref(), result) {
+                    (Some(dest), Some(value)) => if values.insert(&**dest, value).is_some() {
+                        return Err(AsmError::new(line, format!("value %{} is defined twice", dest)))
+                    },
+                    (Some(_), None) =>
+                        return Err(AsmError::new(line, format!("`{}` doesn't give a value", insn.op))),
+                    (None, _) => ()
+                }
+            }
+        }
+    }
+    for (name, &(_, placed)) in &labels {
+        if !placed {
+            return Err(AsmError::new(def.line, format!("label @{} is never placed", name)))
+        }
+    }
+    func.insn_default_return();
+    Ok(())
+}
+fn get_label<'l, 'b, 'a>(labels: &'l mut HashMap<&'b str, (Label<'a>, bool)>, func: &UncompiledFunction<'a>,
+                         name: &'b str) -> &'l mut (Label<'a>, bool) {
+    match labels.entry(name) {
+        Entry::Occupied(entry) => entry.into_mut(),
+        Entry::Vacant(entry) => entry.insert((Label::new(func), false))
+    }
+}
+fn build_insn<'a, 'b>(insn: &'b Insn, line: usize, func: &UncompiledFunction<'a>,
+                      funcs: &[(String, UncompiledFunction<'a>)], values: &HashMap<&str, &'a Val>,
+                      labels: &mut HashMap<&'b str, (Label<'a>, bool)>) -> Result<Option<&'a Val>, AsmError> {
+    let args = &insn.args;
+    let arity = |count: usize| if args.len() == count {
+        Ok(())
+    } else {
+        Err(AsmError::new(line, format!("`{}` takes {} operands, not {}", insn.op, count, args.len())))
+    };
+    let value = |index: usize| match args[index] {
+        Operand::Value(ref name) => match values.get(&**name) {
+            Some(&value) => Ok(value),
+            None => Err(AsmError::new(line, format!("value %{} is not defined", name)))
+        },
+        ref other => Err(AsmError::new(line, format!("expected a value, not `{}`", other)))
+    };
+    // stores and addresses need storage, which constants aren't
+    let place = |index: usize| match try!(value(index)) {
+        value if value.is_constant() => Err(AsmError::new(line, format!("`{}` needs a local, not the constant `{}`", insn.op, args[index]))),
+        value => Ok(lvalue::local(value))
+    };
+    let ty = |index: usize| match args[index] {
+        Operand::Name(ref name) => type_named(name, line),
+        ref other => Err(AsmError::new(line, format!("expected a type, not `{}`", other)))
+    };
+    let int = |index: usize| match args[index] {
+        Operand::Int(value) => Ok(value),
+        ref other => Err(AsmError::new(line, format!("expected an integer, not `{}`", other)))
+    };
+    let label_name = |index: usize| match args[index] {
+        Operand::Label(ref name) => Ok(&**name),
+        ref other => Err(AsmError::new(line, format!("expected a label, not `{}`", other)))
+    };
+    let op = &*insn.op;
+    Ok(match op {
+        "const" => {
+            try!(arity(2));
+            let ty = try!(ty(0));
+            Some(try!(constant(func, ty, &args[1], line)))
+        },
+        "local" => {
+            try!(arity(1));
+            Some(Val::new(func, try!(ty(0))))
+        },
+        "store" => {
+            try!(arity(2));
+            func.insn_store(&try!(place(0)), try!(value(1)));
+            None
+        },
+        "load" => {
+            try!(arity(3));
+            Some(func.insn_load_relative(try!(value(0)), try!(int(1)) as usize, try!(ty(2))))
+        },
+        "store_relative" => {
+            try!(arity(3));
+            func.insn_store_relative(try!(value(0)), try!(int(1)) as usize, try!(value(2)));
+            None
+        },
+        "convert" | "convert_ovf" => {
+            try!(arity(2));
+            Some(func.insn_convert(try!(value(0)), try!(ty(1)), op == "convert_ovf"))
+        },
+        "add" | "sub" | "mul" | "div" | "rem" | "add_ovf" | "sub_ovf" | "mul_ovf" | "and" | "or" | "xor" |
+        "shl" | "shr" | "eq" | "neq" | "lt" | "leq" | "gt" | "geq" | "min" | "max" | "pow" => {
+            try!(arity(2));
+            let (a, b) = (try!(value(0)), try!(value(1)));
+            Some(match op {
+                "add" => func.insn_add(a, b),
+                "sub" => func.insn_sub(a, b),
+                "mul" => func.insn_mul(a, b),
+                "div" => func.insn_div(a, b),
+                "rem" => func.insn_rem(a, b),
+                "add_ovf" => func.insn_add_ovf(a, b),
+                "sub_ovf" => func.insn_sub_ovf(a, b),
+                "mul_ovf" => func.insn_mul_ovf(a, b),
+                "and" => func.insn_and(a, b),
+                "or" => func.insn_or(a, b),
+                "xor" => func.insn_xor(a, b),
+                "shl" => func.insn_shl(a, b),
+                "shr" => func.insn_shr(a, b),
+                "eq" => func.insn_eq(a, b),
+                "neq" => func.insn_neq(a, b),
+                "lt" => func.insn_lt(a, b),
+                "leq" => func.insn_leq(a, b),
+                "gt" => func.insn_gt(a, b),
+                "geq" => func.insn_geq(a, b),
+                "min" => func.insn_min(a, b),
+                "max" => func.insn_max(a, b),
+                _ => func.insn_pow(a, b)
+            })
+        },
+        "neg" | "not" | "abs" | "sign" | "sqrt" | "floor" | "ceil" | "dup" | "address_of" => {
+            try!(arity(1));
+            let a = try!(value(0));
+            Some(match op {
+                "neg" => func.insn_neg(a),
+                "not" => func.insn_not(a),
+                "abs" => func.insn_abs(a),
+                "sign" => func.insn_sign(a),
+                "sqrt" => func.insn_sqrt(a),
+                "floor" => func.insn_floor(a),
+                "ceil" => func.insn_ceil(a),
+                "dup" => func.insn_dup(a),
+                _ => func.insn_address_of(&try!(place(0)))
+            })
+        },
+        "br" => {
+            try!(arity(1));
+            let label = get_label(labels, func, try!(label_name(0)));
+            func.insn_branch(&mut label.0);
+            None
+        },
+        "brif" | "brifnot" => {
+            try!(arity(2));
+            let cond = try!(value(0));
+            let label = get_label(labels, func, try!(label_name(1)));
+            if op == "brif" {
+                func.insn_branch_if(cond, &mut label.0);
+            } else {
+                func.insn_branch_if_not(cond, &mut label.0);
+            }
+            None
+        },
+        "ret" => {
+            if args.is_empty() {
+                func.insn_default_return();
+            } else {
+                try!(arity(1));
+                func.insn_return(try!(value(0)));
+            }
+            None
+        },
+        "call" => {
+            let name = match args.first() {
+                Some(&Operand::Name(ref name)) => name,
+                _ => return Err(AsmError::new(line, "expected the name of the function to call"))
+            };
+            let target = match funcs.iter().find(|&&(ref other, _)| other == name) {
+                Some(&(_, ref target)) => target,
+                None => return Err(AsmError::new(line, format!("function {} is not defined", name)))
+            };
+            let mut call_args = Vec::with_capacity(args.len() - 1);
+            for index in 1..args.len() {
+                call_args.push(try!(value(index)));
+            }
+            Some(func.insn_call(Some(&**name), target, None, &mut call_args, flags::CallFlags::empty()))
+        },
+        _ => return Err(AsmError::new(line, format!("unknown instruction `{}`", op)))
+    })
+}
+fn constant<'a>(func: &UncompiledFunction<'a>, ty: &Ty, value: &Operand, line: usize) -> Result<&'a Val, AsmError> {
+    let kind = ty.get_kind();
+    unsafe {
+        Ok(from_ptr(match *value {
+            Operand::Float(value) if kind == TypeKind::Float32 =>
+                jit_value_create_float32_constant(func.into(), ty.into(), value as f32),
+            Operand::Float(value) if kind == TypeKind::Float64 =>
+                jit_value_create_float64_constant(func.into(), ty.into(), value),
+            Operand::Float(value) if kind == TypeKind::NFloat =>
+                jit_value_create_nfloat_constant(func.into(), ty.into(), value),
+            Operand::Int(value) if ty.is_float() =>
+                return constant(func, ty, &Operand::Float(value as f64), line),
+            Operand::Int(value) if kind == TypeKind::Long || kind == TypeKind::ULong =>
+                jit_value_create_long_constant(func.into(), ty.into(), value),
+            Operand::Int(value) if kind != TypeKind::Void =>
+                jit_value_create_nint_constant(func.into(), ty.into(), value as jit_nint),
+            ref other => return Err(AsmError::new(line, format!("`{}` can't be a constant of this type", other)))
+        }))
+    }
+}
@@ -0,0 +1,183 @@
+use raw::*;
+use debuginfo;
+use events;
+use function::{CompileError, CompiledFunction};
+use util::from_ptr;
+use std::cell::Cell;
+use std::marker::PhantomData;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{channel, Receiver, Sender, TryRecvError};
+use std::thread::{self, JoinHandle};
+
+/// A function waiting to be compiled on a context's compiler thread, with
+/// the pointers passed as integers so they can be sent there
+struct Job {
+    context: usize,
+    func: usize,
+    entry: Arc<AtomicUsize>,
+    done: Sender<bool>
+}
+
+/// Compile a function while holding the build lock of its context and
+/// publish its entry point
+///
+/// Anything else that has to happen afterwards is left to the thread that
+/// owns the function, when it finds out it's done.
+unsafe fn compile(context: jit_context_t, func: jit_function_t, entry: &AtomicUsize) -> bool {
+    jit_context_build_start(context);
+    let ok = jit_function_compile(func) != 0;
+    if ok {
+        entry.store(jit_function_to_closure(func) as usize, Ordering::Release);
+    }
+    jit_context_build_end(context);
+    ok
+}
+
+/// The thread a context compiles functions on in the background, which
+/// takes them in the order they were queued
+pub struct Compiler {
+    jobs: Option<Sender<Job>>,
+    thread: Option<JoinHandle<()>>
+}
+impl Compiler {
+    fn start() -> Compiler {
+        let (jobs, queue) = channel::<Job>();
+        let thread = thread::spawn(move || {
+            while let Ok(job) = queue.recv() {
+                let ok = unsafe {
+                    compile(job.context as jit_context_t, job.func as jit_function_t, &job.entry)
+                };
+                let _ = job.done.send(ok);
+            }
+        });
+        Compiler {
+            jobs: Some(jobs),
+            thread: Some(thread)
+        }
+    }
+}
+impl Drop for Compiler {
+    /// Let the thread finish what was queued, then wait for it to stop
+    fn drop(&mut self) {
+        self.jobs = None;
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Queue an owned function to be compiled on the compiler thread of its
+/// context, starting the thread if there isn't one yet, or compile it now if
+/// `now` is set or there can't be one
+///
+/// `recompiling` and `started` are what to report it compiled with once it's
+/// done, like `compile` does.
+pub fn queue<'a>(compiler: &mut Option<Compiler>, func: jit_function_t, now: bool, recompiling: bool, started: u64) -> PendingCompile<'a> {
+    let entry = Arc::new(AtomicUsize::new(0));
+    let (done, result) = channel();
+    unsafe {
+        let context = jit_function_get_context(func);
+        if now || jit_supports_threads() == 0 {
+            let _ = done.send(compile(context, func, &entry));
+        } else {
+            if compiler.is_none() {
+                *compiler = Some(Compiler::start());
+            }
+            let job = Job {
+                context: context as usize,
+                func: func as usize,
+                entry: entry.clone(),
+                done: done
+            };
+            if let Some(ref jobs) = compiler.as_ref().unwrap().jobs {
+                let _ = jobs.send(job);
+            }
+        }
+    }
+    PendingCompile {
+        func: func,
+        entry: entry,
+        result: result,
+        done: Cell::new(None),
+        recompiling: recompiling,
+        started: started,
+        marker: PhantomData
+    }
+}
+
+/// A function that is being compiled in the background
+///
+/// Until it is done, an interpreter can keep running the baseline version,
+/// checking `get_entry` or loading from `get_entry_slot` to switch over to
+/// the compiled code as soon as it is there.
+pub struct PendingCompile<'a> {
+    func: jit_function_t,
+    entry: Arc<AtomicUsize>,
+    result: Receiver<bool>,
+    done: Cell<Option<bool>>,
+    recompiling: bool,
+    started: u64,
+    marker: PhantomData<&'a ()>
+}
+impl<'a> PendingCompile<'a> {
+    fn poll(&self) -> Option<bool> {
+        if self.done.get().is_none() {
+            match self.result.try_recv() {
+                Ok(ok) => self.finished(ok),
+                Err(TryRecvError::Disconnected) => self.finished(false),
+                Err(TryRecvError::Empty) => ()
+            }
+        }
+        self.done.get()
+    }
+    /// Do what compiling a function on this thread does after it's compiled,
+    /// now that the compiler thread is done with it
+    fn finished(&self, ok: bool) {
+        events::compiled(self.func, self.recompiling, self.started, ok);
+        if ok {
+            debuginfo::compiled(self.func);
+        } else {
+            unsafe { jit_function_abandon(self.func) };
+        }
+        self.done.set(Some(ok))
+    }
+    /// Check if compiling has finished, without waiting for it
+    pub fn is_done(&self) -> bool {
+        self.poll().is_some()
+    }
+    /// Get the address of the compiled code, or 0 if it isn't ready yet
+    pub fn get_entry(&self) -> usize {
+        self.entry.load(Ordering::Acquire)
+    }
+    /// Get the place the address of the compiled code is written to once it
+    /// is ready, so generated code can load it and call through it, falling
+    /// back to the baseline while it is still 0
+    ///
+    /// This lives as long as this does.
+    pub fn get_entry_slot(&self) -> *const usize {
+        &*self.entry as *const AtomicUsize as *const usize
+    }
+    /// Get the compiled function if compiling has finished, without waiting
+    pub fn try_get(&self) -> Option<Result<CompiledFunction<'a>, CompileError>> {
+        self.poll().map(|ok| self.finish(ok))
+    }
+    /// Wait for compiling to finish and get the compiled function
+    pub fn wait(self) -> Result<CompiledFunction<'a>, CompileError> {
+        let ok = match self.poll() {
+            Some(ok) => ok,
+            None => {
+                self.finished(self.result.recv().unwrap_or(false));
+                self.done.get().unwrap()
+            }
+        };
+        self.finish(ok)
+    }
+    fn finish(&self, ok: bool) -> Result<CompiledFunction<'a>, CompileError> {
+        if ok {
+            Ok(from_ptr(self.func))
+        } else {
+            Err(CompileError::Failed)
+        }
+    }
+}
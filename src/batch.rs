@@ -0,0 +1,40 @@
+//! Calling compiled functions directly on many sets of arguments
+use compile::Compile;
+use types::{get, CowType};
+use std::mem;
+
+/// The arguments of one call made by `apply_batch`, as a tuple of up to four
+/// values that are passed straight to the compiled code
+pub trait BatchArgs<'a>: Copy {
+    /// Get the signature of a function that takes these arguments and
+    /// returns `R`
+    fn signature<R>() -> CowType<'a> where R: Compile<'a>;
+    /// Call the code at `entry` with these arguments
+    unsafe fn call<R>(self, entry: usize) -> R;
+}
+impl<'a> BatchArgs<'a> for () {
+    fn signature<R>() -> CowType<'a> where R: Compile<'a> {
+        get::<fn() -> R>()
+    }
+    unsafe fn call<R>(self, entry: usize) -> R {
+        let func: extern fn() -> R = mem::transmute(entry);
+        func()
+    }
+}
+macro_rules! batch_args(
+    ($($ty:ident),+ => $($field:tt),+) => (
+        impl<'a, $($ty),+> BatchArgs<'a> for ($($ty,)+) where $($ty: Compile<'a> + Copy),+ {
+            fn signature<R>() -> CowType<'a> where R: Compile<'a> {
+                get::<fn($($ty),+) -> R>()
+            }
+            unsafe fn call<R>(self, entry: usize) -> R {
+                let func: extern fn($($ty),+) -> R = mem::transmute(entry);
+                func($(self.$field),+)
+            }
+        }
+    )
+);
+batch_args!(A => 0);
+batch_args!(A, B => 0, 1);
+batch_args!(A, B, C => 0, 1, 2);
+batch_args!(A, B, C, D => 0, 1, 2, 3);
@@ -0,0 +1,120 @@
+//! The raw LibJIT bindings this crate is built on, for reaching what the
+//! safe layer doesn't cover
+//!
+//! This is only here with the `raw` feature. Every wrapper has a `raw` method
+//! that gives the LibJIT pointer behind it, and an unsafe `from_raw`
+//! constructor that wraps one, so raw calls can be mixed with the safe layer
+//! without transmuting.
+//!
+//! ```rust
+//! use jit::*;
+//! use jit::bindings::*;
+//! let mut ctx = Context::<()>::new();
+//! let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+//! let doubled = unsafe {
+//!     Val::from_raw(jit_insn_shl(func.raw(), func[0].raw(), func.insn_of(1i32).raw()))
+//! };
+//! func.insn_return(doubled);
+//! func.compile().with(|double: extern fn(i32) -> i32| {
+//!     assert_eq!(double(21), 42);
+//! });
+//! ```
+pub use raw::*;
+use context::Context;
+use function::{CompiledFunction, Func, UncompiledFunction};
+use insn::{Block, Instruction};
+use label::Label;
+use types::{Type, Ty};
+use util::from_ptr;
+use value::Val;
+
+impl<T> Context<T> {
+    /// Get the LibJIT context behind this
+    pub fn raw(&self) -> jit_context_t {
+        self.into()
+    }
+    /// Wrap a LibJIT context, which is destroyed when this is dropped
+    pub unsafe fn from_raw(ctx: jit_context_t) -> Context<T> {
+        from_ptr(ctx)
+    }
+}
+impl Func {
+    /// Get the LibJIT function behind this
+    pub fn raw(&self) -> jit_function_t {
+        self.into()
+    }
+    /// Wrap a LibJIT function, which must live for `'a`
+    pub unsafe fn from_raw<'a>(func: jit_function_t) -> &'a Func {
+        from_ptr(func)
+    }
+}
+impl<'a> UncompiledFunction<'a> {
+    /// Wrap a LibJIT function that is still being built, which is left alone
+    /// when this is dropped
+    pub unsafe fn from_raw(func: jit_function_t) -> UncompiledFunction<'a> {
+        from_ptr(func)
+    }
+}
+impl<'a> CompiledFunction<'a> {
+    /// Wrap a LibJIT function that has been compiled
+    pub unsafe fn from_raw(func: jit_function_t) -> CompiledFunction<'a> {
+        from_ptr(func)
+    }
+}
+impl Val {
+    /// Get the LibJIT value behind this
+    pub fn raw(&self) -> jit_value_t {
+        self.into()
+    }
+    /// Wrap a LibJIT value, which must live for `'a`
+    pub unsafe fn from_raw<'a>(value: jit_value_t) -> &'a Val {
+        from_ptr(value)
+    }
+}
+impl Ty {
+    /// Get the LibJIT type behind this
+    pub fn raw(&self) -> jit_type_t {
+        self.into()
+    }
+    /// Wrap a LibJIT type, which must live for `'a`
+    pub unsafe fn from_raw<'a>(ty: jit_type_t) -> &'a Ty {
+        from_ptr(ty)
+    }
+}
+impl Type {
+    /// Wrap a LibJIT type, taking over the reference to it, so it is freed
+    /// when this is dropped
+    pub unsafe fn from_raw(ty: jit_type_t) -> Type {
+        from_ptr(ty)
+    }
+}
+impl<'a> Label<'a> {
+    /// Get the LibJIT label behind this
+    pub fn raw(&self) -> jit_label_t {
+        **self
+    }
+    /// Wrap a LibJIT label of a function that lives for `'a`
+    pub unsafe fn from_raw(label: jit_label_t) -> Label<'a> {
+        Label::from(label)
+    }
+}
+impl<'a> Instruction<'a> {
+    /// Get the LibJIT instruction behind this
+    pub fn raw(self) -> jit_insn_t {
+        self.into()
+    }
+    /// Wrap a LibJIT instruction of a function that lives for `'a`
+    pub unsafe fn from_raw(insn: jit_insn_t) -> Instruction<'a> {
+        from_ptr(insn)
+    }
+}
+impl<'a> Block<'a> {
+    /// Get the LibJIT block behind this
+    pub fn raw(self) -> jit_block_t {
+        self.into()
+    }
+    /// Wrap a LibJIT block of a function that lives for `'a`
+    pub unsafe fn from_raw(block: jit_block_t) -> Block<'a> {
+        from_ptr(block)
+    }
+}
@@ -0,0 +1,66 @@
+//! Bytes owned by Rust that compiled code can refer to by address
+use std::ops::Deref;
+use std::rc::Rc;
+
+/// Bytes that functions can refer to with `insn_buffer`, which are pinned
+/// for as long as any function refers to them
+///
+/// Each function that refers to the buffer keeps its bytes alive, so dropping
+/// the buffer before the function is fine, and the bytes can only be changed
+/// or resized once every function that refers to them has been destroyed.
+///
+/// ```rust
+/// use jit::*;
+/// let mut buffer = Buffer::new(vec![1, 2, 3]);
+/// {
+///     let mut ctx = Context::<()>::new();
+///     let func = UncompiledFunction::new(&mut ctx, &get::<fn(usize) -> u8>());
+///     let (bytes, _) = func.insn_buffer(&buffer);
+///     func.insn_return(func.insn_load_elem(bytes, &func[0], &get::<u8>()));
+///     assert!(buffer.get_mut().is_none());
+///     func.compile().with(|get: extern fn(usize) -> u8| assert_eq!(get(1), 2));
+/// }
+/// buffer.get_mut().unwrap().push(4);
+/// assert_eq!(&buffer[..], &[1, 2, 3, 4]);
+/// ```
+pub struct Buffer {
+    bytes: Rc<Vec<u8>>
+}
+impl Buffer {
+    /// Make a buffer holding the bytes given
+    pub fn new(bytes: Vec<u8>) -> Buffer {
+        Buffer {
+            bytes: Rc::new(bytes)
+        }
+    }
+    /// Get the bytes so they can be changed or resized, or `None` if a
+    /// function still refers to them
+    pub fn get_mut(&mut self) -> Option<&mut Vec<u8>> {
+        Rc::get_mut(&mut self.bytes)
+    }
+    /// Check if a function still refers to the bytes
+    pub fn is_pinned(&mut self) -> bool {
+        self.get_mut().is_none()
+    }
+}
+impl Deref for Buffer {
+    type Target = [u8];
+    fn deref(&self) -> &[u8] {
+        &self.bytes
+    }
+}
+impl From<Vec<u8>> for Buffer {
+    fn from(bytes: Vec<u8>) -> Buffer {
+        Buffer::new(bytes)
+    }
+}
+impl<'b> From<&'b [u8]> for Buffer {
+    fn from(bytes: &'b [u8]) -> Buffer {
+        Buffer::new(bytes.to_vec())
+    }
+}
+
+/// Get a reference to the bytes in `buffer` that keeps them pinned
+pub fn pin(buffer: &Buffer) -> Rc<Vec<u8>> {
+    buffer.bytes.clone()
+}
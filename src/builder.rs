@@ -0,0 +1,223 @@
+//! Configuring a context in one place, checking the settings fit together
+//! before it is made
+use context::{Context, ContextOptions, EmitBudget, InlineLimits, StackGuard, UnrollLimits};
+use cpu::{self, FeatureError, Features};
+use function::UncompiledFunction;
+use wide;
+use libc::c_uint;
+use std::error::Error;
+use std::fmt;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+/// The reason a `ContextBuilder` couldn't make a context
+pub enum ContextError {
+    /// The feature level can't be used on this host
+    Features(FeatureError),
+    /// The optimization level is above the highest LibJIT supports
+    OptimizationLevel(c_uint),
+    /// Debug info was asked for, but functions are interpreted, so there is
+    /// no native code to describe
+    DebugInfoInterpreted,
+    /// Long helpers were turned off, but the backend can't do 64-bit
+    /// operations without them
+    LongHelpersNeeded,
+    /// The interpreter was ruled out, but LibJIT was built to use it
+    Interpreted
+}
+impl fmt::Display for ContextError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ContextError::Features(ref error) => write!(fmt, "{}: {}", self.description(), error),
+            ContextError::OptimizationLevel(level) => write!(fmt, "{}: {}", self.description(), level),
+            _ => fmt.write_str(self.description())
+        }
+    }
+}
+impl Error for ContextError {
+    fn description(&self) -> &'static str {
+        match *self {
+            ContextError::Features(_) => "The feature level can't be used",
+            ContextError::OptimizationLevel(_) => "The optimization level is too high",
+            ContextError::DebugInfoInterpreted => "Debug info can't be made for interpreted functions",
+            ContextError::LongHelpersNeeded => "The backend needs long helpers for 64-bit operations",
+            ContextError::Interpreted => "LibJIT is using its interpreter"
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+/// Settings for a context, which are all checked against each other and the
+/// host before the context is made by `build`
+///
+/// Anything not set is left as `Context::new` leaves it.
+///
+/// ```rust
+/// use jit::*;
+/// let mut ctx = ContextBuilder::new()
+///     .optimization_level(UncompiledFunction::get_max_optimization_level())
+///     .validation(true)
+///     .debug(false)
+///     .build::<()>()
+///     .unwrap();
+/// assert!(!ctx.is_debug());
+/// let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+/// func.insn_return(func.insn_add(&func[0], func.insn_of(1i32)));
+/// func.compile().with(|inc: extern fn(i32) -> i32| assert_eq!(inc(2), 3));
+///
+/// let builder = ContextBuilder::new().optimization_level(!0);
+/// assert_eq!(builder.check(), Err(ContextError::OptimizationLevel(!0)));
+/// ```
+pub struct ContextBuilder {
+    options: ContextOptions,
+    debug: Option<bool>,
+    features: Option<(Features, Features)>,
+    inline_limits: Option<InlineLimits>,
+    unroll_limits: Option<UnrollLimits>,
+    emit_budget: Option<EmitBudget>,
+    stack_guard: Option<StackGuard>,
+    long_helpers: Option<bool>,
+    deterministic: Option<bool>,
+    interpreter: bool
+}
+impl ContextBuilder {
+    /// Start with the settings `Context::new` uses
+    pub fn new() -> ContextBuilder {
+        ContextBuilder {
+            options: ContextOptions::default(),
+            debug: None,
+            features: None,
+            inline_limits: None,
+            unroll_limits: None,
+            emit_budget: None,
+            stack_guard: None,
+            long_helpers: None,
+            deterministic: None,
+            interpreter: true
+        }
+    }
+    /// Set all the options applied to functions made on the context, which
+    /// the other options set after this change
+    pub fn options(mut self, options: ContextOptions) -> ContextBuilder {
+        self.options = options;
+        self
+    }
+    /// Set the optimization level new functions are given
+    pub fn optimization_level(mut self, level: c_uint) -> ContextBuilder {
+        self.options.optimization_level = Some(level);
+        self
+    }
+    /// Set if the types of values given to instructions are checked
+    pub fn validation(mut self, validation: bool) -> ContextBuilder {
+        self.options.validation = validation;
+        self
+    }
+    /// Set if debug-only instructions are emitted, like `Context::set_debug`
+    pub fn debug(mut self, debug: bool) -> ContextBuilder {
+        self.debug = Some(debug);
+        self
+    }
+    /// Set if compiled functions are registered with debuggers
+    pub fn debug_info(mut self, debug_info: bool) -> ContextBuilder {
+        self.options.debug_info = debug_info;
+        self
+    }
+    /// Set the CPU features generated code must and may use, like
+    /// `Context::set_feature_level`
+    pub fn feature_level(mut self, min: Features, max: Features) -> ContextBuilder {
+        self.features = Some((min, max));
+        self
+    }
+    /// Set how much `insn_call_inline` may copy
+    pub fn inline_limits(mut self, limits: InlineLimits) -> ContextBuilder {
+        self.inline_limits = Some(limits);
+        self
+    }
+    /// Set how much `insn_for_range` may unroll
+    pub fn unroll_limits(mut self, limits: UnrollLimits) -> ContextBuilder {
+        self.unroll_limits = Some(limits);
+        self
+    }
+    /// Set how much functions may have made in them, like
+    /// `Context::set_emit_budget`
+    pub fn emit_budget(mut self, budget: EmitBudget) -> ContextBuilder {
+        self.emit_budget = Some(budget);
+        self
+    }
+    /// Set the stack guard functions check, like `Context::set_stack_guard`
+    pub fn stack_guard(mut self, guard: StackGuard) -> ContextBuilder {
+        self.stack_guard = Some(guard);
+        self
+    }
+    /// Set if 64-bit operations call natives, like `Context::set_long_helpers`
+    pub fn long_helpers(mut self, on: bool) -> ContextBuilder {
+        self.long_helpers = Some(on);
+        self
+    }
+    /// Set if what the wrapper does is reproducible between runs, like
+    /// `Context::enable_deterministic`
+    pub fn deterministic(mut self, on: bool) -> ContextBuilder {
+        self.deterministic = Some(on);
+        self
+    }
+    /// Set if the context may be made when LibJIT interprets functions
+    /// instead of compiling them to native code, which it allows by default
+    pub fn allow_interpreter(mut self, allow: bool) -> ContextBuilder {
+        self.interpreter = allow;
+        self
+    }
+    /// Check the settings fit together and can be used on this host
+    pub fn check(&self) -> Result<(), ContextError> {
+        if let Some((min, max)) = self.features {
+            try!(cpu::check_level(min, max).map_err(ContextError::Features));
+        }
+        if let Some(level) = self.options.optimization_level {
+            if level > UncompiledFunction::get_max_optimization_level() {
+                return Err(ContextError::OptimizationLevel(level))
+            }
+        }
+        if ::uses_interpreter() {
+            if !self.interpreter {
+                return Err(ContextError::Interpreted)
+            } else if self.options.debug_info {
+                return Err(ContextError::DebugInfoInterpreted)
+            }
+        }
+        if self.long_helpers == Some(false) && wide::needed() {
+            return Err(ContextError::LongHelpersNeeded)
+        }
+        Ok(())
+    }
+    /// Check the settings, then make a context with them
+    pub fn build<T>(&self) -> Result<Context<T>, ContextError> {
+        try!(self.check());
+        let mut ctx = Context::new();
+        ctx.set_options(self.options);
+        if let Some(debug) = self.debug {
+            ctx.set_debug(debug);
+        }
+        if let Some((min, max)) = self.features {
+            try!(ctx.set_feature_level(min, max).map_err(ContextError::Features));
+        }
+        if let Some(limits) = self.inline_limits {
+            ctx.set_inline_limits(limits);
+        }
+        if let Some(limits) = self.unroll_limits {
+            ctx.set_unroll_limits(limits);
+        }
+        if let Some(budget) = self.emit_budget {
+            ctx.set_emit_budget(budget);
+        }
+        if let Some(guard) = self.stack_guard {
+            ctx.set_stack_guard(guard);
+        }
+        if let Some(on) = self.long_helpers {
+            ctx.set_long_helpers(on);
+        }
+        match self.deterministic {
+            Some(true) => ctx.enable_deterministic(),
+            Some(false) => ctx.disable_deterministic(),
+            None => ()
+        }
+        Ok(ctx)
+    }
+}
@@ -0,0 +1,216 @@
+//! A C interface to the wrapper, so hosts written in other languages can use
+//! its validation without building LibJIT IR themselves
+//!
+//! Functions are described with a small stack bytecode, where each function
+//! takes some `int64_t` parameters and returns an `int64_t`. The code is an
+//! array of `int64_t` words, each of which is an opcode below, followed by an
+//! operand for `JITRS_CONST` and `JITRS_PARAM`.
+//!
+//! To link this into a C program, build the crate as a static library with
+//! the `capi` feature enabled, like with
+//! `cargo rustc --release --features capi -- --crate-type staticlib`.
+//!
+//! No code given to `jitrs_compile` makes it panic, which would unwind into
+//! the C caller: bad code is reported with an error code, and anything the
+//! wrapper would check by panicking poisons the function instead. Division
+//! and remainder by zero give zero, and dividing the smallest `int64_t` by -1
+//! wraps, instead of raising exceptions with no handler to catch them.
+//!
+//! ```c
+//! int64_t code[] = {JITRS_PARAM, 0, JITRS_PARAM, 1, JITRS_MUL, JITRS_RETURN};
+//! jitrs_context* ctx = jitrs_context_new();
+//! int error;
+//! int64_t (*mul)(int64_t, int64_t) = jitrs_compile(ctx, 2, code, 6, &error);
+//! ```
+use raw::jit_function_to_closure;
+use context::{Context, ContextOptions};
+use function::{Abi, UncompiledFunction};
+use label::Label;
+use types::{consts, Type, Ty};
+use value::Val;
+use libc::{c_char, c_int, c_uint, c_void, size_t};
+use std::{mem, ptr, slice};
+
+/// Push the operand
+pub const JITRS_CONST: i64 = 0;
+/// Push the parameter whose index is the operand
+pub const JITRS_PARAM: i64 = 1;
+/// Pop two values and push their sum
+pub const JITRS_ADD: i64 = 2;
+/// Pop two values and push their difference
+pub const JITRS_SUB: i64 = 3;
+/// Pop two values and push their product
+pub const JITRS_MUL: i64 = 4;
+/// Pop two values and push their quotient
+pub const JITRS_DIV: i64 = 5;
+/// Pop two values and push the remainder of their division
+pub const JITRS_REM: i64 = 6;
+/// Pop a value and push its negation
+pub const JITRS_NEG: i64 = 7;
+/// Pop two values and push their bitwise and
+pub const JITRS_AND: i64 = 8;
+/// Pop two values and push their bitwise or
+pub const JITRS_OR: i64 = 9;
+/// Pop two values and push their bitwise exclusive or
+pub const JITRS_XOR: i64 = 10;
+/// Pop two values and push the first shifted left by the second
+pub const JITRS_SHL: i64 = 11;
+/// Pop two values and push the first shifted right by the second
+pub const JITRS_SHR: i64 = 12;
+/// Pop two values and push 1 if the first is less than the second, or 0
+pub const JITRS_LT: i64 = 13;
+/// Pop two values and push 1 if they are equal, or 0
+pub const JITRS_EQ: i64 = 14;
+/// Pop a value and return it, which must leave the stack empty
+pub const JITRS_RETURN: i64 = 15;
+
+/// The function compiled successfully
+pub const JITRS_OK: c_int = 0;
+/// An opcode wasn't recognised
+pub const JITRS_BAD_OPCODE: c_int = 1;
+/// An opcode needed an operand after the end of the code
+pub const JITRS_MISSING_OPERAND: c_int = 2;
+/// A parameter index was out of range
+pub const JITRS_BAD_PARAM: c_int = 3;
+/// An opcode needed more values than there were on the stack
+pub const JITRS_STACK_UNDERFLOW: c_int = 4;
+/// The code ended without returning, or returned with values left
+pub const JITRS_BAD_RETURN: c_int = 5;
+/// A null pointer was given
+pub const JITRS_NULL: c_int = 6;
+/// The code was valid but couldn't be compiled
+pub const JITRS_COMPILE_FAILED: c_int = 7;
+
+/// A context made with `jitrs_context_new`
+pub type jitrs_context = Context<()>;
+
+#[no_mangle]
+/// Make a new context to compile functions in
+pub extern fn jitrs_context_new() -> *mut jitrs_context {
+    let mut ctx = Context::<()>::new();
+    ctx.set_options(ContextOptions {
+        defer_errors: true,
+        .. ContextOptions::default()
+    });
+    unsafe { mem::transmute(Box::new(ctx)) }
+}
+#[no_mangle]
+/// Destroy a context and every function compiled in it
+pub unsafe extern fn jitrs_context_free(ctx: *mut jitrs_context) {
+    if !ctx.is_null() {
+        let ctx:Box<jitrs_context> = mem::transmute(ctx);
+        drop(ctx)
+    }
+}
+#[no_mangle]
+/// Get a description of the error code given
+pub extern fn jitrs_error_message(error: c_int) -> *const c_char {
+    let message:&'static [u8] = match error {
+        JITRS_OK => b"No error\0",
+        JITRS_BAD_OPCODE => b"Unrecognised opcode\0",
+        JITRS_MISSING_OPERAND => b"Opcode is missing its operand\0",
+        JITRS_BAD_PARAM => b"Parameter index out of range\0",
+        JITRS_STACK_UNDERFLOW => b"Stack underflow\0",
+        JITRS_BAD_RETURN => b"Code must end by returning the only value on the stack\0",
+        JITRS_NULL => b"Null pointer\0",
+        JITRS_COMPILE_FAILED => b"Code couldn't be compiled\0",
+        _ => b"Unknown error\0"
+    };
+    message.as_ptr() as *const c_char
+}
+#[no_mangle]
+/// Compile the code given into a function taking `params` parameters in
+/// `ctx`, and return a pointer to it
+///
+/// If the code is invalid, this returns null and sets `error` if it isn't
+/// null.
+pub unsafe extern fn jitrs_compile(ctx: *mut jitrs_context, params: c_uint,
+                                   code: *const i64, len: size_t, error: *mut c_int) -> *mut c_void {
+    let result = if ctx.is_null() || (code.is_null() && len != 0) {
+        Err(JITRS_NULL)
+    } else {
+        compile(&mut *ctx, params as usize, slice::from_raw_parts(code, len as usize))
+    };
+    let (func, code) = match result {
+        Ok(func) => (func, JITRS_OK),
+        Err(code) => (ptr::null_mut(), code)
+    };
+    if !error.is_null() {
+        *error = code;
+    }
+    func
+}
+
+fn compile(ctx: &mut jitrs_context, params: usize, code: &[i64]) -> Result<*mut c_void, c_int> {
+    let long = consts::get_long();
+    let mut param_types:Vec<&Ty> = (0..params).map(|_| long).collect();
+    let sig = Type::new_signature(Abi::CDecl, long, &mut param_types);
+    let func = UncompiledFunction::new(ctx, &sig);
+    try!(build(&func, params, code));
+    let func = try!(func.try_compile().map_err(|_| JITRS_COMPILE_FAILED));
+    Ok(unsafe { jit_function_to_closure((&func).into()) })
+}
+/// Make instructions that divide `left` by `right`, or get the remainder,
+/// giving zero instead of throwing when `right` is zero and wrapping instead
+/// of throwing when it is -1
+fn divide<'a>(func: &UncompiledFunction<'a>, left: &'a Val, right: &'a Val, rem: bool) -> &'a Val {
+    let result_var = func.new_lvalue(consts::get_long());
+    let result = result_var.get_local().unwrap();
+    let mut done = Label::new(func);
+    func.insn_store(&result_var, func.insn_of(0i64));
+    func.insn_branch_if(func.insn_eq(right, func.insn_of(0i64)), &mut done);
+    if !rem {
+        func.insn_store(&result_var, func.insn_neg(left));
+    }
+    func.insn_branch_if(func.insn_eq(right, func.insn_of(-1i64)), &mut done);
+    func.insn_store(&result_var, if rem { func.insn_rem(left, right) } else { func.insn_div(left, right) });
+    func.insn_label(&mut done);
+    result
+}
+fn build<'a>(func: &UncompiledFunction<'a>, params: usize, code: &[i64]) -> Result<(), c_int> {
+    let long = consts::get_long();
+    let mut stack:Vec<&'a Val> = Vec::new();
+    let mut words = code.iter().cloned();
+    while let Some(op) = words.next() {
+        if op == JITRS_CONST || op == JITRS_PARAM {
+            let operand = try!(words.next().ok_or(JITRS_MISSING_OPERAND));
+            if op == JITRS_CONST {
+                stack.push(func.insn_of(operand));
+            } else if operand < 0 || operand as usize >= params {
+                return Err(JITRS_BAD_PARAM)
+            } else {
+                stack.push(func.insn_dup(&func[operand as usize]));
+            }
+        } else if op == JITRS_NEG {
+            let value = try!(stack.pop().ok_or(JITRS_STACK_UNDERFLOW));
+            stack.push(func.insn_neg(value));
+        } else if op == JITRS_RETURN {
+            let value = try!(stack.pop().ok_or(JITRS_STACK_UNDERFLOW));
+            if !stack.is_empty() || words.next().is_some() {
+                return Err(JITRS_BAD_RETURN)
+            }
+            func.insn_return(value);
+            return Ok(())
+        } else if op >= JITRS_ADD && op <= JITRS_EQ {
+            let right = try!(stack.pop().ok_or(JITRS_STACK_UNDERFLOW));
+            let left = try!(stack.pop().ok_or(JITRS_STACK_UNDERFLOW));
+            stack.push(match op {
+                JITRS_ADD => func.insn_add(left, right),
+                JITRS_SUB => func.insn_sub(left, right),
+                JITRS_MUL => func.insn_mul(left, right),
+                JITRS_DIV => divide(func, left, right, false),
+                JITRS_REM => divide(func, left, right, true),
+                JITRS_AND => func.insn_and(left, right),
+                JITRS_OR => func.insn_or(left, right),
+                JITRS_XOR => func.insn_xor(left, right),
+                JITRS_SHL => func.insn_shl(left, right),
+                JITRS_SHR => func.insn_shr(left, right),
+                JITRS_LT => func.insn_convert(func.insn_lt(left, right), long, false),
+                _ => func.insn_convert(func.insn_eq(left, right), long, false)
+            });
+        } else {
+            return Err(JITRS_BAD_OPCODE)
+        }
+    }
+    Err(JITRS_BAD_RETURN)
+}
@@ -0,0 +1,205 @@
+//! Reclaiming the code of functions that aren't used any more
+//!
+//! LibJIT only frees compiled code when the context it was compiled on is
+//! destroyed, so a context that keeps getting functions added and dropped,
+//! like a REPL's or a long-running server's, grows forever. A `CodeSpace`
+//! spreads functions over a series of contexts, called generations, and hands
+//! out `CodeHandle`s that keep their function's generation alive. Once no
+//! handle refers to any function in an old generation, `collect` destroys its
+//! context, reclaiming all of its code.
+//!
+//! Each handle owns a slot holding its function's entry point, which can be
+//! pointed at a replacement built by `replace`. Generated code that calls a
+//! function from another generation must load the entry from the slot each
+//! time, which `UncompiledFunction::insn_call_slot` does, so the callee can
+//! be replaced or collected without the caller keeping stale code alive.
+//!
+//! ```rust
+//! use jit::*;
+//! let mut space = CodeSpace::<()>::new(1);
+//! let square = space.build(|ctx| {
+//!     let func = UncompiledFunction::new(ctx, &get::<fn(i32) -> i32>());
+//!     func.insn_return(func.insn_mul(&func[0], &func[0]));
+//!     func.compile()
+//! });
+//! let cube = space.build(|ctx| {
+//!     let func = UncompiledFunction::new(ctx, &get::<fn(i32) -> i32>());
+//!     func.insn_return(func.insn_mul(func.insn_mul(&func[0], &func[0]), &func[0]));
+//!     func.compile()
+//! });
+//! assert_eq!(space.generations(), 2);
+//! drop(square);
+//! assert_eq!(space.collect(), 1);
+//! let cube: extern fn(i32) -> i32 = unsafe { cube.get() };
+//! assert_eq!(cube(3), 27);
+//! ```
+use raw::*;
+use context::Context;
+use function::CompiledFunction;
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::{mem, ptr};
+
+/// A count of the handles into a generation, which counts itself while it
+/// is alive
+struct Keep {
+    count: Rc<Cell<usize>>
+}
+impl Keep {
+    fn new() -> Keep {
+        Keep {
+            count: Rc::new(Cell::new(1))
+        }
+    }
+    /// Check if only the generation itself is counted
+    fn is_unused(&self) -> bool {
+        self.count.get() == 1
+    }
+}
+impl Clone for Keep {
+    fn clone(&self) -> Keep {
+        self.count.set(self.count.get() + 1);
+        Keep {
+            count: self.count.clone()
+        }
+    }
+}
+impl Drop for Keep {
+    fn drop(&mut self) {
+        self.count.set(self.count.get() - 1);
+    }
+}
+
+/// A context functions are built on, until it has as many as a generation
+/// may have
+struct Generation<T> {
+    ctx: Box<Context<T>>,
+    functions: usize,
+    keep: Keep
+}
+
+/// The slot a handle's calls go through, and the generation of the function
+/// it points to
+struct Slot {
+    entry: AtomicUsize,
+    keep: RefCell<Keep>
+}
+
+#[derive(Clone)]
+/// A function built in a `CodeSpace`, which keeps the code of the function
+/// its slot points to from being collected
+pub struct CodeHandle {
+    slot: Rc<Slot>
+}
+impl CodeHandle {
+    /// Get the entry point of the function the slot points to now
+    pub fn get_entry(&self) -> usize {
+        self.slot.entry.load(Ordering::SeqCst)
+    }
+    /// Get the address of the slot holding the entry point, for generated
+    /// code to call through with `insn_call_slot`
+    ///
+    /// The slot stays at this address for as long as any clone of this
+    /// handle is alive.
+    pub fn get_slot(&self) -> usize {
+        &self.slot.entry as *const AtomicUsize as usize
+    }
+    /// Get the function the slot points to now as the function pointer type
+    /// `F`, which must match the signature it was built with
+    ///
+    /// The pointer is only valid while this handle, or a clone of it, is
+    /// alive and the function hasn't been replaced.
+    pub unsafe fn get<F>(&self) -> F where F: Copy {
+        if mem::size_of::<F>() != mem::size_of::<usize>() {
+            panic!("Functions can only be got as function pointers");
+        }
+        let entry = self.get_entry();
+        ptr::read(&entry as *const usize as *const F)
+    }
+}
+
+/// Contexts that functions are built on in turn, which are destroyed once
+/// none of their functions are used any more
+///
+/// Dropping the space destroys every generation, so the handles it gave out
+/// can't be called after that.
+pub struct CodeSpace<T = ()> {
+    generations: Vec<Generation<T>>,
+    per_generation: usize
+}
+impl<T> CodeSpace<T> {
+    /// Make a space that starts a new generation every `per_generation`
+    /// functions
+    ///
+    /// Smaller generations can be collected sooner, but every generation is
+    /// a context of its own, with its own pages of code.
+    pub fn new(per_generation: usize) -> CodeSpace<T> {
+        if per_generation == 0 {
+            panic!("Generations must have room for at least one function");
+        }
+        CodeSpace {
+            generations: Vec::new(),
+            per_generation: per_generation
+        }
+    }
+    /// Get the number of generations that haven't been collected
+    pub fn generations(&self) -> usize {
+        self.generations.len()
+    }
+    /// Build a function with `build` on the current generation
+    fn compile<F>(&mut self, build: F) -> (usize, Keep) where F: for<'a> FnOnce(&'a mut Context<T>) -> CompiledFunction<'a> {
+        let full = match self.generations.last() {
+            Some(generation) => generation.functions >= self.per_generation,
+            None => true
+        };
+        if full {
+            self.generations.push(Generation {
+                ctx: Box::new(Context::new()),
+                functions: 0,
+                keep: Keep::new()
+            });
+        }
+        let generation = self.generations.last_mut().unwrap();
+        let entry = {
+            let func = build(&mut *generation.ctx);
+            unsafe { jit_function_to_closure((&func).into()) as usize }
+        };
+        generation.functions += 1;
+        (entry, generation.keep.clone())
+    }
+    /// Build a function with `build` on the current generation, starting a
+    /// new one if it is full, and get a handle on it
+    pub fn build<F>(&mut self, build: F) -> CodeHandle where F: for<'a> FnOnce(&'a mut Context<T>) -> CompiledFunction<'a> {
+        let (entry, keep) = self.compile(build);
+        CodeHandle {
+            slot: Rc::new(Slot {
+                entry: AtomicUsize::new(entry),
+                keep: RefCell::new(keep)
+            })
+        }
+    }
+    /// Build a function with `build` to replace the one `handle` points to,
+    /// pointing its slot, and every clone of it, at the new one
+    ///
+    /// Calls made through the slot go to the new function from then on, and
+    /// the old one's generation can be collected once nothing else uses it.
+    pub fn replace<F>(&mut self, handle: &CodeHandle, build: F) where F: for<'a> FnOnce(&'a mut Context<T>) -> CompiledFunction<'a> {
+        let (entry, keep) = self.compile(build);
+        handle.slot.entry.store(entry, Ordering::SeqCst);
+        *handle.slot.keep.borrow_mut() = keep;
+    }
+    /// Destroy every generation but the current one that no handle points
+    /// into, giving how many were destroyed
+    pub fn collect(&mut self) -> usize {
+        let before = self.generations.len();
+        let current = match self.generations.pop() {
+            Some(current) => current,
+            None => return 0
+        };
+        let generations = mem::replace(&mut self.generations, Vec::new());
+        self.generations = generations.into_iter().filter(|generation| !generation.keep.is_unused()).collect();
+        self.generations.push(current);
+        before - self.generations.len()
+    }
+}
@@ -1,11 +1,192 @@
 use raw::*;
 use alloc::oom;
-use function::Func;
+use cpu::{self, FeatureError, Features};
+use data::ContextData;
+use exception::{self, BuiltinException, FilterAction};
+use externs::{ExternMismatch, ExternSnapshot};
+use function::{CustomOp, Func};
+use handle;
+use function::flags::CallFlags;
+use live::{self, LiveObjects};
+use location::{self, SourceLocation};
+use mathlib;
+use pages::Storage;
+use profile::CallProfile;
+use shadow::{self, PinnedRoots, ShadowFrames};
+use support::{self, Unsupported};
+use types::{consts, StaticType, Ty};
 use util::{from_ptr, from_ptr_opt};
+use wide;
+use libc::{c_char, c_int, c_uint, c_void};
 use std::marker::PhantomData;
-use std::{mem, ptr};
+use std::rc::Rc;
+use std::{mem, ptr, slice};
 use std::ops::{Index, IndexMut};
 use std::iter::IntoIterator;
+
+/// A function that is called when JIT functions are entered or exited,
+/// with the id of the function
+pub type FunctionHook = extern fn(usize);
+
+/// A function that is called with the id of a JIT function when it is about
+/// to run out of stack
+pub type OverflowHandler = extern fn(usize);
+
+#[derive(Clone, Copy)]
+/// How functions built on a context check they have enough stack left
+pub struct StackGuard {
+    /// How many bytes must be left on the stack when a function starts
+    pub headroom: usize,
+    /// The smallest constant `insn_alloca` that gets its own check, so small
+    /// allocations are covered by the headroom instead
+    pub alloca_threshold: usize,
+    /// What is called when there isn't enough stack left. If this returns,
+    /// the function carries on, so it should normally report the overflow
+    /// and abort
+    pub handler: OverflowHandler
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+/// How much `insn_call_inline` may copy into the functions it is used in
+pub struct InlineLimits {
+    /// The most instructions a function can have to be inlined, unless it is
+    /// marked with `Inline::Always`
+    pub max_size: usize,
+    /// How many levels of calls may be inlined into each other
+    pub max_depth: usize
+}
+impl Default for InlineLimits {
+    fn default() -> InlineLimits {
+        InlineLimits {
+            max_size: 32,
+            max_depth: 3
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+/// How much `insn_for_range` unrolls the loops it makes
+///
+/// LibJIT doesn't unroll loops itself, so by default they aren't unrolled.
+pub struct UnrollLimits {
+    /// How many copies of the body each trip round the main loop runs, with
+    /// 1 for no unrolling
+    pub factor: usize,
+    /// The most trips a loop with constant bounds can make to be unrolled
+    /// fully, with no loop left at all
+    pub max_full: usize
+}
+impl Default for UnrollLimits {
+    fn default() -> UnrollLimits {
+        UnrollLimits {
+            factor: 1,
+            max_full: 0
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+/// The most a function may have made in it before making any more poisons
+/// it, to keep programs that aren't trusted from building enormous functions
+///
+/// Instructions made by the `insn_` methods that do arithmetic, conversions,
+/// loads, stores, branches, calls and returns are counted, along with values
+/// made by `Val::new` and `insn_of` and labels made by `Label::new`. By
+/// default nothing is limited.
+pub struct EmitBudget {
+    /// The most instructions a function may have
+    pub max_insns: usize,
+    /// The most values a function may have
+    pub max_values: usize,
+    /// The most labels a function may have
+    pub max_labels: usize
+}
+impl Default for EmitBudget {
+    fn default() -> EmitBudget {
+        EmitBudget {
+            max_insns: usize::max_value(),
+            max_values: usize::max_value(),
+            max_labels: usize::max_value()
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+/// A floating-point precision that float arithmetic can be forced to
+pub enum Precision {
+    /// 32-bit floats, like `f32`
+    Float32,
+    /// 64-bit floats, like `f64`
+    Float64,
+    /// LibJIT's `nfloat`, the widest float the target supports natively,
+    /// which is the x87's 80-bit `long double` on x86 and `f64` on most
+    /// other targets
+    NFloat
+}
+impl Precision {
+    /// Get the type of floats in this precision
+    pub fn get_type(self) -> StaticType {
+        match self {
+            Precision::Float32 => consts::get_float32(),
+            Precision::Float64 => consts::get_float64(),
+            Precision::NFloat => consts::get_nfloat()
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+/// Settings applied to every function made on a context
+///
+/// ```rust
+/// use jit::*;
+/// let mut ctx = Context::<()>::new();
+/// ctx.set_options(ContextOptions {
+///     optimization_level: Some(UncompiledFunction::get_max_optimization_level()),
+///     call_flags: flags::CallFlags::NO_THROW,
+///     .. ContextOptions::default()
+/// });
+/// assert!(ctx.get_options().folding);
+/// ```
+pub struct ContextOptions {
+    /// The optimization level to give new functions, or `None` to leave LibJIT's default
+    pub optimization_level: Option<c_uint>,
+    /// Flags added to the flags given to every call instruction
+    pub call_flags: CallFlags,
+    /// If LibJIT should fold instructions on constants into constants
+    pub folding: bool,
+    /// If the types of values given to instructions should be checked, in
+    /// builds where `ndebug` isn't set
+    pub validation: bool,
+    /// The precision to do all float arithmetic in, converting float operands
+    /// to it first, or `None` to use the precision of the operands like
+    /// LibJIT does, which can give different results on different backends
+    pub float_precision: Option<Precision>,
+    /// If functions should fail to compile when they use a value somewhere
+    /// it might not have been set, like a result from the wrong branch
+    pub strict_ssa: bool,
+    /// If compiled functions should be registered with debuggers through the
+    /// GDB JIT interface, with line info from the locations set with
+    /// `set_current_location`, so `gdb` and `perf` can show them
+    pub debug_info: bool,
+    /// If things that go wrong while building a function, like values of
+    /// the wrong type given to instructions, should poison the function
+    /// instead of panicking, so compiling it gives the first of them
+    pub defer_errors: bool
+}
+impl Default for ContextOptions {
+    fn default() -> ContextOptions {
+        ContextOptions {
+            optimization_level: None,
+            call_flags: CallFlags::empty(),
+            folding: true,
+            validation: true,
+            float_precision: None,
+            strict_ssa: false,
+            debug_info: false,
+            defer_errors: false
+        }
+    }
+}
 /// Holds all of the functions you have built and compiled. There can be
 /// multiple, but normally there is only one.
 ///
@@ -29,6 +210,9 @@ use std::iter::IntoIterator;
 /// assert_eq!(ctx[0], 42);
 /// assert_eq!(ctx[1], 21);
 /// ```
+///
+/// Indexes from 20000 upwards are reserved for the data this library keeps
+/// about the context itself.
 pub struct Context<T = ()> {
     _context: jit_context_t,
     marker: PhantomData<T>
@@ -67,10 +251,661 @@ impl<T = ()> IndexMut<i32> for Context<T> {
 impl<T = ()> Context<T> {
     #[inline(always)]
     /// Create a new JIT Context
+    ///
+    /// To set up its options, debug mode, feature level and the rest in one
+    /// go, with the combinations checked first, use a `ContextBuilder`.
     pub fn new() -> Context<T> {
-        unsafe {
+        handle::destroy_orphans();
+        let ctx: Context<T> = unsafe {
             from_ptr(jit_context_create())
+        };
+        // attach the data now so types are counted from here on
+        ContextData::with((&ctx).into(), |_| ());
+        let mut ctx = ctx;
+        if wide::needed() {
+            ctx.set_long_helpers(true);
+        }
+        let missing = mathlib::missing();
+        if !missing.is_empty() {
+            ctx.set_math_fallbacks(&missing);
+        }
+        let missing = support::missing_insns();
+        if !missing.is_empty() {
+            ctx.set_unsupported(&missing);
         }
+        ctx
+    }
+    /// Set if 64-bit integer division, remainders, shifts and conversions to
+    /// and from floats call natives instead of being left to the backend,
+    /// registering the natives with `register_extern` when turned on
+    ///
+    /// This is turned on when the context is made if the backend generates
+    /// 32-bit code, where those need helpers anyway.
+    ///
+    /// ```rust
+    /// use jit::*;
+    /// let mut ctx = Context::<()>::new();
+    /// ctx.set_long_helpers(true);
+    /// assert!(ctx.extern_snapshot().get("jit_long_div").is_some());
+    /// let func = UncompiledFunction::new(&mut ctx, &get::<fn(i64) -> i64>());
+    /// func.insn_return(func.insn_div(&func[0], func.insn_of(3i64)));
+    /// func.compile().with(|third: extern fn(i64) -> i64| assert_eq!(third(-9), -3));
+    /// ```
+    pub fn set_long_helpers(&mut self, on: bool) {
+        if on {
+            for (name, address, signature) in wide::helpers() {
+                self.register_extern(name, address, &signature);
+            }
+        }
+        ContextData::with(self.into(), |data| data.long_helpers = on)
+    }
+    /// Check if 64-bit operations call natives, as set with `set_long_helpers`
+    pub fn uses_long_helpers(&self) -> bool {
+        ContextData::with(self.into(), |data| data.long_helpers)
+    }
+    /// Make the math instructions named, like `"sinh"` for `insn_sinh`, call
+    /// natives worked out with Rust's standard library instead of LibJIT's
+    /// intrinsics, registering the natives with `register_extern`
+    ///
+    /// This is done when the context is made for the ones `missing_math`
+    /// finds the linked LibJIT can't do, which it would otherwise give NaN
+    /// for. It panics if there is no native for an operation named.
+    ///
+    /// ```rust
+    /// use jit::*;
+    /// let mut ctx = Context::<()>::new();
+    /// ctx.set_math_fallbacks(&["sinh", "atan2"]);
+    /// assert!(ctx.extern_snapshot().get("jit_math_sinh").is_some());
+    /// let func = UncompiledFunction::new(&mut ctx, &get::<fn(f64) -> f64>());
+    /// func.insn_return(func.insn_sinh(&func[0]));
+    /// func.compile().with(|sinh: extern fn(f64) -> f64| assert_eq!(sinh(0.0), 0.0));
+    /// ```
+    pub fn set_math_fallbacks(&mut self, ops: &[&str]) {
+        let mut found = Vec::new();
+        for &op in ops {
+            match mathlib::find(op) {
+                Some(op) => found.push(op),
+                None => panic!("There is no math native for {}", op)
+            }
+        }
+        for &op in &found {
+            let (name, address, signature) = mathlib::helper(op);
+            self.register_extern(&name, address, &signature);
+        }
+        ContextData::with(self.into(), |data| data.math_fallbacks = found)
+    }
+    /// Get the math operations that call natives, as set with
+    /// `set_math_fallbacks`
+    pub fn get_math_fallbacks(&self) -> Vec<&'static str> {
+        ContextData::with(self.into(), |data| data.math_fallbacks.clone())
+    }
+    /// Treat the LibJIT functions named as missing, like `"jit_insn_round"`,
+    /// so instructions that would be made with them are made with a native
+    /// equivalent instead if there is one, or poison the function they are
+    /// made in with an `Unsupported` message if there isn't
+    ///
+    /// This is done when the context is made for the ones `missing_insns`
+    /// finds the linked LibJIT doesn't have, so their instructions call the
+    /// natives instead of poisoning the function. It panics if a function
+    /// named isn't one `missing_insns` checks for.
+    ///
+    /// ```rust
+    /// use jit::*;
+    /// let mut ctx = Context::<()>::new();
+    /// ctx.set_unsupported(&["jit_insn_round"]);
+    /// assert!(ctx.require_insn("jit_insn_round").is_err());
+    /// let func = UncompiledFunction::new(&mut ctx, &get::<fn(f64) -> f64>());
+    /// func.insn_return(func.insn_round(&func[0]));
+    /// func.compile().with(|round: extern fn(f64) -> f64| assert_eq!(round(2.5), 3.0));
+    /// ```
+    pub fn set_unsupported(&mut self, functions: &[&str]) {
+        let mut found = Vec::new();
+        for &function in functions {
+            match support::find_insn(function) {
+                Some(insn) => found.push(insn),
+                None => panic!("{} isn't a LibJIT function that can be missing", function)
+            }
+        }
+        for &(_, fallback) in &found {
+            if let Some(op) = fallback {
+                let (name, address, signature) = mathlib::helper(op);
+                self.register_extern(&name, address, &signature);
+            }
+        }
+        ContextData::with(self.into(), |data| data.unsupported = found.iter().map(|&(function, _)| function).collect())
+    }
+    /// Get the LibJIT functions treated as missing, as set with
+    /// `set_unsupported`
+    pub fn get_unsupported(&self) -> Vec<&'static str> {
+        ContextData::with(self.into(), |data| data.unsupported.clone())
+    }
+    /// Check if the LibJIT function named can be used to make instructions
+    /// on this context, giving an `Unsupported` error if it is treated as
+    /// missing
+    ///
+    /// This lets a frontend pick another way to do something before it makes
+    /// an instruction that has no native equivalent.
+    pub fn require_insn(&self, function: &str) -> Result<(), Unsupported> {
+        support::check_insn(self.into(), function)
+    }
+    /// Set if debug-only instructions like `insn_debug_assert` should be emitted
+    /// into functions built on this context
+    ///
+    /// This defaults to on unless the `ndebug` configuration is set.
+    pub fn set_debug(&mut self, debug: bool) {
+        ContextData::with(self.into(), |data| data.debug = debug)
+    }
+    /// Check if debug-only instructions are emitted into functions built on this context
+    pub fn is_debug(&self) -> bool {
+        ContextData::with(self.into(), |data| data.debug)
+    }
+    /// Request that code generated on this context uses at least the CPU
+    /// features in `min` and nothing outside `max`
+    ///
+    /// LibJIT picks instructions for the platform it was built for, so this
+    /// can't change what gets emitted. It fails if the host CPU lacks any of
+    /// the features in `min`, or if the code generator relies on features
+    /// outside `max`, and otherwise records the level for `get_feature_level`.
+    ///
+    /// ```rust
+    /// use jit::*;
+    /// let mut ctx = Context::<()>::new();
+    /// let backend = cpu::backend_features();
+    /// assert!(ctx.set_feature_level(backend, cpu::Features::all()).is_ok());
+    /// assert_eq!(ctx.get_feature_level(), Some((backend, cpu::Features::all())));
+    /// ```
+    pub fn set_feature_level(&mut self, min: Features, max: Features) -> Result<(), FeatureError> {
+        try!(cpu::check_level(min, max));
+        ContextData::with(self.into(), |data| data.features = Some((min, max)));
+        Ok(())
+    }
+    /// Get the minimum and maximum CPU features last checked with
+    /// `set_feature_level`, or `None` if no level was requested
+    pub fn get_feature_level(&self) -> Option<(Features, Features)> {
+        ContextData::with(self.into(), |data| data.features)
+    }
+    /// Set the options applied to functions made on this context from now on
+    pub fn set_options(&mut self, options: ContextOptions) {
+        unsafe {
+            if jit_context_set_meta_numeric(self.into(), JIT_OPTION_DONT_FOLD, !options.folding as jit_nuint) == 0 {
+                oom()
+            }
+        }
+        ContextData::with(self.into(), |data| data.options = options)
+    }
+    /// Get the options applied to functions made on this context
+    pub fn get_options(&self) -> ContextOptions {
+        ContextData::with(self.into(), |data| data.options)
+    }
+    /// Make every function built on this context from now on call `enter`
+    /// when it starts and `exit` before each return, with the address of the
+    /// function as its id
+    ///
+    /// `exit` is also called when an exception leaves the function, and after
+    /// the callee of a tail call returns, as tail calls are made as ordinary
+    /// calls in functions with hooks. This is useful for tracing, coverage
+    /// and finding reentrancy bugs. Functions built without hooks set have no
+    /// calls added.
+    ///
+    /// ```rust
+    /// use jit::*;
+    /// use std::sync::atomic::{AtomicUsize, Ordering, ATOMIC_USIZE_INIT};
+    /// static DEPTH: AtomicUsize = ATOMIC_USIZE_INIT;
+    /// extern fn enter(_: usize) {
+    ///     DEPTH.fetch_add(1, Ordering::SeqCst);
+    /// }
+    /// extern fn exit(_: usize) {
+    ///     DEPTH.fetch_sub(1, Ordering::SeqCst);
+    /// }
+    /// let mut ctx = Context::<()>::new();
+    /// ctx.set_function_hooks(enter, exit);
+    /// let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+    /// func.insn_return(&func[0]);
+    /// func.compile().with(|func: extern fn(i32) -> i32| assert_eq!(func(3), 3));
+    /// assert_eq!(DEPTH.load(Ordering::SeqCst), 0);
+    /// ```
+    pub fn set_function_hooks(&mut self, enter: FunctionHook, exit: FunctionHook) {
+        ContextData::with(self.into(), |data| data.hooks = Some((enter, exit)))
+    }
+    /// Stop functions built on this context from now on calling hooks
+    pub fn clear_function_hooks(&mut self) {
+        ContextData::with(self.into(), |data| data.hooks = None)
+    }
+    /// Set how large a function `insn_call_inline` will copy in and how deeply
+    /// calls may be inlined into each other, to trade code size against the
+    /// cost of calls
+    pub fn set_inline_limits(&mut self, limits: InlineLimits) {
+        ContextData::with(self.into(), |data| data.inline_limits = limits)
+    }
+    /// Get the limits on inlining functions built on this context
+    pub fn get_inline_limits(&self) -> InlineLimits {
+        ContextData::with(self.into(), |data| data.inline_limits)
+    }
+    /// Set how much `insn_for_range` unrolls loops from now on, to trade
+    /// code size against the cost of branches
+    pub fn set_unroll_limits(&mut self, limits: UnrollLimits) {
+        ContextData::with(self.into(), |data| data.unroll_limits = limits)
+    }
+    /// Get how much `insn_for_range` unrolls loops built on this context
+    pub fn get_unroll_limits(&self) -> UnrollLimits {
+        ContextData::with(self.into(), |data| data.unroll_limits)
+    }
+    /// Set how much functions built on this context from now on may have
+    /// made in them before they are poisoned
+    ///
+    /// Going over the budget is reported like any other error building a
+    /// function, so on a context that defers errors, the instruction that
+    /// goes over gives a placeholder and `try_compile` gives the error, and
+    /// otherwise it panics.
+    ///
+    /// ```rust
+    /// use jit::*;
+    /// let mut ctx = Context::<()>::new();
+    /// ctx.set_options(ContextOptions { defer_errors: true, .. ContextOptions::default() });
+    /// ctx.set_emit_budget(EmitBudget { max_insns: 2, .. EmitBudget::default() });
+    /// let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+    /// let x = func.insn_mul(&func[0], &func[0]);
+    /// func.insn_return(func.insn_add(x, &func[0]));
+    /// assert!(func.is_poisoned());
+    /// assert!(func.try_compile().is_err());
+    /// ```
+    pub fn set_emit_budget(&mut self, budget: EmitBudget) {
+        ContextData::with(self.into(), |data| data.emit_budget = budget)
+    }
+    /// Get how much functions built on this context may have made in them
+    pub fn get_emit_budget(&self) -> EmitBudget {
+        ContextData::with(self.into(), |data| data.emit_budget)
+    }
+    /// Make every function built on this context from now on check there is
+    /// at least `guard.headroom` bytes of stack left when it starts, and make
+    /// each large or variable `insn_alloca` check there is room for the
+    /// allocation too, calling `guard.handler` when there isn't
+    ///
+    /// This turns deep recursion in generated code into a call to the handler
+    /// instead of silently smashing whatever is below the stack.
+    ///
+    /// ```rust
+    /// use jit::*;
+    /// extern fn overflow(_: usize) {
+    ///     panic!("out of stack");
+    /// }
+    /// let mut ctx = Context::<()>::new();
+    /// ctx.set_stack_guard(StackGuard {
+    ///     headroom: 64 * 1024,
+    ///     alloca_threshold: 4096,
+    ///     handler: overflow
+    /// });
+    /// let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+    /// func.insn_return(&func[0]);
+    /// func.compile().with(|func: extern fn(i32) -> i32| assert_eq!(func(3), 3));
+    /// ```
+    pub fn set_stack_guard(&mut self, guard: StackGuard) {
+        ContextData::with(self.into(), |data| data.stack_guard = Some(guard))
+    }
+    /// Get the stack guard functions built on this context check, if any
+    pub fn get_stack_guard(&self) -> Option<StackGuard> {
+        ContextData::with(self.into(), |data| data.stack_guard)
+    }
+    /// Stop functions built on this context from now on checking the stack
+    pub fn clear_stack_guard(&mut self) {
+        ContextData::with(self.into(), |data| data.stack_guard = None)
+    }
+    /// Make every function built on this context from now on count how many
+    /// times each of its blocks runs, which can be read with
+    /// `Func::get_coverage`
+    ///
+    /// The counts aren't updated atomically, so they are only exact for
+    /// functions run on one thread.
+    ///
+    /// ```rust
+    /// use jit::*;
+    /// let mut ctx = Context::<()>::new();
+    /// ctx.enable_coverage();
+    /// let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+    /// func.insn_if(func.insn_lt(&func[0], func.insn_of(0i32)), || {
+    ///     func.insn_return(func.insn_of(-1i32));
+    /// });
+    /// func.insn_return(&func[0]);
+    /// let func = func.compile();
+    /// func.with(|abs: extern fn(i32) -> i32| assert_eq!(abs(3), 3));
+    /// let coverage = func.get_coverage().unwrap();
+    /// assert_eq!(coverage[0], 1);
+    /// assert!(coverage.iter().any(|&count| count == 0));
+    /// ```
+    pub fn enable_coverage(&mut self) {
+        ContextData::with(self.into(), |data| data.coverage = true)
+    }
+    /// Stop functions built on this context from now on counting how many
+    /// times their blocks run
+    pub fn disable_coverage(&mut self) {
+        ContextData::with(self.into(), |data| data.coverage = false)
+    }
+    /// Make every function built on this context from now on count how many
+    /// times it is called, which can be read with `Func::get_call_count` and
+    /// saved for exported functions with `call_profile`
+    ///
+    /// Like coverage, the counts aren't updated atomically.
+    pub fn enable_call_counts(&mut self) {
+        ContextData::with(self.into(), |data| data.call_counts = true)
+    }
+    /// Stop functions built on this context from now on counting their calls
+    pub fn disable_call_counts(&mut self) {
+        ContextData::with(self.into(), |data| data.call_counts = false)
+    }
+    /// Make every function built on this context from now on check on entry
+    /// if it has been retired with `Func::invalidate`
+    ///
+    /// The check is a load and a branch, so functions that may have to be
+    /// retired when their assumptions stop holding, like specialized or
+    /// inline-cached code, can be built with it and the rest without.
+    pub fn enable_invalidation(&mut self) {
+        ContextData::with(self.into(), |data| data.invalidation = true)
+    }
+    /// Stop functions built on this context from now on checking if they have
+    /// been invalidated
+    pub fn disable_invalidation(&mut self) {
+        ContextData::with(self.into(), |data| data.invalidation = false)
+    }
+    /// Keep the IR of every function compiled on this context from now on,
+    /// so `Func::diff_ir` can show what changed when it is compiled again
+    /// or replaced
+    ///
+    /// Each listing is kept until its function is destroyed.
+    pub fn enable_ir_history(&mut self) {
+        ContextData::with(self.into(), |data| data.ir_history = true)
+    }
+    /// Stop keeping the IR of functions compiled on this context from now on
+    pub fn disable_ir_history(&mut self) {
+        ContextData::with(self.into(), |data| data.ir_history = false)
+    }
+    /// Make everything the wrapper decides for functions built on this
+    /// context reproducible, so building the same functions in the same
+    /// order gives the same code every run
+    ///
+    /// This makes `compile_async` compile on the calling thread, so the
+    /// order functions are laid out in doesn't depend on when the compiler
+    /// thread gets to them, and makes `UncompiledFunction::warm_up` ignore
+    /// the hot profile, since call counts differ from run to run. Listings
+    /// from `dump_stable` number values and blocks in the order they appear
+    /// either way.
+    ///
+    /// What LibJIT does can still differ between runs: the addresses of
+    /// natives, globals and other functions are built into code as
+    /// constants, and code goes wherever LibJIT's allocator puts it, so
+    /// code that refers to them, or to itself by its absolute address, can
+    /// have different bytes.
+    ///
+    /// ```rust
+    /// use jit::*;
+    /// let mut ctx = Context::<()>::new();
+    /// ctx.enable_deterministic();
+    /// ctx.set_hot_profile(CallProfile::parse("parse\t5000\n"), 1000);
+    /// let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+    /// assert!(!func.warm_up("parse"));
+    /// ```
+    pub fn enable_deterministic(&mut self) {
+        ContextData::with(self.into(), |data| data.deterministic = true)
+    }
+    /// Let the wrapper do things for functions built on this context that can
+    /// differ between runs again
+    pub fn disable_deterministic(&mut self) {
+        ContextData::with(self.into(), |data| data.deterministic = false)
+    }
+    /// Check if this context was made deterministic with
+    /// `enable_deterministic`
+    pub fn is_deterministic(&self) -> bool {
+        ContextData::with(self.into(), |data| data.deterministic)
+    }
+    /// Get how many times each exported function that counts its calls has
+    /// been called, to save for warming up the next run
+    pub fn call_profile(&self) -> CallProfile {
+        let exports = ContextData::with(self.into(), |data| data.exports.clone());
+        let mut profile = CallProfile::new();
+        for (name, func) in exports {
+            let func: &Func = unsafe { from_ptr(func) };
+            if let Some(count) = func.get_call_count() {
+                profile.insert(&name, count);
+            }
+        }
+        profile
+    }
+    /// Get a C string holding `name` that stays valid for as long as this
+    /// context, which is the same pointer every time the same name is given
+    ///
+    /// The names of native calls are interned like this, so frontends that
+    /// pass names to C themselves can share them instead of allocating one
+    /// per call site.
+    ///
+    /// ```rust
+    /// use jit::*;
+    /// let ctx = Context::<()>::new();
+    /// assert_eq!(ctx.intern_name("sqrt"), ctx.intern_name("sqrt"));
+    /// assert!(ctx.intern_name("sqrt") != ctx.intern_name("cbrt"));
+    /// ```
+    pub fn intern_name(&self, name: &str) -> *const c_char {
+        ContextData::with(self.into(), |data| data.intern(name))
+    }
+    /// Use the call counts of a previous run to warm up this one, making
+    /// functions that were called at least `threshold` times get the highest
+    /// optimization level when `UncompiledFunction::warm_up` is used on them
+    pub fn set_hot_profile(&mut self, profile: CallProfile, threshold: usize) {
+        ContextData::with(self.into(), |data| data.hot_profile = Some((profile, threshold)))
+    }
+    /// Get the names of the functions that were hot in the profile given to
+    /// `set_hot_profile`, so they can be built and compiled ahead of time
+    pub fn get_hot_functions(&self) -> Vec<String> {
+        ContextData::with(self.into(), |data| match data.hot_profile {
+            Some((ref profile, threshold)) => profile.hot(threshold).into_iter().map(|name| name.to_owned()).collect(),
+            None => Vec::new()
+        })
+    }
+    /// Push a filter onto this context's stack of exception filters
+    ///
+    /// Whenever a builtin exception like a division by zero is raised in one of
+    /// this context's functions on this thread, the filters are run from the
+    /// most recently pushed to the first. Each can handle the exception by
+    /// choosing the object to throw, transform the object to throw and pass it
+    /// on, or propagate it unchanged, so layered runtimes can each observe
+    /// exceptions without replacing each other's handlers. If no filter handles
+    /// the exception, whatever handler was installed before is used.
+    pub fn push_exception_filter<F>(&mut self, filter: F)
+        where F:Fn(&Func, BuiltinException, *mut c_void) -> FilterAction + 'static {
+        ContextData::with(self.into(), |data| data.filters.push(Rc::new(filter)));
+        exception::register(self.into());
+    }
+    /// Pop the most recently pushed exception filter off this context's stack,
+    /// returning false if there were none
+    pub fn pop_exception_filter(&mut self) -> bool {
+        let (popped, empty) = ContextData::with(self.into(), |data| {
+            (data.filters.pop().is_some(), data.filters.is_empty())
+        });
+        if empty {
+            exception::unregister(self.into());
+        }
+        popped
+    }
+    /// Iterate through the descriptors of the shadow frames pushed by this
+    /// context's functions in this thread, from the innermost call outwards
+    ///
+    /// Frames are pushed with `insn_shadow_push`.
+    pub fn shadow_frames(&self) -> ShadowFrames {
+        ShadowFrames::new(shadow::top(self.into()))
+    }
+    /// Iterate through the addresses of the stack slots of the values
+    /// pinned by this context's functions in this thread, from the most
+    /// recently pinned
+    ///
+    /// Values are pinned with `insn_pin`. A collector that moves objects can
+    /// rewrite the pointers at these addresses, and the functions will read
+    /// the new ones when they carry on.
+    pub fn pinned_roots(&self) -> PinnedRoots {
+        PinnedRoots::new(shadow::top_root(self.into()))
+    }
+    /// Register the native at `address` with the signature given under `name`,
+    /// so functions can call it with `insn_call_extern`, replacing any native
+    /// registered under that name before
+    pub fn register_extern(&mut self, name: &str, address: usize, signature: &Ty) {
+        ContextData::with(self.into(), |data| {
+            data.externs.retain(|&(ref other, _, _)| other != name);
+            data.externs.push((name.to_owned(), address, signature.to_owned()));
+        })
+    }
+    /// Register a pseudo-instruction under `name`, so functions built on this
+    /// context can make it with `insn_op`, replacing any registered under that
+    /// name before
+    ///
+    /// This lets libraries of higher-level operations, like string or tagged
+    /// value operations, be shared between frontends.
+    pub fn register_op(&mut self, name: &str, expand: CustomOp) {
+        ContextData::with(self.into(), |data| data.ops.insert(name.to_owned(), expand));
+    }
+    /// Register each of the pseudo-instructions given, like a library of
+    /// them, with `register_op`
+    pub fn register_ops(&mut self, ops: &[(&str, CustomOp)]) {
+        for &(name, expand) in ops {
+            self.register_op(name, expand);
+        }
+    }
+    /// Check if a pseudo-instruction is registered under `name`
+    pub fn has_op(&self, name: &str) -> bool {
+        ContextData::with(self.into(), |data| data.ops.contains_key(name))
+    }
+    /// Make a global called `name` of type `ty` that lives as long as the
+    /// context, starting out as `initial`, so functions built on it can use
+    /// it with `insn_load_global` and `insn_store_global`
+    ///
+    /// This panics if `initial` isn't the size of `ty`, or if there is
+    /// already a global called `name`.
+    ///
+    /// ```rust
+    /// use jit::*;
+    /// let mut ctx = Context::<()>::new();
+    /// ctx.new_global("counter", &get::<i32>(), 10i32);
+    /// {
+    ///     let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+    ///     let count = func.insn_add(func.insn_load_global("counter"), &func[0]);
+    ///     func.insn_store_global("counter", count);
+    ///     func.insn_return(count);
+    ///     func.compile().with(|add: extern fn(i32) -> i32| {
+    ///         assert_eq!(add(5), 15);
+    ///         assert_eq!(add(1), 16);
+    ///     });
+    /// }
+    /// assert_eq!(ctx.get_global::<i32>("counter"), Some(16));
+    /// ```
+    pub fn new_global<V>(&mut self, name: &str, ty: &Ty, initial: V) where V: Copy {
+        self.add_global(name, ty, initial, false)
+    }
+    /// Make a global like `new_global` that can't be changed, which is kept
+    /// in pages of its own that are made read-only once `value` is written,
+    /// so generated code that writes to it by mistake faults straight away
+    ///
+    /// Functions can't store to it with `insn_store_global`.
+    ///
+    /// ```rust
+    /// use jit::*;
+    /// let mut ctx = Context::<()>::new();
+    /// ctx.new_readonly_global("limit", &get::<i32>(), 100i32);
+    /// assert!(ctx.is_global_readonly("limit"));
+    /// let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+    /// func.insn_return(func.insn_min(&func[0], func.insn_load_global("limit")));
+    /// func.compile().with(|clamp: extern fn(i32) -> i32| assert_eq!(clamp(250), 100));
+    /// ```
+    pub fn new_readonly_global<V>(&mut self, name: &str, ty: &Ty, value: V) where V: Copy {
+        self.add_global(name, ty, value, true)
+    }
+    fn add_global<V>(&mut self, name: &str, ty: &Ty, initial: V, read_only: bool) where V: Copy {
+        let size = ty.get_size();
+        if size != mem::size_of::<V>() {
+            panic!("The initial value of global {} is {} bytes, but {:?} is {}", name, mem::size_of::<V>(), ty, size);
+        }
+        let bytes = unsafe { slice::from_raw_parts(&initial as *const V as *const u8, size) };
+        let storage = Storage::new(bytes, read_only);
+        ContextData::with(self.into(), |data| {
+            if data.globals.contains_key(name) {
+                panic!("There is already a global called {}", name);
+            }
+            data.globals.insert(name.to_owned(), (storage, ty.to_owned()));
+        })
+    }
+    /// Get the address of the global called `name`, if there is one
+    pub fn get_global_address(&self, name: &str) -> Option<*mut c_void> {
+        ContextData::with(self.into(), |data| {
+            data.globals.get(name).map(|&(ref storage, _)| storage.as_ptr() as *mut c_void)
+        })
+    }
+    /// Check if the global called `name` was made read-only with
+    /// `new_readonly_global`
+    pub fn is_global_readonly(&self, name: &str) -> bool {
+        ContextData::with(self.into(), |data| {
+            data.globals.get(name).map(|&(ref storage, _)| storage.is_read_only()).unwrap_or(false)
+        })
+    }
+    /// Read the value of the global called `name`, if there is one and it is
+    /// the size of `V`
+    pub fn get_global<V>(&self, name: &str) -> Option<V> where V: Copy {
+        ContextData::with(self.into(), |data| match data.globals.get(name) {
+            Some(&(ref storage, ref ty)) if ty.get_size() == mem::size_of::<V>() =>
+                Some(unsafe { ptr::read(storage.as_ptr() as *const V) }),
+            _ => None
+        })
+    }
+    /// Snapshot the names and signatures of the natives registered on this
+    /// context, to save with code built on it
+    pub fn extern_snapshot(&self) -> ExternSnapshot {
+        ContextData::with(self.into(), |data| {
+            let mut snapshot = ExternSnapshot::new();
+            for &(ref name, _, ref signature) in &data.externs {
+                snapshot.insert(name, signature);
+            }
+            snapshot
+        })
+    }
+    /// Check that every native in `needed`, like the snapshot saved with
+    /// precompiled code, is registered on this context with the same
+    /// signature, before any of that code gets to call it
+    pub fn check_externs(&self, needed: &ExternSnapshot) -> Result<(), ExternMismatch> {
+        needed.verify(&self.extern_snapshot())
+    }
+    /// Name the source file that `file` stands for in the locations given to
+    /// `set_current_location`, for the debug info registered with debuggers
+    pub fn set_source_file(&mut self, file: u32, name: &str) {
+        ContextData::with(self.into(), |data| data.source_files.insert(file, name.to_owned()));
+    }
+    /// Walk the native stack of this thread from the innermost call outwards,
+    /// giving each frame of a function from this context and the location
+    /// set with `set_current_location` it was running at
+    ///
+    /// This is meant to be called from native code that generated code has
+    /// called, like an exception filter. Frames of functions built without
+    /// locations have none.
+    pub fn backtrace(&self) -> Vec<(&Func, Option<SourceLocation>)> {
+        location::backtrace(self.into())
+    }
+    /// Count the objects made through the wrapper for this context that are
+    /// still alive
+    ///
+    /// `Type`s aren't attached to a context, so the ones counted are those
+    /// made on this thread since the context was made. When the context is
+    /// dropped in a debug build, any of them that are still alive are
+    /// reported.
+    ///
+    /// ```rust
+    /// use jit::*;
+    /// let mut ctx = Context::<()>::new();
+    /// {
+    ///     let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+    ///     let local_var = func.new_lvalue(&get::<i32>());
+    ///     let local = local_var.get_local().unwrap();
+    ///     func.insn_store(&local_var, &func[0]);
+    ///     func.insn_return(local);
+    ///     func.compile();
+    /// }
+    /// let live = ctx.live_objects();
+    /// assert_eq!((live.functions, live.values, live.types), (1, 0, 0));
+    /// ```
+    pub fn live_objects(&self) -> LiveObjects {
+        live::count(self.into())
     }
     /// Iterate through the functions contained inside this context
     pub fn functions(&self) -> Functions {
@@ -94,9 +929,19 @@ impl<'a, T> IntoIterator for &'a Context<T> {
 impl<T> Drop for Context<T> {
     #[inline(always)]
     fn drop(&mut self) {
-        unsafe {
-            jit_context_destroy(self.into());
+        // finish compiling in the background before the functions go away
+        let compiler = ContextData::with(self.into(), |data| data.compiler.take());
+        mem::drop(compiler);
+        exception::unregister(self.into());
+        shadow::forget(self.into());
+        live::report(self.into());
+        // leave the code for the handles still using it to clean up
+        if !handle::release(self.into()) {
+            unsafe {
+                jit_context_destroy(self.into());
+            }
         }
+        handle::destroy_orphans();
     }
 }
 
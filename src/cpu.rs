@@ -0,0 +1,146 @@
+//! Reports the CPU features the JIT's code generator relies on
+use std::error::Error;
+use std::fmt;
+use std::fs::File;
+use std::io::Read;
+
+pub use self::features::Features;
+/// CPU feature flags
+pub mod features {
+    bitflags!(
+        flags Features: u32 {
+            /// x86 Streaming SIMD Extensions
+            const SSE = 1,
+            /// x86 Streaming SIMD Extensions 2
+            const SSE2 = 2,
+            /// x86 Streaming SIMD Extensions 3
+            const SSE3 = 4,
+            /// x86 Streaming SIMD Extensions 4.1
+            const SSE4_1 = 8,
+            /// x86 Streaming SIMD Extensions 4.2
+            const SSE4_2 = 16,
+            /// x86 Advanced Vector Extensions
+            const AVX = 32,
+            /// x86 Advanced Vector Extensions 2
+            const AVX2 = 64,
+            /// ARM vector floating point
+            const VFP = 128,
+            /// ARM Advanced SIMD
+            const NEON = 256
+        }
+    );
+}
+
+/// Get the features that code generated on this platform relies on
+///
+/// When LibJIT is using its interpreter, no features are needed.
+pub fn backend_features() -> Features {
+    if ::uses_interpreter() {
+        Features::empty()
+    } else if cfg!(target_arch = "x86_64") {
+        Features::SSE | Features::SSE2
+    } else if cfg!(target_arch = "arm") {
+        Features::VFP
+    } else {
+        Features::empty()
+    }
+}
+
+/// Get the features the host CPU supports
+///
+/// This reads `/proc/cpuinfo` where it is available, and otherwise falls back
+/// to the features the crate was compiled to assume.
+pub fn host_features() -> Features {
+    let mut text = String::new();
+    let read = File::open("/proc/cpuinfo").and_then(|mut file| file.read_to_string(&mut text));
+    if read.is_err() {
+        return baseline_features()
+    }
+    let mut features = Features::empty();
+    for line in text.lines() {
+        let mut parts = line.splitn(2, ':');
+        let key = parts.next().unwrap_or("").trim();
+        if key != "flags" && key != "Features" {
+            continue
+        }
+        for flag in parts.next().unwrap_or("").split_whitespace() {
+            features = features | match flag {
+                "sse" => Features::SSE,
+                "sse2" => Features::SSE2,
+                "pni" | "sse3" => Features::SSE3,
+                "sse4_1" => Features::SSE4_1,
+                "sse4_2" => Features::SSE4_2,
+                "avx" => Features::AVX,
+                "avx2" => Features::AVX2,
+                "vfp" | "vfpv3" | "vfpv4" => Features::VFP,
+                "neon" | "asimd" => Features::NEON,
+                _ => Features::empty()
+            }
+        }
+        break
+    }
+    features | baseline_features()
+}
+
+fn baseline_features() -> Features {
+    let mut features = Features::empty();
+    if cfg!(target_feature = "sse") {
+        features = features | Features::SSE;
+    }
+    if cfg!(target_feature = "sse2") {
+        features = features | Features::SSE2;
+    }
+    if cfg!(target_arch = "aarch64") {
+        features = features | Features::NEON | Features::VFP;
+    }
+    features
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+/// An error from requesting a feature level that can't be met
+pub enum FeatureError {
+    /// The host CPU lacks these features from the minimum level
+    Missing(Features),
+    /// The code generator always uses these features, which are above the maximum level
+    Unavoidable(Features)
+}
+impl fmt::Display for FeatureError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            FeatureError::Missing(features) => write!(fmt, "{}: {:?}", self.description(), features),
+            FeatureError::Unavoidable(features) => write!(fmt, "{}: {:?}", self.description(), features)
+        }
+    }
+}
+impl Error for FeatureError {
+    fn description(&self) -> &'static str {
+        match *self {
+            FeatureError::Missing(_) => "The host CPU lacks required features",
+            FeatureError::Unavoidable(_) => "The code generator uses features above the maximum level"
+        }
+    }
+}
+/// Check that the host CPU has the features in `min`, and that the code
+/// generator won't use anything outside `max`
+///
+/// LibJIT picks its instructions itself, so this can't change what it uses,
+/// but it lets an application find out up front whether the code it
+/// generates fits the machines it is shipped to.
+///
+/// ```rust
+/// use jit::*;
+/// let backend = cpu::backend_features();
+/// assert!(cpu::check_level(backend, cpu::Features::all()).is_ok());
+/// assert_eq!(cpu::check_level(cpu::Features::empty(), cpu::Features::empty()).is_ok(), backend.is_empty());
+/// ```
+pub fn check_level(min: Features, max: Features) -> Result<(), FeatureError> {
+    let missing = min - host_features();
+    let unavoidable = backend_features() - max;
+    if !missing.is_empty() {
+        Err(FeatureError::Missing(missing))
+    } else if !unavoidable.is_empty() {
+        Err(FeatureError::Unavoidable(unavoidable))
+    } else {
+        Ok(())
+    }
+}
@@ -0,0 +1,106 @@
+//! Finding instructions that work out a value already worked out earlier in
+//! the same block
+use raw::*;
+use insn::Instruction;
+use replay;
+use types::Ty;
+use util::from_ptr;
+use value::Val;
+use std::collections::HashMap;
+use std::mem;
+
+/// An operand, as what it stands for in the function being built
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+enum Operand {
+    Value(jit_value_t),
+    Int(jit_type_t, i64),
+    Float(jit_type_t, u64)
+}
+
+/// Get what `value` from the function being rebuilt stands for, using the
+/// values it has been mapped to so far
+fn operand(value: jit_value_t, values: &HashMap<jit_value_t, &Val>) -> Operand {
+    unsafe {
+        if jit_value_is_constant(value) == 0 {
+            return Operand::Value(values.get(&value).map(|&mapped| mapped.into()).unwrap_or(value))
+        }
+        let ty = jit_value_get_type(value);
+        let float = {
+            let ty: &Ty = from_ptr(ty);
+            ty.is_float()
+        };
+        if float {
+            Operand::Float(ty, mem::transmute(jit_value_get_float64_constant(value)))
+        } else {
+            Operand::Int(ty, jit_value_get_long_constant(value) as i64)
+        }
+    }
+}
+
+/// Check if the instruction named reads memory, so a store can change what
+/// it gives
+fn loads(name: &str) -> bool {
+    name.starts_with("load_relative_") || name.starts_with("load_element_")
+}
+/// Check if the instruction named writes to memory
+fn stores(name: &str) -> bool {
+    name.starts_with("store_") || name.starts_with("mem")
+}
+
+/// The opcode, result type and operands of an instruction
+type Key<'a> = (&'a str, jit_type_t, Option<Operand>, Option<Operand>);
+
+/// The values worked out so far in a block, by the instruction that worked
+/// them out
+pub struct Table<'a> {
+    entries: HashMap<Key<'a>, &'a Val>
+}
+impl<'a> Table<'a> {
+    /// Start with nothing worked out, like at the start of a block
+    pub fn new() -> Table<'a> {
+        Table {
+            entries: HashMap::new()
+        }
+    }
+    fn key(insn: Instruction<'a>, values: &HashMap<jit_value_t, &'a Val>) -> Option<Key<'a>> {
+        let name = insn.get_name();
+        let matched = replay::decode(name).is_some() || (replay::is_conversion(name) && !name.starts_with("check_"))
+            || loads(name);
+        let dest: jit_value_t = match (insn.get_dest(), insn.dest_is_value()) {
+            (Some(dest), false) => dest.into(),
+            _ => return None
+        };
+        if !matched || unsafe { jit_value_is_temporary(dest) == 0 } {
+            return None
+        }
+        let v1 = insn.get_value1().map(|value| operand(value.into(), values));
+        let v2 = insn.get_value2().map(|value| operand(value.into(), values));
+        Some((name, unsafe { jit_value_get_type(dest) }, v1, v2))
+    }
+    /// Find the value the instruction works out, if it has already been
+    /// worked out
+    pub fn find(&self, insn: Instruction<'a>, values: &HashMap<jit_value_t, &'a Val>) -> Option<&'a Val> {
+        Table::key(insn, values).and_then(|key| self.entries.get(&key).cloned())
+    }
+    /// Record what the instruction that has just been rebuilt worked out, or
+    /// forget what it invalidated
+    pub fn record(&mut self, insn: Instruction<'a>, values: &HashMap<jit_value_t, &'a Val>) {
+        let name = insn.get_name();
+        if stores(name) {
+            self.forget(|&(name, _, _, _)| loads(name));
+        }
+        if let Some(key) = Table::key(insn, values) {
+            let dest: jit_value_t = insn.get_dest().unwrap().into();
+            if let Some(&value) = values.get(&dest) {
+                self.entries.insert(key, value);
+            }
+        } else if let (Some(dest), false) = (insn.get_dest(), insn.dest_is_value()) {
+            let assigned = operand(dest.into(), values);
+            self.forget(|&(_, _, v1, v2)| v1 == Some(assigned) || v2 == Some(assigned));
+        }
+    }
+    fn forget<F>(&mut self, invalid: F) where F:Fn(&Key<'a>) -> bool {
+        let entries = mem::replace(&mut self.entries, HashMap::new());
+        self.entries = entries.into_iter().filter(|&(ref key, _)| !invalid(key)).collect();
+    }
+}
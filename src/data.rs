@@ -0,0 +1,391 @@
+use raw::*;
+use arena::Arena;
+use background::Compiler;
+use context::{ContextOptions, EmitBudget, FunctionHook, InlineLimits, StackGuard, UnrollLimits};
+use cpu::Features;
+use debuginfo::DebugEntry;
+use exception::ExceptionFilter;
+use fence::Fence;
+use function::{CustomOp, EmitError, EmitWarning, GuardHandler, Inline, Overflow};
+use handle::Retain;
+use live;
+use pages::Storage;
+use profile::CallProfile;
+use reduce::Reduction;
+use location::SourceLocation;
+use types::Type;
+use libc::{c_char, c_int};
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::mem;
+use std::rc::Rc;
+use std::sync::Arc;
+use std::sync::atomic::AtomicUsize;
+
+/// The metadata index the wrapper's context data is kept under
+pub const CONTEXT_DATA: c_int = 20000;
+/// The metadata index the wrapper's function data is kept under
+const FUNCTION_DATA: c_int = 20000;
+
+/// Data the wrapper keeps about a context alongside LibJIT's own
+pub struct ContextData {
+    /// If debug-only instructions like `insn_debug_assert` should be emitted
+    pub debug: bool,
+    /// The minimum and maximum CPU features requested for generated code, if any
+    pub features: Option<(Features, Features)>,
+    /// The exception filters, with the most recently pushed last
+    pub filters: Vec<ExceptionFilter>,
+    /// The options applied to new functions
+    pub options: ContextOptions,
+    /// The hooks new functions call on entry and exit
+    pub hooks: Option<(FunctionHook, FunctionHook)>,
+    /// If new functions should count how many times each block runs
+    pub coverage: bool,
+    /// If new functions should count how many times they are called
+    pub call_counts: bool,
+    /// If new functions should check if they have been invalidated on entry
+    pub invalidation: bool,
+    /// If the IR of functions should be kept when they are compiled
+    pub ir_history: bool,
+    /// The profile of a previous run given to `set_hot_profile`, and how many
+    /// calls make a function hot
+    pub hot_profile: Option<(CallProfile, usize)>,
+    /// If what the wrapper does should be reproducible between runs
+    pub deterministic: bool,
+    /// The stack check new functions make
+    pub stack_guard: Option<StackGuard>,
+    /// How much `insn_call_inline` may inline
+    pub inline_limits: InlineLimits,
+    /// How much `insn_for_range` unrolls
+    pub unroll_limits: UnrollLimits,
+    /// How much functions built on the context may have made in them
+    pub emit_budget: EmitBudget,
+    /// The thread functions are compiled on by `compile_async`, once it has
+    /// been started
+    pub compiler: Option<Compiler>,
+    /// The names of the source files given to `set_source_file`
+    pub source_files: HashMap<u32, String>,
+    /// The natives given to `register_extern`, as their names, addresses and
+    /// signatures
+    pub externs: Vec<(String, usize, Type)>,
+    /// The pseudo-instructions given to `register_op`, by name
+    pub ops: HashMap<String, CustomOp>,
+    /// If 64-bit division, shifts and float conversions call the natives
+    /// in `wide`
+    pub long_helpers: bool,
+    /// The math operations that call the natives in `mathlib`
+    pub math_fallbacks: Vec<&'static str>,
+    /// The LibJIT functions for instructions that are treated as missing
+    pub unsupported: Vec<&'static str>,
+    /// The globals made with `new_global`, by name, as their storage and
+    /// types
+    pub globals: HashMap<String, (Storage, Type)>,
+    /// The functions given to `CompiledFunction::export`, by the names they
+    /// are exported under
+    pub exports: Vec<(String, jit_function_t)>,
+    /// How many `Type`s were alive on the thread the context was made on
+    /// when it was made
+    pub types_base: isize,
+    /// Holds the names interned by `intern`, which live as long as the context
+    pub names: Arena,
+    /// The C string each interned name was copied to
+    pub interned: HashMap<String, *const c_char>,
+    /// What keeps the context's code alive for the handles made with
+    /// `to_handle`, once any have been
+    pub retain: Option<Arc<Retain>>,
+    /// The reduction kernels built on the context, by what they do and the
+    /// type of number they work on
+    pub reductions: HashMap<(Reduction, jit_type_t), jit_function_t>,
+    /// The types functions compiled on the context use, which can't be
+    /// changed while it is alive
+    pub fences: HashMap<jit_type_t, Fence>,
+    /// The slots patchable call sites call through, which are kept as long
+    /// as the context so `CallSite`s can outlive the functions making them
+    pub call_sites: Vec<Box<AtomicUsize>>
+}
+impl ContextData {
+    fn new() -> ContextData {
+        ContextData {
+            debug: cfg!(not(ndebug)),
+            features: None,
+            filters: Vec::new(),
+            options: ContextOptions::default(),
+            hooks: None,
+            coverage: false,
+            call_counts: false,
+            invalidation: false,
+            ir_history: false,
+            hot_profile: None,
+            deterministic: false,
+            stack_guard: None,
+            inline_limits: InlineLimits::default(),
+            unroll_limits: UnrollLimits::default(),
+            emit_budget: EmitBudget::default(),
+            compiler: None,
+            source_files: HashMap::new(),
+            externs: Vec::new(),
+            ops: HashMap::new(),
+            long_helpers: false,
+            math_fallbacks: Vec::new(),
+            unsupported: Vec::new(),
+            globals: HashMap::new(),
+            exports: Vec::new(),
+            types_base: live::types(),
+            names: Arena::new(),
+            interned: HashMap::new(),
+            retain: None,
+            reductions: HashMap::new(),
+            fences: HashMap::new(),
+            call_sites: Vec::new()
+        }
+    }
+    /// Get the C string `name` was interned as, copying it into the context's
+    /// names the first time it is seen
+    pub fn intern(&mut self, name: &str) -> *const c_char {
+        if let Some(&c_name) = self.interned.get(name) {
+            return c_name
+        }
+        let c_name = self.names.alloc_str(name);
+        self.interned.insert(name.to_owned(), c_name);
+        c_name
+    }
+    /// Run the closure with the data attached to `ctx`, attaching it first if
+    /// there isn't any yet
+    pub fn with<R, F>(ctx: jit_context_t, cb: F) -> R where F:FnOnce(&mut ContextData) -> R {
+        unsafe {
+            let mut data = jit_context_get_meta(ctx, CONTEXT_DATA);
+            if data.is_null() {
+                let boxed = Box::new(ContextData::new());
+                data = mem::transmute(boxed);
+                if jit_context_set_meta(ctx, CONTEXT_DATA, data, Some(::free_data::<ContextData>)) == 0 {
+                    ::alloc::oom()
+                }
+            }
+            cb(mem::transmute(data))
+        }
+    }
+    /// Run the closure with the data attached to the context `func` belongs to
+    pub fn with_func<R, F>(func: jit_function_t, cb: F) -> R where F:FnOnce(&mut ContextData) -> R {
+        unsafe {
+            ContextData::with(jit_function_get_context(func), cb)
+        }
+    }
+}
+
+/// Data the wrapper keeps about a function alongside LibJIT's own
+///
+/// This is attached to the function as metadata which isn't freed until the
+/// function itself is destroyed, so anything the compiled code points to
+/// can be kept alive here.
+pub struct FunctionData {
+    /// Holds the C strings that instructions in the function refer to
+    pub strings: Arena,
+    /// Values currently pinned with `insn_pin`, each with the pointer to the
+    /// record that lists it in the stack map
+    pub pinned: Vec<(jit_value_t, jit_value_t)>,
+    /// The address of the top of the context's stack map, and the top it had
+    /// when the function was entered, once something has been pinned
+    pub pin_roots: Option<(jit_value_t, jit_value_t)>,
+    /// Pointer parameters marked as not aliasing any other pointer
+    pub noalias: Vec<jit_value_t>,
+    /// Loads through `noalias` pointers that can be reused, as the block they
+    /// were made in, the pointer, offset and type, and the value loaded
+    pub loads: Vec<(jit_block_t, jit_value_t, usize, jit_type_t, jit_value_t)>,
+    /// The hook to call before each return
+    pub exit_hook: Option<FunctionHook>,
+    /// How many times each block has run, if coverage is enabled
+    pub coverage: Option<Vec<Box<usize>>>,
+    /// How many times the function has been called, if call counts are
+    /// enabled
+    pub calls: Option<Box<usize>>,
+    /// The flag the function checks on entry, which is set once it is
+    /// invalidated, if invalidation is enabled
+    pub stale: Option<Arc<AtomicUsize>>,
+    /// Where each side exit of a trace jumps to, or zero to call the trace's
+    /// exit handler
+    pub links: Vec<Box<usize>>,
+    /// The source locations set with `set_current_location`, indexed by the
+    /// offset their instructions are marked with
+    pub locations: Vec<SourceLocation>,
+    /// The object describing the compiled code to debuggers, if the context
+    /// asked for debug info
+    pub debug_entry: Option<DebugEntry>,
+    /// The blocks, instructions, values living across blocks and values
+    /// living in the frame counted just before the function was compiled
+    pub codegen_counts: Option<(usize, usize, usize, usize)>,
+    /// The listing of the IR the function was last compiled with, if its
+    /// context keeps IR history
+    pub ir: Option<String>,
+    /// The listing of the IR the function, or the one it is a new tier of,
+    /// was compiled with before that
+    pub previous_ir: Option<String>,
+    /// How integer arithmetic that follows the overflow mode overflows
+    pub overflow: Overflow,
+    /// The constant tables made with `new_table`, as the storage holding
+    /// the constants and the type of each constant
+    pub tables: Vec<(Storage, Type)>,
+    /// The payload types of `insn_throw_typed` and `insn_start_typed_catcher`,
+    /// kept alive for the natives they are given to
+    pub payload_types: Vec<Type>,
+    /// The bytes of the buffers the function refers to, kept pinned until
+    /// it is destroyed
+    pub buffers: Vec<Rc<Vec<u8>>>,
+    /// How many values have been made in the function with `Val::new`
+    pub values: usize,
+    /// If calls to the function made with `insn_call_inline` should be inlined
+    pub inline: Inline,
+    /// How many levels of calls have been inlined into the function
+    pub inline_depth: usize,
+    /// What failed guards call
+    pub guard_handler: Option<GuardHandler>,
+    /// The exit stub shared by the function's guards, as its label and the
+    /// locals holding the exit id, the captured values and how many there are
+    pub guard_stub: Option<(jit_label_t, jit_value_t, jit_value_t, jit_value_t)>,
+    /// Shared with weak references to the function, which see it cleared once
+    /// the function is destroyed
+    pub alive: Option<Rc<Cell<bool>>>,
+    /// The names given to labels with `name_label`
+    pub label_names: Vec<(jit_label_t, String)>,
+    /// The first thing that went wrong building the function, if its
+    /// context defers errors
+    pub poison: Option<EmitError>,
+    /// The pointers made by `insn_alloca`, and values derived from them,
+    /// while validating
+    pub allocas: Vec<jit_value_t>,
+    /// The suspicious things found while validating the function
+    pub warnings: Vec<EmitWarning>,
+    /// The budget set on the function itself, in place of its context's
+    pub budget: Option<EmitBudget>,
+    /// How many instructions, values and labels have been counted against
+    /// the budget
+    pub spent: (usize, usize, usize),
+    /// The pointer to the state the function is generated for, which helpers
+    /// are given as their first parameter
+    pub env: Option<jit_value_t>,
+    /// The flag each cleanup region sets while it is running and the finally
+    /// block that cleans up after it, from the outermost
+    pub cleanups: Vec<(jit_value_t, jit_label_t)>,
+    /// If the catch block has been started
+    pub catcher: bool
+}
+impl FunctionData {
+    fn new() -> FunctionData {
+        FunctionData {
+            strings: Arena::new(),
+            pinned: Vec::new(),
+            pin_roots: None,
+            noalias: Vec::new(),
+            loads: Vec::new(),
+            exit_hook: None,
+            coverage: None,
+            calls: None,
+            stale: None,
+            links: Vec::new(),
+            locations: Vec::new(),
+            debug_entry: None,
+            codegen_counts: None,
+            ir: None,
+            previous_ir: None,
+            overflow: Overflow::Wrap,
+            tables: Vec::new(),
+            payload_types: Vec::new(),
+            buffers: Vec::new(),
+            values: 0,
+            inline: Inline::Auto,
+            inline_depth: 0,
+            guard_handler: None,
+            guard_stub: None,
+            alive: None,
+            label_names: Vec::new(),
+            poison: None,
+            allocas: Vec::new(),
+            warnings: Vec::new(),
+            budget: None,
+            spent: (0, 0, 0),
+            env: None,
+            cleanups: Vec::new(),
+            catcher: false
+        }
+    }
+    /// Run the closure with the data attached to `func`, attaching it first if
+    /// there isn't any yet
+    pub fn with<R, F>(func: jit_function_t, cb: F) -> R where F:FnOnce(&mut FunctionData) -> R {
+        unsafe {
+            let mut data = jit_function_get_meta(func, FUNCTION_DATA);
+            if data.is_null() {
+                let boxed = Box::new(FunctionData::new());
+                data = mem::transmute(boxed);
+                if jit_function_set_meta(func, FUNCTION_DATA, data, Some(::free_data::<FunctionData>), 0) == 0 {
+                    ::alloc::oom()
+                }
+            }
+            cb(mem::transmute(data))
+        }
+    }
+    /// Give `to` the settings made on `from` that aren't kept in its
+    /// instructions, for a function holding code rebuilt from it
+    pub fn inherit(from: jit_function_t, to: jit_function_t) {
+        let (exit_hook, overflow, inline, guard_handler, budget) = FunctionData::with(from, |data| {
+            (data.exit_hook, data.overflow, data.inline, data.guard_handler, data.budget)
+        });
+        FunctionData::with(to, |data| {
+            data.exit_hook = exit_hook;
+            data.overflow = overflow;
+            data.inline = inline;
+            data.guard_handler = guard_handler;
+            data.budget = budget;
+        })
+    }
+    /// Move the storage the code of `from` points into, like its counters,
+    /// constant tables and pinned buffers, over to `to`, which has been
+    /// rebuilt from its instructions, along with its settings
+    ///
+    /// Anything only `from` uses, like its weak references, stays with it.
+    pub fn adopt(from: jit_function_t, to: jit_function_t) {
+        FunctionData::inherit(from, to);
+        let (strings, coverage, calls, stale, links, locations, tables, payload_types, buffers) =
+            FunctionData::with(from, |data| (
+                mem::replace(&mut data.strings, Arena::new()),
+                data.coverage.take(),
+                data.calls.take(),
+                data.stale.take(),
+                mem::replace(&mut data.links, Vec::new()),
+                mem::replace(&mut data.locations, Vec::new()),
+                mem::replace(&mut data.tables, Vec::new()),
+                mem::replace(&mut data.payload_types, Vec::new()),
+                mem::replace(&mut data.buffers, Vec::new())
+            ));
+        FunctionData::with(to, |data| {
+            data.strings = strings;
+            data.coverage = coverage;
+            data.calls = calls;
+            data.stale = stale;
+            data.links = links;
+            data.locations = locations;
+            data.tables = tables;
+            data.payload_types = payload_types;
+            data.buffers = buffers;
+        })
+    }
+    /// Keep the string given alive for as long as the function and return a
+    /// pointer to its contents
+    pub fn keep_string(func: jit_function_t, text: &str) -> *const c_char {
+        FunctionData::with(func, |data| data.strings.alloc_str(text))
+    }
+    /// Get the flag that says if `func` is still alive
+    pub fn alive(func: jit_function_t) -> Rc<Cell<bool>> {
+        FunctionData::with(func, |data| {
+            if data.alive.is_none() {
+                data.alive = Some(Rc::new(Cell::new(true)));
+            }
+            data.alive.as_ref().unwrap().clone()
+        })
+    }
+}
+impl Drop for FunctionData {
+    fn drop(&mut self) {
+        if let Some(ref alive) = self.alive {
+            alive.set(false);
+        }
+    }
+}
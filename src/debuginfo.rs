@@ -0,0 +1,460 @@
+//! Registers compiled functions with debuggers through the GDB JIT
+//! interface, as in-memory ELF objects with minimal DWARF line and frame
+//! info, so `gdb` and `perf` can show where JIT frames come from
+use raw::*;
+use data::{ContextData, FunctionData};
+use location;
+use libc::{c_uint, c_void};
+use std::collections::HashMap;
+use std::ptr;
+use std::sync::atomic::{AtomicBool, Ordering, ATOMIC_BOOL_INIT};
+
+/// An entry in the list of objects debuggers read, laid out like GDB expects
+#[repr(C)]
+struct CodeEntry {
+    next: *mut CodeEntry,
+    prev: *mut CodeEntry,
+    symfile_addr: *const u8,
+    symfile_size: u64
+}
+
+/// The descriptor GDB finds by name and reads the list of objects from
+#[repr(C)]
+pub struct JitDescriptor {
+    version: u32,
+    action_flag: u32,
+    relevant_entry: *mut CodeEntry,
+    first_entry: *mut CodeEntry
+}
+
+const JIT_NOACTION: u32 = 0;
+const JIT_REGISTER_FN: u32 = 1;
+const JIT_UNREGISTER_FN: u32 = 2;
+
+#[no_mangle]
+#[allow(non_upper_case_globals)]
+pub static mut __jit_debug_descriptor: JitDescriptor = JitDescriptor {
+    version: 1,
+    action_flag: JIT_NOACTION,
+    relevant_entry: 0 as *mut CodeEntry,
+    first_entry: 0 as *mut CodeEntry
+};
+
+/// The function GDB puts a breakpoint on to hear about changes to the list
+#[no_mangle]
+#[inline(never)]
+pub extern fn __jit_debug_register_code() {
+    unsafe {
+        // keep the call from being optimized out
+        ptr::read_volatile(&__jit_debug_descriptor.action_flag);
+    }
+}
+
+/// Held while the list of objects is being changed
+static LOCK: AtomicBool = ATOMIC_BOOL_INIT;
+
+/// Run the closure while holding the lock on the list of objects, then tell
+/// the debugger about the entry given
+unsafe fn notify<F>(action: u32, entry: *mut CodeEntry, cb: F) where F:FnOnce(&mut JitDescriptor) {
+    while LOCK.compare_and_swap(false, true, Ordering::Acquire) {}
+    cb(&mut __jit_debug_descriptor);
+    __jit_debug_descriptor.relevant_entry = entry;
+    __jit_debug_descriptor.action_flag = action;
+    __jit_debug_register_code();
+    __jit_debug_descriptor.action_flag = JIT_NOACTION;
+    LOCK.store(false, Ordering::Release);
+}
+
+/// An object registered with debuggers, which is unregistered when this is
+/// dropped
+pub struct DebugEntry {
+    entry: Box<CodeEntry>,
+    object: Vec<u8>
+}
+impl DebugEntry {
+    fn register(object: Vec<u8>) -> DebugEntry {
+        let mut entry = Box::new(CodeEntry {
+            next: ptr::null_mut(),
+            prev: ptr::null_mut(),
+            symfile_addr: object.as_ptr(),
+            symfile_size: object.len() as u64
+        });
+        let raw = &mut *entry as *mut CodeEntry;
+        unsafe {
+            notify(JIT_REGISTER_FN, raw, |desc| {
+                (*raw).next = desc.first_entry;
+                if !desc.first_entry.is_null() {
+                    (*desc.first_entry).prev = raw;
+                }
+                desc.first_entry = raw;
+            });
+        }
+        DebugEntry {
+            entry: entry,
+            object: object
+        }
+    }
+    /// Get the ELF object that was registered
+    pub fn get_object(&self) -> &[u8] {
+        &self.object
+    }
+}
+impl Drop for DebugEntry {
+    fn drop(&mut self) {
+        let raw = &mut *self.entry as *mut CodeEntry;
+        unsafe {
+            notify(JIT_UNREGISTER_FN, raw, |desc| {
+                let entry = &mut *raw;
+                if entry.prev.is_null() {
+                    desc.first_entry = entry.next;
+                } else {
+                    (*entry.prev).next = entry.next;
+                }
+                if !entry.next.is_null() {
+                    (*entry.next).prev = entry.prev;
+                }
+            });
+        }
+    }
+}
+
+/// Find the start and end addresses of the code of the compiled `func`, by
+/// asking LibJIT which function addresses belong to
+pub fn code_range(func: jit_function_t) -> (usize, usize) {
+    unsafe {
+        let ctx = jit_function_get_context(func);
+        let start = jit_function_to_closure(func) as usize;
+        let inside = |pc: usize| jit_function_from_pc(ctx, pc as *mut c_void, ptr::null_mut()) == func;
+        if start == 0 || !inside(start) {
+            return (start, start)
+        }
+        let (mut inner, mut step) = (start, 16);
+        while inside(start + step) {
+            inner = start + step;
+            step *= 2;
+        }
+        let mut outer = start + step;
+        while outer - inner > 1 {
+            let middle = inner + (outer - inner) / 2;
+            if inside(middle) {
+                inner = middle;
+            } else {
+                outer = middle;
+            }
+        }
+        (start, outer)
+    }
+}
+
+/// A stack trace with one frame, laid out like LibJIT's, so the bytecode
+/// offset of any address can be looked up with `jit_stack_trace_get_offset`
+#[repr(C)]
+struct Trace {
+    size: c_uint,
+    items: [*mut c_void; 1]
+}
+
+/// Find where the code from each source location starts, as the address and
+/// the index of the location
+fn rows(func: jit_function_t, start: usize, end: usize) -> Vec<(usize, usize)> {
+    let mut rows: Vec<(usize, usize)> = Vec::new();
+    unsafe {
+        let ctx = jit_function_get_context(func);
+        for pc in start..end {
+            let mut trace = Trace {
+                size: 1,
+                items: [pc as *mut c_void]
+            };
+            let offset = jit_stack_trace_get_offset(ctx, &mut trace as *mut Trace as jit_stack_trace_t, 0);
+            if location::lookup(func, offset as usize).is_some() &&
+                    rows.last().map(|&(_, last)| last) != Some(offset as usize) {
+                rows.push((pc, offset as usize));
+            }
+        }
+    }
+    rows
+}
+
+/// Bytes being laid out in the target's byte order
+struct Bytes(Vec<u8>);
+impl Bytes {
+    fn u8(&mut self, value: u8) {
+        self.0.push(value);
+    }
+    fn u16(&mut self, value: u16) {
+        self.le(value as u64, 2);
+    }
+    fn u32(&mut self, value: u32) {
+        self.le(value as u64, 4);
+    }
+    fn u64(&mut self, value: u64) {
+        self.le(value, 8);
+    }
+    fn le(&mut self, value: u64, size: usize) {
+        for i in 0..size {
+            self.0.push((value >> (i * 8)) as u8);
+        }
+    }
+    fn uleb(&mut self, mut value: u64) {
+        loop {
+            let byte = (value & 0x7f) as u8;
+            value >>= 7;
+            if value == 0 {
+                return self.0.push(byte)
+            }
+            self.0.push(byte | 0x80);
+        }
+    }
+    fn sleb(&mut self, mut value: i64) {
+        loop {
+            let byte = (value & 0x7f) as u8;
+            value >>= 7;
+            if (value == 0 && byte & 0x40 == 0) || (value == -1 && byte & 0x40 != 0) {
+                return self.0.push(byte)
+            }
+            self.0.push(byte | 0x80);
+        }
+    }
+    fn str(&mut self, text: &str) {
+        self.0.extend(text.bytes());
+        self.0.push(0);
+    }
+    fn align(&mut self, align: usize, fill: u8) {
+        while self.0.len() % align != 0 {
+            self.0.push(fill);
+        }
+    }
+    /// Write the length of what is written in `cb` before it
+    fn sized<F>(&mut self, cb: F) where F:FnOnce(&mut Bytes) {
+        let at = self.0.len();
+        self.u32(0);
+        cb(self);
+        let size = (self.0.len() - at - 4) as u32;
+        for i in 0..4 {
+            self.0[at + i] = (size >> (i * 8)) as u8;
+        }
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+const MACHINE: Option<u16> = Some(62);
+#[cfg(target_arch = "aarch64")]
+const MACHINE: Option<u16> = Some(183);
+#[cfg(not(any(target_arch = "x86_64", target_arch = "aarch64")))]
+const MACHINE: Option<u16> = None;
+
+/// Write the call frame info for the frames LibJIT's x86-64 prologue makes,
+/// which saves the frame pointer and then points it at the saved one
+#[cfg(target_arch = "x86_64")]
+fn frame_info(out: &mut Bytes, start: usize, end: usize) {
+    out.sized(|cie| {
+        cie.u32(0xffffffff);
+        cie.u8(1);
+        cie.str("");
+        cie.uleb(1);
+        cie.sleb(-8);
+        cie.u8(16);
+        // DW_CFA_def_cfa rsp+8, DW_CFA_offset rip at cfa-8
+        cie.u8(0x0c); cie.uleb(7); cie.uleb(8);
+        cie.u8(0x80 | 16); cie.uleb(1);
+        cie.align(8, 0);
+    });
+    out.sized(|fde| {
+        fde.u32(0);
+        fde.u64(start as u64);
+        fde.u64((end - start) as u64);
+        // after `push rbp`, DW_CFA_def_cfa_offset 16, DW_CFA_offset rbp at cfa-16
+        fde.u8(0x40 | 1);
+        fde.u8(0x0e); fde.uleb(16);
+        fde.u8(0x80 | 6); fde.uleb(2);
+        // after `mov rbp, rsp`, DW_CFA_def_cfa_register rbp
+        fde.u8(0x40 | 3);
+        fde.u8(0x0d); fde.uleb(6);
+        fde.align(8, 0);
+    });
+}
+/// The frame layout isn't known on this architecture, so debuggers are left
+/// to work it out
+#[cfg(not(target_arch = "x86_64"))]
+fn frame_info(_: &mut Bytes, _: usize, _: usize) {}
+
+/// Make the ELF object describing the compiled `func` to debuggers
+fn object(func: jit_function_t, machine: u16) -> Vec<u8> {
+    let (start, end) = code_range(func);
+    let name = format!("jit_{:x}", start);
+    let locations = FunctionData::with(func, |data| data.locations.clone());
+    let rows = rows(func, start, end);
+    let mut files = Vec::new();
+    for location in &locations {
+        if !files.contains(&location.file) {
+            files.push(location.file);
+        }
+    }
+    let names: HashMap<u32, String> = ContextData::with_func(func, |data| data.source_files.clone());
+    let mut abbrev = Bytes(Vec::new());
+    // compile unit: name, stmt_list, low_pc, high_pc
+    abbrev.uleb(1); abbrev.uleb(0x11); abbrev.u8(1);
+    for &(attr, form) in &[(0x03, 0x08), (0x10, 0x06), (0x11, 0x01), (0x12, 0x01), (0, 0)] {
+        abbrev.uleb(attr); abbrev.uleb(form);
+    }
+    // subprogram: name, low_pc, high_pc
+    abbrev.uleb(2); abbrev.uleb(0x2e); abbrev.u8(0);
+    for &(attr, form) in &[(0x03, 0x08), (0x11, 0x01), (0x12, 0x01), (0, 0)] {
+        abbrev.uleb(attr); abbrev.uleb(form);
+    }
+    abbrev.u8(0);
+    let mut info = Bytes(Vec::new());
+    info.sized(|unit| {
+        unit.u16(2);
+        unit.u32(0);
+        unit.u8(8);
+        unit.uleb(1);
+        unit.str(&name);
+        unit.u32(0);
+        unit.u64(start as u64);
+        unit.u64(end as u64);
+        unit.uleb(2);
+        unit.str(&name);
+        unit.u64(start as u64);
+        unit.u64(end as u64);
+        unit.u8(0);
+    });
+    let mut line = Bytes(Vec::new());
+    line.sized(|unit| {
+        unit.u16(2);
+        unit.sized(|header| {
+            header.u8(1);
+            header.u8(1);
+            header.u8(-5i8 as u8);
+            header.u8(14);
+            header.u8(13);
+            for &length in &[0, 1, 1, 1, 1, 0, 0, 0, 1, 0, 0, 1] {
+                header.u8(length);
+            }
+            header.u8(0);
+            for file in &files {
+                match names.get(file) {
+                    Some(name) => header.str(name),
+                    None => header.str(&format!("<file {}>", file))
+                }
+                header.uleb(0);
+                header.uleb(0);
+                header.uleb(0);
+            }
+            header.u8(0);
+        });
+        // DW_LNE_set_address
+        unit.u8(0); unit.uleb(9); unit.u8(2); unit.u64(start as u64);
+        let (mut address, mut file, mut row_line) = (start, 1, 1);
+        for &(pc, offset) in &rows {
+            let location = locations[offset];
+            let index = files.iter().position(|&other| other == location.file).unwrap() + 1;
+            if index != file {
+                unit.u8(4); unit.uleb(index as u64);
+                file = index;
+            }
+            unit.u8(3); unit.sleb(location.line as i64 - row_line as i64);
+            row_line = location.line;
+            unit.u8(5); unit.uleb(location.column as u64);
+            unit.u8(2); unit.uleb((pc - address) as u64);
+            address = pc;
+            unit.u8(1);
+        }
+        unit.u8(2); unit.uleb((end - address) as u64);
+        // DW_LNE_end_sequence
+        unit.u8(0); unit.uleb(1); unit.u8(1);
+    });
+    let mut frame = Bytes(Vec::new());
+    frame_info(&mut frame, start, end);
+    let mut strtab = Bytes(vec![0]);
+    strtab.str(&name);
+    let mut symtab = Bytes(vec![0; 24]);
+    symtab.u32(1);
+    symtab.u8(0x12);
+    symtab.u8(0);
+    symtab.u16(1);
+    symtab.u64(0);
+    symtab.u64((end - start) as u64);
+    // name, type, flags, address, contents, link, info, entry size
+    let sections: Vec<(&str, u32, u64, usize, Vec<u8>, u32, u32, u64)> = vec![
+        (".text", 8, 6, start, Vec::new(), 0, 0, 0),
+        (".debug_abbrev", 1, 0, 0, abbrev.0, 0, 0, 0),
+        (".debug_info", 1, 0, 0, info.0, 0, 0, 0),
+        (".debug_line", 1, 0, 0, line.0, 0, 0, 0),
+        (".debug_frame", 1, 0, 0, frame.0, 0, 0, 0),
+        (".symtab", 2, 0, 0, symtab.0, 7, 1, 24),
+        (".strtab", 3, 0, 0, strtab.0, 0, 0, 0)
+    ];
+    let mut shstrtab = Bytes(vec![0]);
+    let mut section_names = Vec::new();
+    for section in &sections {
+        section_names.push(shstrtab.0.len() as u32);
+        shstrtab.str(section.0);
+    }
+    let shstrtab_name = shstrtab.0.len() as u32;
+    shstrtab.str(".shstrtab");
+    let mut out = Bytes(Vec::new());
+    out.0.extend([0x7f, b'E', b'L', b'F', 2, 1, 1].iter().cloned());
+    out.align(16, 0);
+    out.u16(1);
+    out.u16(machine);
+    out.u32(1);
+    out.u64(0);
+    out.u64(0);
+    let shoff_at = out.0.len();
+    out.u64(0);
+    out.u32(0);
+    out.u16(64);
+    out.u16(0);
+    out.u16(0);
+    out.u16(64);
+    out.u16(sections.len() as u16 + 2);
+    out.u16(sections.len() as u16 + 1);
+    let mut offsets = Vec::new();
+    for section in &sections {
+        out.align(8, 0);
+        offsets.push(out.0.len());
+        out.0.extend(section.4.iter().cloned());
+    }
+    let shstrtab_at = out.0.len();
+    out.0.extend(shstrtab.0.iter().cloned());
+    out.align(8, 0);
+    let shoff = out.0.len() as u64;
+    for i in 0..8 {
+        out.0[shoff_at + i] = (shoff >> (i * 8)) as u8;
+    }
+    out.0.extend([0; 64].iter().cloned());
+    let header = |out: &mut Bytes, name: u32, kind: u32, flags: u64, addr: usize, offset: usize,
+                      size: usize, link: u32, info: u32, entsize: u64| {
+        out.u32(name);
+        out.u32(kind);
+        out.u64(flags);
+        out.u64(addr as u64);
+        out.u64(offset as u64);
+        out.u64(size as u64);
+        out.u32(link);
+        out.u32(info);
+        out.u64(if kind == 2 || kind == 8 { 8 } else { 1 });
+        out.u64(entsize);
+    };
+    for (i, section) in sections.iter().enumerate() {
+        let size = if section.1 == 8 { end - start } else { section.4.len() };
+        header(&mut out, section_names[i], section.1, section.2, section.3, offsets[i], size, section.5, section.6, section.7);
+    }
+    header(&mut out, shstrtab_name, 3, 0, 0, shstrtab_at, shstrtab.0.len(), 0, 0, 0);
+    out.0
+}
+
+/// Register the freshly compiled `func` with debuggers if its context asks
+/// for debug info, replacing what was registered for it before
+pub fn compiled(func: jit_function_t) {
+    if !ContextData::with_func(func, |data| data.options.debug_info) {
+        return
+    }
+    let machine = match MACHINE {
+        Some(machine) if cfg!(all(target_pointer_width = "64", target_endian = "little")) => machine,
+        _ => return
+    };
+    let entry = DebugEntry::register(object(func, machine));
+    FunctionData::with(func, |data| data.debug_entry = Some(entry));
+}
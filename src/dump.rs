@@ -0,0 +1,139 @@
+use raw::*;
+use function::Func;
+use insn::Blocks;
+use location;
+use types::TypeKind;
+use util::from_ptr_opt;
+use value::Val;
+use std::collections::HashMap;
+use std::fmt::Write;
+
+/// Gives values and labels numbers in the order they are first seen, so the
+/// listing doesn't depend on where anything was allocated
+struct Numbering {
+    values: HashMap<jit_value_t, String>,
+    labels: HashMap<jit_label_t, usize>,
+    temps: usize
+}
+impl Numbering {
+    fn label(&mut self, label: jit_label_t) -> String {
+        let next = self.labels.len();
+        format!("L{}", *self.labels.entry(label).or_insert(next))
+    }
+    fn value(&mut self, value: &Val) -> String {
+        let ptr: jit_value_t = value.into();
+        if let Some(name) = self.values.get(&ptr) {
+            return name.clone()
+        }
+        let name = if value.is_constant() {
+            constant(value)
+        } else {
+            self.temps += 1;
+            format!("v{}", self.temps - 1)
+        };
+        self.values.insert(ptr, name.clone());
+        name
+    }
+}
+
+/// Write a constant by its value, or just by its type if it is a pointer,
+/// since addresses change from run to run
+pub fn constant(value: &Val) -> String {
+    let ty = value.get_type();
+    let kind = ty.get_kind();
+    unsafe {
+        if ty.is_pointer() {
+            format!("const {:?}", ty)
+        } else if kind == TypeKind::Float32 {
+            format!("{:?}f32", jit_value_get_float32_constant(value.into()))
+        } else if kind == TypeKind::Float64 || kind == TypeKind::NFloat {
+            format!("{:?}f64", jit_value_get_float64_constant(value.into()))
+        } else if kind == TypeKind::Long || kind == TypeKind::ULong {
+            format!("{}{:?}", jit_value_get_long_constant(value.into()), ty)
+        } else if ty.is_int() || kind == TypeKind::SysBool || kind == TypeKind::SysChar {
+            format!("{}{:?}", jit_value_get_nint_constant(value.into()), ty)
+        } else {
+            format!("const {:?}", ty)
+        }
+    }
+}
+
+/// Make a listing of the IR of the function that only depends on the
+/// instructions that were made, not on any pointers
+pub fn stable(func: &Func) -> String {
+    let mut nums = Numbering {
+        values: HashMap::new(),
+        labels: HashMap::new(),
+        temps: 0
+    };
+    let mut text = String::new();
+    let sig = func.get_signature();
+    let _ = write!(text, "function {:?}\n", sig);
+    for index in 0..sig.params().count() {
+        let param: Option<&Val> = from_ptr_opt(unsafe {
+            jit_value_get_param(func.into(), index as u32)
+        });
+        if let Some(param) = param {
+            nums.values.insert(param.into(), format!("p{}", index));
+        }
+    }
+    let func_ptr: jit_function_t = func.into();
+    for (index, block) in Blocks::new(func_ptr).enumerate() {
+        let _ = write!(text, "block {}", index);
+        if let Some(label) = block.get_label() {
+            let _ = write!(text, " ({})", nums.label(label as jit_label_t));
+        }
+        text.push_str(":\n");
+        for insn in block.iter() {
+            text.push_str("    ");
+            let dest = insn.get_dest();
+            if let Some(dest) = dest {
+                if !insn.dest_is_value() {
+                    let _ = write!(text, "{} = ", nums.value(dest));
+                }
+            }
+            text.push_str(insn.get_name());
+            let mut args = Vec::with_capacity(4);
+            if let (Some(dest), true) = (dest, insn.dest_is_value()) {
+                args.push(nums.value(dest));
+            }
+            if let Some(value) = insn.get_value1() {
+                args.push(nums.value(value));
+            }
+            if let Some(value) = insn.get_value2() {
+                args.push(nums.value(value));
+            }
+            if let Some(label) = insn.get_label() {
+                args.push(nums.label(label as jit_label_t));
+            }
+            if let Some(sig) = insn.get_signature() {
+                args.push(format!("{:?}", sig));
+            }
+            for (i, arg) in args.iter().enumerate() {
+                text.push_str(if i == 0 { " " } else { ", " });
+                text.push_str(arg);
+            }
+            if insn.get_name() == "mark_offset" {
+                let offset = insn.get_value1().map(|offset| unsafe {
+                    jit_value_get_nint_constant(offset.into()) as usize
+                });
+                if let Some(loc) = offset.and_then(|offset| location::lookup(func_ptr, offset)) {
+                    let _ = write!(text, " ; at {}:{}:{}", loc.file, loc.line, loc.column);
+                }
+            }
+            text.push('\n');
+        }
+    }
+    text
+}
+
+/// Hash a listing with 64-bit FNV-1a, which unlike the standard hasher is
+/// guaranteed to stay the same between releases
+pub fn fingerprint(text: &str) -> u64 {
+    let mut hash = 0xcbf29ce484222325u64;
+    for &byte in text.as_bytes() {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
@@ -0,0 +1,195 @@
+//! Making instructions in the function currently being built, without
+//! passing it to every helper
+//!
+//! `enter` makes a function the current one on this thread until the scope
+//! it gives is dropped, and the functions here make instructions in it like
+//! the `insn_` methods of the same names. Scopes can be nested, for building
+//! a nested function in the middle of another, with the innermost one being
+//! current. This is only sugar over the methods on `UncompiledFunction`,
+//! which `with` gives access to for anything not covered here.
+//!
+//! ```rust
+//! use jit::*;
+//! fn square_plus_one<'a>(x: &'a Val) -> &'a Val {
+//!     emit::add(emit::mul(x, x), emit::of(1i32))
+//! }
+//! let mut ctx = Context::<()>::new();
+//! let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+//! {
+//!     let _scope = emit::enter(&func);
+//!     emit::ret(square_plus_one(&func[0]));
+//! }
+//! func.compile().with(|f: extern fn(i32) -> i32| assert_eq!(f(3), 10));
+//! ```
+use raw::*;
+use compile::Compile;
+use function::UncompiledFunction;
+use lvalue::LValue;
+use types::Ty;
+use util::from_ptr;
+use value::Val;
+use std::cell::RefCell;
+use std::marker::PhantomData;
+
+thread_local!(static CURRENT: RefCell<Vec<jit_function_t>> = RefCell::new(Vec::new()));
+
+/// The function made current by `enter`, which stops being current when this
+/// is dropped
+pub struct EmitScope<'a> {
+    func: jit_function_t,
+    marker: PhantomData<&'a ()>
+}
+impl<'a> Drop for EmitScope<'a> {
+    fn drop(&mut self) {
+        CURRENT.with(|current| {
+            let mut current = current.borrow_mut();
+            if current.last() != Some(&self.func) {
+                panic!("Emit scopes must be dropped in the reverse order they were entered")
+            }
+            current.pop();
+        })
+    }
+}
+
+/// Make `func` the function instructions are made in on this thread until
+/// the scope given is dropped
+pub fn enter<'a, 'b>(func: &'b UncompiledFunction<'a>) -> EmitScope<'b> {
+    let func: jit_function_t = func.into();
+    CURRENT.with(|current| current.borrow_mut().push(func));
+    EmitScope {
+        func: func,
+        marker: PhantomData
+    }
+}
+/// Check if a function has been made current with `enter` on this thread
+pub fn is_active() -> bool {
+    CURRENT.with(|current| !current.borrow().is_empty())
+}
+/// Get the current function, checking the values given belong to it
+fn current<'a>(name: &str, values: &[&Val]) -> UncompiledFunction<'a> {
+    let func = match CURRENT.with(|current| current.borrow().last().cloned()) {
+        Some(func) => func,
+        None => panic!("emit::{} was used without a current function - use emit::enter first", name)
+    };
+    for value in values {
+        if unsafe { jit_value_get_function((*value).into()) } != func {
+            panic!("emit::{} was given a value from a function other than the current one", name)
+        }
+    }
+    UncompiledFunction::from(func)
+}
+/// Run `cb` with the current function, to make instructions there isn't a
+/// function for here
+pub fn with<'a, R, F>(cb: F) -> R where F: FnOnce(&UncompiledFunction<'a>) -> R {
+    cb(&current("with", &[]))
+}
+
+/// Make a constant in the current function, like `insn_of`
+pub fn of<'a, T>(value: T) -> &'a Val where T: Compile<'a> {
+    current("of", &[]).insn_of(value)
+}
+/// Get a parameter of the current function
+pub fn param<'a>(index: usize) -> &'a Val {
+    let func: UncompiledFunction<'a> = current("param", &[]);
+    let param: jit_value_t = (&func[index]).into();
+    from_ptr(param)
+}
+/// Make a new local variable of the type given in the current function,
+/// like `new_lvalue`
+pub fn local<'a>(ty: &Ty) -> LValue<'a> {
+    current("local", &[]).new_lvalue(ty)
+}
+/// Read the value stored in `source` now, like `insn_read`
+pub fn read<'a>(source: &LValue<'a>) -> &'a Val {
+    current("read", &[]).insn_read(source).get()
+}
+
+macro_rules! emit_binary {
+    ($($(#[$attr:meta])* fn $name:ident => $insn:ident;)*) => ($(
+        $(#[$attr])*
+        pub fn $name<'a>(a: &'a Val, b: &'a Val) -> &'a Val {
+            current(stringify!($name), &[a, b]).$insn(a, b)
+        }
+    )*)
+}
+macro_rules! emit_unary {
+    ($($(#[$attr:meta])* fn $name:ident => $insn:ident;)*) => ($(
+        $(#[$attr])*
+        pub fn $name<'a>(value: &'a Val) -> &'a Val {
+            current(stringify!($name), &[value]).$insn(value)
+        }
+    )*)
+}
+emit_binary! {
+    /// Add the values, like `insn_add`
+    fn add => insn_add;
+    /// Subtract the second value from the first, like `insn_sub`
+    fn sub => insn_sub;
+    /// Multiply the values, like `insn_mul`
+    fn mul => insn_mul;
+    /// Divide the first value by the second, like `insn_div`
+    fn div => insn_div;
+    /// Get the remainder of dividing the first value by the second, like
+    /// `insn_rem`
+    fn rem => insn_rem;
+    /// Bitwise and the values, like `insn_and`
+    fn and => insn_and;
+    /// Bitwise or the values, like `insn_or`
+    fn or => insn_or;
+    /// Bitwise xor the values, like `insn_xor`
+    fn xor => insn_xor;
+    /// Shift the first value left by the second, like `insn_shl`
+    fn shl => insn_shl;
+    /// Shift the first value right by the second, like `insn_shr`
+    fn shr => insn_shr;
+    /// Check if the values are equal, like `insn_eq`
+    fn eq => insn_eq;
+    /// Check if the values are not equal, like `insn_neq`
+    fn neq => insn_neq;
+    /// Check if the first value is less than the second, like `insn_lt`
+    fn lt => insn_lt;
+    /// Check if the first value is at most the second, like `insn_leq`
+    fn leq => insn_leq;
+    /// Check if the first value is greater than the second, like `insn_gt`
+    fn gt => insn_gt;
+    /// Check if the first value is at least the second, like `insn_geq`
+    fn geq => insn_geq;
+}
+emit_unary! {
+    /// Negate the value, like `insn_neg`
+    fn neg => insn_neg;
+    /// Bitwise not the value, like `insn_not`
+    fn not => insn_not;
+}
+
+/// Convert the value to the type given, like `insn_convert`
+pub fn convert<'a>(value: &'a Val, ty: &Ty, overflow_check: bool) -> &'a Val {
+    current("convert", &[value]).insn_convert(value, ty, overflow_check)
+}
+/// Store `value` in `dest`, like `insn_store`
+pub fn store<'a>(dest: &LValue<'a>, value: &'a Val) {
+    let storage = match dest.get_memory() {
+        Some((ptr, _)) => ptr,
+        None => dest.get_local().unwrap()
+    };
+    current("store", &[storage, value]).insn_store(dest, value)
+}
+/// Load a value of the type given from `offset` bytes past the pointer
+/// `base`, like `insn_load_relative`
+pub fn load_relative<'a>(base: &'a Val, offset: usize, ty: &Ty) -> &'a Val {
+    current("load_relative", &[base]).insn_load_relative(base, offset, ty)
+}
+/// Store `value` at `offset` bytes past the pointer `base`, like
+/// `insn_store_relative`
+pub fn store_relative<'a>(base: &'a Val, offset: usize, value: &'a Val) {
+    current("store_relative", &[base, value]).insn_store_relative(base, offset, value)
+}
+/// Return `value` from the current function, like `insn_return`
+pub fn ret<'a>(value: &'a Val) {
+    current("ret", &[value]).insn_return(value)
+}
+/// Return from the current function with no value, like
+/// `insn_default_return`
+pub fn ret_default() {
+    current("ret_default", &[]).insn_default_return()
+}
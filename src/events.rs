@@ -0,0 +1,73 @@
+//! Reports significant events through the `log` crate when the `logging`
+//! feature is enabled, and does nothing otherwise
+use raw::*;
+#[cfg(feature = "logging")]
+use std::fmt::Display;
+#[cfg(feature = "logging")]
+use insn::Blocks;
+#[cfg(feature = "logging")]
+use time::precise_time_ns;
+
+/// Get a timestamp to measure an event's duration from
+#[cfg(feature = "logging")]
+pub fn start() -> u64 {
+    precise_time_ns()
+}
+#[cfg(feature = "logging")]
+fn size(func: jit_function_t) -> usize {
+    Blocks::new(func).map(|block| block.iter().count()).fold(0, |total, count| total + count)
+}
+/// Report that `func` was created
+#[cfg(feature = "logging")]
+pub fn created(func: jit_function_t) {
+    debug!(target: "jit", "created function {:?}", func);
+}
+/// Report that `func` was compiled, starting at the time given
+#[cfg(feature = "logging")]
+pub fn compiled(func: jit_function_t, recompiled: bool, started: u64, ok: bool) {
+    let size = size(func);
+    let micros = (precise_time_ns() - started) / 1000;
+    let action = if recompiled { "recompiled" } else { "compiled" };
+    if ok {
+        info!(target: "jit", "{} function {:?}: {} instructions in {}us", action, func, size, micros);
+    } else {
+        error!(target: "jit", "failed to compile function {:?} after {}us", func, micros);
+    }
+}
+/// Report that `func` was abandoned before being compiled
+#[cfg(feature = "logging")]
+pub fn abandoned(func: jit_function_t) {
+    debug!(target: "jit", "abandoned function {:?}", func);
+}
+/// Report that an exception was raised in `func`, whether or not the
+/// context has any exception filters
+#[cfg(feature = "logging")]
+pub fn exception<T>(func: jit_function_t, exception: T) where T: Display {
+    warn!(target: "jit", "exception raised in function {:?}: {}", func, exception);
+}
+/// Report that something suspicious was made in `func`
+#[cfg(feature = "logging")]
+pub fn warning(func: jit_function_t, message: &str) {
+    warn!(target: "jit", "in function {:?}: {}", func, message);
+}
+
+#[cfg(not(feature = "logging"))]
+#[inline(always)]
+pub fn start() -> u64 {
+    0
+}
+#[cfg(not(feature = "logging"))]
+#[inline(always)]
+pub fn created(_: jit_function_t) {}
+#[cfg(not(feature = "logging"))]
+#[inline(always)]
+pub fn compiled(_: jit_function_t, _: bool, _: u64, _: bool) {}
+#[cfg(not(feature = "logging"))]
+#[inline(always)]
+pub fn abandoned(_: jit_function_t) {}
+#[cfg(not(feature = "logging"))]
+#[inline(always)]
+pub fn exception<T>(_: jit_function_t, _: T) {}
+#[cfg(not(feature = "logging"))]
+#[inline(always)]
+pub fn warning(_: jit_function_t, _: &str) {}
@@ -0,0 +1,401 @@
+use raw::*;
+use data::ContextData;
+use events;
+use function::Func;
+use types::{same_layout, Ty, Type, TypeKind};
+use util::from_ptr;
+use libc::{c_int, c_void};
+use std::cell::{Cell, RefCell};
+use std::error::Error;
+use std::rc::Rc;
+use std::{fmt, mem, ptr, slice};
+
+#[repr(i32)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+/// An exception raised by LibJIT itself rather than thrown by generated code
+pub enum BuiltinException {
+    /// An arithmetic operation overflowed
+    Overflow = 0,
+    /// An arithmetic error occurred
+    Arithmetic = -1,
+    /// A division by zero was attempted
+    DivisionByZero = -2,
+    /// A function failed to compile
+    CompileError = -3,
+    /// There wasn't enough memory
+    OutOfMemory = -4,
+    /// A null pointer was dereferenced
+    NullReference = -5,
+    /// A null function pointer was called
+    NullFunction = -6,
+    /// A nested function was called from somewhere other than its parent
+    CalledNested = -7,
+    /// An array index was out of bounds
+    OutOfBounds = -8,
+    /// A branch was made to a label that was never placed
+    UndefinedLabel = -9
+}
+impl BuiltinException {
+    /// Get the exception with the code given, if there is one
+    pub fn from_code(code: c_int) -> Option<BuiltinException> {
+        Some(match code {
+            0 => BuiltinException::Overflow,
+            -1 => BuiltinException::Arithmetic,
+            -2 => BuiltinException::DivisionByZero,
+            -3 => BuiltinException::CompileError,
+            -4 => BuiltinException::OutOfMemory,
+            -5 => BuiltinException::NullReference,
+            -6 => BuiltinException::NullFunction,
+            -7 => BuiltinException::CalledNested,
+            -8 => BuiltinException::OutOfBounds,
+            -9 => BuiltinException::UndefinedLabel,
+            _ => return None
+        })
+    }
+}
+impl fmt::Display for BuiltinException {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "{}", self.description())
+    }
+}
+impl Error for BuiltinException {
+    fn description(&self) -> &'static str {
+        match *self {
+            BuiltinException::Overflow => "Overflow during checked arithmetic operation",
+            BuiltinException::Arithmetic => "Arithmetic exception (dividing the minimum integer by -1)",
+            BuiltinException::DivisionByZero => "Division by zero",
+            BuiltinException::CompileError => "Error during function compilation",
+            BuiltinException::OutOfMemory => "Out of memory",
+            BuiltinException::NullReference => "Null pointer dereferenced",
+            BuiltinException::NullFunction => "Null function pointer called",
+            BuiltinException::CalledNested => "Nested function called from non-nested context",
+            BuiltinException::OutOfBounds => "Array index out of bounds",
+            BuiltinException::UndefinedLabel => "Undefined label"
+        }
+    }
+}
+#[derive(Clone, Debug, PartialEq, Eq)]
+/// A value thrown with `insn_throw_typed`, as seen from Rust
+pub enum Payload {
+    /// An integer, like an error code, extended to 64 bits
+    Code(i64),
+    /// A pointer
+    Pointer(*mut c_void),
+    /// A struct, as its type and a copy of its bytes
+    Struct(Type, Vec<u8>)
+}
+impl Payload {
+    /// Read a struct payload as a `T`, if it is the size of one
+    ///
+    /// `T` must be laid out like the struct's type.
+    pub unsafe fn read<T>(&self) -> Option<T> {
+        match *self {
+            Payload::Struct(_, ref bytes) if bytes.len() == mem::size_of::<T>() => {
+                let mut value: T = mem::uninitialized();
+                ptr::copy_nonoverlapping(bytes.as_ptr(), &mut value as *mut T as *mut u8, bytes.len());
+                Some(value)
+            },
+            _ => None
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+/// An exception that escaped a function called through `closure_result`
+pub enum JitException {
+    /// LibJIT raised a builtin exception that no filter turned into an object
+    Builtin(BuiltinException),
+    /// This object was thrown
+    Thrown(*mut c_void),
+    /// This value was thrown with `insn_throw_typed`
+    Typed(Payload),
+    /// A function was called after `Func::invalidate` retired its code
+    StaleCode
+}
+impl fmt::Display for JitException {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            JitException::Builtin(kind) => write!(fmt, "{}", kind),
+            JitException::Thrown(object) => write!(fmt, "{} {:?}", self.description(), object),
+            JitException::Typed(ref payload) => write!(fmt, "{} {:?}", self.description(), payload),
+            JitException::StaleCode => write!(fmt, "{}", self.description())
+        }
+    }
+}
+impl Error for JitException {
+    fn description(&self) -> &str {
+        match *self {
+            JitException::Builtin(ref kind) => kind.description(),
+            JitException::Thrown(_) => "Object thrown from generated code",
+            JitException::Typed(_) => "Value thrown from generated code",
+            JitException::StaleCode => "Invalidated function called"
+        }
+    }
+}
+
+/// Stands for the payload being thrown on the thread, so its address is the
+/// object thrown for a typed payload
+static PAYLOAD_OBJECT: u8 = 0;
+thread_local!(static PAYLOAD: RefCell<Option<(Type, Vec<u64>)>> = RefCell::new(None));
+
+fn payload_object() -> usize {
+    &PAYLOAD_OBJECT as *const u8 as usize
+}
+/// Copy the value of type `ty` at `value` to be the payload being thrown on
+/// this thread, giving the object to throw for it
+pub extern fn stash_payload(ty: usize, value: usize) -> usize {
+    let ty: &Ty = from_ptr(ty as jit_type_t);
+    let size = ty.get_size();
+    // keep it in words so it is aligned for any type
+    let mut words = vec![0u64; (size + 7) / 8];
+    unsafe {
+        ptr::copy_nonoverlapping(value as *const u8, words.as_mut_ptr() as *mut u8, size);
+    }
+    PAYLOAD.with(|payload| *payload.borrow_mut() = Some((ty.to_owned(), words)));
+    payload_object()
+}
+/// Get the address of the payload being thrown on this thread if `object`
+/// was thrown for it and it is laid out like `ty`, or zero if not
+pub extern fn payload_address(object: usize, ty: usize) -> usize {
+    if object != payload_object() {
+        return 0
+    }
+    let ty: &Ty = from_ptr(ty as jit_type_t);
+    PAYLOAD.with(|payload| match *payload.borrow() {
+        Some((ref thrown, ref words)) if same_layout(thrown, ty) => words.as_ptr() as usize,
+        _ => 0
+    })
+}
+/// Take the payload being thrown on this thread, if `object` was thrown for
+/// it
+fn take_payload(object: *mut c_void) -> Option<Payload> {
+    if object as usize != payload_object() {
+        return None
+    }
+    PAYLOAD.with(|payload| payload.borrow_mut().take()).map(|(ty, words)| {
+        let size = ty.get_size();
+        let bytes = unsafe { slice::from_raw_parts(words.as_ptr() as *const u8, size) };
+        if ty.is_pointer() {
+            Payload::Pointer(words[0] as usize as *mut c_void)
+        } else if ty.is_int() {
+            let normal: &Ty = unsafe { from_ptr(jit_type_normalize((&*ty).into())) };
+            let kind = normal.get_kind();
+            let signed = kind == TypeKind::SByte || kind == TypeKind::Short || kind == TypeKind::Int ||
+                kind == TypeKind::NInt || kind == TypeKind::Long;
+            // the low bytes of the word are the value on little-endian targets
+            let bits = size * 8;
+            let word = if cfg!(target_endian = "big") { words[0] >> (64 - bits) } else { words[0] };
+            Payload::Code(if bits == 64 {
+                word as i64
+            } else if signed {
+                ((word << (64 - bits)) as i64) >> (64 - bits)
+            } else {
+                (word & ((1u64 << bits) - 1)) as i64
+            })
+        } else {
+            Payload::Struct(ty, bytes.to_vec())
+        }
+    })
+}
+
+/// What an exception filter decided to do with an exception
+pub enum FilterAction {
+    /// Throw this object, without running any more filters
+    Handle(*mut c_void),
+    /// Replace the object to be thrown with this one, then run the next filter
+    Transform(*mut c_void),
+    /// Leave the object to be thrown as it is and run the next filter
+    Propagate
+}
+/// A filter that sees each builtin exception raised in a context's functions
+///
+/// It is given the function the exception was raised in, the exception, and
+/// the object that will be thrown so far (which is null until a filter
+/// transforms it).
+pub type ExceptionFilter = Rc<Fn(&Func, BuiltinException, *mut c_void) -> FilterAction>;
+
+thread_local!(static FILTERED: RefCell<Vec<jit_context_t>> = RefCell::new(Vec::new()));
+thread_local!(static PREVIOUS: RefCell<jit_exception_func> = RefCell::new(None));
+
+extern fn handle_builtin(code: c_int) -> *mut c_void {
+    let kind = match BuiltinException::from_code(code) {
+        Some(kind) => kind,
+        None => return fallback(code)
+    };
+    let contexts = FILTERED.with(|filtered| filtered.borrow().clone());
+    unsafe {
+        let mut value = ptr::null_mut();
+        if let Some((ctx, func)) = raised_in(&contexts) {
+            // calls made through `apply` have already reported it
+            if APPLYING.with(|applying| applying.get().is_null()) {
+                events::exception(func, kind);
+            }
+            let func:&Func = from_ptr(func);
+            // the filters are taken out first, since they may use the context
+            let filters = ContextData::with(ctx, |data| data.filters.clone());
+            for filter in filters.iter().rev() {
+                match filter(func, kind, value) {
+                    FilterAction::Handle(object) => return object,
+                    FilterAction::Transform(object) => value = object,
+                    FilterAction::Propagate => ()
+                }
+            }
+            if !value.is_null() {
+                return value
+            }
+        }
+    }
+    fallback(code)
+}
+/// Find the innermost function on the stack that belongs to one of
+/// `contexts`, and its context
+fn raised_in(contexts: &[jit_context_t]) -> Option<(jit_context_t, jit_function_t)> {
+    unsafe {
+        let trace = jit_exception_get_stack_trace();
+        let size = if trace.is_null() { 0 } else { jit_stack_trace_get_size(trace) };
+        let mut found = None;
+        'search: for posn in 0..size {
+            for &ctx in contexts {
+                let func = jit_stack_trace_get_function(ctx, trace, posn);
+                if !func.is_null() {
+                    found = Some((ctx, func));
+                    break 'search
+                }
+            }
+        }
+        if !trace.is_null() {
+            jit_stack_trace_free(trace);
+        }
+        found
+    }
+}
+fn fallback(code: c_int) -> *mut c_void {
+    PREVIOUS.with(|previous| {
+        match *previous.borrow() {
+            Some(handler) => handler(code),
+            None => ptr::null_mut()
+        }
+    })
+}
+
+/// The objects thrown for builtin exceptions while a call is being caught,
+/// indexed by the negated code
+static BUILTINS: [BuiltinException; 10] = [
+    BuiltinException::Overflow,
+    BuiltinException::Arithmetic,
+    BuiltinException::DivisionByZero,
+    BuiltinException::CompileError,
+    BuiltinException::OutOfMemory,
+    BuiltinException::NullReference,
+    BuiltinException::NullFunction,
+    BuiltinException::CalledNested,
+    BuiltinException::OutOfBounds,
+    BuiltinException::UndefinedLabel
+];
+thread_local!(static OUTER: Cell<jit_exception_func> = Cell::new(None));
+thread_local!(static APPLYING: Cell<jit_function_t> = Cell::new(ptr::null_mut()));
+
+extern fn catch_builtin(code: c_int) -> *mut c_void {
+    if let Some(kind) = BuiltinException::from_code(code) {
+        if cfg!(feature = "logging") {
+            let applying = APPLYING.with(|applying| applying.get());
+            let ctx = unsafe { jit_function_get_context(applying) };
+            let func = raised_in(&[ctx]).map_or(applying, |(_, func)| func);
+            events::exception(func, kind);
+        }
+    }
+    let object = OUTER.with(|outer| match outer.get() {
+        Some(handler) => handler(code),
+        None => ptr::null_mut()
+    });
+    if !object.is_null() {
+        object
+    } else if code <= 0 && code > -(BUILTINS.len() as c_int) {
+        &BUILTINS[-code as usize] as *const BuiltinException as *mut c_void
+    } else {
+        object
+    }
+}
+/// The object thrown when a function whose code was invalidated is called,
+/// which is the wrapper's own rather than a builtin code LibJIT doesn't know
+static STALE_OBJECT: u8 = 0;
+
+/// Throw the exception for calling a function whose code was invalidated,
+/// which its entry calls once it is marked stale
+pub extern fn stale_code() {
+    unsafe {
+        jit_exception_throw(&STALE_OBJECT as *const u8 as *mut c_void);
+    }
+}
+
+/// Raise the exception for an index that is out of bounds, which array views
+/// call when they are checked
+pub extern fn out_of_bounds() {
+    unsafe {
+        jit_exception_builtin(BuiltinException::OutOfBounds as c_int);
+    }
+}
+
+/// Call `func` with `jit_function_apply`, turning builtin exceptions that
+/// aren't handled by the current handler into objects so they can be caught
+/// too, and give the exception that escaped it if there was one
+pub fn apply(func: jit_function_t, args: *mut *mut c_void, ret: *mut c_void) -> Result<(), JitException> {
+    unsafe {
+        let outer = jit_exception_set_handler(Some(catch_builtin));
+        let previous = OUTER.with(|slot| {
+            let previous = slot.get();
+            slot.set(outer);
+            previous
+        });
+        let applying = APPLYING.with(|slot| {
+            let applying = slot.get();
+            slot.set(func);
+            applying
+        });
+        let finished = jit_function_apply(func, args, ret) != 0;
+        APPLYING.with(|slot| slot.set(applying));
+        OUTER.with(|slot| slot.set(previous));
+        jit_exception_set_handler(outer);
+        if finished {
+            return Ok(())
+        }
+        let object = jit_exception_get_last_and_clear();
+        let first = &BUILTINS[0] as *const BuiltinException as usize;
+        let last = &BUILTINS[BUILTINS.len() - 1] as *const BuiltinException as usize;
+        let address = object as usize;
+        if address >= first && address <= last {
+            return Err(JitException::Builtin(*(object as *const BuiltinException)))
+        }
+        // builtin exceptions were reported as they were raised, but thrown
+        // objects only show up here
+        let error = match take_payload(object) {
+            Some(payload) => JitException::Typed(payload),
+            None if object == &STALE_OBJECT as *const u8 as *mut c_void => JitException::StaleCode,
+            None => JitException::Thrown(object)
+        };
+        events::exception(func, &error);
+        Err(error)
+    }
+}
+
+/// Start running the filters of `ctx` on builtin exceptions in this thread
+pub fn register(ctx: jit_context_t) {
+    unsafe {
+        let current = jit_exception_get_handler();
+        let ours:extern fn(c_int) -> *mut c_void = handle_builtin;
+        if current.map(|handler| handler as usize) != Some(ours as usize) {
+            let previous = jit_exception_set_handler(Some(ours));
+            PREVIOUS.with(|slot| *slot.borrow_mut() = previous);
+        }
+    }
+    FILTERED.with(|filtered| {
+        let mut filtered = filtered.borrow_mut();
+        if !filtered.contains(&ctx) {
+            filtered.push(ctx);
+        }
+    })
+}
+/// Stop running the filters of `ctx`
+pub fn unregister(ctx: jit_context_t) {
+    FILTERED.with(|filtered| filtered.borrow_mut().retain(|&other| other != ctx))
+}
@@ -0,0 +1,132 @@
+use types::Ty;
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt;
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+/// The names and signatures of the natives registered on a context, which
+/// can be saved with precompiled code and checked against the host that
+/// loads it
+///
+/// ```rust
+/// use jit::*;
+/// extern fn twice(x: i32) -> i32 {
+///     x * 2
+/// }
+/// let mut ctx = Context::<()>::new();
+/// ctx.register_extern("twice", twice as usize, &get::<fn(i32) -> i32>());
+/// let saved = ctx.extern_snapshot().to_text();
+/// let mut host = Context::<()>::new();
+/// assert!(host.check_externs(&ExternSnapshot::parse(&saved)).is_err());
+/// host.register_extern("twice", twice as usize, &get::<fn(i32) -> i32>());
+/// assert!(host.check_externs(&ExternSnapshot::parse(&saved)).is_ok());
+/// ```
+pub struct ExternSnapshot {
+    externs: BTreeMap<String, String>
+}
+impl ExternSnapshot {
+    /// Make a snapshot with no natives in it
+    pub fn new() -> ExternSnapshot {
+        ExternSnapshot::default()
+    }
+    /// Add the native called `name` with the signature given
+    pub fn insert(&mut self, name: &str, signature: &Ty) {
+        self.externs.insert(name.to_owned(), format!("{:?}", signature));
+    }
+    /// Get the signature of the native called `name`, as it is written out
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.externs.get(name).map(|signature| &**signature)
+    }
+    /// Get how many natives are in this
+    pub fn len(&self) -> usize {
+        self.externs.len()
+    }
+    /// Check if there are no natives in this
+    pub fn is_empty(&self) -> bool {
+        self.externs.is_empty()
+    }
+    /// Write this out as text, with a line for each native of its name and
+    /// signature separated by a tab, sorted by name
+    pub fn to_text(&self) -> String {
+        let mut text = String::new();
+        for (name, signature) in &self.externs {
+            text.push_str(name);
+            text.push('\t');
+            text.push_str(signature);
+            text.push('\n');
+        }
+        text
+    }
+    /// Read a snapshot written out by `to_text`, skipping lines that aren't
+    /// in that form
+    pub fn parse(text: &str) -> ExternSnapshot {
+        let mut externs = BTreeMap::new();
+        for line in text.lines() {
+            let mut parts = line.splitn(2, '\t');
+            if let (Some(name), Some(signature)) = (parts.next(), parts.next()) {
+                externs.insert(name.to_owned(), signature.to_owned());
+            }
+        }
+        ExternSnapshot {
+            externs: externs
+        }
+    }
+    /// Check that `host` offers every native in this with the same signature
+    pub fn verify(&self, host: &ExternSnapshot) -> Result<(), ExternMismatch> {
+        let mut mismatch = ExternMismatch {
+            missing: Vec::new(),
+            changed: Vec::new()
+        };
+        for (name, signature) in &self.externs {
+            match host.externs.get(name) {
+                None => mismatch.missing.push((name.clone(), signature.clone())),
+                Some(offered) if offered != signature =>
+                    mismatch.changed.push((name.clone(), signature.clone(), offered.clone())),
+                Some(_) => ()
+            }
+        }
+        if mismatch.missing.is_empty() && mismatch.changed.is_empty() {
+            Ok(())
+        } else {
+            Err(mismatch)
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+/// The natives a snapshot needs that the host doesn't offer as they were
+///
+/// This displays as a diff, with a `-` line for each native that is
+/// missing, and a `-` line with the signature needed followed by a `+` line
+/// with the one offered for each native whose signature changed.
+pub struct ExternMismatch {
+    missing: Vec<(String, String)>,
+    changed: Vec<(String, String, String)>
+}
+impl ExternMismatch {
+    /// Get the names of the natives the host doesn't offer at all
+    pub fn get_missing(&self) -> Vec<&str> {
+        self.missing.iter().map(|&(ref name, _)| &**name).collect()
+    }
+    /// Get the names of the natives the host offers with another signature
+    pub fn get_changed(&self) -> Vec<&str> {
+        self.changed.iter().map(|&(ref name, _, _)| &**name).collect()
+    }
+}
+impl fmt::Display for ExternMismatch {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        for &(ref name, ref signature) in &self.missing {
+            try!(writeln!(fmt, "- {}\t{}", name, signature));
+        }
+        for &(ref name, ref needed, ref offered) in &self.changed {
+            try!(writeln!(fmt, "- {}\t{}", name, needed));
+            try!(writeln!(fmt, "+ {}\t{}", name, offered));
+        }
+        Ok(())
+    }
+}
+impl Error for ExternMismatch {
+    fn description(&self) -> &str {
+        "The host doesn't offer the natives the code was built against"
+    }
+}
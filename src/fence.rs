@@ -0,0 +1,103 @@
+//! Keeping types from being changed once functions are compiled with them
+//!
+//! Compiled code has the layout of the structs it uses built into it, so
+//! changing a struct afterwards, like renaming its fields, would leave code
+//! compiled before and after disagreeing about it. Just before a function is
+//! compiled, the struct, union and signature types it uses, and the types
+//! they are made of, are fenced by its context, which keeps a reference to
+//! each until it is destroyed. Fenced types can't be changed on the thread
+//! that compiled with them.
+use raw::*;
+use data::ContextData;
+use insn::Blocks;
+use types::Ty;
+use util::from_ptr;
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+
+thread_local!(static FENCED: RefCell<HashMap<jit_type_t, usize>> = RefCell::new(HashMap::new()));
+
+/// A reference to a type a function was compiled with, which keeps it from
+/// being changed while it is alive
+pub struct Fence {
+    ty: jit_type_t
+}
+impl Fence {
+    fn new(ty: jit_type_t) -> Fence {
+        FENCED.with(|fenced| *fenced.borrow_mut().entry(ty).or_insert(0) += 1);
+        Fence {
+            ty: unsafe { jit_type_copy(ty) }
+        }
+    }
+}
+impl Drop for Fence {
+    fn drop(&mut self) {
+        FENCED.with(|fenced| {
+            let mut fenced = fenced.borrow_mut();
+            let last = match fenced.get_mut(&self.ty) {
+                Some(count) => {
+                    *count -= 1;
+                    *count == 0
+                },
+                None => false
+            };
+            if last {
+                fenced.remove(&self.ty);
+            }
+        });
+        unsafe {
+            jit_type_free(self.ty);
+        }
+    }
+}
+
+/// Check if a function compiled on this thread uses `ty`
+pub fn is_fenced(ty: &Ty) -> bool {
+    let ty: jit_type_t = ty.into();
+    FENCED.with(|fenced| fenced.borrow().contains_key(&ty))
+}
+
+/// Add `ty` and the types it is made of to `found`
+fn collect(ty: &Ty, found: &mut HashSet<jit_type_t>) {
+    if !found.insert(ty.into()) {
+        return
+    }
+    if let Some(pointee) = ty.get_ref() {
+        collect(pointee, found);
+    }
+    if ty.is_signature() {
+        for param in ty.params() {
+            collect(param, found);
+        }
+        if let Some(ret) = ty.get_return() {
+            collect(ret, found);
+        }
+    } else if ty.is_struct() || ty.is_union() {
+        for field in ty.fields() {
+            collect(field.get_type(), found);
+        }
+    }
+}
+
+/// Fence the types `func` uses on its context, before it is compiled
+pub fn fence(func: jit_function_t) {
+    let mut found = HashSet::new();
+    collect(from_ptr(unsafe { jit_function_get_signature(func) }), &mut found);
+    for block in Blocks::new(func) {
+        for insn in block.iter() {
+            let values = insn.get_dest().into_iter().chain(insn.get_value1().into_iter()).chain(insn.get_value2().into_iter());
+            for value in values {
+                collect(value.get_type(), &mut found);
+            }
+            if let Some(sig) = insn.get_signature() {
+                collect(sig, &mut found);
+            }
+        }
+    }
+    ContextData::with_func(func, |data| for ty in found {
+        let changeable: &Ty = from_ptr(ty);
+        if (changeable.is_struct() || changeable.is_union() || changeable.is_signature()) && !data.fences.contains_key(&ty) {
+            data.fences.insert(ty, Fence::new(ty));
+        }
+    })
+}
@@ -1,23 +1,74 @@
 use raw::*;
-use context::Context;
+use analysis::BlockGraph;
+use context::{Context, EmitBudget, FunctionHook};
 use compile::Compile;
-use label::Label;
-use types::Ty;
-use insn::Block;
+use label::{self, Label, UnplacedLabel};
+use codespace::CodeHandle;
+use cse;
+use licm;
+use location::{self, CallStack, SourceLocation};
+use mathlib;
+use lvalue::{self, LValue, RValue};
+use params::ParamTypes;
+use types::{consts, get, same_layout, SignatureMismatch, Type, TypeKind, Ty};
+use background::{self, PendingCompile};
+use batch::BatchArgs;
+use handle::CompiledHandle;
+use buffer::{self, Buffer};
+use data::{ContextData, FunctionData};
+use debuginfo;
+use dump;
+use events;
+use fence;
+use exception::{self, JitException};
+use interp;
+use insn::{Block, Blocks, Instruction};
+use replay::{self, Replay};
+use report::{self, CodegenReport};
+use rewrite::{self, Rewrite, Splice};
+use shadow;
+use ssa;
+use stack;
+use support::{self, Unsupported};
 use value::Val;
+use wide;
 use util::{self, from_ptr, from_ptr_opt, from_ptr_oom};
+use pages::Storage;
 use libc::{
     c_char,
     c_int,
     c_uint,
     c_void
 };
+use std::cell::Cell;
+use std::collections::{HashMap, HashSet};
+use std::cmp;
 use std::default::Default;
+use std::error::Error;
 use std::fmt;
-use std::ops::{Deref, DerefMut, Index};
-use std::{mem, ptr};
-use std::ffi::CString;
+use std::ops::{BitAnd, BitOr, Deref, DerefMut, Index, Not};
+use std::{mem, ptr, slice};
 use std::marker::PhantomData;
+use std::rc::Rc;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+extern {
+    fn printf(format: *const c_char, ...) -> c_int;
+}
+extern fn debug_assert_failed(message: *const c_char) {
+    use std::ffi::CStr;
+    use std::io::{self, Write};
+    unsafe {
+        let message = String::from_utf8_lossy(CStr::from_ptr(message).to_bytes());
+        let _ = writeln!(io::stderr(), "Assertion failed in generated code: {}", message);
+        ::libc::abort()
+    }
+}
+/// A LibJIT function that makes an instruction from two values
+type BinOp = unsafe extern "C" fn(jit_function_t, jit_value_t, jit_value_t) -> jit_value_t;
+/// A LibJIT function that makes an instruction from one value
+type UnOp = unsafe extern "C" fn(jit_function_t, jit_value_t) -> jit_value_t;
+
 /// A platform's application binary interface
 ///
 /// This describes how the function should be called
@@ -53,6 +104,19 @@ pub mod flags {
         }
     );
 }
+#[derive(Clone, Copy, Debug, PartialEq)]
+/// Where a function made by `Func::curry` gets one of the target's arguments from,
+/// or the constant `Func::with_defaults` passes when an argument is left out
+pub enum Bind {
+    /// Forward the wrapper's parameter at this index
+    Param(usize),
+    /// Pass this integer, converted to the parameter's type
+    Int(i64),
+    /// Pass this float, converted to the parameter's type
+    Float(f64),
+    /// Pass this pointer
+    Ptr(*mut c_void)
+}
 /// A function
 pub struct Func(PhantomData<[()]>);
 native_ref!(&Func = jit_function_t);
@@ -65,12 +129,423 @@ impl Func {
     pub fn get_signature(&self) -> &Ty {
         unsafe { from_ptr(jit_function_get_signature(self.into())) }
     }
+    /// Mark whether calls to this function made with `insn_call_inline`
+    /// should be inlined
+    pub fn set_inline(&self, inline: Inline) {
+        FunctionData::with(self.into(), |data| data.inline = inline)
+    }
+    /// Get whether calls to this function made with `insn_call_inline` should
+    /// be inlined
+    pub fn get_inline(&self) -> Inline {
+        FunctionData::with(self.into(), |data| data.inline)
+    }
+    /// Get diagnostics about the code generated for this function, like how
+    /// many values had to be spilled and how big its frame is, if it has been
+    /// compiled
+    ///
+    /// ```rust
+    /// use jit::*;
+    /// let mut ctx = Context::<()>::new();
+    /// let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+    /// func.insn_return(func.insn_mul(&func[0], &func[0]));
+    /// let report = func.compile().codegen_report().unwrap();
+    /// assert_eq!(report.blocks, 1);
+    /// assert!(report.code_size > 0);
+    /// ```
+    pub fn codegen_report(&self) -> Option<CodegenReport> {
+        if self.is_compiled() {
+            report::report(self.into())
+        } else {
+            None
+        }
+    }
+    /// Get the ELF object describing this function's code that was
+    /// registered with debuggers, if its context has `debug_info` set
+    pub fn get_debug_object(&self) -> Option<Vec<u8>> {
+        FunctionData::with(self.into(), |data| data.debug_entry.as_ref().map(|entry| entry.get_object().to_vec()))
+    }
+    /// Get the source locations set while building this function, in the
+    /// order they were first set
+    pub fn get_locations(&self) -> Vec<SourceLocation> {
+        FunctionData::with(self.into(), |data| data.locations.clone())
+    }
+    /// Get how many times each block of this function has run, in the order
+    /// they were built, if it was built with coverage enabled
+    pub fn get_coverage(&self) -> Option<Vec<usize>> {
+        FunctionData::with(self.into(), |data| data.coverage.as_ref().map(|counts|
+            counts.iter().map(|count| **count).collect()
+        ))
+    }
+    /// Get the listing of the IR this function was last compiled with, if
+    /// its context keeps IR history
+    pub fn get_ir(&self) -> Option<String> {
+        FunctionData::with(self.into(), |data| data.ir.clone())
+    }
+    /// Compare the IR this function was compiled with before, or the IR of
+    /// the function it was built to replace with `recompiles`, with the IR
+    /// it was last compiled with
+    ///
+    /// This is only available if its context keeps IR history.
+    ///
+    /// ```rust
+    /// use jit::*;
+    /// let mut ctx = Context::<()>::new();
+    /// ctx.enable_ir_history();
+    /// let baseline = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+    /// baseline.insn_return(baseline.insn_mul(&baseline[0], baseline.insn_of(2i32)));
+    /// let optimized = baseline.sibling(&get::<fn(i32) -> i32>());
+    /// let baseline = baseline.compile();
+    /// optimized.recompiles(&baseline);
+    /// optimized.insn_return(optimized.insn_shl(&optimized[0], optimized.insn_of(1i32)));
+    /// let diff = optimized.compile().diff_ir().unwrap();
+    /// assert_eq!(diff.changed(), 1);
+    /// assert_eq!(diff.added() + diff.removed(), 0);
+    /// ```
+    pub fn diff_ir(&self) -> Option<IrDiff> {
+        FunctionData::with(self.into(), |data| match (&data.previous_ir, &data.ir) {
+            (&Some(ref old), &Some(ref new)) => Some(IrDiff::new(old, new)),
+            _ => None
+        })
+    }
+    /// Get how many times this function has been called, if it was built
+    /// with call counts enabled
+    pub fn get_call_count(&self) -> Option<usize> {
+        FunctionData::with(self.into(), |data| data.calls.as_ref().map(|count| **count))
+    }
+    /// Retire this function's code, making every call to it from now on
+    /// throw `JitException::StaleCode` instead of running it, if it was
+    /// built with invalidation enabled, giving whether it was
+    ///
+    /// This looks up the function's data, so it must be done on the thread
+    /// that owns the function. To retire it from another thread or a signal
+    /// handler, get an `Invalidator` with `invalidator` beforehand. Calls
+    /// already running carry on. No code is patched, so there is no
+    /// instruction cache to flush, even on ARM. Calls that were inlined into
+    /// other functions aren't affected.
+    ///
+    /// ```rust
+    /// use jit::*;
+    /// let mut ctx = Context::<()>::new();
+    /// ctx.enable_invalidation();
+    /// let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+    /// func.insn_return(func.insn_add(&func[0], func.insn_of(1i32)));
+    /// let func = func.compile();
+    /// assert!(func.invalidate());
+    /// assert!(func.is_invalidated());
+    /// let inc = func.closure_result::<i32, i32>();
+    /// assert_eq!(inc(1), Err(JitException::StaleCode));
+    /// ```
+    pub fn invalidate(&self) -> bool {
+        match self.invalidator() {
+            Some(invalidator) => {
+                invalidator.invalidate();
+                true
+            },
+            None => false
+        }
+    }
+    /// Check if this function's code has been retired with `invalidate`
+    pub fn is_invalidated(&self) -> bool {
+        self.invalidator().map_or(false, |invalidator| invalidator.is_invalidated())
+    }
+    /// Get a handle that retires this function's code like `invalidate`,
+    /// which can be sent to other threads, if it was built with invalidation
+    /// enabled
+    ///
+    /// ```rust
+    /// use jit::*;
+    /// use std::thread;
+    /// let mut ctx = Context::<()>::new();
+    /// ctx.enable_invalidation();
+    /// let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+    /// func.insn_return(&func[0]);
+    /// let func = func.compile();
+    /// let invalidator = func.invalidator().unwrap();
+    /// thread::spawn(move || invalidator.invalidate()).join().unwrap();
+    /// assert!(func.is_invalidated());
+    /// ```
+    pub fn invalidator(&self) -> Option<Invalidator> {
+        FunctionData::with(self.into(), |data| data.stale.clone().map(|flag| Invalidator {
+            flag: flag
+        }))
+    }
+    /// Make a thin wrapper function on the same context that calls this
+    /// function with the arguments described by `binds`, one for each of its
+    /// parameters
+    ///
+    /// The wrapper's parameters are the ones forwarded with `Bind::Param`, so
+    /// parameters can be dropped, reordered or duplicated to fit a fixed
+    /// callback signature. Every wrapper parameter below the highest forwarded
+    /// index must be forwarded somewhere.
+    ///
+    /// ```rust
+    /// use jit::*;
+    /// let mut ctx = Context::<()>::new();
+    /// let add = {
+    ///     let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32, i32) -> i32>());
+    ///     func.insn_return(func.insn_add(&func[0], &func[1]));
+    ///     func.compile()
+    /// };
+    /// let inc = add.curry(&[Bind::Param(0), Bind::Int(1)]);
+    /// inc.with(|inc: extern fn(i32) -> i32| {
+    ///     assert_eq!(inc(41), 42);
+    /// });
+    /// ```
+    pub fn curry(&self, binds: &[Bind]) -> CompiledFunction {
+        let wrapper = self.wrapper(&self.curried_signature(binds));
+        self.call_curried(&wrapper, binds);
+        wrapper.compile()
+    }
+    /// Make a new function on the same context as this one, with the
+    /// context's options applied, for wrapping this one
+    fn wrapper(&self, signature: &Ty) -> UncompiledFunction {
+        unsafe {
+            let mut func:UncompiledFunction = from_ptr_oom(jit_function_create(
+                jit_function_get_context(self.into()),
+                signature.into()
+            ));
+            func.owned = true;
+            func.apply_options();
+            func
+        }
+    }
+    /// Make thin wrappers on the same context that call this function with
+    /// constant defaults for trailing arguments that are left out
+    ///
+    /// `defaults` has one entry for each parameter, giving its default as a
+    /// `Bind::Int`, `Bind::Float` or `Bind::Ptr`, or `None` if it has none.
+    /// Only trailing parameters can have defaults. There is a wrapper for
+    /// each number of arguments that can be left out, with the one taking
+    /// the fewest arguments first.
+    ///
+    /// ```rust
+    /// use jit::*;
+    /// let mut ctx = Context::<()>::new();
+    /// let add = {
+    ///     let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32, i32) -> i32>());
+    ///     func.insn_return(func.insn_add(&func[0], &func[1]));
+    ///     func.compile()
+    /// };
+    /// let wrappers = add.with_defaults(&[None, Some(Bind::Int(1))]);
+    /// assert_eq!(wrappers.len(), 1);
+    /// wrappers[0].with(|inc: extern fn(i32) -> i32| {
+    ///     assert_eq!(inc(41), 42);
+    /// });
+    /// ```
+    pub fn with_defaults(&self, defaults: &[Option<Bind>]) -> Vec<CompiledFunction> {
+        let params = self.get_signature().params().count();
+        if defaults.len() != params {
+            panic!("Bad defaults - expected {}, got {}", params, defaults.len())
+        }
+        let required = defaults.iter().position(|default| default.is_some()).unwrap_or(params);
+        for (index, default) in defaults.iter().enumerate().skip(required) {
+            match *default {
+                None => panic!("Parameter {} has no default but comes after one that does", index),
+                Some(Bind::Param(_)) => panic!("The default for parameter {} should be a constant", index),
+                Some(_) => ()
+            }
+        }
+        let binds: Vec<Vec<Bind>> = (required..params).map(|taken|
+            (0..params).map(|index| if index < taken {
+                Bind::Param(index)
+            } else {
+                defaults[index].unwrap()
+            }).collect()
+        ).collect();
+        let wrappers = match binds.first() {
+            Some(first) => {
+                let first = self.wrapper(&self.curried_signature(first));
+                let mut wrappers: Vec<UncompiledFunction> = binds[1..].iter()
+                    .map(|binds| first.sibling(&self.curried_signature(binds)))
+                    .collect();
+                wrappers.insert(0, first);
+                wrappers
+            },
+            None => Vec::new()
+        };
+        wrappers.into_iter().zip(binds.iter()).map(|(wrapper, binds)| {
+            self.call_curried(&wrapper, binds);
+            wrapper.compile()
+        }).collect()
+    }
+    /// Make an adapter on the same context that calls this function with
+    /// `user_data` as its first argument and the adapter's arguments as the
+    /// rest, and get the adapter's entry point, which can be given to C code
+    /// as a callback
+    ///
+    /// This lets generated code be used with C APIs whose callbacks don't
+    /// take a context pointer of their own, like `qsort`'s comparator. The
+    /// first parameter of this function must be a pointer. The entry point
+    /// is valid for as long as the context, and exceptions must not escape the
+    /// function, since there are C frames between it and whatever could
+    /// catch them. A function with an environment bound with `bind_env`, like
+    /// a helper, gets `user_data` as its environment, so it must be bound to
+    /// the first parameter.
+    ///
+    /// ```rust
+    /// use jit::*;
+    /// use std::os::raw::c_void;
+    /// let mut ctx = Context::<()>::new();
+    /// let scale = {
+    ///     let func = UncompiledFunction::new(&mut ctx, &get::<fn(&'static i32, i32) -> i32>());
+    ///     let factor = func.insn_load_relative(&func[0], 0, &get::<i32>());
+    ///     func.insn_return(func.insn_mul(factor, &func[1]));
+    ///     func.compile()
+    /// };
+    /// let mut factor = 3i32;
+    /// let callback = scale.as_c_callback(&mut factor as *mut i32 as *mut c_void);
+    /// let callback: extern "C" fn(i32) -> i32 = unsafe { std::mem::transmute(callback) };
+    /// assert_eq!(callback(14), 42);
+    /// ```
+    pub fn as_c_callback(&self, user_data: *mut c_void) -> *mut c_void {
+        let params = self.get_signature().params().count();
+        match self.get_signature().params().next() {
+            Some(first) if first.is_pointer() => (),
+            _ => panic!("The first parameter of a function made into a callback should be a pointer for the user data")
+        }
+        let first = unsafe { jit_value_get_param(self.into(), 0) };
+        if FunctionData::with(self.into(), |data| data.env.map_or(false, |env| env != first)) {
+            panic!("A function made into a callback gets the user data as its environment, so it must be bound to its first parameter")
+        }
+        let mut binds = vec![Bind::Ptr(user_data)];
+        binds.extend((1..params).map(|index| Bind::Param(index - 1)));
+        let adapter = self.curry(&binds);
+        unsafe {
+            jit_function_to_closure((&adapter).into())
+        }
+    }
+    /// Get the signature of a wrapper made by `curry` with `binds`
+    fn curried_signature(&self, binds: &[Bind]) -> Type {
+        let sig = self.get_signature();
+        let params:Vec<&Ty> = sig.params().collect();
+        if binds.len() != params.len() {
+            panic!("Bad binds to curry - expected {}, got {}", params.len(), binds.len())
+        }
+        let mut forwarded:Vec<Option<&Ty>> = Vec::new();
+        for (bind, &param) in binds.iter().zip(params.iter()) {
+            if let Bind::Param(index) = *bind {
+                while forwarded.len() <= index {
+                    forwarded.push(None);
+                }
+                match forwarded[index] {
+                    Some(ty) if ty != param =>
+                        panic!("Parameter {} is forwarded as both {:?} and {:?}", index, ty, param),
+                    _ => forwarded[index] = Some(param)
+                }
+            }
+        }
+        let mut wrapper_params:Vec<&Ty> = forwarded.into_iter().enumerate().map(|(index, ty)|
+            ty.unwrap_or_else(|| panic!("Parameter {} of the curried function is never forwarded", index))
+        ).collect();
+        let abi = unsafe { mem::transmute(jit_type_get_abi(sig.into())) };
+        let ret = sig.get_return().unwrap_or(consts::get_void());
+        Type::new_signature(abi, ret, &mut wrapper_params)
+    }
+    /// Build the body of a wrapper made by `curry` with `binds`
+    fn call_curried(&self, wrapper: &UncompiledFunction, binds: &[Bind]) {
+        let sig = self.get_signature();
+        let ret = sig.get_return().unwrap_or(consts::get_void());
+        let mut args:Vec<&Val> = binds.iter().zip(sig.params()).map(|(bind, param)| match *bind {
+            Bind::Param(index) => &wrapper[index],
+            Bind::Int(value) => wrapper.insn_convert(wrapper.insn_of(value), param, false),
+            Bind::Float(value) => wrapper.insn_convert(wrapper.insn_of(value), param, false),
+            Bind::Ptr(ptr) => wrapper.insn_convert(wrapper.insn_of(ptr as usize), param, false)
+        }).collect();
+        let result = wrapper.insn_call(None, self, None, &mut args, flags::CallFlags::empty());
+        if ret.get_kind() == TypeKind::Void {
+            wrapper.insn_default_return();
+        } else {
+            wrapper.insn_return(result);
+        }
+    }
 }
 /// A function which has already been compiled from an `UncompiledFunction`, so it can
 /// be called but not added to.
 ///
 /// A function persists for the lifetime of its containing context. This is
 /// a function which has already been compiled and is now in executable form.
+/// A value known to be 1 for true or 0 for false, made by
+/// `insn_normalize_bool`
+///
+/// LibJIT treats any value that isn't zero as true when branching, but
+/// arithmetic doesn't, so `2 & 1` is false. Combining `Bool`s with `&`, `|`
+/// and `!` always gives another 0 or 1.
+pub struct Bool<'a> {
+    value: &'a Val
+}
+impl<'a> Clone for Bool<'a> {
+    fn clone(&self) -> Bool<'a> {
+        Bool { value: self.value }
+    }
+}
+impl<'a> Copy for Bool<'a> {}
+impl<'a> Bool<'a> {
+    /// Get the value, to give to instructions
+    pub fn get(self) -> &'a Val {
+        self.value
+    }
+}
+impl<'a> Deref for Bool<'a> {
+    type Target = Val;
+    fn deref(&self) -> &Val {
+        self.value
+    }
+}
+impl<'a> fmt::Debug for Bool<'a> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "bool({:?})", self.value)
+    }
+}
+impl<'a> BitAnd for Bool<'a> {
+    type Output = Bool<'a>;
+    fn bitand(self, other: Bool<'a>) -> Bool<'a> {
+        Bool { value: self.value.get_function().insn_and(self.value, other.value) }
+    }
+}
+impl<'a> BitOr for Bool<'a> {
+    type Output = Bool<'a>;
+    fn bitor(self, other: Bool<'a>) -> Bool<'a> {
+        Bool { value: self.value.get_function().insn_or(self.value, other.value) }
+    }
+}
+impl<'a> Not for Bool<'a> {
+    type Output = Bool<'a>;
+    fn not(self) -> Bool<'a> {
+        Bool { value: self.value.get_function().insn_to_not_bool(self.value) }
+    }
+}
+
+/// A function pointer type compiled code can be got as with
+/// `CompiledFunction::closure`, which knows the sizes of its parameters and
+/// result so they can be checked against the function's signature
+pub trait ClosureType: Copy {
+    /// Get the size of each parameter, in order
+    fn param_sizes() -> Vec<usize>;
+    /// Get the size of the result, which is zero if there isn't one
+    fn result_size() -> usize;
+}
+macro_rules! closure_types(
+    ($($($param:ident),*;)*) => ($(
+        impl<$($param,)* R> ClosureType for extern fn($($param),*) -> R {
+            fn param_sizes() -> Vec<usize> {
+                vec![$(mem::size_of::<$param>()),*]
+            }
+            fn result_size() -> usize {
+                mem::size_of::<R>()
+            }
+        }
+    )*)
+);
+closure_types!{
+    ;
+    A;
+    A, B;
+    A, B, C;
+    A, B, C, D;
+    A, B, C, D, E;
+    A, B, C, D, E, G;
+}
+
 #[derive(Clone, Copy)]
 pub struct CompiledFunction<'a> {
     _func: jit_function_t,
@@ -96,117 +571,791 @@ impl<'a> fmt::Debug for CompiledFunction<'a> {
     }
 }
 impl<'a> CompiledFunction<'a> {
+    /// Export this function under `name`, so it shows up under that name in
+    /// the context's `call_profile`, replacing any function exported under
+    /// that name before
+    pub fn export(&self, name: &str) {
+        let func = self._func;
+        ContextData::with_func(func, |data| {
+            data.exports.retain(|&(ref other, _)| other != name);
+            data.exports.push((name.to_owned(), func));
+        })
+    }
+    /// Make a handle on this function that can be sent to other threads to
+    /// call it with the arguments `A`, as a tuple, giving `R`
+    ///
+    /// This panics if the function doesn't take `A` and give `R`. The
+    /// handle keeps the function's code alive, even after its context is
+    /// dropped.
+    pub fn to_handle<A, R>(&self) -> CompiledHandle<A, R> where A: BatchArgs<'a>, R: Compile<'a> {
+        CompiledHandle::new(self)
+    }
+    #[inline(always)]
+    /// Make a reference to this function that doesn't keep it alive
+    pub fn downgrade(&self) -> WeakFunction<'a> {
+        WeakFunction::new(self._func)
+    }
+    /// Get the address the machine code of this function starts at, which
+    /// is where calls to it go
+    pub fn get_code_address(&self) -> usize {
+        debuginfo::code_range(self._func).0
+    }
+    /// Get the machine code of this function, to hash it as part of a cache
+    /// key, write it out to be disassembled, or copy it somewhere else
+    ///
+    /// The code is only valid at the address it was compiled at, since calls
+    /// out of it and the constants it loads can be relative to it. This is
+    /// empty when the JIT is using its fallback interpreter, which doesn't
+    /// make machine code.
+    ///
+    /// ```rust
+    /// use jit::*;
+    /// let mut ctx = Context::<()>::new();
+    /// let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+    /// func.insn_return(func.insn_mul(&func[0], &func[0]));
+    /// let func = func.compile();
+    /// let code = func.code_bytes().to_vec();
+    /// assert_eq!(code.is_empty(), uses_interpreter());
+    /// ```
+    pub fn code_bytes(&self) -> &[u8] {
+        let (start, end) = debuginfo::code_range(self._func);
+        if end == start {
+            return &[]
+        }
+        unsafe { slice::from_raw_parts(start as *const u8, end - start) }
+    }
     /// Run a closure with the compiled function as an argument
     pub fn with<A, R, F:FnOnce(extern "C" fn(A) -> R)>(self, cb:F) {
         cb(unsafe {
             mem::transmute(jit_function_to_closure(self._func))
         })
     }
+    /// Get the compiled function as the function pointer type `F`
+    ///
+    /// Unless built with `ndebug`, this panics if `F` takes a different number
+    /// of parameters to the function, or a parameter or the result is a
+    /// different size to the one in its signature, instead of giving a
+    /// pointer that calls it with the wrong types. Types of the same size
+    /// aren't told apart, so this can't catch an `f32` passed as an `i32`.
+    ///
+    /// ```rust
+    /// use jit::*;
+    /// let mut ctx = Context::<()>::new();
+    /// let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32, i32) -> i32>());
+    /// func.insn_return(func.insn_sub(&func[0], &func[1]));
+    /// let sub: extern fn(i32, i32) -> i32 = func.compile().closure();
+    /// assert_eq!(sub(5, 3), 2);
+    /// ```
+    pub fn closure<F>(&self) -> F where F: ClosureType {
+        if cfg!(not(ndebug)) {
+            let sig = self.get_signature();
+            let params: Vec<usize> = sig.params().map(|param| param.get_size()).collect();
+            let result = sig.get_return().map(|ty| ty.get_size()).unwrap_or(0);
+            let (expected_params, expected_result) = (F::param_sizes(), F::result_size());
+            if params.len() != expected_params.len() {
+                panic!("closure was asked for a function taking {} parameters, but {:?} takes {}",
+                       expected_params.len(), sig, params.len())
+            }
+            if let Some(index) = params.iter().zip(expected_params.iter()).position(|(a, b)| a != b) {
+                panic!("closure was asked for a function whose parameter {} is {} bytes, but it is {} bytes in {:?}",
+                       index, expected_params[index], params[index], sig)
+            }
+            if result != expected_result {
+                panic!("closure was asked for a function whose result is {} bytes, but it is {} bytes in {:?}",
+                       expected_result, result, sig)
+            }
+        }
+        unsafe {
+            let entry = jit_function_to_closure(self._func);
+            ptr::read(&entry as *const *mut c_void as *const F)
+        }
+    }
+    /// Call the function directly on each tuple of arguments in `args`,
+    /// writing the results to the same place in `results`
+    ///
+    /// This looks up the entry point and checks the signature once, then
+    /// calls the compiled code itself for each row, rather than marshalling
+    /// every call through `jit_function_apply`. Like `with`, exceptions that
+    /// escape the function aren't caught.
+    ///
+    /// ```rust
+    /// use jit::*;
+    /// let mut ctx = Context::<()>::new();
+    /// let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32, i32) -> i32>());
+    /// func.insn_return(func.insn_mul(&func[0], &func[1]));
+    /// let mut products = [0; 3];
+    /// func.compile().apply_batch(&[(1, 2), (3, 4), (5, 6)], &mut products);
+    /// assert_eq!(products, [2, 12, 30]);
+    /// ```
+    pub fn apply_batch<A, R>(&self, args: &[A], results: &mut [R]) where A: BatchArgs<'a>, R: Compile<'a> {
+        if args.len() != results.len() {
+            panic!("apply_batch was given {} rows of arguments but room for {} results", args.len(), results.len())
+        }
+        if let Err(err) = self.get_signature().check_signature(&A::signature::<R>()) {
+            panic!("Arguments given to apply_batch don't match the function: {}", err)
+        }
+        let entry = unsafe { jit_function_to_closure(self._func) as usize };
+        for (&args, result) in args.iter().zip(results.iter_mut()) {
+            *result = unsafe { args.call(entry) };
+        }
+    }
+    /// Get a closure that calls the function, giving the exception that
+    /// escaped it as an error instead of letting it reach the host
+    ///
+    /// The function must take at most one parameter, which is given as `A`,
+    /// or `()` if it takes none.
+    ///
+    /// ```rust
+    /// use jit::*;
+    /// let mut ctx = Context::<()>::new();
+    /// let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+    /// func.insn_return(func.insn_div(func.insn_of(12i32), &func[0]));
+    /// let div = func.compile().closure_result::<i32, i32>();
+    /// assert_eq!(div(4), Ok(3));
+    /// assert_eq!(div(0), Err(JitException::Builtin(BuiltinException::DivisionByZero)));
+    /// ```
+    pub fn closure_result<A, R>(self) -> Box<Fn(A) -> Result<R, JitException> + 'a> where A: 'a, R: Copy + 'a {
+        let func = self._func;
+        let params = unsafe { jit_type_num_params(jit_function_get_signature(func)) };
+        if params > 1 {
+            panic!("closure_result only supports functions with at most one parameter, not {}", params)
+        }
+        Box::new(move |mut arg: A| unsafe {
+            let mut args = [&mut arg as *mut A as *mut c_void];
+            let args = if params == 0 { ptr::null_mut() } else { args.as_mut_ptr() };
+            let mut result: R = mem::zeroed();
+            try!(exception::apply(func, args, &mut result as *mut R as *mut c_void));
+            Ok(result)
+        })
+    }
+}
+
+#[derive(Clone)]
+/// A handle that retires the code of a function built with invalidation
+/// enabled, made by `Func::invalidator`
+///
+/// Unlike the function, this can be sent to and shared between threads.
+/// Invalidating is a single atomic store to the flag the function checks on
+/// entry, which doesn't allocate or lock, so it can be done from a signal
+/// handler too, as long as the handle isn't dropped there. The flag lives
+/// as long as the function or any handle to it does.
+pub struct Invalidator {
+    flag: Arc<AtomicUsize>
+}
+impl Invalidator {
+    /// Make every call to the function from now on throw
+    /// `JitException::StaleCode` instead of running it
+    pub fn invalidate(&self) {
+        self.flag.store(1, Ordering::SeqCst)
+    }
+    /// Check if the function's code has been retired
+    pub fn is_invalidated(&self) -> bool {
+        self.flag.load(Ordering::SeqCst) != 0
+    }
+}
+impl fmt::Debug for Invalidator {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "Invalidator({})", if self.is_invalidated() { "invalidated" } else { "live" })
+    }
+}
+
+/// A reference to a function that doesn't keep it alive
+///
+/// This is useful for caches of functions, which shouldn't stop functions
+/// being abandoned.
+///
+/// ```rust
+/// use jit::*;
+/// let mut ctx = Context::<()>::new();
+/// let func = UncompiledFunction::new(&mut ctx, &get::<fn()>());
+/// let weak = func.downgrade();
+/// assert!(weak.upgrade().is_some());
+/// drop(func);
+/// assert!(weak.upgrade().is_none());
+/// ```
+#[derive(Clone)]
+pub struct WeakFunction<'a> {
+    _func: jit_function_t,
+    alive: Rc<Cell<bool>>,
+    marker: PhantomData<&'a ()>
+}
+impl<'a> WeakFunction<'a> {
+    fn new(func: jit_function_t) -> WeakFunction<'a> {
+        WeakFunction {
+            _func: func,
+            alive: FunctionData::alive(func),
+            marker: PhantomData
+        }
+    }
+    #[inline(always)]
+    /// Check if the function still exists
+    pub fn is_alive(&self) -> bool {
+        self.alive.get()
+    }
+    /// Get the function, if it still exists
+    pub fn upgrade(&self) -> Option<&'a Func> {
+        if self.alive.get() {
+            Some(from_ptr(self._func))
+        } else {
+            None
+        }
+    }
+    /// Get the function if it has been compiled and still exists
+    pub fn upgrade_compiled(&self) -> Option<CompiledFunction<'a>> {
+        if self.alive.get() && unsafe { jit_function_is_compiled(self._func) } != 0 {
+            Some(from_ptr(self._func))
+        } else {
+            None
+        }
+    }
 }
+impl<'a> fmt::Debug for WeakFunction<'a> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "WeakFunction({:?}, alive: {})", self._func, self.alive.get())
+    }
+}
+
+/// The biggest constant power `insn_pow` multiplies out instead of calling
+/// `pow`
+const MAX_REPEAT_MUL: u32 = 16;
 
+/// Check if the types of instructions made from `value` should be checked,
+/// which they aren't once its function is poisoned
+fn validating(value: &Val) -> bool {
+    cfg!(not(ndebug)) && unsafe {
+        ContextData::with(jit_value_get_context(value.into()), |data| data.options.validation) &&
+            !FunctionData::with(jit_value_get_function(value.into()), |data| data.poison.is_some())
+    }
+}
+/// Report a value of the wrong type given to an instruction in the function
+/// it belongs to
+fn invalid(value: &Val, message: String) {
+    fail(unsafe { jit_value_get_function(value.into()) }, message)
+}
+/// Poison `func` with `message` as what went wrong building it, unless it is
+/// already poisoned, if its context defers errors, or panic with it if not
+fn fail(func: jit_function_t, message: String) {
+    if !ContextData::with_func(func, |data| data.options.defer_errors) {
+        panic!("{}", message)
+    }
+    let stack = CallStack::capture();
+    let backtrace = (0..stack.len()).filter_map(|posn| stack.get_pc(posn)).map(|pc| pc as usize).collect();
+    FunctionData::with(func, |data| if data.poison.is_none() {
+        data.poison = Some(EmitError {
+            message: message,
+            location: data.locations.last().cloned(),
+            backtrace: backtrace
+        })
+    })
+}
+/// Count `insns` instructions, `values` values and `labels` labels made in
+/// `func` against its emission budget, poisoning it if that goes over, and
+/// check it isn't poisoned, so more can be made in it
+pub fn spend(func: jit_function_t, insns: usize, values: usize, labels: usize) -> bool {
+    let budget = FunctionData::with(func, |data| data.budget)
+        .unwrap_or_else(|| ContextData::with_func(func, |data| data.emit_budget));
+    let over = FunctionData::with(func, |data| {
+        if data.poison.is_some() {
+            return Err(())
+        }
+        data.spent.0 += insns;
+        data.spent.1 += values;
+        data.spent.2 += labels;
+        Ok(if data.spent.0 > budget.max_insns {
+            Some(format!("Function went over its emission budget of {} instructions", budget.max_insns))
+        } else if data.spent.1 > budget.max_values {
+            Some(format!("Function went over its emission budget of {} values", budget.max_values))
+        } else if data.spent.2 > budget.max_labels {
+            Some(format!("Function went over its emission budget of {} labels", budget.max_labels))
+        } else {
+            None
+        })
+    });
+    match over {
+        Ok(None) => true,
+        Ok(Some(message)) => {
+            fail(func, message);
+            false
+        },
+        Err(()) => false
+    }
+}
 macro_rules! expect(
     ($name:ident, $value:expr, float) => (
-        if cfg!(not(ndebug)) {
+        if validating($value) {
             let ty = $value.get_type();
             if !ty.is_float() {
-                panic!("Value given to {} should be float, got {:?}", stringify!($name), ty);
+                invalid($value, format!("Value given to {} should be float, got {:?}", stringify!($name), ty));
             }
         }
     );
     ($name:ident, $value:expr, primitive) => (
-        if cfg!(not(ndebug)) {
+        if validating($value) {
             let ty = $value.get_type();
             if !ty.is_primitive() {
-                panic!("Value given to {} should be primitive, got {:?}", stringify!($name), ty);
+                invalid($value, format!("Value given to {} should be primitive, got {:?}", stringify!($name), ty));
             }
         }
     );
     ($name:ident, $v1:expr, $v2:expr, primitive) => (
-        if cfg!(not(ndebug)) {
+        if validating($v1) {
             let ty1 = $v1.get_type();
             let ty2 = $v2.get_type();
             if !ty1.is_primitive() {
-                panic!("Values given to {} should be primitive, got {:?}", stringify!($name), ty1);
+                invalid($v1, format!("Values given to {} should be primitive, got {:?}", stringify!($name), ty1));
             } else if !ty2.is_primitive() {
-                panic!("Values given to {} should be primitive, got {:?}", stringify!($name), ty2);
+                invalid($v1, format!("Values given to {} should be primitive, got {:?}", stringify!($name), ty2));
+            }
+        }
+    );
+    ($name:ident, $value:expr, pointer) => (
+        if validating($value) {
+            let ty = $value.get_type();
+            if !ty.is_pointer() {
+                invalid($value, format!("Value given to {} should be pointer, got {:?}", stringify!($name), ty));
             }
         }
     );
     ($name:ident, $value:expr, int) => (
-        if cfg!(not(ndebug)) {
+        if validating($value) {
             let ty = $value.get_type();
             if !ty.is_int() {
-                panic!("Value given to {} should be integer, got {:?}", stringify!($name), ty);
+                invalid($value, format!("Value given to {} should be integer, got {:?}", stringify!($name), ty));
             }
         }
     );
     ($name:ident, $dest:expr, $source:expr, $size:expr) => (
-        if cfg!(not(ndebug)) {
+        if validating($dest) {
             let dest_t = $dest.get_type();
             let source_t = $source.get_type();
             let size_t = $size.get_type();
             if !size_t.is_int() {
-                panic!("Expected integer size for {}, but got {:?}", stringify!($name), size_t);
+                invalid($dest, format!("Expected integer size for {}, but got {:?}", stringify!($name), size_t));
             } else if !dest_t.is_pointer() {
-                panic!("Expected pointer destination for {}, but got {:?}", stringify!($name), size_t);
+                invalid($dest, format!("Expected pointer destination for {}, but got {:?}", stringify!($name), size_t));
             } else if !source_t.is_pointer() {
-                panic!("Expected pointer source for {}, but got {:?}", stringify!($name), size_t);
+                invalid($dest, format!("Expected pointer source for {}, but got {:?}", stringify!($name), size_t));
             }
         }
     )
 );
 
-#[derive(PartialEq)]
-/// A function which has not been compiled yet, so it can have instructions added to it.
-///
-/// A function persists for the lifetime of its containing context. This represents
-/// the function in the "building" state, where the user constructs instructions
-/// that represents the function body. Once the build process is complete, the
-/// user calls `function.compile()` to convert it into its executable form.
-pub struct UncompiledFunction<'a> {
-    _func: jit_function_t,
-    marker: PhantomData<&'a ()>,
-    owned: bool
-}
-impl<'a, 'b> From<&'a UncompiledFunction<'b>> for jit_function_t {
-    /// Convert to a native pointer
-    fn from(func: &'a UncompiledFunction<'b>) -> jit_function_t {
-        func._func
+/// Get the value of `value` if it is an integer constant
+fn int_constant(value: &Val) -> Option<i64> {
+    let ty = value.get_type();
+    if !value.is_constant() || !ty.is_int() {
+        None
+    } else if ty.get_size() == 8 {
+        Some(unsafe { jit_value_get_long_constant(value.into()) })
+    } else {
+        Some(unsafe { jit_value_get_nint_constant(value.into()) } as i64)
     }
 }
-impl<'a, 'b> From<&'a mut UncompiledFunction<'b>> for jit_function_t {
-    /// Convert to a native pointer
-    fn from(func: &'a mut UncompiledFunction<'b>) -> jit_function_t {
-        func._func
+
+/// Get the smallest and biggest values of the integer type given
+fn int_bounds(ty: &Ty) -> (i64, u64) {
+    let ty: &Ty = unsafe { from_ptr(jit_type_normalize(ty.into())) };
+    let kind = ty.get_kind();
+    let signed = kind == TypeKind::SByte || kind == TypeKind::Short || kind == TypeKind::Int ||
+        kind == TypeKind::NInt || kind == TypeKind::Long;
+    let bits = ty.get_size() * 8;
+    if signed {
+        (-1i64 << (bits - 1), !(-1i64 << (bits - 1)) as u64)
+    } else if bits == 64 {
+        (0, !0)
+    } else {
+        (0, (1u64 << bits) - 1)
     }
 }
-impl<'a> From<UncompiledFunction<'a>> for jit_function_t {
-    /// Convert to a native pointer
-    fn from(func: UncompiledFunction<'a>) -> jit_function_t {
-        func._func
-    }
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+/// The first thing that went wrong while building a function on a context
+/// that defers errors, which poisons the function
+pub struct EmitError {
+    /// What went wrong
+    pub message: String,
+    /// The source location that was set when it went wrong, if any
+    pub location: Option<SourceLocation>,
+    /// The return addresses of the native frames that were building the
+    /// function, from the innermost outwards, as far as they could be walked
+    pub backtrace: Vec<usize>
 }
-impl<'a> From<jit_function_t> for UncompiledFunction<'a> {
-    fn from(ptr: jit_function_t) -> UncompiledFunction<'a> {
-        UncompiledFunction {
-            _func: ptr,
-            marker: PhantomData,
-            owned: false
+impl fmt::Display for EmitError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        try!(fmt.write_str(&self.message));
+        if let Some(loc) = self.location {
+            try!(write!(fmt, " at {}:{}:{}", loc.file, loc.line, loc.column));
         }
+        Ok(())
     }
 }
-impl<'a> fmt::Debug for UncompiledFunction<'a> {
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+/// Something suspicious found while validating a function, which is likely
+/// to be a bug in the code generating it but doesn't stop it compiling
+pub struct EmitWarning {
+    /// What looks wrong
+    pub message: String,
+    /// The source location that was set when it was made, if any
+    pub location: Option<SourceLocation>
+}
+impl fmt::Display for EmitWarning {
     fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
-        write!(fmt, "{}", try!(util::dump(|fd| unsafe {
-            jit_dump_function(mem::transmute(fd), self.into(), ptr::null());
-        })))
+        try!(fmt.write_str(&self.message));
+        if let Some(loc) = self.location {
+            try!(write!(fmt, " at {}:{}:{}", loc.file, loc.line, loc.column));
+        }
+        Ok(())
     }
 }
 
-impl<'a> Deref for UncompiledFunction<'a> {
-    type Target = Func;
-    fn deref(&self) -> &Func {
-        unsafe { mem::transmute(self._func) }
+#[derive(Clone, Debug, PartialEq, Eq)]
+/// The reason a function couldn't be compiled
+pub enum CompileError {
+    /// Something went wrong while building the function, which poisoned it
+    Poisoned(EmitError),
+    /// The function is nested inside a parent that hasn't been compiled yet
+    ParentNotCompiled,
+    /// The function branches to labels that were never placed
+    UnplacedLabels(Vec<UnplacedLabel>),
+    /// A value is used somewhere it might not have been set, which is only
+    /// checked when `strict_ssa` is set in the context's options
+    UndefinedUse,
+    /// LibJIT failed to compile the function
+    Failed
+}
+impl fmt::Display for CompileError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        try!(write!(fmt, "{}", self.description()));
+        if let CompileError::Poisoned(ref error) = *self {
+            try!(write!(fmt, ": {}", error));
+        }
+        if let CompileError::UnplacedLabels(ref labels) = *self {
+            for (i, label) in labels.iter().enumerate() {
+                try!(write!(fmt, "{}{}", if i == 0 { ": " } else { ", " }, label));
+            }
+        }
+        Ok(())
+    }
+}
+impl Error for CompileError {
+    fn description(&self) -> &'static str {
+        match *self {
+            CompileError::Poisoned(_) => "Function poisoned while it was built",
+            CompileError::ParentNotCompiled => "Nested function compiled before its parent",
+            CompileError::UnplacedLabels(_) => "Branch to a label that was never placed",
+            CompileError::UndefinedUse => "Value used where it might not have been set",
+            CompileError::Failed => "Failed to compile function"
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+/// The reason passes like `eliminate_common` left a function as it was
+pub enum RebuildError {
+    /// The function makes calls, which can't be made again in the new function
+    Calls,
+    /// The function takes the address of a value or allocates on the stack
+    AddressTaken,
+    /// The function has an instruction, named here, that can't be rebuilt
+    Instruction(String),
+    /// The function has weak references, which would be left pointing to it
+    WeakReferences
+}
+impl fmt::Display for RebuildError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        try!(write!(fmt, "{}", self.description()));
+        if let RebuildError::Instruction(ref name) = *self {
+            try!(write!(fmt, ": {}", name));
+        }
+        Ok(())
+    }
+}
+impl Error for RebuildError {
+    fn description(&self) -> &'static str {
+        match *self {
+            RebuildError::Calls => "Function makes calls",
+            RebuildError::AddressTaken => "Function takes the address of a value",
+            RebuildError::Instruction(_) => "Function has an instruction that can't be rebuilt",
+            RebuildError::WeakReferences => "Function has weak references"
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+/// Why functions couldn't be composed into a pipeline, with stages counted
+/// from zero
+pub enum ComposeError {
+    /// No stages were given
+    Empty,
+    /// The stage at the index is on a different context to the first one
+    Context(usize),
+    /// The stage at the index takes this many parameters instead of the one
+    /// result of the stage before it
+    Arity(usize, usize),
+    /// The stage at the index takes the first type, but the stage before it
+    /// returns the second, and one can't be converted to the other
+    Mismatch(usize, String, String)
+}
+impl fmt::Display for ComposeError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ComposeError::Context(index) =>
+                write!(fmt, "{} {}", self.description(), index),
+            ComposeError::Arity(index, count) =>
+                write!(fmt, "{} {}: takes {} parameters", self.description(), index, count),
+            ComposeError::Mismatch(index, ref expected, ref got) =>
+                write!(fmt, "{} {}: expected {}, got {}", self.description(), index, expected, got),
+            ComposeError::Empty => fmt.write_str(self.description())
+        }
+    }
+}
+impl Error for ComposeError {
+    fn description(&self) -> &'static str {
+        match *self {
+            ComposeError::Empty => "No stages to compose",
+            ComposeError::Context(_) => "Composed a stage from another context at stage",
+            ComposeError::Arity(_, _) => "Composed a stage that doesn't take one parameter at stage",
+            ComposeError::Mismatch(_, _, _) => "Composed a stage that can't take the result before it at stage"
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+/// Why a range of blocks couldn't be moved by `move_blocks_to_end` or
+/// `move_blocks_to_start`
+pub enum MoveError {
+    /// The label with this number hasn't been placed
+    Unplaced(u64),
+    /// The block the second label is at doesn't come after the first one's
+    Empty,
+    /// The range starts with the entry block, which can't be moved
+    Entry,
+    /// The block before the range carries on into it, or the range carries on
+    /// past its end, so moving it would change where the code goes next
+    FallsThrough
+}
+impl fmt::Display for MoveError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            MoveError::Unplaced(label) => write!(fmt, "{} {}", self.description(), label),
+            _ => fmt.write_str(self.description())
+        }
+    }
+}
+impl Error for MoveError {
+    fn description(&self) -> &'static str {
+        match *self {
+            MoveError::Unplaced(_) => "Moved blocks from or to an unplaced label",
+            MoveError::Empty => "Moved a range of blocks that is empty",
+            MoveError::Entry => "Moved a range of blocks starting with the entry block",
+            MoveError::FallsThrough => "Moved a range of blocks that code falls into or out of"
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+/// What happens to a function that is dropped before it is compiled
+pub enum Lifetime {
+    /// Abandon the function, freeing it straight away
+    Abandon,
+    /// Leave the function alone, so it lives until its context is destroyed
+    ///
+    /// Use this when other functions may have been built with calls to it.
+    ContextOwned
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+/// What integer arithmetic made with `insn_arith_add`, `insn_arith_sub`,
+/// `insn_arith_mul` or the operators on `&Val` does when it overflows
+///
+/// This is set per function, so a frontend can pick it once, for example
+/// trapping in debug builds and wrapping in release builds.
+pub enum Overflow {
+    /// Wrap around, like `insn_add`
+    Wrap,
+    /// Throw an overflow exception, like `insn_add_ovf`
+    Trap,
+    /// Clamp the result to the range of its type
+    Saturate
+}
+impl Default for Overflow {
+    fn default() -> Overflow {
+        Overflow::Wrap
+    }
+}
+
+/// An arithmetic operation that follows the function's overflow mode
+#[derive(Clone, Copy, PartialEq)]
+enum Arith {
+    Add,
+    Sub,
+    Mul
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+/// Whether calls to a function made with `insn_call_inline` copy its body in
+pub enum Inline {
+    /// Inline it when it fits in the context's `InlineLimits`
+    Auto,
+    /// Inline it whenever it can be, whatever its size
+    Always,
+    /// Always call it
+    Never
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+/// A table of constants kept alive with a function, made by `new_table`
+pub struct ConstTable {
+    address: usize,
+    len: usize
+}
+impl ConstTable {
+    /// Get how many constants are in the table
+    pub fn get_len(&self) -> usize {
+        self.len
+    }
+}
+
+#[derive(Clone, Copy)]
+/// A call made by `insn_call_site` through a slot holding its target, which
+/// can be changed without recompiling the function that makes the call
+///
+/// The slot is an ordinary word the call loads its target from, so there is
+/// no code to patch and no instruction cache to flush. Changing the target is
+/// a single atomic store, which other threads running the call see the next
+/// time they make it. The slot belongs to the context, so it stays valid even
+/// once the function making the call is gone.
+pub struct CallSite<'a> {
+    slot: *const AtomicUsize,
+    signature: jit_type_t,
+    marker: PhantomData<&'a ()>
+}
+impl<'a> CallSite<'a> {
+    fn slot(&self) -> &AtomicUsize {
+        unsafe { &*self.slot }
+    }
+    /// Make the call go to the function given, which must be compiled and
+    /// have a signature compatible with the one the call was made with
+    pub fn set_target(&self, func: &Func) {
+        if !func.is_compiled() {
+            panic!("Call site targets must be compiled")
+        }
+        let signature: &Ty = from_ptr(self.signature);
+        if let Err(err) = signature.check_signature(func.get_signature()) {
+            panic!("Call site target doesn't match the signature of the call: {}", err)
+        }
+        let closure = unsafe { jit_function_to_closure(func.into()) };
+        self.slot().store(closure as usize, Ordering::Release)
+    }
+    /// Make the call go to the native function at `address`, which must have
+    /// the signature the call was made with
+    pub fn set_native(&self, address: usize) {
+        self.slot().store(address, Ordering::Release)
+    }
+    /// Get the address the call currently goes to
+    pub fn get_target(&self) -> usize {
+        self.slot().load(Ordering::Acquire)
+    }
+}
+
+#[derive(Clone, Copy)]
+/// A frame pushed onto the shadow stack by `insn_shadow_push`
+pub struct ShadowFrame<'a> {
+    slot: &'a Val,
+    frame: &'a Val
+}
+
+/// Called when a guard made by `insn_guard` fails, with the id of the exit,
+/// a pointer to the live values captured by the guard and how many there are
+///
+/// Each value takes up 8 bytes, whatever its type. The bits returned are
+/// given as the result of the function that failed the guard.
+pub type GuardHandler = extern fn(usize, *const u64, usize) -> u64;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+/// Where a dynamic-language value keeps its type tag, and the tag
+/// `guard_param_type` expects it to have
+pub enum TypeTag {
+    /// The tag is the bits of the value itself picked out by `mask`, like
+    /// with low-bit tagging or NaN-boxing
+    Bits {
+        /// The bits of the value that make up the tag
+        mask: u64,
+        /// The tag expected in those bits
+        tag: u64
+    },
+    /// The value points to an object with its tag in the word at `offset`
+    Field {
+        /// Where the tag is in the object
+        offset: usize,
+        /// The tag expected there
+        tag: usize
+    }
+}
+
+/// Expands a pseudo-instruction registered with `register_op` into real
+/// instructions in the function given, with the arguments given to `insn_op`,
+/// giving its result if it has one
+///
+/// Expansions can make other pseudo-instructions with `insn_op`.
+pub type CustomOp = for<'a> fn(&UncompiledFunction<'a>, &[&'a Val]) -> Option<&'a Val>;
+
+#[derive(PartialEq)]
+/// A function which has not been compiled yet, so it can have instructions added to it.
+///
+/// A function persists for the lifetime of its containing context. This represents
+/// the function in the "building" state, where the user constructs instructions
+/// that represents the function body. Once the build process is complete, the
+/// user calls `function.compile()` to convert it into its executable form.
+pub struct UncompiledFunction<'a> {
+    _func: jit_function_t,
+    marker: PhantomData<&'a ()>,
+    owned: bool,
+    lifetime: Lifetime
+}
+impl<'a, 'b> From<&'a UncompiledFunction<'b>> for jit_function_t {
+    /// Convert to a native pointer
+    fn from(func: &'a UncompiledFunction<'b>) -> jit_function_t {
+        func._func
+    }
+}
+impl<'a, 'b> From<&'a mut UncompiledFunction<'b>> for jit_function_t {
+    /// Convert to a native pointer
+    fn from(func: &'a mut UncompiledFunction<'b>) -> jit_function_t {
+        func._func
+    }
+}
+impl<'a> From<UncompiledFunction<'a>> for jit_function_t {
+    /// Convert to a native pointer
+    fn from(func: UncompiledFunction<'a>) -> jit_function_t {
+        func._func
+    }
+}
+impl<'a> From<jit_function_t> for UncompiledFunction<'a> {
+    fn from(ptr: jit_function_t) -> UncompiledFunction<'a> {
+        UncompiledFunction {
+            _func: ptr,
+            marker: PhantomData,
+            owned: false,
+            lifetime: Lifetime::Abandon
+        }
+    }
+}
+/// This is LibJIT's own dump, which may contain addresses, so use
+/// `dump_stable` for output that should be reproducible
+impl<'a> fmt::Debug for UncompiledFunction<'a> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "{}", try!(util::dump(|fd| unsafe {
+            jit_dump_function(mem::transmute(fd), self.into(), ptr::null());
+        })))
+    }
+}
+
+impl<'a> Deref for UncompiledFunction<'a> {
+    type Target = Func;
+    fn deref(&self) -> &Func {
+        unsafe { mem::transmute(self._func) }
     }
 }
 impl<'a> DerefMut for UncompiledFunction<'a> {
@@ -218,7 +1367,8 @@ impl<'a> DerefMut for UncompiledFunction<'a> {
 impl<'a> Drop for UncompiledFunction<'a> {
     #[inline(always)]
     fn drop(&mut self) {
-        if self.owned {
+        if self.owned && self.lifetime == Lifetime::Abandon {
+            events::abandoned(self.into());
             unsafe {
                 jit_function_abandon(self.into());
             }
@@ -258,13 +1408,38 @@ impl<'a> UncompiledFunction<'a> {
                 signature.into()
             ));
             me.owned = true;
-            if cfg!(any(test, bench, ndebug)) {
-                me.set_recompilable();
-                me.set_optimization_level(UncompiledFunction::get_max_optimization_level());
-            }
+            me.apply_options();
             me
         }
     }
+    /// Create a new function with the lifetime policy given
+    ///
+    /// ```rust
+    /// use jit::*;
+    /// let mut ctx = Context::<()>::new();
+    /// let func = UncompiledFunction::new_with_lifetime(&mut ctx, &get::<fn()>(), Lifetime::ContextOwned);
+    /// assert_eq!(func.get_lifetime(), Lifetime::ContextOwned);
+    /// ```
+    pub fn new_with_lifetime<T>(context:&'a mut Context<T>, signature:&Ty, lifetime: Lifetime) -> UncompiledFunction<'a> {
+        let mut me = UncompiledFunction::new(context, signature);
+        me.lifetime = lifetime;
+        me
+    }
+    #[inline(always)]
+    /// Make a reference to this function that doesn't keep it alive
+    pub fn downgrade(&self) -> WeakFunction<'a> {
+        WeakFunction::new(self._func)
+    }
+    #[inline(always)]
+    /// Get what happens to this function if it's dropped before being compiled
+    pub fn get_lifetime(&self) -> Lifetime {
+        self.lifetime
+    }
+    #[inline(always)]
+    /// Set what happens to this function if it's dropped before being compiled
+    pub fn set_lifetime(&mut self, lifetime: Lifetime) {
+        self.lifetime = lifetime;
+    }
     #[inline(always)]
     /// Create a new function block and associate it with a JIT context.
     /// In addition, this function is nested inside the specified *parent*
@@ -273,10 +1448,10 @@ impl<'a> UncompiledFunction<'a> {
     ///
     /// The front end is responsible for ensuring that the nested function can
     /// never be called by anyone except its parent and sibling functions.
-    /// The front end is also responsible for ensuring that the nested function
-    /// is compiled before its parent.
+    /// The parent must be compiled before the nested function, which
+    /// `compile` checks.
     pub fn new_nested<T>(context:&'a mut Context<T>, signature: &Ty,
-                        parent: &'a UncompiledFunction<'a>) -> UncompiledFunction<'a> {
+                        parent: &UncompiledFunction<'a>) -> UncompiledFunction<'a> {
         unsafe {
             let mut me:UncompiledFunction = from_ptr_oom(jit_function_create_nested(
                 context.into(),
@@ -284,612 +1459,3090 @@ impl<'a> UncompiledFunction<'a> {
                 parent.into()
             ));
             me.owned = true;
-            if cfg!(any(test, bench, ndebug)) {
-                me.set_recompilable();
-                me.set_optimization_level(UncompiledFunction::get_max_optimization_level());
-            }
+            me.apply_options();
             me
         }
     }
-    #[inline(always)]
-    /// Make an instruction that converts the value to the type given
-    pub fn insn_convert(&self, v: &'a Val,
-                            t:&Ty, overflow_check:bool) -> &'a Val {
+    /// Create a new function on the same context as this one
+    ///
+    /// This is useful for building several functions at once, such as ones
+    /// that call each other.
+    pub fn sibling(&self, signature: &Ty) -> UncompiledFunction<'a> {
+        let me = self.bare(signature);
+        me.apply_options();
+        me
+    }
+    /// Create a new function on the same context as `stages` that calls each
+    /// of them in order, passing the result of each one to the next
+    ///
+    /// The new function takes the parameters of the first stage and returns
+    /// what the last one does. Every other stage must take one parameter, and
+    /// a result that isn't laid out like it is converted to it if both are
+    /// numbers, which wraps rather than checking for overflow.
+    ///
+    /// ```rust
+    /// use jit::*;
+    /// let mut ctx = Context::<()>::new();
+    /// let square = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+    /// square.insn_return(square.insn_mul(&square[0], &square[0]));
+    /// let half = square.sibling(&get::<fn(f64) -> f64>());
+    /// half.insn_return(half.insn_div(&half[0], half.insn_of(2.0f64)));
+    /// let pipeline = UncompiledFunction::compose(&[&*square, &*half]).unwrap();
+    /// pipeline.compile().with(|f: extern fn(i32) -> f64| assert_eq!(f(3), 4.5));
+    /// ```
+    pub fn compose(stages: &[&'a Func]) -> Result<UncompiledFunction<'a>, ComposeError> {
+        let (first, last) = match (stages.first(), stages.last()) {
+            (Some(first), Some(last)) => (*first, *last),
+            _ => return Err(ComposeError::Empty)
+        };
+        let ctx = unsafe { jit_function_get_context(first.into()) };
+        for (index, stage) in stages.iter().enumerate().skip(1) {
+            if unsafe { jit_function_get_context((*stage).into()) } != ctx {
+                return Err(ComposeError::Context(index))
+            }
+            let sig = stage.get_signature();
+            let count = sig.params().count();
+            if count != 1 {
+                return Err(ComposeError::Arity(index, count))
+            }
+            let (expected, got) = (sig.params().next().unwrap(), stages[index - 1].get_signature().get_return().unwrap());
+            let numbers = expected.is_primitive() && got.is_primitive() && !expected.is_pointer() && !got.is_pointer();
+            if got.get_kind() == TypeKind::Void || !(numbers || same_layout(expected, got)) {
+                return Err(ComposeError::Mismatch(index, format!("{:?}", expected), format!("{:?}", got)))
+            }
+        }
+        let ret = last.get_signature().get_return().unwrap();
+        let mut params: Vec<&Ty> = first.get_signature().params().collect();
+        let sig = Type::new_signature(Abi::CDecl, ret, &mut params);
+        let first: jit_function_t = first.into();
+        let func = UncompiledFunction::from(first).sibling(&sig);
+        let mut args: Vec<&'a Val> = (0..params.len()).map(|index| {
+            let param: jit_value_t = (&func[index]).into();
+            from_ptr(param)
+        }).collect();
+        for (index, stage) in stages.iter().enumerate() {
+            if index > 0 {
+                let expected = stage.get_signature().params().next().unwrap();
+                if !same_layout(expected, args[0].get_type()) {
+                    args[0] = func.insn_convert(args[0], expected, false);
+                }
+            }
+            let result = func.insn_call(None, stage, None, &mut args, flags::CallFlags::empty());
+            args = vec![result];
+        }
+        if ret.get_kind() == TypeKind::Void {
+            func.insn_default_return();
+        } else {
+            func.insn_return(args[0]);
+        }
+        Ok(func)
+    }
+    /// Create a new function nested inside this one, on the same context
+    ///
+    /// This is like `new_nested`, but doesn't need the context to be borrowed
+    /// again while this function is being built.
+    ///
+    /// ```rust
+    /// use jit::*;
+    /// let mut ctx = Context::<()>::new();
+    /// let parent = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+    /// let local_var = parent.new_lvalue(typecs::get_int());
+    /// let local = local_var.get_local().unwrap();
+    /// parent.insn_store(&local_var, &parent[0]);
+    /// let child = parent.nest(&get::<fn() -> i32>());
+    /// let ptr = child.insn_import(local);
+    /// child.insn_return(child.insn_load_relative(ptr, 0, typecs::get_int()));
+    /// let result = parent.insn_call(None, &child, None, &mut [], flags::CallFlags::empty());
+    /// parent.insn_return(result);
+    /// assert_eq!(child.can_compile(), Err(CompileError::ParentNotCompiled));
+    /// let parent = parent.compile();
+    /// child.compile();
+    /// parent.with(|func: extern fn(i32) -> i32| assert_eq!(func(5), 5));
+    /// ```
+    pub fn nest(&self, signature: &Ty) -> UncompiledFunction<'a> {
         unsafe {
-            from_ptr(jit_insn_convert(
-                self.into(),
-                v.into(),
-                t.into(),
-                overflow_check as c_int
-            ))
+            let mut me:UncompiledFunction = from_ptr_oom(jit_function_create_nested(
+                jit_function_get_context(self.into()),
+                signature.into(),
+                self.into()
+            ));
+            me.owned = true;
+            me.apply_options();
+            me
         }
     }
-    #[inline(always)]
-    /// Make an instructional representation of a Rust value
+    /// Make `env` the environment of this function, the pointer to the state
+    /// of the interpreter or VM it is generated for, which helpers made with
+    /// `helper` are given by `insn_call_env`
+    ///
+    /// `env` can be a parameter, or the address of a global from
+    /// `insn_global_address` made at the start of the function.
+    ///
     /// ```rust
     /// use jit::*;
     /// let mut ctx = Context::<()>::new();
-    /// let func = UncompiledFunction::new(&mut ctx, &get::<fn() -> i32>());
-    /// func.insn_return(func.insn_of(42i32));
+    /// let func = UncompiledFunction::new(&mut ctx, &get::<fn(&'static i32, i32) -> i32>());
+    /// func.bind_env(&func[0]);
+    /// let scale = func.helper(&get::<fn(i32) -> i32>());
+    /// let factor = scale.insn_load_relative(scale.get_env().unwrap(), 0, &get::<i32>());
+    /// scale.insn_return(scale.insn_mul(factor, &scale[1]));
+    /// func.insn_return(func.insn_call_env(None, &scale, &mut [&func[1]], flags::CallFlags::empty()));
+    /// scale.compile();
+    /// let func: extern fn(*const i32, i32) -> i32 = func.compile().closure();
+    /// assert_eq!(func(&3, 14), 42);
     /// ```
-    pub fn insn_of<T>(&self, val:T) -> &'a Val where T:Compile<'a> {
-        val.compile(self)
+    pub fn bind_env(&self, env: &'a Val) {
+        if !env.get_type().is_pointer() {
+            panic!("The environment of a function should be a pointer, got {:?}", env.get_type())
+        }
+        let env: jit_value_t = env.into();
+        FunctionData::with(self.into(), |data| data.env = Some(env))
     }
-    #[inline(always)]
-    /// Notify the function building process that this function has a catch block
-    /// in it. This must be called before any code that is part of a try block
-    pub fn insn_uses_catcher(&self) {
-        unsafe {
-            jit_insn_uses_catcher(self.into());
+    /// Get the environment of this function set with `bind_env`, if it has
+    /// one
+    pub fn get_env(&self) -> Option<&'a Val> {
+        FunctionData::with(self.into(), |data| data.env).map(from_ptr)
+    }
+    /// Make a helper on the same context as this function that takes its
+    /// environment as a hidden first parameter, which is the helper's own
+    /// environment, followed by the parameters of `signature`
+    ///
+    /// The parameters of `signature` are at indices from 1 in the helper.
+    /// This panics if this function has no environment.
+    pub fn helper(&self, signature: &Ty) -> UncompiledFunction<'a> {
+        let env = match self.get_env() {
+            Some(env) => env,
+            None => panic!("Helpers can only be made for functions with an environment - use bind_env first")
+        };
+        let mut params: Vec<&Ty> = vec![env.get_type()];
+        params.extend(signature.params());
+        let abi = unsafe { mem::transmute(jit_type_get_abi(signature.into())) };
+        let ret = signature.get_return().unwrap_or(consts::get_void());
+        let helper = self.sibling(&Type::new_signature(abi, ret, &mut params));
+        helper.bind_env(&helper[0]);
+        helper
+    }
+    /// Make an instruction that calls `func`, a helper made with `helper`,
+    /// with this function's environment followed by `args`
+    pub fn insn_call_env(&self, name: Option<&str>, func: &Func, args: &mut [&'a Val], flags: flags::CallFlags) -> &'a Val {
+        let env = match self.get_env() {
+            Some(env) => env,
+            None => panic!("insn_call_env was used in a function with no environment - use bind_env first")
+        };
+        let mut env_args = vec![env];
+        env_args.extend(args.iter().cloned());
+        self.insn_call(name, func, None, &mut env_args, flags)
+    }
+    /// Apply the options of the context this function was made on
+    fn apply_options(&self) {
+        events::created(self.into());
+        let options = ContextData::with_func(self.into(), |data| data.options);
+        if cfg!(any(test, bench, ndebug)) {
+            self.set_recompilable();
+            self.set_optimization_level(UncompiledFunction::get_max_optimization_level());
         }
+        if let Some(level) = options.optimization_level {
+            self.set_optimization_level(level);
+        }
+        if ContextData::with_func(self.into(), |data| data.invalidation) {
+            self.check_stale();
+        }
+        if ContextData::with_func(self.into(), |data| data.coverage) {
+            FunctionData::with(self.into(), |data| data.coverage = Some(Vec::new()));
+            self.count_block();
+        }
+        if ContextData::with_func(self.into(), |data| data.call_counts) {
+            let counter = FunctionData::with(self.into(), |data| {
+                data.calls = Some(Box::new(0));
+                &**data.calls.as_ref().unwrap() as *const usize as jit_nint
+            });
+            self.increment_counter(counter);
+        }
+        if let Some((enter, exit)) = ContextData::with_func(self.into(), |data| data.hooks) {
+            FunctionData::with(self.into(), |data| data.exit_hook = Some(exit));
+            // leaving by an exception has to call the exit hook too, which
+            // the catch block added by `finish_cleanups` does
+            self.insn_uses_catcher();
+            self.call_hook(enter);
+        }
+        self.probe_stack(None);
     }
-    #[inline(always)]
-    /// Make an instruction to throw an exception from the function with the value given
-    pub fn insn_throw(&self, retval: &'a Val) {
-        unsafe {
-            jit_insn_throw(self.into(), retval.into());
+    /// Make instructions that call the context's overflow handler if there is
+    /// less than the guard's headroom plus `size` bytes of stack left, if the
+    /// context has a stack guard
+    fn probe_stack(&self, size: Option<&'a Val>) {
+        let guard = match ContextData::with_func(self.into(), |data| data.stack_guard) {
+            Some(guard) => guard,
+            None => return
+        };
+        let usize_t = get::<usize>();
+        let left = self.insn_call_native0(Some("stack_left"), stack::left, &get::<fn() -> usize>(),
+                                          flags::CallFlags::NO_THROW);
+        let mut needed = self.insn_of(guard.headroom);
+        if let Some(size) = size {
+            needed = self.insn_add(needed, self.insn_convert(size, &usize_t, false));
         }
+        let mut enough = Label::new(self);
+        self.insn_branch_if_not(self.insn_lt(left, needed), &mut enough);
+        let id = self.insn_of(self._func as usize);
+        self.insn_call_native1(Some("stack_overflow"), guard.handler, &get::<fn(usize)>(), [id],
+                               flags::CallFlags::NO_THROW);
+        self.insn_label(&mut enough);
     }
-    #[inline(always)]
-    /// Make an instruction that will return from the function with the value given
-    pub fn insn_return(&self, retval: &'a Val) {
-        unsafe {
-            jit_insn_return(self.into(), retval.into());
+    /// Make instructions that raise a stale code exception if the function
+    /// has been invalidated
+    fn check_stale(&self) {
+        let flag = FunctionData::with(self.into(), |data| {
+            data.stale = Some(Arc::new(AtomicUsize::new(0)));
+            &**data.stale.as_ref().unwrap() as *const AtomicUsize as usize
+        });
+        let flag: &'a Val = unsafe {
+            from_ptr(jit_value_create_nint_constant(self.into(), consts::get_void_ptr().into(), flag as jit_nint))
+        };
+        let stale = self.insn_load_relative(flag, 0, &consts::get_nuint());
+        let mut fresh = Label::new(self);
+        self.insn_branch_if_not(stale, &mut fresh);
+        self.insn_call_native0(Some("stale_code"), exception::stale_code, &get::<fn()>(),
+                               flags::CallFlags::empty());
+        self.insn_label(&mut fresh);
+    }
+    /// Make instructions that count how many times the current block runs, if
+    /// coverage is enabled
+    fn count_block(&self) {
+        let counter = FunctionData::with(self.into(), |data| data.coverage.as_mut().map(|counts| {
+            counts.push(Box::new(0));
+            &**counts.last().unwrap() as *const usize as jit_nint
+        }));
+        if let Some(address) = counter {
+            self.increment_counter(address);
         }
     }
-    #[inline(always)]
-    /// Return from the function
-    pub fn insn_default_return(&self) {
+    /// Make instructions that add one to the counter at `address`
+    fn increment_counter(&self, address: jit_nint) {
         unsafe {
-            jit_insn_default_return(self.into());
+            let func = self.into();
+            let nuint = consts::get_nuint().into();
+            let ptr = jit_value_create_nint_constant(func, consts::get_void_ptr().into(), address);
+            let count = jit_insn_load_relative(func, ptr, 0, nuint);
+            let one = jit_value_create_nint_constant(func, nuint, 1);
+            jit_insn_store_relative(func, ptr, 0, jit_insn_add(func, count, one));
         }
     }
-    #[inline(always)]
-    /// Make an instruction that multiplies the values
-    pub fn insn_mul(&self, v1: &'a Val, v2: &'a Val) -> &'a Val {
-        self.insn_binop(v1, v2, jit_insn_mul)
+    /// Make an instruction that calls the hook given with this function's id
+    fn call_hook(&self, hook: FunctionHook) {
+        let id = self.insn_of(self._func as usize);
+        self.insn_call_native1(Some("function_hook"), hook, &get::<fn(usize)>(), [id], flags::CallFlags::NO_THROW);
     }
-    #[inline(always)]
-    /// Make an instruction that multiplies the values and throws upon overflow
-    pub fn insn_mul_ovf(&self, v1: &'a Val, v2: &'a Val) -> &'a Val {
-        self.insn_binop(v1, v2, jit_insn_mul_ovf)
+    /// Check if `before_leaving` has anything to do
+    fn has_exit_work(&self) -> bool {
+        FunctionData::with(self.into(), |data| data.exit_hook.is_some() || data.pin_roots.is_some())
     }
-    #[inline(always)]
-    /// Make an instruction that adds the values
-    ///
-    /// You can also just use `v1 + v2` in your code instead of running this method,
-    /// `&Val` has the `Add` trait implemented so it can be added with normal operators.
-    pub fn insn_add(&self, v1: &'a Val, v2: &'a Val) -> &'a Val {
-        self.insn_binop(v1, v2, jit_insn_add)
+    /// Call the exit hook, if there is one, and take the values this function
+    /// pinned off the stack map
+    fn before_leaving(&self) {
+        let (exit, roots) = FunctionData::with(self.into(), |data| (data.exit_hook, data.pin_roots));
+        if let Some(exit) = exit {
+            self.call_hook(exit);
+        }
+        if let Some((slot, base)) = roots {
+            let (slot, base): (&'a Val, &'a Val) = (from_ptr(slot), from_ptr(base));
+            self.insn_store_relative(slot, 0, base);
+        }
     }
-    #[inline(always)]
-    /// Make an instruction that adds the values and throws upon overflow
-    pub fn insn_add_ovf(&self, v1: &'a Val, v2: &'a Val) -> &'a Val {
-        self.insn_binop(v1, v2, jit_insn_add_ovf)
+    /// Check if the types of instructions made on this function should be
+    /// checked, which they aren't once it is poisoned
+    fn is_validating(&self) -> bool {
+        cfg!(not(ndebug)) && ContextData::with_func(self.into(), |data| data.options.validation) &&
+            !self.is_poisoned()
     }
-    #[inline(always)]
-    /// Make an instruction that subtracts the second value from the first
+    /// Check if something went wrong while building this function, on a
+    /// context that defers errors
     ///
-    /// You can also just use `v1 - v2` in your code instead of running this method,
-    /// `&Val` has the `Sub` trait implemented so it can be subtracted with normal operators.
-    pub fn insn_sub(&self, v1: &'a Val, v2: &'a Val) -> &'a Val {
-        self.insn_binop(v1, v2, jit_insn_sub)
+    /// Once a function is poisoned, instructions that would work something
+    /// out just give a placeholder, and compiling it gives the first error.
+    ///
+    /// ```rust
+    /// use jit::*;
+    /// let mut ctx = Context::<()>::new();
+    /// ctx.set_options(ContextOptions {
+    ///     defer_errors: true,
+    ///     .. ContextOptions::default()
+    /// });
+    /// let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+    /// func.set_current_location(1, 3, 9);
+    /// func.insn_sqrt(&func[0]);
+    /// assert!(func.is_poisoned());
+    /// let error = func.get_poison().unwrap();
+    /// assert_eq!(error.location.map(|loc| loc.line), Some(3));
+    /// match func.try_compile() {
+    ///     Err(CompileError::Poisoned(first)) => assert_eq!(first, error),
+    ///     _ => panic!("compiled a poisoned function")
+    /// }
+    /// ```
+    pub fn is_poisoned(&self) -> bool {
+        FunctionData::with(self.into(), |data| data.poison.is_some())
     }
-    #[inline(always)]
-    /// Make an instruction that subtracts the second value from the first and throws upon overflow
-    pub fn insn_sub_ovf(&self, v1: &'a Val, v2: &'a Val) -> &'a Val {
-        self.insn_binop(v1, v2, jit_insn_sub_ovf)
+    /// Get the first thing that went wrong while building this function, if
+    /// its context defers errors and something did
+    pub fn get_poison(&self) -> Option<EmitError> {
+        FunctionData::with(self.into(), |data| data.poison.clone())
     }
-    #[inline(always)]
-    /// Make an instruction that divides the first number by the second
+    /// Set how much this function may have made in it before it is
+    /// poisoned, in place of the budget set on its context
     ///
-    /// You can also just use `v1 / v2` in your code instead of running this method,
-    /// `&Val` has the `Div` trait implemented so it can be divided with normal operators.
-    pub fn insn_div(&self, v1: &'a Val, v2: &'a Val) -> &'a Val {
-        self.insn_binop(v1, v2, jit_insn_div)
+    /// What has been made in it already counts against the new budget.
+    pub fn set_emit_budget(&self, budget: EmitBudget) {
+        FunctionData::with(self.into(), |data| data.budget = Some(budget))
     }
-    #[inline(always)]
-    /// Make an instruction that finds the remainder when the first number is
-    /// divided by the second
+    /// Get how much this function may have made in it
+    pub fn get_emit_budget(&self) -> EmitBudget {
+        FunctionData::with(self.into(), |data| data.budget)
+            .unwrap_or_else(|| ContextData::with_func(self.into(), |data| data.emit_budget))
+    }
+    /// Get the suspicious things found while validating this function, like
+    /// pointers from `insn_alloca` escaping the frame they are freed with
     ///
-    /// You can also just use `v1 % v2` in your code instead of running this method,
-    /// `&Val` has the `Rem` trait implemented so it can be done with normal operators.
-    pub fn insn_rem(&self, v1: &'a Val, v2: &'a Val) -> &'a Val {
-        self.insn_binop(v1, v2, jit_insn_rem)
+    /// ```rust
+    /// use jit::*;
+    /// let mut ctx = Context::<()>::new();
+    /// let func = UncompiledFunction::new(&mut ctx, &get::<fn() -> &'static u8>());
+    /// let buffer = func.insn_alloca(func.insn_of(16u32));
+    /// func.insn_return(buffer);
+    /// assert_eq!(func.get_warnings().len(), 1);
+    /// ```
+    pub fn get_warnings(&self) -> Vec<EmitWarning> {
+        FunctionData::with(self.into(), |data| data.warnings.clone())
     }
-    #[inline(always)]
-    /// Make an instruction that checks if the first value is lower than or
-    /// equal to the second
-    pub fn insn_leq(&self, v1: &'a Val, v2: &'a Val) -> &'a Val {
-        self.insn_binop(v1, v2, jit_insn_le)
+    /// Record that `message` describes something suspicious in the function
+    fn warn(&self, message: String) {
+        events::warning(self.into(), &message);
+        FunctionData::with(self.into(), |data| {
+            let location = data.locations.last().cloned();
+            data.warnings.push(EmitWarning {
+                message: message,
+                location: location
+            })
+        })
     }
-    #[inline(always)]
-    /// Make an instruction that checks if the first value is greater than or
-    /// equal to the second
-    pub fn insn_geq(&self, v1: &'a Val, v2: &'a Val) -> &'a Val {
-        self.insn_binop(v1, v2, jit_insn_ge)
+    /// Check if `value` is a pointer made by `insn_alloca`, or derived from
+    /// one, while validating
+    fn is_alloca(&self, value: &Val) -> bool {
+        let value: jit_value_t = value.into();
+        FunctionData::with(self.into(), |data| data.allocas.contains(&value))
     }
-    #[inline(always)]
-    /// Make an instruction that checks if the first value is lower than the second
-    pub fn insn_lt(&self, v1: &'a Val, v2: &'a Val) -> &'a Val {
-        self.insn_binop(v1, v2, jit_insn_lt)
+    /// Track `result` as derived from a pointer made by `insn_alloca` if any
+    /// of `sources` are, while validating
+    fn derive_alloca(&self, sources: &[&Val], result: &Val) {
+        if self.is_validating() && sources.iter().any(|source| self.is_alloca(source)) {
+            let result: jit_value_t = result.into();
+            FunctionData::with(self.into(), |data| data.allocas.push(result));
+        }
     }
-    #[inline(always)]
-    /// Make an instruction that checks if the first value is greater than the second
-    pub fn insn_gt(&self, v1: &'a Val, v2: &'a Val) -> &'a Val {
-        self.insn_binop(v1, v2, jit_insn_gt)
+    /// Get a value to give back in place of an instruction's result once the
+    /// function is poisoned
+    fn placeholder(&self, ty: &Ty) -> &'a Val {
+        unsafe {
+            from_ptr(jit_value_create(self.into(), ty.into()))
+        }
     }
-    #[inline(always)]
-    /// Make an instruction that checks if the values are equal
-    pub fn insn_eq(&self, v1: &'a Val, v2: &'a Val) -> &'a Val {
-        self.insn_binop(v1, v2, jit_insn_eq)
+    /// Give the value LibJIT made for an instruction, or poison the function
+    /// and give a placeholder if it couldn't make it
+    fn emitted(&self, value: jit_value_t, ty: &Ty) -> &'a Val {
+        if value.is_null() {
+            fail(self.into(), "LibJIT couldn't make an instruction".to_owned());
+            self.placeholder(ty)
+        } else {
+            from_ptr(value)
+        }
     }
-    #[inline(always)]
-    /// Make an instruction that checks if the values are not equal
-    pub fn insn_neq(&self, v1: &'a Val, v2: &'a Val) -> &'a Val {
-        self.insn_binop(v1, v2, jit_insn_ne)
+    /// Panic with what differs if a call made by `api` through the signature
+    /// `sig` could go wrong: when it calls a function with a signature that
+    /// doesn't match, when the number of arguments doesn't match, or when it
+    /// is a tail call from a function with a signature that doesn't match
+    fn check_call(&self, api: &str, sig: &Ty, callee: Option<&Ty>, args: usize, flags: flags::CallFlags) {
+        if !self.is_validating() {
+            return
+        }
+        if let Some(callee) = callee {
+            if let Err(err) = sig.check_signature(callee) {
+                panic!("Signature given to {} doesn't match the function called: {}", api, err)
+            }
+        }
+        let params = sig.params().count();
+        let vararg = unsafe { jit_type_get_abi(sig.into()) == Abi::VarArg as jit_abi_t };
+        if args != params && !(vararg && args > params) {
+            panic!("Call made by {} should have {} arguments, got {}", api, params, args)
+        }
+        if flags.contains(flags::CallFlags::TAIL) {
+            if let Err(err) = self.get_signature().check_signature(sig) {
+                panic!("Tail call made by {} doesn't match the signature of the caller: {}", api, err)
+            }
+        }
     }
-    #[inline(always)]
-    /// Make an instruction that performs a bitwise and on the two values
+    /// Add the context's default call flags to the flags given
     ///
-    /// You can also just use `v1 & v2` in your code instead of running this method,
-    /// `&Val` has the `BitAnd` trait implemented so it can be done with normal operators.
-    pub fn insn_and(&self, v1: &'a Val, v2: &'a Val) -> &'a Val {
-        self.insn_binop(v1, v2, jit_insn_and)
+    /// Tail calls are made as ordinary calls in functions with an exit hook
+    /// or pinned values, which `after_call` then returns after.
+    fn call_flags(&self, flags: flags::CallFlags) -> c_int {
+        let mut flags = ContextData::with_func(self.into(), |data| flags | data.options.call_flags);
+        if self.has_exit_work() {
+            flags.remove(flags::CallFlags::TAIL);
+        }
+        flags.bits()
     }
-    #[inline(always)]
-    /// Make an instruction that performs a bitwise or on the two values
-    ///
-    /// You can also just use `v1 | v2` in your code instead of running this method,
-    /// `&Val` has the `BitOr` trait implemented so it can be done with normal operators.
-    pub fn insn_or(&self, v1: &'a Val, v2: &'a Val) -> &'a Val {
-        self.insn_binop(v1, v2, jit_insn_or)
+    /// Return `result` straight after a call made with the flags given if it
+    /// was meant to be a tail call but `call_flags` made it an ordinary one,
+    /// so the exit hook runs and the pins are dropped once the callee has
+    /// returned
+    fn after_call(&self, flags: flags::CallFlags, result: &'a Val) -> &'a Val {
+        let tail = ContextData::with_func(self.into(), |data| (flags | data.options.call_flags).contains(flags::CallFlags::TAIL));
+        if tail && self.has_exit_work() {
+            if result.get_type().get_kind() == TypeKind::Void {
+                self.insn_default_return();
+            } else {
+                self.insn_return(result);
+            }
+        }
+        result
     }
     #[inline(always)]
-    /// Make an instruction that performs a bitwise xor on the two values
-    ///
-    /// You can also just use `v1 ^ v2` in your code instead of running this method,
-    /// `&Val` has the `BitXor` trait implemented so it can be done with normal operators.
-    pub fn insn_xor(&self, v1: &'a Val, v2: &'a Val) -> &'a Val {
-        self.insn_binop(v1, v2, jit_insn_xor)
+    /// Make an instruction that gets the address of `value`, a local variable
+    /// of a function this one is nested inside
+    pub fn insn_import(&self, value: &'a Val) -> &'a Val {
+        unsafe {
+            from_ptr(jit_insn_import(self.into(), value.into()))
+        }
     }
     #[inline(always)]
-    /// Make an instruction that performs a bitwise not on the two values
+    /// Make an instruction that converts the value to the type given
+    pub fn insn_convert(&self, v: &'a Val,
+                            t:&Ty, overflow_check:bool) -> &'a Val {
+        if !spend(self.into(), 1, 0, 0) {
+            return self.placeholder(t)
+        }
+        if !overflow_check {
+            if let Some(converted) = self.long_convert(v, t) {
+                return converted
+            }
+        }
+        unsafe {
+            from_ptr(jit_insn_convert(
+                self.into(),
+                v.into(),
+                t.into(),
+                overflow_check as c_int
+            ))
+        }
+    }
+    /// Make instructions that convert the number to the type given, clamping
+    /// it to the range of the type if it is an integer type and the number is
+    /// out of range, with NaN becoming zero
     ///
-    /// You can also just use `!value` in your code instead of running this method.
-    /// `&Val` has the `Not` trait implemented so it can be inversed with normal operators.
-    pub fn insn_not(&self, value: &'a Val) -> &'a Val {
-        self.insn_unop(value, jit_insn_not)
+    /// Conversions to floats are done like `insn_convert` does them.
+    ///
+    /// ```rust
+    /// use jit::*;
+    /// let mut ctx = Context::<()>::new();
+    /// let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> u8>());
+    /// func.insn_return(func.insn_convert_saturating(&func[0], &get::<u8>()));
+    /// func.compile().with(|clamp: extern fn(i32) -> u8| {
+    ///     assert_eq!(clamp(-5), 0);
+    ///     assert_eq!(clamp(100), 100);
+    ///     assert_eq!(clamp(1000), 255);
+    /// });
+    /// ```
+    pub fn insn_convert_saturating(&self, v: &'a Val, t: &Ty) -> &'a Val {
+        let from = v.get_type();
+        if !t.is_int() || !from.is_primitive() {
+            return self.insn_convert(v, t, false)
+        }
+        let (to_min, to_max) = int_bounds(t);
+        if from.is_int() {
+            let (from_min, from_max) = int_bounds(from);
+            let mut value = v;
+            if to_max < from_max {
+                value = self.insn_min(value, self.insn_int_const(from, to_max as i64));
+            }
+            if to_min > from_min {
+                value = self.insn_max(value, self.insn_int_const(from, to_min));
+            }
+            return self.insn_convert(value, t, false)
+        }
+        let f64_t = get::<f64>();
+        let double = self.insn_convert(v, &f64_t, false);
+        if t.get_size() == 8 {
+            // the range of 64-bit integers can't be given exactly as doubles
+            let no_throw = flags::CallFlags::NO_THROW;
+            let converted = if to_min < 0 {
+                self.insn_call_native1(Some("jit_f64_to_long"), wide::f64_to_long,
+                                       &get::<fn(f64) -> i64>(), [double], no_throw)
+            } else {
+                self.insn_call_native1(Some("jit_f64_to_ulong"), wide::f64_to_ulong,
+                                       &get::<fn(f64) -> u64>(), [double], no_throw)
+            };
+            return self.insn_convert(converted, t, false)
+        }
+        let result = Val::new(self, t);
+        self.insn_if_else(self.insn_is_nan(double), || {
+            self.store_local(result, self.insn_int_const(t, 0))
+        }, || {
+            let clamped = self.insn_max(double, self.insn_of(to_min as f64));
+            let clamped = self.insn_min(clamped, self.insn_of(to_max as f64));
+            self.store_local(result, self.insn_convert(clamped, t, false))
+        });
+        result
     }
-    #[inline(always)]
-    /// Make an instruction that performs a left bitwise shift on the first
-    /// value by the second value
+    /// Make instructions that convert the number to the type given, wrapping
+    /// it around the range of the type if it is an integer type, by keeping
+    /// the low bits of it
     ///
-    /// You can also just use `v1 << v2` in your code instead of running this method,
-    /// `&Val` has the `Shl` trait implemented so it can be shifted with normal operators.
-    pub fn insn_shl(&self, v1: &'a Val, v2: &'a Val) -> &'a Val {
-        self.insn_binop(v1, v2, jit_insn_shl)
+    /// Floats are truncated towards zero and saturated to the range of `i64`
+    /// first, with NaN becoming zero, so `-1.0` wraps to the biggest unsigned
+    /// integer. Conversions to floats are done like `insn_convert` does them.
+    ///
+    /// ```rust
+    /// use jit::*;
+    /// let mut ctx = Context::<()>::new();
+    /// let func = UncompiledFunction::new(&mut ctx, &get::<fn(f64) -> u8>());
+    /// func.insn_return(func.insn_convert_wrapping(&func[0], &get::<u8>()));
+    /// func.compile().with(|wrap: extern fn(f64) -> u8| {
+    ///     assert_eq!(wrap(300.5), 44);
+    ///     assert_eq!(wrap(-1.0), 255);
+    /// });
+    /// ```
+    pub fn insn_convert_wrapping(&self, v: &'a Val, t: &Ty) -> &'a Val {
+        if t.is_int() && v.get_type().is_float() {
+            let long = self.insn_convert_saturating(v, consts::get_long());
+            self.insn_convert(long, t, false)
+        } else {
+            self.insn_convert(v, t, false)
+        }
     }
     #[inline(always)]
-    /// Make an instruction that performs a right bitwise shift on the first
-    /// value by the second value
-    ///
-    /// You can also just use `v1 >> v2` in your code instead of running this method,
-    /// `&Val` has the `Shr` trait implemented so it can be shifted with normal operators.
-    pub fn insn_shr(&self, v1: &'a Val, v2: &'a Val) -> &'a Val {
-        self.insn_binop(v1, v2, jit_insn_shr)
+    /// Make a constant `nint`, which is as wide as a pointer on the target
+    pub fn insn_nint(&self, value: isize) -> &'a Val {
+        value.compile(self)
     }
-    /// Make an instruction that performs a right bitwise shift on the first
-    /// value by the second value
-    pub fn insn_ushr(&self, v1: &'a Val, v2: &'a Val) -> &'a Val {
-        self.insn_binop(v1, v2, jit_insn_ushr)
+    #[inline(always)]
+    /// Make a constant `nuint`, which is as wide as a pointer on the target
+    pub fn insn_nuint(&self, value: usize) -> &'a Val {
+        value.compile(self)
     }
     #[inline(always)]
-    /// Make an instruction that performs a negation on the value
+    /// Make an instruction that converts the integer or pointer `value` to a
+    /// `nint`, sign-extending it if it is a narrower signed integer
     ///
-    /// You can also just use `-value` in your code instead of running this method.
-    /// `&Val` has the `Neg` trait implemented so it can be negatedd with normal operators.
-    pub fn insn_neg(&self, value: &'a Val) -> &'a Val {
-        self.insn_unop(value, jit_insn_neg)
+    /// This is what offsets and indices should be converted to before being
+    /// added to a pointer, so they aren't truncated on 64-bit targets.
+    ///
+    /// ```rust
+    /// use jit::*;
+    /// let mut ctx = Context::<()>::new();
+    /// let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> isize>());
+    /// func.insn_return(func.insn_to_nint(&func[0]));
+    /// func.compile().with(|widen: extern fn(i32) -> isize| {
+    ///     assert_eq!(widen(-1), -1);
+    /// });
+    /// ```
+    pub fn insn_to_nint(&self, value: &'a Val) -> &'a Val {
+        self.insn_native(value, consts::get_nint())
     }
     #[inline(always)]
-    /// Make an instruction that duplicates the value given
-    ///
-    /// This is the same as load
-    pub fn insn_dup(&self, value: &'a Val) -> &'a Val {
-        unsafe {
-            let dup_value = jit_insn_load(self.into(), value.into());
-            from_ptr(dup_value)
+    /// Make an instruction that converts the integer or pointer `value` to a
+    /// `nuint`, zero-extending it if it is narrower
+    pub fn insn_to_nuint(&self, value: &'a Val) -> &'a Val {
+        self.insn_native(value, consts::get_nuint())
+    }
+    fn insn_native(&self, value: &'a Val, ty: &Ty) -> &'a Val {
+        if validating(value) {
+            let value_t = value.get_type();
+            if !value_t.is_int() && !value_t.is_pointer() {
+                invalid(value, format!("Value given to insn_native should be integer or pointer, got {:?}", value_t));
+            }
         }
+        self.insn_convert(value, ty, false)
     }
     #[inline(always)]
-    /// Make an instruction that loads the contents of `src` into a temporary
-    pub fn insn_load(&self, src: &'a Val) -> &'a Val {
-        self.insn_unop(src, jit_insn_load)
+    /// Make an instructional representation of a Rust value
+    /// ```rust
+    /// use jit::*;
+    /// let mut ctx = Context::<()>::new();
+    /// let func = UncompiledFunction::new(&mut ctx, &get::<fn() -> i32>());
+    /// func.insn_return(func.insn_of(42i32));
+    /// ```
+    pub fn insn_of<T>(&self, val:T) -> &'a Val where T:Compile<'a> {
+        spend(self.into(), 0, 1, 0);
+        val.compile(self)
     }
     #[inline(always)]
-    /// Make an instruction that loads a value of the given type from `value + offset`, where
-    /// `value` must be a pointer
-    pub fn insn_load_relative(&self, value: &'a Val, offset: usize, ty: &Ty) -> &'a Val {
-        if cfg!(not(ndebug)) && !value.get_type().is_pointer() {
-            panic!("Value given to insn_load_relative should be pointer, got {:?}", value.get_type());
-        }
+    /// Notify the function building process that this function has a catch block
+    /// in it. This must be called before any code that is part of a try block
+    pub fn insn_uses_catcher(&self) {
         unsafe {
-            from_ptr(jit_insn_load_relative(
-                self.into(),
-                value.into(),
-                offset as jit_nint,
-                ty.into()
-            ))
+            jit_insn_uses_catcher(self.into());
         }
     }
     #[inline(always)]
-    /// Make an instruction that stores the contents of `val` into `dest`, where `dest` is a
-    /// temporary value or local value
-    pub fn insn_store(&self, dest: &'a Val, val: &'a Val) {
+    /// Make an instruction to throw an exception from the function with the value given
+    pub fn insn_throw(&self, retval: &'a Val) {
         unsafe {
-            jit_insn_store(self.into(), dest.into(), val.into());
+            jit_insn_throw(self.into(), retval.into());
         }
     }
-    #[inline(always)]
-    /// Make an instruction that stores the `value` at the address `dest + offset`, where `dest`
-    /// must be a pointer
-    pub fn insn_store_relative(&self, dest: &'a Val, offset: usize, value: &'a Val) {
-        if cfg!(not(ndebug)) && !dest.get_type().is_pointer() {
-            panic!("Destination given to insn_store_relative should be pointer, got {:?}", value.get_type());
+    /// Keep `payload` alive for as long as the function, giving a constant
+    /// holding its address for the payload natives
+    fn payload_type(&self, payload: &Ty) -> &'a Val {
+        FunctionData::with(self.into(), |data| data.payload_types.push(payload.to_owned()));
+        let address: jit_type_t = payload.into();
+        self.insn_of(address as usize)
+    }
+    /// Make instructions that throw `value` as a payload of the type given,
+    /// which is copied so a catcher made with `insn_start_typed_catcher` can
+    /// get it back, or Rust code calling through `closure_result` sees it as
+    /// a `JitException::Typed`
+    ///
+    /// The payload must be an integer, a pointer or a struct, and `value`
+    /// must be laid out like it.
+    ///
+    /// ```rust
+    /// use jit::*;
+    /// let mut ctx = Context::<()>::new();
+    /// let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+    /// func.insn_throw_typed(func.insn_neg(&func[0]), &get::<i32>());
+    /// let call = func.compile().closure_result::<i32, i32>();
+    /// assert_eq!(call(7), Err(JitException::Typed(Payload::Code(-7))));
+    /// ```
+    pub fn insn_throw_typed(&self, value: &'a Val, payload: &Ty) {
+        if self.is_validating() {
+            if !(payload.is_int() || payload.is_pointer() || payload.is_struct()) || payload.get_size() == 0 {
+                return invalid(value, format!("Bad payload type for insn_throw_typed - expected an integer, pointer or struct, got {:?}", payload))
+            }
+            if !same_layout(value.get_type(), payload) {
+                return invalid(value, format!("Bad value for insn_throw_typed - expected {:?}, got {:?}", payload, value.get_type()))
+            }
         }
+        let copy = Val::new(self, payload);
+        self.store_local(copy, value);
+        let object = self.insn_call_native2(Some("stash_payload"), exception::stash_payload,
+                                            &get::<fn(usize, usize) -> usize>(),
+                                            [self.payload_type(payload), self.address_of_local(copy)],
+                                            flags::CallFlags::NO_THROW);
+        self.insn_throw(object);
+    }
+    /// Start the catch block of the function like `insn_start_catcher`, and
+    /// get the payload of the type given that was thrown with
+    /// `insn_throw_typed`
+    ///
+    /// Anything else that is thrown, including payloads of other types, is
+    /// thrown on to the caller before the rest of the catch block runs.
+    ///
+    /// ```rust
+    /// use jit::*;
+    /// let mut ctx = Context::<()>::new();
+    /// let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+    /// func.insn_uses_catcher();
+    /// func.insn_throw_typed(func.insn_mul(&func[0], func.insn_of(10i32)), &get::<i32>());
+    /// let code = func.insn_start_typed_catcher(&get::<i32>());
+    /// func.insn_return(func.insn_add(code, func.insn_of(1i32)));
+    /// func.compile().with(|code: extern fn(i32) -> i32| assert_eq!(code(4), 41));
+    /// ```
+    pub fn insn_start_typed_catcher(&self, payload: &Ty) -> &'a Val {
+        let object = self.insn_start_catcher();
+        let object = self.insn_convert(object, &get::<usize>(), false);
+        let address = self.insn_call_native2(Some("payload_address"), exception::payload_address,
+                                             &get::<fn(usize, usize) -> usize>(),
+                                             [object, self.payload_type(payload)],
+                                             flags::CallFlags::NO_THROW);
+        let mut caught = Label::new(self);
+        self.insn_branch_if(address, &mut caught);
+        self.insn_rethrow_unhandled();
+        self.insn_label(&mut caught);
+        self.insn_load_relative(address, 0, payload)
+    }
+    #[inline(always)]
+    /// Make an instruction that gets the exception that was last thrown in
+    /// this thread
+    pub fn insn_thrown_exception(&self) -> &'a Val {
         unsafe {
-            jit_insn_store_relative(self.into(), dest.into(), offset as jit_nint, value.into());
+            from_ptr(jit_insn_thrown_exception(self.into()))
         }
     }
     #[inline(always)]
-    /// Make an instruction that sets a label
-    pub fn insn_label(&self, label: &mut Label<'a>) {
-        unsafe {
-            jit_insn_label(self.into(), &mut **label);
+    /// Start the catch block of the function, which is run when anything in
+    /// the function throws, and get the exception that was thrown
+    ///
+    /// `insn_uses_catcher` must have been called before anything that can
+    /// throw. There can only be one catch block, and it should end by
+    /// returning or with `insn_rethrow_unhandled`.
+    ///
+    /// ```rust
+    /// use jit::*;
+    /// let mut ctx = Context::<()>::new();
+    /// let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+    /// func.insn_uses_catcher();
+    /// func.insn_return(func.insn_div(func.insn_of(10i32), &func[0]));
+    /// func.insn_start_catcher();
+    /// func.insn_return(func.insn_of(-1i32));
+    /// func.compile().with(|div: extern fn(i32) -> i32| {
+    ///     assert_eq!(div(2), 5);
+    ///     assert_eq!(div(0), -1);
+    /// });
+    /// ```
+    pub fn insn_start_catcher(&self) -> &'a Val {
+        let exception = unsafe {
+            from_ptr(jit_insn_start_catcher(self.into()))
+        };
+        // clean up after the regions from `with_cleanup` that were left by
+        // the exception, from the innermost
+        let cleanups = FunctionData::with(self.into(), |data| {
+            data.catcher = true;
+            data.cleanups.clone()
+        });
+        for &(active, label) in cleanups.iter().rev() {
+            let active: &'a Val = from_ptr(active);
+            self.insn_if(active, || {
+                self.store_local(active, self.insn_of(0i32));
+                self.insn_call_finally(&mut Label::from(label));
+            });
         }
+        exception
     }
     #[inline(always)]
-    /// Make an instruction that branches to a certain label
-    pub fn insn_branch(&self, label: &mut Label<'a>) {
+    /// Make an instruction that throws the exception being caught on to the
+    /// caller, for the end of a catch block
+    pub fn insn_rethrow_unhandled(&self) {
+        self.before_leaving();
         unsafe {
-            jit_insn_branch(self.into(), &mut **label);
+            jit_insn_rethrow_unhandled(self.into());
         }
     }
     #[inline(always)]
-    /// Make an instruction that branches to a certain label if the value is true
-    pub fn insn_branch_if(&self, value: &'a Val, label: &mut Label<'a>) {
+    /// Start a finally block at `label`, which is run by `insn_call_finally`
+    /// and should end with `insn_return_from_finally`
+    pub fn insn_start_finally(&self, label: &mut Label<'a>) {
         unsafe {
-            jit_insn_branch_if(self.into(), value.into(), &mut **label);
+            jit_insn_start_finally(self.into(), &mut **label);
         }
+        self.count_block();
     }
     #[inline(always)]
-    /// Make an instruction that branches to a certain label if the value is false
-    pub fn insn_branch_if_not(&self, value: &'a Val, label: &mut Label<'a>) {
+    /// Make an instruction that returns from a finally block to where it was
+    /// called from
+    pub fn insn_return_from_finally(&self) {
         unsafe {
-            jit_insn_branch_if_not(self.into(), value.into(), &mut **label);
+            jit_insn_return_from_finally(self.into());
         }
     }
     #[inline(always)]
-    /// Make an instruction that branches to a label in the table
-    pub fn insn_jump_table(&self, value: &'a Val, labels: &mut [Label<'a>]) {
+    /// Make an instruction that runs the finally block at `label`
+    pub fn insn_call_finally(&self, label: &mut Label<'a>) {
         unsafe {
-            let mut native_labels: Vec<_> = labels.iter()
-                .map(|label| **label).collect();
-            jit_insn_jump_table(
-                self.into(),
-                value.into(),
-                native_labels.as_mut_ptr(),
-                labels.len() as c_uint
-            );
+            jit_insn_call_finally(self.into(), &mut **label);
         }
     }
     #[inline(always)]
-    /// Make an instruction that gets the inverse cosine of the number given
-    pub fn insn_acos(&self, v: &'a Val) -> &'a Val{
-        self.insn_unop(v, jit_insn_acos)
+    /// Start a filter block at `label`, which is run by `insn_call_filter`,
+    /// and get the value it was called with, which has the type given
+    pub fn insn_start_filter(&self, label: &mut Label<'a>, ty: &Ty) -> &'a Val {
+        let value = unsafe {
+            from_ptr(jit_insn_start_filter(self.into(), &mut **label, ty.into()))
+        };
+        self.count_block();
+        value
     }
     #[inline(always)]
-    /// Make an instruction that gets the inverse sine of the number given
-    pub fn insn_asin(&self, v: &'a Val) -> &'a Val{
-        self.insn_unop(v, jit_insn_asin)
+    /// Make an instruction that returns `value` from a filter block to where
+    /// it was called from
+    pub fn insn_return_from_filter(&self, value: &'a Val) {
+        unsafe {
+            jit_insn_return_from_filter(self.into(), value.into());
+        }
     }
     #[inline(always)]
-    /// Make an instruction that gets the inverse tangent of the number given
-    pub fn insn_atan(&self, v: &'a Val) -> &'a Val{
-        self.insn_unop(v, jit_insn_atan)
+    /// Make an instruction that runs the filter block at `label` with `value`
+    /// and gets the value it returns, which has the type given
+    pub fn insn_call_filter(&self, label: &mut Label<'a>, value: &'a Val, ty: &Ty) -> &'a Val {
+        unsafe {
+            from_ptr(jit_insn_call_filter(self.into(), &mut **label, value.into(), ty.into()))
+        }
     }
     #[inline(always)]
-    /// Make an instruction that gets the inverse tangent of the numbers given
-    pub fn insn_atan2(&self, v1: &'a Val, v2: &'a Val) -> &'a Val {
-        self.insn_binop(v1, v2, jit_insn_atan2)
+    /// Make an instruction that will return from the function with the value given
+    pub fn insn_return(&self, retval: &'a Val) {
+        if !spend(self.into(), 1, 0, 0) {
+            return
+        }
+        if self.is_validating() && self.is_alloca(retval) {
+            self.warn(format!("insn_return returns {:?}, which points to memory from insn_alloca that is freed when the function returns", retval));
+        }
+        self.before_leaving();
+        unsafe {
+            jit_insn_return(self.into(), retval.into());
+        }
     }
     #[inline(always)]
-    /// Make an instruction that finds the nearest integer above a number
-    pub fn insn_ceil(&self, v: &'a Val) -> &'a Val{
-        self.insn_unop(v, jit_insn_ceil)
+    /// Return from the function
+    pub fn insn_default_return(&self) {
+        if !spend(self.into(), 1, 0, 0) {
+            return
+        }
+        self.before_leaving();
+        unsafe {
+            jit_insn_default_return(self.into());
+        }
     }
     #[inline(always)]
-    /// Make an instruction that gets the consine of the number given
-    pub fn insn_cos(&self, v: &'a Val) -> &'a Val{
-        self.insn_unop(v, jit_insn_cos)
+    /// Make an instruction that returns the value of type `ty` that the
+    /// pointer `value` points to, for returning structs
+    pub fn insn_return_ptr(&self, value: &'a Val, ty: &Ty) {
+        expect!(insn_return_ptr, value, pointer);
+        self.before_leaving();
+        unsafe {
+            jit_insn_return_ptr(self.into(), value.into(), ty.into());
+        }
     }
     #[inline(always)]
-    /// Make an instruction that gets the hyperbolic consine of the number given
-    pub fn insn_cosh(&self, v: &'a Val) -> &'a Val{
-        self.insn_unop(v, jit_insn_cosh)
-    }
-    #[inline(always)]
-    /// Make an instruction that gets the natural logarithm rased to the power
-    /// of the number
-    pub fn insn_exp(&self, v: &'a Val) -> &'a Val{
-        self.insn_unop(v, jit_insn_exp)
+    /// Make an instruction that gets the call stack, for building stack
+    /// traces
+    ///
+    /// The value is a pointer to a trace that native code can take with
+    /// `CallStack::from_raw`, which frees it when done.
+    pub fn insn_get_call_stack(&self) -> &'a Val {
+        unsafe {
+            from_ptr(jit_insn_get_call_stack(self.into()))
+        }
     }
-    #[inline(always)]
-    /// Make an instruction that finds the nearest integer below a number
-    pub fn insn_floor(&self, v: &'a Val) -> &'a Val{
-        self.insn_unop(v, jit_insn_floor)
+    /// Make instructions that get a pointer to the function from this
+    /// function's context that called it, or null if it was called from
+    /// anywhere else, for things like a language's `caller()`
+    ///
+    /// The pointer is the same as the address of the caller's `Func`. This
+    /// walks the native stack, so it is slow compared to passing the caller
+    /// in.
+    pub fn insn_get_caller(&self) -> &'a Val {
+        let void_ptr = consts::get_void_ptr();
+        let this: jit_function_t = self.into();
+        let (ctx, this) = unsafe {
+            let ctx = jit_function_get_context(this);
+            (from_ptr(jit_value_create_nint_constant(this, void_ptr.into(), ctx as jit_nint)),
+             from_ptr(jit_value_create_nint_constant(this, void_ptr.into(), this as jit_nint)))
+        };
+        let sig = Type::new_signature(Abi::CDecl, void_ptr, &mut [void_ptr, void_ptr]);
+        let find:extern fn(*mut c_void, *mut c_void) -> *mut c_void = location::find_caller;
+        self.insn_call_native(Some("find_caller"), find as *mut c_void, &sig,
+                              &mut [ctx, this], flags::CallFlags::NO_THROW)
     }
     #[inline(always)]
-    /// Make an instruction that gets the natural logarithm of the number
-    pub fn insn_log(&self, v: &'a Val) -> &'a Val{
-        self.insn_unop(v, jit_insn_log)
+    /// Make an instruction that gets the address of a label as a pointer, for
+    /// computed jumps and exception tables
+    ///
+    /// If the context treats `jit_insn_address_of_label` as missing, or the
+    /// linked LibJIT doesn't have it, this poisons the function with an
+    /// `Unsupported` message, since there is no other way to do it.
+    pub fn insn_address_of_label(&self, label: &mut Label<'a>) -> &'a Val {
+        let ctx = unsafe { jit_function_get_context(self.into()) };
+        if let Err(error) = support::check_insn(ctx, "jit_insn_address_of_label") {
+            fail(self.into(), error.to_string());
+            return self.placeholder(&consts::get_void_ptr())
+        }
+        match support::address_of_label() {
+            Some(f) => unsafe { from_ptr(f(self.into(), &mut **label)) },
+            None => {
+                fail(self.into(), Unsupported { function: "jit_insn_address_of_label" }.to_string());
+                self.placeholder(&consts::get_void_ptr())
+            }
+        }
     }
     #[inline(always)]
-    /// Make an instruction that gets the base 10 logarithm of the number
-    pub fn insn_log10(&self, v: &'a Val) -> &'a Val{
-        self.insn_unop(v, jit_insn_log10)
+    /// Start a new basic block, without setting a label
+    pub fn insn_new_block(&self) {
+        unsafe {
+            jit_insn_new_block(self.into());
+        }
+        self.count_block();
     }
     #[inline(always)]
-    /// Make an instruction the gets the result of raising the first value to
-    /// the power of the second value
-    pub fn insn_pow(&self, v1: &'a Val, v2: &'a Val) -> &'a Val {
-        self.insn_binop(v1, v2, jit_insn_pow)
+    /// Mark the bytecode offset the instructions after this come from, which
+    /// is recorded in stack traces
+    pub fn insn_mark_offset(&self, offset: i32) {
+        unsafe {
+            jit_insn_mark_offset(self.into(), offset as jit_int);
+        }
     }
-    #[inline(always)]
-    /// Make an instruction the gets the result of rounding the value to the
-    /// nearest integer
-    pub fn insn_rint(&self, v: &'a Val) -> &'a Val{
-        self.insn_unop(v, jit_insn_rint)
+    /// Tag the instructions made after this with a location in the source
+    /// this function is compiled from, until another location is set
+    ///
+    /// The location is recorded through the bytecode offsets LibJIT keeps, so
+    /// it is found again by `Context::backtrace` for frames of this function
+    /// once it is compiled, and shown in `dump_stable`.
+    ///
+    /// ```rust
+    /// use jit::*;
+    /// let mut ctx = Context::<()>::new();
+    /// let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+    /// func.set_current_location(1, 10, 5);
+    /// let doubled = func.insn_add(&func[0], &func[0]);
+    /// func.set_current_location(1, 11, 5);
+    /// func.insn_return(doubled);
+    /// assert_eq!(func.get_locations().len(), 2);
+    /// assert!(func.dump_stable().contains("1:11:5"));
+    /// ```
+    pub fn set_current_location(&self, file: u32, line: u32, column: u32) {
+        let location = SourceLocation {
+            file: file,
+            line: line,
+            column: column
+        };
+        let offset = location::offset_of(self.into(), location);
+        self.insn_mark_offset(offset as i32);
     }
     #[inline(always)]
-    /// Make an instruction the gets the result of rounding the value to the
-    /// nearest integer
-    pub fn insn_round(&self, v: &'a Val) -> &'a Val{
-        self.insn_unop(v, jit_insn_round)
+    /// Make a breakpoint, which calls the context's debugger with the data
+    /// given when it is reached and debugging is on
+    pub fn insn_mark_breakpoint(&self, data1: isize, data2: isize) {
+        unsafe {
+            jit_insn_mark_breakpoint(self.into(), data1 as jit_nint, data2 as jit_nint);
+        }
     }
     #[inline(always)]
-    /// Make an instruction the gets the sine of the number
-    pub fn insn_sin(&self, v: &'a Val) -> &'a Val{
-        self.insn_unop(v, jit_insn_sin)
+    /// Make a breakpoint like `insn_mark_breakpoint`, with data that is only
+    /// known at run time
+    pub fn insn_mark_breakpoint_variable(&self, data1: &'a Val, data2: &'a Val) {
+        unsafe {
+            jit_insn_mark_breakpoint_variable(self.into(), data1.into(), data2.into());
+        }
     }
     #[inline(always)]
-    /// Make an instruction the gets the hyperbolic sine of the number
-    pub fn insn_sinh(&self, v: &'a Val) -> &'a Val{
-        self.insn_unop(v, jit_insn_sinh)
+    /// Make an instruction that multiplies the values
+    pub fn insn_mul(&self, v1: &'a Val, v2: &'a Val) -> &'a Val {
+        self.insn_binop(v1, v2, jit_insn_mul)
     }
     #[inline(always)]
-    /// Make an instruction the gets the square root of a number
-    pub fn insn_sqrt(&self, value: &'a Val) -> &'a Val {
-        expect!(insn_sqrt, value, float);
-        self.insn_unop(value, jit_insn_sqrt)
+    /// Make an instruction that multiplies the values and throws upon overflow
+    pub fn insn_mul_ovf(&self, v1: &'a Val, v2: &'a Val) -> &'a Val {
+        self.insn_binop(v1, v2, jit_insn_mul_ovf)
     }
     #[inline(always)]
-    /// Make an instruction the gets the tangent of a number
-    pub fn insn_tan(&self, v: &'a Val) -> &'a Val {
-        self.insn_unop(v, jit_insn_tan)
+    /// Make an instruction that adds the values
+    ///
+    /// `v1 + v2` on values makes `insn_arith_add`, which is the same as this
+    /// unless the function's overflow mode has been changed.
+    pub fn insn_add(&self, v1: &'a Val, v2: &'a Val) -> &'a Val {
+        self.insn_binop(v1, v2, jit_insn_add)
     }
     #[inline(always)]
-    /// Make an instruction the gets the hyperbolic tangent of a number
-    pub fn insn_tanh(&self, v: &'a Val) -> &'a Val{
-        self.insn_unop(v, jit_insn_tanh)
+    /// Make an instruction that adds the values and throws upon overflow
+    pub fn insn_add_ovf(&self, v1: &'a Val, v2: &'a Val) -> &'a Val {
+        self.insn_binop(v1, v2, jit_insn_add_ovf)
     }
     #[inline(always)]
-    /// Make an instruction that truncates the value
-    pub fn insn_trunc(&self, v: &'a Val) -> &'a Val {
-        self.insn_unop(v, jit_insn_trunc)
+    /// Make an instruction that subtracts the second value from the first
+    ///
+    /// `v1 - v2` on values makes `insn_arith_sub`, which is the same as this
+    /// unless the function's overflow mode has been changed.
+    pub fn insn_sub(&self, v1: &'a Val, v2: &'a Val) -> &'a Val {
+        self.insn_binop(v1, v2, jit_insn_sub)
     }
     #[inline(always)]
-    /// Make an instruction that checks if the number is NaN
-    pub fn insn_is_nan(&self, v: &'a Val) -> &'a Val {
-        expect!(insn_is_nan, v, float);
-        self.insn_unop(v, jit_insn_is_nan)
+    /// Make an instruction that subtracts the second value from the first and throws upon overflow
+    pub fn insn_sub_ovf(&self, v1: &'a Val, v2: &'a Val) -> &'a Val {
+        self.insn_binop(v1, v2, jit_insn_sub_ovf)
     }
-    #[inline(always)]
-    /// Make an instruction that checks if the number is finite
-    pub fn insn_is_finite(&self, v: &'a Val) -> &'a Val {
-        expect!(insn_is_finite, v, float);
-        self.insn_unop(v, jit_insn_is_finite)
+    /// Set how integer arithmetic made with `insn_arith_add`, `insn_arith_sub`,
+    /// `insn_arith_mul` or the operators on `&Val` overflows from now on
+    ///
+    /// ```rust
+    /// use jit::*;
+    /// let mut ctx = Context::<()>::new();
+    /// let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+    /// func.set_overflow(Overflow::Saturate);
+    /// func.insn_return(&func[0] * func.insn_of(1000i32));
+    /// func.compile().with(|func: extern fn(i32) -> i32| {
+    ///     assert_eq!(func(3), 3000);
+    ///     assert_eq!(func(1 << 30), i32::max_value());
+    ///     assert_eq!(func(-1 << 30), i32::min_value());
+    /// });
+    /// ```
+    pub fn set_overflow(&self, mode: Overflow) {
+        FunctionData::with(self.into(), |data| data.overflow = mode)
     }
-    #[inline(always)]
-    /// Make an instruction that checks if the number is  infinite
-    pub fn insn_is_inf(&self, v: &'a Val) -> &'a Val {
-        expect!(insn_is_inf, v, float);
-        self.insn_unop(v, jit_insn_is_inf)
+    /// Get how integer arithmetic made with `insn_arith_add`, `insn_arith_sub`,
+    /// `insn_arith_mul` or the operators on `&Val` overflows
+    pub fn get_overflow(&self) -> Overflow {
+        FunctionData::with(self.into(), |data| data.overflow)
     }
-    #[inline(always)]
-    /// Make an instruction that gets the absolute value of a number
-    pub fn insn_abs(&self, v: &'a Val) -> &'a Val {
-        expect!(insn_abs, v, primitive);
-        self.insn_unop(v, jit_insn_abs)
+    /// Make instructions that add the values, overflowing like the function's
+    /// overflow mode says if they are integers
+    pub fn insn_arith_add(&self, v1: &'a Val, v2: &'a Val) -> &'a Val {
+        self.insn_arith(v1, v2, Arith::Add)
     }
-    #[inline(always)]
-    /// Make an instruction that gets the smallest of two numbers
-    pub fn insn_min(&self, v1: &'a Val, v2: &'a Val) -> &'a Val {
-        expect!(insn_min, v1, v2, primitive);
-        self.insn_binop(v1, v2, jit_insn_min)
+    /// Make instructions that subtract the second value from the first,
+    /// overflowing like the function's overflow mode says if they are integers
+    pub fn insn_arith_sub(&self, v1: &'a Val, v2: &'a Val) -> &'a Val {
+        self.insn_arith(v1, v2, Arith::Sub)
     }
-    #[inline(always)]
-    /// Make an instruction that gets the biggest of two numbers
-    pub fn insn_max(&self, v1: &'a Val, v2: &'a Val) -> &'a Val {
-        expect!(insn_max, v1, v2, primitive);
-        self.insn_binop(v1, v2, jit_insn_max)
+    /// Make instructions that multiply the values, overflowing like the
+    /// function's overflow mode says if they are integers
+    pub fn insn_arith_mul(&self, v1: &'a Val, v2: &'a Val) -> &'a Val {
+        self.insn_arith(v1, v2, Arith::Mul)
     }
-    #[inline(always)]
-    /// Make an instruction that gets the sign of a number
-    pub fn insn_sign(&self, v: &'a Val) -> &'a Val {
-        expect!(insn_sign, v, primitive);
-        self.insn_unop(v, jit_insn_sign)
+    fn insn_arith(&self, v1: &'a Val, v2: &'a Val, op: Arith) -> &'a Val {
+        let (wrap, trap) = match op {
+            Arith::Add => (jit_insn_add as BinOp, jit_insn_add_ovf as BinOp),
+            Arith::Sub => (jit_insn_sub as BinOp, jit_insn_sub_ovf as BinOp),
+            Arith::Mul => (jit_insn_mul as BinOp, jit_insn_mul_ovf as BinOp)
+        };
+        let ints = v1.get_type().is_int() && v2.get_type().is_int();
+        match if ints { self.get_overflow() } else { Overflow::Wrap } {
+            Overflow::Wrap => self.insn_binop(v1, v2, wrap),
+            Overflow::Trap => self.insn_binop(v1, v2, trap),
+            Overflow::Saturate => {
+                let result = self.insn_saturating(v1, v2, op, wrap);
+                self.derive_alloca(&[v1, v2], result);
+                result
+            }
+        }
     }
-
-    /// Call the function, which may or may not be translated yet
-    pub fn insn_call(&self, name:Option<&str>, func:&Func, sig:Option<&Ty>,
-        args: &mut [&'a Val], flags: flags::CallFlags) -> &'a Val {
+    /// Make a constant of the integer type given
+    fn insn_int_const(&self, ty: &Ty, value: i64) -> &'a Val {
         unsafe {
-            let mut native_args:&mut [jit_value_t] = mem::transmute(args);
-            let c_name = name.map(|name| CString::new(name.as_bytes()).unwrap());
-            let sig = mem::transmute(sig);
-            from_ptr(jit_insn_call(
-                self.into(),
-                c_name.map(|name| name.as_bytes().as_ptr() as *mut c_char).unwrap_or(ptr::null_mut()),
-                func.into(), sig, native_args.as_mut_ptr(),
-                native_args.len() as c_uint,
-                flags.bits()
-            ))
+            from_ptr(if ty.get_size() == 8 {
+                jit_value_create_long_constant(self.into(), ty.into(), value)
+            } else {
+                jit_value_create_nint_constant(self.into(), ty.into(), value as jit_nint)
+            })
         }
     }
-    #[inline(always)]
-    /// Make an instruction that calls a function that has the signature given
-    /// with some arguments through a pointer to the fucntion
-    pub fn insn_call_indirect(&self, func:&'a Val, signature: &Ty,
-                               args: &mut [&'a Val], flags: flags::CallFlags) -> &'a Val {
-        unsafe {
-            let mut native_args: &mut [jit_value_t] = mem::transmute(args);
-            from_ptr(jit_insn_call_indirect(
-                self.into(),
-                func.into(),
-                signature.into(),
-                native_args.as_mut_ptr(),
-                native_args.len() as c_uint,
-                flags.bits()
-            ))
+    /// Make instructions that do the operation given on the integers and
+    /// clamp the result to the range of the type they are promoted to
+    fn insn_saturating(&self, v1: &'a Val, v2: &'a Val, op: Arith, wrap: BinOp) -> &'a Val {
+        let (t1, t2): (&Ty, &Ty) = unsafe {(
+            from_ptr(jit_type_promote_int(v1.get_type().into())),
+            from_ptr(jit_type_promote_int(v2.get_type().into()))
+        )};
+        let ty = if t2.get_size() > t1.get_size() { t2 } else { t1 };
+        let kind = ty.get_kind();
+        let signed = kind == TypeKind::Int || kind == TypeKind::NInt || kind == TypeKind::Long;
+        let bits = ty.get_size() * 8;
+        let (min, max) = if signed {
+            (-1i64 << (bits - 1), !(-1i64 << (bits - 1)))
+        } else if bits == 64 {
+            (0, -1)
+        } else {
+            (0, (1i64 << bits) - 1)
+        };
+        if bits < 64 {
+            let wide = if signed || op != Arith::Mul { consts::get_long() } else { consts::get_ulong() };
+            let a = self.insn_convert(v1, wide, false);
+            let b = self.insn_convert(v2, wide, false);
+            let result = self.insn_binop(a, b, wrap);
+            let result = self.insn_min(result, self.insn_int_const(wide, max));
+            let result = self.insn_max(result, self.insn_int_const(wide, min));
+            return self.insn_convert(result, ty, false)
+        }
+        let a = self.insn_convert(v1, ty, false);
+        let b = self.insn_convert(v2, ty, false);
+        let wrapped = self.insn_binop(a, b, wrap);
+        let result = Val::new(self, ty);
+        self.store_local(result, wrapped);
+        let zero = self.insn_int_const(ty, 0);
+        let min = self.insn_int_const(ty, min);
+        let max = self.insn_int_const(ty, max);
+        let saturate = |negative: &'a Val| {
+            self.insn_if_else(negative, || self.store_local(result, min), || self.store_local(result, max))
+        };
+        match op {
+            Arith::Add if signed => {
+                let flipped = self.insn_and(self.insn_xor(a, wrapped), self.insn_xor(b, wrapped));
+                self.insn_if(self.insn_lt(flipped, zero), || saturate(self.insn_lt(a, zero)))
+            },
+            Arith::Sub if signed => {
+                let flipped = self.insn_and(self.insn_xor(a, b), self.insn_xor(a, wrapped));
+                self.insn_if(self.insn_lt(flipped, zero), || saturate(self.insn_lt(a, zero)))
+            },
+            Arith::Add => self.insn_if(self.insn_lt(wrapped, a), || self.store_local(result, max)),
+            Arith::Sub => self.insn_if(self.insn_lt(a, b), || self.store_local(result, min)),
+            Arith::Mul if signed => self.insn_if(self.insn_neq(a, zero), || {
+                // Dividing the minimum by -1 overflows too, so check that case by hand
+                let minus_one = self.insn_int_const(ty, -1);
+                self.insn_if_else(self.insn_eq(a, minus_one), || {
+                    self.insn_if(self.insn_eq(b, min), || self.store_local(result, max))
+                }, || {
+                    let overflowed = self.insn_neq(self.insn_div(wrapped, a), b);
+                    self.insn_if(overflowed, || saturate(self.insn_lt(self.insn_xor(a, b), zero)))
+                })
+            }),
+            Arith::Mul => self.insn_if(self.insn_neq(a, zero), || {
+                let overflowed = self.insn_neq(self.insn_div(wrapped, a), b);
+                self.insn_if(overflowed, || self.store_local(result, max))
+            })
         }
+        result
     }
-    /// Make an instruction that calls a native function that has the signature
-    /// given with some arguments
-    fn insn_call_native(&self, name: Option<&str>,
-                        native_func: *mut c_void, signature: &Ty,
-                        args: &mut [&'a Val], flags: flags::CallFlags) -> &'a Val {
-        if cfg!(not(ndebug)) {
-            let name = name.unwrap_or("unnamed function");
-            if !signature.is_signature() {
-                panic!("Bad signature for {} - expected signature, got {:?}", name, signature)
-            }
-            let num_sig_args = signature.params().count();
-            if args.len() != num_sig_args {
-                panic!("Bad arguments to {} - expected {}, got {}", name, num_sig_args, args.len());
-            }
-            for (index, (arg, param)) in args.iter().zip(signature.params()).enumerate() {
-                let ty = arg.get_type();
-                if ty != param {
-                    panic!("Bad argument #{} to {} - expected {:?}, got {:?}", index, name, param, ty);
-                }
-            }
+    #[inline(always)]
+    /// Make an instruction that divides the first number by the second
+    ///
+    /// You can also just use `v1 / v2` in your code instead of running this method,
+    /// `&Val` has the `Div` trait implemented so it can be divided with normal operators.
+    pub fn insn_div(&self, v1: &'a Val, v2: &'a Val) -> &'a Val {
+        if let Some(result) = self.long_helper("div", v1, v2) {
+            return result
         }
-        unsafe {
-            let mut native_args:&mut [jit_value_t] = mem::transmute(args);
-            let c_name = name.map(|name| CString::new(name.as_bytes()).unwrap());
-            from_ptr(jit_insn_call_native(
-                self.into(),
-                c_name.map(|name| name.as_bytes().as_ptr() as *mut c_char).unwrap_or(ptr::null_mut()),
-                native_func,
-                signature.into(),
-                native_args.as_mut_ptr(),
-                native_args.len() as c_uint,
-                flags.bits()
-            ))
+        self.insn_binop(v1, v2, jit_insn_div)
+    }
+    #[inline(always)]
+    /// Make an instruction that finds the remainder when the first number is
+    /// divided by the second
+    ///
+    /// You can also just use `v1 % v2` in your code instead of running this method,
+    /// `&Val` has the `Rem` trait implemented so it can be done with normal operators.
+    pub fn insn_rem(&self, v1: &'a Val, v2: &'a Val) -> &'a Val {
+        if let Some(result) = self.long_helper("rem", v1, v2) {
+            return result
         }
+        self.insn_binop(v1, v2, jit_insn_rem)
     }
     #[inline(always)]
-    /// Make an instruction that calls a Rust function that has the signature
-    /// given with no arguments and expects a return value
-    pub fn insn_call_native0<R>(&self, name: Option<&str>,
-                            native_func: extern fn() -> R,
-                            signature: &Ty,
-                            flags: flags::CallFlags) -> &'a Val {
-        let func_ptr = unsafe { mem::transmute(native_func) };
-        self.insn_call_native(name, func_ptr, signature, &mut [], flags)
+    /// Make an instruction that finds the IEEE remainder when the first number
+    /// is divided by the second, rounding the quotient to the nearest integer
+    pub fn insn_rem_ieee(&self, v1: &'a Val, v2: &'a Val) -> &'a Val {
+        self.insn_binop(v1, v2, jit_insn_rem_ieee)
     }
     #[inline(always)]
-    /// Make an instruction that calls a Rust function that has the signature
-    /// given with a single argument and expects a return value
-    pub fn insn_call_native1<A,R>(&self, name: Option<&str>,
-                                native_func: extern fn(A) -> R,
-                                signature: &Ty,
-                                mut args: [&'a Val; 1],
-                                flags: flags::CallFlags) -> &'a Val {
-        let func_ptr = unsafe { mem::transmute(native_func) };
-        self.insn_call_native(name, func_ptr, signature, &mut args, flags)
+    /// Make an instruction that checks if the first value is lower than or
+    /// equal to the second
+    pub fn insn_leq(&self, v1: &'a Val, v2: &'a Val) -> &'a Val {
+        self.insn_binop(v1, v2, jit_insn_le)
     }
     #[inline(always)]
-    /// Make an instruction that calls a Rust function that has the signature
-    /// given with two arguments and expects a return value
-    pub fn insn_call_native2<A,B,R>(&self, name: Option<&str>,
-                                native_func: extern fn(A, B) -> R,
-                                signature: &Ty,
-                                mut args: [&'a Val; 2],
-                                flags: flags::CallFlags) -> &'a Val {
-        let func_ptr = unsafe { mem::transmute(native_func) };
-        self.insn_call_native(name, func_ptr, signature, &mut args, flags)
+    /// Make an instruction that checks if the first value is greater than or
+    /// equal to the second
+    pub fn insn_geq(&self, v1: &'a Val, v2: &'a Val) -> &'a Val {
+        self.insn_binop(v1, v2, jit_insn_ge)
     }
     #[inline(always)]
-    /// Make an instruction that calls a Rust function that has the signature
-    /// given with three arguments and expects a return value
-    pub fn insn_call_native3<A,B,C,R>(&self, name: Option<&str>,
-                                native_func: extern fn(A, B, C) -> R,
-                                signature: &Ty,
-                                mut args: [&'a Val; 3],
-                                flags: flags::CallFlags) -> &'a Val {
-        let func_ptr = unsafe { mem::transmute(native_func) };
-        self.insn_call_native(name, func_ptr, signature, &mut args, flags)
+    /// Make an instruction that checks if the first value is lower than the second
+    pub fn insn_lt(&self, v1: &'a Val, v2: &'a Val) -> &'a Val {
+        self.insn_binop(v1, v2, jit_insn_lt)
     }
     #[inline(always)]
-    /// Make an instruction that calls a Rust function that has the signature
-    /// given with four arguments and expects a return value
-    pub fn insn_call_native4<A,B,C,D,R>(&self, name: Option<&str>,
-                                native_func: extern fn(A, B, C, D) -> R,
-                                signature: &Ty,
-                                mut args: [&'a Val; 4],
-                                flags: flags::CallFlags) -> &'a Val {
-        let func_ptr = unsafe { mem::transmute(native_func) };
-        self.insn_call_native(name, func_ptr, signature, &mut args
-            , flags)
+    /// Make an instruction that checks if the first value is greater than the second
+    pub fn insn_gt(&self, v1: &'a Val, v2: &'a Val) -> &'a Val {
+        self.insn_binop(v1, v2, jit_insn_gt)
     }
     #[inline(always)]
-    /// Make an instruction that copies `size` bytes from the `source` address to the `dest` address
-    pub fn insn_memcpy(&self, dest: &'a Val, source: &'a Val, size: &'a Val) -> bool {
-        expect!(insn_memcpy, dest, source, size);
-        unsafe {
-            jit_insn_memcpy(self.into(), dest.into(), source.into(), size.into()) != 0
-        }
+    /// Make an instruction that checks if the values are equal
+    pub fn insn_eq(&self, v1: &'a Val, v2: &'a Val) -> &'a Val {
+        self.insn_binop(v1, v2, jit_insn_eq)
+    }
+    #[inline(always)]
+    /// Make an instruction that checks if the values are not equal
+    pub fn insn_neq(&self, v1: &'a Val, v2: &'a Val) -> &'a Val {
+        self.insn_binop(v1, v2, jit_insn_ne)
+    }
+    #[inline(always)]
+    /// Make an instruction that compares the values, giving -1, 0 or 1, and
+    /// -1 if either is NaN
+    pub fn insn_cmpl(&self, v1: &'a Val, v2: &'a Val) -> &'a Val {
+        self.insn_binop(v1, v2, jit_insn_cmpl)
+    }
+    #[inline(always)]
+    /// Make an instruction that compares the values, giving -1, 0 or 1, and
+    /// 1 if either is NaN
+    pub fn insn_cmpg(&self, v1: &'a Val, v2: &'a Val) -> &'a Val {
+        self.insn_binop(v1, v2, jit_insn_cmpg)
+    }
+    /// Make `value` into a boolean that is 1 when it isn't zero and 0 when it
+    /// is, like `insn_to_bool`
+    ///
+    /// The structured control flow helpers like `insn_if` and `insn_while`
+    /// normalize their conditions with this. LibJIT reuses the result of a
+    /// comparison without making any more instructions.
+    ///
+    /// ```rust
+    /// use jit::*;
+    /// let mut ctx = Context::<()>::new();
+    /// let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+    /// let odd = func.insn_normalize_bool(func.insn_and(&func[0], func.insn_of(1i32)));
+    /// let big = func.insn_normalize_bool(func.insn_and(&func[0], func.insn_of(6i32)));
+    /// func.insn_return((odd & big).get());
+    /// func.compile().with(|f: extern fn(i32) -> i32| {
+    ///     assert_eq!(f(3), 1);
+    ///     assert_eq!(f(4), 0);
+    ///     assert_eq!(f(5), 1);
+    /// });
+    /// ```
+    pub fn insn_normalize_bool(&self, value: &'a Val) -> Bool<'a> {
+        Bool { value: self.insn_to_bool(value) }
+    }
+    #[inline(always)]
+    /// Make an instruction that converts the value to a boolean, which is
+    /// true when it isn't zero
+    pub fn insn_to_bool(&self, value: &'a Val) -> &'a Val {
+        self.insn_unop(value, jit_insn_to_bool)
+    }
+    #[inline(always)]
+    /// Make an instruction that converts the value to a boolean, which is
+    /// true when it is zero
+    pub fn insn_to_not_bool(&self, value: &'a Val) -> &'a Val {
+        self.insn_unop(value, jit_insn_to_not_bool)
+    }
+    #[inline(always)]
+    /// Make an instruction that performs a bitwise and on the two values
+    ///
+    /// You can also just use `v1 & v2` in your code instead of running this method,
+    /// `&Val` has the `BitAnd` trait implemented so it can be done with normal operators.
+    pub fn insn_and(&self, v1: &'a Val, v2: &'a Val) -> &'a Val {
+        self.insn_binop(v1, v2, jit_insn_and)
+    }
+    #[inline(always)]
+    /// Make an instruction that performs a bitwise or on the two values
+    ///
+    /// You can also just use `v1 | v2` in your code instead of running this method,
+    /// `&Val` has the `BitOr` trait implemented so it can be done with normal operators.
+    pub fn insn_or(&self, v1: &'a Val, v2: &'a Val) -> &'a Val {
+        self.insn_binop(v1, v2, jit_insn_or)
+    }
+    #[inline(always)]
+    /// Make an instruction that performs a bitwise xor on the two values
+    ///
+    /// You can also just use `v1 ^ v2` in your code instead of running this method,
+    /// `&Val` has the `BitXor` trait implemented so it can be done with normal operators.
+    pub fn insn_xor(&self, v1: &'a Val, v2: &'a Val) -> &'a Val {
+        self.insn_binop(v1, v2, jit_insn_xor)
+    }
+    #[inline(always)]
+    /// Make an instruction that performs a bitwise not on the two values
+    ///
+    /// You can also just use `!value` in your code instead of running this method.
+    /// `&Val` has the `Not` trait implemented so it can be inversed with normal operators.
+    pub fn insn_not(&self, value: &'a Val) -> &'a Val {
+        self.insn_unop(value, jit_insn_not)
+    }
+    #[inline(always)]
+    /// Make an instruction that performs a left bitwise shift on the first
+    /// value by the second value
+    ///
+    /// You can also just use `v1 << v2` in your code instead of running this method,
+    /// `&Val` has the `Shl` trait implemented so it can be shifted with normal operators.
+    pub fn insn_shl(&self, v1: &'a Val, v2: &'a Val) -> &'a Val {
+        if let Some(result) = self.long_helper("shl", v1, v2) {
+            return result
+        }
+        self.insn_binop(v1, v2, jit_insn_shl)
+    }
+    #[inline(always)]
+    /// Make an instruction that performs a right bitwise shift on the first
+    /// value by the second value
+    ///
+    /// You can also just use `v1 >> v2` in your code instead of running this method,
+    /// `&Val` has the `Shr` trait implemented so it can be shifted with normal operators.
+    pub fn insn_shr(&self, v1: &'a Val, v2: &'a Val) -> &'a Val {
+        if let Some(result) = self.long_helper("shr", v1, v2) {
+            return result
+        }
+        self.insn_binop(v1, v2, jit_insn_shr)
+    }
+    /// Make an instruction that performs a right bitwise shift on the first
+    /// value by the second value
+    pub fn insn_ushr(&self, v1: &'a Val, v2: &'a Val) -> &'a Val {
+        if let Some(result) = self.long_helper("ushr", v1, v2) {
+            return result
+        }
+        self.insn_binop(v1, v2, jit_insn_ushr)
+    }
+    #[inline(always)]
+    /// Make an instruction that performs a signed right bitwise shift on the
+    /// first value by the second value, whatever its type
+    pub fn insn_sshr(&self, v1: &'a Val, v2: &'a Val) -> &'a Val {
+        if let Some(result) = self.long_helper("sshr", v1, v2) {
+            return result
+        }
+        self.insn_binop(v1, v2, jit_insn_sshr)
+    }
+    #[inline(always)]
+    /// Make an instruction that performs a negation on the value
+    ///
+    /// You can also just use `-value` in your code instead of running this method.
+    /// `&Val` has the `Neg` trait implemented so it can be negatedd with normal operators.
+    pub fn insn_neg(&self, value: &'a Val) -> &'a Val {
+        self.insn_unop(value, jit_insn_neg)
+    }
+    #[inline(always)]
+    /// Make an instruction that duplicates the value given
+    ///
+    /// This is the same as load
+    pub fn insn_dup(&self, value: &'a Val) -> &'a Val {
+        unsafe {
+            let dup_value = jit_insn_load(self.into(), value.into());
+            from_ptr(dup_value)
+        }
+    }
+    #[inline(always)]
+    /// Make an instruction that loads the contents of `src` into a temporary
+    pub fn insn_load(&self, src: &'a Val) -> &'a Val {
+        self.insn_unop(src, jit_insn_load)
+    }
+    #[inline(always)]
+    /// Make an instruction that loads a value of the given type from `value + offset`, where
+    /// `value` must be a pointer
+    pub fn insn_load_relative(&self, value: &'a Val, offset: usize, ty: &Ty) -> &'a Val {
+        if self.is_validating() && !value.get_type().is_pointer() {
+            fail(self.into(), format!("Value given to insn_load_relative should be pointer, got {:?}", value.get_type()));
+        }
+        if !spend(self.into(), 1, 0, 0) {
+            return self.placeholder(ty)
+        }
+        let (ptr, ty_ptr):(jit_value_t, jit_type_t) = (value.into(), ty.into());
+        let block = unsafe { jit_function_get_current(self.into()) };
+        let cached = FunctionData::with(self.into(), |data| {
+            if !data.noalias.contains(&ptr) {
+                return Err(false)
+            }
+            match data.loads.iter().find(|load| load.0 == block && load.1 == ptr && load.2 == offset && load.3 == ty_ptr) {
+                Some(load) => Ok(load.4),
+                None => Err(true)
+            }
+        });
+        let loaded = match cached {
+            Ok(loaded) => return from_ptr(loaded),
+            Err(cache) => unsafe {
+                let loaded = jit_insn_load_relative(self.into(), ptr, offset as jit_nint, ty_ptr);
+                if cache {
+                    FunctionData::with(self.into(), |data| data.loads.push((block, ptr, offset, ty_ptr, loaded)));
+                }
+                loaded
+            }
+        };
+        from_ptr(loaded)
+    }
+    /// Make instructions that store `value` in `dest`, converting it to the
+    /// type stored there if it is memory
+    pub fn insn_store(&self, dest: &LValue<'a>, value: &'a Val) {
+        match dest.get_memory() {
+            Some((ptr, offset)) => {
+                let ty = dest.get_type();
+                let value = if value.get_type() == ty { value } else { self.insn_convert(value, ty, false) };
+                self.insn_store_relative(ptr, offset, value)
+            },
+            None => self.store_local(dest.get_local().unwrap(), value)
+        }
+    }
+    #[inline(always)]
+    /// Make an instruction that stores the contents of `val` into `dest`, where `dest` is a
+    /// temporary value or local value
+    fn store_local(&self, dest: &'a Val, val: &'a Val) {
+        if !spend(self.into(), 1, 0, 0) {
+            return
+        }
+        self.derive_alloca(&[val], dest);
+        // a noalias parameter that is reassigned could point anywhere
+        let ptr:jit_value_t = dest.into();
+        let was_noalias = FunctionData::with(self.into(), |data| match data.noalias.iter().position(|&param| param == ptr) {
+            Some(index) => {
+                data.noalias.remove(index);
+                true
+            },
+            None => false
+        });
+        self.forget_loads(if was_noalias { None } else { Some(dest) });
+        unsafe {
+            jit_insn_store(self.into(), dest.into(), val.into());
+        }
+    }
+    /// Make a local variable of the type given, as storage that can be
+    /// assigned to and have its address taken
+    pub fn new_lvalue(&self, ty: &Ty) -> LValue<'a> {
+        lvalue::local(Val::new(self, ty))
+    }
+    /// Get the parameter at `index` as storage that can be assigned to and
+    /// have its address taken
+    pub fn param_lvalue(&self, index: usize) -> LValue<'a> {
+        let param = unsafe { from_ptr_opt(jit_value_get_param(self.into(), index as c_uint)) };
+        match param {
+            Some(param) => lvalue::local(param),
+            None => panic!("Function {:?} has no parameter {}", self, index)
+        }
+    }
+    /// Get the memory holding a value of type `ty` at `ptr + offset` as
+    /// storage that can be assigned to and have its address taken
+    pub fn deref_lvalue(&self, ptr: &'a Val, offset: usize, ty: &Ty) -> LValue<'a> {
+        expect!(deref_lvalue, ptr, pointer);
+        lvalue::memory(ptr, offset, ty)
+    }
+    /// Make instructions that read the value stored in `source` now
+    ///
+    /// The result is a copy, for local variables and parameters as well as
+    /// memory, so it doesn't change when `source` is stored to afterwards.
+    /// To use a local variable itself, which sees every store to it, like a
+    /// loop counter read at the top of a loop, use `LValue::get_local`.
+    ///
+    /// ```rust
+    /// use jit::*;
+    /// let mut ctx = Context::<()>::new();
+    /// let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+    /// let var = func.param_lvalue(0);
+    /// let before = func.insn_read(&var).get();
+    /// func.insn_store(&var, func.insn_of(5i32));
+    /// let after = var.get_local().unwrap();
+    /// func.insn_return(func.insn_sub(after, before));
+    /// func.compile().with(|func: extern fn(i32) -> i32| assert_eq!(func(2), 3));
+    /// ```
+    pub fn insn_read(&self, source: &LValue<'a>) -> RValue<'a> {
+        RValue::new(match source.get_memory() {
+            Some((ptr, offset)) => self.insn_load_relative(ptr, offset, source.get_type()),
+            None => self.insn_dup(source.get_local().unwrap())
+        })
+    }
+    #[inline(always)]
+    /// Make an instruction that stores the `value` at the address `dest + offset`, where `dest`
+    /// must be a pointer
+    pub fn insn_store_relative(&self, dest: &'a Val, offset: usize, value: &'a Val) {
+        if self.is_validating() && !dest.get_type().is_pointer() {
+            fail(self.into(), format!("Destination given to insn_store_relative should be pointer, got {:?}", dest.get_type()));
+        }
+        if !spend(self.into(), 1, 0, 0) {
+            return
+        }
+        // memory from insn_alloca can point to itself, but anything else may
+        // outlive the frame
+        if self.is_validating() && self.is_alloca(value) && !self.is_alloca(dest) {
+            self.warn(format!("insn_store_relative stores {:?}, which points to memory from insn_alloca, through {:?}, which may outlive the function's frame", value, dest));
+        }
+        self.forget_stored(dest);
+        unsafe {
+            jit_insn_store_relative(self.into(), dest.into(), offset as jit_nint, value.into());
+        }
+    }
+    /// Make an instruction that gets the address of the global called `name`
+    /// made on the context with `new_global`, and the global's type
+    fn global(&self, name: &str) -> (&'a Val, Type, bool) {
+        let found = ContextData::with_func(self.into(), |data| {
+            data.globals.get(name).map(|&(ref storage, ref ty)| (storage.as_ptr() as jit_nint, ty.clone(), storage.is_read_only()))
+        });
+        match found {
+            Some((address, ty, read_only)) => unsafe {
+                (from_ptr(jit_value_create_nint_constant(self.into(), consts::get_void_ptr().into(), address)), ty, read_only)
+            },
+            None => panic!("No global called {} has been made", name)
+        }
+    }
+    /// Make an instruction that gets the address of the global called `name`
+    /// made on the context with `new_global`
+    pub fn insn_global_address(&self, name: &str) -> &'a Val {
+        self.global(name).0
+    }
+    /// Make an instruction that loads the global called `name` made on the
+    /// context with `new_global`
+    pub fn insn_load_global(&self, name: &str) -> &'a Val {
+        let (address, ty, _) = self.global(name);
+        self.insn_load_relative(address, 0, &ty)
+    }
+    /// Make an instruction that stores `value` in the global called `name`
+    /// made on the context with `new_global`, converting it to the global's
+    /// type first if it isn't already
+    ///
+    /// This panics if the global was made read-only.
+    pub fn insn_store_global(&self, name: &str, value: &'a Val) {
+        let (address, ty, read_only) = self.global(name);
+        if read_only {
+            panic!("The global {} is read-only", name);
+        }
+        let value = if value.get_type() == &*ty { value } else { self.insn_convert(value, &ty, false) };
+        self.insn_store_relative(address, 0, value);
+    }
+    #[inline(always)]
+    /// Make an instruction that gets the address `value + offset`, where
+    /// `value` must be a pointer
+    pub fn insn_add_relative(&self, value: &'a Val, offset: usize) -> &'a Val {
+        expect!(insn_add_relative, value, pointer);
+        unsafe {
+            from_ptr(jit_insn_add_relative(self.into(), value.into(), offset as jit_nint))
+        }
+    }
+    /// Make an instruction that gets the address of the element at `index` of
+    /// the array of `elem_type` values that `ptr` points to, keeping the type
+    /// of `ptr`
+    ///
+    /// The index is widened to a `nint` before it is scaled by the size of
+    /// the element, so this is right on 64-bit targets, and a constant index
+    /// is folded into the offset.
+    ///
+    /// ```rust
+    /// use jit::*;
+    /// let mut ctx = Context::<()>::new();
+    /// let func = UncompiledFunction::new(&mut ctx, &get::<fn(&'static u8) -> &'static u8>());
+    /// let last = func.insn_ptr_add(&func[0], func.insn_of(2i32), &get::<i64>());
+    /// func.insn_return(last);
+    /// func.compile().with(|last: extern fn(&'static u8) -> &'static u8| {
+    ///     let longs = [1i64, 2, 3];
+    ///     let first = unsafe { &*(longs.as_ptr() as *const u8) };
+    ///     assert_eq!(last(first) as *const u8 as *const i64, &longs[2] as *const i64);
+    /// });
+    /// ```
+    pub fn insn_ptr_add(&self, ptr: &'a Val, index: &'a Val, elem_type: &Ty) -> &'a Val {
+        expect!(insn_ptr_add, ptr, pointer);
+        expect!(insn_ptr_add, index, int);
+        let size = elem_type.get_size();
+        if index.is_constant() {
+            let index = unsafe { jit_value_get_nint_constant(index.into()) } as isize;
+            return if index == 0 {
+                ptr
+            } else {
+                unsafe {
+                    from_ptr(jit_insn_add_relative(self.into(), ptr.into(), index * size as isize))
+                }
+            };
+        }
+        let index = self.insn_to_nint(index);
+        let offset = if size == 1 {
+            index
+        } else {
+            self.insn_mul(index, self.insn_nint(size as isize))
+        };
+        let address = self.insn_add(self.insn_to_nint(ptr), offset);
+        self.insn_convert(address, ptr.get_type(), false)
+    }
+    /// Make an instruction that counts the `elem_type` values between the
+    /// pointers `ptr1` and `ptr2`, giving `ptr1 - ptr2` as a `nint`
+    ///
+    /// The pointers must point into the same array.
+    pub fn insn_ptr_diff(&self, ptr1: &'a Val, ptr2: &'a Val, elem_type: &Ty) -> &'a Val {
+        expect!(insn_ptr_diff, ptr1, pointer);
+        expect!(insn_ptr_diff, ptr2, pointer);
+        let bytes = self.insn_sub(self.insn_to_nint(ptr1), self.insn_to_nint(ptr2));
+        match elem_type.get_size() {
+            0 | 1 => bytes,
+            size => self.insn_div(bytes, self.insn_nint(size as isize))
+        }
+    }
+    #[inline(always)]
+    /// Make an instruction that loads the element at `index` of the array of
+    /// `elem_type` values that `base` points to
+    pub fn insn_load_elem(&self, base: &'a Val, index: &'a Val, elem_type: &Ty) -> &'a Val {
+        expect!(insn_load_elem, base, pointer);
+        expect!(insn_load_elem, index, int);
+        if !spend(self.into(), 1, 0, 0) {
+            return self.placeholder(elem_type)
+        }
+        unsafe {
+            from_ptr(jit_insn_load_elem(self.into(), base.into(), index.into(), elem_type.into()))
+        }
+    }
+    #[inline(always)]
+    /// Make an instruction that gets the address of the element at `index` of
+    /// the array of `elem_type` values that `base` points to
+    pub fn insn_load_elem_address(&self, base: &'a Val, index: &'a Val, elem_type: &Ty) -> &'a Val {
+        expect!(insn_load_elem_address, base, pointer);
+        expect!(insn_load_elem_address, index, int);
+        unsafe {
+            from_ptr(jit_insn_load_elem_address(self.into(), base.into(), index.into(), elem_type.into()))
+        }
+    }
+    #[inline(always)]
+    /// Make an instruction that stores `value` as the element at `index` of
+    /// the array that `base` points to
+    pub fn insn_store_elem(&self, base: &'a Val, index: &'a Val, value: &'a Val) {
+        expect!(insn_store_elem, base, pointer);
+        expect!(insn_store_elem, index, int);
+        if !spend(self.into(), 1, 0, 0) {
+            return
+        }
+        self.forget_stored(base);
+        unsafe {
+            jit_insn_store_elem(self.into(), base.into(), index.into(), value.into());
+        }
+    }
+    #[inline(always)]
+    /// Make an instruction that throws a null pointer exception if `value` is
+    /// null
+    pub fn insn_check_null(&self, value: &'a Val) {
+        expect!(insn_check_null, value, pointer);
+        unsafe {
+            jit_insn_check_null(self.into(), value.into());
+        }
+    }
+    #[inline(always)]
+    /// Make an instruction that loads a small struct into registers, so it
+    /// can be passed around by value
+    pub fn insn_load_small(&self, value: &'a Val) -> &'a Val {
+        unsafe {
+            from_ptr(jit_insn_load_small(self.into(), value.into()))
+        }
+    }
+    #[inline(always)]
+    /// Make an instruction that writes a struct kept in registers back to its
+    /// place in memory, before its address is taken
+    pub fn insn_flush_struct(&self, value: &'a Val) {
+        unsafe {
+            jit_insn_flush_struct(self.into(), value.into());
+        }
+    }
+    /// Forget the loads that can be reused through `ptr`, or every pointer
+    fn forget_loads(&self, ptr: Option<&'a Val>) {
+        let ptr:Option<jit_value_t> = ptr.map(|ptr| ptr.into());
+        FunctionData::with(self.into(), |data| match ptr {
+            Some(ptr) => data.loads.retain(|load| load.1 != ptr),
+            None => data.loads.clear()
+        })
+    }
+    /// Forget the loads a store through `ptr` could change, which are only
+    /// the ones through `ptr` itself if it is a noalias parameter, but could
+    /// be any of them if it isn't, since it could have been worked out from
+    /// one
+    fn forget_stored(&self, ptr: &'a Val) {
+        let raw:jit_value_t = ptr.into();
+        let noalias = FunctionData::with(self.into(), |data| data.noalias.contains(&raw));
+        self.forget_loads(if noalias { Some(ptr) } else { None })
+    }
+    /// Mark the pointer parameter given as not aliasing any other pointer the
+    /// function uses, like C's `restrict`
+    ///
+    /// Loads through it are then reused within a block instead of being
+    /// loaded again, until something is stored through it, a call or bulk
+    /// memory operation is made, or something is stored through a pointer
+    /// that isn't another noalias parameter, as that could have been worked
+    /// out from this one. Reassigning it takes the mark away.
+    ///
+    /// ```rust
+    /// use jit::*;
+    /// let mut ctx = Context::<()>::new();
+    /// let func = UncompiledFunction::new(&mut ctx, &get::<fn(&'static i32, &'static i32) -> i32>());
+    /// func.set_param_noalias(0);
+    /// func.set_param_noalias(1);
+    /// let int = typecs::get_int();
+    /// let first = func.insn_load_relative(&func[0], 0, int);
+    /// func.insn_store_relative(&func[1], 0, func.insn_of(3i32));
+    /// let again = func.insn_load_relative(&func[0], 0, int);
+    /// assert!(first == again);
+    /// ```
+    pub fn set_param_noalias(&self, index: usize) {
+        let param = &self[index];
+        if self.is_validating() && !param.get_type().is_pointer() {
+            panic!("Parameter {} given to set_param_noalias should be a pointer, got {:?}", index, param.get_type());
+        }
+        let param:jit_value_t = param.into();
+        FunctionData::with(self.into(), |data| if !data.noalias.contains(&param) {
+            data.noalias.push(param)
+        })
+    }
+    /// Check if the parameter given was marked with `set_param_noalias`
+    pub fn is_param_noalias(&self, index: usize) -> bool {
+        let param:jit_value_t = (&self[index]).into();
+        FunctionData::with(self.into(), |data| data.noalias.contains(&param))
+    }
+    #[inline(always)]
+    /// Make an instruction that sets a label
+    pub fn insn_label(&self, label: &mut Label<'a>) {
+        if !spend(self.into(), 1, 0, 0) {
+            return
+        }
+        unsafe {
+            jit_insn_label(self.into(), &mut **label);
+        }
+        self.count_block();
+    }
+    #[inline(always)]
+    /// Make an instruction that branches to a certain label
+    pub fn insn_branch(&self, label: &mut Label<'a>) {
+        if !spend(self.into(), 1, 0, 0) {
+            return
+        }
+        unsafe {
+            jit_insn_branch(self.into(), &mut **label);
+        }
+    }
+    #[inline(always)]
+    /// Make an instruction that branches to a certain label if the value is true
+    pub fn insn_branch_if(&self, value: &'a Val, label: &mut Label<'a>) {
+        if !spend(self.into(), 1, 0, 0) {
+            return
+        }
+        unsafe {
+            jit_insn_branch_if(self.into(), value.into(), &mut **label);
+        }
+        self.count_block();
+    }
+    #[inline(always)]
+    /// Make an instruction that branches to a certain label if the value is false
+    pub fn insn_branch_if_not(&self, value: &'a Val, label: &mut Label<'a>) {
+        if !spend(self.into(), 1, 0, 0) {
+            return
+        }
+        unsafe {
+            jit_insn_branch_if_not(self.into(), value.into(), &mut **label);
+        }
+        self.count_block();
+    }
+    #[inline(always)]
+    /// Make an instruction that branches to a certain label if `v1` is equal to `v2`
+    ///
+    /// Branching straight on a comparison like this doesn't leave a boolean
+    /// temporary behind, so LibJIT can emit a single compare-and-jump.
+    ///
+    /// ```rust
+    /// use jit::*;
+    /// let mut ctx = Context::<()>::new();
+    /// let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+    /// let mut ten = Label::new(&func);
+    /// func.insn_branch_if_eq(&func[0], func.insn_of(10i32), &mut ten);
+    /// func.insn_return(func.insn_of(1i32));
+    /// func.insn_label(&mut ten);
+    /// func.insn_return(func.insn_of(0i32));
+    /// func.compile().with(|func: extern fn(i32) -> i32| {
+    ///     assert_eq!(func(10), 0);
+    ///     assert_eq!(func(30), 1);
+    /// });
+    /// ```
+    pub fn insn_branch_if_eq(&self, v1: &'a Val, v2: &'a Val, label: &mut Label<'a>) {
+        self.insn_branch_if_cmp(v1, v2, label, jit_insn_eq)
+    }
+    #[inline(always)]
+    /// Make an instruction that branches to a certain label if `v1` is not equal to `v2`
+    pub fn insn_branch_if_neq(&self, v1: &'a Val, v2: &'a Val, label: &mut Label<'a>) {
+        self.insn_branch_if_cmp(v1, v2, label, jit_insn_ne)
+    }
+    #[inline(always)]
+    /// Make an instruction that branches to a certain label if `v1` is less than `v2`
+    pub fn insn_branch_if_lt(&self, v1: &'a Val, v2: &'a Val, label: &mut Label<'a>) {
+        self.insn_branch_if_cmp(v1, v2, label, jit_insn_lt)
+    }
+    #[inline(always)]
+    /// Make an instruction that branches to a certain label if `v1` is less than or equal to `v2`
+    pub fn insn_branch_if_leq(&self, v1: &'a Val, v2: &'a Val, label: &mut Label<'a>) {
+        self.insn_branch_if_cmp(v1, v2, label, jit_insn_le)
+    }
+    #[inline(always)]
+    /// Make an instruction that branches to a certain label if `v1` is greater than `v2`
+    pub fn insn_branch_if_gt(&self, v1: &'a Val, v2: &'a Val, label: &mut Label<'a>) {
+        self.insn_branch_if_cmp(v1, v2, label, jit_insn_gt)
+    }
+    #[inline(always)]
+    /// Make an instruction that branches to a certain label if `v1` is greater than or equal to `v2`
+    pub fn insn_branch_if_geq(&self, v1: &'a Val, v2: &'a Val, label: &mut Label<'a>) {
+        self.insn_branch_if_cmp(v1, v2, label, jit_insn_ge)
+    }
+    /// Make a comparison and branch straight on its result
+    fn insn_branch_if_cmp(&self, v1: &'a Val, v2: &'a Val, label: &mut Label<'a>,
+                          cmp: unsafe extern "C" fn(jit_function_t, jit_value_t, jit_value_t) -> jit_value_t) {
+        expect!(insn_branch_if, v1, v2, primitive);
+        unsafe {
+            let cond = cmp(self.into(), v1.into(), v2.into());
+            jit_insn_branch_if(self.into(), cond, &mut **label);
+        }
+        self.count_block();
+    }
+    #[inline(always)]
+    /// Make an instruction that branches to a label in the table
+    pub fn insn_jump_table(&self, value: &'a Val, labels: &mut [Label<'a>]) {
+        unsafe {
+            let mut native_labels: Vec<_> = labels.iter()
+                .map(|label| **label).collect();
+            jit_insn_jump_table(
+                self.into(),
+                value.into(),
+                native_labels.as_mut_ptr(),
+                labels.len() as c_uint
+            );
+        }
+    }
+    #[inline(always)]
+    /// Make an instruction that gets the inverse cosine of the number given
+    pub fn insn_acos(&self, v: &'a Val) -> &'a Val{
+        self.insn_math("acos", v, jit_insn_acos)
+    }
+    #[inline(always)]
+    /// Make an instruction that gets the inverse sine of the number given
+    pub fn insn_asin(&self, v: &'a Val) -> &'a Val{
+        self.insn_math("asin", v, jit_insn_asin)
+    }
+    #[inline(always)]
+    /// Make an instruction that gets the inverse tangent of the number given
+    pub fn insn_atan(&self, v: &'a Val) -> &'a Val{
+        self.insn_math("atan", v, jit_insn_atan)
+    }
+    #[inline(always)]
+    /// Make an instruction that gets the inverse tangent of the numbers given
+    pub fn insn_atan2(&self, v1: &'a Val, v2: &'a Val) -> &'a Val {
+        match self.math_fallback("atan2", &[v1, v2]) {
+            Some(result) => result,
+            None => self.insn_binop(v1, v2, jit_insn_atan2)
+        }
+    }
+    #[inline(always)]
+    /// Make an instruction that finds the nearest integer above a number
+    pub fn insn_ceil(&self, v: &'a Val) -> &'a Val{
+        self.insn_math("ceil", v, jit_insn_ceil)
+    }
+    #[inline(always)]
+    /// Make an instruction that gets the consine of the number given
+    pub fn insn_cos(&self, v: &'a Val) -> &'a Val{
+        self.insn_math("cos", v, jit_insn_cos)
+    }
+    #[inline(always)]
+    /// Make an instruction that gets the hyperbolic consine of the number given
+    pub fn insn_cosh(&self, v: &'a Val) -> &'a Val{
+        self.insn_math("cosh", v, jit_insn_cosh)
+    }
+    #[inline(always)]
+    /// Make an instruction that gets the natural logarithm rased to the power
+    /// of the number
+    pub fn insn_exp(&self, v: &'a Val) -> &'a Val{
+        self.insn_math("exp", v, jit_insn_exp)
+    }
+    #[inline(always)]
+    /// Make an instruction that finds the nearest integer below a number
+    pub fn insn_floor(&self, v: &'a Val) -> &'a Val{
+        self.insn_math("floor", v, jit_insn_floor)
+    }
+    #[inline(always)]
+    /// Make an instruction that gets the natural logarithm of the number
+    pub fn insn_log(&self, v: &'a Val) -> &'a Val{
+        self.insn_math("log", v, jit_insn_log)
+    }
+    #[inline(always)]
+    /// Make an instruction that gets the base 10 logarithm of the number
+    pub fn insn_log10(&self, v: &'a Val) -> &'a Val{
+        self.insn_math("log10", v, jit_insn_log10)
+    }
+    /// Make an instruction the gets the result of raising the first value to
+    /// the power of the second value
+    ///
+    /// When the context folds constants and the power is a small constant
+    /// whole number, this multiplies instead of calling `pow`.
+    ///
+    /// ```rust
+    /// use jit::*;
+    /// let mut ctx = Context::<()>::new();
+    /// let func = UncompiledFunction::new(&mut ctx, &get::<fn(f64) -> f64>());
+    /// func.insn_return(func.insn_pow(&func[0], func.insn_of(3i32)));
+    /// assert!(!func.dump_stable().contains("pow"));
+    /// func.compile().with(|cube: extern fn(f64) -> f64| assert_eq!(cube(1.5), 3.375));
+    /// ```
+    pub fn insn_pow(&self, v1: &'a Val, v2: &'a Val) -> &'a Val {
+        match self.small_power(v1, v2) {
+            Some(power) => self.insn_repeat_mul(v1, power),
+            None => match self.math_fallback("pow", &[v1, v2]) {
+                Some(result) => result,
+                None => self.insn_binop(v1, v2, jit_insn_pow)
+            }
+        }
+    }
+    /// Get the power `v2` stands for if it is a constant whole number no
+    /// bigger than `MAX_REPEAT_MUL` that gives a result of the same type as
+    /// `v1`, and the context folds constants
+    fn small_power(&self, v1: &'a Val, v2: &'a Val) -> Option<u32> {
+        let folding = ContextData::with_func(self.into(), |data| data.options.folding);
+        if !folding || !v2.is_constant() {
+            return None
+        }
+        let (ty1, ty2) = (v1.get_type(), v2.get_type());
+        let same = ty1.get_kind() == ty2.get_kind();
+        let power = unsafe {
+            if ty2.is_float() && same {
+                jit_value_get_float64_constant(v2.into())
+            } else if ty2.is_int() && (same || ty1.is_float()) {
+                jit_value_get_long_constant(v2.into()) as f64
+            } else {
+                return None
+            }
+        };
+        if power.floor() == power && power >= 0.0 && power <= MAX_REPEAT_MUL as f64 {
+            Some(power as u32)
+        } else {
+            None
+        }
+    }
+    /// Make instructions that raise `value` to `power` by squaring and
+    /// multiplying
+    fn insn_repeat_mul(&self, value: &'a Val, power: u32) -> &'a Val {
+        if power == 0 {
+            return self.insn_convert(self.insn_of(1i32), value.get_type(), false)
+        }
+        let mut result: Option<&'a Val> = None;
+        let mut square = value;
+        let mut rest = power;
+        loop {
+            if rest & 1 == 1 {
+                result = Some(match result {
+                    Some(result) => self.insn_binop(result, square, jit_insn_mul),
+                    None => square
+                });
+            }
+            rest >>= 1;
+            if rest == 0 {
+                return result.unwrap()
+            }
+            square = self.insn_binop(square, square, jit_insn_mul);
+        }
+    }
+    #[inline(always)]
+    /// Make an instruction the gets the result of rounding the value to the
+    /// nearest integer
+    pub fn insn_rint(&self, v: &'a Val) -> &'a Val{
+        self.insn_optional_math("rint", v, "jit_insn_rint")
+    }
+    #[inline(always)]
+    /// Make an instruction the gets the result of rounding the value to the
+    /// nearest integer
+    pub fn insn_round(&self, v: &'a Val) -> &'a Val{
+        self.insn_optional_math("round", v, "jit_insn_round")
+    }
+    #[inline(always)]
+    /// Make an instruction the gets the sine of the number
+    pub fn insn_sin(&self, v: &'a Val) -> &'a Val{
+        self.insn_math("sin", v, jit_insn_sin)
+    }
+    #[inline(always)]
+    /// Make an instruction the gets the hyperbolic sine of the number
+    pub fn insn_sinh(&self, v: &'a Val) -> &'a Val{
+        self.insn_math("sinh", v, jit_insn_sinh)
+    }
+    #[inline(always)]
+    /// Make an instruction the gets the square root of a number
+    pub fn insn_sqrt(&self, value: &'a Val) -> &'a Val {
+        expect!(insn_sqrt, value, float);
+        self.insn_math("sqrt", value, jit_insn_sqrt)
+    }
+    #[inline(always)]
+    /// Make an instruction the gets the tangent of a number
+    pub fn insn_tan(&self, v: &'a Val) -> &'a Val {
+        self.insn_math("tan", v, jit_insn_tan)
+    }
+    #[inline(always)]
+    /// Make an instruction the gets the hyperbolic tangent of a number
+    pub fn insn_tanh(&self, v: &'a Val) -> &'a Val{
+        self.insn_math("tanh", v, jit_insn_tanh)
+    }
+    #[inline(always)]
+    /// Make an instruction that truncates the value
+    pub fn insn_trunc(&self, v: &'a Val) -> &'a Val {
+        self.insn_optional_math("trunc", v, "jit_insn_trunc")
+    }
+    #[inline(always)]
+    /// Make an instruction that checks if the number is NaN
+    pub fn insn_is_nan(&self, v: &'a Val) -> &'a Val {
+        expect!(insn_is_nan, v, float);
+        self.insn_unop(v, jit_insn_is_nan)
+    }
+    #[inline(always)]
+    /// Make an instruction that checks if the number is finite
+    pub fn insn_is_finite(&self, v: &'a Val) -> &'a Val {
+        expect!(insn_is_finite, v, float);
+        self.insn_unop(v, jit_insn_is_finite)
+    }
+    #[inline(always)]
+    /// Make an instruction that checks if the number is  infinite
+    pub fn insn_is_inf(&self, v: &'a Val) -> &'a Val {
+        expect!(insn_is_inf, v, float);
+        self.insn_unop(v, jit_insn_is_inf)
+    }
+    #[inline(always)]
+    /// Make an instruction that gets the absolute value of a number
+    ///
+    /// What this gives for the smallest signed integers and for `-0.0` can
+    /// differ between backends, which `insn_iabs` and `insn_fabs` don't.
+    pub fn insn_abs(&self, v: &'a Val) -> &'a Val {
+        expect!(insn_abs, v, primitive);
+        self.insn_unop(v, jit_insn_abs)
+    }
+    #[inline(always)]
+    /// Make an instruction that gets the smallest of two numbers
+    pub fn insn_min(&self, v1: &'a Val, v2: &'a Val) -> &'a Val {
+        expect!(insn_min, v1, v2, primitive);
+        self.insn_binop(v1, v2, jit_insn_min)
+    }
+    #[inline(always)]
+    /// Make an instruction that gets the biggest of two numbers
+    pub fn insn_max(&self, v1: &'a Val, v2: &'a Val) -> &'a Val {
+        expect!(insn_max, v1, v2, primitive);
+        self.insn_binop(v1, v2, jit_insn_max)
+    }
+    #[inline(always)]
+    /// Make an instruction that gets the sign of a number
+    ///
+    /// See `insn_isign` and `insn_fsign` for versions that do the same on
+    /// every backend.
+    pub fn insn_sign(&self, v: &'a Val) -> &'a Val {
+        expect!(insn_sign, v, primitive);
+        self.insn_unop(v, jit_insn_sign)
+    }
+    /// Make a constant of the number type given
+    fn number_of(&self, value: i32, ty: &Ty) -> &'a Val {
+        self.insn_convert(self.insn_of(value), ty, false)
+    }
+    /// Make instructions that get the absolute value of an integer, which
+    /// has the same type
+    ///
+    /// Unlike `insn_abs`, this does the same on every backend: unsigned
+    /// integers are left as they are, and the smallest value of a signed type,
+    /// like `i32::MIN`, wraps around to itself, like `wrapping_abs`.
+    ///
+    /// ```rust
+    /// use jit::*;
+    /// let mut ctx = Context::<()>::new();
+    /// let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+    /// func.insn_return(func.insn_iabs(&func[0]));
+    /// func.compile().with(|abs: extern fn(i32) -> i32| {
+    ///     assert_eq!(abs(-5), 5);
+    ///     assert_eq!(abs(i32::min_value()), i32::min_value());
+    /// });
+    /// ```
+    pub fn insn_iabs(&self, v: &'a Val) -> &'a Val {
+        expect!(insn_iabs, v, int);
+        let ty = v.get_type();
+        if int_bounds(ty).0 == 0 {
+            return v
+        }
+        // widen small types first, so the shift takes in the sign bit
+        let wide = if ty.get_size() < 4 { self.insn_convert(v, &get::<i32>(), false) } else { v };
+        let mask = self.insn_shr(wide, self.insn_of((wide.get_type().get_size() * 8 - 1) as i32));
+        let abs = self.insn_sub(self.insn_xor(wide, mask), mask);
+        if abs.get_type() == ty { abs } else { self.insn_convert(abs, ty, false) }
+    }
+    /// Make instructions that get the absolute value of a float, which has
+    /// the same type
+    ///
+    /// Unlike `insn_abs`, this does the same on every backend: `-0.0` gives
+    /// `0.0`, infinities give positive infinity, and NaN gives NaN.
+    pub fn insn_fabs(&self, v: &'a Val) -> &'a Val {
+        expect!(insn_fabs, v, float);
+        let ty = v.get_type();
+        let zero = self.number_of(0, ty);
+        let result = Val::new(self, ty);
+        self.store_local(result, self.insn_abs(v));
+        self.insn_if(self.insn_eq(result, zero), || self.store_local(result, zero));
+        result
+    }
+    /// Make instructions that get the sign of an integer, as an `i32` that is
+    /// -1 if it is negative, 0 if it is zero and 1 if it is positive
+    pub fn insn_isign(&self, v: &'a Val) -> &'a Val {
+        expect!(insn_isign, v, int);
+        let zero = self.number_of(0, v.get_type());
+        let sign = self.insn_sub(self.insn_gt(v, zero), self.insn_lt(v, zero));
+        self.insn_convert(sign, &get::<i32>(), false)
+    }
+    /// Make instructions that get the sign of a float, as a float of the same
+    /// type that is -1.0 if it is negative and 1.0 if it is positive
+    ///
+    /// Zeroes and NaN are given back as they are, so `-0.0` keeps its sign.
+    ///
+    /// ```rust
+    /// use jit::*;
+    /// let mut ctx = Context::<()>::new();
+    /// let func = UncompiledFunction::new(&mut ctx, &get::<fn(f64) -> f64>());
+    /// func.insn_return(func.insn_fsign(&func[0]));
+    /// func.compile().with(|sign: extern fn(f64) -> f64| {
+    ///     assert_eq!(sign(-2.5), -1.0);
+    ///     assert!(sign(-0.0).is_sign_negative());
+    ///     assert!(sign(0.0 / 0.0).is_nan());
+    /// });
+    /// ```
+    pub fn insn_fsign(&self, v: &'a Val) -> &'a Val {
+        expect!(insn_fsign, v, float);
+        let ty = v.get_type();
+        let zero = self.number_of(0, ty);
+        let result = Val::new(self, ty);
+        self.store_local(result, v);
+        self.insn_if(self.insn_gt(v, zero), || self.store_local(result, self.number_of(1, ty)));
+        self.insn_if(self.insn_lt(v, zero), || self.store_local(result, self.number_of(-1, ty)));
+        result
+    }
+
+    /// Call the function, which may or may not be translated yet
+    pub fn insn_call(&self, name:Option<&str>, func:&Func, sig:Option<&Ty>,
+        args: &mut [&'a Val], flags: flags::CallFlags) -> &'a Val {
+        self.check_call("insn_call", sig.unwrap_or(func.get_signature()), Some(func.get_signature()), args.len(), flags);
+        if !spend(self.into(), 1, 0, 0) {
+            return self.placeholder(sig.unwrap_or(func.get_signature()).get_return().unwrap())
+        }
+        self.forget_loads(None);
+        unsafe {
+            let mut native_args:&mut [jit_value_t] = mem::transmute(args);
+            let c_name = self.keep_name(name);
+            let sig = mem::transmute(sig);
+            let result = from_ptr(jit_insn_call(
+                self.into(),
+                c_name,
+                func.into(), sig, native_args.as_mut_ptr(),
+                native_args.len() as c_uint,
+                self.call_flags(flags)
+            ));
+            self.after_call(flags, result)
+        }
+    }
+    /// Make instructions that call `func` with some arguments, copying its
+    /// body in place of the call when it should be inlined
+    ///
+    /// A function is inlined when it hasn't been compiled yet, every
+    /// instruction in it can be copied, and it is marked `Inline::Always` or
+    /// is marked `Inline::Auto` and fits in the context's `InlineLimits`.
+    /// Calls and other instructions that can't be copied, like those made by
+    /// hooks, coverage or stack guards, mean it is called normally.
+    ///
+    /// ```rust
+    /// use jit::*;
+    /// let mut ctx = Context::<()>::new();
+    /// let square = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+    /// square.insn_return(square.insn_mul(&square[0], &square[0]));
+    /// let func = square.sibling(&get::<fn(i32) -> i32>());
+    /// let squared = func.insn_call_inline(Some("square"), &square, &mut [&func[0]]);
+    /// func.insn_return(func.insn_add(squared, func.insn_of(1i32)));
+    /// func.compile().with(|func: extern fn(i32) -> i32| assert_eq!(func(3), 10));
+    /// ```
+    pub fn insn_call_inline(&self, name: Option<&str>, func: &Func, args: &mut [&'a Val]) -> &'a Val {
+        if self.should_inline(func) {
+            if let Some(result) = self.inline_body(func, args) {
+                return result
+            }
+        }
+        self.insn_call(name, func, None, args, flags::CallFlags::empty())
+    }
+    /// Check if a call to `func` should be inlined according to its marker
+    /// and the context's limits
+    fn should_inline(&self, func: &Func) -> bool {
+        let source: jit_function_t = func.into();
+        if source == self._func || func.is_compiled() {
+            return false
+        }
+        let (inline, depth) = FunctionData::with(source, |data| (data.inline, data.inline_depth));
+        let limits = ContextData::with_func(self.into(), |data| data.inline_limits);
+        match inline {
+            Inline::Never => false,
+            Inline::Always => true,
+            Inline::Auto => depth < limits.max_depth && Blocks::new(source)
+                .fold(0, |size, block| size + block.iter().count()) <= limits.max_size
+        }
+    }
+    /// Copy the body of `func` in, giving its result, or `None` if it has
+    /// instructions that can't be copied
+    fn inline_body(&self, func: &Func, args: &[&'a Val]) -> Option<&'a Val> {
+        let source: jit_function_t = func.into();
+        let blocks: Vec<jit_block_t> = Blocks::new(source).map(|block| block.into()).collect();
+        let copyable = blocks.iter().all(|&block| {
+            let block: Block = from_ptr(block);
+            block.iter().all(|insn| replay::rebuildable(insn.get_name()))
+        });
+        if !copyable || args.len() != func.get_signature().params().count() {
+            return None
+        }
+        let ret = func.get_signature().get_return().unwrap();
+        let result = if ret.get_kind() == TypeKind::Void { None } else { Some(Val::new(self, ret)) };
+        let rebuilt = {
+            let mut replay = Replay::new(source, self, &blocks, None);
+            for ((index, &arg), ty) in args.iter().enumerate().zip(func.get_signature().params()) {
+                // copy the arguments, since the body may assign to its
+                // parameters, converting them like a call would
+                let param = unsafe { jit_value_get_param(source, index as c_uint) };
+                let local = Val::new(self, ty);
+                self.store_local(local, self.insn_convert(arg, ty, false));
+                replay.values.insert(param, local);
+            }
+            replay.set_result(result);
+            replay.run(&blocks)
+        };
+        if let Err(insn) = rebuilt {
+            panic!("Couldn't finish inlining {:?}, stopped at {}", source, insn)
+        }
+        let depth = FunctionData::with(source, |data| data.inline_depth) + 1;
+        FunctionData::with(self.into(), |data| data.inline_depth = cmp::max(data.inline_depth, depth));
+        Some(match result {
+            Some(result) => result,
+            None => Val::new(self, consts::get_void())
+        })
+    }
+    #[inline(always)]
+    /// Make an instruction that calls a function that has the signature given
+    /// with some arguments through a pointer to the fucntion
+    pub fn insn_call_indirect(&self, func:&'a Val, signature: &Ty,
+                               args: &mut [&'a Val], flags: flags::CallFlags) -> &'a Val {
+        self.check_call("insn_call_indirect", signature, None, args.len(), flags);
+        self.forget_loads(None);
+        unsafe {
+            let mut native_args: &mut [jit_value_t] = mem::transmute(args);
+            let result = from_ptr(jit_insn_call_indirect(
+                self.into(),
+                func.into(),
+                signature.into(),
+                native_args.as_mut_ptr(),
+                native_args.len() as c_uint,
+                self.call_flags(flags)
+            ));
+            self.after_call(flags, result)
+        }
+    }
+    #[inline(always)]
+    /// Make an instruction that calls a function through the vtable entry
+    /// `value`, which has the signature given
+    pub fn insn_call_indirect_vtable(&self, value: &'a Val, signature: &Ty,
+                                     args: &mut [&'a Val], flags: flags::CallFlags) -> &'a Val {
+        self.check_call("insn_call_indirect_vtable", signature, None, args.len(), flags);
+        self.forget_loads(None);
+        unsafe {
+            let mut native_args: &mut [jit_value_t] = mem::transmute(args);
+            let result = from_ptr(jit_insn_call_indirect_vtable(
+                self.into(),
+                value.into(),
+                signature.into(),
+                native_args.as_mut_ptr(),
+                native_args.len() as c_uint,
+                self.call_flags(flags)
+            ));
+            self.after_call(flags, result)
+        }
+    }
+    /// Make a call to `target` through a patchable slot, and get the result
+    /// along with the `CallSite` that can point the call somewhere else later
+    ///
+    /// This is for lazy binding, where the call starts off going to a stub
+    /// that finds the real target and patches the call site, and for inline
+    /// caches that move from one target to another as they see more types.
+    /// The call has the signature of `target`. If `target` isn't compiled
+    /// yet, the slot starts off empty and must be set before the call runs.
+    ///
+    /// ```rust
+    /// use jit::*;
+    /// let mut ctx = Context::<()>::new();
+    /// let double = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+    /// double.insn_return(double.insn_add(&double[0], &double[0]));
+    /// let triple = double.sibling(&get::<fn(i32) -> i32>());
+    /// triple.insn_return(triple.insn_mul(&triple[0], triple.insn_of(3i32)));
+    /// let caller = double.sibling(&get::<fn(i32) -> i32>());
+    /// let (double, triple) = (double.compile(), triple.compile());
+    /// let (result, site) = caller.insn_call_site(&double, &mut [&caller[0]], flags::CallFlags::empty());
+    /// caller.insn_return(result);
+    /// let caller = caller.compile();
+    /// caller.with(|call: extern fn(i32) -> i32| assert_eq!(call(5), 10));
+    /// site.set_target(&triple);
+    /// caller.with(|call: extern fn(i32) -> i32| assert_eq!(call(5), 15));
+    /// ```
+    pub fn insn_call_site(&self, target: &Func, args: &mut [&'a Val],
+                          flags: flags::CallFlags) -> (&'a Val, CallSite<'a>) {
+        let slot = ContextData::with_func(self.into(), |data| {
+            data.call_sites.push(Box::new(AtomicUsize::new(0)));
+            &**data.call_sites.last().unwrap() as *const AtomicUsize
+        });
+        let site = CallSite {
+            slot: slot,
+            signature: target.get_signature().into(),
+            marker: PhantomData
+        };
+        if target.is_compiled() {
+            site.set_target(target);
+        }
+        let void_ptr = consts::get_void_ptr();
+        let slot: &'a Val = unsafe {
+            from_ptr(jit_value_create_nint_constant(self.into(), void_ptr.into(), slot as jit_nint))
+        };
+        let address = self.insn_load_relative(slot, 0, void_ptr);
+        (self.insn_call_indirect(address, target.get_signature(), args, flags), site)
+    }
+    /// Make an instruction that calls the function a `CodeHandle` points to
+    /// through its slot, so it goes to whatever the slot points to when the
+    /// call is made
+    ///
+    /// The call doesn't keep the function alive, so something must keep a
+    /// clone of the handle for as long as this function might make the call.
+    pub fn insn_call_slot(&self, handle: &CodeHandle, signature: &Ty, args: &mut [&'a Val],
+                          flags: flags::CallFlags) -> &'a Val {
+        let void_ptr = consts::get_void_ptr();
+        let slot: &'a Val = unsafe {
+            from_ptr(jit_value_create_nint_constant(self.into(), void_ptr.into(), handle.get_slot() as jit_nint))
+        };
+        let address = self.insn_load_relative(slot, 0, void_ptr);
+        self.insn_call_indirect(address, signature, args, flags)
+    }
+    /// Keep the name of a call alive for as long as the function, since
+    /// LibJIT refers to it when dumping
+    ///
+    /// Names are interned in the context, so calls to the same native share
+    /// one copy of its name however many functions make them.
+    fn keep_name(&self, name: Option<&str>) -> *mut c_char {
+        match name {
+            Some(name) => ContextData::with_func(self.into(), |data| data.intern(name)) as *mut c_char,
+            None => ptr::null_mut()
+        }
+    }
+    /// Make an instruction that calls a native function that has the signature
+    /// given with some arguments
+    fn insn_call_native(&self, name: Option<&str>,
+                        native_func: *mut c_void, signature: &Ty,
+                        args: &mut [&'a Val], flags: flags::CallFlags) -> &'a Val {
+        self.forget_loads(None);
+        if self.is_validating() {
+            let name = name.unwrap_or("unnamed function");
+            if !signature.is_signature() {
+                panic!("Bad signature for {} - expected signature, got {:?}", name, signature)
+            }
+            let num_sig_args = signature.params().count();
+            if args.len() != num_sig_args {
+                panic!("Bad arguments to {} - expected {}, got {}", name, num_sig_args, args.len());
+            }
+            for (index, (arg, param)) in args.iter().zip(signature.params()).enumerate() {
+                let ty = arg.get_type();
+                if ty != param {
+                    panic!("Bad argument #{} to {} - expected {:?}, got {:?}", index, name, param, ty);
+                }
+            }
+        }
+        unsafe {
+            let mut native_args:&mut [jit_value_t] = mem::transmute(args);
+            let c_name = self.keep_name(name);
+            let result = from_ptr(jit_insn_call_native(
+                self.into(),
+                c_name,
+                native_func,
+                signature.into(),
+                native_args.as_mut_ptr(),
+                native_args.len() as c_uint,
+                self.call_flags(flags)
+            ));
+            self.after_call(flags, result)
+        }
+    }
+    #[inline(always)]
+    /// Make an instruction that calls a Rust function that has the signature
+    /// given with no arguments and expects a return value
+    pub fn insn_call_native0<R>(&self, name: Option<&str>,
+                            native_func: extern fn() -> R,
+                            signature: &Ty,
+                            flags: flags::CallFlags) -> &'a Val {
+        let func_ptr = unsafe { mem::transmute(native_func) };
+        self.insn_call_native(name, func_ptr, signature, &mut [], flags)
+    }
+    #[inline(always)]
+    /// Make an instruction that calls a Rust function that has the signature
+    /// given with a single argument and expects a return value
+    pub fn insn_call_native1<A,R>(&self, name: Option<&str>,
+                                native_func: extern fn(A) -> R,
+                                signature: &Ty,
+                                mut args: [&'a Val; 1],
+                                flags: flags::CallFlags) -> &'a Val {
+        let func_ptr = unsafe { mem::transmute(native_func) };
+        self.insn_call_native(name, func_ptr, signature, &mut args, flags)
+    }
+    #[inline(always)]
+    /// Make an instruction that calls a Rust function that has the signature
+    /// given with two arguments and expects a return value
+    pub fn insn_call_native2<A,B,R>(&self, name: Option<&str>,
+                                native_func: extern fn(A, B) -> R,
+                                signature: &Ty,
+                                mut args: [&'a Val; 2],
+                                flags: flags::CallFlags) -> &'a Val {
+        let func_ptr = unsafe { mem::transmute(native_func) };
+        self.insn_call_native(name, func_ptr, signature, &mut args, flags)
+    }
+    #[inline(always)]
+    /// Make an instruction that calls a Rust function that has the signature
+    /// given with three arguments and expects a return value
+    pub fn insn_call_native3<A,B,C,R>(&self, name: Option<&str>,
+                                native_func: extern fn(A, B, C) -> R,
+                                signature: &Ty,
+                                mut args: [&'a Val; 3],
+                                flags: flags::CallFlags) -> &'a Val {
+        let func_ptr = unsafe { mem::transmute(native_func) };
+        self.insn_call_native(name, func_ptr, signature, &mut args, flags)
+    }
+    #[inline(always)]
+    /// Make an instruction that calls a Rust function that has the signature
+    /// given with four arguments and expects a return value
+    pub fn insn_call_native4<A,B,C,D,R>(&self, name: Option<&str>,
+                                native_func: extern fn(A, B, C, D) -> R,
+                                signature: &Ty,
+                                mut args: [&'a Val; 4],
+                                flags: flags::CallFlags) -> &'a Val {
+        let func_ptr = unsafe { mem::transmute(native_func) };
+        self.insn_call_native(name, func_ptr, signature, &mut args
+            , flags)
+    }
+    /// Make an instruction that calls the native registered on the context
+    /// under `name` with `register_extern`, with the arguments given
+    ///
+    /// ```rust
+    /// use jit::*;
+    /// extern fn twice(x: i32) -> i32 {
+    ///     x * 2
+    /// }
+    /// let mut ctx = Context::<()>::new();
+    /// ctx.register_extern("twice", twice as usize, &get::<fn(i32) -> i32>());
+    /// let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+    /// let result = func.insn_call_extern("twice", &mut [&func[0]], flags::CallFlags::empty());
+    /// func.insn_return(result);
+    /// func.compile().with(|func: extern fn(i32) -> i32| assert_eq!(func(21), 42));
+    /// ```
+    pub fn insn_call_extern(&self, name: &str, args: &mut [&'a Val], flags: flags::CallFlags) -> &'a Val {
+        let found = ContextData::with_func(self.into(), |data| {
+            data.externs.iter().find(|&&(ref other, _, _)| other == name)
+                .map(|&(_, address, ref signature)| (address, signature.clone()))
+        });
+        match found {
+            Some((address, signature)) =>
+                self.insn_call_native(Some(name), address as *mut c_void, &signature, args, flags),
+            None => panic!("No native called {} has been registered", name)
+        }
+    }
+    /// Make the pseudo-instruction registered on the context under `name`
+    /// with `register_op`, expanding it into real instructions right here,
+    /// and get its result if it has one
+    ///
+    /// ```rust
+    /// use jit::*;
+    /// fn clamp<'a>(func: &UncompiledFunction<'a>, args: &[&'a Val]) -> Option<&'a Val> {
+    ///     Some(func.insn_min(func.insn_max(args[0], args[1]), args[2]))
+    /// }
+    /// let mut ctx = Context::<()>::new();
+    /// ctx.register_op("clamp", clamp);
+    /// let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+    /// let clamped = func.insn_op("clamp", &[&func[0], func.insn_of(0i32), func.insn_of(10i32)]);
+    /// func.insn_return(clamped.unwrap());
+    /// func.compile().with(|func: extern fn(i32) -> i32| {
+    ///     assert_eq!(func(-5), 0);
+    ///     assert_eq!(func(5), 5);
+    ///     assert_eq!(func(50), 10);
+    /// });
+    /// ```
+    pub fn insn_op(&self, name: &str, args: &[&'a Val]) -> Option<&'a Val> {
+        let expand = ContextData::with_func(self.into(), |data| data.ops.get(name).cloned());
+        match expand {
+            Some(expand) => expand(self, args),
+            None => panic!("No pseudo-instruction called {} has been registered", name)
+        }
+    }
+    #[inline(always)]
+    /// Make an instruction that pushes `value` onto the outgoing argument stack
+    ///
+    /// This and the other low-level call primitives are for building custom
+    /// calling conventions that the `insn_call` family can't express, such as
+    /// passing an interpreter frame. The caller is responsible for making the
+    /// call afterwards and for popping what was pushed with `insn_pop_stack`.
+    pub fn insn_push(&self, value: &'a Val) -> bool {
+        unsafe {
+            jit_insn_push(self.into(), value.into()) != 0
+        }
+    }
+    #[inline(always)]
+    /// Make an instruction that pushes the value of type `ty` that the pointer
+    /// `value` points to onto the outgoing argument stack
+    pub fn insn_push_ptr(&self, value: &'a Val, ty: &Ty) -> bool {
+        expect!(insn_push_ptr, value, pointer);
+        unsafe {
+            jit_insn_push_ptr(self.into(), value.into(), ty.into()) != 0
+        }
+    }
+    /// Make a barrier that memory operations won't be moved across
+    ///
+    /// This is a call to an empty function LibJIT can't see into, so every
+    /// value held in a register is written back before it and read again
+    /// after it. Use it where generated code shares memory with host code that
+    /// relies on ordering, like spinlocks and flag checks.
+    ///
+    /// ```rust
+    /// use jit::*;
+    /// let mut ctx = Context::<()>::new();
+    /// let func = UncompiledFunction::new(&mut ctx, &get::<fn(&'static i32) -> i32>());
+    /// func.insn_store_relative(&func[0], 0, func.insn_of(1i32));
+    /// func.insn_compiler_barrier();
+    /// func.insn_return(func.insn_load_relative(&func[0], 0, typecs::get_int()));
+    /// func.compile().with(|func: extern fn(*mut i32) -> i32| {
+    ///     let mut flag = 0;
+    ///     assert_eq!(func(&mut flag), 1);
+    ///     assert_eq!(flag, 1);
+    /// });
+    /// ```
+    pub fn insn_compiler_barrier(&self) {
+        extern fn barrier() {}
+        self.insn_call_native0(Some("compiler_barrier"), barrier, &get::<fn()>(), flags::CallFlags::NO_THROW);
+    }
+    #[inline(always)]
+    /// Make an instruction that pops `num_items` items off the stack, usually
+    /// the arguments pushed for a call that has just returned
+    pub fn insn_pop_stack(&self, num_items: usize) -> bool {
+        unsafe {
+            jit_insn_pop_stack(self.into(), num_items as jit_nint) != 0
+        }
+    }
+    #[inline(always)]
+    /// Make an instruction that says the result of the call just made is in
+    /// the native register `reg`, and copies it into `value`
+    pub fn insn_return_reg(&self, value: &'a Val, reg: c_int) -> bool {
+        unsafe {
+            jit_insn_return_reg(self.into(), value.into(), reg) != 0
+        }
+    }
+    /// Make an instruction that prints the values given with C's `printf`, after
+    /// checking the format string given against the types of the values
+    ///
+    /// Integers given for `%d`, `%i`, `%u`, `%x`, `%o` and `%c` are converted to
+    /// C `int`s (or `long`s and `long long`s with the `l` and `ll` modifiers,
+    /// while `h` and `hh` leave them as `int`s for `printf` to narrow), and
+    /// floats given for `%f`, `%e` and `%g` are converted to `f64`, just like
+    /// C's variadic argument promotion. `%s` and `%p` take pointers. A `*`
+    /// width or precision takes an integer from the values, before the one
+    /// it formats.
+    ///
+    /// ```rust
+    /// use jit::*;
+    /// let mut ctx = Context::<()>::new();
+    /// let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32, f64)>());
+    /// func.insn_printf("x = %d, y = %f\n", &[&func[0], &func[1]]);
+    /// func.insn_default_return();
+    /// ```
+    pub fn insn_printf(&self, format: &str, values: &[&'a Val]) -> &'a Val {
+        let c_format = FunctionData::keep_string(self.into(), format);
+        let mut args:Vec<&'a Val> = Vec::with_capacity(values.len() + 1);
+        args.push(unsafe {
+            from_ptr(jit_value_create_nint_constant(self.into(), consts::get_void_ptr().into(), c_format as jit_nint))
+        });
+        let mut values = values.iter();
+        let mut chars = format.chars();
+        while let Some(c) = chars.next() {
+            if c != '%' {
+                continue
+            }
+            let mut longs = 0;
+            let mut conv = None;
+            while let Some(c) = chars.next() {
+                match c {
+                    'l' => longs += 1,
+                    // shorter integers are promoted to `int` anyway
+                    'h' => (),
+                    '*' => {
+                        // the width or precision is taken from an `int`
+                        let value = match values.next() {
+                            Some(value) => *value,
+                            None => panic!("Not enough values given to insn_printf for format string {:?}", format)
+                        };
+                        let ty = value.get_type();
+                        if !ty.is_int() {
+                            panic!("Value given to insn_printf for * should not be {:?}", ty)
+                        }
+                        args.push(self.insn_convert(value, consts::get_sys_int(), false));
+                    },
+                    '0'...'9' | '.' | '-' | '+' | ' ' | '#' => (),
+                    c => {
+                        conv = Some(c);
+                        break
+                    }
+                }
+            }
+            let conv = match conv {
+                Some('%') => continue,
+                Some(conv) => conv,
+                None => panic!("Unterminated specifier in format string {:?} given to insn_printf", format)
+            };
+            let value = match values.next() {
+                Some(value) => *value,
+                None => panic!("Not enough values given to insn_printf for format string {:?}", format)
+            };
+            let ty = value.get_type();
+            args.push(match conv {
+                'd' | 'i' | 'u' | 'x' | 'X' | 'o' | 'c' if ty.is_int() => {
+                    let int_t = match longs {
+                        0 => consts::get_sys_int(),
+                        1 => consts::get_sys_long(),
+                        _ => consts::get_sys_longlong()
+                    };
+                    self.insn_convert(value, int_t, false)
+                },
+                'f' | 'F' | 'e' | 'E' | 'g' | 'G' if ty.is_float() =>
+                    self.insn_convert(value, consts::get_float64(), false),
+                's' | 'p' if ty.is_pointer() => value,
+                _ => panic!("Value given to insn_printf for %{} should not be {:?}", conv, ty)
+            });
+        }
+        if values.next().is_some() {
+            panic!("Too many values given to insn_printf for format string {:?}", format)
+        }
+        let mut params:Vec<&Ty> = args.iter().map(|arg| arg.get_type()).collect();
+        let sig = Type::new_signature(Abi::VarArg, consts::get_sys_int(), &mut params);
+        self.insn_call_native(Some("printf"), printf as *mut c_void, &sig, &mut args, flags::CallFlags::empty())
+    }
+    /// Make instructions that abort the process with the message given if `cond`
+    /// is false when run
+    ///
+    /// This only emits anything when the function's context is in debug mode, so
+    /// it compiles to nothing in contexts where `set_debug(false)` has been called.
+    pub fn insn_debug_assert(&self, cond: &'a Val, message: &str) {
+        if !ContextData::with_func(self.into(), |data| data.debug) {
+            return
+        }
+        let mut ok = Label::new(self);
+        self.insn_branch_if(cond, &mut ok);
+        let c_message = FunctionData::keep_string(self.into(), message);
+        let c_message = unsafe {
+            from_ptr(jit_value_create_nint_constant(self.into(), consts::get_void_ptr().into(), c_message as jit_nint))
+        };
+        let sig = Type::new_signature(Abi::CDecl, consts::get_void(), &mut [consts::get_void_ptr()]);
+        let failed:extern fn(*const c_char) = debug_assert_failed;
+        self.insn_call_native(Some("debug_assert_failed"), failed as *mut c_void, &sig,
+                              &mut [c_message], flags::CallFlags::NO_THROW | flags::CallFlags::NO_RETURN);
+        self.insn_label(&mut ok);
+    }
+    /// Pin the value given so it stays in its stack slot until `insn_unpin` is
+    /// called on it
+    ///
+    /// Pinned values are made addressable and volatile, so they are never
+    /// cached in registers and every access goes through the slot. The
+    /// address of the slot is pushed onto the context's stack map, so a
+    /// garbage collector triggered by a native call can find and update the
+    /// values through `Context::pinned_roots`. Everything this function pinned
+    /// is taken off the stack map when it returns or an exception leaves it.
+    ///
+    /// ```rust
+    /// use jit::*;
+    /// extern fn collect(ctx: &Context<()>) {
+    ///     for root in ctx.pinned_roots() {
+    ///         unsafe { *(root as *mut usize) += 8 };
+    ///     }
+    /// }
+    /// let mut ctx = Context::<()>::new();
+    /// let ctx_ptr = &ctx as *const Context<()>;
+    /// let func = UncompiledFunction::new(&mut ctx, &get::<fn(usize, usize) -> usize>());
+    /// let object = func.insn_dup(&func[1]);
+    /// func.insn_pin(object);
+    /// func.insn_call_native1(Some("collect"), collect, &get::<fn(usize)>(),
+    ///                        [&func[0]], flags::CallFlags::empty());
+    /// func.insn_unpin(object);
+    /// func.insn_return(object);
+    /// let moved: extern fn(*const Context<()>, usize) -> usize = func.compile().closure();
+    /// assert_eq!(moved(ctx_ptr, 16), 24);
+    /// assert_eq!(unsafe { (*ctx_ptr).pinned_roots().count() }, 0);
+    /// ```
+    pub fn insn_pin(&self, value: &'a Val) {
+        value.set_addressable();
+        value.set_volatile();
+        self.insn_uses_catcher();
+        let ptr_type = consts::get_void_ptr();
+        let record = Val::new(self, ptr_type);
+        record.set_volatile();
+        // the record and the top of the stack map on entry are found in front
+        // of everything else, so a pin in a loop reuses one record and every
+        // exit can put the stack map back
+        let (mut init, mut after) = (Label::new(self), Label::new(self));
+        self.insn_branch(&mut after);
+        self.insn_label(&mut init);
+        let slot = match FunctionData::with(self.into(), |data| data.pin_roots) {
+            Some((slot, _)) => from_ptr(slot),
+            None => {
+                let ctx = unsafe { jit_function_get_context(self.into()) };
+                let slot_fn:extern fn(jit_context_t) -> *mut *const shadow::Root = shadow::root_slot;
+                let address = self.insn_call_native1(Some("root_slot"), slot_fn, &get::<fn(usize) -> usize>(),
+                                                     [self.insn_of(ctx as usize)], flags::CallFlags::NO_THROW);
+                let slot = Val::new(self, ptr_type);
+                slot.set_volatile();
+                self.store_local(slot, self.insn_convert(address, ptr_type, false));
+                let base = Val::new(self, ptr_type);
+                base.set_volatile();
+                self.store_local(base, self.insn_load_relative(slot, 0, ptr_type));
+                FunctionData::with(self.into(), |data| data.pin_roots = Some((slot.into(), base.into())));
+                slot
+            }
+        };
+        let size = self.insn_of(mem::size_of::<shadow::Root>() as c_uint);
+        self.store_local(record, self.insn_alloca(size));
+        self.insn_label(&mut after);
+        if let Err(error) = self.move_blocks_to_start(&init, &after) {
+            panic!("The record of a pinned value couldn't be made at the start: {}", error)
+        }
+        self.insn_store_relative(record, 0, self.insn_load_relative(slot, 0, ptr_type));
+        self.insn_store_relative(record, mem::size_of::<usize>(), self.address_of_local(value));
+        self.insn_store_relative(slot, 0, record);
+        FunctionData::with(self.into(), |data| data.pinned.push((value.into(), record.into())))
+    }
+    /// Unpin a value previously pinned by `insn_pin`, taking it off the stack
+    /// map
+    pub fn insn_unpin(&self, value: &'a Val) {
+        let ptr:jit_value_t = value.into();
+        let found = FunctionData::with(self.into(), |data| {
+            match data.pinned.iter().rposition(|&(pinned, _)| pinned == ptr) {
+                Some(index) => {
+                    let (_, record) = data.pinned.remove(index);
+                    // the record pinned after this one, if any, points to it
+                    let above = data.pinned.get(index).map(|&(_, above)| above);
+                    Some((record, above, data.pin_roots.unwrap().0))
+                },
+                None => None
+            }
+        });
+        let (record, above, slot) = match found {
+            Some(found) => found,
+            None => panic!("Value {:?} given to insn_unpin was never pinned", value)
+        };
+        let (record, slot): (&'a Val, &'a Val) = (from_ptr(record), from_ptr(slot));
+        let parent = self.insn_load_relative(record, 0, consts::get_void_ptr());
+        match above {
+            Some(above) => self.insn_store_relative(from_ptr(above), 0, parent),
+            None => self.insn_store_relative(slot, 0, parent)
+        }
+    }
+    /// Get the values that are pinned at the current point in the function, in
+    /// the order they were pinned
+    pub fn get_pinned(&self) -> Vec<&'a Val> {
+        FunctionData::with(self.into(), |data| {
+            data.pinned.iter().map(|&(value, _)| from_ptr(value)).collect()
+        })
+    }
+    #[inline(always)]
+    /// Make an instruction that copies `size` bytes from the `source` address to the `dest` address
+    pub fn insn_memcpy(&self, dest: &'a Val, source: &'a Val, size: &'a Val) -> bool {
+        self.forget_loads(None);
+        expect!(insn_memcpy, dest, source, size);
+        unsafe {
+            jit_insn_memcpy(self.into(), dest.into(), source.into(), size.into()) != 0
+        }
     }
     #[inline(always)]
     /// Make an instruction that moves memory from a source address to a destination address
     pub fn insn_memmove(&self, dest: &'a Val, source: &'a Val, size: &'a Val) -> bool {
+        self.forget_loads(None);
         expect!(insn_memmove, dest, source, size);
         unsafe {
             jit_insn_memmove(self.into(), dest.into(), source.into(), size.into()) != 0
         }
     }
     #[inline(always)]
-    /// Make an instruction that sets memory at the destination address
-    pub fn insn_memset(&self, dest: &'a Val, source: &'a Val, size: &'a Val) -> bool {
-        expect!(insn_memset, dest, source, size);
+    /// Make an instruction that sets `size` bytes at the destination address to `value`
+    pub fn insn_memset(&self, dest: &'a Val, value: &'a Val, size: &'a Val) -> bool {
+        self.forget_loads(None);
+        if self.is_validating() && !dest.get_type().is_pointer() {
+            panic!("Expected pointer destination for insn_memset, but got {:?}", dest.get_type());
+        }
+        expect!(insn_memset, size, int);
+        unsafe {
+            jit_insn_memset(self.into(), dest.into(), value.into(), size.into()) != 0
+        }
+    }
+    /// Make the guards in this function made by `insn_guard` call `handler`
+    /// when they fail
+    ///
+    /// This must be called before the first guard is made.
+    pub fn set_guard_handler(&self, handler: GuardHandler) {
+        FunctionData::with(self.into(), |data| data.guard_handler = Some(handler))
+    }
+    /// Make instructions that leave the function through a side exit unless
+    /// `cond` is true, giving the guard handler `exit_id` and the values of
+    /// `live`, which must each fit in 8 bytes and are zero-extended to fill
+    /// them
+    ///
+    /// Every guard in the function shares one exit stub that calls the
+    /// handler, so each guard only adds the instructions to capture its live
+    /// values.
+    ///
+    /// ```rust
+    /// use jit::*;
+    /// use std::slice;
+    /// extern fn failed(id: usize, values: *const u64, count: usize) -> u64 {
+    ///     let values = unsafe { slice::from_raw_parts(values, count) };
+    ///     id as u64 * 100 + values[0]
+    /// }
+    /// let mut ctx = Context::<()>::new();
+    /// let func = UncompiledFunction::new(&mut ctx, &get::<fn(i64) -> i64>());
+    /// func.set_guard_handler(failed);
+    /// let x = &func[0];
+    /// func.insn_guard(func.insn_lt(x, func.insn_of(10i64)), 1, &[x]);
+    /// func.insn_guard(func.insn_gt(x, func.insn_of(0i64)), 2, &[x]);
+    /// func.insn_return(func.insn_mul(x, x));
+    /// func.compile().with(|func: extern fn(i64) -> i64| {
+    ///     assert_eq!(func(3), 9);
+    ///     assert_eq!(func(12), 112);
+    ///     assert_eq!(func(-1), 199);
+    /// });
+    /// ```
+    pub fn insn_guard(&self, cond: &'a Val, exit_id: usize, live: &[&'a Val]) {
+        let (handler, stub) = FunctionData::with(self.into(), |data| (data.guard_handler, data.guard_stub));
+        let handler = match handler {
+            Some(handler) => handler,
+            None => panic!("insn_guard needs a handler set with set_guard_handler first")
+        };
+        let mut passed = Label::new(self);
+        self.insn_branch_if(cond, &mut passed);
+        let count = self.insn_of(live.len());
+        let buffer = self.insn_alloca(self.insn_of(cmp::max(live.len(), 1) * 8));
+        for (index, value) in live.iter().enumerate() {
+            if value.get_type().get_size() < 8 {
+                self.insn_store_relative(buffer, index * 8, self.insn_of(0u64));
+            }
+            self.insn_store_relative(buffer, index * 8, value);
+        }
+        let id = self.insn_of(exit_id);
+        match stub {
+            Some((mut label, id_local, buffer_local, count_local)) => {
+                self.store_local(from_ptr(id_local), id);
+                self.store_local(from_ptr(buffer_local), buffer);
+                self.store_local(from_ptr(count_local), count);
+                unsafe {
+                    jit_insn_branch(self.into(), &mut label);
+                }
+            },
+            None => {
+                let id_local = Val::new(self, consts::get_nuint());
+                let buffer_local = Val::new(self, buffer.get_type());
+                let count_local = Val::new(self, consts::get_nuint());
+                self.store_local(id_local, id);
+                self.store_local(buffer_local, buffer);
+                self.store_local(count_local, count);
+                let mut stub = Label::new(self);
+                self.insn_label(&mut stub);
+                let sig = Type::new_signature(Abi::CDecl, consts::get_ulong(),
+                                              &mut [consts::get_nuint(), buffer.get_type(), consts::get_nuint()]);
+                let bits = self.insn_call_native3(Some("guard_failed"), handler, &sig,
+                                                  [id_local, buffer_local, count_local],
+                                                  flags::CallFlags::empty());
+                let ret = self.get_signature().get_return().unwrap();
+                if ret.get_kind() == TypeKind::Void {
+                    self.insn_default_return();
+                } else {
+                    self.insn_store_relative(buffer_local, 0, bits);
+                    self.insn_return(self.insn_load_relative(buffer_local, 0, ret));
+                }
+                FunctionData::with(self.into(), |data| data.guard_stub = Some((
+                    *stub as jit_label_t, id_local.into(), buffer_local.into(), count_local.into()
+                )));
+            }
+        }
+        self.insn_label(&mut passed);
+    }
+    /// Make instructions that check the tag of the parameter at `index`, and
+    /// when it isn't the one expected, call `slow_path` with the same
+    /// arguments and return what it returns
+    ///
+    /// This is meant to be called before anything else is built into the
+    /// function, so the rest of it can be specialized for the type the tag
+    /// stands for, with `slow_path` being the generic version. `slow_path`
+    /// must have the same signature as this function.
+    ///
+    /// ```rust
+    /// use jit::*;
+    /// let mut ctx = Context::<()>::new();
+    /// let generic = UncompiledFunction::new(&mut ctx, &get::<fn(u64) -> u64>());
+    /// generic.insn_return(generic.insn_of(0u64));
+    /// // small integers are tagged by having their lowest bit set
+    /// let func = generic.sibling(&get::<fn(u64) -> u64>());
+    /// let generic = generic.compile();
+    /// func.guard_param_type(0, TypeTag::Bits { mask: 1, tag: 1 }, &generic);
+    /// func.insn_return(func.insn_shr(&func[0], func.insn_of(1u64)));
+    /// func.compile().with(|untag: extern fn(u64) -> u64| {
+    ///     assert_eq!(untag(7 << 1 | 1), 7);
+    ///     assert_eq!(untag(7 << 1), 0);
+    /// });
+    /// ```
+    pub fn guard_param_type(&self, index: usize, expected: TypeTag, slow_path: &Func) {
+        let sig = self.get_signature();
+        if let Err(err) = sig.check_signature(slow_path.get_signature()) {
+            panic!("The slow path given to guard_param_type doesn't match the function: {}", err)
+        }
+        let params = sig.params().count();
+        if index >= params {
+            panic!("guard_param_type was given parameter {}, but the function only has {}", index, params)
+        }
+        let param = |index: usize| -> &'a Val {
+            from_ptr(unsafe { jit_value_get_param(self.into(), index as c_uint) })
+        };
+        let matches = match expected {
+            TypeTag::Bits { mask, tag } => {
+                let bits = self.insn_convert(param(index), &get::<u64>(), false);
+                self.insn_eq(self.insn_and(bits, self.insn_of(mask)), self.insn_of(tag))
+            },
+            TypeTag::Field { offset, tag } => {
+                expect!(guard_param_type, param(index), pointer);
+                let actual = self.insn_load_relative(param(index), offset, &get::<usize>());
+                self.insn_eq(actual, self.insn_of(tag))
+            }
+        };
+        let mut passed = Label::new(self);
+        self.insn_branch_if(matches, &mut passed);
+        let mut args: Vec<&'a Val> = (0..params).map(param).collect();
+        let result = self.insn_call(None, slow_path, None, &mut args, flags::CallFlags::TAIL);
+        if sig.get_return().map(|ret| ret.get_kind() == TypeKind::Void).unwrap_or(true) {
+            self.insn_default_return();
+        } else {
+            self.insn_return(result);
+        }
+        self.insn_label(&mut passed);
+    }
+    /// Make a table of the constants given that lives as long as the function,
+    /// so they can be loaded with `insn_load_table` instead of each being
+    /// built in the code
+    ///
+    /// Each constant must be a primitive of at most 8 bytes.
+    ///
+    /// ```rust
+    /// use jit::*;
+    /// let mut ctx = Context::<()>::new();
+    /// let func = UncompiledFunction::new(&mut ctx, &get::<fn(usize) -> f64>());
+    /// let table = func.new_table(&[0.5f64, 1.5, 2.5, 3.5]);
+    /// assert_eq!(table.get_len(), 4);
+    /// func.insn_return(func.insn_load_table(table, &func[0]));
+    /// func.compile().with(|func: extern fn(usize) -> f64| assert_eq!(func(2), 2.5));
+    /// ```
+    pub fn new_table<T>(&self, values: &[T]) -> ConstTable where T: Compile<'a> + Copy {
+        self.add_table(values, false)
+    }
+    /// Make a table like `new_table` in pages of its own, which are made
+    /// read-only once the constants are written, so generated code that
+    /// writes to it by mistake faults straight away
+    pub fn new_readonly_table<T>(&self, values: &[T]) -> ConstTable where T: Compile<'a> + Copy {
+        self.add_table(values, true)
+    }
+    fn add_table<T>(&self, values: &[T], read_only: bool) -> ConstTable where T: Compile<'a> + Copy {
+        let size = mem::size_of::<T>();
+        if size > 8 {
+            panic!("Constants in tables can be at most 8 bytes, but {:?} is {}", get::<T>(), size);
+        }
+        // each constant takes a whole word, so they can be indexed the same
+        let mut words = vec![0u64; values.len()];
+        unsafe {
+            ptr::copy_nonoverlapping(values.as_ptr(), words.as_mut_ptr() as *mut T, values.len());
+        }
+        let bytes = unsafe { slice::from_raw_parts(words.as_ptr() as *const u8, words.len() * 8) };
+        let storage = Storage::new(bytes, read_only);
+        let address = storage.as_ptr() as usize;
+        FunctionData::with(self.into(), |data| data.tables.push((storage, get::<T>().into_owned())));
+        ConstTable {
+            address: address,
+            len: values.len()
+        }
+    }
+    /// Get constants holding the address and length of the bytes in
+    /// `buffer`, which stay pinned for as long as this function exists
+    pub fn insn_buffer(&self, buffer: &Buffer) -> (&'a Val, &'a Val) {
+        let bytes = buffer::pin(buffer);
+        let (address, len) = (bytes.as_ptr() as jit_nint, bytes.len());
+        FunctionData::with(self.into(), |data| data.buffers.push(bytes));
+        let address = unsafe {
+            from_ptr(jit_value_create_nint_constant(self.into(), consts::get_void_ptr().into(), address))
+        };
+        (address, self.insn_of(len))
+    }
+    /// Make an instruction that loads the constant at `index` in the table
+    ///
+    /// The index isn't checked, so it must be less than the table's length.
+    pub fn insn_load_table(&self, table: ConstTable, index: &'a Val) -> &'a Val {
+        expect!(insn_load_table, index, int);
+        let ty = FunctionData::with(self.into(), |data| data.tables.iter()
+            .find(|&&(ref storage, _)| storage.as_ptr() as usize == table.address)
+            .map(|&(_, ref ty)| -> jit_type_t { (&**ty).into() }));
+        let ty = match ty {
+            Some(ty) => ty,
+            None => panic!("The table {:?} wasn't made for this function", table)
+        };
         unsafe {
-            jit_insn_memset(self.into(), dest.into(), source.into(), size.into()) != 0
+            let base = jit_value_create_nint_constant(self.into(), consts::get_void_ptr().into(),
+                                                      table.address as jit_nint);
+            if index.is_constant() {
+                let offset = jit_value_get_nint_constant(index.into()) as usize * jit_type_get_size(ty) as usize;
+                from_ptr(jit_insn_load_relative(self.into(), base, offset as jit_nint, ty))
+            } else {
+                from_ptr(jit_insn_load_elem(self.into(), base, index.into(), ty))
+            }
+        }
+    }
+    /// Make an instruction that gets the length of the nul-terminated string
+    /// at `ptr` by calling `strlen`
+    ///
+    /// ```rust
+    /// use jit::*;
+    /// let mut ctx = Context::<()>::new();
+    /// let func = UncompiledFunction::new(&mut ctx, &get::<fn(&'static u8) -> usize>());
+    /// func.insn_return(func.insn_strlen(&func[0]));
+    /// func.compile().with(|strlen: extern fn(*const u8) -> usize| {
+    ///     assert_eq!(strlen(b"hello\0".as_ptr()), 5);
+    /// });
+    /// ```
+    pub fn insn_strlen(&self, ptr: &'a Val) -> &'a Val {
+        expect!(insn_strlen, ptr, pointer);
+        let sig = Type::new_signature(Abi::CDecl, &get::<usize>(), &mut [ptr.get_type()]);
+        self.insn_call_native(Some("strlen"), ::libc::strlen as *mut c_void, &sig, &mut [ptr],
+                              flags::CallFlags::NO_THROW)
+    }
+    /// Make instructions that compare the nul-terminated strings at `a` and
+    /// `b` by calling `strcmp`, giving an `i32` that is negative, zero or
+    /// positive like `strcmp`'s result
+    pub fn insn_strcmp(&self, a: &'a Val, b: &'a Val) -> &'a Val {
+        expect!(insn_strcmp, a, pointer);
+        expect!(insn_strcmp, b, pointer);
+        let sig = Type::new_signature(Abi::CDecl, &get::<i32>(), &mut [a.get_type(), b.get_type()]);
+        self.insn_call_native(Some("strcmp"), ::libc::strcmp as *mut c_void, &sig, &mut [a, b],
+                              flags::CallFlags::NO_THROW)
+    }
+    /// Make instructions that compare `len` bytes at `a` and `b`, giving an
+    /// `i32` that is negative, zero or positive like `memcmp`'s result
+    ///
+    /// When `len` is a small constant the bytes are compared inline instead
+    /// of calling `memcmp`.
+    ///
+    /// ```rust
+    /// use jit::*;
+    /// let mut ctx = Context::<()>::new();
+    /// let func = UncompiledFunction::new(&mut ctx, &get::<fn(&'static u8, &'static u8) -> i32>());
+    /// func.insn_return(func.insn_memcmp(&func[0], &func[1], func.insn_of(3usize)));
+    /// let cmp: extern fn(*const u8, *const u8) -> i32 = func.compile().closure();
+    /// assert_eq!(cmp(b"abc".as_ptr(), b"abc".as_ptr()), 0);
+    /// assert!(cmp(b"abc".as_ptr(), b"abd".as_ptr()) < 0);
+    /// ```
+    pub fn insn_memcmp(&self, a: &'a Val, b: &'a Val, len: &'a Val) -> &'a Val {
+        /// The longest constant length that is compared inline
+        const INLINE_MAX: usize = 16;
+        expect!(insn_memcmp, a, pointer);
+        expect!(insn_memcmp, b, pointer);
+        expect!(insn_memcmp, len, int);
+        let int = get::<i32>();
+        if len.is_constant() {
+            let count = unsafe { jit_value_get_nint_constant(len.into()) };
+            if count >= 0 && count as usize <= INLINE_MAX {
+                let ubyte = get::<u8>();
+                let result = Val::new(self, &int);
+                self.store_local(result, self.insn_of(0i32));
+                let mut done = Label::new(self);
+                for offset in 0..count as usize {
+                    let x = self.insn_convert(self.insn_load_relative(a, offset, &ubyte), &int, false);
+                    let y = self.insn_convert(self.insn_load_relative(b, offset, &ubyte), &int, false);
+                    let diff = self.insn_sub(x, y);
+                    self.store_local(result, diff);
+                    self.insn_branch_if(diff, &mut done);
+                }
+                self.insn_label(&mut done);
+                return result
+            }
         }
+        let usize_t = get::<usize>();
+        let len = self.insn_convert(len, &usize_t, false);
+        let sig = Type::new_signature(Abi::CDecl, &int, &mut [a.get_type(), b.get_type(), &usize_t]);
+        self.insn_call_native(Some("memcmp"), ::libc::memcmp as *mut c_void, &sig, &mut [a, b, len],
+                              flags::CallFlags::NO_THROW)
     }
     #[inline(always)]
     /// Make an instruction that allocates `size` bytes of memory from the stack
     pub fn insn_alloca(&self, size: &'a Val) -> &'a Val {
         expect!(insn_alloca, size, int);
-        unsafe {
-            from_ptr(jit_insn_alloca(self.into(), size.into()))
+        let threshold = ContextData::with_func(self.into(), |data| data.stack_guard.map(|guard| guard.alloca_threshold));
+        if let Some(threshold) = threshold {
+            let small = size.is_constant() && unsafe {
+                jit_value_get_nint_constant(size.into()) as usize
+            } < threshold;
+            if !small {
+                self.probe_stack(Some(size));
+            }
+        }
+        let pointer = unsafe {
+            jit_insn_alloca(self.into(), size.into())
+        };
+        if self.is_validating() {
+            FunctionData::with(self.into(), |data| data.allocas.push(pointer));
+        }
+        from_ptr(pointer)
+    }
+    /// Make instructions that get the address of `source`
+    pub fn insn_address_of(&self, source: &LValue<'a>) -> &'a Val {
+        match source.get_memory() {
+            Some((ptr, 0)) => ptr,
+            Some((ptr, offset)) => self.insn_add_relative(ptr, offset),
+            None => self.address_of_local(source.get_local().unwrap())
         }
     }
     #[inline(always)]
-    /// Make an instruction that gets the address of a value
-    pub fn insn_address_of(&self, value: &'a Val) -> &'a Val {
+    /// Make an instruction that gets the address of a local value
+    fn address_of_local(&self, value: &'a Val) -> &'a Val {
         unsafe {
             from_ptr(jit_insn_address_of(self.into(), value.into()))
         }
@@ -902,9 +4555,18 @@ impl<'a> UncompiledFunction<'a> {
                         jit_value_t,
                         jit_value_t) -> jit_value_t)
                     -> &'a Val {
-        unsafe {
-            from_ptr(f(self.into(), v1.into(), v2.into()))
+        if !spend(self.into(), 1, 0, 0) {
+            return self.placeholder(v1.get_type())
+        }
+        let (a, b) = (self.to_precision(v1), self.to_precision(v2));
+        let value = unsafe { f(self.into(), a.into(), b.into()) };
+        let result = self.emitted(value, a.get_type());
+        // arithmetic on a pointer from insn_alloca points into the same
+        // memory, however it was made, but comparisons don't
+        if result.get_type().get_size() == mem::size_of::<usize>() {
+            self.derive_alloca(&[v1, v2], result);
         }
+        result
     }
     #[inline(always)]
     fn insn_unop(&self,
@@ -913,15 +4575,124 @@ impl<'a> UncompiledFunction<'a> {
                         jit_function_t,
                         jit_value_t) -> jit_value_t)
                     -> &'a Val {
-        unsafe {
-            from_ptr(f(self.into(), value.into()))
+        if !spend(self.into(), 1, 0, 0) {
+            return self.placeholder(value.get_type())
+        }
+        let value = self.to_precision(value);
+        let result = unsafe { f(self.into(), value.into()) };
+        self.emitted(result, value.get_type())
+    }
+    #[inline(always)]
+    fn insn_math(&self, op: &'static str, value: &'a Val, f: UnOp) -> &'a Val {
+        match self.math_fallback(op, &[value]) {
+            Some(result) => result,
+            None => self.insn_unop(value, f)
+        }
+    }
+    /// Make an instruction with the optional LibJIT function named, or a call
+    /// to the native that does `op` if the linked LibJIT doesn't have it
+    fn insn_optional_math(&self, op: &'static str, value: &'a Val, function: &'static str) -> &'a Val {
+        match support::unary_insn(function) {
+            Some(f) => self.insn_math(op, value, f),
+            None => self.math_fallback(op, &[value]).unwrap_or_else(|| {
+                fail(self.into(), Unsupported { function: function }.to_string());
+                self.placeholder(value.get_type())
+            })
+        }
+    }
+    /// Make a call to the native in `mathlib` that does `op`, if the context
+    /// uses it instead of LibJIT's intrinsic or LibJIT's instruction for it is
+    /// missing
+    fn math_fallback(&self, op: &'static str, args: &[&'a Val]) -> Option<&'a Val> {
+        let fallback = ContextData::with_func(self.into(), |data| {
+            data.math_fallbacks.contains(&op) ||
+                data.unsupported.iter().any(|&name| support::find_insn(name).and_then(|(_, op)| op) == Some(op))
+        });
+        if !fallback || !args.iter().all(|arg| arg.get_type().is_primitive()) {
+            return None
+        }
+        let f64_t = get::<f64>();
+        let ty = args[0].get_type();
+        let mut args: Vec<&'a Val> = args.iter()
+            .map(|&arg| if arg.get_type().get_kind() == TypeKind::Float64 { arg } else { self.insn_convert(arg, &f64_t, false) })
+            .collect();
+        let result = self.insn_call_extern(&mathlib::native_name(op), &mut args, flags::CallFlags::NO_THROW);
+        Some(if ty.is_float() && ty.get_kind() != TypeKind::Float64 { self.insn_convert(result, ty, false) } else { result })
+    }
+    /// Make a call to the native in `wide` that does `op` on 64-bit integers,
+    /// if the context uses them and `v1` is one
+    fn long_helper(&self, op: &str, v1: &'a Val, v2: &'a Val) -> Option<&'a Val> {
+        if !ContextData::with_func(self.into(), |data| data.long_helpers) || (v1.is_constant() && v2.is_constant()) {
+            return None
+        }
+        let ty = v1.get_type();
+        let kind = ty.get_kind();
+        if kind != TypeKind::Long && kind != TypeKind::ULong {
+            return None
+        }
+        let unsigned = kind == TypeKind::ULong;
+        let (long_t, ulong_t, amount_t) = (get::<i64>(), get::<u64>(), get::<u32>());
+        let (name, value) = match op {
+            "div" | "rem" if v2.get_type() == ty => {
+                let name = if unsigned { format!("jit_ulong_{}", op) } else { format!("jit_long_{}", op) };
+                return Some(self.insn_call_extern(&name, &mut [v1, v2], flags::CallFlags::empty()))
+            },
+            "shl" => ("jit_long_shl", self.insn_convert(v1, &long_t, false)),
+            "shr" if unsigned => ("jit_ulong_shr", v1),
+            "shr" | "sshr" => ("jit_long_shr", self.insn_convert(v1, &long_t, false)),
+            "ushr" => ("jit_ulong_shr", self.insn_convert(v1, &ulong_t, false)),
+            _ => return None
+        };
+        if !v2.get_type().is_int() {
+            return None
+        }
+        let amount = self.insn_convert(v2, &amount_t, false);
+        let result = self.insn_call_extern(name, &mut [value, amount], flags::CallFlags::NO_THROW);
+        Some(self.insn_convert(result, ty, false))
+    }
+    /// Make a call to the native in `wide` that converts between a 64-bit
+    /// integer and a float, if the context uses them and it is that kind of
+    /// conversion
+    fn long_convert(&self, v: &'a Val, t: &Ty) -> Option<&'a Val> {
+        if v.is_constant() || !ContextData::with_func(self.into(), |data| data.long_helpers) {
+            return None
+        }
+        let (from, to) = (v.get_type().get_kind(), t.get_kind());
+        let f64_t = get::<f64>();
+        let no_throw = flags::CallFlags::NO_THROW;
+        if (from == TypeKind::Long || from == TypeKind::ULong) && t.is_float() {
+            let name = if from == TypeKind::Long { "jit_long_to_f64" } else { "jit_ulong_to_f64" };
+            let converted = self.insn_call_extern(name, &mut [v], no_throw);
+            Some(self.insn_convert(converted, t, false))
+        } else if v.get_type().is_float() && (to == TypeKind::Long || to == TypeKind::ULong) {
+            let name = if to == TypeKind::Long { "jit_f64_to_long" } else { "jit_f64_to_ulong" };
+            let wide = self.insn_convert(v, &f64_t, false);
+            Some(self.insn_call_extern(name, &mut [wide], no_throw))
+        } else {
+            None
+        }
+    }
+    /// Convert a float to the precision the context forces float arithmetic
+    /// to, if there is one
+    fn to_precision(&self, value: &'a Val) -> &'a Val {
+        let precision = ContextData::with_func(self.into(), |data| data.options.float_precision);
+        match precision {
+            Some(precision) if value.get_type().is_float() => {
+                let ty = precision.get_type();
+                if value.get_type() == ty {
+                    value
+                } else {
+                    self.insn_convert(value, ty, false)
+                }
+            },
+            _ => value
         }
     }
     #[inline(always)]
     /// Make instructions to run the block if the condition is met
     pub fn insn_if<B>(&self, cond: &'a Val, block: B) where B:FnOnce() {
         let mut after = Label::new(self);
-        self.insn_branch_if_not(cond, &mut after);
+        self.insn_branch_if_not(self.insn_normalize_bool(cond).get(), &mut after);
         block();
         self.insn_label(&mut after);
     }
@@ -929,7 +4700,7 @@ impl<'a> UncompiledFunction<'a> {
     /// Make instructions to run the block if the condition is not met
     pub fn insn_if_not<B>(&self, cond: &'a Val, block: B) where B:FnOnce() {
         let mut after = Label::new(self);
-        self.insn_branch_if(cond, &mut after);
+        self.insn_branch_if(self.insn_normalize_bool(cond).get(), &mut after);
         block();
         self.insn_label(&mut after);
     }
@@ -938,7 +4709,7 @@ impl<'a> UncompiledFunction<'a> {
     pub fn insn_if_else<A, B>(&self, cond: &'a Val, if_block: A, else_block: B) where A:FnOnce(), B:FnOnce() {
         let mut after = Label::new(self);
         let mut end = Label::new(self);
-        self.insn_branch_if_not(cond, &mut after);
+        self.insn_branch_if_not(self.insn_normalize_bool(cond).get(), &mut after);
         if_block();
         self.insn_branch(&mut end);
         self.insn_label(&mut after);
@@ -952,18 +4723,313 @@ impl<'a> UncompiledFunction<'a> {
         block();
         self.insn_branch(&mut start);
     }
-    /// Make instructions to run the block and continue running it so long
-    /// as the condition is met
-    pub fn insn_while<C, B>(&self, cond: C, block: B)
-        where C:FnOnce() -> &'a Val, B:FnOnce() {
+    /// Make instructions to run the block and continue running it so long
+    /// as the condition is met
+    pub fn insn_while<C, B>(&self, cond: C, block: B)
+        where C:FnOnce() -> &'a Val, B:FnOnce() {
+        let mut start = Label::new(self);
+        self.insn_label(&mut start);
+        let mut after = Label::new(self);
+        let cond_v = self.insn_normalize_bool(cond());
+        self.insn_branch_if_not(cond_v.get(), &mut after);
+        block();
+        self.insn_branch(&mut start);
+        self.insn_label(&mut after);
+    }
+    /// Make instructions to run `body` for each index from `start` up to
+    /// `end`, strip-mined into a main loop that handles `width` indices at a
+    /// time and a tail loop for the indices left over
+    ///
+    /// `body` is called twice while building, with the index value and how
+    /// many indices from it the iteration covers: `width` for the main loop
+    /// and 1 for the tail, so it can emit wide operations for the first and
+    /// scalar ones for the second. It must not store to the index. The main
+    /// loop runs while `width` more indices fit below `end`, so `end` should
+    /// be at least `width` below the biggest value of its type.
+    ///
+    /// ```rust
+    /// use jit::*;
+    /// let mut ctx = Context::<()>::new();
+    /// let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+    /// let (wide, narrow) = (func.new_lvalue(&get::<i32>()), func.new_lvalue(&get::<i32>()));
+    /// func.insn_store(&wide, func.insn_of(0i32));
+    /// func.insn_store(&narrow, func.insn_of(0i32));
+    /// func.insn_for_range_vectorized(func.insn_of(0i32), &func[0], 4, |_, width| {
+    ///     let count = if width == 4 { &wide } else { &narrow };
+    ///     func.insn_store(count, func.insn_add(func.insn_read(count).get(), func.insn_of(1i32)));
+    /// });
+    /// let (wide, narrow) = (func.insn_read(&wide).get(), func.insn_read(&narrow).get());
+    /// func.insn_return(func.insn_add(func.insn_mul(wide, func.insn_of(100i32)), narrow));
+    /// func.compile().with(|count: extern fn(i32) -> i32| assert_eq!(count(11), 203));
+    /// ```
+    pub fn insn_for_range_vectorized<F>(&self, start: &'a Val, end: &'a Val, width: usize, mut body: F)
+        where F: FnMut(&'a Val, usize) {
+        if width == 0 {
+            panic!("Strip-mined loops must handle at least one index at a time")
+        }
+        let ty = start.get_type();
+        let index = Val::new(self, ty);
+        self.store_local(index, start);
+        if width > 1 {
+            let step = self.insn_int_const(ty, width as i64);
+            self.insn_while(|| self.insn_leq(self.insn_add(index, step), end), || {
+                body(index, width);
+                self.store_local(index, self.insn_add(index, step));
+            });
+        }
+        let one = self.insn_int_const(ty, 1);
+        self.insn_while(|| self.insn_lt(index, end), || {
+            body(index, 1);
+            self.store_local(index, self.insn_add(index, one));
+        });
+    }
+    /// Make instructions to run `body` for each index from `start` up to
+    /// `end`, unrolled as far as the context's `UnrollLimits` allow
+    ///
+    /// If `start` and `end` are constants no more than `max_full` apart, the
+    /// loop is unrolled fully, with `body` called once for each index as a
+    /// constant. Otherwise `body` is called `factor` times for a main loop
+    /// that handles that many indices a trip, then once for a tail loop for
+    /// the indices left over, which is left out when the bounds are
+    /// constants that leave none. `body` must not store to the index.
+    ///
+    /// ```rust
+    /// use jit::*;
+    /// let mut ctx = Context::<()>::new();
+    /// ctx.set_unroll_limits(UnrollLimits { factor: 4, max_full: 8 });
+    /// let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+    /// let sum_var = func.new_lvalue(&get::<i32>());
+    /// let sum = sum_var.get_local().unwrap();
+    /// func.insn_store(&sum_var, func.insn_of(0i32));
+    /// func.insn_for_range(func.insn_of(0i32), &func[0], |index| {
+    ///     func.insn_store(&sum_var, func.insn_add(sum, index));
+    /// });
+    /// func.insn_return(sum);
+    /// func.compile().with(|sum: extern fn(i32) -> i32| {
+    ///     assert_eq!(sum(10), 45);
+    ///     assert_eq!(sum(3), 3);
+    /// });
+    /// ```
+    pub fn insn_for_range<F>(&self, start: &'a Val, end: &'a Val, mut body: F) where F: FnMut(&'a Val) {
+        let limits = ContextData::with_func(self.into(), |data| data.unroll_limits);
+        let ty = start.get_type();
+        let trips = match (int_constant(start), int_constant(end)) {
+            (Some(first), Some(last)) => Some(if last > first { last.wrapping_sub(first) as u64 } else { 0 }),
+            _ => None
+        };
+        if let Some(trips) = trips {
+            if trips <= limits.max_full as u64 {
+                let first = int_constant(start).unwrap();
+                for trip in 0..trips as i64 {
+                    body(self.insn_int_const(ty, first + trip));
+                }
+                return
+            }
+        }
+        let factor = cmp::max(limits.factor, 1);
+        let index = Val::new(self, ty);
+        self.store_local(index, start);
+        if factor > 1 && trips.map_or(true, |trips| trips >= factor as u64) {
+            let step = self.insn_int_const(ty, factor as i64);
+            self.insn_while(|| self.insn_leq(self.insn_add(index, step), end), || {
+                for offset in 0..factor {
+                    if offset == 0 {
+                        body(index);
+                    } else {
+                        let next = self.insn_add(index, self.insn_int_const(ty, offset as i64));
+                        body(if next.get_type() == ty { next } else { self.insn_convert(next, ty, false) });
+                    }
+                }
+                self.store_local(index, self.insn_add(index, step));
+            });
+        }
+        if factor == 1 || trips.map_or(true, |trips| trips % factor as u64 != 0) {
+            let one = self.insn_int_const(ty, 1);
+            self.insn_while(|| self.insn_lt(index, end), || {
+                body(index);
+                self.store_local(index, self.insn_add(index, one));
+            });
+        }
+    }
+    /// Build a region of code with `body`, then run the code `cleanup` builds
+    /// when it is left, whether by carrying on past its end or by an
+    /// exception, like a destructor for what the region acquired
+    ///
+    /// The cleanup is built as a finally block. If an exception leaves the
+    /// region, the catch block runs it before anything else, and a function
+    /// with no catch block of its own is given one that runs it, then throws
+    /// the exception on to the caller. Returning or branching out of the
+    /// region skips the cleanup, so the region should only be left by
+    /// reaching its end. Regions can be nested, and are cleaned up from the
+    /// innermost. Regions in the catch block itself are only cleaned up on
+    /// reaching their end.
+    ///
+    /// ```rust
+    /// use jit::*;
+    /// let mut ctx = Context::<()>::new();
+    /// let func = UncompiledFunction::new(&mut ctx, &get::<fn(&'static i32) -> i32>());
+    /// let result_var = func.new_lvalue(&get::<i32>());
+    /// let result = result_var.get_local().unwrap();
+    /// func.with_cleanup(|| {
+    ///     func.insn_store_relative(&func[0], 4, func.insn_of(1i32));
+    ///     func.insn_store(&result_var, func.insn_div(func.insn_of(12i32), func.insn_load_relative(&func[0], 0, &get::<i32>())));
+    /// }, || {
+    ///     func.insn_store_relative(&func[0], 4, func.insn_of(0i32));
+    /// });
+    /// func.insn_return(result);
+    /// let div = func.compile().closure_result::<*mut i32, i32>();
+    /// let mut state = [0i32, 0];
+    /// assert!(div(state.as_mut_ptr()).is_err());
+    /// assert_eq!(state[1], 0);
+    /// state[0] = 3;
+    /// assert_eq!(div(state.as_mut_ptr()), Ok(4));
+    /// assert_eq!(state[1], 0);
+    /// ```
+    pub fn with_cleanup<B, C>(&self, body: B, cleanup: C) where B:FnOnce(), C:FnOnce() {
+        self.insn_uses_catcher();
+        let active = Val::new(self, &get::<i32>());
+        active.set_volatile();
+        // the flag has to be clear wherever an exception comes from, so it is
+        // cleared in front of everything else
+        let (mut init, mut after) = (Label::new(self), Label::new(self));
+        self.insn_branch(&mut after);
+        self.insn_label(&mut init);
+        self.store_local(active, self.insn_of(0i32));
+        self.insn_label(&mut after);
+        if let Err(error) = self.move_blocks_to_start(&init, &after) {
+            panic!("The flag of a cleanup region couldn't be cleared at the start: {}", error)
+        }
+        let mut finally = Label::new(self);
+        FunctionData::with(self.into(), |data| data.cleanups.push((active.into(), *finally as jit_label_t)));
+        self.store_local(active, self.insn_of(1i32));
+        body();
+        self.store_local(active, self.insn_of(0i32));
+        self.insn_call_finally(&mut finally);
+        let mut done = Label::new(self);
+        self.insn_branch(&mut done);
+        self.insn_start_finally(&mut finally);
+        cleanup();
+        self.insn_return_from_finally();
+        self.insn_label(&mut done);
+    }
+    /// Give a function with cleanup regions, an exit hook or pinned values but
+    /// no catch block one that cleans up after them, calls the exit hook,
+    /// unpins the values and throws the exception on to the caller
+    fn finish_cleanups(&self) {
+        if !FunctionData::with(self.into(), |data| {
+            (!data.cleanups.is_empty() || data.exit_hook.is_some() || data.pin_roots.is_some()) && !data.catcher
+        }) {
+            return
+        }
+        if self.get_current().map_or(false, |block| !block.ends_in_dead()) {
+            self.insn_default_return();
+        }
+        self.insn_start_catcher();
+        self.insn_rethrow_unhandled();
+    }
+    /// Build a cold region of code with `block`, then move it out into a
+    /// separate function which is called in its place, to keep this function
+    /// small
+    ///
+    /// The values from the rest of this function that the region uses are
+    /// found by walking its instructions, and are passed to the new function
+    /// as parameters. The region should only affect the rest of this function
+    /// through memory, since assignments it makes to values can't be seen
+    /// after it. If the region can't be moved because it returns, branches out,
+    /// makes calls or assigns to values from outside it, it is left inline and
+    /// `None` is returned.
+    ///
+    /// ```rust
+    /// use jit::*;
+    /// let mut ctx = Context::<()>::new();
+    /// let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32, &'static i32)>());
+    /// let cond = func.insn_lt(&func[0], func.insn_of(0i32));
+    /// func.insn_if(cond, || {
+    ///     assert!(func.outline(|| {
+    ///         func.insn_store_relative(&func[1], 0, func.insn_neg(&func[0]));
+    ///     }).is_some());
+    /// });
+    /// func.insn_default_return();
+    /// ```
+    pub fn outline<B>(&self, block: B) -> Option<CompiledFunction<'a>> where B:FnOnce() {
         let mut start = Label::new(self);
-        self.insn_label(&mut start);
         let mut after = Label::new(self);
-        let cond_v = cond();
-        self.insn_branch_if_not(cond_v, &mut after);
+        let mut done = Label::new(self);
+        self.insn_branch(&mut after);
+        self.insn_label(&mut start);
         block();
-        self.insn_branch(&mut start);
+        self.insn_branch(&mut done);
         self.insn_label(&mut after);
+        let outlined = self.outline_region(*start, *after, *done);
+        match outlined {
+            Some((func, mut args)) => {
+                self.insn_call(None, &func, None, &mut args, flags::CallFlags::empty());
+                self.insn_label(&mut done);
+                Some(func)
+            },
+            None => {
+                self.insn_branch(&mut start);
+                self.insn_label(&mut done);
+                None
+            }
+        }
+    }
+    fn outline_region(&self, start: u64, end: u64, exit: u64) -> Option<(CompiledFunction<'a>, Vec<&'a Val>)> {
+        let this:jit_function_t = self.into();
+        let (first, last) = unsafe {
+            (jit_block_from_label(this, start as jit_label_t), jit_block_from_label(this, end as jit_label_t))
+        };
+        let blocks:Vec<jit_block_t> = self.blocks()
+            .map(|block| block.into())
+            .skip_while(|&block| block != first)
+            .take_while(|&block| block != last)
+            .collect();
+        let mut live:Vec<jit_value_t> = Vec::new();
+        for &block in &blocks {
+            let block:Block = from_ptr(block);
+            for insn in block.iter() {
+                let dest = insn.get_dest().map(|dest| dest.into());
+                let mut used:Vec<jit_value_t> = insn.get_value1().into_iter()
+                    .chain(insn.get_value2().into_iter())
+                    .map(|value| value.into())
+                    .collect();
+                if insn.dest_is_value() {
+                    used.extend(dest.into_iter());
+                } else if let Some(dest) = dest {
+                    if unsafe { !blocks.contains(&jit_value_get_block(dest)) } {
+                        return None
+                    }
+                }
+                for value in used {
+                    let outside = unsafe {
+                        jit_value_is_constant(value) == 0 && !blocks.contains(&jit_value_get_block(value))
+                    };
+                    if outside && !live.contains(&value) {
+                        live.push(value);
+                    }
+                }
+            }
+        }
+        let args:Vec<&'a Val> = live.iter().map(|&value| from_ptr(value)).collect();
+        let mut params:Vec<&Ty> = args.iter().map(|arg| arg.get_type()).collect();
+        let sig = Type::new_signature(Abi::CDecl, consts::get_void(), &mut params);
+        let mut func:UncompiledFunction<'a> = unsafe {
+            from_ptr_oom(jit_function_create(jit_function_get_context(this), (&sig).into()))
+        };
+        func.owned = true;
+        func.apply_options();
+        let rebuilt = {
+            let mut replay = Replay::new(this, &func, &blocks, Some(exit as jit_label_t));
+            for (index, &value) in live.iter().enumerate() {
+                let param = unsafe { from_ptr(jit_value_get_param((&func).into(), index as c_uint)) };
+                replay.values.insert(value, param);
+            }
+            replay.run(&blocks)
+        };
+        match rebuilt {
+            Ok(()) => Some((func.compile(), args)),
+            Err(_) => None
+        }
     }
     #[inline(always)]
     /// Set the optimization level of the function, where the bigger the level,
@@ -974,6 +5040,13 @@ impl<'a> UncompiledFunction<'a> {
         }
     }
     #[inline(always)]
+    /// Get the optimization level of the function
+    pub fn get_optimization_level(&self) -> c_uint {
+        unsafe {
+            jit_function_get_optimization_level(self.into())
+        }
+    }
+    #[inline(always)]
     /// Get the max optimization level
     pub fn get_max_optimization_level() -> c_uint {
         unsafe {
@@ -981,6 +5054,16 @@ impl<'a> UncompiledFunction<'a> {
         }
     }
     #[inline(always)]
+    /// Mark this function as a new version of `older`, like a more optimized
+    /// tier or a respecialization, so `diff_ir` compares them once it is
+    /// compiled
+    ///
+    /// This does nothing unless the context keeps IR history and `older`
+    /// was compiled while it did.
+    pub fn recompiles(&self, older: &Func) {
+        let ir = FunctionData::with(older.into(), |data| data.ir.clone());
+        FunctionData::with(self.into(), |data| data.previous_ir = ir);
+    }
     /// Make this function a candidate for recompilation
     pub fn set_recompilable(&self) {
         unsafe {
@@ -993,24 +5076,934 @@ impl<'a> UncompiledFunction<'a> {
             from_ptr_opt(jit_function_get_entry(self.into()))
         }
     }
+    /// Iterate through the blocks of this function in order
+    pub fn blocks(&self) -> Blocks<'a> {
+        Blocks::new(self.into())
+    }
+    /// Get the block `label` is placed at, if it has been placed
+    pub fn get_block(&self, label: &Label<'a>) -> Option<Block<'a>> {
+        unsafe {
+            from_ptr_opt(jit_block_from_label(self.into(), **label as jit_label_t))
+        }
+    }
+    /// Find where the blocks from the one `from` is placed at up to the one
+    /// `to` is placed at are, checking nothing falls into them
+    fn block_range(&self, from: &Label<'a>, to: &Label<'a>) -> Result<(Vec<Block<'a>>, usize, usize), MoveError> {
+        let first = try!(self.get_block(from).ok_or(MoveError::Unplaced(**from)));
+        let end = try!(self.get_block(to).ok_or(MoveError::Unplaced(**to)));
+        let blocks: Vec<Block<'a>> = self.blocks().collect();
+        let (first, end): (jit_block_t, jit_block_t) = (first.into(), end.into());
+        let first = blocks.iter().position(|&block| { let block: jit_block_t = block.into(); block == first }).unwrap();
+        let end = blocks.iter().position(|&block| { let block: jit_block_t = block.into(); block == end }).unwrap();
+        if end <= first {
+            Err(MoveError::Empty)
+        } else if first == 0 {
+            Err(MoveError::Entry)
+        } else if !blocks[first - 1].ends_in_dead() {
+            Err(MoveError::FallsThrough)
+        } else {
+            Ok((blocks, first, end))
+        }
+    }
+    /// Move the blocks from the one `from` is placed at up to, but not
+    /// including, the one `to` is placed at to the end of the function, so
+    /// code that is rarely run, like error handling, is kept out of the way
+    /// of the code around it
+    ///
+    /// Building carries on in a new block after the ones moved. So nothing
+    /// goes somewhere else once they are moved, the blocks before the range
+    /// and at the end of it, and the last block of the function, must end in
+    /// a branch or a return rather than carrying on into the next block.
+    ///
+    /// ```rust
+    /// use jit::*;
+    /// let mut ctx = Context::<()>::new();
+    /// let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+    /// let (mut cold, mut hot) = (Label::new(&func), Label::new(&func));
+    /// func.insn_branch_if(func.insn_lt(&func[0], func.insn_of(0i32)), &mut cold);
+    /// func.insn_branch(&mut hot);
+    /// func.insn_label(&mut cold);
+    /// func.insn_return(func.insn_of(0i32));
+    /// func.insn_label(&mut hot);
+    /// func.insn_return(func.insn_mul(&func[0], func.insn_of(2i32)));
+    /// func.move_blocks_to_end(&cold, &hot).unwrap();
+    /// func.compile().with(|double: extern fn(i32) -> i32| {
+    ///     assert_eq!(double(4), 8);
+    ///     assert_eq!(double(-4), 0);
+    /// });
+    /// ```
+    pub fn move_blocks_to_end(&self, from: &Label<'a>, to: &Label<'a>) -> Result<(), MoveError> {
+        let (blocks, _, end) = try!(self.block_range(from, to));
+        if !blocks[end - 1].ends_in_dead() || !blocks[blocks.len() - 1].ends_in_dead() {
+            return Err(MoveError::FallsThrough)
+        }
+        unsafe {
+            jit_insn_move_blocks_to_end(self.into(), **from as jit_label_t, **to as jit_label_t);
+        }
+        Ok(())
+    }
+    /// Move the blocks from the one `from` is placed at up to, but not
+    /// including, the one `to` is placed at to the start of the function,
+    /// after the entry block and any blocks moved there before, so they run
+    /// first, like setup code that was only found to be needed later on
+    ///
+    /// The last block moved carries on into the code that came after the
+    /// entry block, unless it branches or returns. The block before the range
+    /// must not carry on into it, since it would carry on into the block `to`
+    /// is placed at instead.
+    pub fn move_blocks_to_start(&self, from: &Label<'a>, to: &Label<'a>) -> Result<(), MoveError> {
+        try!(self.block_range(from, to));
+        unsafe {
+            jit_insn_move_blocks_to_start(self.into(), **from as jit_label_t, **to as jit_label_t);
+        }
+        Ok(())
+    }
+    /// Make a listing of the instructions in this function that numbers
+    /// values and labels in the order they appear, so unlike the `Debug`
+    /// output it is the same from run to run and can be compared against
+    /// golden files
+    ///
+    /// Constants are written by value, except pointer constants which are
+    /// only written by type.
+    pub fn dump_stable(&self) -> String {
+        dump::stable(self)
+    }
+    /// Hash the listing from `dump_stable`, so two functions built the same
+    /// way have the same fingerprint, even in different contexts or runs
+    ///
+    /// ```rust
+    /// use jit::*;
+    /// fn build(ctx: &mut Context<()>) -> u64 {
+    ///     let func = UncompiledFunction::new(ctx, &get::<fn(i32) -> i32>());
+    ///     let x = &func[0];
+    ///     let doubled = func.insn_add(x, x);
+    ///     func.insn_return(doubled);
+    ///     func.fingerprint()
+    /// }
+    /// let mut ctx1 = Context::<()>::new();
+    /// let mut ctx2 = Context::<()>::new();
+    /// assert_eq!(build(&mut ctx1), build(&mut ctx2));
+    /// ```
+    pub fn fingerprint(&self) -> u64 {
+        dump::fingerprint(&self.dump_stable())
+    }
+    /// Make instructions that push a frame with the descriptor given onto the
+    /// shadow stack of this function's context, so the host can walk the
+    /// frames with `Context::shadow_frames` without unwinding
+    ///
+    /// This should be made at the start of the function. The frame must be
+    /// popped with `insn_shadow_pop` before each return, and
+    /// `insn_shadow_catcher` must be called once after the rest of the body so
+    /// it is popped when an exception is thrown too.
+    ///
+    /// ```rust
+    /// use jit::*;
+    /// extern fn depth(ctx: &Context<()>) -> usize {
+    ///     ctx.shadow_frames().count()
+    /// }
+    /// let mut ctx = Context::<()>::new();
+    /// let ctx_ptr = &ctx as *const Context<()>;
+    /// let func = UncompiledFunction::new(&mut ctx, &get::<fn(usize) -> usize>());
+    /// let frame = func.insn_shadow_push("depth".as_ptr() as *const _);
+    /// let result = func.insn_call_native1(None, depth, &get::<fn(usize) -> usize>(),
+    ///                                     [&func[0]], flags::CallFlags::empty());
+    /// func.insn_shadow_pop(frame);
+    /// func.insn_return(result);
+    /// func.insn_shadow_catcher(frame);
+    /// func.compile().with(|depth: extern fn(*const Context<()>) -> usize| assert_eq!(depth(ctx_ptr), 1));
+    /// ```
+    pub fn insn_shadow_push(&self, descriptor: *const c_void) -> ShadowFrame<'a> {
+        let ptr_type = consts::get_void_ptr();
+        let ptr_size = mem::size_of::<usize>();
+        self.insn_uses_catcher();
+        let ctx = unsafe { jit_function_get_context(self.into()) };
+        let slot_fn:extern fn(jit_context_t) -> *mut *const shadow::Frame = shadow::slot;
+        let address = self.insn_call_native1(Some("shadow_slot"), slot_fn, &get::<fn(usize) -> usize>(),
+                                             [self.insn_of(ctx as usize)], flags::CallFlags::NO_THROW);
+        let slot = Val::new(self, ptr_type);
+        slot.set_volatile();
+        self.store_local(slot, self.insn_convert(address, ptr_type, false));
+        let frame = Val::new(self, ptr_type);
+        frame.set_volatile();
+        let size = self.insn_of(mem::size_of::<shadow::Frame>() as c_uint);
+        self.store_local(frame, self.insn_alloca(size));
+        self.insn_store_relative(frame, 0, self.insn_load_relative(slot, 0, ptr_type));
+        self.insn_store_relative(frame, ptr_size, self.insn_convert(self.insn_of(descriptor as usize), ptr_type, false));
+        self.insn_store_relative(slot, 0, frame);
+        ShadowFrame {
+            slot: slot,
+            frame: frame
+        }
+    }
+    /// Make instructions that pop the frame given off the shadow stack
+    pub fn insn_shadow_pop(&self, frame: ShadowFrame<'a>) {
+        let parent = self.insn_load_relative(frame.frame, 0, consts::get_void_ptr());
+        self.insn_store_relative(frame.slot, 0, parent);
+    }
+    /// Make the catcher that pops the frame given when an exception is
+    /// thrown, then throws it on
+    ///
+    /// Nothing can be added to the function after this.
+    pub fn insn_shadow_catcher(&self, frame: ShadowFrame<'a>) {
+        self.insn_start_catcher();
+        self.insn_shadow_pop(frame);
+        self.insn_rethrow_unhandled();
+    }
+    /// Get the values computed in this function that no instruction uses
+    ///
+    /// This usually means a result was dropped by mistake. Results of calls
+    /// aren't included, since they're often ignored on purpose.
+    ///
+    /// ```rust
+    /// use jit::*;
+    /// let mut ctx = Context::<()>::new();
+    /// let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32, i32) -> i32>());
+    /// let sum = func.insn_add(&func[0], &func[1]);
+    /// func.insn_return(&func[0]);
+    /// assert_eq!(func.unused_values(), vec![sum]);
+    /// ```
+    pub fn unused_values(&self) -> Vec<&'a Val> {
+        let mut used = HashSet::new();
+        let mut defined = Vec::new();
+        for block in self.blocks() {
+            for insn in block.iter() {
+                for value in insn.get_value1().into_iter().chain(insn.get_value2().into_iter()) {
+                    used.insert(value as *const Val);
+                }
+                if let Some(dest) = insn.get_dest() {
+                    let name = insn.get_name();
+                    if insn.dest_is_value() {
+                        used.insert(dest as *const Val);
+                    } else if !name.starts_with("call") && name != "return_reg" &&
+                            !defined.iter().any(|&other| other as *const Val == dest as *const Val) {
+                        defined.push(dest);
+                    }
+                }
+            }
+        }
+        defined.into_iter().filter(|&value| !used.contains(&(value as *const Val))).collect()
+    }
+    /// Get the values this function uses somewhere they might not have been
+    /// set yet, on some path through the branches that were made
+    ///
+    /// This catches a value made in one branch being used after the branches
+    /// join, which LibJIT would compile to garbage. Set `strict_ssa` in the
+    /// context's options to check this whenever a function is compiled.
+    ///
+    /// ```rust
+    /// use jit::*;
+    /// let mut ctx = Context::<()>::new();
+    /// let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+    /// let mut doubled = None;
+    /// func.insn_if(func.insn_gt(&func[0], func.insn_of(0i32)), || {
+    ///     doubled = Some(func.insn_add(&func[0], &func[0]));
+    /// });
+    /// func.insn_return(doubled.unwrap());
+    /// assert_eq!(func.undefined_uses(), vec![doubled.unwrap()]);
+    /// ```
+    pub fn undefined_uses(&self) -> Vec<&'a Val> {
+        ssa::undefined_uses(self.into())
+    }
+    /// Give the function the highest optimization level if the function
+    /// exported as `name` was hot in the profile given to the context's
+    /// `set_hot_profile`, giving whether it was
+    ///
+    /// This should be done before compiling, for functions that will be
+    /// exported under the same name as in the run that was profiled.
+    ///
+    /// ```rust
+    /// use jit::*;
+    /// let mut ctx = Context::<()>::new();
+    /// ctx.set_hot_profile(CallProfile::parse("parse\t5000\nrender\t3\n"), 1000);
+    /// let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+    /// assert!(func.warm_up("parse"));
+    /// assert_eq!(func.get_optimization_level(), UncompiledFunction::get_max_optimization_level());
+    /// assert!(!func.warm_up("render"));
+    /// ```
+    pub fn warm_up(&self, name: &str) -> bool {
+        let hot = ContextData::with_func(self.into(), |data| match data.hot_profile {
+            Some(_) if data.deterministic => false,
+            Some((ref profile, threshold)) => profile.get(name).map_or(false, |count| count >= threshold),
+            None => false
+        });
+        if hot {
+            self.set_optimization_level(UncompiledFunction::get_max_optimization_level());
+        }
+        hot
+    }
+    /// Get the parameters of the function as typed values, if it takes
+    /// parameters laid out like the types in the tuple `P`, or what differs
+    /// if it doesn't
+    ///
+    /// ```rust
+    /// use jit::*;
+    /// let mut ctx = Context::<()>::new();
+    /// let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32, f64) -> f64>());
+    /// assert!(func.params_as::<(i32, i32)>().is_err());
+    /// let (x, y) = func.params_as::<(i32, f64)>().unwrap();
+    /// func.insn_return(func.insn_mul(func.insn_convert(x.get(), &get::<f64>(), false), y.get()));
+    /// func.compile();
+    /// ```
+    pub fn params_as<P>(&self) -> Result<P::Values, SignatureMismatch> where P: ParamTypes<'a> {
+        P::get_values(self)
+    }
     /// Get the current block of this function
     pub fn get_current(&self) -> Option<Block<'a>> {
         unsafe {
             from_ptr_opt(jit_function_get_current(self.into()))
         }
     }
+    /// Check if the function can be compiled yet
+    pub fn can_compile(&self) -> Result<(), CompileError> {
+        unsafe {
+            let parent = jit_function_get_nested_parent(self.into());
+            if !parent.is_null() && jit_function_is_compiled(parent) == 0 {
+                Err(CompileError::ParentNotCompiled)
+            } else {
+                Ok(())
+            }
+        }
+    }
+    /// Get the labels that instructions in the function branch to but that
+    /// were never placed with `insn_label`, which compiling checks for
+    ///
+    /// ```rust
+    /// use jit::*;
+    /// let mut ctx = Context::<()>::new();
+    /// let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+    /// let mut missing = Label::new(&func);
+    /// func.name_label(&missing, "missing");
+    /// func.set_current_location(1, 4, 2);
+    /// func.insn_branch_if(&func[0], &mut missing);
+    /// func.insn_return(&func[0]);
+    /// let unplaced = func.unplaced_labels();
+    /// assert_eq!(unplaced.len(), 1);
+    /// assert_eq!(unplaced[0].name, Some("missing".to_owned()));
+    /// assert_eq!(unplaced[0].location.map(|loc| loc.line), Some(4));
+    /// match func.try_compile() {
+    ///     Err(CompileError::UnplacedLabels(labels)) => assert_eq!(labels, unplaced),
+    ///     _ => panic!("compiled with an unplaced label")
+    /// }
+    /// ```
+    pub fn unplaced_labels(&self) -> Vec<UnplacedLabel> {
+        label::unplaced(self.into())
+    }
+    /// Give `label` a name to report it by if it is never placed
+    pub fn name_label(&self, label: &Label<'a>, name: &str) {
+        FunctionData::with(self.into(), |data| {
+            let label = **label as jit_label_t;
+            data.label_names.retain(|&(other, _)| other != label);
+            data.label_names.push((label, name.to_owned()));
+        })
+    }
+    /// Check the function can be compiled yet, that every label it branches
+    /// to was placed, and that it passes the strict checks if the context
+    /// asks for them
+    fn check_compile(&self) -> Result<(), CompileError> {
+        self.finish_cleanups();
+        if let Some(error) = self.get_poison() {
+            return Err(CompileError::Poisoned(error))
+        }
+        try!(self.can_compile());
+        let unplaced = self.unplaced_labels();
+        if !unplaced.is_empty() {
+            return Err(CompileError::UnplacedLabels(unplaced))
+        }
+        let strict = ContextData::with_func(self.into(), |data| data.options.strict_ssa);
+        if strict && !self.undefined_uses().is_empty() {
+            return Err(CompileError::UndefinedUse)
+        }
+        Ok(())
+    }
     #[inline(always)]
     /// Compile the function
+    ///
+    /// If it can't be compiled, like when it is poisoned or branches to a
+    /// label that was never placed, it is left uncompiled and calling it
+    /// throws `BuiltinException::CompileError`. Use `try_compile` to find out
+    /// why instead.
+    ///
+    /// ```rust
+    /// use jit::*;
+    /// let mut ctx = Context::<()>::new();
+    /// ctx.set_options(ContextOptions {
+    ///     defer_errors: true,
+    ///     .. ContextOptions::default()
+    /// });
+    /// let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+    /// func.insn_return(func.insn_sqrt(&func[0]));
+    /// let call = func.compile().closure_result::<i32, i32>();
+    /// assert_eq!(call(4), Err(JitException::Builtin(BuiltinException::CompileError)));
+    /// ```
     pub fn compile(self) -> CompiledFunction<'a> {
         if !self.owned {
             panic!("The function must be owned")
         }
+        let ptr: jit_function_t = (&self).into();
+        let compiled = match self.check_compile() {
+            Ok(()) => self.compile_in_place().map_err(|_| CompileError::Failed),
+            Err(error) => {
+                mem::forget(self);
+                Err(error)
+            }
+        };
+        match compiled {
+            Ok(func) => func,
+            Err(error) => {
+                events::warning(ptr, &format!("left uncompiled, so calling it throws: {}", error));
+                from_ptr(ptr)
+            }
+        }
+    }
+    /// Compile the function, or give the reason it couldn't be compiled
+    ///
+    /// If it couldn't, the function is abandoned.
+    pub fn try_compile(self) -> Result<CompiledFunction<'a>, CompileError> {
+        if !self.owned {
+            panic!("The function must be owned")
+        }
+        try!(self.check_compile());
+        self.compile_whole()
+    }
+    /// Rebuild the function with the instructions in its loops that work out
+    /// the same value on every iteration moved in front of the loop, giving
+    /// the rebuilt function, or this one if there was nothing to move
+    ///
+    /// Loops are found from the branches back to earlier blocks that the
+    /// structured control flow helpers make, and only loops that are entered
+    /// by falling into them are changed. Only arithmetic, comparisons and
+    /// conversions that can't trap are moved, so nothing is done the loop
+    /// wouldn't have done. This is never done unless it is asked for.
+    ///
+    /// Like `compile_split`, this makes a new function, so don't do it to a
+    /// function that other functions already call. The tables, counters and
+    /// buffers this function's code points into move over to the new one.
+    /// Functions that do something that can't be rebuilt, like making calls
+    /// or taking the address of a value, or that have weak references, are
+    /// left as they are, with the reason `check_rebuild` gives logged.
+    ///
+    /// ```rust
+    /// use jit::*;
+    /// let mut ctx = Context::<()>::new();
+    /// let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+    /// let total_var = func.new_lvalue(&get::<i32>());
+    /// let total = total_var.get_local().unwrap();
+    /// func.insn_store(&total_var, func.insn_of(0i32));
+    /// func.insn_while(|| func.insn_lt(total, func.insn_of(100i32)), || {
+    ///     let step = func.insn_mul(&func[0], func.insn_of(3i32));
+    ///     func.insn_store(&total_var, func.insn_add(total, step));
+    /// });
+    /// func.insn_return(total);
+    /// let func = func.hoist_invariants();
+    /// func.compile().with(|count: extern fn(i32) -> i32| {
+    ///     assert_eq!(count(1), 102);
+    /// });
+    /// ```
+    pub fn hoist_invariants(self) -> UncompiledFunction<'a> {
+        if let Err(error) = self.check_rebuild() {
+            events::warning((&self).into(), &format!("hoist_invariants skipped: {}", error));
+            return self
+        }
+        match self.hoist() {
+            Some(func) => {
+                FunctionData::adopt((&self).into(), (&func).into());
+                func
+            },
+            None => self
+        }
+    }
+    fn hoist(&self) -> Option<UncompiledFunction<'a>> {
+        let graph: BlockGraph<'a> = BlockGraph::new(self);
+        let blocks = graph.get_blocks();
+        let loops = licm::find(&graph);
+        let hoisted = licm::invariants(blocks, &loops);
+        if hoisted.is_empty() {
+            return None
+        }
+        self.rebuild_blocks(|replay, i, block| {
+            for (index, l) in loops.iter().enumerate().filter(|&(_, l)| l.header == i) {
+                for insn in blocks[l.header..l.end + 1].iter().flat_map(|block| block.iter()) {
+                    let ptr: jit_insn_t = insn.into();
+                    if hoisted.get(&ptr) == Some(&index) {
+                        try!(replay.rebuild_insn(insn));
+                    }
+                }
+            }
+            replay.start_block(block.into());
+            for insn in block.iter() {
+                let ptr: jit_insn_t = insn.into();
+                if !hoisted.contains_key(&ptr) {
+                    try!(replay.rebuild_insn(insn));
+                }
+            }
+            Ok(())
+        })
+    }
+    /// Check if passes that rebuild the function, like `eliminate_common`,
+    /// `hoist_invariants` and `rewrite`, can do so, giving the reason they
+    /// would leave it as it is if they can't
+    ///
+    /// ```rust
+    /// use jit::*;
+    /// let mut ctx = Context::<()>::new();
+    /// let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+    /// assert_eq!(func.check_rebuild(), Ok(()));
+    /// let local = func.new_lvalue(&get::<i32>());
+    /// func.insn_address_of(&local);
+    /// func.insn_return(&func[0]);
+    /// assert_eq!(func.check_rebuild(), Err(RebuildError::AddressTaken));
+    /// ```
+    pub fn check_rebuild(&self) -> Result<(), RebuildError> {
+        let this: jit_function_t = self.into();
+        if let Some(error) = licm::blocker(this) {
+            Err(error)
+        } else if FunctionData::with(this, |data| data.alive.is_some()) {
+            Err(RebuildError::WeakReferences)
+        } else {
+            Ok(())
+        }
+    }
+    /// Rebuild the function into a new one, with `each` rebuilding each block
+    /// given its index, or give `None` if anything can't be rebuilt
+    fn rebuild_blocks<F>(&self, mut each: F) -> Option<UncompiledFunction<'a>>
+        where F: for<'b> FnMut(&mut Replay<'b, 'a>, usize, Block<'a>) -> Result<(), String> {
+        let this: jit_function_t = self.into();
+        if self.check_rebuild().is_err() {
+            return None
+        }
+        let sig = self.get_signature();
+        let ret = sig.get_return().unwrap();
+        let func = self.inherit(self.bare(sig));
+        {
+            let blocks: Vec<Block<'a>> = self.blocks().collect();
+            let raw_blocks: Vec<jit_block_t> = blocks.iter().map(|&block| block.into()).collect();
+            let result = if ret.get_kind() == TypeKind::Void { None } else { Some(Val::new(&func, ret)) };
+            let mut replay = Replay::new(this, &func, &raw_blocks, None);
+            for index in 0..sig.params().count() {
+                unsafe {
+                    let param = jit_value_get_param((&func).into(), index as c_uint);
+                    replay.values.insert(jit_value_get_param(this, index as c_uint), from_ptr(param));
+                }
+            }
+            replay.set_result(result);
+            for (i, &block) in blocks.iter().enumerate() {
+                if each(&mut replay, i, block).is_err() {
+                    return None
+                }
+            }
+            replay.finish();
+            match result {
+                Some(result) => func.insn_return(result),
+                None => func.insn_default_return()
+            }
+        }
+        Some(func)
+    }
+    /// Rebuild the function with instructions that work out a value already
+    /// worked out earlier in the same block left out, using the earlier value
+    /// instead, giving the rebuilt function, or this one if it couldn't be
+    /// rebuilt
+    ///
+    /// Arithmetic, comparisons and conversions are matched by their opcode
+    /// and operands, with constants matched by value. Loads are matched too,
+    /// until a store to memory. Assigning to a local forgets everything
+    /// worked out from it.
+    ///
+    /// Like `compile_split`, this makes a new function, so don't do it to a
+    /// function that other functions already call. The tables, counters and
+    /// buffers this function's code points into move over to the new one.
+    /// Functions that do something that can't be rebuilt, like making calls
+    /// or taking the address of a value, or that have weak references, are
+    /// left as they are, with the reason `check_rebuild` gives logged.
+    ///
+    /// ```rust
+    /// use jit::*;
+    /// let mut ctx = Context::<()>::new();
+    /// let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+    /// let left = func.insn_mul(&func[0], func.insn_of(3i32));
+    /// let right = func.insn_mul(&func[0], func.insn_of(3i32));
+    /// func.insn_return(func.insn_add(left, right));
+    /// let func = func.eliminate_common();
+    /// assert_eq!(func.dump_stable().matches("imul").count(), 1);
+    /// func.compile().with(|sixfold: extern fn(i32) -> i32| {
+    ///     assert_eq!(sixfold(2), 12);
+    /// });
+    /// ```
+    pub fn eliminate_common(self) -> UncompiledFunction<'a> {
+        if let Err(error) = self.check_rebuild() {
+            events::warning((&self).into(), &format!("eliminate_common skipped: {}", error));
+            return self
+        }
+        let rebuilt = self.rebuild_blocks(|replay, _, block| {
+            let mut table = cse::Table::new();
+            replay.start_block(block.into());
+            for insn in block.iter() {
+                if let Some(value) = table.find(insn, &replay.values) {
+                    replay.values.insert(insn.get_dest().unwrap().into(), value);
+                    continue
+                }
+                try!(replay.rebuild_insn(insn));
+                table.record(insn, &replay.values);
+            }
+            Ok(())
+        });
+        match rebuilt {
+            Some(func) => {
+                FunctionData::adopt((&self).into(), (&func).into());
+                func
+            },
+            None => self
+        }
+    }
+    /// Rebuild the function into a new one, running `visit` on each
+    /// instruction to say whether to keep it, delete it, or replace it with
+    /// instructions it makes through the `Splice`, or give `None` if the
+    /// function couldn't be rebuilt
+    ///
+    /// The visitor is given the instruction from this function, whose
+    /// operands it can map to the new function with `Splice::map`. Anything
+    /// it makes comes before the instruction if it is kept. Replacing an
+    /// instruction makes later uses of its result use the value given
+    /// instead, and any value can be re-pointed like that with
+    /// `Splice::repoint`.
+    ///
+    /// Like `eliminate_common`, this leaves this function as it is, and fails
+    /// on functions that do something that can't be rebuilt, like making
+    /// calls or taking the address of a value, for the reason
+    /// `check_rebuild` gives.
+    ///
+    /// ```rust
+    /// use jit::*;
+    /// let mut ctx = Context::<()>::new();
+    /// let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+    /// func.insn_return(func.insn_mul(&func[0], func.insn_of(8i32)));
+    /// // turn multiplying by 8 into shifting by 3
+    /// let func = func.rewrite(|splice, insn| {
+    ///     if insn.get_name() != "imul" {
+    ///         return Rewrite::Keep
+    ///     }
+    ///     let value = splice.map(insn.get_value1().unwrap());
+    ///     let shifted = splice.get_func().insn_shl(value, splice.get_func().insn_of(3i32));
+    ///     Rewrite::Replace(Some(shifted))
+    /// }).unwrap();
+    /// assert!(func.dump_stable().contains("ishl"));
+    /// func.compile().with(|times8: extern fn(i32) -> i32| assert_eq!(times8(5), 40));
+    /// ```
+    pub fn rewrite<F>(&self, mut visit: F) -> Option<UncompiledFunction<'a>>
+        where F: for<'r, 'b> FnMut(&mut Splice<'r, 'b, 'a>, Instruction<'a>) -> Rewrite<'a> {
+        self.rebuild_blocks(|replay, _, block| {
+            replay.start_block(block.into());
+            for insn in block.iter() {
+                try!(rewrite::visit(replay, insn, &mut visit));
+            }
+            Ok(())
+        })
+    }
+    /// Compile the function as a chain of smaller functions with at most
+    /// about `max_insns` instructions each, which go from one to the next
+    /// with tail calls
+    ///
+    /// LibJIT gets very slow on functions with tens of thousands of
+    /// instructions. This cuts the function between blocks and passes the
+    /// values that live across the cuts along as parameters.
+    ///
+    /// The compiled function is a new one that starts the chain, so don't do
+    /// this to a function that other functions already call. The tables,
+    /// counters and buffers this function's code points into move over to
+    /// it, and each piece gets this function's settings, like its
+    /// optimization level and overflow mode. If the function can't be split,
+    /// because it does something that can't be rebuilt, like making calls or
+    /// taking the address of a value, or it has weak references, it is
+    /// compiled whole.
+    ///
+    /// ```rust
+    /// use jit::*;
+    /// let mut ctx = Context::<()>::new();
+    /// let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+    /// let total_var = func.new_lvalue(&get::<i32>());
+    /// let total = total_var.get_local().unwrap();
+    /// func.insn_store(&total_var, &func[0]);
+    /// for step in 0..20 {
+    ///     let mut skip = Label::new(&func);
+    ///     func.insn_branch_if(func.insn_gt(total, func.insn_of(1000i32)), &mut skip);
+    ///     func.insn_store(&total_var, func.insn_add(total, func.insn_of(step)));
+    ///     func.insn_label(&mut skip);
+    /// }
+    /// func.insn_return(total);
+    /// func.compile_split(10).unwrap().with(|run: extern fn(i32) -> i32| {
+    ///     assert_eq!(run(0), 190);
+    ///     assert_eq!(run(2000), 2000);
+    /// });
+    /// ```
+    pub fn compile_split(self, max_insns: usize) -> Result<CompiledFunction<'a>, CompileError> {
+        if !self.owned {
+            panic!("The function must be owned")
+        }
+        try!(self.check_compile());
+        match self.split(max_insns) {
+            Some(entry) => Ok(entry),
+            None => self.compile_whole()
+        }
+    }
+    /// Make a new function on the same context without applying the
+    /// context's options, for holding code rebuilt from this one
+    ///
+    /// The instructions the options add, like counters and hooks, are
+    /// rebuilt along with the rest, so only settings are copied over, with
+    /// `inherit`.
+    fn bare(&self, signature: &Ty) -> UncompiledFunction<'a> {
+        unsafe {
+            let mut func:UncompiledFunction<'a> = from_ptr_oom(jit_function_create(
+                jit_function_get_context(self.into()),
+                signature.into()
+            ));
+            func.owned = true;
+            func
+        }
+    }
+    /// Rebuild the function as a chain of functions, each taking the index of
+    /// the block it should start at, the parameters of this one and every
+    /// value that lives across blocks, and compile them
+    fn split(&self, max_insns: usize) -> Option<CompiledFunction<'a>> {
+        let this: jit_function_t = self.into();
+        if FunctionData::with(this, |data| data.alive.is_some()) {
+            return None
+        }
+        let sig = self.get_signature();
+        let param_count = sig.params().count();
+        let param = |func: &UncompiledFunction<'a>, index: usize| -> &'a Val {
+            from_ptr(unsafe { jit_value_get_param(func.into(), index as c_uint) })
+        };
+        let mut values: Vec<jit_value_t> = (0..param_count).map(|index| param(self, index).into()).collect();
+        let mut seen: HashSet<jit_value_t> = values.iter().cloned().collect();
+        let mut chunks: Vec<Vec<jit_block_t>> = vec![Vec::new()];
+        let mut size = 0;
+        for block in self.blocks() {
+            let mut count = 0;
+            for insn in block.iter() {
+                let name = insn.get_name();
+                if !replay::rebuildable(name) || name == "address_of" || name == "alloca" {
+                    return None
+                }
+                let used = insn.get_value1().into_iter()
+                    .chain(insn.get_value2().into_iter())
+                    .chain(insn.get_dest().into_iter());
+                for value in used {
+                    let ptr: jit_value_t = value.into();
+                    let lives = unsafe {
+                        jit_value_is_constant(ptr) == 0 && jit_value_is_temporary(ptr) == 0
+                    };
+                    if lives && seen.insert(ptr) {
+                        values.push(ptr);
+                    }
+                }
+                count += 1;
+            }
+            if size > 0 && size + count > max_insns {
+                chunks.push(Vec::new());
+                size = 0;
+            }
+            chunks.last_mut().unwrap().push(block.into());
+            size += count;
+        }
+        if chunks.len() < 2 {
+            return None
+        }
+        let chunk_of: HashMap<jit_block_t, usize> = chunks.iter().enumerate()
+            .flat_map(|(index, chunk)| chunk.iter().map(move |&block| (block, index)))
+            .collect();
+        // each chunk can be started at its first block, or any block another
+        // chunk branches to
+        let mut entries: Vec<Vec<jit_block_t>> = chunks.iter().map(|chunk| vec![chunk[0]]).collect();
+        for (index, chunk) in chunks.iter().enumerate() {
+            for &block in chunk {
+                let block: Block = from_ptr(block);
+                for insn in block.iter().filter(|insn| insn.get_name().starts_with("br")) {
+                    let target = match insn.get_label() {
+                        Some(label) => unsafe { jit_block_from_label(this, label as jit_label_t) },
+                        None => continue
+                    };
+                    if let Some(&other) = chunk_of.get(&target) {
+                        if other != index && !entries[other].contains(&target) {
+                            entries[other].push(target);
+                        }
+                    }
+                }
+            }
+        }
+        let ret = sig.get_return().unwrap();
+        let void = ret.get_kind() == TypeKind::Void;
+        let chunk_sig = {
+            let mut params: Vec<&Ty> = vec![consts::get_uint()];
+            params.extend(values.iter().map(|&value| -> &Ty { unsafe { from_ptr(jit_value_get_type(value)) } }));
+            Type::new_signature(Abi::CDecl, ret, &mut params)
+        };
+        let funcs: Vec<UncompiledFunction<'a>> = chunks.iter().map(|_| self.inherit(self.bare(&chunk_sig))).collect();
+        for (index, func) in funcs.iter().enumerate() {
+            let chunk = &chunks[index];
+            let result = if void { None } else { Some(Val::new(func, ret)) };
+            let mut replay = Replay::new(this, func, chunk, None);
+            for (param_index, &value) in values.iter().enumerate() {
+                replay.values.insert(value, param(func, param_index + 1));
+            }
+            replay.set_result(result);
+            replay.allow_outside();
+            let mut starts: Vec<Label<'a>> = entries[index].iter()
+                .map(|&block| Label::from(replay.get_label(block).unwrap()))
+                .collect();
+            func.insn_jump_table(param(func, 0), &mut starts);
+            if replay.rebuild(chunk).is_err() {
+                return None
+            }
+            let last: Block = from_ptr(*chunk.last().unwrap());
+            if !last.ends_in_dead() && index + 1 < chunks.len() {
+                func.insn_branch(replay.outside_label(chunks[index + 1][0]).unwrap());
+            }
+            for (block, mut label) in replay.take_outside() {
+                func.insn_label(&mut label);
+                let next = chunk_of[&block];
+                let start = entries[next].iter().position(|&other| other == block).unwrap();
+                let mut args: Vec<&'a Val> = vec![func.insn_of(start as u32)];
+                args.extend(values.iter().map(|value| replay.values[value]));
+                let result = func.insn_call(None, &funcs[next], None, &mut args, flags::CallFlags::TAIL);
+                if void {
+                    func.insn_default_return();
+                } else {
+                    func.insn_return(result);
+                }
+            }
+            replay.finish();
+            match result {
+                Some(result) => func.insn_return(result),
+                None => func.insn_default_return()
+            }
+        }
+        let entry = self.inherit(self.bare(sig));
+        {
+            let mut args: Vec<&'a Val> = vec![entry.insn_of(0u32)];
+            for (index, &value) in values.iter().enumerate() {
+                args.push(if index < param_count {
+                    param(&entry, index)
+                } else {
+                    Val::new(&entry, unsafe { from_ptr(jit_value_get_type(value)) })
+                });
+            }
+            let result = entry.insn_call(None, &funcs[0], None, &mut args, flags::CallFlags::empty());
+            if void {
+                entry.insn_default_return();
+            } else {
+                entry.insn_return(result);
+            }
+        }
+        for func in funcs {
+            if func.compile_whole().is_err() {
+                return None
+            }
+        }
+        let entry = entry.compile_whole().ok();
+        if let Some(ref entry) = entry {
+            FunctionData::adopt(this, entry.into());
+        }
+        entry
+    }
+    /// Give `func`, which holds code rebuilt from this function, this
+    /// function's optimization level and the settings made on it
+    fn inherit(&self, func: UncompiledFunction<'a>) -> UncompiledFunction<'a> {
+        unsafe {
+            jit_function_set_optimization_level((&func).into(), jit_function_get_optimization_level(self.into()));
+            if jit_function_is_recompilable(self.into()) != 0 {
+                jit_function_set_recompilable((&func).into());
+            }
+        }
+        FunctionData::inherit(self.into(), (&func).into());
+        func
+    }
+    /// Compile the function as it is, abandoning it if it can't be
+    fn compile_whole(self) -> Result<CompiledFunction<'a>, CompileError> {
+        self.compile_in_place().map_err(|func| {
+            unsafe { jit_function_abandon(func) };
+            CompileError::Failed
+        })
+    }
+    /// Compile the function as it is, giving it back uncompiled if LibJIT
+    /// fails
+    fn compile_in_place(self) -> Result<CompiledFunction<'a>, jit_function_t> {
         unsafe {
             let ptr = (&self).into();
             mem::forget(self);
-            jit_function_compile(ptr);
-            from_ptr(ptr)
+            let recompiling = jit_function_is_compiled(ptr) != 0;
+            report::record(ptr);
+            irdiff::snapshot(ptr);
+            fence::fence(ptr);
+            let started = events::start();
+            let ok = jit_function_compile(ptr) != 0;
+            events::compiled(ptr, recompiling, started, ok);
+            if ok {
+                debuginfo::compiled(ptr);
+                Ok(from_ptr(ptr))
+            } else {
+                Err(ptr)
+            }
+        }
+    }
+    /// Compile the function on its context's compiler thread, so this thread
+    /// can carry on with something else, like interpreting a baseline
+    /// version, in the meantime
+    ///
+    /// Functions are compiled one at a time in the order they were queued,
+    /// while holding the context's build lock. It goes through the same steps
+    /// as `compile` otherwise, with the ones after compiling done on this
+    /// thread the first time the `PendingCompile` is found to be done.
+    /// Without thread support in LibJIT, or on a context made deterministic,
+    /// the function is compiled straight away instead.
+    ///
+    /// This is unsafe because nothing else takes the build lock: while any
+    /// function on the context is pending, building or compiling anything
+    /// else on it, including through this crate, is a data race unless it is
+    /// done between `jit_context_build_start` and `jit_context_build_end`.
+    ///
+    /// ```rust
+    /// use jit::*;
+    /// let mut ctx = Context::<()>::new();
+    /// let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+    /// func.insn_return(func.insn_mul(&func[0], func.insn_of(3i32)));
+    /// let pending = unsafe { func.compile_async() }.unwrap();
+    /// pending.wait().unwrap().with(|triple: extern fn(i32) -> i32| {
+    ///     assert_eq!(triple(4), 12);
+    /// });
+    /// ```
+    pub unsafe fn compile_async(self) -> Result<PendingCompile<'a>, CompileError> {
+        if !self.owned {
+            panic!("The function must be owned")
         }
+        try!(self.check_compile());
+        let ptr = (&self).into();
+        mem::forget(self);
+        let recompiling = jit_function_is_compiled(ptr) != 0;
+        report::record(ptr);
+        irdiff::snapshot(ptr);
+        fence::fence(ptr);
+        let started = events::start();
+        Ok(ContextData::with_func(ptr, |data| {
+            let now = data.deterministic;
+            background::queue(&mut data.compiler, ptr, now, recompiling, started)
+        }))
+    }
+    /// Run the function through the interpreter, then compile it and check
+    /// that calling it with the same arguments gives exactly the same result
+    ///
+    /// This is for tests, to catch code that is miscompiled.
+    ///
+    /// ```rust
+    /// use jit::*;
+    /// use jit::interp::Value;
+    /// let mut ctx = Context::<()>::new();
+    /// let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+    /// func.insn_return(func.insn_shl(&func[0], func.insn_of(2i32)));
+    /// assert!(func.check_against_interp(&[Value::Int(5)]).is_ok());
+    /// ```
+    pub fn check_against_interp(self, args: &[interp::Value]) -> Result<CompiledFunction<'a>, interp::CheckError> {
+        interp::check(self, args)
     }
     #[inline(always)]
     /// Compile the function and call a closure with it directly
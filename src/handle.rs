@@ -0,0 +1,166 @@
+//! Calling compiled functions from other threads
+//!
+//! Contexts and their functions can only be used on the thread that made
+//! them, but the code they compile can run anywhere. A `CompiledHandle` is
+//! just the entry point of a function, checked against the signature it is
+//! called with when it is made, and a reference that keeps the code alive,
+//! so it can be sent to worker threads without the building API coming too.
+//! Once a context has given out handles, dropping it leaves its code in
+//! place until the last of them is dropped too.
+//!
+//! The wrapper's data on a context can only be freed on the thread that made
+//! it, so when the last handle is dropped on another thread the context is
+//! left for that thread to destroy. It does this the next time it makes or
+//! drops a context, or calls `destroy_orphans`.
+use raw::*;
+use batch::BatchArgs;
+use compile::Compile;
+use data::ContextData;
+use function::Func;
+use std::marker::PhantomData;
+use std::mem;
+use std::sync::{Arc, Mutex};
+
+/// The contexts made on a thread whose last handle was dropped on another
+type Orphans = Arc<Mutex<Vec<usize>>>;
+
+thread_local!(static ORPHANS: Orphans = Arc::new(Mutex::new(Vec::new())));
+
+/// Keeps the code of a context alive while handles to it exist, destroying
+/// the context once the last is dropped after the context itself
+pub struct Retain {
+    ctx: usize,
+    /// Where the thread that made the context looks for ones to destroy
+    owner: Orphans
+}
+impl Drop for Retain {
+    fn drop(&mut self) {
+        let here = ORPHANS.with(|orphans| &**orphans as *const _ == &*self.owner as *const _);
+        if here {
+            unsafe {
+                jit_context_destroy(self.ctx as jit_context_t);
+            }
+        } else {
+            self.owner.lock().unwrap().push(self.ctx);
+        }
+    }
+}
+
+/// Get the reference that keeps the code of `ctx` alive
+fn retain(ctx: jit_context_t) -> Arc<Retain> {
+    ContextData::with(ctx, |data| {
+        if data.retain.is_none() {
+            data.retain = Some(Arc::new(Retain {
+                ctx: ctx as usize,
+                owner: ORPHANS.with(|orphans| orphans.clone())
+            }));
+        }
+        data.retain.as_ref().unwrap().clone()
+    })
+}
+
+/// Destroy the contexts made on this thread whose last handle has been
+/// dropped on another thread since, giving how many there were
+///
+/// This is done whenever a context is made or dropped, so it is only needed
+/// by threads that stop making contexts but keep running.
+///
+/// ```rust
+/// use jit::*;
+/// use std::thread;
+/// let handle = {
+///     let mut ctx = Context::<()>::new();
+///     let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+///     func.insn_return(&func[0]);
+///     func.compile().to_handle::<(i32,), i32>()
+/// };
+/// thread::spawn(move || assert_eq!(handle.call((7,)), 7)).join().unwrap();
+/// assert_eq!(destroy_orphans(), 1);
+/// ```
+pub fn destroy_orphans() -> usize {
+    let orphans = ORPHANS.with(|orphans| mem::replace(&mut *orphans.lock().unwrap(), Vec::new()));
+    for &ctx in &orphans {
+        unsafe {
+            jit_context_destroy(ctx as jit_context_t);
+        }
+    }
+    orphans.len()
+}
+
+/// A compiled function that can be called from any thread, taking the
+/// arguments `A` as a tuple and giving `R`
+///
+/// Exceptions that escape the function aren't caught, like with `with`.
+///
+/// ```rust
+/// use jit::*;
+/// use std::thread;
+/// let handle = {
+///     let mut ctx = Context::<()>::new();
+///     let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32, i32) -> i32>());
+///     func.insn_return(func.insn_mul(&func[0], &func[1]));
+///     func.compile().to_handle::<(i32, i32), i32>()
+/// };
+/// let workers: Vec<_> = (0..4).map(|n| {
+///     let handle = handle.clone();
+///     thread::spawn(move || handle.call((n, 10)))
+/// }).collect();
+/// let results: Vec<i32> = workers.into_iter().map(|worker| worker.join().unwrap()).collect();
+/// assert_eq!(results, [0, 10, 20, 30]);
+/// ```
+pub struct CompiledHandle<A, R> {
+    entry: usize,
+    retain: Arc<Retain>,
+    marker: PhantomData<fn(A) -> R>
+}
+impl<A, R> CompiledHandle<A, R> {
+    /// Make a handle on `func`, which must be compiled and take `A` and
+    /// give `R`
+    pub fn new<'a>(func: &Func) -> CompiledHandle<A, R> where A: BatchArgs<'a>, R: Compile<'a> {
+        if !func.is_compiled() {
+            panic!("Handles can only be made on compiled functions")
+        }
+        if let Err(err) = func.get_signature().check_signature(&A::signature::<R>()) {
+            panic!("The handle's arguments don't match the function: {}", err)
+        }
+        let ptr: jit_function_t = func.into();
+        CompiledHandle {
+            entry: unsafe { jit_function_to_closure(ptr) as usize },
+            retain: retain(unsafe { jit_function_get_context(ptr) }),
+            marker: PhantomData
+        }
+    }
+    /// Get the address of the function's code
+    pub fn get_entry(&self) -> usize {
+        self.entry
+    }
+    /// Call the function with the arguments given
+    pub fn call<'a>(&self, args: A) -> R where A: BatchArgs<'a> {
+        unsafe { args.call(self.entry) }
+    }
+}
+impl<A, R> Clone for CompiledHandle<A, R> {
+    fn clone(&self) -> CompiledHandle<A, R> {
+        CompiledHandle {
+            entry: self.entry,
+            retain: self.retain.clone(),
+            marker: PhantomData
+        }
+    }
+}
+
+/// Take the reference `ctx` holds on its code, giving whether any handles
+/// still hold it, in which case the last of them destroys the context
+pub fn release(ctx: jit_context_t) -> bool {
+    match ContextData::with(ctx, |data| data.retain.take()) {
+        Some(retain) => match Arc::try_unwrap(retain) {
+            // nothing else holds it, so let the context destroy itself
+            Ok(retain) => {
+                mem::forget(retain);
+                false
+            },
+            Err(_) => true
+        },
+        None => false
+    }
+}
@@ -3,7 +3,7 @@ use function::Func;
 use types::Ty;
 use util::{from_ptr, from_ptr_opt};
 use value::Val;
-use std::{ffi, fmt, mem, str};
+use std::{ffi, fmt, mem, ptr, str};
 use std::marker::PhantomData;
 
 /// Represents a single LibJIT instruction
@@ -57,6 +57,17 @@ impl<'a> Instruction<'a> {
 			from_ptr_opt(jit_insn_get_signature(self._insn))
 		}
 	}
+	/// Get the label this instruction branches to, if it is a branch
+	pub fn get_label(self) -> Option<u64> {
+		unsafe {
+			let label = jit_insn_get_label(self._insn);
+			if label == !0 {
+				None
+			} else {
+				Some(label as u64)
+			}
+		}
+	}
 	/// Get the name of the instruction
 	pub fn get_name(self) -> &'a str {
 		unsafe {
@@ -116,6 +127,17 @@ impl<'a> Block<'a> {
 			jit_block_ends_in_dead(self._block) != 0
 		}
 	}
+	/// Get the first label attached to the start of this block, if there is one
+	pub fn get_label(self) -> Option<u64> {
+		unsafe {
+			let label = jit_block_get_label(self._block);
+			if label == !0 {
+				None
+			} else {
+				Some(label as u64)
+			}
+		}
+	}
 	/// Iterate through the instructions
 	pub fn iter(self) -> InstructionIter<'a> {
 		unsafe {
@@ -130,3 +152,29 @@ impl<'a> Block<'a> {
 		}
 	}
 }
+
+/// Iterates through the blocks of a function in order
+pub struct Blocks<'a> {
+	func: jit_function_t,
+	last: jit_block_t,
+	marker: PhantomData<&'a ()>
+}
+impl<'a> Blocks<'a> {
+	/// Iterate through the blocks of the function given
+	pub fn new(func: jit_function_t) -> Blocks<'a> {
+		Blocks {
+			func: func,
+			last: ptr::null_mut(),
+			marker: PhantomData
+		}
+	}
+}
+impl<'a> Iterator for Blocks<'a> {
+	type Item = Block<'a>;
+	fn next(&mut self) -> Option<Block<'a>> {
+		unsafe {
+			self.last = jit_block_next(self.func, self.last);
+			from_ptr_opt(self.last)
+		}
+	}
+}
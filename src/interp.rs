@@ -0,0 +1,840 @@
+//! Runs a function's IR directly, without compiling it
+//!
+//! This walks the blocks and instructions of a function that hasn't been
+//! compiled yet and executes them one at a time, so code can still be run on
+//! platforms where LibJIT's code generator is broken, and so the results of
+//! the interpreter and the compiled code can be compared in tests.
+//!
+//! ```rust
+//! use jit::*;
+//! use jit::interp::Value;
+//! let mut ctx = Context::<()>::new();
+//! let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32, i32) -> i32>());
+//! func.insn_return(func.insn_mul(&func[0], &func[1]));
+//! assert_eq!(interp::run(&func, &[Value::Int(6), Value::Int(7)]), Ok(Value::Int(42)));
+//! ```
+use raw::*;
+use exception::BuiltinException;
+use context::Context;
+use function::{CompiledFunction, UncompiledFunction};
+use insn::{Block, Instruction};
+use replay::{decode, inverse, is_conversion};
+use types::{get, TypeKind};
+use value::Val;
+use util::from_ptr;
+use libc::c_void;
+use std::collections::HashMap;
+use std::error::Error;
+use std::{fmt, mem, ptr};
+
+/// The default number of instructions `run` executes before giving up
+pub const DEFAULT_STEP_LIMIT: usize = 1 << 24;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+/// A value held by the interpreter
+///
+/// Integers smaller than an `int` are widened to `Int`, and pointers are held
+/// as unsigned integers of the native width, like LibJIT does.
+pub enum Value {
+    /// The result of an instruction that gives nothing
+    Void,
+    /// A signed 32-bit integer
+    Int(i32),
+    /// An unsigned 32-bit integer
+    UInt(u32),
+    /// A signed 64-bit integer
+    Long(i64),
+    /// An unsigned 64-bit integer
+    ULong(u64),
+    /// A 32-bit float
+    Float32(f32),
+    /// A 64-bit float
+    Float64(f64)
+}
+impl Value {
+    /// Get the raw bits of this value, for comparing results exactly
+    pub fn to_bits(self) -> u64 {
+        unsafe {
+            match self {
+                Value::Void => 0,
+                Value::Int(v) => v as u32 as u64,
+                Value::UInt(v) => v as u64,
+                Value::Long(v) => v as u64,
+                Value::ULong(v) => v,
+                Value::Float32(v) => mem::transmute::<f32, u32>(v) as u64,
+                Value::Float64(v) => mem::transmute(v)
+            }
+        }
+    }
+    /// Check if this value is non-zero
+    pub fn is_true(self) -> bool {
+        match self {
+            Value::Void => false,
+            Value::Int(v) => v != 0,
+            Value::UInt(v) => v != 0,
+            Value::Long(v) => v != 0,
+            Value::ULong(v) => v != 0,
+            Value::Float32(v) => v != 0.0,
+            Value::Float64(v) => v != 0.0
+        }
+    }
+    fn is_float(self) -> bool {
+        match self {
+            Value::Float32(_) | Value::Float64(_) => true,
+            _ => false
+        }
+    }
+    fn as_i64(self) -> i64 {
+        match self {
+            Value::Void => 0,
+            Value::Int(v) => v as i64,
+            Value::UInt(v) => v as i64,
+            Value::Long(v) => v,
+            Value::ULong(v) => v as i64,
+            Value::Float32(v) => v as i64,
+            Value::Float64(v) => v as i64
+        }
+    }
+    fn as_u64(self) -> u64 {
+        match self {
+            Value::Float32(v) => v as u64,
+            Value::Float64(v) => v as u64,
+            other => other.as_i64() as u64
+        }
+    }
+    fn as_f64(self) -> f64 {
+        match self {
+            Value::UInt(v) => v as f64,
+            Value::ULong(v) => v as f64,
+            Value::Float32(v) => v as f64,
+            Value::Float64(v) => v,
+            other => other.as_i64() as f64
+        }
+    }
+    /// Check if this value fits in the range given
+    fn fits(self, min: i64, max: u64) -> bool {
+        match self {
+            Value::Void => true,
+            Value::UInt(_) | Value::ULong(_) => self.as_u64() <= max,
+            Value::Float32(_) | Value::Float64(_) => {
+                let v = self.as_f64();
+                v >= min as f64 && v <= max as f64
+            },
+            _ => {
+                let v = self.as_i64();
+                v >= min && (v < 0 || v as u64 <= max)
+            }
+        }
+    }
+    /// Convert this value to a kind, optionally failing if it doesn't fit
+    fn convert(self, kind: Kind, check: bool) -> Result<Value, InterpError> {
+        let (min, max) = match kind {
+            Kind::SByte => (-0x80, 0x7f),
+            Kind::UByte => (0, 0xff),
+            Kind::Short => (-0x8000, 0x7fff),
+            Kind::UShort => (0, 0xffff),
+            Kind::Int => (-0x8000_0000, 0x7fff_ffff),
+            Kind::UInt => (0, 0xffff_ffff),
+            Kind::Long => (-0x8000_0000_0000_0000, 0x7fff_ffff_ffff_ffff),
+            Kind::ULong => (0, !0),
+            _ => (0, 0)
+        };
+        if check && !kind.is_float() && !self.fits(min, max) {
+            return Err(InterpError::Exception(BuiltinException::Overflow))
+        }
+        let bits = self.as_i64();
+        Ok(match kind {
+            Kind::Void => Value::Void,
+            Kind::SByte => Value::Int(bits as i8 as i32),
+            Kind::UByte => Value::Int(bits as u8 as i32),
+            Kind::Short => Value::Int(bits as i16 as i32),
+            Kind::UShort => Value::Int(bits as u16 as i32),
+            Kind::Int => Value::Int(bits as i32),
+            Kind::UInt => Value::UInt(if self.is_float() { self.as_u64() as u32 } else { bits as u32 }),
+            Kind::Long => Value::Long(bits),
+            Kind::ULong => Value::ULong(self.as_u64()),
+            Kind::Float32 => Value::Float32(match self {
+                Value::ULong(v) => v as f32,
+                other => other.as_f64() as f32
+            }),
+            Kind::Float64 => Value::Float64(self.as_f64()),
+            Kind::Struct => return Err(InterpError::Unsupported("struct value".to_owned()))
+        })
+    }
+}
+impl fmt::Display for Value {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Value::Void => fmt.write_str("void"),
+            Value::Int(v) => write!(fmt, "{}i32", v),
+            Value::UInt(v) => write!(fmt, "{}u32", v),
+            Value::Long(v) => write!(fmt, "{}i64", v),
+            Value::ULong(v) => write!(fmt, "{}u64", v),
+            Value::Float32(v) => write!(fmt, "{}f32", v),
+            Value::Float64(v) => write!(fmt, "{}f64", v)
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+/// An error that stopped the interpreter
+pub enum InterpError {
+    /// The function contains an instruction the interpreter can't run
+    Unsupported(String),
+    /// A builtin exception like a division by zero was raised
+    Exception(BuiltinException),
+    /// The function threw a value
+    Thrown(Value),
+    /// The function ran for more instructions than it was allowed
+    StepLimit
+}
+impl fmt::Display for InterpError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            InterpError::Unsupported(ref what) => write!(fmt, "{}: {}", self.description(), what),
+            InterpError::Exception(exception) => write!(fmt, "{}: {}", self.description(), exception),
+            InterpError::Thrown(value) => write!(fmt, "{}: {}", self.description(), value),
+            InterpError::StepLimit => write!(fmt, "{}", self.description())
+        }
+    }
+}
+impl Error for InterpError {
+    fn description(&self) -> &str {
+        match *self {
+            InterpError::Unsupported(_) => "Unsupported by the interpreter",
+            InterpError::Exception(_) => "Builtin exception raised",
+            InterpError::Thrown(_) => "Value thrown",
+            InterpError::StepLimit => "Step limit reached"
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Kind {
+    Void, SByte, UByte, Short, UShort, Int, UInt, Long, ULong, Float32, Float64, Struct
+}
+impl Kind {
+    fn of(ty: jit_type_t) -> Kind {
+        let kind:TypeKind = unsafe { mem::transmute(jit_type_get_kind(jit_type_normalize(ty))) };
+        if kind == TypeKind::SByte { Kind::SByte }
+        else if kind == TypeKind::UByte { Kind::UByte }
+        else if kind == TypeKind::Short { Kind::Short }
+        else if kind == TypeKind::UShort { Kind::UShort }
+        else if kind == TypeKind::Int { Kind::Int }
+        else if kind == TypeKind::UInt { Kind::UInt }
+        else if kind == TypeKind::Long { Kind::Long }
+        else if kind == TypeKind::ULong { Kind::ULong }
+        else if kind == TypeKind::Float32 { Kind::Float32 }
+        else if kind == TypeKind::Float64 || kind == TypeKind::NFloat { Kind::Float64 }
+        else if kind == TypeKind::Void { Kind::Void }
+        else { Kind::Struct }
+    }
+    fn of_value(value: jit_value_t) -> Kind {
+        Kind::of(unsafe { jit_value_get_type(value) })
+    }
+    fn is_float(self) -> bool {
+        self == Kind::Float32 || self == Kind::Float64
+    }
+    fn size(self) -> usize {
+        match self {
+            Kind::Void | Kind::Struct => 0,
+            Kind::SByte | Kind::UByte => 1,
+            Kind::Short | Kind::UShort => 2,
+            Kind::Int | Kind::UInt | Kind::Float32 => 4,
+            Kind::Long | Kind::ULong | Kind::Float64 => 8
+        }
+    }
+    unsafe fn read(self, addr: usize) -> Result<Value, InterpError> {
+        if addr == 0 {
+            return Err(InterpError::Exception(BuiltinException::NullReference))
+        }
+        Ok(match self {
+            Kind::SByte => Value::Int(ptr::read(addr as *const i8) as i32),
+            Kind::UByte => Value::Int(ptr::read(addr as *const u8) as i32),
+            Kind::Short => Value::Int(ptr::read(addr as *const i16) as i32),
+            Kind::UShort => Value::Int(ptr::read(addr as *const u16) as i32),
+            Kind::Int => Value::Int(ptr::read(addr as *const i32)),
+            Kind::UInt => Value::UInt(ptr::read(addr as *const u32)),
+            Kind::Long => Value::Long(ptr::read(addr as *const i64)),
+            Kind::ULong => Value::ULong(ptr::read(addr as *const u64)),
+            Kind::Float32 => Value::Float32(ptr::read(addr as *const f32)),
+            Kind::Float64 => Value::Float64(ptr::read(addr as *const f64)),
+            Kind::Void | Kind::Struct => return Err(InterpError::Unsupported("struct load".to_owned()))
+        })
+    }
+    unsafe fn write(self, addr: usize, value: Value) -> Result<(), InterpError> {
+        if addr == 0 {
+            return Err(InterpError::Exception(BuiltinException::NullReference))
+        }
+        match try!(value.convert(self, false)) {
+            Value::Int(v) => match self {
+                Kind::SByte | Kind::UByte => ptr::write(addr as *mut u8, v as u8),
+                Kind::Short | Kind::UShort => ptr::write(addr as *mut u16, v as u16),
+                _ => ptr::write(addr as *mut i32, v)
+            },
+            Value::UInt(v) => ptr::write(addr as *mut u32, v),
+            Value::Long(v) => ptr::write(addr as *mut i64, v),
+            Value::ULong(v) => ptr::write(addr as *mut u64, v),
+            Value::Float32(v) => ptr::write(addr as *mut f32, v),
+            Value::Float64(v) => ptr::write(addr as *mut f64, v),
+            Value::Void => return Err(InterpError::Unsupported("struct store".to_owned()))
+        }
+        Ok(())
+    }
+}
+
+fn exception<T>(kind: BuiltinException) -> Result<T, InterpError> {
+    Err(InterpError::Exception(kind))
+}
+fn boolean(cond: bool) -> Value {
+    Value::Int(cond as i32)
+}
+macro_rules! int_binary(
+    ($op:expr, $overflow:expr, $x:expr, $y:expr, $variant:ident, $signed:expr, $min:expr) => ({
+        let (x, y) = ($x, $y);
+        let checked = |result: Option<_>| result.map(Value::$variant).ok_or(InterpError::Exception(BuiltinException::Overflow));
+        match $op {
+            "add" if $overflow => checked(x.checked_add(y)),
+            "sub" if $overflow => checked(x.checked_sub(y)),
+            "mul" if $overflow => checked(x.checked_mul(y)),
+            "add" => Ok(Value::$variant(x.wrapping_add(y))),
+            "sub" => Ok(Value::$variant(x.wrapping_sub(y))),
+            "mul" => Ok(Value::$variant(x.wrapping_mul(y))),
+            "div" | "rem" if y == 0 => exception(BuiltinException::DivisionByZero),
+            "div" | "rem" if $signed && x == $min && y == !0 => exception(BuiltinException::Arithmetic),
+            "div" => Ok(Value::$variant(x / y)),
+            "rem" => Ok(Value::$variant(x % y)),
+            "and" => Ok(Value::$variant(x & y)),
+            "or" => Ok(Value::$variant(x | y)),
+            "xor" => Ok(Value::$variant(x ^ y)),
+            "shl" => Ok(Value::$variant(x.wrapping_shl(y as u32))),
+            "shr" => Ok(Value::$variant(x.wrapping_shr(y as u32))),
+            "eq" => Ok(boolean(x == y)),
+            "ne" => Ok(boolean(x != y)),
+            "lt" => Ok(boolean(x < y)),
+            "le" => Ok(boolean(x <= y)),
+            "gt" => Ok(boolean(x > y)),
+            "ge" => Ok(boolean(x >= y)),
+            "cmp" | "cmpl" | "cmpg" => Ok(Value::Int(if x < y { -1 } else if x > y { 1 } else { 0 })),
+            "min" => Ok(Value::$variant(if x < y { x } else { y })),
+            "max" => Ok(Value::$variant(if x > y { x } else { y })),
+            op => Err(InterpError::Unsupported(format!("integer {}", op)))
+        }
+    })
+);
+macro_rules! float_binary(
+    ($op:expr, $x:expr, $y:expr, $variant:ident) => ({
+        let (x, y) = ($x, $y);
+        match $op {
+            "add" => Ok(Value::$variant(x + y)),
+            "sub" => Ok(Value::$variant(x - y)),
+            "mul" => Ok(Value::$variant(x * y)),
+            "div" => Ok(Value::$variant(x / y)),
+            "rem" => Ok(Value::$variant(x % y)),
+            "rem_ieee" => {
+                // the quotient is rounded to the nearest integer, with ties
+                // going to the even one like IEEE says
+                let quotient = x / y;
+                let mut nearest = quotient.round();
+                if (nearest - quotient).abs() == 0.5 {
+                    nearest = (quotient / 2.0).round() * 2.0;
+                }
+                Ok(Value::$variant(x - nearest * y))
+            },
+            "eq" => Ok(boolean(x == y)),
+            "ne" => Ok(boolean(x != y)),
+            "lt" => Ok(boolean(x < y)),
+            "le" => Ok(boolean(x <= y)),
+            "gt" => Ok(boolean(x > y)),
+            "ge" => Ok(boolean(x >= y)),
+            "cmpl" | "cmpg" if x.is_nan() || y.is_nan() => Ok(Value::Int(if $op == "cmpl" { -1 } else { 1 })),
+            "cmpl" | "cmpg" => Ok(Value::Int(if x < y { -1 } else if x > y { 1 } else { 0 })),
+            "atan2" => Ok(Value::$variant(x.atan2(y))),
+            "pow" => Ok(Value::$variant(x.powf(y))),
+            "min" => Ok(Value::$variant(x.min(y))),
+            "max" => Ok(Value::$variant(x.max(y))),
+            op => Err(InterpError::Unsupported(format!("float {}", op)))
+        }
+    })
+);
+macro_rules! float_unary(
+    ($op:expr, $x:expr, $variant:ident) => ({
+        let x = $x;
+        match $op {
+            "neg" => Ok(Value::$variant(-x)),
+            "acos" => Ok(Value::$variant(x.acos())),
+            "asin" => Ok(Value::$variant(x.asin())),
+            "atan" => Ok(Value::$variant(x.atan())),
+            "ceil" => Ok(Value::$variant(x.ceil())),
+            "cos" => Ok(Value::$variant(x.cos())),
+            "cosh" => Ok(Value::$variant(x.cosh())),
+            "exp" => Ok(Value::$variant(x.exp())),
+            "floor" => Ok(Value::$variant(x.floor())),
+            "log" => Ok(Value::$variant(x.ln())),
+            "log10" => Ok(Value::$variant(x.log10())),
+            "rint" => {
+                let rounded = x.round();
+                let halfway = (rounded - x).abs() == 0.5 && rounded % 2.0 != 0.0;
+                Ok(Value::$variant(if halfway { rounded - (rounded - x) * 2.0 } else { rounded }))
+            },
+            "round" => Ok(Value::$variant(x.round())),
+            "sin" => Ok(Value::$variant(x.sin())),
+            "sinh" => Ok(Value::$variant(x.sinh())),
+            "sqrt" => Ok(Value::$variant(x.sqrt())),
+            "tan" => Ok(Value::$variant(x.tan())),
+            "tanh" => Ok(Value::$variant(x.tanh())),
+            "trunc" => Ok(Value::$variant(x.trunc())),
+            "abs" => Ok(Value::$variant(x.abs())),
+            "sign" => Ok(Value::Int(if x < 0.0 { -1 } else if x > 0.0 { 1 } else { 0 })),
+            "nan" => Ok(boolean(x.is_nan())),
+            "finite" => Ok(boolean(x.is_finite())),
+            "inf" => Ok(boolean(x.is_infinite())),
+            op => Err(InterpError::Unsupported(format!("float {}", op)))
+        }
+    })
+);
+macro_rules! int_unary(
+    ($op:expr, $x:expr, $variant:ident, signed) => ({
+        let x = $x;
+        match $op {
+            "neg" => Ok(Value::$variant(x.wrapping_neg())),
+            "not" => Ok(Value::$variant(!x)),
+            "abs" => Ok(Value::$variant(if x < 0 { x.wrapping_neg() } else { x })),
+            "sign" => Ok(Value::Int(x.signum() as i32)),
+            op => Err(InterpError::Unsupported(format!("integer {}", op)))
+        }
+    });
+    ($op:expr, $x:expr, $variant:ident, unsigned) => ({
+        let x = $x;
+        match $op {
+            "neg" => Ok(Value::$variant(x.wrapping_neg())),
+            "not" => Ok(Value::$variant(!x)),
+            "abs" => Ok(Value::$variant(x)),
+            "sign" => Ok(Value::Int((x != 0) as i32)),
+            op => Err(InterpError::Unsupported(format!("integer {}", op)))
+        }
+    })
+);
+fn binary(op: &str, overflow: bool, a: Value, b: Value) -> Result<Value, InterpError> {
+    match (a, b) {
+        (Value::Int(x), Value::Int(y)) => int_binary!(op, overflow, x, y, Int, true, i32::min_value()),
+        (Value::UInt(x), Value::UInt(y)) => int_binary!(op, overflow, x, y, UInt, false, 0),
+        (Value::Long(x), Value::Long(y)) => int_binary!(op, overflow, x, y, Long, true, i64::min_value()),
+        (Value::ULong(x), Value::ULong(y)) => int_binary!(op, overflow, x, y, ULong, false, 0),
+        (Value::Float32(x), Value::Float32(y)) => float_binary!(op, x, y, Float32),
+        (Value::Float64(x), Value::Float64(y)) => float_binary!(op, x, y, Float64),
+        (a, b) => Err(InterpError::Unsupported(format!("{} on {} and {}", op, a, b)))
+    }
+}
+fn unary(op: &str, a: Value) -> Result<Value, InterpError> {
+    match a {
+        Value::Int(x) => int_unary!(op, x, Int, signed),
+        Value::UInt(x) => int_unary!(op, x, UInt, unsigned),
+        Value::Long(x) => int_unary!(op, x, Long, signed),
+        Value::ULong(x) => int_unary!(op, x, ULong, unsigned),
+        Value::Float32(x) => float_unary!(op, x, Float32),
+        Value::Float64(x) => float_unary!(op, x, Float64),
+        Value::Void => Err(InterpError::Unsupported(format!("{} on void", op)))
+    }
+}
+fn compare(op: &str, inverted: bool, a: Value, b: Value) -> Result<Value, InterpError> {
+    if inverted {
+        let op = try!(inverse(op).ok_or(InterpError::Unsupported(op.to_owned())));
+        binary(op, false, a, b).map(|result| boolean(!result.is_true()))
+    } else {
+        binary(op, false, a, b)
+    }
+}
+
+enum Step {
+    Next,
+    Jump(jit_label_t),
+    Return(Value)
+}
+
+struct Frame {
+    func: jit_function_t,
+    values: HashMap<jit_value_t, Value>,
+    allocations: Vec<Vec<u8>>
+}
+impl Frame {
+    fn get(&self, value: jit_value_t) -> Result<Value, InterpError> {
+        unsafe {
+            if value.is_null() {
+                Err(InterpError::Unsupported("missing operand".to_owned()))
+            } else if jit_value_is_constant(value) != 0 {
+                let kind = Kind::of_value(value);
+                Ok(match kind {
+                    Kind::Long | Kind::ULong => try!(Value::Long(jit_value_get_long_constant(value)).convert(kind, false)),
+                    Kind::Float32 => Value::Float32(jit_value_get_float32_constant(value)),
+                    Kind::Float64 => if jit_type_get_kind(jit_type_normalize(jit_value_get_type(value))) == TypeKind::NFloat.bits() {
+                        Value::Float64(jit_value_get_nfloat_constant(value) as f64)
+                    } else {
+                        Value::Float64(jit_value_get_float64_constant(value))
+                    },
+                    _ => try!(Value::Long(jit_value_get_nint_constant(value) as i64).convert(kind, false))
+                })
+            } else {
+                match self.values.get(&value) {
+                    Some(&value) => Ok(value),
+                    None => Err(InterpError::Unsupported("value used before it was set".to_owned()))
+                }
+            }
+        }
+    }
+    fn set(&mut self, dest: jit_value_t, value: Value) -> Result<(), InterpError> {
+        let value = try!(value.convert(Kind::of_value(dest), false));
+        self.values.insert(dest, value);
+        Ok(())
+    }
+    fn step(&mut self, insn: Instruction) -> Result<Step, InterpError> {
+        let name = insn.get_name();
+        let (dest, v1, v2) = unsafe {
+            (jit_insn_get_dest(insn.into()), jit_insn_get_value1(insn.into()), jit_insn_get_value2(insn.into()))
+        };
+        let label = || unsafe { jit_insn_get_label(insn.into()) };
+        if name == "nop" || name == "mark_offset" || name == "mark_breakpoint" {
+            Ok(Step::Next)
+        } else if name == "br" {
+            Ok(Step::Jump(label()))
+        } else if name.starts_with("br_") && (name.ends_with("false") || name.ends_with("true")) {
+            let cond = try!(self.get(v1)).is_true();
+            Ok(if cond == name.ends_with("true") { Step::Jump(label()) } else { Step::Next })
+        } else if name.starts_with("br_") {
+            let decoded = try!(decode(&name[3..]).ok_or(InterpError::Unsupported(name.to_owned())));
+            let cond = try!(compare(decoded.op, decoded.inverted, try!(self.get(v1)), try!(self.get(v2))));
+            Ok(if cond.is_true() { Step::Jump(label()) } else { Step::Next })
+        } else if name == "jump_table" {
+            let index = try!(self.get(dest)).as_u64() as usize;
+            let (labels, count) = unsafe {
+                (jit_value_get_nint_constant(v1) as *const jit_label_t, jit_value_get_nint_constant(v2) as usize)
+            };
+            Ok(if index < count { Step::Jump(unsafe { *labels.offset(index as isize) }) } else { Step::Next })
+        } else if name == "return" {
+            Ok(Step::Return(Value::Void))
+        } else if name.starts_with("return_") && name != "return_reg" {
+            Ok(Step::Return(try!(self.get(v1))))
+        } else if name == "throw" {
+            Err(InterpError::Thrown(try!(self.get(v1))))
+        } else if name == "check_null" {
+            if try!(self.get(v1)).as_u64() == 0 {
+                exception(BuiltinException::NullReference)
+            } else {
+                Ok(Step::Next)
+            }
+        } else if name.starts_with("copy_") || is_conversion(name) {
+            let value = try!(try!(self.get(v1)).convert(Kind::of_value(dest), name.starts_with("check_")));
+            try!(self.set(dest, value));
+            Ok(Step::Next)
+        } else if name == "alloca" {
+            let size = try!(self.get(v1)).as_u64() as usize;
+            let mut memory = vec![0u8; size + 16];
+            let addr = (memory.as_mut_ptr() as usize + 15) & !15;
+            self.allocations.push(memory);
+            try!(self.set(dest, Value::ULong(addr as u64)));
+            Ok(Step::Next)
+        } else if name == "add_relative" {
+            let addr = try!(self.get(v1)).as_u64().wrapping_add(unsafe { jit_value_get_nint_constant(v2) } as u64);
+            try!(self.set(dest, Value::ULong(addr)));
+            Ok(Step::Next)
+        } else if name.starts_with("load_relative_") {
+            let addr = try!(self.get(v1)).as_u64().wrapping_add(unsafe { jit_value_get_nint_constant(v2) } as u64);
+            let value = try!(unsafe { Kind::of_value(dest).read(addr as usize) });
+            try!(self.set(dest, value));
+            Ok(Step::Next)
+        } else if name.starts_with("store_relative_") {
+            let addr = try!(self.get(dest)).as_u64().wrapping_add(unsafe { jit_value_get_nint_constant(v2) } as u64);
+            try!(unsafe { Kind::of_value(v1).write(addr as usize, try!(self.get(v1))) });
+            Ok(Step::Next)
+        } else if name.starts_with("load_element_") {
+            let kind = Kind::of_value(dest);
+            let addr = try!(self.get(v1)).as_u64().wrapping_add(try!(self.get(v2)).as_u64().wrapping_mul(kind.size() as u64));
+            let value = try!(unsafe { kind.read(addr as usize) });
+            try!(self.set(dest, value));
+            Ok(Step::Next)
+        } else if name.starts_with("store_element_") {
+            let kind = Kind::of_value(v2);
+            let addr = try!(self.get(dest)).as_u64().wrapping_add(try!(self.get(v1)).as_u64().wrapping_mul(kind.size() as u64));
+            try!(unsafe { kind.write(addr as usize, try!(self.get(v2))) });
+            Ok(Step::Next)
+        } else if name == "memcpy" || name == "memmove" || name == "memset" {
+            let (target, a, size) = (try!(self.get(dest)).as_u64() as usize, try!(self.get(v1)), try!(self.get(v2)).as_u64() as usize);
+            if target == 0 || (name != "memset" && a.as_u64() == 0) {
+                return exception(BuiltinException::NullReference)
+            }
+            unsafe {
+                if name == "memset" {
+                    ptr::write_bytes(target as *mut u8, a.as_u64() as u8, size);
+                } else {
+                    ptr::copy(a.as_u64() as usize as *const u8, target as *mut u8, size);
+                }
+            }
+            Ok(Step::Next)
+        } else if let Some(decoded) = decode(name) {
+            let a = try!(self.get(v1));
+            let result = if v2.is_null() {
+                try!(unary(decoded.op, a))
+            } else if decoded.inverted {
+                try!(compare(decoded.op, true, a, try!(self.get(v2))))
+            } else {
+                try!(binary(decoded.op, decoded.overflow, a, try!(self.get(v2))))
+            };
+            try!(self.set(dest, result));
+            Ok(Step::Next)
+        } else {
+            Err(InterpError::Unsupported(name.to_owned()))
+        }
+    }
+}
+
+/// Run `func` with the arguments given, which are converted to the types of
+/// its parameters, and give the value it returns
+pub fn run(func: &UncompiledFunction, args: &[Value]) -> Result<Value, InterpError> {
+    run_with_limit(func, args, DEFAULT_STEP_LIMIT)
+}
+/// Run `func` like `run`, giving up after `limit` instructions
+pub fn run_with_limit(func: &UncompiledFunction, args: &[Value], limit: usize) -> Result<Value, InterpError> {
+    let mut frame = Frame {
+        func: func.into(),
+        values: HashMap::new(),
+        allocations: Vec::new()
+    };
+    let signature = func.get_signature();
+    let ret = match signature.get_return() {
+        Some(ty) => Kind::of(ty.into()),
+        None => Kind::Void
+    };
+    let num_params = signature.params().count();
+    if args.len() != num_params {
+        return Err(InterpError::Unsupported(format!("{} arguments given for {} parameters", args.len(), num_params)))
+    }
+    for (index, &arg) in args.iter().enumerate() {
+        let param = unsafe { jit_value_get_param(frame.func, index as u32) };
+        try!(frame.set(param, arg));
+    }
+    let mut steps = 0;
+    let mut block = unsafe { jit_function_get_entry(frame.func) };
+    'blocks: while !block.is_null() {
+        let current:Block = from_ptr(block);
+        for insn in current.iter() {
+            steps += 1;
+            if steps > limit {
+                return Err(InterpError::StepLimit)
+            }
+            match try!(frame.step(insn)) {
+                Step::Next => (),
+                Step::Jump(label) => {
+                    block = unsafe { jit_block_from_label(frame.func, label) };
+                    continue 'blocks
+                },
+                Step::Return(value) => return if ret == Kind::Void { Ok(Value::Void) } else { value.convert(ret, false) }
+            }
+        }
+        block = unsafe { jit_block_next(frame.func, block) };
+    }
+    Ok(Value::Void)
+}
+
+/// Call a compiled function with the arguments given, converting them to the
+/// types of its parameters
+///
+/// ```rust
+/// use jit::*;
+/// use jit::interp::Value;
+/// let mut ctx = Context::<()>::new();
+/// let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+/// func.insn_return(func.insn_neg(&func[0]));
+/// let func = func.compile();
+/// assert_eq!(interp::call(&func, &[Value::Int(3)]), Ok(Value::Int(-3)));
+/// ```
+pub fn call(func: &CompiledFunction, args: &[Value]) -> Result<Value, InterpError> {
+    let num_params = func.get_signature().params().count();
+    if args.len() != num_params {
+        return Err(InterpError::Unsupported(format!("{} arguments given for {} parameters", args.len(), num_params)))
+    }
+    apply(func.into(), args)
+}
+/// Call the compiled function with the arguments given through `jit_function_apply`
+fn apply(func: jit_function_t, args: &[Value]) -> Result<Value, InterpError> {
+    unsafe {
+        let signature = jit_function_get_signature(func);
+        let mut storage = vec![0u64; args.len()];
+        for (index, &arg) in args.iter().enumerate() {
+            let kind = Kind::of(jit_type_get_param(signature, index as u32));
+            try!(kind.write(storage.as_mut_ptr().offset(index as isize) as usize, arg));
+        }
+        let mut arg_ptrs:Vec<*mut c_void> = storage.iter_mut().map(|arg| arg as *mut u64 as *mut c_void).collect();
+        let mut ret = [0u64; 2];
+        if jit_function_apply(func, arg_ptrs.as_mut_ptr(), ret.as_mut_ptr() as *mut c_void) == 0 {
+            return Err(InterpError::Thrown(Value::ULong(jit_exception_get_last() as u64)))
+        }
+        match Kind::of(jit_type_get_return(signature)) {
+            Kind::Void => Ok(Value::Void),
+            kind => kind.read(ret.as_ptr() as usize)
+        }
+    }
+}
+/// Check if two results are the same, treating all NaNs as equal
+fn same(a: Value, b: Value) -> bool {
+    match (a, b) {
+        (Value::Float32(x), Value::Float32(y)) if x.is_nan() && y.is_nan() => true,
+        (Value::Float64(x), Value::Float64(y)) if x.is_nan() && y.is_nan() => true,
+        (Value::Float32(_), Value::Float32(_)) | (Value::Float64(_), Value::Float64(_)) => a.to_bits() == b.to_bits(),
+        _ => a == b
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+/// The reason running a function through both the interpreter and the compiler failed
+pub enum CheckError {
+    /// The interpreter couldn't run the function to a result
+    Interp(InterpError),
+    /// The compiled function threw an exception
+    Compiled(InterpError),
+    /// The results differed
+    Mismatch {
+        /// The result from the interpreter
+        interpreted: Value,
+        /// The result from the compiled function
+        compiled: Value
+    }
+}
+impl fmt::Display for CheckError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            CheckError::Interp(ref error) => write!(fmt, "{}: {}", self.description(), error),
+            CheckError::Compiled(ref error) => write!(fmt, "{}: {}", self.description(), error),
+            CheckError::Mismatch { interpreted, compiled } =>
+                write!(fmt, "{}: interpreted {}, compiled {}", self.description(), interpreted, compiled)
+        }
+    }
+}
+impl Error for CheckError {
+    fn description(&self) -> &str {
+        match *self {
+            CheckError::Interp(_) => "The interpreter failed",
+            CheckError::Compiled(_) => "The compiled function failed",
+            CheckError::Mismatch { .. } => "The interpreted and compiled results differ"
+        }
+    }
+}
+/// Run `func` through the interpreter, then compile it and call it with the
+/// same arguments, and check that both give exactly the same result
+///
+/// Floats are compared by their bits, except that all NaNs are equal. Since
+/// builtin exceptions raised by compiled code can't be recovered from, the
+/// compiled function isn't called when the interpreter raises one.
+pub fn check<'a>(func: UncompiledFunction<'a>, args: &[Value]) -> Result<CompiledFunction<'a>, CheckError> {
+    let interpreted = try!(run(&func, args).map_err(CheckError::Interp));
+    let func = func.compile();
+    let compiled = try!(apply(func.into(), args).map_err(CheckError::Compiled));
+    if same(interpreted, compiled) {
+        Ok(func)
+    } else {
+        Err(CheckError::Mismatch {
+            interpreted: interpreted,
+            compiled: compiled
+        })
+    }
+}
+
+/// A randomly generated function whose interpreted and compiled results differ
+#[derive(Debug)]
+pub struct FuzzFailure {
+    /// The seed of the function that failed, which `fuzz` regenerates it from
+    pub seed: u64,
+    /// The function's IR
+    pub ir: String,
+    /// The arguments it was given
+    pub args: Vec<Value>,
+    /// How it failed
+    pub error: CheckError
+}
+impl fmt::Display for FuzzFailure {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        try!(writeln!(fmt, "Seed {} failed with {:?}: {}", self.seed, self.args, self.error));
+        fmt.write_str(&self.ir)
+    }
+}
+/// A xorshift generator, so runs can be repeated from their seed
+struct Rng(u64);
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+    fn below(&mut self, max: usize) -> usize {
+        (self.next() % max as u64) as usize
+    }
+}
+fn generate<'a>(func: &UncompiledFunction<'a>, rng: &mut Rng, float: bool) -> &'a Val {
+    let mut pool:Vec<&'a Val> = (0..3).map(|index| {
+        unsafe { from_ptr(jit_value_get_param(func.into(), index)) }
+    }).collect();
+    pool.push(if float { func.insn_of(rng.next() as i32 as f64 / 7.0) } else { func.insn_of(rng.next() as i32) });
+    for _ in 0..(3 + rng.below(10)) {
+        let a = pool[rng.below(pool.len())];
+        let b = pool[rng.below(pool.len())];
+        let value = match rng.below(if float { 9 } else { 12 }) {
+            0 => func.insn_add(a, b),
+            1 => func.insn_sub(a, b),
+            2 => func.insn_mul(a, b),
+            3 => func.insn_min(a, b),
+            4 => func.insn_max(a, b),
+            5 => func.insn_neg(a),
+            6 => func.insn_convert(func.insn_lt(a, b), a.get_type(), false),
+            7 => {
+                let result_var = func.new_lvalue(a.get_type());
+                let result = result_var.get_local().unwrap();
+                func.insn_if_else(func.insn_eq(a, b), || func.insn_store(&result_var, a), || func.insn_store(&result_var, b));
+                result
+            },
+            8 if float => func.insn_div(a, b),
+            8 => func.insn_and(a, b),
+            9 => func.insn_or(a, b),
+            10 => func.insn_xor(a, b),
+            _ => func.insn_shl(a, func.insn_and(b, func.insn_of(31i32)))
+        };
+        pool.push(value);
+    }
+    pool[pool.len() - 1]
+}
+/// Generate `count` small random functions on `ctx`, starting from `seed`,
+/// and check each with random arguments against the interpreter
+///
+/// ```rust
+/// use jit::*;
+/// let mut ctx = Context::<()>::new();
+/// if let Err(failure) = interp::fuzz(&mut ctx, 42, 20) {
+///     panic!("{}", failure);
+/// }
+/// ```
+pub fn fuzz<T>(ctx: &mut Context<T>, seed: u64, count: usize) -> Result<(), FuzzFailure> {
+    let int_sig = get::<fn(i32, i32, i32) -> i32>();
+    let float_sig = get::<fn(f64, f64, f64) -> f64>();
+    for index in 0..count {
+        let seed = seed.wrapping_add(index as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15) | 1;
+        let mut rng = Rng(seed);
+        let float = rng.below(2) == 0;
+        let func = UncompiledFunction::new(ctx, if float { &float_sig } else { &int_sig });
+        let result = generate(&func, &mut rng, float);
+        func.insn_return(result);
+        let args:Vec<Value> = (0..3).map(|_| if float {
+            Value::Float64(rng.next() as i32 as f64 / 3.0)
+        } else {
+            Value::Int(rng.next() as i32)
+        }).collect();
+        let ir = format!("{:?}", func);
+        if let Err(error) = check(func, &args) {
+            return Err(FuzzFailure {
+                seed: seed,
+                ir: ir,
+                args: args,
+                error: error
+            })
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,164 @@
+//! Comparing the IR of a function between compilations
+//!
+//! LibJIT frees the IR of a function once it is compiled, so while IR
+//! history is enabled on a context, the listing from `dump_stable` is kept for
+//! each function just before it is compiled. A function that is compiled
+//! again, or built with `UncompiledFunction::recompiles` as a new tier of
+//! another, keeps the listing it had before too, and `Func::diff_ir` lines
+//! the two up.
+use raw::*;
+use data::{ContextData, FunctionData};
+use dump;
+use util::from_ptr;
+use std::{cmp, fmt};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+/// A line of a function's listing that differs between two compilations,
+/// with lines counted from zero
+pub enum IrChange {
+    /// The line was only in the new listing
+    Added {
+        /// Where the line is in the new listing
+        line: usize,
+        /// The instruction or block header
+        text: String
+    },
+    /// The line was only in the old listing
+    Removed {
+        /// Where the line was in the old listing
+        line: usize,
+        /// The instruction or block header
+        text: String
+    },
+    /// The line took the place of a different one
+    Changed {
+        /// Where the line was in the old listing
+        old_line: usize,
+        /// Where the line is in the new listing
+        new_line: usize,
+        /// What the line was
+        old: String,
+        /// What the line is now
+        new: String
+    }
+}
+impl fmt::Display for IrChange {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            IrChange::Added { ref text, .. } => write!(fmt, "+ {}", text.trim()),
+            IrChange::Removed { ref text, .. } => write!(fmt, "- {}", text.trim()),
+            IrChange::Changed { ref old, ref new, .. } => write!(fmt, "~ {} => {}", old.trim(), new.trim())
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+/// The differences between the IR a function was compiled with before and
+/// the IR it was compiled with after, in the order they appear
+///
+/// Values are numbered in the order they first appear, so an instruction
+/// that adds a temporary can make later lines using other temporaries show
+/// up as changed too.
+pub struct IrDiff {
+    /// Each line that differs
+    pub changes: Vec<IrChange>
+}
+impl IrDiff {
+    /// Line up the lines of two listings
+    pub fn new(old: &str, new: &str) -> IrDiff {
+        let old: Vec<&str> = old.lines().collect();
+        let new: Vec<&str> = new.lines().collect();
+        // the longest common subsequence of the lines from each pair of
+        // positions onwards
+        let mut common = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+        for i in (0..old.len()).rev() {
+            for j in (0..new.len()).rev() {
+                common[i][j] = if old[i] == new[j] {
+                    common[i + 1][j + 1] + 1
+                } else {
+                    cmp::max(common[i + 1][j], common[i][j + 1])
+                };
+            }
+        }
+        let mut changes = Vec::new();
+        let (mut removed, mut added) = (Vec::new(), Vec::new());
+        let (mut i, mut j) = (0, 0);
+        while i < old.len() || j < new.len() {
+            if i < old.len() && j < new.len() && old[i] == new[j] {
+                IrDiff::pair(&mut changes, &mut removed, &mut added);
+                i += 1;
+                j += 1;
+            } else if j < new.len() && (i == old.len() || common[i][j + 1] >= common[i + 1][j]) {
+                added.push((j, new[j]));
+                j += 1;
+            } else {
+                removed.push((i, old[i]));
+                i += 1;
+            }
+        }
+        IrDiff::pair(&mut changes, &mut removed, &mut added);
+        IrDiff {
+            changes: changes
+        }
+    }
+    /// Turn a run of removed and added lines into changes, pairing them up
+    /// in order
+    fn pair(changes: &mut Vec<IrChange>, removed: &mut Vec<(usize, &str)>, added: &mut Vec<(usize, &str)>) {
+        let paired = cmp::min(removed.len(), added.len());
+        for (&(old_line, old), &(new_line, new)) in removed.iter().zip(added.iter()) {
+            changes.push(IrChange::Changed {
+                old_line: old_line,
+                new_line: new_line,
+                old: old.to_owned(),
+                new: new.to_owned()
+            });
+        }
+        for &(line, text) in &removed[paired..] {
+            changes.push(IrChange::Removed { line: line, text: text.to_owned() });
+        }
+        for &(line, text) in &added[paired..] {
+            changes.push(IrChange::Added { line: line, text: text.to_owned() });
+        }
+        removed.clear();
+        added.clear();
+    }
+    /// Check if the IR is the same
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+    /// Count the lines that were added
+    pub fn added(&self) -> usize {
+        self.changes.iter().filter(|change| match **change { IrChange::Added { .. } => true, _ => false }).count()
+    }
+    /// Count the lines that were removed
+    pub fn removed(&self) -> usize {
+        self.changes.iter().filter(|change| match **change { IrChange::Removed { .. } => true, _ => false }).count()
+    }
+    /// Count the lines that were changed
+    pub fn changed(&self) -> usize {
+        self.changes.iter().filter(|change| match **change { IrChange::Changed { .. } => true, _ => false }).count()
+    }
+}
+impl fmt::Display for IrDiff {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        for change in &self.changes {
+            try!(write!(fmt, "{}\n", change));
+        }
+        Ok(())
+    }
+}
+
+/// Keep the listing of `func` before it is compiled, if its context keeps IR
+/// history, moving the one from its last compilation to be the previous one
+pub fn snapshot(func: jit_function_t) {
+    if !ContextData::with_func(func, |data| data.ir_history) {
+        return
+    }
+    let listing = dump::stable(from_ptr(func));
+    FunctionData::with(func, |data| {
+        if let Some(last) = data.ir.take() {
+            data.previous_ir = Some(last);
+        }
+        data.ir = Some(listing);
+    });
+}
@@ -26,7 +26,7 @@
 #![crate_name = "jit"]
 #![allow(raw_pointer_derive, non_camel_case_types, non_upper_case_globals)]
 #![deny(unused_attributes, dead_code, unused_parens, unknown_lints, unreachable_code, unused_allocation, unused_allocation, unused_must_use)]
-#![feature(alloc, plugin, unboxed_closures, optin_builtin_traits, associated_consts, raw, oom)]
+#![feature(alloc, plugin, unboxed_closures, optin_builtin_traits, associated_consts, raw, oom, linkage)]
 #![plugin(rustc_bitflags)]
 
 //! This crate wraps LibJIT in an idiomatic style.
@@ -53,20 +53,46 @@ extern crate rustc_bitflags;
 extern crate alloc;
 extern crate libc;
 extern crate libjit_sys as raw;
+#[cfg(feature = "logging")] #[macro_use]
+extern crate log;
+#[cfg(feature = "logging")]
+extern crate time;
 use raw::*;
 use libc::c_void;
 use std::mem;
+pub use access::{FieldRef, StructAccess};
+pub use array::ArrayView;
+pub use background::PendingCompile;
+pub use batch::BatchArgs;
+pub use buffer::Buffer;
+pub use builder::{ContextBuilder, ContextError};
+pub use codespace::{CodeHandle, CodeSpace};
 pub use compile::Compile;
-pub use context::Context;
+pub use context::{Context, ContextOptions, EmitBudget, FunctionHook, InlineLimits, OverflowHandler, Precision, StackGuard, UnrollLimits};
 pub use elf::*;
-pub use function::{flags, Abi, UncompiledFunction, Func, CompiledFunction};
+pub use externs::{ExternMismatch, ExternSnapshot};
+pub use exception::{BuiltinException, ExceptionFilter, FilterAction, JitException, Payload};
+pub use irdiff::{IrChange, IrDiff};
+pub use function::{flags, Abi, Bind, Bool, CallSite, ClosureType, CompileError, ComposeError, ConstTable, EmitError, EmitWarning, CustomOp, GuardHandler, Inline, Invalidator, Lifetime, MoveError, Overflow, RebuildError, UncompiledFunction, Func, CompiledFunction, ShadowFrame, TypeTag, WeakFunction};
 pub use function::flags::CallFlags;
-pub use label::Label;
-pub use insn::{Block, Instruction, InstructionIter};
+pub use handle::{CompiledHandle, destroy_orphans};
+pub use label::{Label, UnplacedLabel};
+pub use insn::{Block, Blocks, Instruction, InstructionIter};
+pub use layout::Layout;
+pub use live::LiveObjects;
+pub use location::{CallStack, SourceLocation};
+pub use lvalue::{LValue, RValue};
+pub use num::{kernel, JitNum, NativeInt};
+pub use report::CodegenReport;
+pub use rewrite::{Rewrite, Splice};
+pub use shadow::{PinnedRoots, ShadowFrames};
+pub use support::{has_function, is_static, missing_insns, missing_math, require, version, Unsupported, Version};
 pub use types::kind::TypeKind;
-pub use types::{kind, get, Type, Field, Fields, Params, CowType, StaticType, Ty, TaggedType};
+pub use types::{kind, get, Type, Field, Fields, Params, CowType, SignatureMismatch, StaticType, Ty, TaggedType, TypeFenced};
 pub use types::consts as typecs;
 pub use value::Val;
+pub use params::{ParamTypes, Typed};
+pub use profile::CallProfile;
 
 
 extern fn free_data<T>(data: *mut c_void) {
@@ -106,12 +132,65 @@ pub fn supports_virtual_memory() -> bool {
 }
 #[macro_use]
 mod macros;
+mod access;
+pub mod analysis;
+mod arena;
+mod array;
+pub mod asm;
+mod background;
+mod batch;
+mod buffer;
+#[cfg(feature = "raw")]
+pub mod bindings;
+#[cfg(feature = "capi")]
+pub mod capi;
+mod builder;
+mod codespace;
 mod context;
 mod compile;
+pub mod cpu;
+mod cse;
+mod data;
+mod debuginfo;
+mod dump;
 mod elf;
+pub mod emit;
+mod events;
+mod exception;
+mod externs;
+mod fence;
 mod function;
+mod handle;
 mod insn;
+mod irdiff;
+pub mod interp;
 mod label;
+mod layout;
+mod licm;
+mod live;
+mod location;
+mod lvalue;
+#[cfg(feature = "mathexpr")]
+pub mod mathexpr;
+mod mathlib;
+mod num;
+mod pages;
+mod params;
+mod profile;
+pub mod reduce;
+mod replay;
+mod report;
+mod rewrite;
+mod shadow;
+#[cfg(feature = "testing")]
+pub mod snapshot;
+mod ssa;
+mod stack;
+#[cfg(feature = "testing")]
+pub mod stress;
+mod support;
+pub mod trace;
 mod types;
 mod util;
 mod value;
+mod wide;
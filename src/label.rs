@@ -1,10 +1,10 @@
-use raw::{
-    jit_label_t,
-    jit_function_reserve_label
-};
-use function::UncompiledFunction;
+use raw::*;
+use data::FunctionData;
+use function::{self, UncompiledFunction};
+use insn::Blocks;
+use location::{self, SourceLocation};
 use std::marker::PhantomData;
-use std::fmt;
+use std::{fmt, slice};
 use std::ops::{Deref, DerefMut};
 #[derive(PartialEq)]
 /// A label in the code that can be branched to in instructions
@@ -21,6 +21,7 @@ impl<'a> Label<'a> {
     #[inline(always)]
     /// Create a new label
     pub fn new(func:&UncompiledFunction<'a>) -> Label<'a> {
+        function::spend(func.into(), 0, 0, 1);
         unsafe {
             Label {
                 _label: jit_function_reserve_label(func.into()),
@@ -29,6 +30,15 @@ impl<'a> Label<'a> {
         }
     }
 }
+impl<'a> From<jit_label_t> for Label<'a> {
+    /// Convert from a native label
+    fn from(label: jit_label_t) -> Label<'a> {
+        Label {
+            _label: label,
+            marker: PhantomData,
+        }
+    }
+}
 impl<'a> Deref for Label<'a> {
     type Target = u64;
     fn deref(&self) -> &u64 {
@@ -40,3 +50,76 @@ impl<'a> DerefMut for Label<'a> {
         &mut self._label
     }
 }
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+/// A label that is branched to but never placed with `insn_label`
+pub struct UnplacedLabel {
+    /// The label's number, as shown when it is displayed
+    pub label: u64,
+    /// The name given to the label with `name_label`, if it has one
+    pub name: Option<String>,
+    /// Where the first branch to the label was made, if a location was set
+    pub location: Option<SourceLocation>
+}
+impl fmt::Display for UnplacedLabel {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        try!(write!(fmt, "label {}", self.label));
+        if let Some(ref name) = self.name {
+            try!(write!(fmt, " ({})", name));
+        }
+        if let Some(loc) = self.location {
+            try!(write!(fmt, " branched to at {}:{}:{}", loc.file, loc.line, loc.column));
+        }
+        Ok(())
+    }
+}
+
+/// Find the labels in `func` that instructions refer to but that were never
+/// placed, in the order they are first referred to
+pub fn unplaced(func: jit_function_t) -> Vec<UnplacedLabel> {
+    let mut found: Vec<UnplacedLabel> = Vec::new();
+    let mut location = None;
+    for block in Blocks::new(func) {
+        for insn in block.iter() {
+            let name = insn.get_name();
+            let labels = unsafe {
+                if name == "mark_offset" {
+                    let offset = insn.get_value1().map(|offset| jit_value_get_nint_constant(offset.into()) as usize);
+                    location = offset.and_then(|offset| location::lookup(func, offset));
+                    continue
+                } else if name == "jump_table" {
+                    match (insn.get_value1(), insn.get_value2()) {
+                        (Some(labels), Some(count)) => {
+                            let labels = jit_value_get_nint_constant(labels.into()) as *const jit_label_t;
+                            let count = jit_value_get_nint_constant(count.into()) as usize;
+                            slice::from_raw_parts(labels, count).iter().map(|&label| label as u64).collect()
+                        },
+                        _ => Vec::new()
+                    }
+                } else {
+                    insn.get_label().into_iter().collect::<Vec<_>>()
+                }
+            };
+            for label in labels {
+                let placed = unsafe { !jit_block_from_label(func, label as jit_label_t).is_null() };
+                if !placed && !found.iter().any(|other| other.label == label) {
+                    found.push(UnplacedLabel {
+                        label: label,
+                        name: None,
+                        location: location
+                    });
+                }
+            }
+        }
+    }
+    if !found.is_empty() {
+        FunctionData::with(func, |data| {
+            for unplaced in &mut found {
+                unplaced.name = data.label_names.iter()
+                    .find(|&&(label, _)| label as u64 == unplaced.label)
+                    .map(|&(_, ref name)| name.clone());
+            }
+        });
+    }
+    found
+}
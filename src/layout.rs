@@ -0,0 +1,137 @@
+use raw::*;
+use context::Context;
+use function::{flags, Abi, CompiledFunction, UncompiledFunction};
+use types::{consts, Type, Ty};
+use value::Val;
+use std::cmp;
+/// The memory layout of an object made up of a sequence of fields
+///
+/// This lays out each field at the next offset that satisfies its alignment,
+/// like a C compiler would for a struct with the same fields, so the offsets
+/// can be given straight to `insn_load_relative` and `insn_store_relative`.
+///
+/// ```rust
+/// use jit::*;
+/// let layout = Layout::new(&[typecs::get_ubyte(), typecs::get_int(), typecs::get_float64()]);
+/// assert_eq!(layout.get_offset(0), 0);
+/// assert_eq!(layout.get_offset(1), 4);
+/// assert_eq!(layout.get_padding(1), 3);
+/// assert_eq!(layout.get_offset(2), 8);
+/// assert_eq!(layout.get_size(), 16);
+/// ```
+#[derive(Clone)]
+pub struct Layout {
+    types: Vec<Type>,
+    offsets: Vec<usize>,
+    size: usize,
+    alignment: usize
+}
+#[inline(always)]
+fn align_up(offset: usize, alignment: usize) -> usize {
+    if alignment <= 1 {
+        offset
+    } else {
+        (offset + alignment - 1) / alignment * alignment
+    }
+}
+impl Layout {
+    /// Calculate the layout of an object with the fields given
+    pub fn new(fields: &[&Ty]) -> Layout {
+        let mut offsets = Vec::with_capacity(fields.len());
+        let mut offset = 0;
+        let mut alignment = 1;
+        for field in fields {
+            let field_align = cmp::max(field.get_alignment(), 1);
+            offset = align_up(offset, field_align);
+            offsets.push(offset);
+            offset += field.get_size();
+            alignment = cmp::max(alignment, field_align);
+        }
+        Layout {
+            types: fields.iter().map(|ty| ty.to_owned()).collect(),
+            offsets: offsets,
+            size: align_up(offset, alignment),
+            alignment: alignment
+        }
+    }
+    #[inline(always)]
+    /// Get the number of fields in this layout
+    pub fn len(&self) -> usize {
+        self.offsets.len()
+    }
+    #[inline(always)]
+    /// Get the offset of the field at `index` from the start of the object
+    pub fn get_offset(&self, index: usize) -> usize {
+        self.offsets[index]
+    }
+    #[inline(always)]
+    /// Get the type of the field at `index`
+    pub fn get_type(&self, index: usize) -> &Ty {
+        &self.types[index]
+    }
+    /// Get the number of padding bytes inserted directly before the field at `index`
+    pub fn get_padding(&self, index: usize) -> usize {
+        if index == 0 {
+            self.offsets[0]
+        } else {
+            let end = self.offsets[index - 1] + self.types[index - 1].get_size();
+            self.offsets[index] - end
+        }
+    }
+    /// Get the number of padding bytes after the last field
+    pub fn get_trailing_padding(&self) -> usize {
+        match self.offsets.last() {
+            Some(&offset) => self.size - offset - self.types[self.len() - 1].get_size(),
+            None => self.size
+        }
+    }
+    #[inline(always)]
+    /// Get the total size of the object in bytes, including trailing padding
+    pub fn get_size(&self) -> usize {
+        self.size
+    }
+    #[inline(always)]
+    /// Get the alignment of the object in bytes
+    pub fn get_alignment(&self) -> usize {
+        self.alignment
+    }
+    /// Make a struct type descriptor with this layout
+    pub fn to_type(&self) -> Type {
+        let mut fields:Vec<&Ty> = self.types.iter().map(|ty| &**ty).collect();
+        let ty = Type::new_struct(&mut fields);
+        unsafe {
+            jit_type_set_size_and_alignment((&ty).into(), self.size as jit_nint, self.alignment as jit_nint);
+        }
+        ty
+    }
+    /// Make an instruction that loads the field at `index` from the object `ptr` points to
+    pub fn insn_load<'a>(&self, func: &UncompiledFunction<'a>, ptr: &'a Val, index: usize) -> &'a Val {
+        func.insn_load_relative(ptr, self.offsets[index], &self.types[index])
+    }
+    /// Make an instruction that stores `value` into the field at `index` of the object `ptr` points to
+    pub fn insn_store<'a>(&self, func: &UncompiledFunction<'a>, ptr: &'a Val, index: usize, value: &'a Val) {
+        func.insn_store_relative(ptr, self.offsets[index], value)
+    }
+    /// Build a constructor function with the signature `fn() -> *mut u8`, which
+    /// allocates an object with this layout using `alloc` and zero-initializes it
+    ///
+    /// `alloc` is called with the size of the object and should return a pointer
+    /// aligned to at least `get_alignment()` bytes. If it returns null, the
+    /// constructor returns null without initializing anything.
+    pub fn build_constructor<'a, T>(&self, ctx: &'a mut Context<T>,
+                                    alloc: extern fn(usize) -> *mut u8) -> CompiledFunction<'a> {
+        let void_ptr = consts::get_void_ptr();
+        let sig = Type::new_signature(Abi::CDecl, void_ptr, &mut []);
+        let alloc_sig = Type::new_signature(Abi::CDecl, void_ptr, &mut [consts::get_nuint()]);
+        let func = UncompiledFunction::new(ctx, &sig);
+        {
+            let size = func.insn_of(self.size);
+            let ptr = func.insn_call_native1(Some("alloc"), alloc, &alloc_sig, [size], flags::CallFlags::NO_THROW);
+            func.insn_if(ptr, || {
+                func.insn_memset(ptr, func.insn_of(0u8), size);
+            });
+            func.insn_return(ptr);
+        }
+        func.compile()
+    }
+}
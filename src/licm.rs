@@ -0,0 +1,108 @@
+//! Finding loops and the instructions in them that can be hoisted out
+use raw::*;
+use analysis::BlockGraph;
+use function::RebuildError;
+use insn::{Block, Blocks};
+use replay;
+use std::collections::{HashMap, HashSet};
+
+/// A loop, as the indices of its first block, which all entries go to and
+/// the back edges go to, and its last block
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Loop {
+    pub header: usize,
+    pub end: usize
+}
+
+/// Find the loops in `blocks` that can have a preheader put before them,
+/// outermost first
+///
+/// A loop is a branch back to an earlier block, the header, along with the
+/// blocks between them, which is what the structured control flow helpers
+/// make. It is only kept if it is only entered by falling into the header,
+/// so code put just before the header runs once before the loop starts.
+pub fn find(graph: &BlockGraph) -> Vec<Loop> {
+    let targets: Vec<&[usize]> = (0..graph.len()).map(|i| graph.get_branch_targets(i)).collect();
+    let mut ends: HashMap<usize, usize> = HashMap::new();
+    for (i, targets) in targets.iter().enumerate() {
+        for &target in targets.iter().filter(|&&target| target <= i) {
+            let end = ends.entry(target).or_insert(i);
+            if *end < i {
+                *end = i;
+            }
+        }
+    }
+    let mut loops: Vec<Loop> = ends.into_iter()
+        .map(|(header, end)| Loop { header: header, end: end })
+        .filter(|l| targets.iter().enumerate().all(|(i, targets)|
+            (i >= l.header && i <= l.end) || targets.iter().all(|&target| target < l.header || target > l.end)
+        ))
+        .collect();
+    loops.sort_by(|a, b| (a.header, b.end).cmp(&(b.header, a.end)));
+    loops
+}
+
+/// Check if the instruction named computes its result from its operands
+/// alone, without trapping, so it can run before the loop it is in even if
+/// the loop never would have run it
+fn pure(name: &str) -> bool {
+    match replay::decode(name) {
+        Some(decoded) => !decoded.overflow && decoded.op != "div" && decoded.op != "rem",
+        None => name.starts_with("copy_") || (replay::is_conversion(name) && !name.starts_with("check_"))
+    }
+}
+
+/// Find the instructions that compute the same value on every iteration of
+/// a loop, and the index in `loops` of the outermost loop they can be
+/// hoisted out of
+pub fn invariants(blocks: &[Block], loops: &[Loop]) -> HashMap<jit_insn_t, usize> {
+    let mut hoisted: HashMap<jit_insn_t, usize> = HashMap::new();
+    for (index, l) in loops.iter().enumerate() {
+        let body = &blocks[l.header..l.end + 1];
+        let defined: HashSet<jit_value_t> = body.iter()
+            .flat_map(|block| block.iter())
+            .filter(|&insn| {
+                let ptr: jit_insn_t = insn.into();
+                !hoisted.contains_key(&ptr) && !insn.dest_is_value()
+            })
+            .filter_map(|insn| insn.get_dest().map(|dest| dest.into()))
+            .collect();
+        let mut invariant: HashSet<jit_value_t> = HashSet::new();
+        for insn in body.iter().flat_map(|block| block.iter()) {
+            let ptr: jit_insn_t = insn.into();
+            let dest: jit_value_t = match (insn.get_dest(), insn.dest_is_value()) {
+                (Some(dest), false) => dest.into(),
+                _ => continue
+            };
+            if hoisted.contains_key(&ptr) || !pure(insn.get_name()) ||
+                    unsafe { jit_value_is_temporary(dest) == 0 } {
+                continue
+            }
+            let operands = insn.get_value1().into_iter().chain(insn.get_value2().into_iter());
+            let fixed = operands.map(|value| value.into()).all(|value: jit_value_t| unsafe {
+                jit_value_is_constant(value) != 0 || invariant.contains(&value) ||
+                    (!defined.contains(&value) && jit_value_is_volatile(value) == 0)
+            });
+            if fixed {
+                invariant.insert(dest);
+                hoisted.insert(ptr, index);
+            }
+        }
+    }
+    hoisted
+}
+
+/// Find why `func` can't be rebuilt with instructions moved, if it can't
+///
+/// Calls are reported before anything else, since setting up their
+/// arguments makes instructions that can't be rebuilt either.
+pub fn blocker(func: jit_function_t) -> Option<RebuildError> {
+    let names: Vec<&str> = Blocks::new(func).flat_map(|block| block.iter().map(|insn| insn.get_name())).collect();
+    if names.iter().any(|name| name.starts_with("call")) {
+        Some(RebuildError::Calls)
+    } else if names.iter().any(|&name| name == "address_of" || name == "alloca") {
+        Some(RebuildError::AddressTaken)
+    } else {
+        names.iter().find(|&&name| !replay::rebuildable(name)).map(|&name| RebuildError::Instruction(name.to_owned()))
+    }
+}
@@ -0,0 +1,81 @@
+//! Counting the objects made through the wrapper that are still alive, to
+//! catch ones that are never freed or freed too often
+use raw::*;
+use data::{ContextData, FunctionData};
+use std::cell::Cell;
+use std::io::{self, Write};
+use std::ptr;
+
+thread_local!(static TYPES: Cell<isize> = Cell::new(0));
+
+#[derive(PartialEq, Eq)]
+/// Counts the `Type` holding it as alive until it is dropped
+pub struct Counted(());
+impl Counted {
+    pub fn new() -> Counted {
+        TYPES.with(|types| types.set(types.get() + 1));
+        Counted(())
+    }
+}
+impl Drop for Counted {
+    fn drop(&mut self) {
+        TYPES.with(|types| types.set(types.get() - 1));
+    }
+}
+
+/// Get how many `Type`s made on this thread are alive
+pub fn types() -> isize {
+    TYPES.with(|types| types.get())
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+/// How many of the objects made through the wrapper are still alive, from
+/// `Context::live_objects`
+pub struct LiveObjects {
+    /// The functions on the context
+    pub functions: usize,
+    /// The values made with `Val::new` in functions on the context that
+    /// haven't been compiled yet
+    pub values: usize,
+    /// How many more `Type`s have been made than dropped on this thread since
+    /// the context was made, which is negative if some were freed too often
+    pub types: isize
+}
+
+/// Count the objects made through the wrapper for `ctx` that are alive
+pub fn count(ctx: jit_context_t) -> LiveObjects {
+    let mut live = LiveObjects {
+        functions: 0,
+        values: 0,
+        types: types() - ContextData::with(ctx, |data| data.types_base)
+    };
+    unsafe {
+        let mut func = jit_function_next(ctx, ptr::null_mut());
+        while !func.is_null() {
+            live.functions += 1;
+            if jit_function_is_compiled(func) == 0 {
+                live.values += FunctionData::with(func, |data| data.values);
+            }
+            func = jit_function_next(ctx, func);
+        }
+    }
+    live
+}
+
+/// Print the `Type`s made while `ctx` was alive that haven't been dropped
+/// by the time it is, in debug builds
+///
+/// Functions and their values are freed along with the context, so types
+/// are the only objects that can outlive it.
+pub fn report(ctx: jit_context_t) {
+    if cfg!(ndebug) {
+        return
+    }
+    let live = count(ctx);
+    let mut stderr = io::stderr();
+    if live.types > 0 {
+        let _ = writeln!(stderr, "Context dropped with {} types made since it was created still alive", live.types);
+    } else if live.types < 0 {
+        let _ = writeln!(stderr, "Context dropped after {} more types were freed than made", -live.types);
+    }
+}
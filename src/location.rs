@@ -0,0 +1,158 @@
+use raw::*;
+use context::Context;
+use data::FunctionData;
+use function::Func;
+use util::from_ptr;
+use libc::{c_uint, c_void};
+use std::ptr;
+
+/// The offset LibJIT gives for frames it doesn't know the offset of
+const JIT_NO_OFFSET: c_uint = !0;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+/// A place in the source a frontend compiled a function from
+pub struct SourceLocation {
+    /// The frontend's number for the file
+    pub file: u32,
+    /// The line, as numbered by the frontend
+    pub line: u32,
+    /// The column, as numbered by the frontend
+    pub column: u32
+}
+
+/// Get the offset to mark the instructions coming from `location` with in
+/// `func`, reusing the last one if it is the same place
+pub fn offset_of(func: jit_function_t, location: SourceLocation) -> usize {
+    FunctionData::with(func, |data| {
+        if data.locations.last() != Some(&location) {
+            data.locations.push(location);
+        }
+        data.locations.len() - 1
+    })
+}
+/// Find the location that `offset` was marked with in `func`
+pub fn lookup(func: jit_function_t, offset: usize) -> Option<SourceLocation> {
+    FunctionData::with(func, |data| data.locations.get(offset).cloned())
+}
+
+/// The native call stack of a thread, captured from the innermost call
+/// outwards
+///
+/// This can be captured by native code that generated code has called, or
+/// made from the trace an instruction made with `insn_get_call_stack` gave
+/// to native code. It is freed when dropped.
+pub struct CallStack {
+    trace: jit_stack_trace_t
+}
+impl CallStack {
+    /// Capture the call stack of this thread
+    pub fn capture() -> CallStack {
+        unsafe {
+            CallStack::from_raw(jit_exception_get_stack_trace() as *mut c_void)
+        }
+    }
+    /// Take ownership of a stack trace LibJIT made, like the value of an
+    /// instruction made with `insn_get_call_stack`
+    pub unsafe fn from_raw(trace: *mut c_void) -> CallStack {
+        CallStack {
+            trace: trace as jit_stack_trace_t
+        }
+    }
+    /// Get the number of frames in the stack, which is 0 if it couldn't be
+    /// captured
+    pub fn len(&self) -> usize {
+        if self.trace.is_null() {
+            0
+        } else {
+            unsafe { jit_stack_trace_get_size(self.trace) as usize }
+        }
+    }
+    /// Check if no frames could be captured
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    /// Get the address the frame at `posn` was running at, counting from the
+    /// innermost frame
+    pub fn get_pc(&self, posn: usize) -> Option<*mut c_void> {
+        if posn >= self.len() {
+            return None
+        }
+        let pc = unsafe { jit_stack_trace_get_pc(self.trace, posn as c_uint) };
+        if pc.is_null() { None } else { Some(pc) }
+    }
+    /// Get the function from `ctx` that the frame at `posn` was running, or
+    /// `None` if it was running something else, like native code
+    pub fn get_function<'a, T>(&self, ctx: &'a Context<T>, posn: usize) -> Option<&'a Func> {
+        function_at(self, ctx.into(), posn)
+    }
+    /// Get the location set with `set_current_location` that the frame at
+    /// `posn` was running at, if it was running a function from `ctx` built
+    /// with locations
+    pub fn get_location<T>(&self, ctx: &Context<T>, posn: usize) -> Option<SourceLocation> {
+        location_at(self, ctx.into(), posn)
+    }
+    /// Find the function from `ctx` that called `callee`, going by the
+    /// innermost frame of `callee` in this stack
+    pub fn get_caller<'a, T>(&self, ctx: &'a Context<T>, callee: &Func) -> Option<&'a Func> {
+        caller_at(self, ctx.into(), callee.into())
+    }
+}
+impl Drop for CallStack {
+    fn drop(&mut self) {
+        if !self.trace.is_null() {
+            unsafe {
+                jit_stack_trace_free(self.trace);
+            }
+        }
+    }
+}
+
+fn function_at<'a>(stack: &CallStack, ctx: jit_context_t, posn: usize) -> Option<&'a Func> {
+    if posn >= stack.len() {
+        return None
+    }
+    unsafe {
+        let func = jit_stack_trace_get_function(ctx, stack.trace, posn as c_uint);
+        if func.is_null() { None } else { Some(from_ptr(func)) }
+    }
+}
+fn location_at(stack: &CallStack, ctx: jit_context_t, posn: usize) -> Option<SourceLocation> {
+    let func = match function_at(stack, ctx, posn) {
+        Some(func) => func.into(),
+        None => return None
+    };
+    let offset = unsafe { jit_stack_trace_get_offset(ctx, stack.trace, posn as c_uint) };
+    if offset == JIT_NO_OFFSET {
+        None
+    } else {
+        lookup(func, offset as usize)
+    }
+}
+fn caller_at<'a>(stack: &CallStack, ctx: jit_context_t, callee: jit_function_t) -> Option<&'a Func> {
+    let mut posns = 0..stack.len();
+    if !posns.any(|posn| function_at(stack, ctx, posn).map(|func| -> jit_function_t { func.into() }) == Some(callee)) {
+        return None
+    }
+    posns.filter_map(|posn| function_at(stack, ctx, posn)).next()
+}
+/// Find the function from `ctx` that called `callee` on this thread, or null
+/// if it wasn't called by one, for `insn_get_caller`
+pub extern fn find_caller(ctx: *mut c_void, callee: *mut c_void) -> *mut c_void {
+    match caller_at(&CallStack::capture(), ctx as jit_context_t, callee as jit_function_t) {
+        Some(caller) => {
+            let caller: jit_function_t = caller.into();
+            caller as *mut c_void
+        },
+        None => ptr::null_mut()
+    }
+}
+
+/// Walk the native stack from the caller outwards, giving each frame of a
+/// function from `ctx` and the location it was running at, if it was built
+/// with locations
+pub fn backtrace<'a>(ctx: jit_context_t) -> Vec<(&'a Func, Option<SourceLocation>)> {
+    let stack = CallStack::capture();
+    (0..stack.len()).filter_map(|posn| function_at(&stack, ctx, posn)
+        .map(|func| (func, location_at(&stack, ctx, posn))))
+        .collect()
+}
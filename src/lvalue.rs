@@ -0,0 +1,105 @@
+//! Storage that can be assigned to and have its address taken, kept apart
+//! from values that can only be read
+use types::{Ty, Type};
+use value::Val;
+use std::ops::Deref;
+
+/// Where an `LValue` lives
+enum Place<'a> {
+    /// A local variable or parameter
+    Value(&'a Val),
+    /// Memory at an offset from a pointer, holding a value of a type
+    Memory(&'a Val, usize, Type)
+}
+
+/// Storage in a function that can be assigned to and have its address
+/// taken: a local variable, a parameter, or memory a pointer points to
+///
+/// These can only be made by `UncompiledFunction::new_lvalue`,
+/// `param_lvalue` and `deref_lvalue`, so `insn_store` and `insn_address_of`
+/// can't be given a constant or the result of another instruction.
+///
+/// ```rust
+/// use jit::*;
+/// let mut ctx = Context::<()>::new();
+/// let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+/// let total = func.new_lvalue(&get::<i32>());
+/// func.insn_store(&total, &func[0]);
+/// let doubled = func.insn_add(func.insn_read(&total).get(), &func[0]);
+/// func.insn_store(&total, doubled);
+/// func.insn_return(func.insn_read(&total).get());
+/// func.compile().with(|double: extern fn(i32) -> i32| assert_eq!(double(21), 42));
+/// ```
+pub struct LValue<'a> {
+    place: Place<'a>
+}
+impl<'a> LValue<'a> {
+    /// Get the type of the value stored here
+    pub fn get_type(&self) -> &Ty {
+        match self.place {
+            Place::Value(value) => value.get_type(),
+            Place::Memory(_, _, ref ty) => &**ty
+        }
+    }
+    /// Get the local variable or parameter this is, if it isn't memory
+    ///
+    /// Unlike the copy `insn_read` makes, this is the variable itself, so
+    /// instructions given it see whatever was stored to it last when they run.
+    pub fn get_local(&self) -> Option<&'a Val> {
+        match self.place {
+            Place::Value(value) => Some(value),
+            Place::Memory(_, _, _) => None
+        }
+    }
+    /// Get the pointer and offset of the memory this is, if it isn't a local
+    /// variable or parameter
+    pub fn get_memory(&self) -> Option<(&'a Val, usize)> {
+        match self.place {
+            Place::Value(_) => None,
+            Place::Memory(ptr, offset, _) => Some((ptr, offset))
+        }
+    }
+}
+
+/// Wrap a local variable or parameter, which the wrapper knows `value` is
+pub fn local<'a>(value: &'a Val) -> LValue<'a> {
+    LValue {
+        place: Place::Value(value)
+    }
+}
+/// Wrap the memory holding a value of type `ty` at `ptr + offset`
+pub fn memory<'a>(ptr: &'a Val, offset: usize, ty: &Ty) -> LValue<'a> {
+    LValue {
+        place: Place::Memory(ptr, offset, ty.to_owned())
+    }
+}
+
+#[derive(Clone, Copy)]
+/// A value that can only be read, like a constant or the result of an
+/// instruction, which can't be assigned to or have its address taken
+pub struct RValue<'a> {
+    value: &'a Val
+}
+impl<'a> RValue<'a> {
+    /// Wrap a value that is only read
+    pub fn new(value: &'a Val) -> RValue<'a> {
+        RValue {
+            value: value
+        }
+    }
+    /// Get the value, to give to instructions
+    pub fn get(self) -> &'a Val {
+        self.value
+    }
+}
+impl<'a> Deref for RValue<'a> {
+    type Target = Val;
+    fn deref(&self) -> &Val {
+        self.value
+    }
+}
+impl<'a> From<RValue<'a>> for &'a Val {
+    fn from(value: RValue<'a>) -> &'a Val {
+        value.value
+    }
+}
@@ -248,3 +248,16 @@ macro_rules! builtin_types(
         $(builtin_type!($c_name -> $rust_name);)+
     )
 );
+
+macro_rules! param_tuple(
+    ($($ty:ident $index:expr),+) => (
+        impl<'a, $($ty),+> ParamTypes<'a> for ($($ty,)+) where $($ty:Compile<'a>),+ {
+            type Values = ($(Typed<'a, $ty>,)+);
+            fn get_values(func: &UncompiledFunction<'a>) -> Result<Self::Values, SignatureMismatch> {
+                let types = [$(get::<$ty>()),+];
+                try!(check_params(func, &types));
+                Ok(($(Typed::new(param(func, $index)),)+))
+            }
+        }
+    )
+);
@@ -0,0 +1,286 @@
+//! Compiling arithmetic expressions written as text
+//!
+//! Expressions use `+`, `-`, `*`, `/` and `^` for powers, with the usual
+//! precedence, along with brackets, numbers, the constants `pi` and `e`, the
+//! variables, and calls to the math functions `sin`, `cos`, `tan`, `asin`,
+//! `acos`, `atan`, `sinh`, `cosh`, `tanh`, `exp`, `log`, `log10`, `sqrt`,
+//! `abs`, `floor`, `ceil`, `round`, `trunc`, `pow`, `atan2`, `min` and `max`,
+//! which are built with the matching math instructions.
+//!
+//! ```rust
+//! use jit::*;
+//! let mut ctx = Context::<()>::new();
+//! let func = mathexpr::compile(&mut ctx, "2*x + sin(y)").unwrap();
+//! let mut results = [0.0; 2];
+//! func.apply_batch(&[(1.5, 0.0), (0.0, 3.0)], &mut results);
+//! assert_eq!(results, [3.0, 3f64.sin()]);
+//! ```
+use context::Context;
+use function::{Abi, CompiledFunction, UncompiledFunction};
+use types::{get, Type, Ty};
+use value::Val;
+use std::error::Error;
+use std::f64::consts;
+use std::fmt;
+
+#[derive(Clone, Debug, PartialEq)]
+/// An error in an expression's text
+pub struct ExprError {
+    /// The offset in bytes the error was found at
+    pub position: usize,
+    /// What went wrong
+    pub message: String
+}
+impl ExprError {
+    fn new<S: Into<String>>(position: usize, message: S) -> ExprError {
+        ExprError {
+            position: position,
+            message: message.into()
+        }
+    }
+}
+impl fmt::Display for ExprError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "at {}: {}", self.position, self.message)
+    }
+}
+impl Error for ExprError {
+    fn description(&self) -> &str {
+        &self.message
+    }
+}
+
+/// A parsed expression
+enum Expr {
+    Number(f64),
+    Var(usize),
+    Neg(Box<Expr>),
+    Binary(char, Box<Expr>, Box<Expr>),
+    Call(String, Vec<Expr>)
+}
+
+/// The functions expressions can call, and how many arguments they take
+const FUNCTIONS: &'static [(&'static str, usize)] = &[
+    ("sin", 1), ("cos", 1), ("tan", 1), ("asin", 1), ("acos", 1), ("atan", 1),
+    ("sinh", 1), ("cosh", 1), ("tanh", 1), ("exp", 1), ("log", 1), ("log10", 1),
+    ("sqrt", 1), ("abs", 1), ("floor", 1), ("ceil", 1), ("round", 1), ("trunc", 1),
+    ("pow", 2), ("atan2", 2), ("min", 2), ("max", 2)
+];
+
+struct Parser<'t> {
+    text: &'t str,
+    pos: usize,
+    vars: &'t [&'t str]
+}
+impl<'t> Parser<'t> {
+    fn skip_space(&mut self) {
+        while self.text[self.pos..].starts_with(' ') || self.text[self.pos..].starts_with('\t') {
+            self.pos += 1;
+        }
+    }
+    fn peek(&mut self) -> Option<char> {
+        self.skip_space();
+        self.text[self.pos..].chars().next()
+    }
+    fn eat(&mut self, c: char) -> bool {
+        if self.peek() == Some(c) {
+            self.pos += c.len_utf8();
+            true
+        } else {
+            false
+        }
+    }
+    fn expect(&mut self, c: char) -> Result<(), ExprError> {
+        if self.eat(c) {
+            Ok(())
+        } else {
+            Err(ExprError::new(self.pos, format!("expected '{}'", c)))
+        }
+    }
+    fn take_while<F>(&mut self, pred: F) -> &'t str where F: Fn(char) -> bool {
+        let start = self.pos;
+        let len = self.text[start..].find(|c| !pred(c)).unwrap_or(self.text.len() - start);
+        self.pos += len;
+        &self.text[start..start + len]
+    }
+    fn sum(&mut self) -> Result<Expr, ExprError> {
+        let mut left = try!(self.product());
+        loop {
+            let op = match self.peek() {
+                Some(op) if op == '+' || op == '-' => op,
+                _ => return Ok(left)
+            };
+            self.pos += 1;
+            left = Expr::Binary(op, Box::new(left), Box::new(try!(self.product())));
+        }
+    }
+    fn product(&mut self) -> Result<Expr, ExprError> {
+        let mut left = try!(self.unary());
+        loop {
+            let op = match self.peek() {
+                Some(op) if op == '*' || op == '/' => op,
+                _ => return Ok(left)
+            };
+            self.pos += 1;
+            left = Expr::Binary(op, Box::new(left), Box::new(try!(self.unary())));
+        }
+    }
+    fn unary(&mut self) -> Result<Expr, ExprError> {
+        if self.eat('-') {
+            Ok(Expr::Neg(Box::new(try!(self.unary()))))
+        } else if self.eat('+') {
+            self.unary()
+        } else {
+            let base = try!(self.atom());
+            if self.eat('^') {
+                // powers bind to the right, and tighter than a leading minus
+                Ok(Expr::Binary('^', Box::new(base), Box::new(try!(self.unary()))))
+            } else {
+                Ok(base)
+            }
+        }
+    }
+    fn atom(&mut self) -> Result<Expr, ExprError> {
+        let start = self.pos;
+        match self.peek() {
+            Some('(') => {
+                self.pos += 1;
+                let inner = try!(self.sum());
+                try!(self.expect(')'));
+                Ok(inner)
+            },
+            Some(c) if c.is_digit(10) || c == '.' => {
+                self.skip_space();
+                let start = self.pos;
+                self.take_while(|c| c.is_digit(10) || c == '.');
+                let mark = self.pos;
+                if self.text[mark..].starts_with('e') || self.text[mark..].starts_with('E') {
+                    // an exponent, which is only taken if it has digits
+                    self.pos += 1;
+                    if self.text[self.pos..].starts_with('-') || self.text[self.pos..].starts_with('+') {
+                        self.pos += 1;
+                    }
+                    if self.take_while(|c| c.is_digit(10)).is_empty() {
+                        self.pos = mark;
+                    }
+                }
+                let text = &self.text[start..self.pos];
+                text.parse().map(Expr::Number).map_err(|_| ExprError::new(start, format!("bad number '{}'", text)))
+            },
+            Some(c) if c.is_alphabetic() || c == '_' => {
+                self.skip_space();
+                let start = self.pos;
+                let name = self.take_while(|c| c.is_alphanumeric() || c == '_');
+                if self.eat('(') {
+                    let arity = match FUNCTIONS.iter().find(|&&(other, _)| other == name) {
+                        Some(&(_, arity)) => arity,
+                        None => return Err(ExprError::new(start, format!("unknown function '{}'", name)))
+                    };
+                    let mut args = Vec::new();
+                    if !self.eat(')') {
+                        loop {
+                            args.push(try!(self.sum()));
+                            if self.eat(')') {
+                                break
+                            }
+                            try!(self.expect(','));
+                        }
+                    }
+                    if args.len() != arity {
+                        return Err(ExprError::new(start, format!("{} takes {} arguments, got {}", name, arity, args.len())))
+                    }
+                    Ok(Expr::Call(name.to_owned(), args))
+                } else if let Some(index) = self.vars.iter().position(|&var| var == name) {
+                    Ok(Expr::Var(index))
+                } else if name == "pi" {
+                    Ok(Expr::Number(consts::PI))
+                } else if name == "e" {
+                    Ok(Expr::Number(consts::E))
+                } else {
+                    Err(ExprError::new(start, format!("unknown variable '{}'", name)))
+                }
+            },
+            Some(c) => Err(ExprError::new(start, format!("unexpected '{}'", c))),
+            None => Err(ExprError::new(start, "unexpected end of expression"))
+        }
+    }
+}
+
+/// Parse `text`, with the variables given
+fn parse(text: &str, vars: &[&str]) -> Result<Expr, ExprError> {
+    let mut parser = Parser {
+        text: text,
+        pos: 0,
+        vars: vars
+    };
+    let expr = try!(parser.sum());
+    match parser.peek() {
+        Some(c) => Err(ExprError::new(parser.pos, format!("unexpected '{}'", c))),
+        None => Ok(expr)
+    }
+}
+
+/// Build the instructions that work out `expr`
+fn build<'a>(func: &UncompiledFunction<'a>, expr: &Expr) -> &'a Val {
+    match *expr {
+        Expr::Number(value) => func.insn_of(value),
+        Expr::Var(index) => &func[index],
+        Expr::Neg(ref inner) => func.insn_neg(build(func, inner)),
+        Expr::Binary(op, ref left, ref right) => {
+            let (left, right) = (build(func, left), build(func, right));
+            match op {
+                '+' => func.insn_add(left, right),
+                '-' => func.insn_sub(left, right),
+                '*' => func.insn_mul(left, right),
+                '/' => func.insn_div(left, right),
+                _ => func.insn_pow(left, right)
+            }
+        },
+        Expr::Call(ref name, ref args) => {
+            let args: Vec<&'a Val> = args.iter().map(|arg| build(func, arg)).collect();
+            match &**name {
+                "sin" => func.insn_sin(args[0]),
+                "cos" => func.insn_cos(args[0]),
+                "tan" => func.insn_tan(args[0]),
+                "asin" => func.insn_asin(args[0]),
+                "acos" => func.insn_acos(args[0]),
+                "atan" => func.insn_atan(args[0]),
+                "sinh" => func.insn_sinh(args[0]),
+                "cosh" => func.insn_cosh(args[0]),
+                "tanh" => func.insn_tanh(args[0]),
+                "exp" => func.insn_exp(args[0]),
+                "log" => func.insn_log(args[0]),
+                "log10" => func.insn_log10(args[0]),
+                "sqrt" => func.insn_sqrt(args[0]),
+                "abs" => func.insn_abs(args[0]),
+                "floor" => func.insn_floor(args[0]),
+                "ceil" => func.insn_ceil(args[0]),
+                "round" => func.insn_round(args[0]),
+                "trunc" => func.insn_trunc(args[0]),
+                "pow" => func.insn_pow(args[0], args[1]),
+                "atan2" => func.insn_atan2(args[0], args[1]),
+                "min" => func.insn_min(args[0], args[1]),
+                _ => func.insn_max(args[0], args[1])
+            }
+        }
+    }
+}
+
+/// Compile `text` into a function taking a parameter for each of the
+/// variables given, in order, as `f64`s, and returning its value as an `f64`
+pub fn compile_vars<'a, T>(ctx: &'a mut Context<T>, text: &str, vars: &[&str]) -> Result<CompiledFunction<'a>, ExprError> {
+    let expr = try!(parse(text, vars));
+    let f64_t = get::<f64>();
+    let mut params: Vec<&Ty> = vars.iter().map(|_| &*f64_t).collect();
+    let sig = Type::new_signature(Abi::CDecl, &f64_t, &mut params);
+    let func = UncompiledFunction::new(ctx, &sig);
+    let result = build(&func, &expr);
+    func.insn_return(result);
+    Ok(func.compile())
+}
+
+/// Compile `text` into a `fn(f64, f64) -> f64` whose parameters are the
+/// variables `x` and `y`
+pub fn compile<'a, T>(ctx: &'a mut Context<T>, text: &str) -> Result<CompiledFunction<'a>, ExprError> {
+    compile_vars(ctx, text, &["x", "y"])
+}
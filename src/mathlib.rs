@@ -0,0 +1,178 @@
+//! Natives for the math instructions the linked LibJIT can't do
+//!
+//! LibJIT does math instructions like `sinh` and `atan2` by calling its own
+//! intrinsics, which call the C library's math functions when LibJIT was
+//! built with them, and give NaN when it wasn't, as on minimal C libraries.
+//! Each intrinsic is probed once with an argument it has a result for, and
+//! contexts register these natives for the ones that gave NaN, so the
+//! instructions call them instead. Intrinsics the linked LibJIT doesn't have
+//! at all aren't called, and count as missing too.
+use raw::*;
+use types::{get, Type};
+use std::sync::atomic::{AtomicUsize, Ordering, ATOMIC_USIZE_INIT};
+
+/// Which intrinsics gave NaN, with the top bit set once they are probed
+static MISSING: AtomicUsize = ATOMIC_USIZE_INIT;
+const PROBED: usize = 1 << 31;
+
+/// The operations, with whether each takes two arguments
+const OPS: [(&'static str, bool); 20] = [
+    ("acos", false), ("asin", false), ("atan", false), ("atan2", true), ("ceil", false),
+    ("cos", false), ("cosh", false), ("exp", false), ("floor", false), ("log", false),
+    ("log10", false), ("pow", true), ("rint", false), ("round", false), ("sin", false),
+    ("sinh", false), ("sqrt", false), ("tan", false), ("tanh", false), ("trunc", false)
+];
+
+type Unary = unsafe extern "C" fn(f64) -> f64;
+type Binary = unsafe extern "C" fn(f64, f64) -> f64;
+
+// The intrinsics are linked weakly, so they are null when the linked LibJIT
+// doesn't have them instead of failing to link or load
+extern {
+    #[linkage = "extern_weak"]
+    static jit_float64_acos: Option<Unary>;
+    #[linkage = "extern_weak"]
+    static jit_float64_asin: Option<Unary>;
+    #[linkage = "extern_weak"]
+    static jit_float64_atan: Option<Unary>;
+    #[linkage = "extern_weak"]
+    static jit_float64_atan2: Option<Binary>;
+    #[linkage = "extern_weak"]
+    static jit_float64_ceil: Option<Unary>;
+    #[linkage = "extern_weak"]
+    static jit_float64_cos: Option<Unary>;
+    #[linkage = "extern_weak"]
+    static jit_float64_cosh: Option<Unary>;
+    #[linkage = "extern_weak"]
+    static jit_float64_exp: Option<Unary>;
+    #[linkage = "extern_weak"]
+    static jit_float64_floor: Option<Unary>;
+    #[linkage = "extern_weak"]
+    static jit_float64_log: Option<Unary>;
+    #[linkage = "extern_weak"]
+    static jit_float64_log10: Option<Unary>;
+    #[linkage = "extern_weak"]
+    static jit_float64_pow: Option<Binary>;
+    #[linkage = "extern_weak"]
+    static jit_float64_rint: Option<Unary>;
+    #[linkage = "extern_weak"]
+    static jit_float64_round: Option<Unary>;
+    #[linkage = "extern_weak"]
+    static jit_float64_sin: Option<Unary>;
+    #[linkage = "extern_weak"]
+    static jit_float64_sinh: Option<Unary>;
+    #[linkage = "extern_weak"]
+    static jit_float64_sqrt: Option<Unary>;
+    #[linkage = "extern_weak"]
+    static jit_float64_tan: Option<Unary>;
+    #[linkage = "extern_weak"]
+    static jit_float64_tanh: Option<Unary>;
+    #[linkage = "extern_weak"]
+    static jit_float64_trunc: Option<Unary>;
+}
+
+/// Work out the operation at `index` in `OPS` with LibJIT's intrinsic, if
+/// the linked LibJIT has it
+fn probe(index: usize) -> Option<f64> {
+    unsafe {
+        match index {
+            0 => jit_float64_acos.map(|f| f(0.5)),
+            1 => jit_float64_asin.map(|f| f(0.5)),
+            2 => jit_float64_atan.map(|f| f(1.0)),
+            3 => jit_float64_atan2.map(|f| f(1.0, 1.0)),
+            4 => jit_float64_ceil.map(|f| f(1.5)),
+            5 => jit_float64_cos.map(|f| f(1.0)),
+            6 => jit_float64_cosh.map(|f| f(1.0)),
+            7 => jit_float64_exp.map(|f| f(1.0)),
+            8 => jit_float64_floor.map(|f| f(1.5)),
+            9 => jit_float64_log.map(|f| f(2.0)),
+            10 => jit_float64_log10.map(|f| f(2.0)),
+            11 => jit_float64_pow.map(|f| f(2.0, 0.5)),
+            12 => jit_float64_rint.map(|f| f(1.5)),
+            13 => jit_float64_round.map(|f| f(1.5)),
+            14 => jit_float64_sin.map(|f| f(1.0)),
+            15 => jit_float64_sinh.map(|f| f(1.0)),
+            16 => jit_float64_sqrt.map(|f| f(2.0)),
+            17 => jit_float64_tan.map(|f| f(1.0)),
+            18 => jit_float64_tanh.map(|f| f(1.0)),
+            _ => jit_float64_trunc.map(|f| f(1.5))
+        }
+    }
+}
+
+/// Get the names of the operations the linked LibJIT can't do, probing its
+/// intrinsics the first time
+pub fn missing() -> Vec<&'static str> {
+    let mut missing = MISSING.load(Ordering::Acquire);
+    if missing & PROBED == 0 {
+        missing = OPS.iter().enumerate()
+            .filter(|&(index, _)| probe(index).map_or(true, f64::is_nan))
+            .fold(PROBED, |missing, (index, _)| missing | 1 << index);
+        MISSING.store(missing, Ordering::Release);
+    }
+    OPS.iter().enumerate().filter(|&(index, _)| missing & 1 << index != 0).map(|(_, &(name, _))| name).collect()
+}
+/// Find the operation called `name`, if there is a native for it
+pub fn find(name: &str) -> Option<&'static str> {
+    OPS.iter().map(|&(op, _)| op).find(|&op| op == name)
+}
+/// Get the name the native for `op` is registered under
+pub fn native_name(op: &str) -> String {
+    format!("jit_math_{}", op)
+}
+
+extern fn math_acos(x: f64) -> f64 { x.acos() }
+extern fn math_asin(x: f64) -> f64 { x.asin() }
+extern fn math_atan(x: f64) -> f64 { x.atan() }
+extern fn math_atan2(y: f64, x: f64) -> f64 { y.atan2(x) }
+extern fn math_ceil(x: f64) -> f64 { x.ceil() }
+extern fn math_cos(x: f64) -> f64 { x.cos() }
+extern fn math_cosh(x: f64) -> f64 { x.cosh() }
+extern fn math_exp(x: f64) -> f64 { x.exp() }
+extern fn math_floor(x: f64) -> f64 { x.floor() }
+extern fn math_log(x: f64) -> f64 { x.ln() }
+extern fn math_log10(x: f64) -> f64 { x.log10() }
+extern fn math_pow(x: f64, y: f64) -> f64 { x.powf(y) }
+// halfway cases go to the even neighbour, like the C library's in the
+// default rounding mode
+extern fn math_rint(x: f64) -> f64 {
+    let rounded = x.round();
+    if (x - x.trunc()).abs() == 0.5 { 2.0 * (x / 2.0).round() } else { rounded }
+}
+extern fn math_round(x: f64) -> f64 { x.round() }
+extern fn math_sin(x: f64) -> f64 { x.sin() }
+extern fn math_sinh(x: f64) -> f64 { x.sinh() }
+extern fn math_sqrt(x: f64) -> f64 { x.sqrt() }
+extern fn math_tan(x: f64) -> f64 { x.tan() }
+extern fn math_tanh(x: f64) -> f64 { x.tanh() }
+extern fn math_trunc(x: f64) -> f64 { x.trunc() }
+
+/// Get the name, address and signature of the native for `op`
+pub fn helper(op: &str) -> (String, usize, Type) {
+    let address = match op {
+        "acos" => math_acos as usize,
+        "asin" => math_asin as usize,
+        "atan" => math_atan as usize,
+        "atan2" => math_atan2 as usize,
+        "ceil" => math_ceil as usize,
+        "cos" => math_cos as usize,
+        "cosh" => math_cosh as usize,
+        "exp" => math_exp as usize,
+        "floor" => math_floor as usize,
+        "log" => math_log as usize,
+        "log10" => math_log10 as usize,
+        "pow" => math_pow as usize,
+        "rint" => math_rint as usize,
+        "round" => math_round as usize,
+        "sin" => math_sin as usize,
+        "sinh" => math_sinh as usize,
+        "sqrt" => math_sqrt as usize,
+        "tan" => math_tan as usize,
+        "tanh" => math_tanh as usize,
+        "trunc" => math_trunc as usize,
+        _ => panic!("There is no math native for {}", op)
+    };
+    let binary = OPS.iter().any(|&(name, binary)| name == op && binary);
+    let signature = if binary { get::<fn(f64, f64) -> f64>() } else { get::<fn(f64) -> f64>() };
+    (native_name(op), address, signature)
+}
@@ -0,0 +1,134 @@
+use compile::Compile;
+use context::Context;
+use function::{Abi, CompiledFunction, UncompiledFunction};
+use types::{consts, StaticType, Type, Ty};
+use value::Val;
+
+/// A number type that kernels can be written generically over
+///
+/// This lets one Rust function describe a kernel, which can then be built
+/// for each number type it is needed for.
+///
+/// ```rust
+/// use jit::*;
+/// use jit::interp::Value;
+/// fn axpy<'a, T: JitNum>(func: &UncompiledFunction<'a>) {
+///     let scaled = func.insn_mul(&func[0], &func[1]);
+///     let result = func.insn_add(scaled, &func[2]);
+///     func.insn_return(func.insn_add(result, T::insn_lit(func, 1.0)));
+/// }
+/// let mut int_ctx = Context::<()>::new();
+/// let int_axpy = kernel::<i32, _, _>(&mut int_ctx, 3, axpy::<i32>);
+/// assert_eq!(interp::call(&int_axpy, &[Value::Int(2), Value::Int(3), Value::Int(4)]), Ok(Value::Int(11)));
+/// let mut float_ctx = Context::<()>::new();
+/// let float_axpy = kernel::<f64, _, _>(&mut float_ctx, 3, axpy::<f64>);
+/// assert_eq!(interp::call(&float_axpy, &[Value::Float64(0.5), Value::Float64(3.0), Value::Float64(1.0)]),
+///            Ok(Value::Float64(3.5)));
+/// ```
+pub trait JitNum: Copy + 'static {
+    /// Get the type LibJIT uses for this number
+    fn jit_type() -> StaticType;
+    /// Check if this is a floating-point number
+    fn is_float() -> bool;
+    /// Convert a literal to this type, like an `as` cast
+    fn from_f64(value: f64) -> Self;
+    /// Make a constant of this type
+    fn insn_const<'a>(func: &UncompiledFunction<'a>, value: Self) -> &'a Val;
+    /// Make a constant of this type from a literal, like an `as` cast
+    fn insn_lit<'a>(func: &UncompiledFunction<'a>, value: f64) -> &'a Val {
+        Self::insn_const(func, Self::from_f64(value))
+    }
+    /// Make an instruction that converts `value` to this type
+    fn insn_cast<'a>(func: &UncompiledFunction<'a>, value: &'a Val) -> &'a Val {
+        func.insn_convert(value, Self::jit_type(), false)
+    }
+    /// Make the signature of a kernel taking `params` of this type and
+    /// returning one
+    fn signature(params: usize) -> Type {
+        let ty = Self::jit_type();
+        let mut param_types:Vec<&Ty> = (0..params).map(|_| ty).collect();
+        Type::new_signature(Abi::CDecl, ty, &mut param_types)
+    }
+}
+macro_rules! jit_nums(
+    ($($ty:ident => $get:ident, $float:expr);+) => ($(
+        impl JitNum for $ty {
+            #[inline(always)]
+            fn jit_type() -> StaticType {
+                consts::$get()
+            }
+            #[inline(always)]
+            fn is_float() -> bool {
+                $float
+            }
+            #[inline(always)]
+            fn from_f64(value: f64) -> $ty {
+                value as $ty
+            }
+            #[inline(always)]
+            fn insn_const<'a>(func: &UncompiledFunction<'a>, value: $ty) -> &'a Val {
+                <$ty as Compile<'a>>::compile(value, func)
+            }
+        }
+    )+)
+);
+jit_nums!{
+    i32 => get_int, false;
+    u32 => get_uint, false;
+    i64 => get_long, false;
+    u64 => get_ulong, false;
+    isize => get_nint, false;
+    usize => get_nuint, false;
+    f32 => get_float32, true;
+    f64 => get_float64, true
+}
+
+/// An integer as wide as a pointer on the target, which LibJIT calls `nint`
+/// or `nuint`
+///
+/// Offsets, indices and sizes used in pointer arithmetic should be one of
+/// these rather than a fixed `i32`, so they are 64 bits wide on 64-bit
+/// targets and don't get truncated or mixed with pointers of another size.
+///
+/// ```rust
+/// use jit::*;
+/// assert_eq!(<isize as JitNum>::jit_type().get_size(), std::mem::size_of::<isize>());
+/// assert!(<isize as NativeInt>::is_signed());
+/// assert!(!<usize as NativeInt>::is_signed());
+/// ```
+pub trait NativeInt: JitNum {
+    /// Check if this is signed
+    fn is_signed() -> bool;
+    /// Make an instruction that converts the integer or pointer `value` to
+    /// this, sign-extending it if it is a signed integer narrower than this
+    fn insn_widen<'a>(func: &UncompiledFunction<'a>, value: &'a Val) -> &'a Val;
+}
+impl NativeInt for isize {
+    #[inline(always)]
+    fn is_signed() -> bool {
+        true
+    }
+    #[inline(always)]
+    fn insn_widen<'a>(func: &UncompiledFunction<'a>, value: &'a Val) -> &'a Val {
+        func.insn_to_nint(value)
+    }
+}
+impl NativeInt for usize {
+    #[inline(always)]
+    fn is_signed() -> bool {
+        false
+    }
+    #[inline(always)]
+    fn insn_widen<'a>(func: &UncompiledFunction<'a>, value: &'a Val) -> &'a Val {
+        func.insn_to_nuint(value)
+    }
+}
+
+/// Build and compile a kernel for the number type `T`, whose `params`
+/// parameters and result are all `T`, with `body` making its instructions
+pub fn kernel<'a, T, C, F>(ctx: &'a mut Context<C>, params: usize, body: F) -> CompiledFunction<'a>
+    where T: JitNum, F: FnOnce(&UncompiledFunction<'a>) {
+    let func = UncompiledFunction::new(ctx, &T::signature(params));
+    body(&func);
+    func.compile()
+}
@@ -0,0 +1,102 @@
+//! Storage for data that generated code reads, which can be put in pages of
+//! its own that are made read-only once the data is written
+use alloc::oom;
+use std::{cmp, ptr};
+
+/// Data kept alive for generated code, like a global or a table of constants
+pub enum Storage {
+    /// Words on the heap, which code may write to
+    Writable(Vec<u64>),
+    /// Pages that faults are raised on any write to
+    ReadOnly(Pages)
+}
+impl Storage {
+    /// Copy `bytes` into new storage, which is read-only from the start if
+    /// `read_only` is set
+    pub fn new(bytes: &[u8], read_only: bool) -> Storage {
+        if read_only {
+            Storage::ReadOnly(Pages::new(bytes))
+        } else {
+            // store it as words so it is aligned for any type
+            let mut words = vec![0u64; (bytes.len() + 7) / 8];
+            unsafe {
+                ptr::copy_nonoverlapping(bytes.as_ptr(), words.as_mut_ptr() as *mut u8, bytes.len());
+            }
+            Storage::Writable(words)
+        }
+    }
+    /// Get the address of the data
+    pub fn as_ptr(&self) -> *mut u8 {
+        match *self {
+            Storage::Writable(ref words) => words.as_ptr() as *mut u8,
+            Storage::ReadOnly(ref pages) => pages.ptr
+        }
+    }
+    /// Check if the data is read-only
+    pub fn is_read_only(&self) -> bool {
+        match *self {
+            Storage::Writable(_) => false,
+            Storage::ReadOnly(_) => true
+        }
+    }
+}
+
+/// Pages mapped just for some data, which are read-only once it is copied in
+pub struct Pages {
+    ptr: *mut u8,
+    len: usize
+}
+#[cfg(unix)]
+impl Pages {
+    fn new(bytes: &[u8]) -> Pages {
+        use libc::{mmap, mprotect, sysconf, MAP_ANON, MAP_FAILED, MAP_PRIVATE, PROT_READ, PROT_WRITE, _SC_PAGESIZE};
+        unsafe {
+            let page = sysconf(_SC_PAGESIZE) as usize;
+            let len = cmp::max(bytes.len(), 1).checked_add(page - 1).unwrap_or_else(|| oom()) / page * page;
+            let mapped = mmap(ptr::null_mut(), len, PROT_READ | PROT_WRITE, MAP_PRIVATE | MAP_ANON, -1, 0);
+            if mapped == MAP_FAILED {
+                oom()
+            }
+            ptr::copy_nonoverlapping(bytes.as_ptr(), mapped as *mut u8, bytes.len());
+            if mprotect(mapped, len, PROT_READ) != 0 {
+                panic!("Couldn't make the pages at {:?} read-only", mapped);
+            }
+            Pages {
+                ptr: mapped as *mut u8,
+                len: len
+            }
+        }
+    }
+}
+#[cfg(unix)]
+impl Drop for Pages {
+    fn drop(&mut self) {
+        unsafe {
+            ::libc::munmap(self.ptr as *mut ::libc::c_void, self.len);
+        }
+    }
+}
+// without a way to protect pages, the data is only kept apart
+#[cfg(not(unix))]
+impl Pages {
+    fn new(bytes: &[u8]) -> Pages {
+        let mut words = vec![0u64; (cmp::max(bytes.len(), 1) + 7) / 8].into_boxed_slice();
+        unsafe {
+            ptr::copy_nonoverlapping(bytes.as_ptr(), words.as_mut_ptr() as *mut u8, bytes.len());
+        }
+        let len = words.len();
+        Pages {
+            ptr: Box::into_raw(words) as *mut u8,
+            len: len
+        }
+    }
+}
+#[cfg(not(unix))]
+impl Drop for Pages {
+    fn drop(&mut self) {
+        unsafe {
+            let words: Box<[u64]> = Box::from_raw(::std::slice::from_raw_parts_mut(self.ptr as *mut u64, self.len));
+            drop(words);
+        }
+    }
+}
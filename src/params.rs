@@ -0,0 +1,84 @@
+//! Getting a function's parameters as typed values, checked against its
+//! signature
+use raw::*;
+use compile::Compile;
+use function::UncompiledFunction;
+use types::{get, same_layout, CowType, SignatureMismatch};
+use util::from_ptr;
+use value::Val;
+use std::marker::PhantomData;
+use std::ops::Deref;
+
+/// A value known to have the type of `T`, like a parameter got with
+/// `UncompiledFunction::params_as`
+pub struct Typed<'a, T> {
+    value: &'a Val,
+    marker: PhantomData<T>
+}
+impl<'a, T> Clone for Typed<'a, T> {
+    fn clone(&self) -> Typed<'a, T> {
+        Typed::new(self.value)
+    }
+}
+impl<'a, T> Copy for Typed<'a, T> {}
+impl<'a, T> Typed<'a, T> {
+    fn new(value: &'a Val) -> Typed<'a, T> {
+        Typed {
+            value: value,
+            marker: PhantomData
+        }
+    }
+    /// Get the value, to give to instructions
+    pub fn get(self) -> &'a Val {
+        self.value
+    }
+}
+impl<'a, T> Deref for Typed<'a, T> {
+    type Target = Val;
+    fn deref(&self) -> &Val {
+        self.value
+    }
+}
+
+/// A tuple of the types a function's parameters should have
+pub trait ParamTypes<'a> {
+    /// The tuple of typed values the parameters are got as
+    type Values;
+    /// Get the parameters of `func`, or what differs if they don't have
+    /// these types
+    fn get_values(func: &UncompiledFunction<'a>) -> Result<Self::Values, SignatureMismatch>;
+}
+impl<'a> ParamTypes<'a> for () {
+    type Values = ();
+    fn get_values(func: &UncompiledFunction<'a>) -> Result<(), SignatureMismatch> {
+        check_params(func, &[])
+    }
+}
+
+/// Check `func` takes parameters laid out like `types`
+fn check_params(func: &UncompiledFunction, types: &[CowType]) -> Result<(), SignatureMismatch> {
+    let sig = func.get_signature();
+    let count = sig.params().count();
+    if count != types.len() {
+        return Err(SignatureMismatch::Arity(types.len(), count))
+    }
+    for (index, (expected, got)) in types.iter().zip(sig.params()).enumerate() {
+        if !same_layout(expected, got) {
+            return Err(SignatureMismatch::Param(index, format!("{:?}", expected), format!("{:?}", got)))
+        }
+    }
+    Ok(())
+}
+/// Get the parameter of `func` at `index`, which has been checked to exist
+fn param<'a>(func: &UncompiledFunction<'a>, index: u32) -> &'a Val {
+    unsafe {
+        from_ptr(jit_value_get_param(func.into(), index))
+    }
+}
+
+param_tuple!(A 0);
+param_tuple!(A 0, B 1);
+param_tuple!(A 0, B 1, C 2);
+param_tuple!(A 0, B 1, C 2, D 3);
+param_tuple!(A 0, B 1, C 2, D 3, E 4);
+param_tuple!(A 0, B 1, C 2, D 3, E 4, F 5);
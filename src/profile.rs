@@ -0,0 +1,78 @@
+//! Call counts of exported functions, which can be saved at the end of one
+//! run and used to warm up the next
+use std::collections::BTreeMap;
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+/// How many times each exported function was called, by the name it was
+/// exported under
+///
+/// ```rust
+/// use jit::*;
+/// let mut ctx = Context::<()>::new();
+/// ctx.enable_call_counts();
+/// let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+/// func.insn_return(func.insn_add(&func[0], func.insn_of(1i32)));
+/// let func = func.compile();
+/// func.export("inc");
+/// for _ in 0..3 {
+///     func.with(|inc: extern fn(i32) -> i32| assert_eq!(inc(1), 2));
+/// }
+/// let saved = ctx.call_profile().to_text();
+/// assert_eq!(CallProfile::parse(&saved).get("inc"), Some(3));
+/// ```
+pub struct CallProfile {
+    counts: BTreeMap<String, usize>
+}
+impl CallProfile {
+    /// Make a profile with no functions in it
+    pub fn new() -> CallProfile {
+        CallProfile::default()
+    }
+    /// Add `count` calls to the function called `name`
+    pub fn insert(&mut self, name: &str, count: usize) {
+        *self.counts.entry(name.to_owned()).or_insert(0) += count;
+    }
+    /// Get how many times the function called `name` was called
+    pub fn get(&self, name: &str) -> Option<usize> {
+        self.counts.get(name).cloned()
+    }
+    /// Get how many functions are in this
+    pub fn len(&self) -> usize {
+        self.counts.len()
+    }
+    /// Check if there are no functions in this
+    pub fn is_empty(&self) -> bool {
+        self.counts.is_empty()
+    }
+    /// Get the names of the functions called at least `threshold` times,
+    /// sorted by name
+    pub fn hot(&self, threshold: usize) -> Vec<&str> {
+        self.counts.iter()
+            .filter(|&(_, &count)| count >= threshold)
+            .map(|(name, _)| &**name)
+            .collect()
+    }
+    /// Write this out as text, with a line for each function of its name and
+    /// call count separated by a tab, sorted by name
+    pub fn to_text(&self) -> String {
+        let mut text = String::new();
+        for (name, count) in &self.counts {
+            text.push_str(&format!("{}\t{}\n", name, count));
+        }
+        text
+    }
+    /// Read a profile written out by `to_text`, skipping lines that aren't
+    /// in that form
+    pub fn parse(text: &str) -> CallProfile {
+        let mut profile = CallProfile::new();
+        for line in text.lines() {
+            let mut parts = line.splitn(2, '\t');
+            if let (Some(name), Some(count)) = (parts.next(), parts.next()) {
+                if let Ok(count) = count.parse() {
+                    profile.insert(name, count);
+                }
+            }
+        }
+        profile
+    }
+}
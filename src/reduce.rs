@@ -0,0 +1,131 @@
+//! Prebuilt kernels for reducing buffers of numbers
+//!
+//! Each kernel takes a pointer to the first number in each buffer it reads
+//! and how many numbers there are, and gives back one number of the same
+//! type. A kernel is built the first time it is asked for on a context for a
+//! number type, with the context's `UnrollLimits`, and is kept on the context
+//! from then on, so asking again is only a lookup. `sum`, `min`, `max` and
+//! `dot` call them on slices, which means the types always match.
+//!
+//! ```rust
+//! use jit::*;
+//! use jit::reduce;
+//! let mut ctx = Context::<()>::new();
+//! assert_eq!(reduce::sum(&mut ctx, &[1i32, 2, 3, 4]), 10);
+//! assert_eq!(reduce::max(&mut ctx, &[1.5f64, -2.0, 0.5]), Some(1.5));
+//! assert_eq!(reduce::min::<u32, _>(&mut ctx, &[]), None);
+//! assert_eq!(reduce::dot(&mut ctx, &[1.0f32, 2.0], &[3.0, 4.0]), 11.0);
+//! ```
+use raw::*;
+use context::Context;
+use data::ContextData;
+use function::{Abi, CompiledFunction, UncompiledFunction};
+use num::JitNum;
+use types::{consts, Type, Ty};
+use util::from_ptr;
+use value::Val;
+use std::mem;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+/// What a reduction kernel works out from its buffers
+pub enum Reduction {
+    /// Add up the numbers, giving zero for an empty buffer
+    Sum,
+    /// Find the smallest number, giving zero for an empty buffer
+    Min,
+    /// Find the biggest number, giving zero for an empty buffer
+    Max,
+    /// Add up the products of the numbers at each index of two buffers of
+    /// the same length
+    Dot
+}
+impl Reduction {
+    /// Count the buffers a kernel for this reduction reads
+    pub fn buffers(self) -> usize {
+        if self == Reduction::Dot { 2 } else { 1 }
+    }
+    /// Make the signature of a kernel for this reduction over numbers of the
+    /// type `T`, which takes a pointer to each buffer then the length
+    pub fn signature<T>(self) -> Type where T: JitNum {
+        let mut params: Vec<&Ty> = (0..self.buffers()).map(|_| consts::get_void_ptr()).collect();
+        params.push(consts::get_nuint());
+        Type::new_signature(Abi::CDecl, T::jit_type(), &mut params)
+    }
+}
+
+/// Make the instructions of a kernel for `op` over numbers of the type `T`
+fn build<'a, T>(func: &UncompiledFunction<'a>, op: Reduction) where T: JitNum {
+    let ty = T::jit_type();
+    let (first, len) = (&func[0], &func[op.buffers()]);
+    let result_var = func.new_lvalue(ty);
+    let result = result_var.get_local().unwrap();
+    let zero = func.insn_of(0usize);
+    func.insn_store(&result_var, T::insn_lit(func, 0.0));
+    match op {
+        Reduction::Sum | Reduction::Dot => func.insn_for_range(zero, len, |index| {
+            let mut value = func.insn_load_elem(first, index, ty);
+            if op == Reduction::Dot {
+                value = func.insn_mul(value, func.insn_load_elem(&func[1], index, ty));
+            }
+            func.insn_store(&result_var, func.insn_add(result, value));
+        }),
+        Reduction::Min | Reduction::Max => func.insn_if(func.insn_neq(len, zero), || {
+            func.insn_store(&result_var, func.insn_load_elem(first, zero, ty));
+            func.insn_for_range(func.insn_of(1usize), len, |index| {
+                let value = func.insn_load_elem(first, index, ty);
+                func.insn_store(&result_var, if op == Reduction::Min {
+                    func.insn_min(result, value)
+                } else {
+                    func.insn_max(result, value)
+                });
+            });
+        })
+    }
+    func.insn_return(result);
+}
+
+/// Get the kernel for `op` over numbers of the type `T` on `ctx`, building
+/// and compiling it if this is the first time it has been asked for
+pub fn reduction<'a, T, C>(ctx: &'a mut Context<C>, op: Reduction) -> CompiledFunction<'a> where T: JitNum {
+    let ty: jit_type_t = T::jit_type().into();
+    if let Some(func) = ContextData::with((&*ctx).into(), |data| data.reductions.get(&(op, ty)).cloned()) {
+        return from_ptr(func)
+    }
+    let func = UncompiledFunction::new(ctx, &op.signature::<T>());
+    build::<T>(&func, op);
+    let func = func.compile();
+    let raw: jit_function_t = (&func).into();
+    ContextData::with_func(raw, |data| data.reductions.insert((op, ty), raw));
+    func
+}
+
+/// Get the entry point of the kernel for `op` over `T` on `ctx`
+fn entry<T, C>(ctx: &mut Context<C>, op: Reduction) -> usize where T: JitNum {
+    let func = reduction::<T, C>(ctx, op);
+    unsafe { jit_function_to_closure((&func).into()) as usize }
+}
+
+/// Add up `values`
+pub fn sum<T, C>(ctx: &mut Context<C>, values: &[T]) -> T where T: JitNum {
+    let sum: extern fn(*const T, usize) -> T = unsafe { mem::transmute(entry::<T, C>(ctx, Reduction::Sum)) };
+    sum(values.as_ptr(), values.len())
+}
+/// Find the smallest of `values`, if there are any
+pub fn min<T, C>(ctx: &mut Context<C>, values: &[T]) -> Option<T> where T: JitNum {
+    let min: extern fn(*const T, usize) -> T = unsafe { mem::transmute(entry::<T, C>(ctx, Reduction::Min)) };
+    if values.is_empty() { None } else { Some(min(values.as_ptr(), values.len())) }
+}
+/// Find the biggest of `values`, if there are any
+pub fn max<T, C>(ctx: &mut Context<C>, values: &[T]) -> Option<T> where T: JitNum {
+    let max: extern fn(*const T, usize) -> T = unsafe { mem::transmute(entry::<T, C>(ctx, Reduction::Max)) };
+    if values.is_empty() { None } else { Some(max(values.as_ptr(), values.len())) }
+}
+/// Add up the products of the numbers at each index of `a` and `b`, which
+/// panics if they have different lengths
+pub fn dot<T, C>(ctx: &mut Context<C>, a: &[T], b: &[T]) -> T where T: JitNum {
+    if a.len() != b.len() {
+        panic!("Dot products need buffers of the same length, got {} and {}", a.len(), b.len())
+    }
+    let dot: extern fn(*const T, *const T, usize) -> T = unsafe { mem::transmute(entry::<T, C>(ctx, Reduction::Dot)) };
+    dot(a.as_ptr(), b.as_ptr(), a.len())
+}
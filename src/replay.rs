@@ -0,0 +1,429 @@
+//! Rebuilding instructions read back from one function's IR into another
+use raw::*;
+use function::UncompiledFunction;
+use insn::{Block, Instruction};
+use label::Label;
+use lvalue;
+use value::Val;
+use support;
+use util::from_ptr;
+use std::collections::HashMap;
+
+type BinaryInsn = unsafe extern "C" fn(jit_function_t, jit_value_t, jit_value_t) -> jit_value_t;
+type UnaryInsn = unsafe extern "C" fn(jit_function_t, jit_value_t) -> jit_value_t;
+
+/// A typed arithmetic, comparison or math opcode split into its parts
+pub struct Decoded<'n> {
+    /// The operation without its type prefix or modifiers, like `add`
+    pub op: &'n str,
+    /// If the operation traps on overflow
+    pub overflow: bool,
+    /// If the operation is a float comparison that is true for NaN operands
+    pub inverted: bool
+}
+/// Split an opcode name like `iadd_ovf_un` into its operation and modifiers
+///
+/// Unsigned variants aren't distinguished, since the builder picks them from
+/// the types of the operands.
+pub fn decode(name: &str) -> Option<Decoded> {
+    let rest = if name.starts_with("nf") {
+        &name[2..]
+    } else if name.starts_with("i") || name.starts_with("l") || name.starts_with("f") || name.starts_with("d") {
+        &name[1..]
+    } else {
+        return None
+    };
+    let rest = if rest.ends_with("_un") { &rest[..rest.len() - 3] } else { rest };
+    let (rest, inverted) = if rest.ends_with("_inv") { (&rest[..rest.len() - 4], true) } else { (rest, false) };
+    let (rest, overflow) = if rest.ends_with("_ovf") { (&rest[..rest.len() - 4], true) } else { (rest, false) };
+    if binary(rest, overflow).is_some() || unary(rest).is_some() {
+        Some(Decoded {
+            op: rest,
+            overflow: overflow,
+            inverted: inverted
+        })
+    } else {
+        None
+    }
+}
+/// Get the comparison that is true exactly when `op` is false
+pub fn inverse(op: &str) -> Option<&'static str> {
+    Some(match op {
+        "eq" => "ne",
+        "ne" => "eq",
+        "lt" => "ge",
+        "le" => "gt",
+        "gt" => "le",
+        "ge" => "lt",
+        _ => return None
+    })
+}
+fn binary(op: &str, overflow: bool) -> Option<BinaryInsn> {
+    Some(match (op, overflow) {
+        ("add", false) => jit_insn_add,
+        ("add", true) => jit_insn_add_ovf,
+        ("sub", false) => jit_insn_sub,
+        ("sub", true) => jit_insn_sub_ovf,
+        ("mul", false) => jit_insn_mul,
+        ("mul", true) => jit_insn_mul_ovf,
+        ("div", false) => jit_insn_div,
+        ("rem", false) => jit_insn_rem,
+        ("rem_ieee", false) => jit_insn_rem_ieee,
+        ("and", false) => jit_insn_and,
+        ("or", false) => jit_insn_or,
+        ("xor", false) => jit_insn_xor,
+        ("shl", false) => jit_insn_shl,
+        ("shr", false) => jit_insn_shr,
+        ("eq", false) => jit_insn_eq,
+        ("ne", false) => jit_insn_ne,
+        ("lt", false) => jit_insn_lt,
+        ("le", false) => jit_insn_le,
+        ("gt", false) => jit_insn_gt,
+        ("ge", false) => jit_insn_ge,
+        ("cmp", false) | ("cmpl", false) => jit_insn_cmpl,
+        ("cmpg", false) => jit_insn_cmpg,
+        ("atan2", false) => jit_insn_atan2,
+        ("pow", false) => jit_insn_pow,
+        ("min", false) => jit_insn_min,
+        ("max", false) => jit_insn_max,
+        _ => return None
+    })
+}
+fn unary(op: &str) -> Option<UnaryInsn> {
+    Some(match op {
+        "neg" => jit_insn_neg,
+        "not" => jit_insn_not,
+        "acos" => jit_insn_acos,
+        "asin" => jit_insn_asin,
+        "atan" => jit_insn_atan,
+        "ceil" => jit_insn_ceil,
+        "cos" => jit_insn_cos,
+        "cosh" => jit_insn_cosh,
+        "exp" => jit_insn_exp,
+        "floor" => jit_insn_floor,
+        "log" => jit_insn_log,
+        "log10" => jit_insn_log10,
+        "rint" | "round" | "trunc" => return support::unary_insn(&format!("jit_insn_{}", op)),
+        "sin" => jit_insn_sin,
+        "sinh" => jit_insn_sinh,
+        "sqrt" => jit_insn_sqrt,
+        "tan" => jit_insn_tan,
+        "tanh" => jit_insn_tanh,
+        "abs" => jit_insn_abs,
+        "sign" => jit_insn_sign,
+        "nan" => jit_insn_is_nan,
+        "finite" => jit_insn_is_finite,
+        "inf" => jit_insn_is_inf,
+        _ => return None
+    })
+}
+/// Check if the opcode converts its operand to the type of its destination
+pub fn is_conversion(name: &str) -> bool {
+    (name.starts_with("trunc_") || name.starts_with("check_") || name.starts_with("expand_")
+        || name.contains("_to_") || name == "low_word") && name != "check_null"
+}
+
+/// The opcodes that return a value, which can be rebuilt when the result of
+/// the blocks is being collected
+const RETURNS: &'static [&'static str] = &[
+    "return_int", "return_long", "return_float32", "return_float64", "return_nfloat"
+];
+/// Check if an instruction with the opcode given can be rebuilt, assuming
+/// its operands can be, and that returns are collected with `set_result`
+pub fn rebuildable(name: &str) -> bool {
+    match name {
+        "nop" | "mark_offset" | "mark_breakpoint" | "br" | "throw" | "check_null" | "address_of"
+            | "alloca" | "add_relative" | "memcpy" | "memmove" | "memset" | "return" => true,
+        _ if name.starts_with("br_") => {
+            name.ends_with("false") || name.ends_with("true") || match decode(&name[3..]) {
+                Some(decoded) => !decoded.inverted || inverse(decoded.op).is_some(),
+                None => false
+            }
+        },
+        _ => name.starts_with("copy_") || is_conversion(name) || name.starts_with("load_relative_")
+            || name.starts_with("store_relative_") || name.starts_with("load_element_")
+            || name.starts_with("store_element_") || decode(name).is_some()
+            || RETURNS.iter().any(|&ret| ret == name)
+    }
+}
+
+/// Find the label made for a block that isn't being rebuilt, making one if
+/// there isn't one yet
+fn outside_label<'o, 'a>(func: &UncompiledFunction<'a>, outside: &'o mut Vec<(jit_block_t, Label<'a>)>,
+                         block: jit_block_t) -> &'o mut Label<'a> {
+    let index = match outside.iter().position(|&(other, _)| other == block) {
+        Some(index) => index,
+        None => {
+            outside.push((block, Label::new(func)));
+            outside.len() - 1
+        }
+    };
+    &mut outside[index].1
+}
+
+/// Rebuilds a sequence of blocks from one function into another
+///
+/// Values from outside the blocks must be mapped in `values` before running,
+/// and branches may only go to the blocks being rebuilt or to the exit label,
+/// which becomes a return from the function being built, unless branches to
+/// other blocks are allowed with `allow_outside`.
+pub struct Replay<'b, 'a:'b> {
+    func: &'b UncompiledFunction<'a>,
+    source: jit_function_t,
+    /// The values in the function being built that values in the source map to
+    pub values: HashMap<jit_value_t, &'a Val>,
+    labels: HashMap<jit_block_t, Label<'a>>,
+    exit: Option<(jit_label_t, Label<'a>)>,
+    result: Option<(Option<&'a Val>, Label<'a>)>,
+    outside: Option<Vec<(jit_block_t, Label<'a>)>>
+}
+impl<'b, 'a> Replay<'b, 'a> {
+    /// Prepare to rebuild `blocks` from `source` into `func`
+    pub fn new(source: jit_function_t, func: &'b UncompiledFunction<'a>,
+               blocks: &[jit_block_t], exit: Option<jit_label_t>) -> Replay<'b, 'a> {
+        Replay {
+            func: func,
+            source: source,
+            values: HashMap::new(),
+            labels: blocks.iter().map(|&block| (block, Label::new(func))).collect(),
+            exit: exit.map(|label| (label, Label::new(func))),
+            result: None,
+            outside: None
+        }
+    }
+    /// Make returns in the blocks store their value in `result` and jump to
+    /// the end of the rebuilt blocks, instead of failing to be rebuilt
+    pub fn set_result(&mut self, result: Option<&'a Val>) {
+        self.result = Some((result, Label::new(self.func)));
+    }
+    /// Make branches to blocks that aren't being rebuilt go to labels of
+    /// their own, which are given by `take_outside` after running
+    pub fn allow_outside(&mut self) {
+        self.outside = Some(Vec::new());
+    }
+    /// Get the labels made for blocks that aren't being rebuilt, so the caller
+    /// can set them and say what happens there
+    pub fn take_outside(&mut self) -> Vec<(jit_block_t, Label<'a>)> {
+        self.outside.take().unwrap_or(Vec::new())
+    }
+    /// Get the function being built
+    pub fn get_func(&self) -> &'b UncompiledFunction<'a> {
+        self.func
+    }
+    /// Get the label the block being rebuilt given starts with
+    pub fn get_label(&self, block: jit_block_t) -> Option<jit_label_t> {
+        self.labels.get(&block).map(|label| **label as jit_label_t)
+    }
+    /// Get the label for a block that isn't being rebuilt, if that is allowed
+    pub fn outside_label(&mut self, block: jit_block_t) -> Option<&mut Label<'a>> {
+        let func = self.func;
+        match self.outside {
+            Some(ref mut outside) => Some(outside_label(func, outside, block)),
+            None => None
+        }
+    }
+    /// Rebuild the blocks in order, or give the name of the first instruction
+    /// that couldn't be rebuilt
+    pub fn run(&mut self, blocks: &[jit_block_t]) -> Result<(), String> {
+        try!(self.rebuild(blocks));
+        self.finish();
+        Ok(())
+    }
+    /// Rebuild the blocks in order without finishing, so more code can be
+    /// added after the last block before the exit and result labels
+    pub fn rebuild(&mut self, blocks: &[jit_block_t]) -> Result<(), String> {
+        for &block in blocks {
+            self.start_block(block);
+            let block:Block<'a> = from_ptr(block);
+            for insn in block.iter() {
+                try!(self.insn(insn));
+            }
+        }
+        Ok(())
+    }
+    /// Set the label of a block being rebuilt, so the instructions rebuilt
+    /// after this are in it
+    pub fn start_block(&mut self, block: jit_block_t) {
+        self.func.insn_label(self.labels.get_mut(&block).unwrap());
+    }
+    /// Rebuild one instruction where the function being built is up to, or
+    /// give its name if it couldn't be rebuilt
+    pub fn rebuild_insn(&mut self, insn: Instruction<'a>) -> Result<(), String> {
+        self.insn(insn)
+    }
+    /// Set the exit and result labels after the rebuilt blocks
+    pub fn finish(&mut self) {
+        let func = self.func;
+        if let Some(ref mut exit) = self.exit {
+            func.insn_label(&mut exit.1);
+            func.insn_default_return();
+        }
+        if let Some((_, ref mut after)) = self.result {
+            func.insn_label(after);
+        }
+    }
+    fn target(&mut self, insn: Instruction) -> Result<&mut Label<'a>, String> {
+        let func = self.func;
+        let label = unsafe { jit_insn_get_label(insn.into()) };
+        if let Some(ref mut exit) = self.exit {
+            if exit.0 == label {
+                return Ok(&mut exit.1)
+            }
+        }
+        let block = unsafe { jit_block_from_label(self.source, label) };
+        if let Some(label) = self.labels.get_mut(&block) {
+            return Ok(label)
+        }
+        match self.outside {
+            Some(ref mut outside) => Ok(outside_label(func, outside, block)),
+            None => Err(format!("{} to a block that isn't being rebuilt", insn.get_name()))
+        }
+    }
+    /// Get the value in the function being built that `value` maps to,
+    /// mapping it first if it is a constant or local not seen yet
+    pub fn value(&mut self, value: jit_value_t) -> Result<&'a Val, String> {
+        if let Some(&mapped) = self.values.get(&value) {
+            return Ok(mapped)
+        }
+        let mapped:&'a Val = unsafe {
+            if jit_value_is_constant(value) != 0 {
+                let constant = jit_value_get_constant(value);
+                from_ptr(jit_value_create_constant(self.func.into(), &constant))
+            } else if jit_value_is_temporary(value) == 0 {
+                from_ptr(jit_value_create(self.func.into(), jit_value_get_type(value)))
+            } else {
+                return Err("a temporary used before it was defined".to_owned())
+            }
+        };
+        self.values.insert(value, mapped);
+        Ok(mapped)
+    }
+    /// Make `dest` map to `result`, storing it if `dest` is a local
+    pub fn define(&mut self, dest: jit_value_t, result: &'a Val) {
+        if let Some(&local) = self.values.get(&dest) {
+            self.func.insn_store(&lvalue::local(local), result);
+        } else if unsafe { jit_value_is_temporary(dest) != 0 } {
+            self.values.insert(dest, result);
+        } else {
+            let local:&'a Val = unsafe {
+                from_ptr(jit_value_create(self.func.into(), jit_value_get_type(dest)))
+            };
+            self.func.insn_store(&lvalue::local(local), result);
+            self.values.insert(dest, local);
+        }
+    }
+    fn insn(&mut self, insn: Instruction<'a>) -> Result<(), String> {
+        let name = insn.get_name();
+        let func = self.func;
+        let raw_func:jit_function_t = func.into();
+        let (dest, v1, v2) = unsafe {
+            (jit_insn_get_dest(insn.into()), jit_insn_get_value1(insn.into()), jit_insn_get_value2(insn.into()))
+        };
+        unsafe {
+            if name == "nop" || name == "mark_offset" || name == "mark_breakpoint" {
+                // nothing to rebuild
+            } else if name == "br" {
+                func.insn_branch(try!(self.target(insn)));
+            } else if name.starts_with("br_") && (name.ends_with("false") || name.ends_with("true")) {
+                let cond = try!(self.value(v1));
+                if name.ends_with("true") {
+                    func.insn_branch_if(cond, try!(self.target(insn)));
+                } else {
+                    func.insn_branch_if_not(cond, try!(self.target(insn)));
+                }
+            } else if name.starts_with("br_") {
+                let decoded = match decode(&name[3..]) {
+                    Some(decoded) => decoded,
+                    None => return Err(name.to_owned())
+                };
+                let (a, b) = (try!(self.value(v1)), try!(self.value(v2)));
+                if decoded.inverted {
+                    let op = try!(inverse(decoded.op).ok_or(name.to_owned()));
+                    let cond = from_ptr(binary(op, false).unwrap()(raw_func, a.into(), b.into()));
+                    func.insn_branch_if_not(cond, try!(self.target(insn)));
+                } else {
+                    let cond = from_ptr(binary(decoded.op, false).unwrap()(raw_func, a.into(), b.into()));
+                    func.insn_branch_if(cond, try!(self.target(insn)));
+                }
+            } else if name == "return" || RETURNS.iter().any(|&ret| ret == name) {
+                let value = if name == "return" { None } else { Some(try!(self.value(v1))) };
+                match self.result {
+                    Some((local, ref mut after)) => {
+                        if let (Some(local), Some(value)) = (local, value) {
+                            func.insn_store(&lvalue::local(local), value);
+                        }
+                        func.insn_branch(after);
+                    },
+                    None => return Err(name.to_owned())
+                }
+            } else if name == "throw" {
+                func.insn_throw(try!(self.value(v1)));
+            } else if name == "check_null" {
+                jit_insn_check_null(raw_func, try!(self.value(v1)).into());
+            } else if name.starts_with("copy_") {
+                let value = try!(self.value(v1));
+                let ty = jit_value_get_type(dest);
+                if ty == jit_value_get_type(v1) {
+                    self.define(dest, value);
+                } else {
+                    let result = func.insn_convert(value, from_ptr(ty), false);
+                    self.define(dest, result);
+                }
+            } else if is_conversion(name) {
+                let value = try!(self.value(v1));
+                let result = func.insn_convert(value, from_ptr(jit_value_get_type(dest)), name.starts_with("check_"));
+                self.define(dest, result);
+            } else if name == "address_of" {
+                let result = func.insn_address_of(&lvalue::local(try!(self.value(v1))));
+                self.define(dest, result);
+            } else if name == "alloca" {
+                let result = func.insn_alloca(try!(self.value(v1)));
+                self.define(dest, result);
+            } else if name == "add_relative" {
+                let value = try!(self.value(v1));
+                let result = from_ptr(jit_insn_add_relative(raw_func, value.into(), jit_value_get_nint_constant(v2)));
+                self.define(dest, result);
+            } else if name.starts_with("load_relative_") {
+                let value = try!(self.value(v1));
+                let offset = jit_value_get_nint_constant(v2) as usize;
+                let result = func.insn_load_relative(value, offset, from_ptr(jit_value_get_type(dest)));
+                self.define(dest, result);
+            } else if name.starts_with("store_relative_") {
+                let (ptr, value) = (try!(self.value(dest)), try!(self.value(v1)));
+                func.insn_store_relative(ptr, jit_value_get_nint_constant(v2) as usize, value);
+            } else if name.starts_with("load_element_") {
+                let (base, index) = (try!(self.value(v1)), try!(self.value(v2)));
+                let result = from_ptr(jit_insn_load_elem(raw_func, base.into(), index.into(), jit_value_get_type(dest)));
+                self.define(dest, result);
+            } else if name.starts_with("store_element_") {
+                let (base, index, value) = (try!(self.value(dest)), try!(self.value(v1)), try!(self.value(v2)));
+                jit_insn_store_elem(raw_func, base.into(), index.into(), value.into());
+            } else if name == "memcpy" || name == "memmove" || name == "memset" {
+                let (target, a, b) = (try!(self.value(dest)), try!(self.value(v1)), try!(self.value(v2)));
+                match name {
+                    "memcpy" => func.insn_memcpy(target, a, b),
+                    "memmove" => func.insn_memmove(target, a, b),
+                    _ => func.insn_memset(target, a, b)
+                };
+            } else if let Some(decoded) = decode(name) {
+                let a = try!(self.value(v1));
+                let result = if let Some(op) = unary(decoded.op) {
+                    from_ptr(op(raw_func, a.into()))
+                } else {
+                    let b = try!(self.value(v2));
+                    if decoded.inverted {
+                        let op = try!(inverse(decoded.op).ok_or(name.to_owned()));
+                        let cond = binary(op, false).unwrap()(raw_func, a.into(), b.into());
+                        from_ptr(jit_insn_to_not_bool(raw_func, cond))
+                    } else {
+                        from_ptr(binary(decoded.op, decoded.overflow).unwrap()(raw_func, a.into(), b.into()))
+                    }
+                };
+                self.define(dest, result);
+            } else {
+                return Err(name.to_owned())
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,126 @@
+use raw::*;
+use data::FunctionData;
+use debuginfo;
+use insn::Blocks;
+use std::collections::HashSet;
+use std::slice;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+/// Diagnostics about the code generated for a function, for tuning how hot
+/// kernels are emitted
+pub struct CodegenReport {
+    /// How many blocks the function had when it was compiled
+    pub blocks: usize,
+    /// How many instructions the function had when it was compiled
+    pub instructions: usize,
+    /// How many values live across blocks, which LibJIT only keeps in
+    /// registers within a block, so each one is spilled to the frame at
+    /// block boundaries unless it got one of the few global registers
+    pub spilled: usize,
+    /// How many values live in the frame because their address is taken or
+    /// they are volatile
+    pub addressable: usize,
+    /// How many bytes of machine code were generated
+    pub code_size: usize,
+    /// How many bytes of stack the function's frame reserves, as decoded from
+    /// its prologue, where the prologue is understood
+    pub frame_size: Option<usize>,
+    /// How many callee-saved registers the prologue saves, where the prologue
+    /// is understood
+    pub saved_registers: Option<usize>
+}
+
+/// Count what the report needs from the IR of `func`, which LibJIT frees
+/// once it is compiled, and keep it for when the report is asked for
+pub fn record(func: jit_function_t) {
+    let counts = count(func);
+    FunctionData::with(func, |data| data.codegen_counts = Some(counts));
+}
+/// Count the blocks, instructions, values living across blocks and values
+/// living in the frame of `func`
+fn count(func: jit_function_t) -> (usize, usize, usize, usize) {
+    let (mut blocks, mut instructions) = (0, 0);
+    let mut seen = HashSet::new();
+    let (mut spilled, mut addressable) = (0, 0);
+    for block in Blocks::new(func) {
+        blocks += 1;
+        for insn in block.iter() {
+            instructions += 1;
+            let values = insn.get_dest().into_iter()
+                .chain(insn.get_value1().into_iter())
+                .chain(insn.get_value2().into_iter());
+            for value in values {
+                let ptr: jit_value_t = value.into();
+                if value.is_constant() || !seen.insert(ptr) {
+                    continue
+                }
+                unsafe {
+                    if jit_value_is_addressable(ptr) != 0 || jit_value_is_volatile(ptr) != 0 {
+                        addressable += 1;
+                    } else if jit_value_is_local(ptr) != 0 && jit_value_is_temporary(ptr) == 0 {
+                        spilled += 1;
+                    }
+                }
+            }
+        }
+    }
+    (blocks, instructions, spilled, addressable)
+}
+
+/// Decode the frame size and saved registers from the prologue LibJIT's
+/// x86-64 backend makes, which pushes `rbp`, points it at the stack, reserves
+/// the frame and then moves the callee-saved registers into its bottom
+#[cfg(target_arch = "x86_64")]
+fn prologue(code: &[u8]) -> (Option<usize>, Option<usize>) {
+    if code.len() < 4 || code[0] != 0x55 || !(code[1..4] == [0x48, 0x8b, 0xec] || code[1..4] == [0x48, 0x89, 0xe5]) {
+        return (None, None)
+    }
+    let mut at = 4;
+    let frame = if code.len() >= at + 4 && code[at..at + 3] == [0x48, 0x83, 0xec] {
+        at += 4;
+        code[at - 1] as usize
+    } else if code.len() >= at + 7 && code[at..at + 3] == [0x48, 0x81, 0xec] {
+        at += 7;
+        code[at - 4..at].iter().rev().fold(0, |size, &byte| size << 8 | byte as usize)
+    } else {
+        0
+    };
+    let mut saved = 0;
+    // mov [rsp + disp], reg
+    while code.len() >= at + 4 && (code[at] == 0x48 || code[at] == 0x4c) && code[at + 1] == 0x89 &&
+            code[at + 2] & 7 == 4 && code[at + 3] == 0x24 {
+        at += match code[at + 2] >> 6 {
+            0 => 4,
+            1 => 5,
+            2 => 8,
+            _ => break
+        };
+        saved += 1;
+    }
+    (Some(frame), Some(saved))
+}
+/// The prologue isn't understood on this architecture
+#[cfg(not(target_arch = "x86_64"))]
+fn prologue(_: &[u8]) -> (Option<usize>, Option<usize>) {
+    (None, None)
+}
+
+/// Make the report for the compiled `func`, if it was counted before it was
+/// compiled
+pub fn report(func: jit_function_t) -> Option<CodegenReport> {
+    let counts = FunctionData::with(func, |data| data.codegen_counts);
+    counts.map(|(blocks, instructions, spilled, addressable)| {
+        let (start, end) = debuginfo::code_range(func);
+        let code = unsafe { slice::from_raw_parts(start as *const u8, end - start) };
+        let (frame_size, saved_registers) = prologue(code);
+        CodegenReport {
+            blocks: blocks,
+            instructions: instructions,
+            spilled: spilled,
+            addressable: addressable,
+            code_size: end - start,
+            frame_size: frame_size,
+            saved_registers: saved_registers
+        }
+    })
+}
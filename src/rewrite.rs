@@ -0,0 +1,70 @@
+//! Rewriting a function instruction by instruction
+use raw::*;
+use function::UncompiledFunction;
+use insn::Instruction;
+use replay::Replay;
+use value::Val;
+
+/// What `UncompiledFunction::rewrite` does with an instruction it visits
+pub enum Rewrite<'a> {
+    /// Rebuild the instruction as it is, after anything the visitor made
+    Keep,
+    /// Leave the instruction out, which must only be done to instructions
+    /// whose result isn't used afterwards
+    Delete,
+    /// Leave the instruction out, having made the instructions that replace
+    /// it, with uses of its result going to the value given instead
+    Replace(Option<&'a Val>)
+}
+
+/// The function being built by `UncompiledFunction::rewrite`, as seen from a
+/// visitor, which can make instructions in it where the rewrite is up to
+pub struct Splice<'r, 'b: 'r, 'a: 'b> {
+    replay: &'r mut Replay<'b, 'a>
+}
+impl<'r, 'b, 'a> Splice<'r, 'b, 'a> {
+    /// Wrap the replay doing the rewrite
+    pub fn new(replay: &'r mut Replay<'b, 'a>) -> Splice<'r, 'b, 'a> {
+        Splice {
+            replay: replay
+        }
+    }
+    /// Get the function being built, to make instructions in
+    pub fn get_func(&self) -> &'b UncompiledFunction<'a> {
+        self.replay.get_func()
+    }
+    /// Get the value in the function being built that `value`, from the
+    /// function being rewritten, has become, like an operand of the
+    /// instruction being visited
+    ///
+    /// This panics if `value` is a temporary that hasn't been worked out yet.
+    pub fn map(&mut self, value: &Val) -> &'a Val {
+        match self.replay.value(value.into()) {
+            Ok(mapped) => mapped,
+            Err(_) => panic!("{:?} is used before it is worked out", value)
+        }
+    }
+    /// Make the rest of the function use `with` where the function being
+    /// rewritten used `value`
+    pub fn repoint(&mut self, value: &Val, with: &'a Val) {
+        let value: jit_value_t = value.into();
+        self.replay.define(value, with)
+    }
+}
+
+/// Run `each` on `insn`, then do what it says, or give the name of the
+/// instruction if it can't be rebuilt
+pub fn visit<'b, 'a, F>(replay: &mut Replay<'b, 'a>, insn: Instruction<'a>, each: &mut F) -> Result<(), String>
+    where F: for<'r> FnMut(&mut Splice<'r, 'b, 'a>, Instruction<'a>) -> Rewrite<'a> {
+    let rewrite = each(&mut Splice::new(replay), insn);
+    match rewrite {
+        Rewrite::Keep => replay.rebuild_insn(insn),
+        Rewrite::Delete => Ok(()),
+        Rewrite::Replace(value) => {
+            if let (Some(dest), Some(value)) = (insn.get_dest(), value) {
+                Splice::new(replay).repoint(dest, value);
+            }
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,126 @@
+use raw::*;
+use libc::c_void;
+use std::cell::RefCell;
+use std::marker::PhantomData;
+use std::ptr;
+
+/// A frame on a shadow stack, as laid out by the code functions emit
+#[repr(C)]
+pub struct Frame {
+    /// The frame below this one
+    pub parent: *const Frame,
+    /// The descriptor given when the frame was pushed
+    pub descriptor: *const c_void
+}
+
+/// A value pinned with `insn_pin`, as laid out by the code functions emit
+#[repr(C)]
+pub struct Root {
+    /// The value pinned before this one
+    pub parent: *const Root,
+    /// The address of the pinned value's stack slot
+    pub address: *mut c_void
+}
+
+type Slots<T> = RefCell<Vec<(jit_context_t, Box<*const T>)>>;
+
+thread_local!(static SLOTS: Slots<Frame> = RefCell::new(Vec::new()));
+thread_local!(static ROOTS: Slots<Root> = RefCell::new(Vec::new()));
+
+/// Find the top of the stack of `ctx` in `slots`, making it if there isn't one
+fn find<T>(slots: &Slots<T>, ctx: jit_context_t) -> *mut *const T {
+    let mut slots = slots.borrow_mut();
+    if let Some(&mut (_, ref mut top)) = slots.iter_mut().find(|&&mut (other, _)| other == ctx) {
+        return &mut **top as *mut *const T
+    }
+    let mut top = Box::new(ptr::null());
+    let address = &mut *top as *mut *const T;
+    slots.push((ctx, top));
+    address
+}
+/// Get the address of the top of the shadow stack of `ctx` in this thread
+///
+/// This is called by the code functions emit on entry, so it must stay
+/// valid until the context is destroyed.
+pub extern fn slot(ctx: jit_context_t) -> *mut *const Frame {
+    SLOTS.with(|slots| find(slots, ctx))
+}
+/// Get the address of the most recently pinned value of `ctx` in this
+/// thread, which stays valid like `slot`
+pub extern fn root_slot(ctx: jit_context_t) -> *mut *const Root {
+    ROOTS.with(|slots| find(slots, ctx))
+}
+/// Get the top frame of the shadow stack of `ctx` in this thread
+pub fn top(ctx: jit_context_t) -> *const Frame {
+    unsafe { *slot(ctx) }
+}
+/// Get the most recently pinned value of `ctx` in this thread
+pub fn top_root(ctx: jit_context_t) -> *const Root {
+    unsafe { *root_slot(ctx) }
+}
+/// Forget the shadow stack and pinned values of `ctx` in this thread
+pub fn forget(ctx: jit_context_t) {
+    SLOTS.with(|slots| slots.borrow_mut().retain(|&(other, _)| other != ctx));
+    ROOTS.with(|slots| slots.borrow_mut().retain(|&(other, _)| other != ctx))
+}
+
+/// An iterator through the descriptors of the frames on a shadow stack,
+/// from the most recently pushed
+pub struct ShadowFrames<'a> {
+    frame: *const Frame,
+    marker: PhantomData<&'a ()>
+}
+impl<'a> ShadowFrames<'a> {
+    /// Iterate from `frame` down
+    pub fn new(frame: *const Frame) -> ShadowFrames<'a> {
+        ShadowFrames {
+            frame: frame,
+            marker: PhantomData
+        }
+    }
+}
+impl<'a> Iterator for ShadowFrames<'a> {
+    type Item = *const c_void;
+    fn next(&mut self) -> Option<*const c_void> {
+        if self.frame.is_null() {
+            None
+        } else {
+            unsafe {
+                let frame = &*self.frame;
+                self.frame = frame.parent;
+                Some(frame.descriptor)
+            }
+        }
+    }
+}
+
+/// An iterator through the addresses of the stack slots of the values
+/// pinned with `insn_pin` in the functions running on this thread, from the
+/// most recently pinned
+pub struct PinnedRoots<'a> {
+    root: *const Root,
+    marker: PhantomData<&'a ()>
+}
+impl<'a> PinnedRoots<'a> {
+    /// Iterate from `root` down
+    pub fn new(root: *const Root) -> PinnedRoots<'a> {
+        PinnedRoots {
+            root: root,
+            marker: PhantomData
+        }
+    }
+}
+impl<'a> Iterator for PinnedRoots<'a> {
+    type Item = *mut c_void;
+    fn next(&mut self) -> Option<*mut c_void> {
+        if self.root.is_null() {
+            None
+        } else {
+            unsafe {
+                let root = &*self.root;
+                self.root = root.parent;
+                Some(root.address)
+            }
+        }
+    }
+}
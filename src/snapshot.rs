@@ -0,0 +1,202 @@
+//! Inspecting the instructions a function has been built with, for testing
+//! frontends
+//!
+//! An `IrSnapshot` reads back the IR of a function that hasn't been compiled
+//! yet, with each instruction's opcode and the values it uses, so a test can
+//! check what a piece of source lowers to without compiling or calling
+//! anything. Values are numbered in the order they first appear, like in
+//! `dump_stable`, so snapshots of functions built the same way are equal.
+//!
+//! This is the IR as LibJIT keeps it, so instructions on constants it folded
+//! as they were made don't show up, and neither do constants themselves until
+//! an instruction uses them.
+//!
+//! ```rust
+//! use jit::*;
+//! use jit::snapshot::{IrSnapshot, ValueId};
+//! let mut ctx = Context::<()>::new();
+//! let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32, i32) -> i32>());
+//! func.insn_return(func.insn_add(&func[0], &func[1]));
+//! let snapshot = IrSnapshot::new(&func);
+//! assert_eq!(snapshot.opcodes(), ["iadd", "return_int"]);
+//! let add = &snapshot.insns[0];
+//! assert_eq!(add.args[0].id, ValueId::Param(0));
+//! assert_eq!(add.dest.as_ref().unwrap().kind, TypeKind::Int);
+//! ```
+use raw::*;
+use dump;
+use function::UncompiledFunction;
+use insn::Blocks;
+use types::TypeKind;
+use util::from_ptr_opt;
+use value::Val;
+use std::collections::HashMap;
+use std::fmt;
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+/// Which value an instruction uses
+pub enum ValueId {
+    /// The parameter with the index given
+    Param(usize),
+    /// The value that was the nth to appear that isn't a parameter or a
+    /// constant
+    Temp(usize),
+    /// A constant, written like it is in `dump_stable`
+    Constant(String)
+}
+impl fmt::Display for ValueId {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ValueId::Param(index) => write!(fmt, "p{}", index),
+            ValueId::Temp(index) => write!(fmt, "v{}", index),
+            ValueId::Constant(ref text) => write!(fmt, "{}", text)
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+/// A value used by an instruction, and the kind of its type
+pub struct IrValue {
+    /// Which value it is
+    pub id: ValueId,
+    /// The kind of its type
+    pub kind: TypeKind
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+/// An instruction read back from a function
+pub struct IrInsn {
+    /// The block it is in, counted from zero
+    pub block: usize,
+    /// The name of its opcode, like `iadd`
+    pub opcode: String,
+    /// The value it sets, if it sets one
+    pub dest: Option<IrValue>,
+    /// The values it uses, in order, including the destination of
+    /// instructions like stores that use it as an operand
+    pub args: Vec<IrValue>,
+    /// The label it branches to, numbered in the order labels appear
+    pub label: Option<usize>,
+    /// The signature it calls with, written like its `Debug` output
+    pub signature: Option<String>
+}
+impl fmt::Display for IrInsn {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(ref dest) = self.dest {
+            try!(write!(fmt, "{} = ", dest.id));
+        }
+        try!(write!(fmt, "{}", self.opcode));
+        let mut args: Vec<String> = self.args.iter().map(|arg| arg.id.to_string()).collect();
+        if let Some(label) = self.label {
+            args.push(format!("L{}", label));
+        }
+        if let Some(ref sig) = self.signature {
+            args.push(sig.clone());
+        }
+        for (index, arg) in args.iter().enumerate() {
+            try!(write!(fmt, "{}{}", if index == 0 { " " } else { ", " }, arg));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+/// The instructions a function has been built with so far
+pub struct IrSnapshot {
+    /// Each instruction, in the order of the blocks they are in
+    pub insns: Vec<IrInsn>,
+    /// The label each block starts with, if it has one, numbered like the
+    /// labels of branches
+    pub block_labels: Vec<Option<usize>>
+}
+impl IrSnapshot {
+    /// Read back the instructions of `func`
+    pub fn new(func: &UncompiledFunction) -> IrSnapshot {
+        let func: jit_function_t = func.into();
+        let mut ids: HashMap<jit_value_t, ValueId> = HashMap::new();
+        let mut labels: HashMap<jit_label_t, usize> = HashMap::new();
+        let params = unsafe { jit_type_num_params(jit_function_get_signature(func)) };
+        for index in 0..params {
+            let param: Option<&Val> = from_ptr_opt(unsafe { jit_value_get_param(func, index) });
+            if let Some(param) = param {
+                ids.insert(param.into(), ValueId::Param(index as usize));
+            }
+        }
+        let mut temps = 0;
+        let mut number = |value: &Val| {
+            let ptr: jit_value_t = value.into();
+            let id = ids.entry(ptr).or_insert_with(|| if value.is_constant() {
+                ValueId::Constant(dump::constant(value))
+            } else {
+                temps += 1;
+                ValueId::Temp(temps - 1)
+            }).clone();
+            IrValue {
+                id: id,
+                kind: value.get_type().get_kind()
+            }
+        };
+        let mut label_number = |label: u64| {
+            let next = labels.len();
+            *labels.entry(label as jit_label_t).or_insert(next)
+        };
+        let mut snapshot = IrSnapshot {
+            insns: Vec::new(),
+            block_labels: Vec::new()
+        };
+        for (index, block) in Blocks::new(func).enumerate() {
+            snapshot.block_labels.push(block.get_label().map(&mut label_number));
+            for insn in block.iter() {
+                let mut args = Vec::with_capacity(3);
+                let dest = match insn.get_dest() {
+                    Some(dest) if insn.dest_is_value() => {
+                        args.push(number(dest));
+                        None
+                    },
+                    Some(dest) => Some(number(dest)),
+                    None => None
+                };
+                args.extend(insn.get_value1().map(&mut number));
+                args.extend(insn.get_value2().map(&mut number));
+                snapshot.insns.push(IrInsn {
+                    block: index,
+                    opcode: insn.get_name().to_owned(),
+                    dest: dest,
+                    args: args,
+                    label: insn.get_label().map(&mut label_number),
+                    signature: insn.get_signature().map(|sig| format!("{:?}", sig))
+                });
+            }
+        }
+        snapshot
+    }
+    /// Get the name of each opcode, in order
+    pub fn opcodes(&self) -> Vec<&str> {
+        self.insns.iter().map(|insn| &*insn.opcode).collect()
+    }
+    /// Get the number of instructions
+    pub fn len(&self) -> usize {
+        self.insns.len()
+    }
+    /// Check if there are no instructions
+    pub fn is_empty(&self) -> bool {
+        self.insns.is_empty()
+    }
+}
+impl fmt::Display for IrSnapshot {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        let mut block = None;
+        for insn in &self.insns {
+            if block != Some(insn.block) {
+                block = Some(insn.block);
+                try!(write!(fmt, "block {}", insn.block));
+                if let Some(label) = self.block_labels[insn.block] {
+                    try!(write!(fmt, " (L{})", label));
+                }
+                try!(write!(fmt, ":\n"));
+            }
+            try!(write!(fmt, "    {}\n", insn));
+        }
+        Ok(())
+    }
+}
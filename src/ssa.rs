@@ -0,0 +1,117 @@
+use raw::*;
+use analysis::BlockGraph;
+use insn::Instruction;
+use util::from_ptr;
+use value::Val;
+use std::collections::{HashMap, HashSet};
+use std::slice;
+
+/// Find the blocks the instruction at the end of a block can branch to
+pub fn targets(func: jit_function_t, insn: Instruction) -> Vec<jit_block_t> {
+    let name = insn.get_name();
+    unsafe {
+        if name == "jump_table" {
+            let (labels, count) = match (insn.get_value1(), insn.get_value2()) {
+                (Some(labels), Some(count)) => (labels, count),
+                _ => return Vec::new()
+            };
+            let labels = jit_value_get_nint_constant(labels.into()) as *const jit_label_t;
+            let count = jit_value_get_nint_constant(count.into()) as usize;
+            slice::from_raw_parts(labels, count).iter()
+                .map(|&label| jit_block_from_label(func, label))
+                .collect()
+        } else if name.starts_with("br") {
+            insn.get_label().into_iter()
+                .map(|label| jit_block_from_label(func, label as jit_label_t))
+                .collect()
+        } else {
+            Vec::new()
+        }
+    }
+}
+
+/// Find the values used somewhere they might not have been set yet, in the
+/// order they are first found
+///
+/// A value is set at a point if an instruction sets it on every path from the
+/// start of the function to there, following the branches that were made.
+/// Only values set by instructions are checked, so parameters, constants and
+/// values whose address is taken are left alone. Blocks that no branch leads
+/// to, like catchers and finally blocks, are taken to have everything set.
+pub fn undefined_uses<'a>(func: jit_function_t) -> Vec<&'a Val> {
+    let graph: BlockGraph<'a> = BlockGraph::new(from_ptr(func));
+    let blocks = graph.get_blocks();
+    let mut values: HashMap<jit_value_t, usize> = HashMap::new();
+    let mut addressed = HashSet::new();
+    let mut defs = vec![Vec::new(); blocks.len()];
+    for (i, &block) in blocks.iter().enumerate() {
+        for insn in block.iter() {
+            if let (Some(dest), false) = (insn.get_dest(), insn.dest_is_value()) {
+                let next = values.len();
+                defs[i].push(*values.entry(dest.into()).or_insert(next));
+            }
+            if insn.get_name() == "address_of" {
+                if let Some(value) = insn.get_value1() {
+                    addressed.insert(value as *const Val);
+                }
+            }
+        }
+    }
+    // `None` stands for every value being set, for blocks nothing leads to yet
+    let mut outs: Vec<Option<Vec<bool>>> = vec![None; blocks.len()];
+    let mut ins: Vec<Option<Vec<bool>>> = vec![None; blocks.len()];
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for i in 0..blocks.len() {
+            let set_in = if i == 0 {
+                Some(vec![false; values.len()])
+            } else {
+                graph.get_predecessors(i).iter().fold(None, |set: Option<Vec<bool>>, &pred| match (set, outs[pred].as_ref()) {
+                    (Some(set), Some(out)) => Some(set.iter().zip(out).map(|(&a, &b)| a && b).collect()),
+                    (None, Some(out)) => Some(out.clone()),
+                    (set, None) => set
+                })
+            };
+            let set_out = set_in.clone().map(|mut set| {
+                for &def in &defs[i] {
+                    set[def] = true;
+                }
+                set
+            });
+            if set_out != outs[i] {
+                outs[i] = set_out;
+                changed = true;
+            }
+            ins[i] = set_in;
+        }
+    }
+    let mut found = Vec::new();
+    for (&block, set) in blocks.iter().zip(ins) {
+        let mut set = match set {
+            Some(set) => set,
+            None => continue
+        };
+        for insn in block.iter() {
+            let dest = if insn.dest_is_value() { insn.get_dest() } else { None };
+            let used = insn.get_value1().into_iter()
+                .chain(insn.get_value2().into_iter())
+                .chain(dest.into_iter());
+            for value in used {
+                let ptr: jit_value_t = value.into();
+                if let Some(&id) = values.get(&ptr) {
+                    let value: &'a Val = from_ptr(ptr);
+                    if !set[id] && !addressed.contains(&(value as *const Val)) &&
+                            !found.iter().any(|&other| other as *const Val == value as *const Val) {
+                        found.push(value);
+                    }
+                }
+            }
+            if let (Some(dest), false) = (insn.get_dest(), insn.dest_is_value()) {
+                let ptr: jit_value_t = dest.into();
+                set[values[&ptr]] = true;
+            }
+        }
+    }
+    found
+}
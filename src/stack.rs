@@ -0,0 +1,66 @@
+use libc::{c_int, c_void, size_t};
+use std::cell::Cell;
+
+#[cfg(target_os = "linux")]
+extern {
+    fn pthread_self() -> usize;
+    fn pthread_getattr_np(thread: usize, attr: *mut [u64; 16]) -> c_int;
+    fn pthread_attr_getstack(attr: *const [u64; 16], addr: *mut *mut c_void, size: *mut size_t) -> c_int;
+    fn pthread_attr_destroy(attr: *mut [u64; 16]) -> c_int;
+}
+#[cfg(target_os = "macos")]
+extern {
+    fn pthread_self() -> usize;
+    fn pthread_get_stackaddr_np(thread: usize) -> *mut c_void;
+    fn pthread_get_stacksize_np(thread: usize) -> size_t;
+}
+
+/// Find the lowest address of the current thread's stack
+#[cfg(target_os = "linux")]
+fn lowest() -> usize {
+    unsafe {
+        let mut attr = [0u64; 16];
+        let mut addr = 0 as *mut c_void;
+        let mut size = 0;
+        if pthread_getattr_np(pthread_self(), &mut attr) != 0 {
+            return 0
+        }
+        let found = pthread_attr_getstack(&attr, &mut addr, &mut size) == 0;
+        pthread_attr_destroy(&mut attr);
+        if found { addr as usize } else { 0 }
+    }
+}
+/// Find the lowest address of the current thread's stack
+#[cfg(target_os = "macos")]
+fn lowest() -> usize {
+    unsafe {
+        let thread = pthread_self();
+        pthread_get_stackaddr_np(thread) as usize - pthread_get_stacksize_np(thread) as usize
+    }
+}
+/// Find the lowest address of the current thread's stack, which isn't known
+/// on this platform, so nothing is ever considered to overflow
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+fn lowest() -> usize {
+    0
+}
+
+thread_local!(static LIMIT: Cell<Option<usize>> = Cell::new(None));
+
+/// Get how many bytes are left on the current thread's stack below the
+/// caller's frame
+pub extern fn left() -> usize {
+    let marker = 0u8;
+    let sp = &marker as *const u8 as usize;
+    LIMIT.with(|limit| {
+        let lowest = match limit.get() {
+            Some(lowest) => lowest,
+            None => {
+                let lowest = lowest();
+                limit.set(Some(lowest));
+                lowest
+            }
+        };
+        sp.saturating_sub(lowest)
+    })
+}
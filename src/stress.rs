@@ -0,0 +1,190 @@
+//! Stress tests for using LibJIT from several threads at once
+//!
+//! LibJIT locks a context while functions are built and compiled on it, and
+//! compiled code can be called from any thread. These run threads that build,
+//! compile, call and drop functions at the same time, both on contexts of
+//! their own and against code compiled on a context another thread is still
+//! building on, checking the result of every call. Panics in the threads are
+//! reported as failures, but a crash takes the whole process down, so they
+//! are best run from a test of their own.
+//!
+//! ```rust
+//! use jit::stress::{self, StressConfig};
+//! let report = stress::run(&StressConfig {
+//!     threads: 2,
+//!     iterations: 4,
+//!     shared_functions: 2
+//! });
+//! assert!(report.is_ok(), "{}", report);
+//! assert!(report.calls > 0);
+//! ```
+use raw::*;
+use context::Context;
+use function::UncompiledFunction;
+use types::get;
+use std::{fmt, mem, thread};
+
+/// How many times each compiled function is called with different arguments
+const CALLS: i32 = 64;
+
+#[derive(Clone, Copy, Debug)]
+/// How much work each stress test does
+pub struct StressConfig {
+    /// How many threads to run at once
+    pub threads: usize,
+    /// How many functions each thread builds, or how many rounds of calls it
+    /// makes to the shared functions
+    pub iterations: usize,
+    /// How many functions are compiled on the shared context before the
+    /// threads start calling them
+    pub shared_functions: usize
+}
+impl Default for StressConfig {
+    fn default() -> StressConfig {
+        StressConfig {
+            threads: 4,
+            iterations: 32,
+            shared_functions: 8
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+/// What a stress test did, and what went wrong
+pub struct StressReport {
+    /// How many functions were compiled
+    pub built: usize,
+    /// How many calls were made to compiled functions
+    pub calls: usize,
+    /// A description of each wrong result and each thread that panicked
+    pub failures: Vec<String>
+}
+impl StressReport {
+    /// Check if nothing went wrong
+    pub fn is_ok(&self) -> bool {
+        self.failures.is_empty()
+    }
+    /// Add what another test did to this report
+    fn merge(&mut self, other: StressReport) {
+        self.built += other.built;
+        self.calls += other.calls;
+        self.failures.extend(other.failures);
+    }
+    /// Call the function at `entry`, which works out `x * factor + 1`, with
+    /// each argument and check what it gives
+    fn check(&mut self, entry: usize, factor: i32, what: &str) {
+        let func: extern fn(i32) -> i32 = unsafe { mem::transmute(entry) };
+        for x in 0..CALLS {
+            let expected = x.wrapping_mul(factor).wrapping_add(1);
+            let got = func(x);
+            self.calls += 1;
+            if got != expected {
+                self.failures.push(format!("{} gave {} for {} instead of {}", what, got, x, expected));
+            }
+        }
+    }
+}
+impl fmt::Display for StressReport {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        try!(write!(fmt, "{} functions built, {} calls made, {} failures", self.built, self.calls, self.failures.len()));
+        for failure in &self.failures {
+            try!(write!(fmt, "\n{}", failure));
+        }
+        Ok(())
+    }
+}
+
+/// Build and compile a function on `ctx` that works out `x * factor + 1`,
+/// giving its entry point
+fn build(ctx: &mut Context<()>, factor: i32) -> usize {
+    let func = UncompiledFunction::new(ctx, &get::<fn(i32) -> i32>());
+    func.insn_return(func.insn_add(func.insn_mul(&func[0], func.insn_of(factor)), func.insn_of(1i32)));
+    let func = func.compile();
+    unsafe { jit_function_to_closure((&func).into()) as usize }
+}
+
+/// Build a function on `ctx` and drop it without compiling it
+fn abandon(ctx: &mut Context<()>) {
+    let func = UncompiledFunction::new(ctx, &get::<fn(i32) -> i32>());
+    func.insn_return(&func[0]);
+}
+
+/// Join each thread, adding its report to `report`
+fn join(report: &mut StressReport, threads: Vec<thread::JoinHandle<StressReport>>) {
+    for (index, thread) in threads.into_iter().enumerate() {
+        match thread.join() {
+            Ok(other) => report.merge(other),
+            Err(_) => report.failures.push(format!("Thread {} panicked", index))
+        }
+    }
+}
+
+/// Run threads that each make contexts of their own to build, compile, call
+/// and drop functions on
+///
+/// This does nothing if LibJIT wasn't built with thread support.
+pub fn per_thread(config: &StressConfig) -> StressReport {
+    let mut report = StressReport::default();
+    if !::supports_threads() {
+        return report
+    }
+    let iterations = config.iterations;
+    let threads = (0..config.threads).map(|index| thread::spawn(move || {
+        let mut report = StressReport::default();
+        for iteration in 0..iterations {
+            let factor = (index * iterations + iteration) as i32;
+            let mut ctx = Context::<()>::new();
+            abandon(&mut ctx);
+            let entry = build(&mut ctx, factor);
+            report.built += 1;
+            report.check(entry, factor, &format!("Thread {}'s function {}", index, iteration));
+        }
+        report
+    })).collect();
+    join(&mut report, threads);
+    report
+}
+
+/// Compile functions on a context, then run threads that call them while this
+/// thread carries on building, compiling, calling and dropping functions on
+/// the same context
+pub fn shared(config: &StressConfig) -> StressReport {
+    let mut report = StressReport::default();
+    let mut ctx = Context::<()>::new();
+    let entries: Vec<(usize, i32)> = (0..config.shared_functions).map(|factor| {
+        let factor = factor as i32 + 2;
+        (build(&mut ctx, factor), factor)
+    }).collect();
+    report.built += entries.len();
+    let iterations = config.iterations;
+    let threads = (0..config.threads).map(|index| {
+        let entries = entries.clone();
+        thread::spawn(move || {
+            let mut report = StressReport::default();
+            for _ in 0..iterations {
+                for &(entry, factor) in &entries {
+                    report.check(entry, factor, &format!("Shared function {} on thread {}", factor, index));
+                }
+            }
+            report
+        })
+    }).collect();
+    for iteration in 0..iterations {
+        let factor = -(iteration as i32);
+        abandon(&mut ctx);
+        let entry = build(&mut ctx, factor);
+        report.built += 1;
+        report.check(entry, factor, &format!("Function {} built alongside the calls", iteration));
+    }
+    // the context has to outlive the threads calling its code
+    join(&mut report, threads);
+    drop(ctx);
+    report
+}
+
+/// Run every stress test with the configuration given
+pub fn run(config: &StressConfig) -> StressReport {
+    let mut report = per_thread(config);
+    report.merge(shared(config));
+    report
+}
@@ -0,0 +1,236 @@
+use raw::{self, jit_context_t, jit_function_t, jit_label_t, jit_value_t};
+use data::ContextData;
+use mathlib;
+use std::error::Error;
+use std::fmt;
+use std::sync::atomic::{AtomicUsize, Ordering, ATOMIC_USIZE_INIT};
+
+/// The LibJIT functions for instructions that not every release has, and the
+/// math operation in `mathlib` each can be done with instead, if any
+const OPTIONAL_INSNS: &'static [(&'static str, Option<&'static str>)] = &[
+    ("jit_insn_address_of_label", None),
+    ("jit_insn_rint", Some("rint")),
+    ("jit_insn_round", Some("round")),
+    ("jit_insn_trunc", Some("trunc"))
+];
+/// The signature of `jit_insn_rint`, `jit_insn_round` and `jit_insn_trunc`
+pub type UnaryInsn = unsafe extern "C" fn(jit_function_t, jit_value_t) -> jit_value_t;
+/// The signature of `jit_insn_address_of_label`
+pub type LabelInsn = unsafe extern "C" fn(jit_function_t, *mut jit_label_t) -> jit_value_t;
+
+// These are linked weakly, so they are null when the linked LibJIT doesn't
+// have them instead of failing to link or load, and have to be called
+// through `unary_insn` and `address_of_label` instead of from `raw`
+extern {
+    #[linkage = "extern_weak"]
+    static jit_insn_address_of_label: Option<LabelInsn>;
+    #[linkage = "extern_weak"]
+    static jit_insn_rint: Option<UnaryInsn>;
+    #[linkage = "extern_weak"]
+    static jit_insn_round: Option<UnaryInsn>;
+    #[linkage = "extern_weak"]
+    static jit_insn_trunc: Option<UnaryInsn>;
+}
+/// Which of `OPTIONAL_INSNS` are missing as a bitmask, with the top bit set
+/// once LibJIT has been probed
+static MISSING_INSNS: AtomicUsize = ATOMIC_USIZE_INIT;
+const PROBED: usize = 1 << 31;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+/// A version of LibJIT
+pub struct Version {
+    /// The major version
+    pub major: u32,
+    /// The minor version
+    pub minor: u32,
+    /// The patch version
+    pub patch: u32
+}
+impl Version {
+    /// Make a version from its parts
+    pub fn new(major: u32, minor: u32, patch: u32) -> Version {
+        Version {
+            major: major,
+            minor: minor,
+            patch: patch
+        }
+    }
+    /// Parse a version like `0.1.3`, ignoring any suffix after the numbers
+    pub fn parse(text: &str) -> Option<Version> {
+        let mut parts = text.split('.').map(|part| {
+            let digits:String = part.chars().take_while(|c| c.is_digit(10)).collect();
+            digits.parse::<u32>().ok()
+        });
+        let major = match parts.next() {
+            Some(Some(major)) => major,
+            _ => return None
+        };
+        let minor = parts.next().and_then(|part| part).unwrap_or(0);
+        let patch = parts.next().and_then(|part| part).unwrap_or(0);
+        Some(Version::new(major, minor, patch))
+    }
+}
+impl fmt::Display for Version {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+/// Get the version of LibJIT that was linked, if it is known
+///
+/// It isn't known when LibJIT was found through `LIBJIT_LIB_DIR` without
+/// `LIBJIT_VERSION` being set too.
+pub fn version() -> Option<Version> {
+    Version::parse(raw::LIBJIT_VERSION)
+}
+/// Check if LibJIT was linked statically
+pub fn is_static() -> bool {
+    raw::LIBJIT_STATIC
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+/// An error from using something the linked LibJIT doesn't have
+pub struct Unsupported {
+    /// The name of the missing LibJIT function
+    pub function: &'static str
+}
+impl fmt::Display for Unsupported {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "{}: {}", self.description(), self.function)
+    }
+}
+impl Error for Unsupported {
+    fn description(&self) -> &'static str {
+        "The linked LibJIT doesn't have this function"
+    }
+}
+
+/// Check if the linked LibJIT has the function named
+///
+/// The functions `missing_insns` checks for are linked weakly, so they are
+/// always found if they are there. Others are looked up with `dlsym`, and
+/// are assumed to be there when LibJIT is linked statically, or on
+/// platforms without `dlsym`, since the wrapper wouldn't link without them.
+pub fn has_function(name: &str) -> bool {
+    if find_insn(name).is_some() {
+        return linked(name)
+    }
+    if raw::LIBJIT_STATIC {
+        return true
+    }
+    lookup(name)
+}
+#[cfg(unix)]
+fn lookup(name: &str) -> bool {
+    use libc::{dlclose, dlopen, dlsym, RTLD_LAZY};
+    use std::ffi::CString;
+    use std::ptr;
+    let c_name = CString::new(name.as_bytes()).unwrap();
+    unsafe {
+        let handle = dlopen(ptr::null(), RTLD_LAZY);
+        if handle.is_null() {
+            return false
+        }
+        let found = !dlsym(handle, c_name.as_ptr()).is_null();
+        dlclose(handle);
+        found
+    }
+}
+#[cfg(not(unix))]
+fn lookup(_name: &str) -> bool {
+    true
+}
+/// Check if the optional LibJIT function named was linked
+fn linked(function: &str) -> bool {
+    if function == "jit_insn_address_of_label" {
+        address_of_label().is_some()
+    } else {
+        unary_insn(function).is_some()
+    }
+}
+/// Get `jit_insn_address_of_label`, if the linked LibJIT has it
+pub fn address_of_label() -> Option<LabelInsn> {
+    unsafe { jit_insn_address_of_label }
+}
+/// Get the optional LibJIT function named that makes an instruction on one
+/// value, if the linked LibJIT has it
+pub fn unary_insn(function: &str) -> Option<UnaryInsn> {
+    unsafe {
+        match function {
+            "jit_insn_rint" => jit_insn_rint,
+            "jit_insn_round" => jit_insn_round,
+            "jit_insn_trunc" => jit_insn_trunc,
+            _ => None
+        }
+    }
+}
+/// Get the names of the math operations the linked LibJIT gives NaN for,
+/// because it was built without the C library functions they need, like
+/// `"sinh"` for `insn_sinh`
+///
+/// LibJIT is probed the first time this is called. Contexts make these
+/// operations call natives of their own with `Context::set_math_fallbacks`
+/// when they are made.
+///
+/// ```rust
+/// use jit::*;
+/// let ctx = Context::<()>::new();
+/// assert_eq!(ctx.get_math_fallbacks(), missing_math());
+/// ```
+pub fn missing_math() -> Vec<&'static str> {
+    mathlib::missing()
+}
+/// Get the LibJIT functions for instructions that not every release has,
+/// which the linked LibJIT is missing, like `"jit_insn_round"` for
+/// `insn_round`
+///
+/// LibJIT is probed the first time this is called. Contexts make the
+/// instructions that have a native equivalent call it instead, and poison
+/// functions that use the others, with `Context::set_unsupported` when they
+/// are made.
+///
+/// ```rust
+/// use jit::*;
+/// let ctx = Context::<()>::new();
+/// assert_eq!(ctx.get_unsupported(), missing_insns());
+/// ```
+pub fn missing_insns() -> Vec<&'static str> {
+    let mut missing = MISSING_INSNS.load(Ordering::Acquire);
+    if missing & PROBED == 0 {
+        missing = OPTIONAL_INSNS.iter().enumerate()
+            .filter(|&(_, &(name, _))| !linked(name))
+            .fold(PROBED, |missing, (index, _)| missing | 1 << index);
+        MISSING_INSNS.store(missing, Ordering::Release);
+    }
+    OPTIONAL_INSNS.iter().enumerate()
+        .filter(|&(index, _)| missing & 1 << index != 0)
+        .map(|(_, &(name, _))| name)
+        .collect()
+}
+/// Find the optional LibJIT function called `name`, and the math operation
+/// that can be done instead of it, if any
+pub fn find_insn(name: &str) -> Option<(&'static str, Option<&'static str>)> {
+    OPTIONAL_INSNS.iter().cloned().find(|&(function, _)| function == name)
+}
+/// Check if `ctx` treats the LibJIT function named as missing, giving an
+/// `Unsupported` error if it does
+pub fn check_insn(ctx: jit_context_t, function: &str) -> Result<(), Unsupported> {
+    match ContextData::with(ctx, |data| data.unsupported.iter().cloned().find(|&name| name == function)) {
+        Some(name) => Err(Unsupported { function: name }),
+        None => Ok(())
+    }
+}
+/// Check the linked LibJIT has the function named, giving an `Unsupported`
+/// error if it doesn't
+///
+/// ```rust
+/// use jit::*;
+/// assert!(require("jit_context_create").is_ok());
+/// ```
+pub fn require(name: &'static str) -> Result<(), Unsupported> {
+    if has_function(name) {
+        Ok(())
+    } else {
+        Err(Unsupported { function: name })
+    }
+}
@@ -0,0 +1,203 @@
+//! Building blocks for tracing JITs
+//!
+//! A `Trace` records a straight run of instructions, such as one the
+//! interpreter took through a hot loop, along with guards that check the
+//! assumptions made while recording still hold. When a guard fails, the trace
+//! leaves through a side exit, which calls the exit handler with the number
+//! of the exit and the interpreter's state so it can carry on interpreting
+//! from there. Once another trace has been recorded starting from a hot exit,
+//! the two can be stitched together so that exit jumps straight into the
+//! other trace instead.
+//!
+//! ```rust
+//! use jit::*;
+//! use jit::trace::Trace;
+//! use std::mem;
+//! extern fn exit(id: usize, state: *mut u8) -> usize {
+//!     let state: &mut i32 = unsafe { mem::transmute(state) };
+//!     id * 1000 + *state as usize
+//! }
+//! let mut ctx = Context::<()>::new();
+//! let mut trace = Trace::new(&mut ctx, exit);
+//! let int = get::<i32>();
+//! let counter = trace.insn_load_relative(trace.state(), 0, &int);
+//! let left = trace.insn_sub(counter, trace.insn_of(1i32));
+//! trace.insn_store_relative(trace.state(), 0, left);
+//! let more = trace.insn_gt(left, trace.insn_of(0i32));
+//! trace.guard(more);
+//! trace.close_loop();
+//! let trace = trace.compile();
+//! let mut counter = 5i32;
+//! assert_eq!(trace.run(&mut counter as *mut i32 as *mut u8), 0);
+//! assert_eq!(counter, 0);
+//! ```
+use raw::*;
+use context::Context;
+use data::FunctionData;
+use function::{flags, Abi, CompiledFunction, UncompiledFunction};
+use label::Label;
+use types::{consts, Type};
+use util::from_ptr;
+use value::Val;
+use std::mem;
+use std::ops::Deref;
+
+/// Called when a trace leaves through a side exit that isn't stitched to
+/// another trace, with the number of the exit and the state the trace was
+/// run with, to give the result of the trace
+pub type ExitHandler = extern fn(usize, *mut u8) -> usize;
+
+/// A trace that is being recorded
+///
+/// This dereferences to the function the trace is built in, so operations are
+/// recorded with the usual instructions.
+pub struct Trace<'a> {
+    func: UncompiledFunction<'a>,
+    handler: ExitHandler,
+    head: Label<'a>,
+    exits: Vec<Label<'a>>
+}
+impl<'a> Trace<'a> {
+    /// Start recording a trace on the context given, which will call
+    /// `handler` when it leaves through a side exit
+    pub fn new<T>(ctx: &'a mut Context<T>, handler: ExitHandler) -> Trace<'a> {
+        let sig = Type::new_signature(Abi::CDecl, consts::get_nuint(), &mut [consts::get_void_ptr()]);
+        Trace::start(UncompiledFunction::new(ctx, &sig), handler)
+    }
+    /// Start recording another trace on the same context, so the two can be
+    /// stitched together once they are compiled
+    pub fn sibling(&self, handler: ExitHandler) -> Trace<'a> {
+        Trace::start(self.func.sibling(self.func.get_signature()), handler)
+    }
+    fn start(func: UncompiledFunction<'a>, handler: ExitHandler) -> Trace<'a> {
+        let mut head = Label::new(&func);
+        func.insn_label(&mut head);
+        Trace {
+            func: func,
+            handler: handler,
+            head: head,
+            exits: Vec::new()
+        }
+    }
+    /// Get the state pointer the trace was run with
+    pub fn state(&self) -> &'a Val {
+        from_ptr(unsafe { jit_value_get_param((&self.func).into(), 0) })
+    }
+    /// Make a guard that leaves the trace through a new side exit unless
+    /// `cond` is true, and return the number of the exit
+    pub fn guard(&mut self, cond: &'a Val) -> usize {
+        let mut exit = Label::new(&self.func);
+        self.func.insn_branch_if_not(cond, &mut exit);
+        self.exits.push(exit);
+        self.exits.len() - 1
+    }
+    /// Leave the trace through a new side exit and return its number
+    pub fn exit(&mut self) -> usize {
+        let mut exit = Label::new(&self.func);
+        self.func.insn_branch(&mut exit);
+        self.exits.push(exit);
+        self.exits.len() - 1
+    }
+    /// Jump back to the start of the trace, for traces of loops
+    pub fn close_loop(&mut self) {
+        self.func.insn_branch(&mut self.head);
+    }
+    /// Get how many side exits the trace has so far
+    pub fn get_exits(&self) -> usize {
+        self.exits.len()
+    }
+    /// Compile the trace, leaving through a last side exit if the recording
+    /// doesn't end with `close_loop` or `exit`
+    pub fn compile(mut self) -> CompiledTrace<'a> {
+        if unsafe { jit_block_current_is_dead((&self.func).into()) } == 0 {
+            self.exit();
+        }
+        let state = self.state();
+        let Trace { func, handler, exits, .. } = self;
+        let count = exits.len();
+        let links = FunctionData::with((&func).into(), |data| {
+            data.links = (0..count).map(|_| Box::new(0)).collect();
+            data.links.iter().map(|link| &**link as *const usize as jit_nint).collect::<Vec<_>>()
+        });
+        {
+            let sig = func.get_signature();
+            let void_ptr = consts::get_void_ptr();
+            let handler_sig = Type::new_signature(Abi::CDecl, consts::get_nuint(), &mut [consts::get_nuint(), void_ptr]);
+            for (id, (mut exit, link)) in exits.into_iter().zip(links).enumerate() {
+                func.insn_label(&mut exit);
+                let link: &Val = from_ptr(unsafe {
+                    jit_value_create_nint_constant((&func).into(), void_ptr.into(), link)
+                });
+                let target = func.insn_load_relative(link, 0, void_ptr);
+                let mut unlinked = Label::new(&func);
+                func.insn_branch_if_not(target, &mut unlinked);
+                let result = func.insn_call_indirect(target, sig, &mut [state], flags::CallFlags::TAIL);
+                func.insn_return(result);
+                func.insn_label(&mut unlinked);
+                let id = func.insn_of(id);
+                let result = func.insn_call_native2(Some("trace_exit"), handler, &handler_sig, [id, state],
+                                                    flags::CallFlags::empty());
+                func.insn_return(result);
+            }
+        }
+        CompiledTrace {
+            func: func.compile(),
+            exits: count
+        }
+    }
+}
+impl<'a> Deref for Trace<'a> {
+    type Target = UncompiledFunction<'a>;
+    fn deref(&self) -> &UncompiledFunction<'a> {
+        &self.func
+    }
+}
+
+/// A trace that has been compiled
+#[derive(Clone, Copy)]
+pub struct CompiledTrace<'a> {
+    func: CompiledFunction<'a>,
+    exits: usize
+}
+impl<'a> CompiledTrace<'a> {
+    /// Get the function the trace was compiled to, which takes the state
+    /// pointer and returns a `usize`
+    pub fn get_function(&self) -> CompiledFunction<'a> {
+        self.func
+    }
+    /// Get how many side exits the trace has
+    pub fn get_exits(&self) -> usize {
+        self.exits
+    }
+    /// Run the trace with the state pointer given and get its result
+    pub fn run(&self, state: *mut u8) -> usize {
+        unsafe {
+            let func: extern fn(*mut u8) -> usize = mem::transmute(jit_function_to_closure((&self.func).into()));
+            func(state)
+        }
+    }
+    /// Make the side exit given jump to `target` instead of calling the exit
+    /// handler, so its result is the result of `target`
+    pub fn stitch(&self, exit: usize, target: &CompiledTrace<'a>) {
+        let closure = unsafe { jit_function_to_closure((&target.func).into()) } as usize;
+        self.set_link(exit, closure)
+    }
+    /// Make the side exit given call the exit handler again
+    pub fn unstitch(&self, exit: usize) {
+        self.set_link(exit, 0)
+    }
+    /// Check if the side exit given is stitched to another trace
+    pub fn is_stitched(&self, exit: usize) -> bool {
+        self.check_exit(exit);
+        FunctionData::with((&self.func).into(), |data| *data.links[exit] != 0)
+    }
+    fn set_link(&self, exit: usize, target: usize) {
+        self.check_exit(exit);
+        FunctionData::with((&self.func).into(), |data| *data.links[exit] = target)
+    }
+    fn check_exit(&self, exit: usize) {
+        if exit >= self.exits {
+            panic!("Trace has no side exit {}, it only has {}", exit, self.exits)
+        }
+    }
+}
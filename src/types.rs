@@ -1,12 +1,15 @@
 use raw::*;
 use compile::Compile;
+use fence;
 use function::Abi;
+use live::Counted;
 use alloc::oom;
 use libc::{c_char, c_uint, c_void};
 use util::{from_ptr, from_ptr_opt};
 use std::borrow::*;
 use std::marker::PhantomData;
 use std::{fmt, mem, str};
+use std::error::Error;
 use std::iter::IntoIterator;
 use std::ffi::{self, CString};
 use std::ops::{Deref, DerefMut};
@@ -284,6 +287,10 @@ impl<'a> Iterator for Params<'a> {
 /// Types are not attached to a context so they are reference-counted by LibJIT,
 /// so internally they are represented as `Rc<Ty>`. This represents a reference
 /// to the inner `Ty`.
+///
+/// Like `&Val`, a `&Ty` is just LibJIT's pointer to the type, so it is `Copy`
+/// and passing one around never allocates or touches the reference count.
+/// Only making an owned `Type` does.
 pub struct Ty(PhantomData<[()]>);
 native_ref!(&Ty = jit_type_t);
 impl ToOwned for Ty {
@@ -313,8 +320,9 @@ impl Borrow<Ty> for Type {
 #[derive(PartialEq, Eq)]
 pub struct Type {
     _type: jit_type_t,
+    _live: Counted
 }
-native_ref!(Type, _type: jit_type_t);
+native_ref!(Type, _type: jit_type_t, _live = Counted::new());
 impl Clone for Type {
     #[inline]
     /// Make a copy of the type descriptor by increasing its reference count.
@@ -395,6 +403,18 @@ impl Type {
             from_ptr(ptr)
         }
     }
+    /// Create a type descriptor for a pointer to `pointee`, like
+    /// `new_pointer`
+    ///
+    /// ```rust
+    /// use jit::*;
+    /// let int_ptr_ptr = Type::pointer_to(&Type::pointer_to(&get::<i32>()));
+    /// assert_eq!(int_ptr_ptr.pointer_depth(), 2);
+    /// assert_eq!(int_ptr_ptr.strip_pointers(), &get::<i32>() as &Ty);
+    /// ```
+    pub fn pointer_to(pointee: &Ty) -> Type {
+        Type::new_pointer(pointee)
+    }
 }
 impl Ty {
     #[inline(always)]
@@ -440,11 +460,18 @@ impl Ty {
     /// use jit::*;
     /// let f64_t = get::<f64>();
     /// let mut ty = Type::new_struct(&mut [&f64_t, &f64_t]);
-    /// ty.set_names(&["x", "y"]);
+    /// ty.set_names(&["x", "y"]).unwrap();
     /// assert_eq!(ty.get_field("x").unwrap().get_type(), &f64_t as &Ty);
     /// assert_eq!(ty.get_field("y").unwrap().get_type(), &f64_t as &Ty);
     /// ```
-    pub fn set_names(&mut self, names: &[&str]) {
+    ///
+    /// Once a function using the type has been compiled on this thread, this
+    /// gives `TypeFenced` instead of changing it, because the compiled code
+    /// depends on it. Make a new type instead.
+    pub fn set_names(&mut self, names: &[&str]) -> Result<(), TypeFenced> {
+        if fence::is_fenced(self) {
+            return Err(TypeFenced)
+        }
         unsafe {
             let names = names.iter()
                              .map(|name| CString::new(name.as_bytes()).unwrap())
@@ -456,6 +483,12 @@ impl Ty {
                 oom();
             }
         }
+        Ok(())
+    }
+    /// Check if a function using this type has been compiled on this thread,
+    /// so it can't be changed any more
+    pub fn is_fenced(&self) -> bool {
+        fence::is_fenced(self)
     }
     #[inline(always)]
     /// Iterate over the type's fields
@@ -580,6 +613,40 @@ impl Ty {
             jit_type_is_pointer(self.into()) != 0
         }
     }
+    /// Get how many pointers deep this type is, which is 0 if it isn't a
+    /// pointer
+    ///
+    /// ```rust
+    /// use jit::*;
+    /// assert_eq!(get::<i32>().pointer_depth(), 0);
+    /// assert_eq!(Type::pointer_to(&get::<i32>()).pointer_depth(), 1);
+    /// ```
+    pub fn pointer_depth(&self) -> usize {
+        let mut depth = 0;
+        let mut ty = self;
+        while let Some(pointee) = ty.pointee() {
+            depth += 1;
+            ty = pointee;
+        }
+        depth
+    }
+    /// Get the type this points to through every level of pointers, or this
+    /// type if it isn't a pointer
+    pub fn strip_pointers(&self) -> &Ty {
+        let mut ty = self;
+        while let Some(pointee) = ty.pointee() {
+            ty = pointee;
+        }
+        ty
+    }
+    /// Get the type this points to if it is a pointer with a known pointee
+    fn pointee(&self) -> Option<&Ty> {
+        if self.is_pointer() {
+            self.get_ref()
+        } else {
+            None
+        }
+    }
     #[inline(always)]
     /// Check if this is tagged
     pub fn is_tagged(&self) -> bool {
@@ -587,6 +654,126 @@ impl Ty {
             jit_type_is_tagged(self.into()) != 0
         }
     }
+    /// Check if code calling through this signature can call a function that
+    /// has the signature `other`, which means they have the same ABI, the
+    /// same number of parameters and the same layout for the return type and
+    /// each parameter
+    ///
+    /// Names and tags are ignored, and pointers are all the same.
+    ///
+    /// ```rust
+    /// use jit::*;
+    /// let sig = get::<fn(i32, &'static u8) -> f64>();
+    /// assert!(sig.signature_compatible(&get::<fn(i32, &'static u16) -> f64>()));
+    /// assert!(!sig.signature_compatible(&get::<fn(i32) -> f64>()));
+    /// assert!(!sig.signature_compatible(&get::<fn(i64, &'static u8) -> f64>()));
+    /// ```
+    pub fn signature_compatible(&self, other: &Ty) -> bool {
+        self.check_signature(other).is_ok()
+    }
+    /// Check if code calling through this signature can call a function that
+    /// has the signature `other`, like `signature_compatible`, getting what
+    /// differs if it can't
+    pub fn check_signature(&self, other: &Ty) -> Result<(), SignatureMismatch> {
+        if !self.is_signature() || !other.is_signature() {
+            return Err(SignatureMismatch::NotSignature)
+        }
+        unsafe {
+            if jit_type_get_abi(self.into()) != jit_type_get_abi(other.into()) {
+                return Err(SignatureMismatch::Abi)
+            }
+        }
+        let (expected, got) = (self.params().count(), other.params().count());
+        if expected != got {
+            return Err(SignatureMismatch::Arity(expected, got))
+        }
+        let (expected, got) = (self.get_return().unwrap(), other.get_return().unwrap());
+        if !same_layout(expected, got) {
+            return Err(SignatureMismatch::Return(format!("{:?}", expected), format!("{:?}", got)))
+        }
+        for (index, (expected, got)) in self.params().zip(other.params()).enumerate() {
+            if !same_layout(expected, got) {
+                return Err(SignatureMismatch::Param(index, format!("{:?}", expected), format!("{:?}", got)))
+            }
+        }
+        Ok(())
+    }
+}
+/// Check if two types are laid out and passed the same way
+pub fn same_layout(a: &Ty, b: &Ty) -> bool {
+    let (a, b): (&Ty, &Ty) = unsafe {
+        (from_ptr(jit_type_normalize(a.into())), from_ptr(jit_type_normalize(b.into())))
+    };
+    if a.get_kind() != b.get_kind() {
+        false
+    } else if a.is_signature() {
+        a.signature_compatible(b)
+    } else if a.is_struct() || a.is_union() {
+        a.get_size() == b.get_size() && a.get_alignment() == b.get_alignment() &&
+            a.fields().count() == b.fields().count() &&
+            a.fields().zip(b.fields()).all(|(fa, fb)|
+                fa.get_offset() == fb.get_offset() && same_layout(fa.get_type(), fb.get_type())
+            )
+    } else {
+        true
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+/// A difference between two signatures that means code calling through one
+/// can't call a function with the other
+pub enum SignatureMismatch {
+    /// One of the types isn't a signature
+    NotSignature,
+    /// The signatures use different ABIs
+    Abi,
+    /// The signatures take different numbers of parameters, as the number
+    /// expected and the number given
+    Arity(usize, usize),
+    /// The parameter at the index has the first type in the expected
+    /// signature and the second in the one given
+    Param(usize, String, String),
+    /// The return type is the first in the expected signature and the second
+    /// in the one given
+    Return(String, String)
+}
+impl fmt::Display for SignatureMismatch {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            SignatureMismatch::Arity(expected, got) =>
+                write!(fmt, "{}: expected {}, got {}", self.description(), expected, got),
+            SignatureMismatch::Param(index, ref expected, ref got) =>
+                write!(fmt, "{} {}: expected {}, got {}", self.description(), index, expected, got),
+            SignatureMismatch::Return(ref expected, ref got) =>
+                write!(fmt, "{}: expected {}, got {}", self.description(), expected, got),
+            _ => fmt.write_str(self.description())
+        }
+    }
+}
+impl Error for SignatureMismatch {
+    fn description(&self) -> &'static str {
+        match *self {
+            SignatureMismatch::NotSignature => "The type isn't a signature",
+            SignatureMismatch::Abi => "The signatures use different ABIs",
+            SignatureMismatch::Arity(_, _) => "The signatures take different numbers of parameters",
+            SignatureMismatch::Param(_, _, _) => "The signatures differ in parameter",
+            SignatureMismatch::Return(_, _) => "The signatures return different types"
+        }
+    }
+}
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+/// A type couldn't be changed because functions compiled with it depend on
+/// it, so a new type should be made instead
+pub struct TypeFenced;
+impl fmt::Display for TypeFenced {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.write_str(self.description())
+    }
+}
+impl Error for TypeFenced {
+    fn description(&self) -> &'static str {
+        "The type is used by a compiled function, so make a new type instead of changing it"
+    }
 }
 impl<'a> IntoIterator for &'a Ty {
     type IntoIter = Fields<'a>;
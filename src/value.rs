@@ -1,5 +1,6 @@
 use raw::*;
-use function::UncompiledFunction;
+use data::FunctionData;
+use function::{self, UncompiledFunction};
 use types::*;
 use util::from_ptr;
 use std::marker::PhantomData;
@@ -11,6 +12,11 @@ use std::ops::*;
 /// temporary result, is represented by an object of type `Val`. The JIT then
 /// allocates registers or memory locations to the values as appropriate. This is
 /// why `Val` is always behind a reference
+///
+/// A `&Val` is just LibJIT's pointer to the value, with the lifetime of the
+/// function it is in, so it is `Copy` and making one never allocates. It is
+/// already what a newtype over `jit_value_t` would be, so the wrapper doesn't
+/// have one.
 pub struct Val(PhantomData<[()]>);
 native_ref!(&Val = jit_value_t);
 impl fmt::Debug for Val {
@@ -26,6 +32,8 @@ impl Val {
     /// converted into a function-wide local variable if it is ever referenced
     /// from a different block.
     pub fn new<'a>(func:&UncompiledFunction<'a>, value_type:&Ty) -> &'a Val {
+        FunctionData::with(func.into(), |data| data.values += 1);
+        function::spend(func.into(), 0, 1, 0);
         unsafe {
             from_ptr(jit_value_create(func.into(), value_type.into()))
         }
@@ -50,6 +58,28 @@ impl Val {
             jit_value_is_temporary(self.into()) != 0
         }
     }
+    /// Determine if a value is a constant.
+    #[inline]
+    pub fn is_constant(&self) -> bool {
+        unsafe {
+            jit_value_is_constant(self.into()) != 0
+        }
+    }
+    /// Determine if a value is a parameter of its function.
+    #[inline]
+    pub fn is_parameter(&self) -> bool {
+        unsafe {
+            jit_value_is_parameter(self.into()) != 0
+        }
+    }
+    /// Determine if a value is a local variable.  i.e. its scope extends over
+    /// multiple blocks within its function.
+    #[inline]
+    pub fn is_local(&self) -> bool {
+        unsafe {
+            jit_value_is_local(self.into()) != 0
+        }
+    }
     /// Determine if a value is addressable.
     #[inline]
     pub fn is_addressable(&self) -> bool {
@@ -67,6 +97,22 @@ impl Val {
             jit_value_set_addressable(self.into())
         }
     }
+    /// Determine if a value is volatile.
+    #[inline]
+    pub fn is_volatile(&self) -> bool {
+        unsafe {
+            jit_value_is_volatile(self.into()) != 0
+        }
+    }
+    /// Set a flag on a value to indicate that it is volatile.
+    /// The value will always be loaded from and stored to its memory location
+    /// instead of being cached in a register.
+    #[inline]
+    pub fn set_volatile(&self) -> () {
+        unsafe {
+            jit_value_set_volatile(self.into())
+        }
+    }
 }
 macro_rules! bin_op {
     ($trait_ty:ident, $trait_func:ident, $func:ident) => (
@@ -88,15 +134,15 @@ macro_rules! un_op {
         }
     )
 }
-bin_op!{Add, add, insn_add}
+bin_op!{Add, add, insn_arith_add}
 bin_op!{BitAnd, bitand, insn_and}
 bin_op!{BitOr, bitor, insn_or}
 bin_op!{BitXor, bitxor, insn_xor}
 bin_op!{Div, div, insn_div}
-bin_op!{Mul, mul, insn_mul}
+bin_op!{Mul, mul, insn_arith_mul}
 bin_op!{Rem, rem, insn_rem}
 bin_op!{Shl, shl, insn_shl}
 bin_op!{Shr, shr, insn_shr}
-bin_op!{Sub, sub, insn_sub}
+bin_op!{Sub, sub, insn_arith_sub}
 un_op!{Neg, neg, insn_neg}
 un_op!{Not, not, insn_not}
@@ -0,0 +1,116 @@
+//! Natives for the 64-bit operations 32-bit backends can't do inline
+//!
+//! On a 32-bit backend, a 64-bit integer lives in a pair of registers, and
+//! division, remainders, shifts and conversions to and from floats on them
+//! are calls either way. These are registered on contexts that need them
+//! under their names, so precompiled code that uses them says so in its
+//! extern snapshot, and the instructions for those operations call them.
+use raw::*;
+use exception::BuiltinException;
+use types::{get, Type};
+use libc::c_int;
+
+/// Check if the backend needs 64-bit operations done by natives, because it
+/// generates 32-bit code
+pub fn needed() -> bool {
+    cfg!(target_pointer_width = "32") && !::uses_interpreter()
+}
+
+/// Raise a builtin exception, like LibJIT's own helpers do
+fn raise(exception: BuiltinException) {
+    unsafe {
+        jit_exception_builtin(exception as c_int);
+    }
+}
+
+extern fn long_div(a: i64, b: i64) -> i64 {
+    if b == 0 {
+        raise(BuiltinException::DivisionByZero);
+        0
+    } else if b == -1 && a == i64::min_value() {
+        raise(BuiltinException::Arithmetic);
+        0
+    } else {
+        a / b
+    }
+}
+extern fn long_rem(a: i64, b: i64) -> i64 {
+    if b == 0 {
+        raise(BuiltinException::DivisionByZero);
+        0
+    } else if b == -1 {
+        0
+    } else {
+        a % b
+    }
+}
+extern fn ulong_div(a: u64, b: u64) -> u64 {
+    if b == 0 {
+        raise(BuiltinException::DivisionByZero);
+        0
+    } else {
+        a / b
+    }
+}
+extern fn ulong_rem(a: u64, b: u64) -> u64 {
+    if b == 0 {
+        raise(BuiltinException::DivisionByZero);
+        0
+    } else {
+        a % b
+    }
+}
+// shifts only use the low 6 bits of the amount, like LibJIT's
+extern fn long_shl(a: i64, amount: u32) -> i64 {
+    a << (amount & 63)
+}
+extern fn long_shr(a: i64, amount: u32) -> i64 {
+    a >> (amount & 63)
+}
+extern fn ulong_shr(a: u64, amount: u32) -> u64 {
+    a >> (amount & 63)
+}
+extern fn long_to_f64(a: i64) -> f64 {
+    a as f64
+}
+extern fn ulong_to_f64(a: u64) -> f64 {
+    a as f64
+}
+// out of range floats saturate, and NaN becomes 0
+pub extern fn f64_to_long(a: f64) -> i64 {
+    if a != a {
+        0
+    } else if a >= 9223372036854775807.0 {
+        i64::max_value()
+    } else if a <= -9223372036854775808.0 {
+        i64::min_value()
+    } else {
+        a as i64
+    }
+}
+pub extern fn f64_to_ulong(a: f64) -> u64 {
+    if a != a || a <= 0.0 {
+        0
+    } else if a >= 18446744073709551615.0 {
+        u64::max_value()
+    } else {
+        a as u64
+    }
+}
+
+/// Get the name, address and signature of each native
+pub fn helpers() -> Vec<(&'static str, usize, Type)> {
+    vec![
+        ("jit_long_div", long_div as usize, get::<fn(i64, i64) -> i64>()),
+        ("jit_long_rem", long_rem as usize, get::<fn(i64, i64) -> i64>()),
+        ("jit_ulong_div", ulong_div as usize, get::<fn(u64, u64) -> u64>()),
+        ("jit_ulong_rem", ulong_rem as usize, get::<fn(u64, u64) -> u64>()),
+        ("jit_long_shl", long_shl as usize, get::<fn(i64, u32) -> i64>()),
+        ("jit_long_shr", long_shr as usize, get::<fn(i64, u32) -> i64>()),
+        ("jit_ulong_shr", ulong_shr as usize, get::<fn(u64, u32) -> u64>()),
+        ("jit_long_to_f64", long_to_f64 as usize, get::<fn(i64) -> f64>()),
+        ("jit_ulong_to_f64", ulong_to_f64 as usize, get::<fn(u64) -> f64>()),
+        ("jit_f64_to_long", f64_to_long as usize, get::<fn(f64) -> i64>()),
+        ("jit_f64_to_ulong", f64_to_ulong as usize, get::<fn(f64) -> u64>())
+    ]
+}
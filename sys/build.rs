@@ -1,4 +1,6 @@
-use std::io;
+extern crate pkg_config;
+
+use std::io::{self, Read, Write};
 use std::fs;
 use std::env;
 use std::path::Path;
@@ -14,6 +16,8 @@ static MINGW:&'static str = "c:/mingw";
 
 static INSTALL_AUTOTOOLS_MSG:&'static str = "Failed to generate configuration script. Did you forget to install autotools, bison, flex, and libtool?";
 
+static NOT_FOUND_MSG:&'static str = "Failed to find LibJIT. Install it so pkg-config can find it, set LIBJIT_LIB_DIR to the directory containing it, or enable the `bundled` feature to build it from source";
+
 static USE_CARGO_MSG:&'static str = "Build script should be ran with Cargo, run `cargo build` instead";
 
 #[cfg(windows)]
@@ -31,6 +35,57 @@ fn exists<P: AsRef<Path>>(path: P) -> io::Result<bool> {
 }
 
 fn main() {
+	let link_static = env::var("CARGO_FEATURE_STATIC").is_ok() || env::var("LIBJIT_STATIC").is_ok();
+	if let Some(lib_dir) = env::var("LIBJIT_LIB_DIR").ok() {
+		let kind = if link_static { "static" } else { "dylib" };
+		println!("cargo:rustc-link-search=native={}", lib_dir);
+		println!("cargo:rustc-link-lib={}=jit", kind);
+		let version = env::var("LIBJIT_VERSION").unwrap_or(String::new());
+		write_info(&version, link_static);
+		return
+	}
+	if env::var("LIBJIT_NO_PKG_CONFIG").is_err() &&
+		pkg_config::Config::new().statik(link_static).find("jit").is_ok() {
+		write_info(&pkg_config_version(), link_static);
+		return
+	}
+	if env::var("CARGO_FEATURE_BUNDLED").is_ok() {
+		build_bundled();
+		write_info(&bundled_version(), true)
+	} else {
+		panic!("{}", NOT_FOUND_MSG)
+	}
+}
+/// Write what was linked to a file `lib.rs` includes
+fn write_info(version: &str, link_static: bool) {
+	let out_dir = env::var("OUT_DIR").ok().expect(USE_CARGO_MSG);
+	let path = Path::new(&*out_dir).join("info.rs");
+	let mut file = fs::File::create(&path).unwrap();
+	write!(file, "/// The version of LibJIT that was linked, or an empty string if it isn't known\n\
+		pub const LIBJIT_VERSION: &'static str = {:?};\n\
+		/// If LibJIT was linked statically\n\
+		pub const LIBJIT_STATIC: bool = {};\n", version, link_static).unwrap();
+}
+fn pkg_config_version() -> String {
+	Command::new("pkg-config").args(&["--modversion", "jit"]).output().ok()
+		.and_then(|output| String::from_utf8(output.stdout).ok())
+		.map(|version| version.trim().to_string())
+		.unwrap_or(String::new())
+}
+fn bundled_version() -> String {
+	let manifest_dir = env::var("CARGO_MANIFEST_DIR").ok().expect(USE_CARGO_MSG);
+	let mut text = String::new();
+	let path = Path::new(&manifest_dir).join("libjit/configure.ac");
+	if fs::File::open(&path).and_then(|mut file| file.read_to_string(&mut text)).is_err() {
+		return String::new()
+	}
+	// The version is the second argument of AC_INIT, like `AC_INIT([libjit], [0.1.3], ...)`
+	text.lines().find(|line| line.starts_with("AC_INIT"))
+		.and_then(|line| line.split(',').nth(1))
+		.map(|version| version.trim().trim_matches(|c| c == '[' || c == ']').to_string())
+		.unwrap_or(String::new())
+}
+fn build_bundled() {
 	if cfg!(windows) && !exists(&Path::new(MINGW)).unwrap() {
 		panic!("{}", INSTALL_COMPILER_MSG);
 	}
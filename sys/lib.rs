@@ -2,6 +2,8 @@
 
 extern crate libc;
 use libc::*;
+
+include!(concat!(env!("OUT_DIR"), "/info.rs"));
 /* automatically generated by rust-bindgen */
 
 pub type jit_sbyte = c_char;
@@ -258,6 +260,12 @@ pub static JIT_PROT_READ: c_uint = 1;
 pub static JIT_PROT_READ_WRITE: c_uint = 2;
 pub static JIT_PROT_EXEC_READ: c_uint = 3;
 pub static JIT_PROT_EXEC_READ_WRITE: c_uint = 4;
+pub static JIT_OPTION_CACHE_LIMIT: c_int = 10000;
+pub static JIT_OPTION_CACHE_PAGE_SIZE: c_int = 10001;
+pub static JIT_OPTION_PRE_COMPILE: c_int = 10002;
+pub static JIT_OPTION_DONT_FOLD: c_int = 10003;
+pub static JIT_OPTION_POSITION_INDEPENDENT: c_int = 10004;
+pub static JIT_OPTION_CACHE_MAX_PAGE_FACTOR: c_int = 10005;
 #[repr(C)]
 pub struct jit_crawl_mark_t {
     pub mark: *mut c_void,
@@ -0,0 +1,25 @@
+extern crate libjit_sys;
+
+use libjit_sys::*;
+
+#[test]
+fn test_linked_as_asked() {
+    if cfg!(feature = "static") {
+        assert!(LIBJIT_STATIC);
+    }
+    unsafe {
+        let ctx = jit_context_create();
+        assert!(!ctx.is_null());
+        jit_context_destroy(ctx);
+    }
+}
+
+#[test]
+fn test_version_is_numbered() {
+    // it is empty when LibJIT was found through LIBJIT_LIB_DIR without a
+    // LIBJIT_VERSION
+    if !LIBJIT_VERSION.is_empty() {
+        let major = LIBJIT_VERSION.split('.').next().unwrap();
+        assert!(major.parse::<u32>().is_ok(), "{:?} isn't a version", LIBJIT_VERSION);
+    }
+}
@@ -0,0 +1,77 @@
+extern crate jit;
+use jit::*;
+
+#[repr(C)]
+struct Inner {
+    tag: u8,
+    amount: i64
+}
+#[repr(C)]
+struct Outer {
+    id: i32,
+    inner: Inner
+}
+
+fn outer_type() -> Type {
+    let mut inner = Type::new_struct(&mut [&get::<u8>(), &get::<i64>()]);
+    inner.set_names(&["tag", "amount"]).unwrap();
+    let mut outer = Type::new_struct(&mut [&get::<i32>(), &inner]);
+    outer.set_names(&["id", "inner"]).unwrap();
+    outer
+}
+
+#[test]
+fn test_field_offsets() {
+    let outer = outer_type();
+    let access = StructAccess::new(&outer);
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(&'static u8)>());
+    let amount = access.field(&func[0], "inner").field("amount");
+    assert_eq!(amount.get_offset(), 16);
+    assert_eq!(amount.get_type(), &*get::<i64>());
+}
+
+#[test]
+fn test_load_store_nested() {
+    let outer = outer_type();
+    let access = StructAccess::new(&outer);
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(&'static u8) -> i32>());
+    {
+        let inner = access.field(&func[0], "inner");
+        let amount = inner.field("amount");
+        amount.store(func.insn_add(amount.load(), func.insn_of(5i64)));
+        inner.field("tag").store(func.insn_of(7u8));
+        func.insn_return(access.field(&func[0], "id").load());
+    }
+    let mut value = Outer { id: 3, inner: Inner { tag: 0, amount: 10 } };
+    func.compile().with(|run: extern fn(*mut Outer) -> i32| {
+        assert_eq!(run(&mut value), 3);
+    });
+    assert_eq!(value.inner.amount, 15);
+    assert_eq!(value.inner.tag, 7);
+}
+
+#[test]
+#[should_panic]
+fn test_missing_field() {
+    let outer = outer_type();
+    let access = StructAccess::new(&outer);
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(&'static u8)>());
+    access.field(&func[0], "missing");
+}
+
+#[test]
+fn test_store_converts() {
+    let mut pair = Type::new_struct(&mut [&get::<u8>(), &get::<u8>(), &get::<i16>()]);
+    pair.set_names(&["low", "high", "rest"]).unwrap();
+    let access = StructAccess::new(&pair);
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(&'static u8)>());
+    access.field(&func[0], "low").store(func.insn_of(0x1234i32));
+    func.insn_default_return();
+    let mut bytes = [0u8, 0x56, 0x78, 0x9a];
+    func.compile().with(|run: extern fn(*mut u8)| run(bytes.as_mut_ptr()));
+    assert_eq!(bytes, [0x34, 0x56, 0x78, 0x9a]);
+}
@@ -0,0 +1,67 @@
+extern crate jit;
+use jit::*;
+
+#[test]
+fn test_returned_alloca_warns() {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn() -> &'static u8>());
+    let buffer = func.insn_alloca(func.insn_of(16u32));
+    func.insn_return(func.insn_add(buffer, func.insn_of(4usize)));
+    let warnings = func.get_warnings();
+    assert_eq!(warnings.len(), 1);
+    assert!(warnings[0].message.contains("insn_return"));
+    assert_eq!(warnings[0].location, None);
+}
+
+#[test]
+fn test_stored_alloca_warns() {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(&'static usize)>());
+    func.set_current_location(2, 7, 3);
+    let buffer = func.insn_alloca(func.insn_of(8u32));
+    let copy_var = func.new_lvalue(&buffer.get_type());
+    let copy = copy_var.get_local().unwrap();
+    func.insn_store(&copy_var, buffer);
+    func.insn_store_relative(&func[0], 0, copy);
+    func.insn_default_return();
+    let warnings = func.get_warnings();
+    assert_eq!(warnings.len(), 1);
+    assert!(warnings[0].message.contains("insn_store_relative"));
+    assert_eq!(warnings[0].location, Some(SourceLocation { file: 2, line: 7, column: 3 }));
+}
+
+#[test]
+fn test_local_use_is_quiet() {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(usize) -> usize>());
+    let buffer = func.insn_alloca(func.insn_of(16u32));
+    func.insn_store_relative(buffer, 0, &func[0]);
+    func.insn_store_relative(buffer, 8, buffer);
+    func.insn_return(func.insn_load_relative(buffer, 0, &get::<usize>()));
+    assert!(func.get_warnings().is_empty());
+    func.compile().with(|f: extern fn(usize) -> usize| assert_eq!(f(5), 5));
+}
+
+#[test]
+fn test_operators_track_alloca() {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn() -> &'static u8>());
+    let buffer = func.insn_alloca(func.insn_of(16u32));
+    let end = buffer + func.insn_of(8usize);
+    func.insn_return(end - func.insn_of(4usize));
+    let warnings = func.get_warnings();
+    assert_eq!(warnings.len(), 1);
+    assert!(warnings[0].message.contains("insn_return"));
+}
+
+#[test]
+fn test_overflow_modes_track_alloca() {
+    for &mode in &[Overflow::Trap, Overflow::Saturate] {
+        let mut ctx = Context::<()>::new();
+        let func = UncompiledFunction::new(&mut ctx, &get::<fn() -> &'static u8>());
+        func.set_overflow(mode);
+        let buffer = func.insn_alloca(func.insn_of(16u32));
+        func.insn_return(buffer + func.insn_of(4usize));
+        assert_eq!(func.get_warnings().len(), 1);
+    }
+}
@@ -0,0 +1,87 @@
+extern crate jit;
+use jit::*;
+use jit::analysis::BlockGraph;
+
+#[test]
+fn test_nested_loops() {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+    let total_var = func.new_lvalue(&get::<i32>());
+    let total = total_var.get_local().unwrap();
+    func.insn_store(&total_var, func.insn_of(0i32));
+    func.insn_for_range(func.insn_of(0i32), &func[0], |_| {
+        func.insn_for_range(func.insn_of(0i32), &func[0], |index| {
+            func.insn_store(&total_var, func.insn_add(total, index));
+        });
+    });
+    func.insn_return(total);
+    let graph = BlockGraph::new(&func);
+    assert!(graph.is_reducible());
+    let loops = graph.loops();
+    assert_eq!(loops.get_loops().len(), 2);
+    let (outer, inner) = (&loops.get_loops()[0], &loops.get_loops()[1]);
+    assert_eq!(outer.parent, None);
+    assert_eq!(inner.parent, Some(0));
+    assert!(outer.contains(inner.header));
+    assert!(inner.blocks.iter().all(|&block| outer.contains(block)));
+    assert_eq!(loops.get_depth(0), 0);
+    assert_eq!(loops.get_depth(outer.header), 1);
+    assert_eq!(loops.get_depth(inner.header), 2);
+    assert_eq!(loops.get_innermost(inner.header), Some(1));
+    let doms = graph.dominators();
+    assert!(doms.dominates(outer.header, inner.header));
+    assert!(!doms.dominates(inner.header, outer.header));
+    func.compile().with(|f: extern fn(i32) -> i32| assert_eq!(f(3), 9));
+}
+
+#[test]
+fn test_diamond() {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+    let result_var = func.new_lvalue(&get::<i32>());
+    let result = result_var.get_local().unwrap();
+    func.insn_if_else(&func[0], || {
+        func.insn_store(&result_var, func.insn_of(1i32));
+    }, || {
+        func.insn_store(&result_var, func.insn_of(2i32));
+    });
+    func.insn_return(result);
+    let graph = BlockGraph::new(&func);
+    let doms = graph.dominators();
+    let last = graph.len() - 1;
+    assert!(graph.loops().get_loops().is_empty());
+    assert!(graph.is_reducible());
+    assert_eq!(doms.get_immediate(0), None);
+    for block in 1..graph.len() {
+        if doms.is_reachable(block) {
+            assert!(doms.dominates(0, block));
+        }
+    }
+    // neither arm dominates the join, which both reach
+    let arms: Vec<usize> = (1..last).filter(|&block| graph.get_successors(block).contains(&last)).collect();
+    assert!(arms.len() >= 2);
+    assert!(arms.iter().all(|&arm| !doms.dominates(arm, last) || arm == 0));
+    for block in 0..graph.len() {
+        for succ in graph.get_successors(block) {
+            assert!(graph.get_predecessors(succ).contains(&block));
+        }
+    }
+}
+
+#[test]
+fn test_irreducible() {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32, i32) -> i32>());
+    let (mut a, mut b) = (Label::new(&func), Label::new(&func));
+    func.insn_branch_if(&func[0], &mut b);
+    func.insn_label(&mut a);
+    func.insn_branch_if(&func[1], &mut b);
+    func.insn_return(func.insn_of(1i32));
+    func.insn_label(&mut b);
+    func.insn_branch_if(&func[1], &mut a);
+    func.insn_return(func.insn_of(2i32));
+    let graph = BlockGraph::new(&func);
+    assert!(!graph.is_reducible());
+    // neither block of the cycle dominates the other, so it isn't a natural loop
+    assert!(graph.loops().get_loops().is_empty());
+}
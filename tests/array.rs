@@ -0,0 +1,58 @@
+extern crate jit;
+use jit::*;
+
+#[test]
+fn test_row_major() {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(&'static i32, i32, i32) -> i32>());
+    let matrix = ArrayView::new(&func[0], &get::<i32>(), &[func.insn_of(2i32), func.insn_of(3i32)]);
+    assert_eq!(matrix.get_rank(), 2);
+    assert!(!matrix.is_checked());
+    let old = matrix.load(&[&func[1], &func[2]]);
+    matrix.store(&[&func[1], &func[2]], func.insn_of(-1i32));
+    func.insn_return(old);
+    let at: extern fn(*mut i32, i32, i32) -> i32 = func.compile().closure();
+    let mut values = [0, 1, 2, 10, 11, 12];
+    assert_eq!(at(values.as_mut_ptr(), 1, 2), 12);
+    assert_eq!(at(values.as_mut_ptr(), 0, 1), 1);
+    assert_eq!(values, [0, -1, 2, 10, 11, -1]);
+}
+
+#[test]
+fn test_strided() {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(&'static f64, u8, u8) -> f64>());
+    // a 2 by 2 window of a 4 by 4 column-major matrix
+    let extent = func.insn_of(2u8);
+    let window = ArrayView::strided(&func[0], &get::<f64>(), &[extent, extent], &[func.insn_of(1i32), func.insn_of(4i32)]);
+    func.insn_return(window.load(&[&func[1], &func[2]]));
+    let at: extern fn(*const f64, u8, u8) -> f64 = func.compile().closure();
+    let values: Vec<f64> = (0..16).map(|x| x as f64).collect();
+    assert_eq!(at(values.as_ptr(), 0, 0), 0.0);
+    assert_eq!(at(values.as_ptr(), 1, 0), 1.0);
+    assert_eq!(at(values.as_ptr(), 1, 1), 5.0);
+}
+
+#[test]
+fn test_checked() {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+    let values = [1i32, 2, 3];
+    let base = func.insn_convert(func.insn_of(values.as_ptr() as isize), &get::<&'static i32>(), false);
+    let mut view = ArrayView::new(base, &get::<i32>(), &[func.insn_of(3i32)]);
+    view.set_checked(true);
+    func.insn_return(view.load(&[&func[0]]));
+    let at = func.compile().closure_result::<i32, i32>();
+    assert_eq!(at(2), Ok(3));
+    assert_eq!(at(3), Err(JitException::Builtin(BuiltinException::OutOfBounds)));
+    assert_eq!(at(-1), Err(JitException::Builtin(BuiltinException::OutOfBounds)));
+}
+
+#[test]
+#[should_panic]
+fn test_wrong_rank() {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(&'static i32) -> i32>());
+    let view = ArrayView::new(&func[0], &get::<i32>(), &[func.insn_of(2i32), func.insn_of(2i32)]);
+    view.load(&[func.insn_of(0i32)]);
+}
@@ -0,0 +1,117 @@
+extern crate jit;
+use jit::*;
+use jit::asm::{self, AsmError};
+
+static PROGRAM:&'static str = "
+; squares a number then adds one
+function square(%x: long) -> long {
+    %result = mul %x, %x
+    ret %result
+}
+
+function main(%x: long) -> long {
+    %squared = call square, %x
+    %one = const long 1
+    %result = add %squared, %one
+    ret %result
+}
+";
+
+#[test]
+fn test_asm_calls() {
+    let mut ctx = Context::<()>::new();
+    let funcs = asm::assemble(&mut ctx, PROGRAM).unwrap();
+    assert_eq!(funcs.len(), 2);
+    assert_eq!(funcs[1].0, "main");
+    funcs[1].1.with(|main: extern fn(i64) -> i64| {
+        assert_eq!(main(3), 10);
+        assert_eq!(main(-4), 17);
+    });
+}
+
+#[test]
+fn test_asm_round_trip() {
+    let program = asm::parse(PROGRAM).unwrap();
+    let printed = program.to_string();
+    let reparsed = asm::parse(&printed).unwrap();
+    assert_eq!(reparsed.to_string(), printed);
+    assert_eq!(reparsed.functions.len(), program.functions.len());
+    for (a, b) in reparsed.functions.iter().zip(program.functions.iter()) {
+        assert_eq!(a.name, b.name);
+        assert_eq!(a.params, b.params);
+        let a_body:Vec<_> = a.body.iter().map(|&(_, ref stmt)| stmt).collect();
+        let b_body:Vec<_> = b.body.iter().map(|&(_, ref stmt)| stmt).collect();
+        assert_eq!(a_body, b_body);
+    }
+}
+
+#[test]
+fn test_asm_floats() {
+    let mut ctx = Context::<()>::new();
+    let funcs = asm::assemble(&mut ctx, "
+function half(%x: float64) -> float64 {
+    %half = const float64 0.5
+    %result = mul %x, %half
+    ret %result
+}").unwrap();
+    funcs[0].1.with(|half: extern fn(f64) -> f64| assert_eq!(half(3.0), 1.5));
+}
+
+#[test]
+fn test_asm_errors() {
+    let error = |text: &str| asm::parse(text).err().map(|error: AsmError| error.line);
+    assert_eq!(error("function f() -> int {\n    %x = const int\n"), Some(1));
+    assert_eq!(error("function f() -> wide {\n}"), Some(1));
+    assert_eq!(error("function f() {\n    %x = const int 1, 2.5.3\n}"), Some(2));
+    let mut ctx = Context::<()>::new();
+    let built = asm::parse("function f() -> int {\n    br @nowhere\n    %y = add %x, %x\n}").unwrap().build(&mut ctx);
+    assert_eq!(built.err(), Some(AsmError {
+        line: 3,
+        message: "value %x is not defined".to_string()
+    }));
+}
+
+#[test]
+fn test_asm_store_needs_local() {
+    let mut ctx = Context::<()>::new();
+    let built = asm::parse("function f() {\n    %one = const int 1\n    store %one, %one\n}").unwrap().build(&mut ctx);
+    assert_eq!(built.err(), Some(AsmError {
+        line: 3,
+        message: "`store` needs a local, not the constant `%one`".to_string()
+    }));
+}
+
+#[test]
+fn test_asm_type_errors() {
+    let mut ctx = Context::<()>::new();
+    {
+        let built = asm::parse("function f(%x: int) -> int {\n    %one = const int 1\n    %root = sqrt %x\n    ret %root\n}")
+            .unwrap().build(&mut ctx);
+        let error = built.err().unwrap();
+        assert_eq!(error.line, 3);
+        assert!(error.message.contains("insn_sqrt should be float"));
+    }
+    assert!(!ctx.get_options().defer_errors);
+}
+
+/// Run a program's `main` the way the `asm_repl` example does
+fn run_main(text: &str, args: &[interp::Value]) -> Result<interp::Value, String> {
+    let program = try!(asm::parse(text).map_err(|error| error.to_string()));
+    let mut ctx = Context::<()>::new();
+    let funcs = try!(program.build(&mut ctx).map_err(|error| error.to_string()));
+    let mut compiled = Vec::new();
+    for (name, func) in funcs {
+        compiled.push((name, try!(func.try_compile().map_err(|error| error.to_string()))));
+    }
+    let &(_, ref main) = compiled.iter().find(|&&(ref name, _)| name == "main").unwrap();
+    interp::call(main, args).map_err(|error| error.to_string())
+}
+
+#[test]
+fn test_asm_repl_pipeline() {
+    let text = "function square(%x: int) -> int {\n    %y = mul %x, %x\n    ret %y\n}\n\
+                function main(%x: int) -> int {\n    %y = call square, %x\n    ret %y\n}\n";
+    assert_eq!(run_main(text, &[interp::Value::Int(4)]), Ok(interp::Value::Int(16)));
+    let error = run_main("function main() -> int {\n    ret %nothing\n}\n", &[]).err().unwrap();
+    assert!(error.contains("%nothing"));
+}
@@ -0,0 +1,62 @@
+extern crate jit;
+use jit::*;
+use std::mem;
+
+#[test]
+fn test_compile_in_order() {
+    let mut ctx = Context::<()>::new();
+    let first = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+    let second = first.sibling(&get::<fn(i32) -> i32>());
+    first.insn_return(first.insn_add(&first[0], first.insn_of(1i32)));
+    second.insn_return(second.insn_sub(&second[0], second.insn_of(1i32)));
+    let first = unsafe { first.compile_async() }.unwrap();
+    let second = unsafe { second.compile_async() }.unwrap();
+    let second = second.wait().unwrap();
+    assert!(first.is_done());
+    first.wait().unwrap().with(|inc: extern fn(i32) -> i32| {
+        assert_eq!(inc(1), 2);
+    });
+    second.with(|dec: extern fn(i32) -> i32| {
+        assert_eq!(dec(1), 0);
+    });
+}
+
+#[test]
+fn test_entry() {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+    func.insn_return(func.insn_neg(&func[0]));
+    let pending = unsafe { func.compile_async() }.unwrap();
+    while pending.try_get().is_none() {}
+    let entry = pending.get_entry();
+    assert!(entry != 0);
+    assert_eq!(unsafe { *pending.get_entry_slot() }, entry);
+    let neg: extern fn(i32) -> i32 = unsafe { mem::transmute(entry) };
+    assert_eq!(neg(5), -5);
+}
+
+#[test]
+fn test_strict_failure_up_front() {
+    let mut ctx = Context::<()>::new();
+    ctx.set_options(ContextOptions {
+        strict_ssa: true,
+        .. ContextOptions::default()
+    });
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+    let mut result = None;
+    func.insn_if(func.insn_gt(&func[0], func.insn_of(0i32)), || {
+        result = Some(func.insn_neg(&func[0]));
+    });
+    func.insn_return(result.unwrap());
+    assert_eq!(unsafe { func.compile_async() }.err(), Some(CompileError::UndefinedUse));
+}
+
+#[test]
+fn test_same_steps_as_compile() {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+    func.insn_return(func.insn_neg(&func[0]));
+    let func = unsafe { func.compile_async() }.unwrap().wait().unwrap();
+    let report = func.codegen_report().unwrap();
+    assert!(report.instructions > 0);
+}
@@ -0,0 +1,47 @@
+extern crate jit;
+use jit::*;
+
+fn opcodes(func: &UncompiledFunction) -> Vec<String> {
+    let mut names = Vec::new();
+    for block in func.blocks() {
+        names.extend(block.iter().map(|insn| insn.get_name().to_owned()));
+    }
+    names
+}
+
+#[test]
+fn test_barrier_between_store_and_load() {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(&'static i32) -> i32>());
+    func.insn_store_relative(&func[0], 0, func.insn_of(1i32));
+    func.insn_compiler_barrier();
+    func.insn_return(func.insn_load_relative(&func[0], 0, typecs::get_int()));
+    let opcodes = opcodes(&func);
+    let store = opcodes.iter().position(|op| op.starts_with("store_relative")).unwrap();
+    let barrier = opcodes.iter().position(|op| op.starts_with("call")).unwrap();
+    let load = opcodes.iter().position(|op| op.starts_with("load_relative")).unwrap();
+    assert!(store < barrier && barrier < load, "{:?}", opcodes);
+    func.compile().with(|func: extern fn(*mut i32) -> i32| {
+        let mut flag = 0;
+        assert_eq!(func(&mut flag), 1);
+        assert_eq!(flag, 1);
+    });
+}
+
+#[test]
+fn test_barriers_in_loop() {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(&'static i32) -> i32>());
+    func.insn_for_range(func.insn_of(0i32), func.insn_of(5i32), |_| {
+        let count = func.insn_load_relative(&func[0], 0, typecs::get_int());
+        func.insn_compiler_barrier();
+        func.insn_store_relative(&func[0], 0, func.insn_add(count, func.insn_of(1i32)));
+        func.insn_compiler_barrier();
+    });
+    func.insn_return(func.insn_load_relative(&func[0], 0, typecs::get_int()));
+    func.compile().with(|func: extern fn(*mut i32) -> i32| {
+        let mut count = 10;
+        assert_eq!(func(&mut count), 15);
+        assert_eq!(count, 15);
+    });
+}
@@ -0,0 +1,55 @@
+extern crate jit;
+use jit::*;
+
+#[test]
+fn test_rows() {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(f64, f64, f64) -> f64>());
+    func.insn_return(func.insn_add(func.insn_mul(&func[0], &func[1]), &func[2]));
+    let func = func.compile();
+    let rows: Vec<(f64, f64, f64)> = (0..100).map(|i| (i as f64, 2.0, 0.5)).collect();
+    let mut results = vec![0.0; rows.len()];
+    func.apply_batch(&rows, &mut results);
+    for (i, &result) in results.iter().enumerate() {
+        assert_eq!(result, i as f64 * 2.0 + 0.5);
+    }
+}
+
+#[test]
+fn test_single_argument() {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(u64) -> u64>());
+    func.insn_return(func.insn_shl(&func[0], func.insn_of(1u64)));
+    let mut results = [0u64; 3];
+    func.compile().apply_batch(&[(1u64,), (2,), (3,)], &mut results);
+    assert_eq!(results, [2, 4, 6]);
+}
+
+#[test]
+fn test_empty() {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+    func.insn_return(&func[0]);
+    let mut results: [i32; 0] = [];
+    func.compile().apply_batch::<(i32,), i32>(&[], &mut results);
+}
+
+#[test]
+#[should_panic]
+fn test_wrong_signature() {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+    func.insn_return(&func[0]);
+    let mut results = [0i64; 1];
+    func.compile().apply_batch(&[(1i64,)], &mut results);
+}
+
+#[test]
+#[should_panic]
+fn test_wrong_length() {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+    func.insn_return(&func[0]);
+    let mut results = [0i32; 1];
+    func.compile().apply_batch(&[(1i32,), (2,)], &mut results);
+}
@@ -0,0 +1,64 @@
+extern crate jit;
+use jit::*;
+
+#[test]
+fn test_normalize() {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+    func.insn_return(func.insn_normalize_bool(&func[0]).get());
+    func.compile().with(|f: extern fn(i32) -> i32| {
+        assert_eq!(f(0), 0);
+        assert_eq!(f(7), 1);
+        assert_eq!(f(-2), 1);
+    });
+}
+
+#[test]
+fn test_combining() {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+    let low = func.insn_normalize_bool(func.insn_and(&func[0], func.insn_of(2i32)));
+    let high = func.insn_normalize_bool(func.insn_and(&func[0], func.insn_of(8i32)));
+    func.insn_return((!(low | high)).get());
+    func.compile().with(|f: extern fn(i32) -> i32| {
+        assert_eq!(f(1), 1);
+        assert_eq!(f(2), 0);
+        assert_eq!(f(8), 0);
+        assert_eq!(f(10), 0);
+    });
+}
+
+#[test]
+fn test_float_condition() {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(f64) -> i32>());
+    let result_var = func.new_lvalue(&get::<i32>());
+    let result = result_var.get_local().unwrap();
+    func.insn_if_else(&func[0], || {
+        func.insn_store(&result_var, func.insn_of(1i32));
+    }, || {
+        func.insn_store(&result_var, func.insn_of(2i32));
+    });
+    func.insn_return(result);
+    func.compile().with(|f: extern fn(f64) -> i32| {
+        assert_eq!(f(0.0), 2);
+        assert_eq!(f(0.25), 1);
+        assert_eq!(f(-3.0), 1);
+    });
+}
+
+#[test]
+fn test_while_on_counter() {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+    let (count_var, steps_var) = (func.new_lvalue(&get::<i32>()), func.new_lvalue(&get::<i32>()));
+    let (count, steps) = (count_var.get_local().unwrap(), steps_var.get_local().unwrap());
+    func.insn_store(&count_var, &func[0]);
+    func.insn_store(&steps_var, func.insn_of(0i32));
+    func.insn_while(|| count, || {
+        func.insn_store(&count_var, func.insn_sub(count, func.insn_of(1i32)));
+        func.insn_store(&steps_var, func.insn_add(steps, func.insn_of(1i32)));
+    });
+    func.insn_return(steps);
+    func.compile().with(|f: extern fn(i32) -> i32| assert_eq!(f(5), 5));
+}
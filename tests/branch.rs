@@ -0,0 +1,54 @@
+extern crate jit;
+use jit::*;
+
+/// Build a function that gives 1 if the comparison named branches on its
+/// parameters, or 0
+fn build(func: &UncompiledFunction, cmp: &str) {
+    let mut taken = Label::new(func);
+    let (a, b) = (&func[0], &func[1]);
+    match cmp {
+        "eq" => func.insn_branch_if_eq(a, b, &mut taken),
+        "neq" => func.insn_branch_if_neq(a, b, &mut taken),
+        "lt" => func.insn_branch_if_lt(a, b, &mut taken),
+        "leq" => func.insn_branch_if_leq(a, b, &mut taken),
+        "gt" => func.insn_branch_if_gt(a, b, &mut taken),
+        _ => func.insn_branch_if_geq(a, b, &mut taken)
+    }
+    func.insn_return(func.insn_of(0i32));
+    func.insn_label(&mut taken);
+    func.insn_return(func.insn_of(1i32));
+}
+
+#[test]
+fn test_fused_branches() {
+    let cmps: [(&str, fn(i32, i32) -> bool); 6] = [
+        ("eq", |a, b| a == b),
+        ("neq", |a, b| a != b),
+        ("lt", |a, b| a < b),
+        ("leq", |a, b| a <= b),
+        ("gt", |a, b| a > b),
+        ("geq", |a, b| a >= b)
+    ];
+    for &(cmp, expected) in &cmps {
+        let mut ctx = Context::<()>::new();
+        let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32, i32) -> i32>());
+        build(&func, cmp);
+        let call: extern fn(i32, i32) -> i32 = func.compile().closure();
+        for &(a, b) in &[(1, 2), (2, 2), (3, 2)] {
+            assert_eq!(call(a, b) == 1, expected(a, b), "{} {} {}", a, cmp, b);
+        }
+    }
+}
+
+#[test]
+fn test_no_boolean_temporary() {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32, i32) -> i32>());
+    build(&func, "lt");
+    let mut opcodes = Vec::new();
+    for block in func.blocks() {
+        opcodes.extend(block.iter().map(|insn| insn.get_name().to_owned()));
+    }
+    assert!(opcodes.iter().all(|op| op != "ilt"));
+    assert!(opcodes.iter().any(|op| op.starts_with("br_ilt")));
+}
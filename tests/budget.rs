@@ -0,0 +1,66 @@
+extern crate jit;
+use jit::*;
+
+fn deferring(budget: EmitBudget) -> Context<()> {
+    ContextBuilder::new()
+        .options(ContextOptions {
+            defer_errors: true,
+            .. ContextOptions::default()
+        })
+        .emit_budget(budget)
+        .build()
+        .unwrap()
+}
+
+#[test]
+fn test_within_budget() {
+    let mut ctx = deferring(EmitBudget { max_insns: 8, max_values: 8, max_labels: 1 });
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+    let x = func.insn_mul(&func[0], func.insn_of(3i32));
+    func.insn_return(func.insn_add(x, func.insn_of(1i32)));
+    assert!(!func.is_poisoned());
+    func.compile().with(|f: extern fn(i32) -> i32| assert_eq!(f(2), 7));
+}
+
+#[test]
+fn test_instructions() {
+    let mut ctx = deferring(EmitBudget { max_insns: 10, .. EmitBudget::default() });
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+    let mut x = &func[0];
+    for _ in 0..100 {
+        x = func.insn_add(x, &func[0]);
+    }
+    func.insn_return(x);
+    let error = func.get_poison().unwrap();
+    assert!(error.message.contains("10 instructions"));
+    match func.try_compile() {
+        Err(CompileError::Poisoned(first)) => assert_eq!(first, error),
+        _ => panic!("compiled a function over its budget")
+    }
+}
+
+#[test]
+fn test_values_and_labels() {
+    let mut ctx = deferring(EmitBudget { max_values: 2, .. EmitBudget::default() });
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn()>());
+    for _ in 0..3 {
+        Val::new(&func, &get::<i32>());
+    }
+    assert!(func.get_poison().unwrap().message.contains("2 values"));
+    let func = func.sibling(&get::<fn()>());
+    func.set_emit_budget(EmitBudget { max_labels: 1, .. EmitBudget::default() });
+    let _first = Label::new(&func);
+    assert!(!func.is_poisoned());
+    let _second = Label::new(&func);
+    assert!(func.get_poison().unwrap().message.contains("1 labels"));
+}
+
+#[test]
+#[should_panic(expected = "emission budget")]
+fn test_panics_without_deferring() {
+    let mut ctx = Context::<()>::new();
+    ctx.set_emit_budget(EmitBudget { max_insns: 1, .. EmitBudget::default() });
+    assert_eq!(ctx.get_emit_budget().max_insns, 1);
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+    func.insn_return(func.insn_neg(func.insn_neg(&func[0])));
+}
@@ -0,0 +1,46 @@
+extern crate jit;
+use jit::*;
+
+#[test]
+fn test_address_and_length() {
+    let buffer = Buffer::from(&b"hello"[..]);
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(usize) -> usize>());
+    let (bytes, len) = func.insn_buffer(&buffer);
+    let last = func.insn_sub(len, func.insn_of(1usize));
+    let byte = func.insn_load_elem(bytes, last, &get::<u8>());
+    func.insn_return(func.insn_add(func.insn_convert(byte, &get::<usize>(), false), &func[0]));
+    func.compile().with(|last: extern fn(usize) -> usize| {
+        assert_eq!(last(1), b'o' as usize + 1);
+    });
+}
+
+#[test]
+fn test_pinned_until_context_drops() {
+    let mut buffer = Buffer::new(vec![7; 4]);
+    assert!(!buffer.is_pinned());
+    {
+        let mut ctx = Context::<()>::new();
+        let func = UncompiledFunction::new(&mut ctx, &get::<fn(usize) -> u8>());
+        let (bytes, _) = func.insn_buffer(&buffer);
+        func.insn_return(func.insn_load_elem(bytes, &func[0], &get::<u8>()));
+        let func = func.compile();
+        assert!(buffer.is_pinned());
+        assert!(buffer.get_mut().is_none());
+        func.with(|get: extern fn(usize) -> u8| assert_eq!(get(3), 7));
+    }
+    buffer.get_mut().unwrap().resize(8, 1);
+    assert_eq!(buffer.len(), 8);
+}
+
+#[test]
+fn test_outlives_buffer() {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(usize) -> u8>());
+    {
+        let buffer = Buffer::new(vec![1, 2, 3]);
+        let (bytes, _) = func.insn_buffer(&buffer);
+        func.insn_return(func.insn_load_elem(bytes, &func[0], &get::<u8>()));
+    }
+    func.compile().with(|get: extern fn(usize) -> u8| assert_eq!(get(2), 3));
+}
@@ -0,0 +1,52 @@
+extern crate jit;
+use jit::*;
+
+#[test]
+fn test_defaults_match_new() {
+    let ctx = ContextBuilder::new().build::<()>().unwrap();
+    let plain = Context::<()>::new();
+    assert_eq!(ctx.is_debug(), plain.is_debug());
+    assert_eq!(ctx.uses_long_helpers(), plain.uses_long_helpers());
+    assert_eq!(ctx.get_inline_limits(), plain.get_inline_limits());
+    assert!(ctx.get_stack_guard().is_none());
+}
+
+#[test]
+fn test_settings_applied() {
+    let limits = InlineLimits {
+        max_size: 8,
+        max_depth: 1
+    };
+    let ctx = ContextBuilder::new()
+        .options(ContextOptions {
+            strict_ssa: true,
+            .. ContextOptions::default()
+        })
+        .validation(false)
+        .debug(true)
+        .inline_limits(limits)
+        .long_helpers(true)
+        .build::<()>()
+        .unwrap();
+    let options = ctx.get_options();
+    assert!(options.strict_ssa);
+    assert!(!options.validation);
+    assert!(ctx.is_debug());
+    assert_eq!(ctx.get_inline_limits(), limits);
+    assert!(ctx.uses_long_helpers());
+}
+
+#[test]
+fn test_incompatible_settings() {
+    let features = ContextBuilder::new().feature_level(cpu::Features::all(), cpu::Features::empty());
+    match features.check() {
+        Err(ContextError::Features(_)) => (),
+        other => panic!("expected a feature error, got {:?}", other)
+    }
+    let native = ContextBuilder::new().allow_interpreter(false).debug_info(true);
+    if uses_interpreter() {
+        assert_eq!(native.check(), Err(ContextError::Interpreted));
+    } else {
+        assert_eq!(native.check(), Ok(()));
+    }
+}
@@ -0,0 +1,55 @@
+extern crate jit;
+use jit::*;
+
+extern fn native_negate(x: i32) -> i32 {
+    -x
+}
+
+#[test]
+fn test_patch_to_native() {
+    let mut ctx = Context::<()>::new();
+    let inc = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+    inc.insn_return(inc.insn_add(&inc[0], inc.insn_of(1i32)));
+    let caller = inc.sibling(&get::<fn(i32) -> i32>());
+    let inc = inc.compile();
+    let (result, site) = caller.insn_call_site(&inc, &mut [&caller[0]], flags::CallFlags::empty());
+    caller.insn_return(result);
+    let caller = caller.compile();
+    caller.with(|call: extern fn(i32) -> i32| assert_eq!(call(1), 2));
+    site.set_native(native_negate as usize);
+    assert_eq!(site.get_target(), native_negate as usize);
+    caller.with(|call: extern fn(i32) -> i32| assert_eq!(call(1), -1));
+}
+
+#[test]
+fn test_lazy_target() {
+    let mut ctx = Context::<()>::new();
+    let later = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+    let caller = later.sibling(&get::<fn(i32) -> i32>());
+    let (result, site) = caller.insn_call_site(&later, &mut [&caller[0]], flags::CallFlags::empty());
+    caller.insn_return(result);
+    let caller = caller.compile();
+    assert_eq!(site.get_target(), 0);
+    later.insn_return(later.insn_mul(&later[0], &later[0]));
+    let later = later.compile();
+    site.set_target(&later);
+    caller.with(|call: extern fn(i32) -> i32| assert_eq!(call(7), 49));
+}
+
+#[test]
+fn test_outlives_caller() {
+    let mut ctx = Context::<()>::new();
+    let inc = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+    inc.insn_return(inc.insn_add(&inc[0], inc.insn_of(1i32)));
+    let caller = inc.sibling(&get::<fn(i32) -> i32>());
+    let other = inc.sibling(&get::<fn(i32) -> i32>());
+    let inc = inc.compile();
+    let (result, site) = caller.insn_call_site(&inc, &mut [&caller[0]], flags::CallFlags::empty());
+    caller.insn_return(result);
+    drop(caller);
+    let (result, _) = other.insn_call_site(&inc, &mut [&other[0]], flags::CallFlags::empty());
+    other.insn_return(result);
+    site.set_native(native_negate as usize);
+    assert_eq!(site.get_target(), native_negate as usize);
+    other.compile().with(|call: extern fn(i32) -> i32| assert_eq!(call(1), 2));
+}
@@ -0,0 +1,48 @@
+extern crate jit;
+use jit::*;
+
+extern fn stack_depth(ctx: usize) -> usize {
+    let ctx = unsafe { &*(ctx as *const Context<()>) };
+    let stack = CallStack::capture();
+    (0..stack.len()).filter(|&posn| stack.get_function(ctx, posn).is_some()).count()
+}
+
+#[test]
+fn test_capture() {
+    let mut ctx = Context::<()>::new();
+    let address = &ctx as *const Context<()> as usize;
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(usize) -> usize>());
+    let depth = func.insn_call_native1(Some("stack_depth"), stack_depth,
+                                       &get::<fn(usize) -> usize>(), [&func[0]],
+                                       CallFlags::empty());
+    func.insn_return(depth);
+    func.compile().with(|depth: extern fn(usize) -> usize| {
+        assert_eq!(depth(address), 1);
+    });
+}
+
+#[test]
+fn test_caller() {
+    let mut ctx = Context::<()>::new();
+    let callee = UncompiledFunction::new(&mut ctx, &get::<fn(usize) -> usize>());
+    let caller = callee.insn_get_caller();
+    callee.insn_return(callee.insn_convert(caller, &get::<usize>(), false));
+    let outer = callee.sibling(&get::<fn(usize) -> usize>());
+    let result = outer.insn_call(None, &callee, None, &mut [&outer[0]], CallFlags::empty());
+    outer.insn_return(result);
+    let outer_address = &*outer as *const Func as usize;
+    let callee = callee.compile();
+    outer.compile().with(|outer: extern fn(usize) -> usize| {
+        assert_eq!(outer(0), outer_address);
+    });
+    callee.with(|callee: extern fn(usize) -> usize| {
+        assert_eq!(callee(0), 0);
+    });
+}
+
+#[test]
+fn test_empty_stack() {
+    let stack = unsafe { CallStack::from_raw(std::ptr::null_mut()) };
+    assert!(stack.is_empty());
+    assert_eq!(stack.get_pc(0), None);
+}
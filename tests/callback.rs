@@ -0,0 +1,42 @@
+extern crate jit;
+use jit::*;
+use std::mem;
+use std::os::raw::{c_int, c_void};
+
+extern "C" {
+    fn qsort(base: *mut c_void, count: usize, size: usize,
+             compare: extern "C" fn(*const c_void, *const c_void) -> c_int);
+}
+
+#[test]
+fn test_qsort_comparator() {
+    let mut ctx = Context::<()>::new();
+    let compare = {
+        let sig = get::<fn(&'static i32, &'static i32, &'static i32) -> i32>();
+        let func = UncompiledFunction::new(&mut ctx, &sig);
+        let direction = func.insn_load_relative(&func[0], 0, &get::<i32>());
+        let a = func.insn_load_relative(&func[1], 0, &get::<i32>());
+        let b = func.insn_load_relative(&func[2], 0, &get::<i32>());
+        func.insn_return(func.insn_mul(func.insn_sub(a, b), direction));
+        func.compile()
+    };
+    let mut descending = -1i32;
+    let callback = compare.as_c_callback(&mut descending as *mut i32 as *mut c_void);
+    let mut values = [3i32, 9, -4, 7, 0];
+    unsafe {
+        qsort(values.as_mut_ptr() as *mut c_void, values.len(), mem::size_of::<i32>(), mem::transmute(callback));
+    }
+    assert_eq!(values, [9, 7, 3, 0, -4]);
+}
+
+#[test]
+#[should_panic]
+fn test_needs_pointer_first() {
+    let mut ctx = Context::<()>::new();
+    let func = {
+        let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+        func.insn_return(&func[0]);
+        func.compile()
+    };
+    func.as_c_callback(0 as *mut c_void);
+}
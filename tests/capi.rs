@@ -0,0 +1,61 @@
+#![cfg(feature = "capi")]
+extern crate jit;
+use jit::capi::*;
+use std::mem;
+use std::ptr;
+
+#[test]
+fn test_capi_compile() {
+    let code = [JITRS_PARAM, 0, JITRS_PARAM, 1, JITRS_MUL, JITRS_CONST, 1, JITRS_ADD, JITRS_RETURN];
+    unsafe {
+        let ctx = jitrs_context_new();
+        let mut error = -1;
+        let func = jitrs_compile(ctx, 2, code.as_ptr(), code.len() as usize, &mut error);
+        assert_eq!(error, JITRS_OK);
+        assert!(!func.is_null());
+        let func:extern fn(i64, i64) -> i64 = mem::transmute(func);
+        assert_eq!(func(6, 7), 43);
+        jitrs_context_free(ctx);
+    }
+}
+
+#[test]
+fn test_capi_errors() {
+    unsafe {
+        let ctx = jitrs_context_new();
+        let mut error = -1;
+        let bad_param = [JITRS_PARAM, 3, JITRS_RETURN];
+        assert!(jitrs_compile(ctx, 1, bad_param.as_ptr(), 3, &mut error).is_null());
+        assert_eq!(error, JITRS_BAD_PARAM);
+        let underflow = [JITRS_CONST, 1, JITRS_ADD, JITRS_RETURN];
+        assert!(jitrs_compile(ctx, 0, underflow.as_ptr(), 4, &mut error).is_null());
+        assert_eq!(error, JITRS_STACK_UNDERFLOW);
+        let no_return = [JITRS_CONST, 1];
+        assert!(jitrs_compile(ctx, 0, no_return.as_ptr(), 2, &mut error).is_null());
+        assert_eq!(error, JITRS_BAD_RETURN);
+        assert!(jitrs_compile(ptr::null_mut(), 0, ptr::null(), 0, &mut error).is_null());
+        assert_eq!(error, JITRS_NULL);
+        jitrs_context_free(ctx);
+    }
+}
+
+#[test]
+fn test_capi_division() {
+    let div = [JITRS_PARAM, 0, JITRS_PARAM, 1, JITRS_DIV, JITRS_RETURN];
+    let rem = [JITRS_PARAM, 0, JITRS_PARAM, 1, JITRS_REM, JITRS_RETURN];
+    unsafe {
+        let ctx = jitrs_context_new();
+        let mut error = -1;
+        let div:extern fn(i64, i64) -> i64 = mem::transmute(jitrs_compile(ctx, 2, div.as_ptr(), div.len(), &mut error));
+        assert_eq!(error, JITRS_OK);
+        let rem:extern fn(i64, i64) -> i64 = mem::transmute(jitrs_compile(ctx, 2, rem.as_ptr(), rem.len(), &mut error));
+        assert_eq!(error, JITRS_OK);
+        assert_eq!(div(7, 2), 3);
+        assert_eq!(rem(7, 2), 1);
+        assert_eq!(div(7, 0), 0);
+        assert_eq!(rem(7, 0), 0);
+        assert_eq!(div(i64::min_value(), -1), i64::min_value());
+        assert_eq!(rem(i64::min_value(), -1), 0);
+        jitrs_context_free(ctx);
+    }
+}
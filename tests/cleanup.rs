@@ -0,0 +1,71 @@
+extern crate jit;
+use jit::*;
+
+/// Build a function that takes `[divisor, log]`, and in nested regions
+/// divides by the divisor, with each cleanup shifting its digit into the log
+fn build_nested(func: &UncompiledFunction) {
+    let state = &func[0];
+    let log = |digit: i32| {
+        let shifted = func.insn_mul(func.insn_load_relative(state, 4, &get::<i32>()), func.insn_of(10i32));
+        func.insn_store_relative(state, 4, func.insn_add(shifted, func.insn_of(digit)));
+    };
+    let result_var = func.new_lvalue(&get::<i32>());
+    func.with_cleanup(|| {
+        func.with_cleanup(|| {
+            let divisor = func.insn_load_relative(state, 0, &get::<i32>());
+            func.insn_store(&result_var, func.insn_div(func.insn_of(100i32), divisor));
+        }, || log(2));
+    }, || log(1));
+}
+
+#[test]
+fn test_cleans_up_on_fallthrough() {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(&'static i32) -> i32>());
+    build_nested(&func);
+    func.insn_return(func.insn_of(0i32));
+    let call = func.compile().closure_result::<*mut i32, i32>();
+    let mut state = [5i32, 0];
+    assert_eq!(call(state.as_mut_ptr()), Ok(0));
+    assert_eq!(state[1], 21);
+}
+
+#[test]
+fn test_cleans_up_on_exception() {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(&'static i32) -> i32>());
+    build_nested(&func);
+    func.insn_return(func.insn_of(0i32));
+    let call = func.compile().closure_result::<*mut i32, i32>();
+    let mut state = [0i32, 0];
+    assert_eq!(call(state.as_mut_ptr()), Err(JitException::Builtin(BuiltinException::DivisionByZero)));
+    assert_eq!(state[1], 21);
+}
+
+#[test]
+fn test_exception_outside_region() {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(&'static i32) -> i32>());
+    func.with_cleanup(|| (), || {
+        func.insn_store_relative(&func[0], 4, func.insn_of(7i32));
+    });
+    func.insn_store_relative(&func[0], 4, func.insn_of(0i32));
+    func.insn_return(func.insn_div(func.insn_of(1i32), func.insn_load_relative(&func[0], 0, &get::<i32>())));
+    let call = func.compile().closure_result::<*mut i32, i32>();
+    let mut state = [0i32, 0];
+    assert!(call(state.as_mut_ptr()).is_err());
+    assert_eq!(state[1], 0);
+}
+
+#[test]
+fn test_own_catcher_cleans_up_first() {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(&'static i32) -> i32>());
+    build_nested(&func);
+    func.insn_return(func.insn_of(0i32));
+    func.insn_start_catcher();
+    func.insn_return(func.insn_load_relative(&func[0], 4, &get::<i32>()));
+    let call = func.compile().closure_result::<*mut i32, i32>();
+    let mut state = [0i32, 0];
+    assert_eq!(call(state.as_mut_ptr()), Ok(21));
+}
@@ -0,0 +1,48 @@
+extern crate jit;
+use jit::*;
+
+#[test]
+fn test_matching_closure() {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(i64, f64, u8) -> f64>());
+    let sum = func.insn_add(func.insn_convert(&func[0], &get::<f64>(), false), &func[1]);
+    func.insn_return(func.insn_add(sum, func.insn_convert(&func[2], &get::<f64>(), false)));
+    let add: extern fn(i64, f64, u8) -> f64 = func.compile().closure();
+    assert_eq!(add(1, 0.5, 2), 3.5);
+}
+
+#[test]
+fn test_no_params() {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn() -> i32>());
+    func.insn_return(func.insn_of(42i32));
+    let answer: extern fn() -> i32 = func.compile().closure();
+    assert_eq!(answer(), 42);
+}
+
+#[test]
+#[should_panic(expected = "taking 1 parameters")]
+fn test_wrong_arity() {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32, i32) -> i32>());
+    func.insn_return(&func[0]);
+    let _: extern fn(i32) -> i32 = func.compile().closure();
+}
+
+#[test]
+#[should_panic(expected = "parameter 1")]
+fn test_wrong_param_size() {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32, i64) -> i32>());
+    func.insn_return(&func[0]);
+    let _: extern fn(i32, i32) -> i32 = func.compile().closure();
+}
+
+#[test]
+#[should_panic(expected = "result")]
+fn test_wrong_result() {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32)>());
+    func.insn_default_return();
+    let _: extern fn(i32) -> i64 = func.compile().closure();
+}
@@ -0,0 +1,39 @@
+extern crate jit;
+use jit::*;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+fn hash_square(ctx: &mut Context<()>) -> (u64, usize) {
+    let func = UncompiledFunction::new(ctx, &get::<fn(i32) -> i32>());
+    func.insn_return(func.insn_mul(&func[0], &func[0]));
+    let func = func.compile();
+    let mut hasher = DefaultHasher::new();
+    func.code_bytes().hash(&mut hasher);
+    (hasher.finish(), func.code_bytes().len())
+}
+
+#[test]
+fn test_code_starts_at_entry() {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+    func.insn_return(func.insn_add(&func[0], func.insn_of(1i32)));
+    let func = func.compile();
+    let entry: extern fn(i32) -> i32 = func.closure();
+    assert_eq!(func.get_code_address(), entry as usize);
+    if !uses_interpreter() {
+        let code = func.code_bytes();
+        assert!(code.len() > 0);
+        assert_eq!(code.as_ptr() as usize, entry as usize);
+    }
+}
+
+#[test]
+fn test_same_code_same_hash() {
+    if uses_interpreter() {
+        return
+    }
+    let (first, first_len) = hash_square(&mut Context::new());
+    let (second, second_len) = hash_square(&mut Context::new());
+    assert_eq!(first_len, second_len);
+    assert_eq!(first, second);
+}
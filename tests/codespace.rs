@@ -0,0 +1,61 @@
+extern crate jit;
+use jit::*;
+
+fn constant(space: &mut CodeSpace, value: i32) -> CodeHandle {
+    space.build(|ctx| {
+        let func = UncompiledFunction::new(ctx, &get::<fn(i32) -> i32>());
+        func.insn_return(func.insn_add(&func[0], func.insn_of(value)));
+        func.compile()
+    })
+}
+
+#[test]
+fn test_generations() {
+    let mut space = CodeSpace::<()>::new(2);
+    let handles: Vec<CodeHandle> = (0..5).map(|i| constant(&mut space, i)).collect();
+    assert_eq!(space.generations(), 3);
+    for (i, handle) in handles.iter().enumerate() {
+        let add: extern fn(i32) -> i32 = unsafe { handle.get() };
+        assert_eq!(add(10), 10 + i as i32);
+    }
+}
+
+#[test]
+fn test_collect_unused() {
+    let mut space = CodeSpace::<()>::new(1);
+    let first = constant(&mut space, 1);
+    let second = constant(&mut space, 2);
+    let kept = first.clone();
+    drop(first);
+    let _third = constant(&mut space, 3);
+    assert_eq!(space.collect(), 0);
+    drop(kept);
+    drop(second);
+    assert_eq!(space.collect(), 2);
+    assert_eq!(space.generations(), 1);
+    // the current generation is never collected
+    assert_eq!(space.collect(), 0);
+}
+
+#[test]
+fn test_replace_through_slot() {
+    let mut space = CodeSpace::<()>::new(1);
+    let callee = constant(&mut space, 1);
+    let caller = space.build(|ctx| {
+        let func = UncompiledFunction::new(ctx, &get::<fn(i32) -> i32>());
+        let result = func.insn_call_slot(&callee, &get::<fn(i32) -> i32>(), &mut [&func[0]], CallFlags::empty());
+        func.insn_return(result);
+        func.compile()
+    });
+    let call: extern fn(i32) -> i32 = unsafe { caller.get() };
+    assert_eq!(call(5), 6);
+    space.replace(&callee, |ctx| {
+        let func = UncompiledFunction::new(ctx, &get::<fn(i32) -> i32>());
+        func.insn_return(func.insn_mul(&func[0], func.insn_of(100i32)));
+        func.compile()
+    });
+    assert_eq!(call(5), 500);
+    // the first version of the callee isn't used by anything now
+    assert_eq!(space.collect(), 1);
+    assert_eq!(call(6), 600);
+}
@@ -0,0 +1,47 @@
+extern crate jit;
+use jit::*;
+
+#[test]
+fn test_pipeline() {
+    let mut ctx = Context::<()>::new();
+    let double = UncompiledFunction::new(&mut ctx, &get::<fn(i32, i32) -> i32>());
+    double.insn_return(double.insn_mul(double.insn_add(&double[0], &double[1]), double.insn_of(2i32)));
+    let inc = double.sibling(&get::<fn(i64) -> i64>());
+    inc.insn_return(inc.insn_add(&inc[0], inc.insn_of(1i64)));
+    let neg = double.sibling(&get::<fn(i64) -> i64>());
+    neg.insn_return(neg.insn_neg(&neg[0]));
+    let pipeline = UncompiledFunction::compose(&[&*double, &*inc, &*neg]).unwrap();
+    assert!(pipeline.get_signature().signature_compatible(&get::<fn(i32, i32) -> i64>()));
+    let handle = pipeline.compile().to_handle::<(i32, i32), i64>();
+    assert_eq!(handle.call((2, 3)), -11);
+}
+
+#[test]
+fn test_single_stage() {
+    let mut ctx = Context::<()>::new();
+    let square = UncompiledFunction::new(&mut ctx, &get::<fn(u32) -> u32>());
+    square.insn_return(square.insn_mul(&square[0], &square[0]));
+    let pipeline = UncompiledFunction::compose(&[&*square]).unwrap();
+    pipeline.compile().with(|f: extern fn(u32) -> u32| assert_eq!(f(7), 49));
+}
+
+#[test]
+fn test_errors() {
+    let mut ctx = Context::<()>::new();
+    let pair = UncompiledFunction::new(&mut ctx, &get::<fn(i32, i32) -> i32>());
+    pair.insn_return(&pair[0]);
+    let point = pair.sibling(&get::<fn(&'static u8) -> i32>());
+    point.insn_return(point.insn_of(0i32));
+    let nothing = pair.sibling(&get::<fn(i32)>());
+    nothing.insn_default_return();
+    assert_eq!(UncompiledFunction::compose(&[]).err(), Some(ComposeError::Empty));
+    assert_eq!(UncompiledFunction::compose(&[&*pair, &*pair]).err(), Some(ComposeError::Arity(1, 2)));
+    match UncompiledFunction::compose(&[&*pair, &*point]) {
+        Err(ComposeError::Mismatch(1, _, _)) => (),
+        other => panic!("expected a mismatch, got {:?}", other.err())
+    }
+    match UncompiledFunction::compose(&[&*nothing, &*nothing]) {
+        Err(ComposeError::Mismatch(1, _, _)) => (),
+        other => panic!("expected a mismatch, got {:?}", other.err())
+    }
+}
@@ -0,0 +1,62 @@
+extern crate jit;
+use jit::*;
+
+#[test]
+fn test_saturating_ints() {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(i64) -> i32>());
+    func.insn_return(func.insn_convert_saturating(&func[0], &get::<i32>()));
+    func.compile().with(|clamp: extern fn(i64) -> i32| {
+        assert_eq!(clamp(7), 7);
+        assert_eq!(clamp(1 << 40), i32::max_value());
+        assert_eq!(clamp(-(1 << 40)), i32::min_value());
+    });
+}
+
+#[test]
+fn test_saturating_unsigned() {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(u32) -> i16>());
+    func.insn_return(func.insn_convert_saturating(&func[0], &get::<i16>()));
+    func.compile().with(|clamp: extern fn(u32) -> i16| {
+        assert_eq!(clamp(12), 12);
+        assert_eq!(clamp(u32::max_value()), i16::max_value());
+    });
+}
+
+#[test]
+fn test_saturating_floats() {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(f64) -> i32>());
+    func.insn_return(func.insn_convert_saturating(&func[0], &get::<i32>()));
+    func.compile().with(|clamp: extern fn(f64) -> i32| {
+        assert_eq!(clamp(-2.75), -2);
+        assert_eq!(clamp(1e20), i32::max_value());
+        assert_eq!(clamp(-1e20), i32::min_value());
+        assert_eq!(clamp(0.0 / 0.0), 0);
+    });
+}
+
+#[test]
+fn test_saturating_floats_to_long() {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(f64) -> u64>());
+    func.insn_return(func.insn_convert_saturating(&func[0], &get::<u64>()));
+    func.compile().with(|clamp: extern fn(f64) -> u64| {
+        assert_eq!(clamp(-3.0), 0);
+        assert_eq!(clamp(1e30), u64::max_value());
+        assert_eq!(clamp(42.9), 42);
+    });
+}
+
+#[test]
+fn test_wrapping() {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i8>());
+    func.insn_return(func.insn_convert_wrapping(&func[0], &get::<i8>()));
+    func.compile().with(|wrap: extern fn(i32) -> i8| {
+        assert_eq!(wrap(5), 5);
+        assert_eq!(wrap(200), -56);
+        assert_eq!(wrap(-129), 127);
+    });
+}
@@ -0,0 +1,58 @@
+extern crate jit;
+use jit::*;
+
+fn build_sign(ctx: &mut Context<()>) -> CompiledFunction {
+    let func = UncompiledFunction::new(ctx, &get::<fn(i32) -> i32>());
+    let mut negative = Label::new(&func);
+    func.insn_branch_if(func.insn_lt(&func[0], func.insn_of(0i32)), &mut negative);
+    func.insn_return(func.insn_of(1i32));
+    func.insn_label(&mut negative);
+    func.insn_return(func.insn_of(-1i32));
+    func.compile()
+}
+
+#[test]
+fn test_counts_blocks() {
+    let mut ctx = Context::<()>::new();
+    ctx.enable_coverage();
+    let func = build_sign(&mut ctx);
+    assert_eq!(func.get_coverage(), Some(vec![0, 0, 0]));
+    func.with(|sign: extern fn(i32) -> i32| {
+        assert_eq!(sign(5), 1);
+        assert_eq!(sign(7), 1);
+        assert_eq!(sign(-2), -1);
+    });
+    assert_eq!(func.get_coverage(), Some(vec![3, 2, 1]));
+}
+
+#[test]
+fn test_counts_loop_body() {
+    let mut ctx = Context::<()>::new();
+    ctx.enable_coverage();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+    let mut top = Label::new(&func);
+    let mut done = Label::new(&func);
+    let count_var = func.param_lvalue(0);
+    let count = count_var.get_local().unwrap();
+    func.insn_label(&mut top);
+    func.insn_branch_if_not(count, &mut done);
+    func.insn_store(&count_var, func.insn_sub(count, func.insn_of(1i32)));
+    func.insn_branch(&mut top);
+    func.insn_label(&mut done);
+    func.insn_return(count);
+    let func = func.compile();
+    func.with(|count_down: extern fn(i32) -> i32| assert_eq!(count_down(4), 0));
+    let coverage = func.get_coverage().unwrap();
+    // entry, loop top, loop body and exit
+    assert_eq!(coverage, [1, 5, 4, 1]);
+}
+
+#[test]
+fn test_disabled_coverage() {
+    let mut ctx = Context::<()>::new();
+    ctx.enable_coverage();
+    ctx.disable_coverage();
+    let func = build_sign(&mut ctx);
+    func.with(|sign: extern fn(i32) -> i32| assert_eq!(sign(-3), -1));
+    assert_eq!(func.get_coverage(), None);
+}
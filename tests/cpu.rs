@@ -0,0 +1,38 @@
+extern crate jit;
+use jit::*;
+use jit::cpu::{FeatureError, Features};
+
+#[test]
+fn test_host_runs_backend() {
+    let backend = cpu::backend_features();
+    assert!(cpu::host_features().contains(backend));
+    assert_eq!(cpu::check_level(backend, Features::all()), Ok(()));
+}
+
+#[test]
+fn test_level_errors() {
+    let backend = cpu::backend_features();
+    if !backend.is_empty() {
+        assert_eq!(cpu::check_level(Features::empty(), Features::empty()), Err(FeatureError::Unavoidable(backend)));
+    }
+    let missing = Features::all() - cpu::host_features();
+    if !missing.is_empty() {
+        assert_eq!(cpu::check_level(Features::all(), Features::all()), Err(FeatureError::Missing(missing)));
+    }
+}
+
+#[test]
+fn test_context_level() {
+    let backend = cpu::backend_features();
+    let mut ctx = Context::<()>::new();
+    assert_eq!(ctx.get_feature_level(), None);
+    assert_eq!(ctx.set_feature_level(backend, Features::all()), Ok(()));
+    assert_eq!(ctx.get_feature_level(), Some((backend, Features::all())));
+    let missing = Features::all() - cpu::host_features();
+    if !missing.is_empty() {
+        assert!(ctx.set_feature_level(Features::all(), Features::all()).is_err());
+        assert_eq!(ctx.get_feature_level(), Some((backend, Features::all())));
+    }
+    let ctx = ContextBuilder::new().feature_level(backend, Features::all()).build::<()>().unwrap();
+    assert_eq!(ctx.get_feature_level(), Some((backend, Features::all())));
+}
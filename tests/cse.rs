@@ -0,0 +1,106 @@
+extern crate jit;
+use jit::*;
+
+fn count(func: &UncompiledFunction) -> usize {
+    func.blocks().fold(0, |count, block| count + block.iter().count())
+}
+
+#[test]
+fn test_repeated_subtrees() {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32, i32) -> i32>());
+    let tree = |func: &UncompiledFunction| {
+        let sum = func.insn_add(&func[0], &func[1]);
+        func.insn_mul(sum, func.insn_of(2i32))
+    };
+    let left = tree(&func);
+    let right = tree(&func);
+    func.insn_return(func.insn_sub(left, right));
+    let before = count(&func);
+    let func = func.eliminate_common();
+    assert!(count(&func) <= before - 2);
+    let func = func.compile();
+    assert_eq!(interp::call(&func, &[interp::Value::Int(3), interp::Value::Int(4)]), Ok(interp::Value::Int(0)));
+}
+
+#[test]
+fn test_store_is_barrier() {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(&'static u8) -> i32>());
+    let first = func.insn_load_relative(&func[0], 0, &get::<i32>());
+    func.insn_store_relative(&func[0], 0, func.insn_add(first, func.insn_of(1i32)));
+    let second = func.insn_load_relative(&func[0], 0, &get::<i32>());
+    func.insn_return(func.insn_add(first, second));
+    let func = func.eliminate_common();
+    assert_eq!(func.dump_stable().matches("load_relative").count(), 2);
+    func.compile().with(|bump: extern fn(&'static u8) -> i32| {
+        let mut value = 5i32;
+        assert_eq!(bump(unsafe { &*(&mut value as *mut i32 as *const u8) }), 11);
+        assert_eq!(value, 6);
+    });
+}
+
+#[test]
+fn test_assignment_forgets() {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+    let x_var = func.new_lvalue(&get::<i32>());
+    let x = x_var.get_local().unwrap();
+    func.insn_store(&x_var, &func[0]);
+    let first = func.insn_neg(x);
+    func.insn_store(&x_var, func.insn_of(10i32));
+    let second = func.insn_neg(x);
+    func.insn_return(func.insn_add(first, second));
+    let func = func.eliminate_common();
+    func.compile().with(|sum: extern fn(i32) -> i32| {
+        assert_eq!(sum(1), -11);
+    });
+}
+
+#[test]
+fn test_keeps_storage() {
+    let mut ctx = Context::<()>::new();
+    ctx.enable_call_counts();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(usize) -> i32>());
+    let table = func.new_table(&[3i32, 5, 7]);
+    let a = func.insn_load_table(table, &func[0]);
+    let b = func.insn_load_table(table, &func[0]);
+    func.insn_return(func.insn_add(a, b));
+    let func = func.eliminate_common().compile();
+    func.with(|twice: extern fn(usize) -> i32| {
+        assert_eq!(twice(1), 10);
+        assert_eq!(twice(2), 14);
+    });
+    assert_eq!(func.get_call_count(), Some(2));
+}
+
+#[test]
+fn test_weak_references_stop_rebuild() {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+    let a = func.insn_mul(&func[0], &func[0]);
+    let b = func.insn_mul(&func[0], &func[0]);
+    func.insn_return(func.insn_add(a, b));
+    let weak = func.downgrade();
+    let func = func.eliminate_common();
+    assert_eq!(func.dump_stable().matches("imul").count(), 2);
+    assert!(weak.is_alive());
+}
+
+#[test]
+fn test_calls_are_reported() {
+    let mut ctx = Context::<()>::new();
+    let negate = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+    negate.insn_return(negate.insn_neg(&negate[0]));
+    let func = negate.sibling(&get::<fn(i32) -> i32>());
+    let negate = negate.compile();
+    let a = func.insn_mul(&func[0], &func[0]);
+    let negated = func.insn_call(None, &negate, None, &mut [a], flags::CallFlags::empty());
+    let b = func.insn_mul(&func[0], &func[0]);
+    func.insn_return(func.insn_add(negated, func.insn_add(a, b)));
+    assert_eq!(func.check_rebuild(), Err(RebuildError::Calls));
+    assert_eq!(func.check_rebuild().unwrap_err().to_string(), "Function makes calls");
+    let func = func.eliminate_common();
+    assert_eq!(func.dump_stable().matches("imul").count(), 2);
+    func.compile().with(|square: extern fn(i32) -> i32| assert_eq!(square(3), 9));
+}
@@ -0,0 +1,27 @@
+extern crate jit;
+use jit::*;
+
+#[test]
+fn test_bind_and_permute() {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32, i32, f64) -> f64>());
+    let ints = func.insn_sub(&func[0], &func[1]);
+    func.insn_return(func.insn_mul(func.insn_convert(ints, &get::<f64>(), false), &func[2]));
+    let func = func.compile();
+    let swapped = func.curry(&[Bind::Param(1), Bind::Param(0), Bind::Float(0.5)]);
+    assert_eq!(swapped.get_signature().params().count(), 2);
+    let swapped: extern fn(i32, i32) -> f64 = swapped.closure();
+    assert_eq!(swapped(1, 5), 2.0);
+    let twice = func.curry(&[Bind::Param(0), Bind::Int(1), Bind::Param(1)]);
+    let twice: extern fn(i32, f64) -> f64 = twice.closure();
+    assert_eq!(twice(4, 2.0), 6.0);
+}
+
+#[test]
+#[should_panic]
+fn test_wrong_count() {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32, i32) -> i32>());
+    func.insn_return(&func[0]);
+    func.compile().curry(&[Bind::Param(0)]);
+}
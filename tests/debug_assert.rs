@@ -0,0 +1,35 @@
+extern crate jit;
+use jit::*;
+
+fn build(ctx: &mut Context<()>) -> (usize, CompiledFunction) {
+    let func = UncompiledFunction::new(ctx, &get::<fn(i32) -> i32>());
+    func.insn_debug_assert(func.insn_gt(&func[0], func.insn_of(0i32)), "x must be positive");
+    func.insn_return(func.insn_mul(&func[0], func.insn_of(2i32)));
+    let calls = func.blocks().flat_map(|block| block.iter()).filter(|insn| insn.get_name().starts_with("call")).count();
+    (calls, func.compile())
+}
+
+#[test]
+fn test_debug_default() {
+    let ctx = Context::<()>::new();
+    assert_eq!(ctx.is_debug(), cfg!(not(ndebug)));
+}
+
+#[test]
+fn test_assert_in_debug() {
+    let mut ctx = Context::<()>::new();
+    ctx.set_debug(true);
+    let (calls, func) = build(&mut ctx);
+    assert_eq!(calls, 1);
+    func.with(|double: extern fn(i32) -> i32| assert_eq!(double(4), 8));
+}
+
+#[test]
+fn test_nothing_in_release() {
+    let mut ctx = Context::<()>::new();
+    ctx.set_debug(false);
+    assert!(!ctx.is_debug());
+    let (calls, func) = build(&mut ctx);
+    assert_eq!(calls, 0);
+    func.with(|double: extern fn(i32) -> i32| assert_eq!(double(-4), -8));
+}
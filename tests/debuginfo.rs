@@ -0,0 +1,37 @@
+extern crate jit;
+use jit::*;
+
+fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+    haystack.windows(needle.len()).any(|window| window == needle)
+}
+
+#[test]
+fn test_off_by_default() {
+    let mut ctx = Context::<()>::new();
+    assert!(!ctx.get_options().debug_info);
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+    func.insn_return(&func[0]);
+    assert_eq!(func.compile().get_debug_object(), None);
+}
+
+#[test]
+#[cfg(target_arch = "x86_64")]
+fn test_object_registered() {
+    let mut ctx = Context::<()>::new();
+    ctx.set_options(ContextOptions {
+        debug_info: true,
+        .. ContextOptions::default()
+    });
+    ctx.set_source_file(0, "square.src");
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+    func.set_current_location(0, 3, 1);
+    let squared = func.insn_mul(&func[0], &func[0]);
+    func.set_current_location(0, 4, 1);
+    func.insn_return(squared);
+    let func = func.compile();
+    let object = func.get_debug_object().unwrap();
+    assert_eq!(&object[..4], b"\x7fELF");
+    assert!(contains(&object, b"square.src\0"));
+    assert!(contains(&object, b".debug_line\0"));
+    func.with(|square: extern fn(i32) -> i32| assert_eq!(square(7), 49));
+}
@@ -0,0 +1,45 @@
+extern crate jit;
+use jit::*;
+
+#[test]
+fn test_trailing_defaults() {
+    let mut ctx = Context::<()>::new();
+    let sum = {
+        let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32, i32, f64) -> f64>());
+        let ints = func.insn_add(&func[0], func.insn_mul(&func[1], func.insn_of(10i32)));
+        func.insn_return(func.insn_add(func.insn_convert(ints, &get::<f64>(), false), &func[2]));
+        func.compile()
+    };
+    let wrappers = sum.with_defaults(&[None, Some(Bind::Int(2)), Some(Bind::Float(0.5))]);
+    assert_eq!(wrappers.len(), 2);
+    assert_eq!(wrappers[0].get_signature().params().count(), 1);
+    assert_eq!(wrappers[1].get_signature().params().count(), 2);
+    wrappers[0].with(|sum: extern fn(i32) -> f64| {
+        assert_eq!(sum(1), 21.5);
+    });
+    assert_eq!(interp::call(&wrappers[1], &[interp::Value::Int(1), interp::Value::Int(3)]),
+               Ok(interp::Value::Float64(31.5)));
+}
+
+#[test]
+fn test_no_defaults() {
+    let mut ctx = Context::<()>::new();
+    let neg = {
+        let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+        func.insn_return(func.insn_neg(&func[0]));
+        func.compile()
+    };
+    assert!(neg.with_defaults(&[None]).is_empty());
+}
+
+#[test]
+#[should_panic]
+fn test_gap() {
+    let mut ctx = Context::<()>::new();
+    let add = {
+        let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32, i32) -> i32>());
+        func.insn_return(func.insn_add(&func[0], &func[1]));
+        func.compile()
+    };
+    add.with_defaults(&[Some(Bind::Int(1)), None]);
+}
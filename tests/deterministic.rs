@@ -0,0 +1,53 @@
+extern crate jit;
+use jit::*;
+
+fn build(ctx: &mut Context<()>) -> Vec<u8> {
+    let func = UncompiledFunction::new(ctx, &get::<fn(i32, i32) -> i32>());
+    func.insn_if(func.insn_lt(&func[0], &func[1]), || {
+        func.insn_return(func.insn_sub(&func[1], &func[0]));
+    });
+    func.insn_return(func.insn_sub(&func[0], &func[1]));
+    func.compile().code_bytes().to_vec()
+}
+
+#[test]
+fn test_builder_sets_deterministic() {
+    let ctx = ContextBuilder::new().deterministic(true).build::<()>().unwrap();
+    assert!(ctx.is_deterministic());
+    assert!(!Context::<()>::new().is_deterministic());
+}
+
+#[test]
+fn test_async_compiles_now() {
+    let mut ctx = Context::<()>::new();
+    ctx.enable_deterministic();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+    func.insn_return(func.insn_neg(&func[0]));
+    let pending = unsafe { func.compile_async() }.unwrap();
+    assert!(pending.is_done());
+    assert!(pending.get_entry() != 0);
+    pending.wait().unwrap().with(|neg: extern fn(i32) -> i32| assert_eq!(neg(4), -4));
+}
+
+#[test]
+fn test_ignores_hot_profile() {
+    let mut ctx = Context::<()>::new();
+    ctx.set_hot_profile(CallProfile::parse("parse\t5000\n"), 1000);
+    ctx.enable_deterministic();
+    {
+        let func = UncompiledFunction::new(&mut ctx, &get::<fn()>());
+        let level = func.get_optimization_level();
+        assert!(!func.warm_up("parse"));
+        assert_eq!(func.get_optimization_level(), level);
+    }
+    ctx.disable_deterministic();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn()>());
+    assert!(func.warm_up("parse"));
+}
+
+#[test]
+fn test_same_code_every_time() {
+    let mut first = ContextBuilder::new().deterministic(true).build::<()>().unwrap();
+    let mut second = ContextBuilder::new().deterministic(true).build::<()>().unwrap();
+    assert_eq!(build(&mut first), build(&mut second));
+}
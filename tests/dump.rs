@@ -0,0 +1,29 @@
+extern crate jit;
+use jit::*;
+
+fn listing(ctx: &mut Context<()>, offset: i32) -> (String, u64) {
+    let func = UncompiledFunction::new(ctx, &get::<fn(i32) -> i32>());
+    let x = &func[0];
+    let sum = func.insn_add(x, func.insn_of(offset));
+    func.insn_return(sum);
+    (func.dump_stable(), func.fingerprint())
+}
+
+#[test]
+fn test_stable_across_contexts() {
+    let mut ctx1 = Context::<()>::new();
+    let mut ctx2 = Context::<()>::new();
+    let (text1, hash1) = listing(&mut ctx1, 3);
+    let (text2, hash2) = listing(&mut ctx2, 3);
+    assert_eq!(text1, text2);
+    assert_eq!(hash1, hash2);
+    assert!(text1.contains("p0"));
+    assert!(!text1.contains("0x"));
+}
+
+#[test]
+fn test_fingerprint_differs() {
+    let mut ctx1 = Context::<()>::new();
+    let mut ctx2 = Context::<()>::new();
+    assert!(listing(&mut ctx1, 3).1 != listing(&mut ctx2, 4).1);
+}
@@ -0,0 +1,63 @@
+extern crate jit;
+use jit::*;
+
+fn clamp<'a>(value: &'a Val, low: &'a Val, high: &'a Val) -> &'a Val {
+    let result = emit::local(&get::<i32>());
+    emit::store(&result, value);
+    emit::with(|func| {
+        func.insn_if(emit::lt(value, low), || emit::store(&result, low));
+        func.insn_if(emit::gt(value, high), || emit::store(&result, high));
+    });
+    emit::read(&result)
+}
+
+#[test]
+fn test_emit_helpers() {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+    {
+        let _scope = emit::enter(&func);
+        assert!(emit::is_active());
+        let doubled = emit::mul(emit::param(0), emit::of(2i32));
+        emit::ret(clamp(doubled, emit::of(-10i32), emit::of(10i32)));
+    }
+    assert!(!emit::is_active());
+    func.compile().with(|clamped: extern fn(i32) -> i32| {
+        assert_eq!(clamped(3), 6);
+        assert_eq!(clamped(20), 10);
+        assert_eq!(clamped(-8), -10);
+    });
+}
+
+#[test]
+fn test_nested_scopes() {
+    let mut ctx = Context::<()>::new();
+    let outer = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+    let inner = outer.sibling(&get::<fn(i32) -> i32>());
+    {
+        let _outer = emit::enter(&outer);
+        {
+            let _inner = emit::enter(&inner);
+            emit::ret(emit::neg(emit::param(0)));
+        }
+        emit::ret(emit::add(emit::param(0), emit::of(1i32)));
+    }
+    inner.compile().with(|neg: extern fn(i32) -> i32| assert_eq!(neg(4), -4));
+    outer.compile().with(|inc: extern fn(i32) -> i32| assert_eq!(inc(4), 5));
+}
+
+#[test]
+#[should_panic(expected = "without a current function")]
+fn test_no_current_function() {
+    emit::of(1i32);
+}
+
+#[test]
+#[should_panic(expected = "from a function other than the current one")]
+fn test_value_from_other_function() {
+    let mut ctx = Context::<()>::new();
+    let first = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+    let second = first.sibling(&get::<fn(i32) -> i32>());
+    let _scope = emit::enter(&second);
+    emit::neg(&first[0]);
+}
@@ -0,0 +1,83 @@
+extern crate jit;
+use jit::*;
+use std::mem;
+use std::os::raw::c_void;
+
+struct Vm {
+    stack: [i64; 4],
+    calls: i64
+}
+
+#[test]
+fn test_helpers_get_env() {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(&'static i64, i64) -> i64>());
+    func.bind_env(&func[0]);
+    assert!(func.get_env().is_some());
+    let push = func.helper(&get::<fn(i64) -> i64>());
+    assert_eq!(push.get_signature().params().count(), 2);
+    let env = push.get_env().unwrap();
+    let calls = push.insn_load_relative(env, mem::size_of::<[i64; 4]>(), &get::<i64>());
+    push.insn_store_relative(env, mem::size_of::<[i64; 4]>(), push.insn_add(calls, push.insn_of(1i64)));
+    push.insn_store_relative(env, 0, &push[1]);
+    push.insn_return(push.insn_load_relative(env, mem::size_of::<[i64; 4]>(), &get::<i64>()));
+    let push = push.compile();
+    func.insn_call_env(None, &push, &mut [&func[1]], flags::CallFlags::empty());
+    func.insn_return(func.insn_call_env(None, &push, &mut [&func[1]], flags::CallFlags::empty()));
+    let func: extern fn(*mut Vm, i64) -> i64 = func.compile().closure();
+    let mut vm = Vm { stack: [0; 4], calls: 0 };
+    assert_eq!(func(&mut vm, 7), 2);
+    assert_eq!(vm.stack[0], 7);
+    assert_eq!(vm.calls, 2);
+}
+
+#[test]
+fn test_global_env() {
+    let mut ctx = Context::<()>::new();
+    ctx.new_global("counter", &get::<i64>(), 41i64);
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn() -> i64>());
+    func.bind_env(func.insn_global_address("counter"));
+    let bump = func.helper(&get::<fn()>());
+    let env = bump.get_env().unwrap();
+    bump.insn_store_relative(env, 0, bump.insn_add(bump.insn_load_relative(env, 0, &get::<i64>()), bump.insn_of(1i64)));
+    bump.insn_default_return();
+    let bump = bump.compile();
+    func.insn_call_env(None, &bump, &mut [], flags::CallFlags::empty());
+    func.insn_return(func.insn_load_relative(func.get_env().unwrap(), 0, &get::<i64>()));
+    let func: extern fn() -> i64 = func.compile().closure();
+    assert_eq!(func(), 42);
+}
+
+#[test]
+#[should_panic(expected = "pointer")]
+fn test_env_must_be_pointer() {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32)>());
+    func.bind_env(&func[0]);
+}
+
+#[test]
+#[should_panic(expected = "bind_env")]
+fn test_helper_needs_env() {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32)>());
+    func.helper(&get::<fn()>());
+}
+
+#[test]
+fn test_helper_as_callback() {
+    let mut ctx = Context::<()>::new();
+    let read = {
+        let func = UncompiledFunction::new(&mut ctx, &get::<fn(&'static i32)>());
+        func.bind_env(&func[0]);
+        let read = func.helper(&get::<fn(i32) -> i32>());
+        let env = read.get_env().unwrap();
+        read.insn_return(read.insn_add(read.insn_load_relative(env, 0, &get::<i32>()), &read[1]));
+        func.insn_default_return();
+        read.compile()
+    };
+    let mut base = 40i32;
+    let callback = read.as_c_callback(&mut base as *mut i32 as *mut c_void);
+    let callback: extern fn(i32) -> i32 = unsafe { mem::transmute(callback) };
+    assert_eq!(callback(2), 42);
+}
@@ -0,0 +1,45 @@
+extern crate jit;
+use jit::*;
+
+extern fn add(x: i32, y: i32) -> i32 {
+    x + y
+}
+extern fn negate(x: i32) -> i32 {
+    -x
+}
+
+#[test]
+fn test_snapshot_round_trip() {
+    let mut ctx = Context::<()>::new();
+    ctx.register_extern("negate", negate as usize, &get::<fn(i32) -> i32>());
+    ctx.register_extern("add", add as usize, &get::<fn(i32, i32) -> i32>());
+    let snapshot = ctx.extern_snapshot();
+    assert_eq!(snapshot.len(), 2);
+    assert_eq!(ExternSnapshot::parse(&snapshot.to_text()), snapshot);
+    assert!(ctx.check_externs(&snapshot).is_ok());
+}
+
+#[test]
+fn test_mismatch_diff() {
+    let mut build = Context::<()>::new();
+    build.register_extern("negate", negate as usize, &get::<fn(i32) -> i32>());
+    build.register_extern("add", add as usize, &get::<fn(i32, i32) -> i32>());
+    let mut host = Context::<()>::new();
+    host.register_extern("negate", negate as usize, &get::<fn(i64) -> i64>());
+    let mismatch = host.check_externs(&build.extern_snapshot()).unwrap_err();
+    assert_eq!(mismatch.get_missing(), vec!["add"]);
+    assert_eq!(mismatch.get_changed(), vec!["negate"]);
+    let diff = mismatch.to_string();
+    assert!(diff.lines().any(|line| line.starts_with("- add\t")));
+    assert!(diff.lines().any(|line| line.starts_with("+ negate\t")));
+}
+
+#[test]
+fn test_call_extern() {
+    let mut ctx = Context::<()>::new();
+    ctx.register_extern("negate", negate as usize, &get::<fn(i32) -> i32>());
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+    let negated = func.insn_call_extern("negate", &mut [&func[0]], flags::CallFlags::empty());
+    func.insn_return(negated);
+    func.compile().with(|func: extern fn(i32) -> i32| assert_eq!(func(5), -5));
+}
@@ -0,0 +1,39 @@
+extern crate jit;
+use jit::*;
+
+#[test]
+fn test_fenced_after_compile() {
+    let mut point = Type::new_struct(&mut [&get::<i32>(), &get::<i32>()]);
+    point.set_names(&["x", "y"]).unwrap();
+    assert!(!point.is_fenced());
+    {
+        let mut ctx = Context::<()>::new();
+        let sig = Type::new_signature(Abi::CDecl, &get::<i32>(), &mut [&Type::new_pointer(&point)]);
+        let func = UncompiledFunction::new(&mut ctx, &sig);
+        let p_var = func.new_lvalue(&point);
+        func.insn_store(&p_var, func.insn_load_relative(&func[0], 0, &point));
+        let x = func.insn_load_relative(func.insn_address_of(&p_var), 0, &get::<i32>());
+        func.insn_return(x);
+        assert!(!point.is_fenced());
+        func.compile();
+        assert!(point.is_fenced());
+        assert_eq!(point.set_names(&["a", "b"]), Err(TypeFenced));
+        assert!(point.get_field("x").is_some());
+        assert!(point.get_field("a").is_none());
+    }
+    // dropping the context drops its fences
+    assert!(!point.is_fenced());
+    point.set_names(&["a", "b"]).unwrap();
+    assert!(point.get_field("a").is_some());
+}
+
+#[test]
+fn test_unused_types_stay_open() {
+    let mut ctx = Context::<()>::new();
+    let mut other = Type::new_struct(&mut [&get::<f64>()]);
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+    func.insn_return(&func[0]);
+    func.compile();
+    assert!(!other.is_fenced());
+    assert_eq!(other.set_names(&["value"]), Ok(()));
+}
@@ -0,0 +1,60 @@
+extern crate jit;
+use jit::*;
+use std::cell::Cell;
+use std::rc::Rc;
+
+static HANDLED: u8 = 1;
+static TRANSFORMED: u8 = 2;
+
+fn build_divide(ctx: &mut Context<()>) -> CompiledFunction {
+    let func = UncompiledFunction::new(ctx, &get::<fn(i32) -> i32>());
+    func.insn_return(func.insn_div(func.insn_of(1i32), &func[0]));
+    func.compile()
+}
+
+#[test]
+fn test_filters_run_from_last() {
+    let mut ctx = Context::<()>::new();
+    let order = Rc::new(Cell::new(0));
+    let first = order.clone();
+    ctx.push_exception_filter(move |_, kind, object| {
+        assert_eq!(kind, BuiltinException::DivisionByZero);
+        assert_eq!(object as *const u8, &TRANSFORMED as *const u8);
+        first.set(first.get() * 10 + 1);
+        FilterAction::Handle(&HANDLED as *const u8 as *mut _)
+    });
+    let second = order.clone();
+    ctx.push_exception_filter(move |_, _, _| {
+        second.set(second.get() * 10 + 2);
+        FilterAction::Transform(&TRANSFORMED as *const u8 as *mut _)
+    });
+    let call = build_divide(&mut ctx).closure_result::<i32, i32>();
+    assert_eq!(call(0), Err(JitException::Thrown(&HANDLED as *const u8 as *mut _)));
+    assert_eq!(order.get(), 21);
+}
+
+#[test]
+fn test_propagated_to_caller() {
+    let mut ctx = Context::<()>::new();
+    ctx.push_exception_filter(|_, _, _| FilterAction::Propagate);
+    assert!(ctx.pop_exception_filter());
+    assert!(!ctx.pop_exception_filter());
+    ctx.push_exception_filter(|_, _, _| FilterAction::Propagate);
+    let call = build_divide(&mut ctx).closure_result::<i32, i32>();
+    assert_eq!(call(2), Ok(0));
+    assert_eq!(call(0), Err(JitException::Builtin(BuiltinException::DivisionByZero)));
+}
+
+#[test]
+fn test_filter_uses_context() {
+    let mut ctx = Context::<()>::new();
+    let ctx_ptr = &ctx as *const Context<()>;
+    ctx.push_exception_filter(move |_, _, _| {
+        // the context isn't borrowed while the filters run
+        let limits = unsafe { (*ctx_ptr).get_inline_limits() };
+        assert_eq!(limits, InlineLimits::default());
+        FilterAction::Handle(&HANDLED as *const u8 as *mut _)
+    });
+    let call = build_divide(&mut ctx).closure_result::<i32, i32>();
+    assert_eq!(call(0), Err(JitException::Thrown(&HANDLED as *const u8 as *mut _)));
+}
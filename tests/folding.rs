@@ -0,0 +1,27 @@
+extern crate jit;
+use jit::*;
+
+fn build(ctx: &mut Context<()>) -> String {
+    let func = UncompiledFunction::new(ctx, &get::<fn() -> i32>());
+    func.insn_return(func.insn_add(func.insn_of(2i32), func.insn_of(3i32)));
+    func.dump_stable()
+}
+
+#[test]
+fn test_folds_by_default() {
+    let mut ctx = Context::<()>::new();
+    assert!(!build(&mut ctx).contains("iadd"));
+}
+
+#[test]
+fn test_folding_off() {
+    let mut ctx = Context::<()>::new();
+    ctx.set_options(ContextOptions {
+        folding: false,
+        .. ContextOptions::default()
+    });
+    assert!(build(&mut ctx).contains("iadd"));
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn() -> i32>());
+    func.insn_return(func.insn_add(func.insn_of(2i32), func.insn_of(3i32)));
+    func.compile().with(|five: extern fn() -> i32| assert_eq!(five(), 5));
+}
@@ -0,0 +1,65 @@
+extern crate jit;
+use jit::*;
+
+#[test]
+fn test_counter() {
+    let mut ctx = Context::<()>::new();
+    ctx.new_global("calls", &get::<u64>(), 0u64);
+    {
+        let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+        let calls = func.insn_load_global("calls");
+        func.insn_store_global("calls", func.insn_add(calls, func.insn_of(1u64)));
+        func.insn_return(&func[0]);
+        func.compile().with(|id: extern fn(i32) -> i32| {
+            for i in 0..5 {
+                assert_eq!(id(i), i);
+            }
+        });
+    }
+    assert_eq!(ctx.get_global::<u64>("calls"), Some(5));
+}
+
+#[test]
+fn test_shared_between_functions() {
+    let mut ctx = Context::<()>::new();
+    ctx.new_global("scale", &get::<f64>(), 1.0f64);
+    let set = UncompiledFunction::new(&mut ctx, &get::<fn(f64) -> f64>());
+    set.insn_store_global("scale", &set[0]);
+    set.insn_return(&set[0]);
+    let scale = set.sibling(&get::<fn(f64) -> f64>());
+    scale.insn_return(scale.insn_mul(&scale[0], scale.insn_load_global("scale")));
+    let set = set.compile();
+    let scale = scale.compile();
+    set.with(|set: extern fn(f64) -> f64| set(2.5));
+    scale.with(|scale: extern fn(f64) -> f64| assert_eq!(scale(4.0), 10.0));
+}
+
+#[test]
+fn test_store_converts() {
+    let mut ctx = Context::<()>::new();
+    ctx.new_global("byte", &get::<u8>(), 0u8);
+    {
+        let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+        func.insn_store_global("byte", &func[0]);
+        func.insn_return(&func[0]);
+        func.compile().with(|store: extern fn(i32) -> i32| store(0x1ff));
+    }
+    assert_eq!(ctx.get_global::<u8>("byte"), Some(0xff));
+    assert!(ctx.get_global_address("byte").is_some());
+    assert_eq!(ctx.get_global::<u32>("byte"), None);
+}
+
+#[test]
+#[should_panic]
+fn test_size_mismatch() {
+    let mut ctx = Context::<()>::new();
+    ctx.new_global("wrong", &get::<i64>(), 0i32);
+}
+
+#[test]
+#[should_panic]
+fn test_missing() {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+    func.insn_load_global("nothing");
+}
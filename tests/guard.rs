@@ -0,0 +1,67 @@
+extern crate jit;
+use jit::*;
+use std::cell::RefCell;
+use std::{mem, slice};
+
+thread_local!(static EXITS: RefCell<Vec<(usize, Vec<u64>)>> = RefCell::new(Vec::new()));
+
+extern fn record(id: usize, values: *const u64, count: usize) -> u64 {
+    let values = unsafe { slice::from_raw_parts(values, count) }.to_vec();
+    EXITS.with(|exits| exits.borrow_mut().push((id, values)));
+    id as u64
+}
+
+fn take_exits() -> Vec<(usize, Vec<u64>)> {
+    EXITS.with(|exits| exits.borrow_mut().drain(..).collect())
+}
+
+#[test]
+fn test_guard_captures_values() {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32, f64) -> i64>());
+    func.set_guard_handler(record);
+    func.insn_guard(func.insn_geq(&func[0], func.insn_of(0i32)), 7, &[&func[0], &func[1]]);
+    func.insn_guard(func.insn_lt(&func[1], func.insn_of(100.0f64)), 8, &[&func[1]]);
+    func.insn_guard(func.insn_neq(&func[0], func.insn_of(5i32)), 9, &[]);
+    func.insn_return(func.insn_convert(&func[0], &get::<i64>(), false));
+    let call: extern fn(i32, f64) -> i64 = func.compile().closure();
+    assert_eq!(call(3, 1.5), 3);
+    assert!(take_exits().is_empty());
+    assert_eq!(call(-1, 1.5), 7);
+    assert_eq!(call(2, 200.0), 8);
+    assert_eq!(call(5, 0.0), 9);
+    let bits = |value: f64| unsafe { mem::transmute::<f64, u64>(value) };
+    assert_eq!(take_exits(), [
+        (7, vec![0xffff_ffff, bits(1.5)]),
+        (8, vec![bits(200.0)]),
+        (9, vec![])
+    ]);
+}
+
+#[test]
+fn test_guard_in_void_function() {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(&'static i32)>());
+    func.set_guard_handler(record);
+    let value = func.insn_load_relative(&func[0], 0, typecs::get_int());
+    func.insn_guard(func.insn_gt(value, func.insn_of(0i32)), 1, &[value]);
+    func.insn_store_relative(&func[0], 0, func.insn_mul(value, func.insn_of(2i32)));
+    func.insn_default_return();
+    func.compile().with(|func: extern fn(*mut i32)| {
+        let mut number = 4;
+        func(&mut number);
+        assert_eq!(number, 8);
+        number = -4;
+        func(&mut number);
+        assert_eq!(number, -4);
+    });
+    assert_eq!(take_exits(), [(1, vec![0xffff_fffc])]);
+}
+
+#[test]
+#[should_panic(expected = "needs a handler")]
+fn test_guard_needs_handler() {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32)>());
+    func.insn_guard(func.insn_geq(&func[0], func.insn_of(0i32)), 1, &[]);
+}
@@ -0,0 +1,50 @@
+extern crate jit;
+use jit::*;
+
+#[repr(C)]
+struct Object {
+    tag: usize,
+    value: i64
+}
+
+#[test]
+fn test_field_tag() {
+    let mut ctx = Context::<()>::new();
+    let generic = UncompiledFunction::new(&mut ctx, &get::<fn(&'static u8) -> i64>());
+    generic.insn_return(generic.insn_of(-1i64));
+    let func = generic.sibling(&get::<fn(&'static u8) -> i64>());
+    let generic = generic.compile();
+    func.guard_param_type(0, TypeTag::Field { offset: 0, tag: 2 }, &generic);
+    let value = func.insn_load_relative(&func[0], 8, &get::<i64>());
+    func.insn_return(func.insn_add(value, value));
+    func.compile().with(|double: extern fn(&'static u8) -> i64| {
+        let int = Object { tag: 2, value: 21 };
+        let other = Object { tag: 3, value: 21 };
+        assert_eq!(double(unsafe { &*(&int as *const Object as *const u8) }), 42);
+        assert_eq!(double(unsafe { &*(&other as *const Object as *const u8) }), -1);
+    });
+}
+
+#[test]
+fn test_second_param() {
+    let mut ctx = Context::<()>::new();
+    let generic = UncompiledFunction::new(&mut ctx, &get::<fn(i64, u64) -> i64>());
+    generic.insn_return(generic.insn_neg(&generic[0]));
+    let func = generic.sibling(&get::<fn(i64, u64) -> i64>());
+    let generic = generic.compile();
+    func.guard_param_type(1, TypeTag::Bits { mask: 0xff00, tag: 0x100 }, &generic);
+    func.insn_return(&func[0]);
+    let func = func.compile();
+    assert_eq!(interp::call(&func, &[interp::Value::Long(5), interp::Value::ULong(0x1ff)]), Ok(interp::Value::Long(5)));
+    assert_eq!(interp::call(&func, &[interp::Value::Long(5), interp::Value::ULong(0x2ff)]), Ok(interp::Value::Long(-5)));
+}
+
+#[test]
+#[should_panic]
+fn test_mismatched_slow_path() {
+    let mut ctx = Context::<()>::new();
+    let generic = UncompiledFunction::new(&mut ctx, &get::<fn(u64) -> i32>());
+    generic.insn_return(generic.insn_of(0i32));
+    let func = generic.sibling(&get::<fn(u64) -> u64>());
+    func.guard_param_type(0, TypeTag::Bits { mask: 1, tag: 1 }, &generic);
+}
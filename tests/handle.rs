@@ -0,0 +1,63 @@
+extern crate jit;
+use jit::*;
+use std::sync::Arc;
+use std::thread;
+
+fn add_handle() -> CompiledHandle<(i64, i64), i64> {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(i64, i64) -> i64>());
+    func.insn_return(func.insn_add(&func[0], &func[1]));
+    func.compile().to_handle()
+}
+
+#[test]
+fn test_outlives_context() {
+    let handle = add_handle();
+    assert_eq!(handle.call((40, 2)), 42);
+}
+
+#[test]
+fn test_shared_between_threads() {
+    let handle = Arc::new(add_handle());
+    let workers: Vec<_> = (0..8i64).map(|n| {
+        let handle = handle.clone();
+        thread::spawn(move || (0..1000i64).fold(0, |sum, i| handle.call((sum, i * n))))
+    }).collect();
+    for (n, worker) in workers.into_iter().enumerate() {
+        assert_eq!(worker.join().unwrap(), 499500 * n as i64);
+    }
+}
+
+#[test]
+fn test_context_kept_by_clones() {
+    let handle = add_handle();
+    let clone = handle.clone();
+    drop(handle);
+    assert!(clone.get_entry() != 0);
+    assert_eq!(clone.call((1, 2)), 3);
+}
+
+#[test]
+#[should_panic(expected = "don't match the function")]
+fn test_mismatched_handle() {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+    func.insn_return(&func[0]);
+    func.compile().to_handle::<(f64,), f64>();
+}
+
+#[test]
+fn test_dropped_on_other_thread() {
+    let handle = add_handle();
+    thread::spawn(move || assert_eq!(handle.call((2, 3)), 5)).join().unwrap();
+    assert_eq!(destroy_orphans(), 1);
+    assert_eq!(destroy_orphans(), 0);
+}
+
+#[test]
+fn test_orphans_destroyed_by_new_context() {
+    let handle = add_handle();
+    thread::spawn(move || drop(handle)).join().unwrap();
+    drop(Context::<()>::new());
+    assert_eq!(destroy_orphans(), 0);
+}
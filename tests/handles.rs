@@ -0,0 +1,41 @@
+extern crate jit;
+use jit::*;
+use std::ffi::CStr;
+use std::mem;
+
+#[test]
+fn test_handles_are_pointers() {
+    assert_eq!(mem::size_of::<&Val>(), mem::size_of::<usize>());
+    assert_eq!(mem::size_of::<&Ty>(), mem::size_of::<usize>());
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+    let param = &func[0];
+    let copy = param;
+    assert_eq!(param as *const Val, copy as *const Val);
+    assert_eq!(param as *const Val, &func[0] as *const Val);
+}
+
+#[test]
+fn test_interned_names_stay_valid() {
+    let ctx = Context::<()>::new();
+    let names: Vec<String> = (0..1000).map(|index| format!("native_function_number_{}", index)).collect();
+    let c_names: Vec<_> = names.iter().map(|name| ctx.intern_name(name)).collect();
+    for (name, &c_name) in names.iter().zip(c_names.iter()) {
+        assert_eq!(unsafe { CStr::from_ptr(c_name) }.to_str().unwrap(), &**name);
+        assert_eq!(ctx.intern_name(name), c_name);
+    }
+}
+
+#[test]
+fn test_many_kept_strings() {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+    let mut total = func.insn_of(0i32);
+    // enough format strings to fill more than one chunk
+    for index in 0..200 {
+        let format = format!("{:040}: %d\n", index);
+        total = func.insn_add(total, func.insn_printf(&format, &[&func[0]]));
+    }
+    func.insn_return(total);
+    func.compile().with(|print: extern fn(i32) -> i32| assert_eq!(print(7), 200 * 44));
+}
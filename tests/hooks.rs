@@ -0,0 +1,73 @@
+extern crate jit;
+use jit::*;
+use std::cell::Cell;
+
+thread_local!(static DEPTH: Cell<isize> = Cell::new(0));
+thread_local!(static EXITS: Cell<usize> = Cell::new(0));
+
+extern fn enter(_: usize) {
+    DEPTH.with(|depth| depth.set(depth.get() + 1));
+}
+extern fn exit(_: usize) {
+    DEPTH.with(|depth| depth.set(depth.get() - 1));
+    EXITS.with(|exits| exits.set(exits.get() + 1));
+}
+fn depth() -> isize {
+    DEPTH.with(|depth| depth.get())
+}
+fn exits() -> usize {
+    EXITS.with(|exits| exits.get())
+}
+
+#[test]
+fn test_return() {
+    let mut ctx = Context::<()>::new();
+    ctx.set_function_hooks(enter, exit);
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+    func.insn_return(func.insn_neg(&func[0]));
+    func.compile().with(|neg: extern fn(i32) -> i32| assert_eq!(neg(3), -3));
+    assert_eq!(depth(), 0);
+    assert_eq!(exits(), 1);
+}
+
+#[test]
+fn test_tail_call() {
+    let mut ctx = Context::<()>::new();
+    ctx.set_function_hooks(enter, exit);
+    let inc = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+    inc.insn_return(inc.insn_add(&inc[0], inc.insn_of(1i32)));
+    let caller = inc.sibling(&get::<fn(i32) -> i32>());
+    let inc = inc.compile();
+    caller.insn_call(None, &inc, None, &mut [&caller[0]], flags::CallFlags::TAIL);
+    caller.compile().with(|call: extern fn(i32) -> i32| assert_eq!(call(1), 2));
+    assert_eq!(depth(), 0);
+    assert_eq!(exits(), 2);
+}
+
+#[test]
+fn test_exception() {
+    let mut ctx = Context::<()>::new();
+    ctx.set_function_hooks(enter, exit);
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+    func.insn_return(func.insn_div(func.insn_of(12i32), &func[0]));
+    let div = func.compile().closure_result::<i32, i32>();
+    assert_eq!(div(4), Ok(3));
+    assert!(div(0).is_err());
+    assert_eq!(depth(), 0);
+    assert_eq!(exits(), 2);
+}
+
+#[test]
+fn test_rethrow() {
+    let mut ctx = Context::<()>::new();
+    ctx.set_function_hooks(enter, exit);
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+    func.insn_uses_catcher();
+    func.insn_return(func.insn_div(func.insn_of(12i32), &func[0]));
+    func.insn_start_catcher();
+    func.insn_rethrow_unhandled();
+    let div = func.compile().closure_result::<i32, i32>();
+    assert!(div(0).is_err());
+    assert_eq!(depth(), 0);
+    assert_eq!(exits(), 1);
+}
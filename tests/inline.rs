@@ -0,0 +1,58 @@
+extern crate jit;
+use jit::*;
+
+#[test]
+fn test_inline_limits() {
+    let mut ctx = Context::<()>::new();
+    assert_eq!(ctx.get_inline_limits(), InlineLimits::default());
+    ctx.set_inline_limits(InlineLimits { max_size: 4, max_depth: 1 });
+    assert_eq!(ctx.get_inline_limits().max_size, 4);
+}
+
+#[test]
+fn test_inline_markers() {
+    let mut ctx = Context::<()>::new();
+    let add = UncompiledFunction::new(&mut ctx, &get::<fn(i32, i32) -> i32>());
+    assert_eq!(add.get_inline(), Inline::Auto);
+    add.set_inline(Inline::Never);
+    add.insn_return(add.insn_add(&add[0], &add[1]));
+    let func = add.sibling(&get::<fn(i32) -> i32>());
+    let result = func.insn_call_inline(None, &add, &mut [&func[0], &func[0]]);
+    func.insn_return(result);
+    let calls = func.blocks().flat_map(|block| block.iter()).filter(|insn| insn.get_name().starts_with("call")).count();
+    assert_eq!(calls, 1);
+}
+
+#[test]
+fn test_inline_branches() {
+    let mut ctx = Context::<()>::new();
+    let abs = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+    abs.insn_if(abs.insn_lt(&abs[0], abs.insn_of(0i32)), || {
+        abs.insn_return(abs.insn_neg(&abs[0]));
+    });
+    abs.insn_return(&abs[0]);
+    let func = abs.sibling(&get::<fn(i32) -> i32>());
+    let result = func.insn_call_inline(None, &abs, &mut [&func[0]]);
+    func.insn_return(func.insn_mul(result, func.insn_of(2i32)));
+    let calls = func.blocks().flat_map(|block| block.iter()).filter(|insn| insn.get_name().starts_with("call")).count();
+    assert_eq!(calls, 0);
+    func.compile().with(|func: extern fn(i32) -> i32| {
+        assert_eq!(func(-4), 8);
+        assert_eq!(func(5), 10);
+    });
+}
+
+#[test]
+fn test_inline_converts_args() {
+    let mut ctx = Context::<()>::new();
+    let widen = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i64>());
+    widen.set_inline(Inline::Always);
+    widen.insn_return(widen.insn_convert(&widen[0], &get::<i64>(), false));
+    let func = widen.sibling(&get::<fn(i64) -> i64>());
+    let result = func.insn_call_inline(None, &widen, &mut [&func[0]]);
+    func.insn_return(result);
+    func.compile().with(|func: extern fn(i64) -> i64| {
+        assert_eq!(func(0x1_0000_0005), 5);
+        assert_eq!(func(-1), -1);
+    });
+}
@@ -0,0 +1,71 @@
+extern crate jit;
+use jit::*;
+use jit::interp::{InterpError, Value};
+
+#[test]
+fn test_interp_loop() {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+    let total_var = func.new_lvalue(typecs::get_int());
+    let total = total_var.get_local().unwrap();
+    let count_var = func.new_lvalue(typecs::get_int());
+    let count = count_var.get_local().unwrap();
+    func.insn_store(&total_var, func.insn_of(0i32));
+    func.insn_store(&count_var, &func[0]);
+    func.insn_while(|| func.insn_gt(count, func.insn_of(0i32)), || {
+        func.insn_store(&total_var, func.insn_add(total, count));
+        func.insn_store(&count_var, func.insn_sub(count, func.insn_of(1i32)));
+    });
+    func.insn_return(total);
+    assert_eq!(interp::run(&func, &[Value::Int(10)]), Ok(Value::Int(55)));
+    assert_eq!(interp::run_with_limit(&func, &[Value::Int(1000)], 100), Err(InterpError::StepLimit));
+    assert!(func.check_against_interp(&[Value::Int(100)]).is_ok());
+}
+
+#[test]
+fn test_interp_division_by_zero() {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32, i32) -> i32>());
+    func.insn_return(func.insn_div(&func[0], &func[1]));
+    assert_eq!(interp::run(&func, &[Value::Int(7), Value::Int(2)]), Ok(Value::Int(3)));
+    assert_eq!(interp::run(&func, &[Value::Int(7), Value::Int(0)]),
+               Err(InterpError::Exception(BuiltinException::DivisionByZero)));
+}
+
+#[test]
+fn test_interp_floats() {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(f64, f64) -> f64>());
+    func.insn_return(func.insn_sqrt(func.insn_add(func.insn_mul(&func[0], &func[0]), func.insn_mul(&func[1], &func[1]))));
+    assert!(func.check_against_interp(&[Value::Float64(3.0), Value::Float64(4.0)]).is_ok());
+}
+
+#[test]
+fn test_fuzz_against_interp() {
+    let mut ctx = Context::<()>::new();
+    if let Err(failure) = interp::fuzz(&mut ctx, 1, 200) {
+        panic!("{}", failure);
+    }
+}
+
+#[test]
+fn test_interp_rem_ieee_ties_to_even() {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(f64, f64) -> f64>());
+    func.insn_return(func.insn_rem_ieee(&func[0], &func[1]));
+    assert_eq!(interp::run(&func, &[Value::Float64(5.0), Value::Float64(2.0)]), Ok(Value::Float64(1.0)));
+    assert_eq!(interp::run(&func, &[Value::Float64(7.0), Value::Float64(2.0)]), Ok(Value::Float64(-1.0)));
+    assert_eq!(interp::run(&func, &[Value::Float64(-5.0), Value::Float64(2.0)]), Ok(Value::Float64(-1.0)));
+}
+
+#[test]
+fn test_rem_ieee_matches_compiled() {
+    for &(x, y) in &[(5.0, 2.0), (7.0, 2.0), (-5.0, 2.0), (9.0, 6.0), (10.0, 3.0)] {
+        let mut ctx = Context::<()>::new();
+        let func = UncompiledFunction::new(&mut ctx, &get::<fn(f64, f64) -> f64>());
+        func.insn_return(func.insn_rem_ieee(&func[0], &func[1]));
+        if let Err(error) = func.check_against_interp(&[Value::Float64(x), Value::Float64(y)]) {
+            panic!("{} rem {}: {:?}", x, y, error);
+        }
+    }
+}
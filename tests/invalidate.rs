@@ -0,0 +1,100 @@
+extern crate jit;
+use jit::*;
+use std::thread;
+
+#[test]
+fn test_invalidate() {
+    let mut ctx = Context::<()>::new();
+    ctx.enable_invalidation();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+    func.insn_return(func.insn_mul(&func[0], func.insn_of(2i32)));
+    let func = func.compile();
+    assert!(!func.is_invalidated());
+    let double = func.closure_result::<i32, i32>();
+    assert_eq!(double(4), Ok(8));
+    assert!(func.invalidate());
+    assert!(func.is_invalidated());
+    assert_eq!(double(4), Err(JitException::StaleCode));
+}
+
+#[test]
+fn test_invalidate_callee() {
+    let mut ctx = Context::<()>::new();
+    ctx.enable_invalidation();
+    let callee = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+    callee.insn_return(callee.insn_add(&callee[0], callee.insn_of(1i32)));
+    let caller = callee.sibling(&get::<fn(i32) -> i32>());
+    let callee = callee.compile();
+    let result = caller.insn_call(None, &callee, None, &mut [&caller[0]], flags::CallFlags::empty());
+    caller.insn_return(result);
+    let caller = caller.compile();
+    let call = caller.closure_result::<i32, i32>();
+    assert_eq!(call(1), Ok(2));
+    callee.invalidate();
+    assert!(!caller.is_invalidated());
+    assert_eq!(call(1), Err(JitException::StaleCode));
+}
+
+#[test]
+fn test_invalidation_disabled() {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+    func.insn_return(&func[0]);
+    let func = func.compile();
+    assert!(!func.invalidate());
+    assert!(!func.is_invalidated());
+    func.with(|id: extern fn(i32) -> i32| assert_eq!(id(3), 3));
+}
+
+#[test]
+fn test_build_after_invalidation() {
+    let mut ctx = Context::<()>::new();
+    ctx.enable_invalidation();
+    let old = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+    old.insn_return(old.insn_neg(&old[0]));
+    let replacement = old.sibling(&get::<fn(i32) -> i32>());
+    let old = old.compile();
+    assert!(old.invalidate());
+    replacement.insn_return(replacement.insn_sub(replacement.insn_of(0i32), &replacement[0]));
+    let replacement = replacement.compile();
+    assert!(!replacement.is_invalidated());
+    let negate = replacement.closure_result::<i32, i32>();
+    assert_eq!(negate(5), Ok(-5));
+    assert_eq!(old.closure_result::<i32, i32>()(5), Err(JitException::StaleCode));
+}
+
+#[test]
+fn test_invalidate_from_other_thread() {
+    let mut ctx = Context::<()>::new();
+    ctx.enable_invalidation();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+    func.insn_return(&func[0]);
+    let func = func.compile();
+    let invalidator = func.invalidator().unwrap();
+    assert!(!invalidator.is_invalidated());
+    let call = func.closure_result::<i32, i32>();
+    assert_eq!(call(3), Ok(3));
+    thread::spawn(move || invalidator.invalidate()).join().unwrap();
+    assert!(func.is_invalidated());
+    assert_eq!(call(3), Err(JitException::StaleCode));
+}
+
+#[test]
+fn test_stale_code_caught_in_generated_code() {
+    let mut ctx = Context::<()>::new();
+    ctx.enable_invalidation();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+    func.insn_return(&func[0]);
+    let caller = func.sibling(&get::<fn(i32) -> i32>());
+    let func = func.compile();
+    // the catcher sees the wrapper's own object, rather than a builtin code
+    // LibJIT doesn't know
+    caller.insn_uses_catcher();
+    caller.insn_return(caller.insn_call(None, &func, None, &mut [&caller[0]], flags::CallFlags::empty()));
+    let thrown = caller.insn_start_catcher();
+    caller.insn_return(caller.insn_convert(caller.insn_neq(thrown, caller.insn_of(0usize)), &get::<i32>(), false));
+    let caller = caller.compile();
+    caller.with(|call: extern fn(i32) -> i32| assert_eq!(call(5), 5));
+    assert!(func.invalidate());
+    caller.with(|call: extern fn(i32) -> i32| assert_eq!(call(5), 1));
+}
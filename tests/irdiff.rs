@@ -0,0 +1,56 @@
+extern crate jit;
+use jit::*;
+
+#[test]
+fn test_diff_listings() {
+    let old = "function a\nblock 0:\n    v0 = add p0, p1\n    return v0\n";
+    let new = "function a\nblock 0:\n    v0 = mul p0, p1\n    check_null p0\n    return v0\n";
+    let diff = IrDiff::new(old, new);
+    assert_eq!(diff.changes, vec![
+        IrChange::Changed {
+            old_line: 2,
+            new_line: 2,
+            old: "    v0 = add p0, p1".to_owned(),
+            new: "    v0 = mul p0, p1".to_owned()
+        },
+        IrChange::Added {
+            line: 3,
+            text: "    check_null p0".to_owned()
+        }
+    ]);
+    assert_eq!(diff.to_string(), "~ v0 = add p0, p1 => v0 = mul p0, p1\n+ check_null p0\n");
+    assert!(IrDiff::new(old, old).is_empty());
+    assert_eq!(IrDiff::new(new, old).removed(), 1);
+}
+
+#[test]
+fn test_diff_tiers() {
+    let mut ctx = Context::<()>::new();
+    ctx.enable_ir_history();
+    let baseline = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+    let doubled = baseline.insn_add(&baseline[0], &baseline[0]);
+    baseline.insn_return(baseline.insn_add(doubled, baseline.insn_of(1i32)));
+    let tier = baseline.sibling(&get::<fn(i32) -> i32>());
+    let baseline = baseline.compile();
+    assert!(baseline.get_ir().is_some());
+    assert!(baseline.diff_ir().is_none());
+    tier.recompiles(&baseline);
+    let doubled = tier.insn_add(&tier[0], &tier[0]);
+    tier.insn_return(doubled);
+    let tier = tier.compile();
+    let diff = tier.diff_ir().unwrap();
+    // the add of one is gone, and the return gives the first add instead
+    assert_eq!(diff.removed(), 1);
+    assert_eq!(diff.changed(), 1);
+    assert_eq!(diff.added(), 0);
+}
+
+#[test]
+fn test_no_history() {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+    func.insn_return(&func[0]);
+    let func = func.compile();
+    assert!(func.get_ir().is_none());
+    assert!(func.diff_ir().is_none());
+}
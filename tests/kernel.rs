@@ -0,0 +1,52 @@
+extern crate jit;
+use jit::*;
+
+/// `x * x - 0.5`, cast like `as` for integers
+fn square_less_half<'a, T: JitNum>(func: &UncompiledFunction<'a>) {
+    let square = func.insn_mul(&func[0], &func[0]);
+    func.insn_return(func.insn_sub(square, T::insn_lit(func, 0.5)));
+}
+
+/// The bigger of the two parameters, cast to `T` from `f64`
+fn bigger<'a, T: JitNum>(func: &UncompiledFunction<'a>) {
+    let a = func.insn_convert(&func[0], &get::<f64>(), false);
+    let b = func.insn_convert(&func[1], &get::<f64>(), false);
+    func.insn_return(T::insn_cast(func, func.insn_max(a, b)));
+}
+
+#[test]
+fn test_kernel_per_type() {
+    let mut ctx = Context::<()>::new();
+    let call: extern fn(i32) -> i32 = kernel::<i32, _, _>(&mut ctx, 1, square_less_half::<i32>).closure();
+    assert_eq!(call(3), 9);
+    let mut ctx = Context::<()>::new();
+    let call: extern fn(u64) -> u64 = kernel::<u64, _, _>(&mut ctx, 1, square_less_half::<u64>).closure();
+    assert_eq!(call(1 << 20), 1 << 40);
+    let mut ctx = Context::<()>::new();
+    let call: extern fn(f32) -> f32 = kernel::<f32, _, _>(&mut ctx, 1, square_less_half::<f32>).closure();
+    assert_eq!(call(3.0), 8.5);
+    let mut ctx = Context::<()>::new();
+    let call: extern fn(f64) -> f64 = kernel::<f64, _, _>(&mut ctx, 1, square_less_half::<f64>).closure();
+    assert_eq!(call(-1.5), 1.75);
+}
+
+#[test]
+fn test_kernel_casts() {
+    let mut ctx = Context::<()>::new();
+    let call: extern fn(isize, isize) -> isize = kernel::<isize, _, _>(&mut ctx, 2, bigger::<isize>).closure();
+    assert_eq!(call(-7, -3), -3);
+    let mut ctx = Context::<()>::new();
+    let call: extern fn(f32, f32) -> f32 = kernel::<f32, _, _>(&mut ctx, 2, bigger::<f32>).closure();
+    assert_eq!(call(2.5, -4.0), 2.5);
+}
+
+#[test]
+fn test_num_types() {
+    assert_eq!(i32::from_f64(2.9), 2);
+    assert!(f32::is_float() && f64::is_float());
+    assert!(!i64::is_float() && !usize::is_float());
+    assert_eq!(<u64 as JitNum>::jit_type().get_kind(), TypeKind::ULong);
+    let sig = f32::signature(3);
+    assert_eq!(sig.params().count(), 3);
+    assert_eq!(sig.get_return().unwrap().get_kind(), TypeKind::Float32);
+}
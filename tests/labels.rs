@@ -0,0 +1,42 @@
+extern crate jit;
+use jit::*;
+
+#[test]
+fn test_placed_labels() {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+    let mut done = Label::new(&func);
+    func.insn_branch_if(&func[0], &mut done);
+    func.insn_return(func.insn_of(1i32));
+    func.insn_label(&mut done);
+    func.insn_return(func.insn_of(2i32));
+    assert!(func.unplaced_labels().is_empty());
+    func.compile().with(|f: extern fn(i32) -> i32| {
+        assert_eq!(f(0), 1);
+        assert_eq!(f(5), 2);
+    });
+}
+
+#[test]
+fn test_unplaced_labels_reported_once() {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+    let mut first = Label::new(&func);
+    let mut second = Label::new(&func);
+    func.name_label(&second, "exit");
+    func.insn_branch_if(&func[0], &mut first);
+    func.set_current_location(2, 7, 1);
+    func.insn_branch_if_not(&func[0], &mut second);
+    func.insn_branch(&mut first);
+    let unplaced = func.unplaced_labels();
+    assert_eq!(unplaced.len(), 2);
+    assert_eq!(unplaced[0].label, *first);
+    assert_eq!(unplaced[0].name, None);
+    assert_eq!(unplaced[0].location, None);
+    assert_eq!(unplaced[1].label, *second);
+    assert_eq!(unplaced[1].name, Some("exit".to_owned()));
+    assert_eq!(unplaced[1].location, Some(SourceLocation { file: 2, line: 7, column: 1 }));
+    let error = func.try_compile().err().unwrap();
+    assert_eq!(error, CompileError::UnplacedLabels(unplaced));
+    assert!(error.to_string().contains("(exit) branched to at 2:7:1"));
+}
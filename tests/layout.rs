@@ -0,0 +1,41 @@
+extern crate jit;
+use jit::*;
+use std::ptr;
+
+static mut OBJECT: [u64; 2] = [!0, !0];
+
+extern fn alloc_static(size: usize) -> *mut u8 {
+    assert_eq!(size, 16);
+    unsafe { OBJECT.as_mut_ptr() as *mut u8 }
+}
+extern fn alloc_fails(_: usize) -> *mut u8 {
+    ptr::null_mut()
+}
+
+#[test]
+fn test_layout_offsets() {
+    let layout = Layout::new(&[typecs::get_short(), typecs::get_float64(), typecs::get_ubyte()]);
+    assert_eq!(layout.len(), 3);
+    assert_eq!(layout.get_offset(1), 8);
+    assert_eq!(layout.get_padding(1), 6);
+    assert_eq!(layout.get_trailing_padding(), 7);
+    assert_eq!(layout.get_size(), 24);
+    assert_eq!(layout.to_type().get_size(), 24);
+}
+
+#[test]
+fn test_constructor_zeroes() {
+    let mut ctx = Context::<()>::new();
+    let layout = Layout::new(&[typecs::get_int(), typecs::get_float64()]);
+    let new: extern fn() -> *mut u8 = layout.build_constructor(&mut ctx, alloc_static).closure();
+    assert_eq!(new(), unsafe { OBJECT.as_mut_ptr() as *mut u8 });
+    assert_eq!(unsafe { OBJECT }, [0, 0]);
+}
+
+#[test]
+fn test_constructor_checks_null() {
+    let mut ctx = Context::<()>::new();
+    let layout = Layout::new(&[typecs::get_int(), typecs::get_float64()]);
+    let new: extern fn() -> *mut u8 = layout.build_constructor(&mut ctx, alloc_fails).closure();
+    assert!(new().is_null());
+}
@@ -0,0 +1,58 @@
+extern crate jit;
+use jit::*;
+
+/// Build a loop adding `x * 3 + 1` to a total until it reaches `limit`
+fn build<'a>(func: &UncompiledFunction<'a>) {
+    let total_var = func.new_lvalue(&get::<i32>());
+    let total = total_var.get_local().unwrap();
+    func.insn_store(&total_var, func.insn_of(0i32));
+    func.insn_while(|| func.insn_lt(total, &func[1]), || {
+        let step = func.insn_add(func.insn_mul(&func[0], func.insn_of(3i32)), func.insn_of(1i32));
+        func.insn_store(&total_var, func.insn_add(total, step));
+    });
+    func.insn_return(total);
+}
+
+fn loop_body(listing: &str) -> String {
+    let start = listing.find("block 2").unwrap();
+    listing[start..].to_owned()
+}
+
+#[test]
+fn test_invariants_hoisted() {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32, i32) -> i32>());
+    build(&func);
+    assert!(loop_body(&func.dump_stable()).contains("imul"));
+    let func = func.hoist_invariants();
+    let listing = func.dump_stable();
+    assert!(listing.contains("imul"));
+    assert!(!loop_body(&listing).contains("imul"));
+}
+
+#[test]
+fn test_keeps_results_and_counts() {
+    let mut ctx = Context::<()>::new();
+    ctx.enable_call_counts();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32, i32) -> i32>());
+    build(&func);
+    let func = func.hoist_invariants().compile();
+    assert_eq!(func.to_handle::<(i32, i32), i32>().call((2, 20)), 21);
+    assert_eq!(func.get_call_count(), Some(1));
+}
+
+#[test]
+fn test_division_left_in_loop() {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32, i32) -> i32>());
+    let total_var = func.new_lvalue(&get::<i32>());
+    let total = total_var.get_local().unwrap();
+    func.insn_store(&total_var, func.insn_of(0i32));
+    func.insn_while(|| func.insn_lt(total, &func[1]), || {
+        func.insn_store(&total_var, func.insn_add(total, func.insn_div(func.insn_of(100i32), &func[0])));
+    });
+    func.insn_return(total);
+    let before = func.dump_stable();
+    let func = func.hoist_invariants();
+    assert_eq!(func.dump_stable(), before);
+}
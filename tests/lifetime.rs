@@ -0,0 +1,49 @@
+extern crate jit;
+use jit::*;
+
+#[test]
+fn test_abandon_on_drop() {
+    let mut ctx = Context::<()>::new();
+    {
+        let func = UncompiledFunction::new(&mut ctx, &get::<fn() -> i32>());
+        assert_eq!(func.get_lifetime(), Lifetime::Abandon);
+        func.insn_return(func.insn_of(1i32));
+    }
+    assert_eq!(ctx.functions().count(), 0);
+}
+
+#[test]
+fn test_context_owned_survives_drop() {
+    let mut ctx = Context::<()>::new();
+    {
+        let func = UncompiledFunction::new_with_lifetime(&mut ctx, &get::<fn() -> i32>(),
+                                                         Lifetime::ContextOwned);
+        func.insn_return(func.insn_of(1i32));
+    }
+    assert_eq!(ctx.functions().count(), 1);
+}
+
+#[test]
+fn test_weak_function_cache() {
+    use std::collections::HashMap;
+    let mut compiled_ctx = Context::<()>::new();
+    let mut abandoned_ctx = Context::<()>::new();
+    let mut cache:HashMap<u64, WeakFunction> = HashMap::new();
+    {
+        let func = UncompiledFunction::new(&mut compiled_ctx, &get::<fn(i32) -> i32>());
+        func.insn_return(func.insn_add(&func[0], func.insn_of(1i32)));
+        cache.insert(1, func.downgrade());
+        assert!(cache[&1].upgrade_compiled().is_none());
+        func.compile();
+    }
+    {
+        let func = UncompiledFunction::new(&mut abandoned_ctx, &get::<fn()>());
+        cache.insert(2, func.downgrade());
+    }
+    assert!(cache[&1].is_alive());
+    cache[&1].upgrade_compiled().unwrap().with(|func: extern fn(i32) -> i32| {
+        assert_eq!(func(41), 42);
+    });
+    assert!(!cache[&2].is_alive());
+    assert!(cache[&2].upgrade().is_none());
+}
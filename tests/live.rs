@@ -0,0 +1,45 @@
+extern crate jit;
+use jit::*;
+
+#[test]
+fn test_values_until_compiled() {
+    let mut ctx = Context::<()>::new();
+    {
+        let mut func = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+        func.set_lifetime(Lifetime::ContextOwned);
+        let local_var = func.new_lvalue(&get::<i32>());
+        let local = local_var.get_local().unwrap();
+        func.insn_store(&local_var, &func[0]);
+        let other = func.sibling(&get::<fn(i32) -> i32>());
+        other.insn_return(Val::new(&other, &get::<i32>()));
+        other.compile();
+        func.insn_return(local);
+    }
+    let live = ctx.live_objects();
+    assert_eq!(live.functions, 2);
+    assert_eq!(live.values, 1);
+}
+
+#[test]
+fn test_abandoned() {
+    let mut ctx = Context::<()>::new();
+    {
+        let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+        Val::new(&func, &get::<i32>());
+    }
+    let live = ctx.live_objects();
+    assert_eq!((live.functions, live.values), (0, 0));
+}
+
+#[test]
+fn test_types() {
+    let ctx = Context::<()>::new();
+    assert_eq!(ctx.live_objects().types, 0);
+    let sig = get::<fn(i32, f64) -> i32>();
+    let copy = sig.clone();
+    assert_eq!(ctx.live_objects().types, 2);
+    drop(sig);
+    assert_eq!(ctx.live_objects().types, 1);
+    drop(copy);
+    assert_eq!(ctx.live_objects().types, 0);
+}
@@ -0,0 +1,48 @@
+extern crate jit;
+use jit::*;
+
+extern fn innermost_line(ctx: usize) -> u32 {
+    let ctx = unsafe { &*(ctx as *const Context<()>) };
+    match ctx.backtrace().first() {
+        Some(&(_, Some(location))) => location.line,
+        _ => 0
+    }
+}
+
+#[test]
+fn test_locations_deduplicated() {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+    func.set_current_location(0, 1, 1);
+    func.set_current_location(0, 1, 1);
+    func.set_current_location(0, 2, 4);
+    func.insn_return(&func[0]);
+    assert_eq!(func.get_locations(), vec![
+        SourceLocation { file: 0, line: 1, column: 1 },
+        SourceLocation { file: 0, line: 2, column: 4 }
+    ]);
+}
+
+#[test]
+fn test_dump_shows_location() {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+    func.set_current_location(3, 7, 9);
+    func.insn_return(&func[0]);
+    assert!(func.dump_stable().contains("mark_offset 0i32 ; at 3:7:9"));
+}
+
+#[test]
+fn test_backtrace() {
+    let mut ctx = Context::<()>::new();
+    let address = &ctx as *const Context<()> as usize;
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(usize) -> u32>());
+    func.set_current_location(0, 42, 1);
+    let line = func.insn_call_native1(Some("innermost_line"), innermost_line,
+                                      &get::<fn(usize) -> u32>(), [&func[0]],
+                                      CallFlags::empty());
+    func.insn_return(line);
+    func.compile().with(|line: extern fn(usize) -> u32| {
+        assert_eq!(line(address), 42);
+    });
+}
@@ -0,0 +1,60 @@
+#![cfg(feature = "logging")]
+extern crate jit;
+extern crate log;
+use jit::*;
+use log::{Log, LogLevelFilter, LogMetadata, LogRecord};
+use std::cell::RefCell;
+use std::sync::{Once, ONCE_INIT};
+
+thread_local!(static MESSAGES: RefCell<Vec<String>> = RefCell::new(Vec::new()));
+
+struct Recorder;
+impl Log for Recorder {
+    fn enabled(&self, metadata: &LogMetadata) -> bool {
+        metadata.target() == "jit"
+    }
+    fn log(&self, record: &LogRecord) {
+        if self.enabled(record.metadata()) {
+            MESSAGES.with(|messages| messages.borrow_mut().push(format!("{}", record.args())));
+        }
+    }
+}
+
+static INIT: Once = ONCE_INIT;
+
+/// Start recording this thread's messages, and take the ones so far
+fn take_messages() -> Vec<String> {
+    INIT.call_once(|| {
+        log::set_logger(|max| {
+            max.set(LogLevelFilter::Trace);
+            Box::new(Recorder)
+        }).unwrap();
+    });
+    MESSAGES.with(|messages| messages.borrow_mut().drain(..).collect())
+}
+
+#[test]
+fn test_logs_exceptions_without_filters() {
+    take_messages();
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+    func.insn_return(func.insn_div(func.insn_of(1i32), &func[0]));
+    let call = func.compile().closure_result::<i32, i32>();
+    assert!(call(0).is_err());
+    let messages = take_messages();
+    let raised: Vec<&String> = messages.iter().filter(|message| message.starts_with("exception raised")).collect();
+    assert_eq!(raised.len(), 1);
+    assert!(raised[0].ends_with(&BuiltinException::DivisionByZero.to_string()));
+}
+
+#[test]
+fn test_logs_compiles() {
+    take_messages();
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn() -> i32>());
+    func.insn_return(func.insn_of(1i32));
+    func.compile();
+    let messages = take_messages();
+    assert!(messages.iter().any(|message| message.starts_with("created function")));
+    assert!(messages.iter().any(|message| message.starts_with("compiled function")));
+}
@@ -0,0 +1,67 @@
+extern crate jit;
+use jit::*;
+
+#[test]
+fn test_param_lvalue() {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+    let param = func.param_lvalue(0);
+    assert_eq!(param.get_local(), Some(&func[0]));
+    let value = func.insn_read(&param).get();
+    func.insn_store(&param, func.insn_mul(value, func.insn_of(3i32)));
+    func.insn_return(&func[0]);
+    func.compile().with(|triple: extern fn(i32) -> i32| assert_eq!(triple(5), 15));
+}
+
+#[test]
+fn test_memory_lvalue() {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(&'static i32)>());
+    let second = func.deref_lvalue(&func[0], 4, &get::<i32>());
+    assert!(second.get_local().is_none());
+    assert_eq!(second.get_type().get_kind(), TypeKind::Int);
+    let first = func.deref_lvalue(&func[0], 0, &get::<i32>());
+    func.insn_store(&second, func.insn_read(&first).get());
+    // stores through the address of the lvalue go to the same place
+    let address = func.insn_address_of(&first);
+    func.insn_store_relative(address, 0, func.insn_of(7i32));
+    func.insn_default_return();
+    let mut pair = [3i32, 0i32];
+    func.compile().with(|copy: extern fn(*mut i32)| copy(pair.as_mut_ptr()));
+    assert_eq!(pair, [7, 3]);
+}
+
+#[test]
+fn test_local_address() {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+    let local = func.new_lvalue(&get::<i32>());
+    func.insn_store(&local, &func[0]);
+    let address = func.insn_address_of(&local);
+    func.insn_store_relative(address, 0, func.insn_add(func.insn_load_relative(address, 0, &get::<i32>()), func.insn_of(1i32)));
+    func.insn_return(func.insn_read(&local).get());
+    func.compile().with(|inc: extern fn(i32) -> i32| assert_eq!(inc(41), 42));
+}
+
+#[test]
+fn test_read_is_a_copy() {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(&'static i32) -> i32>());
+    let local = func.new_lvalue(&get::<i32>());
+    func.insn_store(&local, func.insn_of(1i32));
+    let local_before = func.insn_read(&local).get();
+    func.insn_store(&local, func.insn_of(20i32));
+    let memory = func.deref_lvalue(&func[0], 0, &get::<i32>());
+    let memory_before = func.insn_read(&memory).get();
+    func.insn_store(&memory, func.insn_of(300i32));
+    // the reads keep the values from before the stores, and the local
+    // itself sees the last one
+    let before = func.insn_add(local_before, memory_before);
+    let after = func.insn_add(local.get_local().unwrap(), func.insn_read(&memory).get());
+    func.insn_return(func.insn_add(func.insn_mul(before, func.insn_of(1000i32)), after));
+    func.compile().with(|read: extern fn(*mut i32) -> i32| {
+        let mut value = 4000;
+        assert_eq!(read(&mut value), 4001 * 1000 + 320);
+        assert_eq!(value, 300);
+    });
+}
@@ -0,0 +1,52 @@
+#![cfg(feature = "mathexpr")]
+extern crate jit;
+use jit::*;
+
+fn eval(text: &str, x: f64, y: f64) -> f64 {
+    let mut ctx = Context::<()>::new();
+    let func = mathexpr::compile(&mut ctx, text).unwrap();
+    let mut result = [0.0];
+    func.apply_batch(&[(x, y)], &mut result);
+    result[0]
+}
+
+#[test]
+fn test_precedence() {
+    assert_eq!(eval("1 + 2 * 3", 0.0, 0.0), 7.0);
+    assert_eq!(eval("(1 + 2) * 3", 0.0, 0.0), 9.0);
+    assert_eq!(eval("2 ^ 3 ^ 2", 0.0, 0.0), 512.0);
+    assert_eq!(eval("-x^2", 3.0, 0.0), -9.0);
+    assert_eq!(eval("x - y - 1", 10.0, 4.0), 5.0);
+    assert_eq!(eval("x / y / 2", 12.0, 3.0), 2.0);
+}
+
+#[test]
+fn test_functions() {
+    assert_eq!(eval("2*x + sin(y)", 1.5, 0.0), 3.0);
+    assert_eq!(eval("sqrt(x) + abs(y)", 16.0, -2.0), 6.0);
+    assert_eq!(eval("max(x, y) - min(x, y)", 2.0, 7.0), 5.0);
+    assert_eq!(eval("floor(x) + ceil(y)", 1.5, 1.5), 3.0);
+    assert_eq!(eval("1.5e2 + 2E-1", 0.0, 0.0), 150.2);
+    assert!((eval("cos(pi)", 0.0, 0.0) + 1.0).abs() < 1e-12);
+}
+
+#[test]
+fn test_vars() {
+    let mut ctx = Context::<()>::new();
+    let func = mathexpr::compile_vars(&mut ctx, "a*b*c", &["a", "b", "c"]).unwrap();
+    let mut result = [0.0];
+    func.apply_batch(&[(2.0, 3.0, 4.0)], &mut result);
+    assert_eq!(result[0], 24.0);
+}
+
+#[test]
+fn test_errors() {
+    let mut ctx = Context::<()>::new();
+    let err = |ctx: &mut Context<()>, text| mathexpr::compile(ctx, text).err().unwrap();
+    assert_eq!(err(&mut ctx, "x + z").position, 4);
+    assert_eq!(err(&mut ctx, "foo(x)").message, "unknown function 'foo'");
+    assert_eq!(err(&mut ctx, "pow(x)").message, "pow takes 2 arguments, got 1");
+    assert_eq!(err(&mut ctx, "(x + y").message, "expected ')'");
+    assert_eq!(err(&mut ctx, "x y").position, 2);
+    assert_eq!(err(&mut ctx, "").message, "unexpected end of expression");
+}
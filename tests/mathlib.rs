@@ -0,0 +1,63 @@
+extern crate jit;
+use jit::*;
+
+#[test]
+fn test_new_contexts_fall_back_for_missing() {
+    let ctx = Context::<()>::new();
+    assert_eq!(ctx.get_math_fallbacks(), missing_math());
+    assert_eq!(missing_math(), missing_math());
+}
+
+#[test]
+fn test_unary_fallbacks() {
+    let mut ctx = Context::<()>::new();
+    ctx.set_math_fallbacks(&["sinh", "cosh", "rint"]);
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(f64) -> f64>());
+    let sum = func.insn_add(func.insn_sinh(&func[0]), func.insn_cosh(&func[0]));
+    func.insn_return(func.insn_add(sum, func.insn_rint(&func[0])));
+    func.compile().with(|f: extern fn(f64) -> f64| {
+        assert!((f(1.0) - (1f64.exp() + 1.0)).abs() < 1e-12);
+        assert!((f(2.5) - (2.5f64.exp() + 2.0)).abs() < 1e-12);
+    });
+}
+
+#[test]
+fn test_binary_fallbacks_keep_type() {
+    let mut ctx = Context::<()>::new();
+    ctx.set_math_fallbacks(&["atan2", "pow"]);
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(f32, f32) -> f32>());
+    let angle = func.insn_atan2(&func[0], &func[1]);
+    assert_eq!(angle.get_type().get_kind(), TypeKind::Float32);
+    func.insn_return(func.insn_add(angle, func.insn_pow(&func[1], func.insn_of(0.5f32))));
+    let f = func.compile().to_handle::<(f32, f32), f32>();
+    assert_eq!(f.call((0.0, 4.0)), 2.0);
+}
+
+#[test]
+fn test_fallbacks_match_intrinsics() {
+    let mut ctx = Context::<()>::new();
+    ctx.set_math_fallbacks(&["atan2"]);
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(f64, f64) -> f64>());
+    func.insn_return(func.insn_atan2(&func[0], &func[1]));
+    let atan2 = func.compile().to_handle::<(f64, f64), f64>();
+    assert_eq!(atan2.call((1.0, -1.0)), 1f64.atan2(-1.0));
+}
+
+#[test]
+#[should_panic(expected = "no math native")]
+fn test_unknown_fallback() {
+    let mut ctx = Context::<()>::new();
+    ctx.set_math_fallbacks(&["gamma"]);
+}
+
+#[test]
+fn test_new_contexts_compute_whatever_is_linked() {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(f64) -> f64>());
+    let sum = func.insn_add(func.insn_sinh(&func[0]), func.insn_cosh(&func[0]));
+    func.insn_return(func.insn_add(sum, func.insn_pow(&func[0], func.insn_of(2.0f64))));
+    func.compile().with(|f: extern fn(f64) -> f64| {
+        assert!((f(1.0) - (1f64.exp() + 1.0)).abs() < 1e-12);
+        assert!((f(3.0) - (3f64.exp() + 9.0)).abs() < 1e-12);
+    });
+}
@@ -0,0 +1,68 @@
+extern crate jit;
+use jit::*;
+
+#[test]
+fn test_move_to_end() {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+    let (mut cold, mut hot) = (Label::new(&func), Label::new(&func));
+    func.insn_branch_if(func.insn_eq(&func[0], func.insn_of(0i32)), &mut cold);
+    func.insn_branch(&mut hot);
+    func.insn_label(&mut cold);
+    func.insn_return(func.insn_of(-1i32));
+    func.insn_label(&mut hot);
+    func.insn_return(func.insn_add(&func[0], func.insn_of(1i32)));
+    func.move_blocks_to_end(&cold, &hot).unwrap();
+    let labels: Vec<Option<u64>> = func.blocks().map(|block| block.get_label()).collect();
+    let cold_at = labels.iter().position(|&label| label == Some(*cold)).unwrap();
+    let hot_at = labels.iter().position(|&label| label == Some(*hot)).unwrap();
+    assert!(cold_at > hot_at);
+    func.compile().with(|inc: extern fn(i32) -> i32| {
+        assert_eq!(inc(0), -1);
+        assert_eq!(inc(4), 5);
+    });
+}
+
+#[test]
+fn test_falls_into_range() {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+    let (mut from, mut to) = (Label::new(&func), Label::new(&func));
+    func.insn_branch_if(func.insn_eq(&func[0], func.insn_of(0i32)), &mut to);
+    func.insn_label(&mut from);
+    func.insn_return(func.insn_of(1i32));
+    func.insn_label(&mut to);
+    func.insn_return(func.insn_of(2i32));
+    assert_eq!(func.move_blocks_to_end(&from, &to), Err(MoveError::FallsThrough));
+}
+
+#[test]
+fn test_bad_ranges() {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn()>());
+    let (mut first, mut second, unplaced) = (Label::new(&func), Label::new(&func), Label::new(&func));
+    func.insn_label(&mut first);
+    func.insn_default_return();
+    func.insn_label(&mut second);
+    func.insn_default_return();
+    assert_eq!(func.move_blocks_to_end(&second, &first), Err(MoveError::Empty));
+    assert_eq!(func.move_blocks_to_end(&first, &unplaced), Err(MoveError::Unplaced(*unplaced)));
+    assert!(func.get_block(&unplaced).is_none());
+}
+
+#[test]
+fn test_move_to_start() {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+    let result_var = func.new_lvalue(&get::<i32>());
+    let result = result_var.get_local().unwrap();
+    let (mut setup, mut end) = (Label::new(&func), Label::new(&func));
+    func.insn_return(func.insn_add(result, &func[0]));
+    // the setup is only found to be needed once the body has been built
+    func.insn_label(&mut setup);
+    func.insn_store(&result_var, func.insn_of(100i32));
+    func.insn_label(&mut end);
+    func.insn_default_return();
+    func.move_blocks_to_start(&setup, &end).unwrap();
+    func.compile().with(|add: extern fn(i32) -> i32| assert_eq!(add(1), 101));
+}
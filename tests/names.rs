@@ -0,0 +1,33 @@
+extern crate jit;
+use jit::*;
+use std::ffi::CStr;
+
+extern fn twice(x: i32) -> i32 {
+    x * 2
+}
+
+#[test]
+fn test_intern_name() {
+    let ctx = Context::<()>::new();
+    let first = ctx.intern_name("twice");
+    assert_eq!(first, ctx.intern_name("twice"));
+    assert!(first != ctx.intern_name("thrice"));
+    assert_eq!(unsafe { CStr::from_ptr(first) }.to_str(), Ok("twice"));
+}
+
+#[test]
+fn test_native_calls_share_names() {
+    let mut ctx = Context::<()>::new();
+    for _ in 0..2 {
+        let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+        let once = func.insn_call_native1(Some("twice"), twice, &get::<fn(i32) -> i32>(), [&func[0]], flags::CallFlags::NO_THROW);
+        let again = func.insn_call_native1(Some("twice"), twice, &get::<fn(i32) -> i32>(), [once], flags::CallFlags::NO_THROW);
+        func.insn_return(again);
+        func.compile().with(|quadruple: extern fn(i32) -> i32| {
+            assert_eq!(quadruple(3), 12);
+        });
+    }
+    let interned = ctx.intern_name("twice");
+    assert_eq!(interned, ctx.intern_name("twice"));
+    assert_eq!(unsafe { CStr::from_ptr(interned) }.to_str(), Ok("twice"));
+}
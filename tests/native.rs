@@ -0,0 +1,54 @@
+extern crate jit;
+use jit::*;
+use std::mem;
+
+#[test]
+fn test_native_types() {
+    assert_eq!(get::<isize>().get_size(), mem::size_of::<isize>());
+    assert_eq!(<usize as JitNum>::jit_type().get_size(), mem::size_of::<usize>());
+    assert_eq!(<isize as JitNum>::jit_type().get_size(), get::<&'static u8>().get_size());
+}
+
+#[test]
+fn test_constants() {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(isize) -> isize>());
+    assert_eq!(func.insn_nuint(1).get_type().get_size(), mem::size_of::<usize>());
+    func.insn_return(func.insn_add(&func[0], func.insn_nint(isize::min_value())));
+    func.compile().with(|min: extern fn(isize) -> isize| {
+        assert_eq!(min(0), isize::min_value());
+        assert_eq!(min(1), isize::min_value() + 1);
+    });
+}
+
+#[test]
+fn test_sign_extend() {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> isize>());
+    func.insn_return(<isize as NativeInt>::insn_widen(&func, &func[0]));
+    func.compile().with(|widen: extern fn(i32) -> isize| {
+        assert_eq!(widen(-5), -5);
+        assert_eq!(widen(i32::max_value()), i32::max_value() as isize);
+    });
+}
+
+#[test]
+fn test_zero_extend() {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(u32) -> usize>());
+    func.insn_return(func.insn_to_nuint(&func[0]));
+    func.compile().with(|widen: extern fn(u32) -> usize| {
+        assert_eq!(widen(u32::max_value()), u32::max_value() as usize);
+    });
+}
+
+#[test]
+fn test_kernel() {
+    let mut ctx = Context::<()>::new();
+    let double = kernel::<isize, _, _>(&mut ctx, 1, |func| {
+        func.insn_return(func.insn_add(&func[0], &func[0]));
+    });
+    double.with(|double: extern fn(isize) -> isize| {
+        assert_eq!(double(1 << 20), 1 << 21);
+    });
+}
@@ -0,0 +1,37 @@
+extern crate jit;
+use jit::*;
+
+#[test]
+fn test_nested_imports_parent_local() {
+    let mut ctx = Context::<()>::new();
+    let parent = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+    let local_var = parent.new_lvalue(typecs::get_int());
+    let local = local_var.get_local().unwrap();
+    parent.insn_store(&local_var, parent.insn_mul(&parent[0], parent.insn_of(2i32)));
+    let child = parent.nest(&get::<fn() -> i32>());
+    let ptr = child.insn_import(local);
+    let value = child.insn_load_relative(ptr, 0, typecs::get_int());
+    child.insn_return(child.insn_add(value, child.insn_of(1i32)));
+    let result = parent.insn_call(None, &child, None, &mut [], flags::CallFlags::empty());
+    parent.insn_return(result);
+    let parent = parent.compile();
+    let child = child.compile();
+    parent.with(|func: extern fn(i32) -> i32| {
+        assert_eq!(func(4), 9);
+        assert_eq!(func(-3), -5);
+    });
+    drop(child);
+}
+
+#[test]
+fn test_nested_before_parent() {
+    let mut ctx = Context::<()>::new();
+    let parent = UncompiledFunction::new(&mut ctx, &get::<fn() -> i32>());
+    let child = parent.nest(&get::<fn() -> i32>());
+    child.insn_return(child.insn_of(1i32));
+    assert_eq!(child.can_compile(), Err(CompileError::ParentNotCompiled));
+    assert_eq!(child.try_compile().err(), Some(CompileError::ParentNotCompiled));
+    parent.insn_return(parent.insn_of(0i32));
+    assert!(parent.can_compile().is_ok());
+    parent.compile();
+}
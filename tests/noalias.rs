@@ -0,0 +1,55 @@
+extern crate jit;
+use jit::*;
+
+#[test]
+fn test_reused_past_other_noalias() {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(&'static i32, &'static i32) -> i32>());
+    func.set_param_noalias(0);
+    func.set_param_noalias(1);
+    let int = typecs::get_int();
+    let first = func.insn_load_relative(&func[0], 0, int);
+    func.insn_store_relative(&func[1], 0, func.insn_of(3i32));
+    assert!(first == func.insn_load_relative(&func[0], 0, int));
+    func.insn_store_relative(&func[0], 0, func.insn_of(4i32));
+    assert!(first != func.insn_load_relative(&func[0], 0, int));
+}
+
+#[test]
+fn test_store_through_derived_pointer() {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(&'static i32) -> i32>());
+    func.set_param_noalias(0);
+    let int = typecs::get_int();
+    let first = func.insn_load_relative(&func[0], 4, int);
+    let second = func.insn_add_relative(&func[0], 4);
+    func.insn_store_relative(second, 0, func.insn_of(7i32));
+    let again = func.insn_load_relative(&func[0], 4, int);
+    assert!(first != again);
+    func.insn_return(func.insn_add(first, again));
+    let add: extern fn(*mut i32) -> i32 = func.compile().closure();
+    let mut pair = [1, 2];
+    assert_eq!(add(pair.as_mut_ptr()), 9);
+    assert_eq!(pair, [1, 7]);
+}
+
+#[test]
+fn test_reassigned() {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(&'static i32, &'static i32) -> i32>());
+    func.set_param_noalias(0);
+    func.set_param_noalias(1);
+    let int = typecs::get_int();
+    let first = func.insn_load_relative(&func[0], 0, int);
+    func.insn_store(&func.param_lvalue(1), &func[0]);
+    assert!(!func.is_param_noalias(1));
+    assert!(func.is_param_noalias(0));
+    func.insn_store_relative(&func[1], 0, func.insn_of(5i32));
+    let again = func.insn_load_relative(&func[0], 0, int);
+    assert!(first != again);
+    func.insn_return(func.insn_add(first, again));
+    let add: extern fn(*mut i32, *mut i32) -> i32 = func.compile().closure();
+    let (mut a, mut b) = (1, 2);
+    assert_eq!(add(&mut a, &mut b), 6);
+    assert_eq!((a, b), (5, 2));
+}
@@ -0,0 +1,73 @@
+extern crate jit;
+use jit::*;
+use std::f64;
+
+#[test]
+fn test_cmpl_cmpg() {
+    let mut ctx = Context::<()>::new();
+    let cmpl = UncompiledFunction::new(&mut ctx, &get::<fn(f64) -> i32>());
+    cmpl.insn_return(cmpl.insn_cmpl(&cmpl[0], cmpl.insn_of(1.0f64)));
+    let cmpg = cmpl.sibling(&get::<fn(f64) -> i32>());
+    cmpg.insn_return(cmpg.insn_cmpg(&cmpg[0], cmpg.insn_of(1.0f64)));
+    cmpl.compile().with(|cmp: extern fn(f64) -> i32| {
+        assert_eq!(cmp(0.0), -1);
+        assert_eq!(cmp(1.0), 0);
+        assert_eq!(cmp(2.0), 1);
+        assert_eq!(cmp(f64::NAN), -1);
+    });
+    cmpg.compile().with(|cmp: extern fn(f64) -> i32| {
+        assert_eq!(cmp(f64::NAN), 1);
+    });
+}
+
+#[test]
+fn test_to_bool() {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> bool>());
+    func.insn_return(func.insn_to_bool(&func[0]));
+    func.compile().with(|to_bool: extern fn(i32) -> bool| {
+        assert!(to_bool(5));
+        assert!(!to_bool(0));
+    });
+}
+
+#[test]
+fn test_sshr() {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+    func.insn_return(func.insn_sshr(&func[0], func.insn_of(2i32)));
+    func.compile().with(|shr: extern fn(i32) -> i32| {
+        assert_eq!(shr(-8), -2);
+        assert_eq!(shr(8), 2);
+    });
+}
+
+#[test]
+fn test_elems() {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(&'static u8, usize) -> i32>());
+    let int = get::<i32>();
+    let value = func.insn_load_elem(&func[0], &func[1], &int);
+    let next = func.insn_add(&func[1], func.insn_of(1usize));
+    func.insn_store_elem(&func[0], next, value);
+    func.insn_return(value);
+    let mut values = [1i32, 2, 3, 4];
+    let run: extern fn(*mut i32, usize) -> i32 = func.compile().closure();
+    assert_eq!(run(values.as_mut_ptr(), 1), 2);
+    assert_eq!(values, [1, 2, 2, 4]);
+}
+
+#[test]
+fn test_check_null() {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(&'static u8) -> i32>());
+    func.insn_uses_catcher();
+    func.insn_check_null(&func[0]);
+    func.insn_return(func.insn_of(1i32));
+    func.insn_start_catcher();
+    func.insn_return(func.insn_of(0i32));
+    func.compile().with(|check: extern fn(*const u8) -> i32| {
+        assert_eq!(check(&0u8), 1);
+        assert_eq!(check(0 as *const u8), 0);
+    });
+}
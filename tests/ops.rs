@@ -0,0 +1,52 @@
+extern crate jit;
+use jit::*;
+
+fn square<'a>(func: &UncompiledFunction<'a>, args: &[&'a Val]) -> Option<&'a Val> {
+    Some(func.insn_mul(args[0], args[0]))
+}
+fn sum_squares<'a>(func: &UncompiledFunction<'a>, args: &[&'a Val]) -> Option<&'a Val> {
+    let mut sum = func.insn_op("square", &[args[0]]).unwrap();
+    for &arg in &args[1..] {
+        sum = func.insn_add(sum, func.insn_op("square", &[arg]).unwrap());
+    }
+    Some(sum)
+}
+fn store_zero<'a>(func: &UncompiledFunction<'a>, args: &[&'a Val]) -> Option<&'a Val> {
+    func.insn_store_relative(args[0], 0, func.insn_of(0i32));
+    None
+}
+
+#[test]
+fn test_composed() {
+    let mut ctx = Context::<()>::new();
+    ctx.register_ops(&[("square", square as CustomOp), ("sum_squares", sum_squares as CustomOp)]);
+    assert!(ctx.has_op("square"));
+    assert!(!ctx.has_op("cube"));
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32, i32) -> i32>());
+    let result = func.insn_op("sum_squares", &[&func[0], &func[1]]).unwrap();
+    func.insn_return(result);
+    let func = func.compile();
+    assert_eq!(interp::call(&func, &[interp::Value::Int(3), interp::Value::Int(4)]), Ok(interp::Value::Int(25)));
+}
+
+#[test]
+fn test_no_result() {
+    let mut ctx = Context::<()>::new();
+    ctx.register_op("store_zero", store_zero);
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(&'static u8)>());
+    assert!(func.insn_op("store_zero", &[&func[0]]).is_none());
+    func.insn_default_return();
+    func.compile().with(|zero: extern fn(&'static u8)| {
+        let mut value = 5i32;
+        zero(unsafe { &*(&mut value as *mut i32 as *const u8) });
+        assert_eq!(value, 0);
+    });
+}
+
+#[test]
+#[should_panic]
+fn test_unregistered() {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+    func.insn_op("square", &[&func[0]]);
+}
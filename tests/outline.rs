@@ -0,0 +1,38 @@
+extern crate jit;
+use jit::*;
+
+#[test]
+fn test_outlined_region_runs() {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32, &'static i32)>());
+    let cond = func.insn_lt(&func[0], func.insn_of(0i32));
+    func.insn_if(cond, || {
+        assert!(func.outline(|| {
+            func.insn_store_relative(&func[1], 0, func.insn_neg(&func[0]));
+        }).is_some());
+    });
+    func.insn_default_return();
+    let calls = func.blocks().flat_map(|block| block.iter()).filter(|insn| insn.get_name().starts_with("call")).count();
+    assert_eq!(calls, 1);
+    let run: extern fn(i32, *mut i32) = func.compile().closure();
+    let mut out = 0;
+    run(-5, &mut out);
+    assert_eq!(out, 5);
+    run(7, &mut out);
+    assert_eq!(out, 5);
+}
+
+#[test]
+fn test_returning_region_stays_inline() {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+    let cond = func.insn_lt(&func[0], func.insn_of(0i32));
+    func.insn_if(cond, || {
+        assert!(func.outline(|| func.insn_return(func.insn_of(0i32))).is_none());
+    });
+    func.insn_return(&func[0]);
+    func.compile().with(|clamp: extern fn(i32) -> i32| {
+        assert_eq!(clamp(-3), 0);
+        assert_eq!(clamp(3), 3);
+    });
+}
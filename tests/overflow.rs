@@ -0,0 +1,56 @@
+extern crate jit;
+use jit::*;
+use jit::interp::Value;
+
+fn saturating<'a, F>(ctx: &'a mut Context<()>, sig: &Ty, op: F) -> CompiledFunction<'a> where F: Fn(&UncompiledFunction) -> () {
+    let func = UncompiledFunction::new(ctx, sig);
+    func.set_overflow(Overflow::Saturate);
+    op(&func);
+    func.compile()
+}
+
+#[test]
+fn test_saturating_i64() {
+    let mut ctx = Context::<()>::new();
+    let add = saturating(&mut ctx, &get::<fn(i64, i64) -> i64>(), |func| {
+        func.insn_return(func.insn_arith_add(&func[0], &func[1]))
+    });
+    let max = i64::max_value();
+    let min = i64::min_value();
+    assert_eq!(interp::call(&add, &[Value::Long(max), Value::Long(1)]), Ok(Value::Long(max)));
+    assert_eq!(interp::call(&add, &[Value::Long(min), Value::Long(-1)]), Ok(Value::Long(min)));
+    assert_eq!(interp::call(&add, &[Value::Long(2), Value::Long(3)]), Ok(Value::Long(5)));
+}
+
+#[test]
+fn test_saturating_i64_mul() {
+    let mut ctx = Context::<()>::new();
+    let mul = saturating(&mut ctx, &get::<fn(i64, i64) -> i64>(), |func| {
+        func.insn_return(func.insn_arith_mul(&func[0], &func[1]))
+    });
+    let max = i64::max_value();
+    let min = i64::min_value();
+    assert_eq!(interp::call(&mul, &[Value::Long(max / 2), Value::Long(3)]), Ok(Value::Long(max)));
+    assert_eq!(interp::call(&mul, &[Value::Long(max / 2), Value::Long(-3)]), Ok(Value::Long(min)));
+    assert_eq!(interp::call(&mul, &[Value::Long(-1), Value::Long(min)]), Ok(Value::Long(max)));
+    assert_eq!(interp::call(&mul, &[Value::Long(-7), Value::Long(6)]), Ok(Value::Long(-42)));
+}
+
+#[test]
+fn test_saturating_u32_sub() {
+    let mut ctx = Context::<()>::new();
+    let sub = saturating(&mut ctx, &get::<fn(u32, u32) -> u32>(), |func| {
+        func.insn_return(func.insn_arith_sub(&func[0], &func[1]))
+    });
+    assert_eq!(interp::call(&sub, &[Value::UInt(3), Value::UInt(5)]), Ok(Value::UInt(0)));
+    assert_eq!(interp::call(&sub, &[Value::UInt(5), Value::UInt(3)]), Ok(Value::UInt(2)));
+}
+
+#[test]
+fn test_default_wraps() {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+    assert_eq!(func.get_overflow(), Overflow::Wrap);
+    func.insn_return(&func[0] + func.insn_of(1i32));
+    func.compile().with(|func: extern fn(i32) -> i32| assert_eq!(func(i32::max_value()), i32::min_value()));
+}
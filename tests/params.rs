@@ -0,0 +1,31 @@
+extern crate jit;
+use jit::*;
+
+#[test]
+fn test_single_param() {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+    let (x,) = func.params_as::<(i32,)>().unwrap();
+    func.insn_return(func.insn_add(x.get(), x.get()));
+    func.compile().with(|double: extern fn(i32) -> i32| assert_eq!(double(21), 42));
+}
+
+#[test]
+fn test_typed_values_deref() {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(u8, f32, i64) -> i64>());
+    let (a, b, c) = func.params_as::<(u8, f32, i64)>().unwrap();
+    assert_eq!(a.get_type().get_kind(), TypeKind::UByte);
+    assert_eq!(b.get_type().get_kind(), TypeKind::Float32);
+    assert_eq!(c.get(), &func[2]);
+}
+
+#[test]
+fn test_mismatches() {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32, f64) -> i32>());
+    assert_eq!(func.params_as::<(i32,)>().err(), Some(SignatureMismatch::Arity(1, 2)));
+    assert_eq!(func.params_as::<(i32, f32)>().err(),
+               Some(SignatureMismatch::Param(1, "f32".into(), "f64".into())));
+    assert!(func.params_as::<()>().is_err());
+}
@@ -0,0 +1,60 @@
+extern crate jit;
+use jit::*;
+
+#[test]
+fn test_throw_codes() {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+    let narrow = func.insn_convert(&func[0], &get::<i8>(), false);
+    func.insn_throw_typed(narrow, &get::<i8>());
+    let call = func.compile().closure_result::<i32, i32>();
+    assert_eq!(call(-3), Err(JitException::Typed(Payload::Code(-3))));
+    assert_eq!(call(200), Err(JitException::Typed(Payload::Code(-56))));
+}
+
+#[test]
+fn test_throw_unsigned() {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(u16) -> i32>());
+    func.insn_throw_typed(&func[0], &get::<u16>());
+    let call = func.compile().closure_result::<u16, i32>();
+    assert_eq!(call(65535), Err(JitException::Typed(Payload::Code(65535))));
+}
+
+#[test]
+fn test_throw_struct() {
+    let mut ctx = Context::<()>::new();
+    let pair = get::<(i32, i32)>();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+    let value_var = func.new_lvalue(&pair);
+    let value = value_var.get_local().unwrap();
+    let address = func.insn_address_of(&value_var);
+    func.insn_store_relative(address, 0, &func[0]);
+    func.insn_store_relative(address, 4, func.insn_neg(&func[0]));
+    func.insn_throw_typed(value, &pair);
+    let call = func.compile().closure_result::<i32, i32>();
+    match call(5) {
+        Err(JitException::Typed(payload)) => assert_eq!(unsafe { payload.read::<[i32; 2]>() }, Some([5, -5])),
+        other => panic!("Expected a struct payload, got {:?}", other)
+    }
+}
+
+#[test]
+fn test_catch_other_payload() {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+    func.insn_uses_catcher();
+    func.insn_throw_typed(&func[0], &get::<i32>());
+    let pointer = func.insn_start_typed_catcher(&get::<&'static u8>());
+    func.insn_return(func.insn_convert(pointer, &get::<i32>(), false));
+    let call = func.compile().closure_result::<i32, i32>();
+    assert_eq!(call(9), Err(JitException::Typed(Payload::Code(9))));
+}
+
+#[test]
+#[should_panic(expected = "Bad value for insn_throw_typed")]
+fn test_throw_mismatch() {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+    func.insn_throw_typed(&func[0], &get::<i64>());
+}
@@ -0,0 +1,88 @@
+extern crate jit;
+use jit::*;
+
+/// Move every pinned object 8 bytes up, like a compacting collector
+extern fn collect(ctx: &Context<()>) -> usize {
+    let mut count = 0;
+    for root in ctx.pinned_roots() {
+        unsafe { *(root as *mut usize) += 8 };
+        count += 1;
+    }
+    count
+}
+
+#[test]
+fn test_collector_moves_pinned() {
+    let mut ctx = Context::<()>::new();
+    let ctx_ptr = &ctx as *const Context<()>;
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(usize, usize) -> usize>());
+    let (a, b) = (func.insn_dup(&func[1]), func.insn_dup(&func[1]));
+    func.insn_pin(a);
+    func.insn_pin(b);
+    let both = func.insn_call_native1(Some("collect"), collect, &get::<fn(usize) -> usize>(),
+                                      [&func[0]], flags::CallFlags::empty());
+    func.insn_unpin(a);
+    let one = func.insn_call_native1(Some("collect"), collect, &get::<fn(usize) -> usize>(),
+                                     [&func[0]], flags::CallFlags::empty());
+    func.insn_unpin(b);
+    let counts = func.insn_add(func.insn_mul(both, func.insn_of(1000usize)), func.insn_mul(one, func.insn_of(100usize)));
+    func.insn_return(func.insn_add(counts, func.insn_add(a, b)));
+    let call: extern fn(*const Context<()>, usize) -> usize = func.compile().closure();
+    assert_eq!(call(ctx_ptr, 0), 2100 + 8 + 16);
+    assert_eq!(unsafe { (*ctx_ptr).pinned_roots().count() }, 0);
+}
+
+#[test]
+fn test_pinned_in_loop() {
+    let mut ctx = Context::<()>::new();
+    let ctx_ptr = &ctx as *const Context<()>;
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(usize) -> usize>());
+    let most_var = func.new_lvalue(&get::<usize>());
+    let most = most_var.get_local().unwrap();
+    func.insn_store(&most_var, func.insn_of(0usize));
+    func.insn_for_range(func.insn_of(0i32), func.insn_of(3i32), |_| {
+        let object = func.insn_dup(func.insn_of(0usize));
+        func.insn_pin(object);
+        let count = func.insn_call_native1(Some("collect"), collect, &get::<fn(usize) -> usize>(),
+                                           [&func[0]], flags::CallFlags::empty());
+        func.insn_unpin(object);
+        func.insn_store(&most_var, func.insn_max(most, count));
+    });
+    func.insn_return(most);
+    let call: extern fn(*const Context<()>) -> usize = func.compile().closure();
+    assert_eq!(call(ctx_ptr), 1);
+    assert_eq!(unsafe { (*ctx_ptr).pinned_roots().count() }, 0);
+}
+
+#[test]
+fn test_exception_unpins() {
+    let mut ctx = Context::<()>::new();
+    let ctx_ptr = &ctx as *const Context<()>;
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+    let object = func.insn_dup(&func[0]);
+    func.insn_pin(object);
+    let quotient = func.insn_div(func.insn_of(100i32), object);
+    func.insn_unpin(object);
+    func.insn_return(quotient);
+    let call = func.compile().closure_result::<i32, i32>();
+    assert_eq!(call(4), Ok(25));
+    assert_eq!(call(0), Err(JitException::Builtin(BuiltinException::DivisionByZero)));
+    assert_eq!(unsafe { (*ctx_ptr).pinned_roots().count() }, 0);
+}
+
+#[test]
+fn test_tail_call_unpins() {
+    let mut ctx = Context::<()>::new();
+    let ctx_ptr = &ctx as *const Context<()>;
+    let count = UncompiledFunction::new(&mut ctx, &get::<fn(usize) -> usize>());
+    count.insn_return(count.insn_call_native1(Some("collect"), collect, &get::<fn(usize) -> usize>(),
+                                              [&count[0]], flags::CallFlags::empty()));
+    let func = count.sibling(&get::<fn(usize) -> usize>());
+    let count = count.compile();
+    let object = func.insn_dup(&func[0]);
+    func.insn_pin(object);
+    func.insn_call(None, &count, None, &mut [&func[0]], flags::CallFlags::TAIL);
+    let call: extern fn(*const Context<()>) -> usize = func.compile().closure();
+    assert_eq!(call(ctx_ptr), 1);
+    assert_eq!(unsafe { (*ctx_ptr).pinned_roots().count() }, 0);
+}
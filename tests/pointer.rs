@@ -0,0 +1,42 @@
+extern crate jit;
+use jit::*;
+
+fn as_byte<T>(value: &T) -> &'static u8 {
+    unsafe { &*(value as *const T as *const u8) }
+}
+
+#[test]
+fn test_variable_index() {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(&'static u8) -> i32>());
+    let elem = func.insn_ptr_add(&func[0], func.insn_of(-1i32), &get::<i32>());
+    assert_eq!(elem.get_type(), func[0].get_type());
+    let index_var = func.new_lvalue(&get::<i32>());
+    let index = index_var.get_local().unwrap();
+    func.insn_store(&index_var, func.insn_of(3i32));
+    let elem = func.insn_ptr_add(elem, index, &get::<i32>());
+    func.insn_return(func.insn_load_relative(elem, 0, &get::<i32>()));
+    func.compile().with(|get: extern fn(&'static u8) -> i32| {
+        let ints = [10i32, 20, 30, 40];
+        assert_eq!(get(as_byte(&ints[1])), 40);
+    });
+}
+
+#[test]
+fn test_constant_zero() {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(&'static u8) -> &'static u8>());
+    assert_eq!(func.insn_ptr_add(&func[0], func.insn_of(0i32), &get::<f64>()), &func[0]);
+}
+
+#[test]
+fn test_diff() {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(&'static u8) -> isize>());
+    let end = func.insn_ptr_add(&func[0], func.insn_of(5u32), &get::<i64>());
+    func.insn_return(func.insn_ptr_diff(end, &func[0], &get::<i64>()));
+    func.compile().with(|len: extern fn(&'static u8) -> isize| {
+        let longs = [0i64; 5];
+        assert_eq!(len(as_byte(&longs[0])), 5);
+    });
+}
@@ -0,0 +1,46 @@
+extern crate jit;
+use jit::*;
+
+#[test]
+fn test_depth() {
+    let int_t = get::<i32>();
+    let ptr = Type::pointer_to(&int_t);
+    let ptr_ptr = Type::pointer_to(&ptr);
+    assert_eq!(int_t.pointer_depth(), 0);
+    assert_eq!(ptr.pointer_depth(), 1);
+    assert_eq!(ptr_ptr.pointer_depth(), 2);
+    assert_eq!(ptr_ptr.strip_pointers(), &int_t as &Ty);
+    assert_eq!(int_t.strip_pointers(), &int_t as &Ty);
+    assert!(ptr_ptr.get_ref().unwrap().is_pointer());
+}
+
+#[test]
+fn test_load_through_pointer_to() {
+    let mut ctx = Context::<()>::new();
+    let int_t = get::<i32>();
+    let ptr_t = Type::pointer_to(&int_t);
+    let sig = Type::new_signature(Abi::CDecl, &int_t, &mut [&Type::pointer_to(&ptr_t)]);
+    let func = UncompiledFunction::new(&mut ctx, &sig);
+    let inner = func.insn_load_relative(&func[0], 0, &ptr_t);
+    func.insn_return(func.insn_load_relative(inner, 0, &int_t));
+    func.compile().with(|load: extern fn(*const *const i32) -> i32| {
+        let value = 42;
+        assert_eq!(load(&(&value as *const i32)), 42);
+    });
+}
+
+#[test]
+#[should_panic]
+fn test_load_from_integer() {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+    func.insn_load_relative(&func[0], 0, &get::<i32>());
+}
+
+#[test]
+#[should_panic]
+fn test_store_elem_to_integer() {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(i64) -> i32>());
+    func.insn_store_elem(&func[0], func.insn_of(0usize), func.insn_of(1i32));
+}
@@ -0,0 +1,66 @@
+extern crate jit;
+use jit::*;
+
+fn deferring() -> Context<()> {
+    let mut ctx = Context::<()>::new();
+    ctx.set_options(ContextOptions {
+        defer_errors: true,
+        .. ContextOptions::default()
+    });
+    ctx
+}
+
+#[test]
+fn test_first_error_kept() {
+    let mut ctx = deferring();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+    assert!(!func.is_poisoned());
+    func.set_current_location(4, 1, 1);
+    let root = func.insn_sqrt(&func[0]);
+    func.set_current_location(4, 2, 1);
+    let loaded = func.insn_load_relative(&func[0], 0, &get::<i32>());
+    func.insn_return(func.insn_add(root, loaded));
+    let error = func.get_poison().unwrap();
+    assert!(error.message.contains("insn_sqrt"));
+    assert_eq!(error.location, Some(SourceLocation { file: 4, line: 1, column: 1 }));
+    let message = func.try_compile().err().unwrap().to_string();
+    assert!(message.contains("insn_sqrt"));
+    assert!(message.ends_with("at 4:1:1"));
+}
+
+#[test]
+fn test_clean_function_compiles() {
+    let mut ctx = deferring();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(f64) -> f64>());
+    func.insn_return(func.insn_sqrt(&func[0]));
+    assert!(!func.is_poisoned());
+    func.compile().with(|root: extern fn(f64) -> f64| assert_eq!(root(9.0), 3.0));
+}
+
+#[test]
+#[should_panic]
+fn test_panics_without_deferring() {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+    func.insn_sqrt(&func[0]);
+}
+
+#[test]
+fn test_poisoned_compile_throws_when_called() {
+    let mut ctx = deferring();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+    func.insn_return(func.insn_sqrt(&func[0]));
+    let call = func.compile().closure_result::<i32, i32>();
+    assert_eq!(call(4), Err(JitException::Builtin(BuiltinException::CompileError)));
+}
+
+#[test]
+fn test_unplaced_label_compile_throws_when_called() {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+    let mut missing = Label::new(&func);
+    func.insn_branch(&mut missing);
+    func.insn_return(&func[0]);
+    let call = func.compile().closure_result::<i32, i32>();
+    assert_eq!(call(4), Err(JitException::Builtin(BuiltinException::CompileError)));
+}
@@ -0,0 +1,50 @@
+extern crate jit;
+use jit::*;
+
+fn build(ctx: &mut Context<()>, power: i32) -> String {
+    let func = UncompiledFunction::new(ctx, &get::<fn(f64) -> f64>());
+    func.insn_return(func.insn_pow(&func[0], func.insn_of(power)));
+    let dump = func.dump_stable();
+    let func = func.compile();
+    for &x in &[0.5f64, 2.0, -3.0] {
+        assert_eq!(interp::call(&func, &[interp::Value::Float64(x)]), Ok(interp::Value::Float64(x.powi(power))));
+    }
+    dump
+}
+
+#[test]
+fn test_small_powers() {
+    let mut ctx = Context::<()>::new();
+    for power in 0..17 {
+        assert!(!build(&mut ctx, power).contains("pow"));
+    }
+}
+
+#[test]
+fn test_big_and_negative_powers() {
+    let mut ctx = Context::<()>::new();
+    assert!(build(&mut ctx, 17).contains("pow"));
+    assert!(build(&mut ctx, -2).contains("pow"));
+}
+
+#[test]
+fn test_integers() {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+    func.insn_return(func.insn_pow(&func[0], func.insn_of(5i32)));
+    assert!(!func.dump_stable().contains("pow"));
+    func.compile().with(|fifth: extern fn(i32) -> i32| assert_eq!(fifth(-3), -243));
+}
+
+#[test]
+fn test_without_folding() {
+    let mut ctx = Context::<()>::new();
+    ctx.set_options(ContextOptions {
+        folding: false,
+        .. ContextOptions::default()
+    });
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(f64) -> f64>());
+    func.insn_return(func.insn_pow(&func[0], func.insn_of(2.0f64)));
+    assert!(func.dump_stable().contains("pow"));
+    func.compile().with(|square: extern fn(f64) -> f64| assert_eq!(square(3.0), 9.0));
+}
@@ -0,0 +1,32 @@
+extern crate jit;
+use jit::*;
+
+#[test]
+fn test_forced_float32() {
+    let mut ctx = Context::<()>::new();
+    ctx.set_options(ContextOptions {
+        float_precision: Some(Precision::Float32),
+        .. ContextOptions::default()
+    });
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(f64) -> f64>());
+    func.insn_return(func.insn_add(&func[0], func.insn_of(0.2f64)));
+    func.compile().with(|func: extern fn(f64) -> f64| {
+        assert_eq!(func(0.1), (0.1f32 + 0.2f32) as f64);
+    });
+}
+
+#[test]
+fn test_unforced() {
+    let mut ctx = Context::<()>::new();
+    assert_eq!(ctx.get_options().float_precision, None);
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(f64) -> f64>());
+    func.insn_return(func.insn_add(&func[0], func.insn_of(0.2f64)));
+    func.compile().with(|func: extern fn(f64) -> f64| assert_eq!(func(0.1), 0.1 + 0.2));
+}
+
+#[test]
+fn test_precision_types() {
+    assert_eq!(Precision::Float32.get_type(), &*get::<f32>());
+    assert_eq!(Precision::Float64.get_type(), &*get::<f64>());
+    assert!(Precision::NFloat.get_type().is_float());
+}
@@ -0,0 +1,36 @@
+extern crate jit;
+use jit::*;
+
+#[test]
+fn test_printf_counts() {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32, f64) -> i32>());
+    func.insn_return(func.insn_printf("x = %d, y = %.1f\n", &[&func[0], &func[1]]));
+    func.compile().with(|print: extern fn(i32, f64) -> i32| assert_eq!(print(12, 2.5), 16));
+}
+
+#[test]
+fn test_printf_star_and_short() {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+    let short = func.insn_convert(&func[0], &get::<i16>(), false);
+    let values = [func.insn_of(5i32), &func[0], short, func.insn_of(300i32), func.insn_of(7i64)];
+    func.insn_return(func.insn_printf("[%*d|%hd|%hhu|%lld]\n", &values));
+    func.compile().with(|print: extern fn(i32) -> i32| assert_eq!(print(42), 16));
+}
+
+#[test]
+#[should_panic(expected = "for * should not be")]
+fn test_printf_star_needs_int() {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(f64)>());
+    func.insn_printf("%*d\n", &[&func[0], func.insn_of(1i32)]);
+}
+
+#[test]
+#[should_panic(expected = "Not enough values")]
+fn test_printf_star_counts_values() {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32)>());
+    func.insn_printf("%*d\n", &[&func[0]]);
+}
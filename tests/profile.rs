@@ -0,0 +1,55 @@
+extern crate jit;
+use jit::*;
+
+#[test]
+fn test_call_counts() {
+    let mut ctx = Context::<()>::new();
+    ctx.enable_call_counts();
+    let counted = {
+        let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+        func.insn_return(func.insn_neg(&func[0]));
+        func.compile()
+    };
+    counted.export("neg");
+    for i in 0..5 {
+        counted.with(|neg: extern fn(i32) -> i32| assert_eq!(neg(i), -i));
+    }
+    assert_eq!(counted.get_call_count(), Some(5));
+    ctx.disable_call_counts();
+    let uncounted = {
+        let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+        func.insn_return(&func[0]);
+        func.compile()
+    };
+    uncounted.export("id");
+    assert_eq!(uncounted.get_call_count(), None);
+    let profile = ctx.call_profile();
+    assert_eq!(profile.len(), 1);
+    assert_eq!(profile.get("neg"), Some(5));
+    assert_eq!(profile.get("id"), None);
+}
+
+#[test]
+fn test_profile_text() {
+    let mut profile = CallProfile::new();
+    profile.insert("b", 2);
+    profile.insert("a", 10);
+    profile.insert("b", 1);
+    assert_eq!(profile.to_text(), "a\t10\nb\t3\n");
+    assert_eq!(CallProfile::parse(&profile.to_text()), profile);
+    assert_eq!(CallProfile::parse("junk\nc\tnot a count\nd\t4\n").len(), 1);
+    assert_eq!(profile.hot(3), vec!["a", "b"]);
+    assert_eq!(profile.hot(4), vec!["a"]);
+}
+
+#[test]
+fn test_warm_up() {
+    let mut ctx = Context::<()>::new();
+    ctx.set_hot_profile(CallProfile::parse("hot\t100\ncold\t1\n"), 50);
+    assert_eq!(ctx.get_hot_functions(), vec!["hot".to_owned()]);
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+    assert!(!func.warm_up("cold"));
+    assert!(!func.warm_up("missing"));
+    assert!(func.warm_up("hot"));
+    assert_eq!(func.get_optimization_level(), UncompiledFunction::get_max_optimization_level());
+}
@@ -0,0 +1,20 @@
+#![cfg(feature = "testing")]
+extern crate jit;
+use jit::*;
+use jit::snapshot::{IrSnapshot, ValueId};
+
+#[test]
+fn test_low_level_call_insns() {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32, &'static i64) -> i32>());
+    let result = Val::new(&func, &get::<i32>());
+    let pair = Type::new_struct(&mut [&get::<i64>(), &get::<i64>()]);
+    assert!(func.insn_push(&func[0]));
+    assert!(func.insn_push_ptr(&func[1], &pair));
+    assert!(func.insn_pop_stack(2));
+    assert!(func.insn_return_reg(result, 0));
+    let snapshot = IrSnapshot::new(&func);
+    assert_eq!(snapshot.opcodes(), ["push_int", "push_struct", "pop_stack", "return_reg"]);
+    assert_eq!(snapshot.insns[0].args[0].id, ValueId::Param(0));
+    assert_eq!(snapshot.insns[1].args[0].id, ValueId::Param(1));
+}
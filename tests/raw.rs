@@ -0,0 +1,47 @@
+#![cfg(feature = "raw")]
+extern crate jit;
+use jit::*;
+use jit::bindings::*;
+
+#[test]
+fn test_round_trips() {
+    let mut ctx = Context::<()>::new();
+    let ctx_raw = ctx.raw();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+    unsafe {
+        assert_eq!(jit_function_get_context(func.raw()), ctx_raw);
+        assert_eq!(Func::from_raw(func.raw()).raw(), func.raw());
+        assert_eq!(Val::from_raw(func[0].raw()) as *const Val, &func[0] as *const Val);
+        let ty = Ty::from_raw(jit_value_get_type(func[0].raw()));
+        assert_eq!(ty.get_kind(), TypeKind::Int);
+        let sig = Type::from_raw(jit_type_copy(jit_function_get_signature(func.raw())));
+        assert_eq!(sig.params().count(), 1);
+        let label = Label::new(&func);
+        assert_eq!(Label::from_raw(label.raw()).raw(), label.raw());
+    }
+    func.insn_return(&func[0]);
+    for block in func.blocks() {
+        unsafe {
+            assert_eq!(Block::from_raw(block.raw()).raw(), block.raw());
+        }
+        for insn in block.iter() {
+            unsafe {
+                assert_eq!(Instruction::from_raw(insn.raw()).get_name(), insn.get_name());
+            }
+        }
+    }
+}
+
+#[test]
+fn test_mixed_with_raw_calls() {
+    let mut ctx = unsafe { Context::<()>::from_raw(jit_context_create()) };
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+    let tripled = unsafe {
+        let three = jit_value_create_nint_constant(func.raw(), typecs::get_int().raw(), 3);
+        Val::from_raw(jit_insn_mul(func.raw(), func[0].raw(), three))
+    };
+    func.insn_return(func.insn_add(tripled, func.insn_of(1i32)));
+    let compiled = func.compile();
+    let func = unsafe { CompiledFunction::from_raw(compiled.raw()) };
+    func.with(|call: extern fn(i32) -> i32| assert_eq!(call(5), 16));
+}
@@ -0,0 +1,40 @@
+extern crate jit;
+use jit::*;
+
+#[test]
+fn test_readonly_global() {
+    let mut ctx = Context::<()>::new();
+    ctx.new_readonly_global("answer", &get::<i64>(), 42i64);
+    ctx.new_global("writable", &get::<i64>(), 0i64);
+    assert!(ctx.is_global_readonly("answer"));
+    assert!(!ctx.is_global_readonly("writable"));
+    assert!(!ctx.is_global_readonly("missing"));
+    assert_eq!(ctx.get_global::<i64>("answer"), Some(42));
+    {
+        let func = UncompiledFunction::new(&mut ctx, &get::<fn(i64) -> i64>());
+        func.insn_return(func.insn_add(&func[0], func.insn_load_global("answer")));
+        func.compile().with(|add: extern fn(i64) -> i64| assert_eq!(add(8), 50));
+    }
+}
+
+#[test]
+#[should_panic]
+fn test_store_readonly_global() {
+    let mut ctx = Context::<()>::new();
+    ctx.new_readonly_global("answer", &get::<i64>(), 42i64);
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(i64) -> i64>());
+    func.insn_store_global("answer", &func[0]);
+}
+
+#[test]
+fn test_readonly_table() {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(usize) -> i32>());
+    let table = func.new_readonly_table(&[3i32, 1, 4, 1, 5, 9]);
+    assert_eq!(table.get_len(), 6);
+    func.insn_return(func.insn_load_table(table, &func[0]));
+    func.compile().with(|digit: extern fn(usize) -> i32| {
+        assert_eq!(digit(0), 3);
+        assert_eq!(digit(5), 9);
+    });
+}
@@ -0,0 +1,42 @@
+extern crate jit;
+use jit::*;
+use jit::reduce::{self, Reduction};
+
+#[test]
+fn test_ints() {
+    let mut ctx = Context::<()>::new();
+    let values: Vec<i64> = (-50..50).collect();
+    assert_eq!(reduce::sum(&mut ctx, &values), -50);
+    assert_eq!(reduce::min(&mut ctx, &values), Some(-50));
+    assert_eq!(reduce::max(&mut ctx, &values), Some(49));
+    assert_eq!(reduce::dot(&mut ctx, &values[..3], &[1, 2, 3]), -50 - 49 * 2 - 48 * 3);
+    assert_eq!(reduce::sum::<i64, _>(&mut ctx, &[]), 0);
+    assert_eq!(reduce::max::<i64, _>(&mut ctx, &[]), None);
+}
+
+#[test]
+fn test_floats() {
+    let mut ctx = Context::<()>::new();
+    ctx.set_unroll_limits(UnrollLimits { factor: 4, max_full: 0 });
+    let values = [0.5f64, 3.25, -1.0, 2.0, 8.5];
+    assert_eq!(reduce::sum(&mut ctx, &values), 13.25);
+    assert_eq!(reduce::min(&mut ctx, &values), Some(-1.0));
+    assert_eq!(reduce::max(&mut ctx, &values), Some(8.5));
+    assert_eq!(reduce::dot(&mut ctx, &values, &values), 0.25 + 10.5625 + 1.0 + 4.0 + 72.25);
+    assert_eq!(reduce::min(&mut ctx, &[7.0f32]), Some(7.0));
+}
+
+#[test]
+fn test_kernel_signature() {
+    let mut ctx = Context::<()>::new();
+    let func = reduce::reduction::<u32, _>(&mut ctx, Reduction::Dot);
+    assert!(func.get_signature().signature_compatible(&Reduction::Dot.signature::<u32>()));
+    assert_eq!(func.get_signature().params().count(), 3);
+}
+
+#[test]
+#[should_panic]
+fn test_dot_lengths() {
+    let mut ctx = Context::<()>::new();
+    reduce::dot(&mut ctx, &[1i32, 2], &[3]);
+}
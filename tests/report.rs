@@ -0,0 +1,41 @@
+extern crate jit;
+use jit::*;
+
+#[test]
+fn test_not_compiled() {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+    func.insn_return(&func[0]);
+    assert_eq!(func.codegen_report(), None);
+}
+
+#[test]
+fn test_loop_report() {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+    let total_var = func.new_lvalue(&get::<i32>());
+    let total = total_var.get_local().unwrap();
+    let index_var = func.new_lvalue(&get::<i32>());
+    let index = index_var.get_local().unwrap();
+    func.insn_store(&total_var, func.insn_of(0i32));
+    func.insn_store(&index_var, func.insn_of(0i32));
+    let mut top = Label::new(&func);
+    let mut done = Label::new(&func);
+    func.insn_label(&mut top);
+    func.insn_branch_if_not(func.insn_lt(index, &func[0]), &mut done);
+    func.insn_store(&total_var, func.insn_add(total, index));
+    func.insn_store(&index_var, func.insn_add(index, func.insn_of(1i32)));
+    func.insn_branch(&mut top);
+    func.insn_label(&mut done);
+    func.insn_return(total);
+    let func = func.compile();
+    let report = func.codegen_report().unwrap();
+    assert!(report.blocks >= 3);
+    assert!(report.spilled >= 2);
+    assert_eq!(report.addressable, 0);
+    assert!(report.code_size > 0);
+    if cfg!(target_arch = "x86_64") {
+        assert!(report.frame_size.is_some());
+    }
+    func.with(|sum: extern fn(i32) -> i32| assert_eq!(sum(5), 10));
+}
@@ -0,0 +1,54 @@
+extern crate jit;
+use jit::*;
+
+static OBJECT: u8 = 0;
+
+#[test]
+fn test_no_params_thrown() {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn() -> i32>());
+    let object = func.insn_convert(func.insn_of(&OBJECT as *const u8 as usize), typecs::get_void_ptr(), false);
+    func.insn_throw(object);
+    let call = func.compile().closure_result::<(), i32>();
+    assert_eq!(call(()), Err(JitException::Thrown(&OBJECT as *const u8 as *mut _)));
+}
+
+#[test]
+fn test_void_result() {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32)>());
+    let quotient = func.insn_div(func.insn_of(1i32), &func[0]);
+    // use the quotient so the division isn't dropped
+    let mut done = Label::new(&func);
+    func.insn_branch_if(quotient, &mut done);
+    func.insn_label(&mut done);
+    func.insn_default_return();
+    let call = func.compile().closure_result::<i32, ()>();
+    assert_eq!(call(1), Ok(()));
+    assert_eq!(call(0), Err(JitException::Builtin(BuiltinException::DivisionByZero)));
+}
+
+#[test]
+fn test_callee_exception_and_recovery() {
+    let mut ctx = Context::<()>::new();
+    let divide = UncompiledFunction::new(&mut ctx, &get::<fn(i64) -> i64>());
+    divide.insn_return(divide.insn_div(divide.insn_of(1000i64), &divide[0]));
+    let func = divide.sibling(&get::<fn(i64) -> i64>());
+    let divide = divide.compile();
+    let quotient = func.insn_call(None, &divide, None, &mut [&func[0]], flags::CallFlags::empty());
+    func.insn_return(func.insn_add(quotient, func.insn_of(1i64)));
+    let call = func.compile().closure_result::<i64, i64>();
+    for _ in 0..3 {
+        assert_eq!(call(0), Err(JitException::Builtin(BuiltinException::DivisionByZero)));
+        assert_eq!(call(10), Ok(101));
+    }
+}
+
+#[test]
+#[should_panic(expected = "at most one parameter")]
+fn test_too_many_params() {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32, i32) -> i32>());
+    func.insn_return(&func[0]);
+    func.compile().closure_result::<i32, i32>();
+}
@@ -0,0 +1,86 @@
+extern crate jit;
+use jit::*;
+
+#[test]
+fn test_keep_everything() {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+    func.insn_return(func.insn_add(&func[0], func.insn_of(1i32)));
+    let rewritten = func.rewrite(|_, _| Rewrite::Keep).unwrap();
+    rewritten.compile().with(|inc: extern fn(i32) -> i32| assert_eq!(inc(4), 5));
+}
+
+#[test]
+fn test_replace_with_sequence() {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+    func.insn_return(func.insn_neg(&func[0]));
+    // negate as 0 - x, through a local
+    let rewritten = func.rewrite(|splice, insn| {
+        if insn.get_name() != "ineg" {
+            return Rewrite::Keep
+        }
+        let value = splice.map(insn.get_value1().unwrap());
+        let new = splice.get_func();
+        let local_var = new.new_lvalue(&get::<i32>());
+        let local = local_var.get_local().unwrap();
+        new.insn_store(&local_var, new.insn_of(0i32));
+        Rewrite::Replace(Some(new.insn_sub(local, value)))
+    }).unwrap();
+    assert!(!rewritten.dump_stable().contains("ineg"));
+    rewritten.compile().with(|neg: extern fn(i32) -> i32| assert_eq!(neg(7), -7));
+}
+
+#[test]
+fn test_delete() {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+    let local_var = func.new_lvalue(&get::<i32>());
+    let local = local_var.get_local().unwrap();
+    func.insn_store(&local_var, &func[0]);
+    func.insn_store(&local_var, func.insn_mul(local, func.insn_of(2i32)));
+    func.insn_return(local);
+    let mut seen_mul = false;
+    // drop the doubling and the store of its result
+    let rewritten = func.rewrite(|_, insn| {
+        if insn.get_name() == "imul" {
+            seen_mul = true;
+            Rewrite::Delete
+        } else if seen_mul && insn.get_name().starts_with("copy_") {
+            seen_mul = false;
+            Rewrite::Delete
+        } else {
+            Rewrite::Keep
+        }
+    }).unwrap();
+    rewritten.compile().with(|id: extern fn(i32) -> i32| assert_eq!(id(9), 9));
+}
+
+#[test]
+fn test_repoint() {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+    func.insn_return(func.insn_add(&func[0], &func[0]));
+    // make every use of the parameter use 10 instead
+    let mut first = true;
+    let rewritten = func.rewrite(|splice, insn| {
+        if first {
+            first = false;
+            let ten = splice.get_func().insn_of(10i32);
+            splice.repoint(insn.get_value1().unwrap(), ten);
+        }
+        Rewrite::Keep
+    }).unwrap();
+    rewritten.compile().with(|twenty: extern fn(i32) -> i32| assert_eq!(twenty(1), 20));
+}
+
+#[test]
+fn test_calls_not_rewritten() {
+    let mut ctx = Context::<()>::new();
+    let callee = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+    callee.insn_return(&callee[0]);
+    let func = callee.sibling(&get::<fn(i32) -> i32>());
+    let result = func.insn_call(None, &callee, None, &mut [&func[0]], CallFlags::empty());
+    func.insn_return(result);
+    assert!(func.rewrite(|_, _| Rewrite::Keep).is_none());
+}
@@ -0,0 +1,51 @@
+extern crate jit;
+use jit::*;
+
+static OUTER: u8 = 1;
+static INNER: u8 = 2;
+
+/// Check the frames are the inner one over the outer one
+extern fn walk(ctx: &Context<()>) -> usize {
+    let frames: Vec<usize> = ctx.shadow_frames().map(|descriptor| descriptor as usize).collect();
+    assert_eq!(frames, [&INNER as *const u8 as usize, &OUTER as *const u8 as usize]);
+    frames.len()
+}
+
+#[test]
+fn test_nested_frames() {
+    let mut ctx = Context::<()>::new();
+    let ctx_ptr = &ctx as *const Context<()>;
+    let inner = UncompiledFunction::new(&mut ctx, &get::<fn(usize) -> usize>());
+    let frame = inner.insn_shadow_push(&INNER as *const u8 as *const _);
+    let depth = inner.insn_call_native1(Some("walk"), walk, &get::<fn(usize) -> usize>(),
+                                        [&inner[0]], flags::CallFlags::empty());
+    inner.insn_shadow_pop(frame);
+    inner.insn_return(depth);
+    inner.insn_shadow_catcher(frame);
+    let outer = inner.sibling(&get::<fn(usize) -> usize>());
+    let inner = inner.compile();
+    let frame = outer.insn_shadow_push(&OUTER as *const u8 as *const _);
+    let depth = outer.insn_call(None, &inner, None, &mut [&outer[0]], flags::CallFlags::empty());
+    outer.insn_shadow_pop(frame);
+    outer.insn_return(depth);
+    outer.insn_shadow_catcher(frame);
+    let call: extern fn(*const Context<()>) -> usize = outer.compile().closure();
+    assert_eq!(call(ctx_ptr), 2);
+    assert_eq!(unsafe { (*ctx_ptr).shadow_frames().count() }, 0);
+}
+
+#[test]
+fn test_exception_pops_frame() {
+    let mut ctx = Context::<()>::new();
+    let ctx_ptr = &ctx as *const Context<()>;
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+    let frame = func.insn_shadow_push(&OUTER as *const u8 as *const _);
+    let quotient = func.insn_div(func.insn_of(100i32), &func[0]);
+    func.insn_shadow_pop(frame);
+    func.insn_return(quotient);
+    func.insn_shadow_catcher(frame);
+    let call = func.compile().closure_result::<i32, i32>();
+    assert_eq!(call(4), Ok(25));
+    assert_eq!(call(0), Err(JitException::Builtin(BuiltinException::DivisionByZero)));
+    assert_eq!(unsafe { (*ctx_ptr).shadow_frames().count() }, 0);
+}
@@ -0,0 +1,85 @@
+extern crate jit;
+use jit::*;
+
+#[test]
+fn test_iabs() {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(i64) -> i64>());
+    func.insn_return(func.insn_iabs(&func[0]));
+    func.compile().with(|abs: extern fn(i64) -> i64| {
+        assert_eq!(abs(-7), 7);
+        assert_eq!(abs(7), 7);
+        assert_eq!(abs(0), 0);
+        assert_eq!(abs(i64::min_value()), i64::min_value());
+    });
+}
+
+#[test]
+fn test_iabs_keeps_small_types() {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(i8) -> i8>());
+    let abs = func.insn_iabs(&func[0]);
+    assert_eq!(abs.get_type().get_kind(), get::<i8>().get_kind());
+    func.insn_return(abs);
+    func.compile().with(|abs: extern fn(i8) -> i8| {
+        assert_eq!(abs(-100), 100);
+        assert_eq!(abs(-128), -128);
+    });
+}
+
+#[test]
+fn test_iabs_unsigned() {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(u32) -> u32>());
+    func.insn_return(func.insn_iabs(&func[0]));
+    func.compile().with(|abs: extern fn(u32) -> u32| assert_eq!(abs(u32::max_value()), u32::max_value()));
+}
+
+#[test]
+fn test_fabs() {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(f32) -> f32>());
+    func.insn_return(func.insn_fabs(&func[0]));
+    func.compile().with(|abs: extern fn(f32) -> f32| {
+        assert_eq!(abs(-1.5), 1.5);
+        assert!(abs(-0.0).is_sign_positive());
+        assert_eq!(abs(-1.0 / 0.0), 1.0 / 0.0);
+        assert!(abs(0.0 / 0.0).is_nan());
+    });
+}
+
+#[test]
+fn test_isign() {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(i64) -> i32>());
+    let sign = func.insn_isign(&func[0]);
+    assert_eq!(sign.get_type().get_kind(), get::<i32>().get_kind());
+    func.insn_return(sign);
+    func.compile().with(|sign: extern fn(i64) -> i32| {
+        assert_eq!(sign(-40), -1);
+        assert_eq!(sign(0), 0);
+        assert_eq!(sign(i64::max_value()), 1);
+        assert_eq!(sign(i64::min_value()), -1);
+    });
+}
+
+#[test]
+fn test_fsign() {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(f32) -> f32>());
+    func.insn_return(func.insn_fsign(&func[0]));
+    func.compile().with(|sign: extern fn(f32) -> f32| {
+        assert_eq!(sign(3.0), 1.0);
+        assert_eq!(sign(-1.0 / 0.0), -1.0);
+        assert!(sign(0.0).is_sign_positive());
+        assert!(sign(-0.0).is_sign_negative());
+    });
+}
+
+#[test]
+#[should_panic(expected = "should be float")]
+fn test_fabs_needs_float() {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32)>());
+    func.insn_fabs(&func[0]);
+}
@@ -0,0 +1,76 @@
+extern crate jit;
+use jit::*;
+
+#[test]
+fn test_compatible() {
+    let sig = get::<fn(i32, f64) -> i64>();
+    assert!(sig.signature_compatible(&get::<fn(i32, f64) -> i64>()));
+    assert!(!sig.signature_compatible(&get::<fn(i32, f32) -> i64>()));
+    assert!(!sig.signature_compatible(&get::<fn(i32, f64) -> i32>()));
+    assert!(!sig.signature_compatible(&get::<i32>()));
+    let vararg = Type::new_signature(Abi::VarArg, &get::<i64>(), &mut [&get::<i32>(), &get::<f64>()]);
+    assert!(!sig.signature_compatible(&vararg));
+}
+
+#[test]
+fn test_mismatch() {
+    let sig = get::<fn(i32, f64) -> i64>();
+    assert_eq!(sig.check_signature(&get::<fn(i32) -> i64>()), Err(SignatureMismatch::Arity(2, 1)));
+    assert_eq!(sig.check_signature(&get::<fn(i32, i32) -> i64>()),
+               Err(SignatureMismatch::Param(1, "f64".into(), "i32".into())));
+    assert_eq!(format!("{}", sig.check_signature(&get::<fn(i32, f64) -> u8>()).unwrap_err()),
+               "The signatures return different types: expected i64, got u8");
+}
+
+#[test]
+fn test_structs() {
+    let sig = get::<fn((i32, i32)) -> i32>();
+    assert!(sig.signature_compatible(&get::<fn((i32, i32)) -> i32>()));
+    assert!(!sig.signature_compatible(&get::<fn((i32, f32)) -> i32>()));
+}
+
+#[test]
+#[should_panic]
+fn test_call_arity() {
+    let mut ctx = Context::<()>::new();
+    let callee = UncompiledFunction::new(&mut ctx, &get::<fn(i32, i32) -> i32>());
+    callee.insn_return(&callee[0]);
+    let caller = callee.sibling(&get::<fn(i32) -> i32>());
+    caller.insn_call(None, &callee, None, &mut [&caller[0]], flags::CallFlags::empty());
+}
+
+#[test]
+#[should_panic]
+fn test_call_signature() {
+    let mut ctx = Context::<()>::new();
+    let callee = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+    callee.insn_return(&callee[0]);
+    let caller = callee.sibling(&get::<fn(i32) -> i32>());
+    let sig = get::<fn(f64) -> i32>();
+    caller.insn_call(None, &callee, Some(&sig), &mut [&caller[0]], flags::CallFlags::empty());
+}
+
+#[test]
+#[should_panic]
+fn test_tail_call() {
+    let mut ctx = Context::<()>::new();
+    let callee = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i64>());
+    callee.insn_return(callee.insn_convert(&callee[0], &get::<i64>(), false));
+    let caller = callee.sibling(&get::<fn(i32) -> i32>());
+    caller.insn_call(None, &callee, None, &mut [&caller[0]], flags::CallFlags::TAIL);
+}
+
+#[test]
+#[should_panic]
+fn test_call_site_target() {
+    let mut ctx = Context::<()>::new();
+    let double = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+    double.insn_return(double.insn_add(&double[0], &double[0]));
+    let wide = double.sibling(&get::<fn(i64) -> i64>());
+    wide.insn_return(&wide[0]);
+    let caller = double.sibling(&get::<fn(i32) -> i32>());
+    let (double, wide) = (double.compile(), wide.compile());
+    let (result, site) = caller.insn_call_site(&double, &mut [&caller[0]], flags::CallFlags::empty());
+    caller.insn_return(result);
+    site.set_target(&wide);
+}
@@ -0,0 +1,62 @@
+#![cfg(feature = "testing")]
+extern crate jit;
+use jit::*;
+use jit::snapshot::{IrSnapshot, IrValue, ValueId};
+
+fn build(ctx: &mut Context<()>) -> IrSnapshot {
+    let func = UncompiledFunction::new(ctx, &get::<fn(i32) -> i32>());
+    let doubled = func.insn_mul(&func[0], func.insn_of(2i32));
+    func.insn_return(func.insn_add(doubled, &func[0]));
+    IrSnapshot::new(&func)
+}
+
+#[test]
+fn test_operands() {
+    let mut ctx = Context::<()>::new();
+    let snapshot = build(&mut ctx);
+    assert_eq!(snapshot.opcodes(), ["imul", "iadd", "return_int"]);
+    let mul = &snapshot.insns[0];
+    assert_eq!(mul.dest, Some(IrValue { id: ValueId::Temp(0), kind: TypeKind::Int }));
+    assert_eq!(mul.args[0].id, ValueId::Param(0));
+    match mul.args[1].id {
+        ValueId::Constant(_) => (),
+        ref id => panic!("Expected a constant, got {:?}", id)
+    }
+    let add = &snapshot.insns[1];
+    assert_eq!(add.args[0].id, ValueId::Temp(0));
+    assert_eq!(add.dest.as_ref().map(|dest| &dest.id), Some(&ValueId::Temp(1)));
+    assert_eq!(snapshot.insns[2].args[0].id, ValueId::Temp(1));
+}
+
+#[test]
+fn test_same_in_every_context() {
+    let mut ctx1 = Context::<()>::new();
+    let mut ctx2 = Context::<()>::new();
+    assert_eq!(build(&mut ctx1), build(&mut ctx2));
+}
+
+#[test]
+fn test_branches() {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+    let mut negative = Label::new(&func);
+    func.insn_branch_if(func.insn_lt(&func[0], func.insn_of(0i32)), &mut negative);
+    func.insn_return(&func[0]);
+    func.insn_label(&mut negative);
+    func.insn_return(func.insn_neg(&func[0]));
+    let snapshot = IrSnapshot::new(&func);
+    let branch = snapshot.insns.iter().find(|insn| insn.label.is_some()).unwrap();
+    let target = snapshot.insns.iter().find(|insn| insn.opcode == "ineg").unwrap();
+    assert!(target.block > branch.block);
+    assert_eq!(snapshot.block_labels[target.block], branch.label);
+    assert!(snapshot.to_string().contains("ineg p0"));
+}
+
+#[test]
+fn test_empty() {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn()>());
+    let snapshot = IrSnapshot::new(&func);
+    assert!(snapshot.is_empty());
+    assert_eq!(snapshot.len(), 0);
+}
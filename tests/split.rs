@@ -0,0 +1,73 @@
+extern crate jit;
+use jit::*;
+
+/// Build a function that sums the numbers below its argument in a loop,
+/// padded out with enough blocks that it has to be split
+fn build_sum(func: &UncompiledFunction) {
+    let total_var = func.new_lvalue(&get::<i32>());
+    let total = total_var.get_local().unwrap();
+    let index_var = func.new_lvalue(&get::<i32>());
+    let index = index_var.get_local().unwrap();
+    func.insn_store(&total_var, func.insn_of(0i32));
+    func.insn_store(&index_var, func.insn_of(0i32));
+    let mut head = Label::new(func);
+    let mut done = Label::new(func);
+    func.insn_label(&mut head);
+    func.insn_branch_if_not(func.insn_lt(index, &func[0]), &mut done);
+    for _ in 0..10 {
+        let mut skip = Label::new(func);
+        func.insn_branch_if(func.insn_lt(total, func.insn_of(0i32)), &mut skip);
+        func.insn_store(&total_var, func.insn_add(total, func.insn_of(0i32)));
+        func.insn_label(&mut skip);
+    }
+    func.insn_store(&total_var, func.insn_add(total, index));
+    func.insn_store(&index_var, func.insn_add(index, func.insn_of(1i32)));
+    func.insn_branch(&mut head);
+    func.insn_label(&mut done);
+    func.insn_return(total);
+}
+
+#[test]
+fn test_split_loop() {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+    build_sum(&func);
+    func.compile_split(8).unwrap().with(|sum: extern fn(i32) -> i32| {
+        assert_eq!(sum(0), 0);
+        assert_eq!(sum(5), 10);
+        assert_eq!(sum(100), 4950);
+    });
+}
+
+#[test]
+fn test_entry_keeps_storage() {
+    let mut ctx = Context::<()>::new();
+    ctx.enable_call_counts();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+    build_sum(&func);
+    let func = func.compile_split(8).unwrap();
+    func.with(|sum: extern fn(i32) -> i32| {
+        assert_eq!(sum(10), 45);
+    });
+    assert_eq!(func.get_call_count(), Some(1));
+}
+
+#[test]
+fn test_unsplittable_compiles_whole() {
+    extern fn identity(x: i32) -> i32 {
+        x
+    }
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+    let called = func.insn_call_native1(None, identity, &get::<fn(i32) -> i32>(), [&func[0]],
+                                        flags::CallFlags::empty());
+    let mut skip = Label::new(&func);
+    func.insn_branch_if(func.insn_lt(called, func.insn_of(0i32)), &mut skip);
+    func.insn_return(func.insn_of(1i32));
+    func.insn_label(&mut skip);
+    func.insn_return(func.insn_of(-1i32));
+    func.compile_split(1).unwrap().with(|sign: extern fn(i32) -> i32| {
+        assert_eq!(sign(5), 1);
+        assert_eq!(sign(-5), -1);
+    });
+}
@@ -0,0 +1,66 @@
+extern crate jit;
+use jit::*;
+
+fn strict() -> Context<()> {
+    let mut ctx = Context::<()>::new();
+    ctx.set_options(ContextOptions {
+        strict_ssa: true,
+        .. ContextOptions::default()
+    });
+    ctx
+}
+
+#[test]
+fn test_value_from_one_branch() {
+    let mut ctx = strict();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+    let mut result = None;
+    func.insn_if(func.insn_gt(&func[0], func.insn_of(0i32)), || {
+        result = Some(func.insn_mul(&func[0], func.insn_of(3i32)));
+    });
+    func.insn_return(result.unwrap());
+    assert_eq!(func.undefined_uses().len(), 1);
+    assert_eq!(func.try_compile().err(), Some(CompileError::UndefinedUse));
+}
+
+#[test]
+fn test_local_set_on_both_branches() {
+    let mut ctx = strict();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+    let result_var = func.new_lvalue(&get::<i32>());
+    let result = result_var.get_local().unwrap();
+    func.insn_if_else(func.insn_gt(&func[0], func.insn_of(0i32)), || {
+        func.insn_store(&result_var, func.insn_of(1i32));
+    }, || {
+        func.insn_store(&result_var, func.insn_of(-1i32));
+    });
+    func.insn_return(result);
+    assert!(func.undefined_uses().is_empty());
+    func.compile().with(|sign: extern fn(i32) -> i32| {
+        assert_eq!(sign(5), 1);
+        assert_eq!(sign(-5), -1);
+    });
+}
+
+#[test]
+fn test_local_set_on_one_path() {
+    let mut ctx = strict();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+    let total_var = func.new_lvalue(&get::<i32>());
+    let total = total_var.get_local().unwrap();
+    let mut done = Label::new(&func);
+    func.insn_branch_if_not(func.insn_gt(&func[0], func.insn_of(0i32)), &mut done);
+    func.insn_store(&total_var, &func[0]);
+    func.insn_label(&mut done);
+    func.insn_return(total);
+    assert_eq!(func.undefined_uses(), vec![total]);
+}
+
+#[test]
+fn test_off_by_default() {
+    let mut ctx = Context::<()>::new();
+    assert!(!ctx.get_options().strict_ssa);
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+    func.insn_return(func.insn_add(&func[0], func.insn_of(1i32)));
+    assert!(func.undefined_uses().is_empty());
+}
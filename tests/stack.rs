@@ -0,0 +1,37 @@
+extern crate jit;
+use jit::*;
+use std::sync::atomic::{AtomicUsize, Ordering, ATOMIC_USIZE_INIT};
+
+static OVERFLOWS: AtomicUsize = ATOMIC_USIZE_INIT;
+
+extern fn overflow(_: usize) {
+    OVERFLOWS.fetch_add(1, Ordering::SeqCst);
+}
+
+#[test]
+fn test_handler_called_without_headroom() {
+    let mut ctx = Context::<()>::new();
+    ctx.set_stack_guard(StackGuard {
+        headroom: !0 >> 1,
+        alloca_threshold: 4096,
+        handler: overflow
+    });
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+    func.insn_return(&func[0]);
+    let before = OVERFLOWS.load(Ordering::SeqCst);
+    func.compile().with(|func: extern fn(i32) -> i32| assert_eq!(func(5), 5));
+    assert!(OVERFLOWS.load(Ordering::SeqCst) > before);
+}
+
+#[test]
+fn test_cleared_guard() {
+    let mut ctx = Context::<()>::new();
+    ctx.set_stack_guard(StackGuard {
+        headroom: 1024,
+        alloca_threshold: 4096,
+        handler: overflow
+    });
+    assert!(ctx.get_stack_guard().is_some());
+    ctx.clear_stack_guard();
+    assert!(ctx.get_stack_guard().is_none());
+}
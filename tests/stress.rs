@@ -0,0 +1,18 @@
+#![cfg(feature = "testing")]
+extern crate jit;
+use jit::stress::{self, StressConfig};
+
+#[test]
+fn test_per_thread() {
+    let report = stress::per_thread(&StressConfig::default());
+    assert!(report.is_ok(), "{}", report);
+}
+
+#[test]
+fn test_shared() {
+    let config = StressConfig::default();
+    let report = stress::shared(&config);
+    assert!(report.is_ok(), "{}", report);
+    assert_eq!(report.built, config.shared_functions + config.iterations);
+    assert!(report.calls >= config.threads * config.iterations * config.shared_functions);
+}
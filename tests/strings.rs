@@ -0,0 +1,37 @@
+extern crate jit;
+use jit::*;
+
+#[test]
+fn test_strcmp() {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(&'static u8, &'static u8) -> i32>());
+    func.insn_return(func.insn_strcmp(&func[0], &func[1]));
+    let cmp: extern fn(*const u8, *const u8) -> i32 = func.compile().closure();
+    assert_eq!(cmp(b"jit\0".as_ptr(), b"jit\0".as_ptr()), 0);
+    assert!(cmp(b"jit\0".as_ptr(), b"jiu\0".as_ptr()) < 0);
+    assert!(cmp(b"jiz\0".as_ptr(), b"ji\0".as_ptr()) > 0);
+}
+
+#[test]
+fn test_memcmp_variable_length() {
+    static A: &'static [u8] = b"hello world";
+    static B: &'static [u8] = b"hello there";
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(usize) -> i32>());
+    let cmp = func.insn_memcmp(func.insn_of(&A[0]), func.insn_of(&B[0]), &func[0]);
+    func.insn_return(cmp);
+    func.compile().with(|cmp: extern fn(usize) -> i32| {
+        assert_eq!(cmp(5), 0);
+        assert!(cmp(7) > 0);
+    });
+}
+
+#[test]
+fn test_memcmp_inline() {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(&'static u8, &'static u8) -> i32>());
+    func.insn_return(func.insn_memcmp(&func[0], &func[1], func.insn_of(4usize)));
+    let cmp: extern fn(*const u8, *const u8) -> i32 = func.compile().closure();
+    assert!(cmp(b"abcd".as_ptr(), b"abce".as_ptr()) < 0);
+    assert!(cmp(b"abcf".as_ptr(), b"abce".as_ptr()) > 0);
+}
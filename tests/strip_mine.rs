@@ -0,0 +1,44 @@
+extern crate jit;
+use jit::*;
+
+static VALUES: [i32; 11] = [1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11];
+
+/// Build a function summing `count` i32s from the array at `values`
+fn summer<'a>(ctx: &'a mut Context<()>, width: usize) -> CompiledFunction<'a> {
+    let func = UncompiledFunction::new(ctx, &get::<fn(&'static i32, i32) -> i32>());
+    let total_var = func.new_lvalue(&get::<i32>());
+    let total = total_var.get_local().unwrap();
+    func.insn_store(&total_var, func.insn_of(0i32));
+    func.insn_for_range_vectorized(func.insn_of(0i32), &func[1], width, |index, lanes| {
+        let offset = func.insn_mul(func.insn_to_nint(index), func.insn_of(4isize));
+        let base = func.insn_add(&func[0], offset);
+        for lane in 0..lanes {
+            let value = func.insn_load_relative(base, lane * 4, &get::<i32>());
+            func.insn_store(&total_var, func.insn_add(total, value));
+        }
+    });
+    func.insn_return(total);
+    func.compile()
+}
+
+#[test]
+fn test_main_loop_and_tail() {
+    for &width in &[1, 2, 4, 8, 16] {
+        let mut ctx = Context::<()>::new();
+        let func = summer(&mut ctx, width);
+        let args: Vec<(&'static i32, i32)> = (0..VALUES.len() as i32 + 1).map(|count| (&VALUES[0], count)).collect();
+        let mut sums = vec![0; args.len()];
+        func.apply_batch(&args, &mut sums);
+        for (count, &sum) in sums.iter().enumerate() {
+            assert_eq!(sum, VALUES[..count].iter().fold(0, |total, value| total + value));
+        }
+    }
+}
+
+#[test]
+#[should_panic]
+fn test_zero_width() {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32)>());
+    func.insn_for_range_vectorized(func.insn_of(0i32), &func[0], 0, |_, _| ());
+}
@@ -0,0 +1,62 @@
+extern crate jit;
+use jit::*;
+
+#[test]
+fn test_int_table() {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(usize) -> i32>());
+    let table = func.new_table(&[-3i32, 0, 7, i32::min_value()]);
+    assert_eq!(table.get_len(), 4);
+    func.insn_return(func.insn_load_table(table, &func[0]));
+    func.compile().with(|load: extern fn(usize) -> i32| {
+        assert_eq!(load(0), -3);
+        assert_eq!(load(2), 7);
+        assert_eq!(load(3), i32::min_value());
+    });
+}
+
+#[test]
+fn test_tables_of_each_type() {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(usize) -> f64>());
+    let floats = func.new_table(&[0.25f32, -8.0]);
+    let doubles = func.new_table(&[1e100f64, 0.5]);
+    let longs = func.new_table(&[1u64 << 40, 3]);
+    let float = func.insn_convert(func.insn_load_table(floats, &func[0]), &get::<f64>(), false);
+    let long = func.insn_convert(func.insn_load_table(longs, &func[0]), &get::<f64>(), false);
+    let double = func.insn_load_table(doubles, &func[0]);
+    func.insn_return(func.insn_add(func.insn_add(float, double), long));
+    func.compile().with(|sum: extern fn(usize) -> f64| {
+        assert_eq!(sum(0), 0.25 + 1e100 + (1u64 << 40) as f64);
+        assert_eq!(sum(1), -8.0 + 0.5 + 3.0);
+    });
+}
+
+#[test]
+fn test_table_in_loop() {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(usize) -> i64>());
+    let table = func.new_table(&[1i64, 10, 100, 1000, 10000]);
+    let total_var = func.new_lvalue(&get::<i64>());
+    let total = total_var.get_local().unwrap();
+    func.insn_store(&total_var, func.insn_of(0i64));
+    func.insn_for_range(func.insn_of(0usize), &func[0], |index| {
+        func.insn_store(&total_var, func.insn_add(total, func.insn_load_table(table, index)));
+    });
+    func.insn_return(total);
+    func.compile().with(|sum: extern fn(usize) -> i64| {
+        assert_eq!(sum(0), 0);
+        assert_eq!(sum(3), 111);
+        assert_eq!(sum(5), 11111);
+    });
+}
+
+#[test]
+#[should_panic(expected = "wasn't made for this function")]
+fn test_table_of_other_function() {
+    let mut ctx = Context::<()>::new();
+    let first = UncompiledFunction::new(&mut ctx, &get::<fn(usize) -> i32>());
+    let table = first.new_table(&[1i32, 2]);
+    let second = first.sibling(&get::<fn(usize) -> i32>());
+    second.insn_load_table(table, &second[0]);
+}
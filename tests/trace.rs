@@ -0,0 +1,49 @@
+extern crate jit;
+use jit::*;
+use jit::trace::Trace;
+use std::mem;
+
+extern fn exit(id: usize, state: *mut u8) -> usize {
+    let state: &mut i32 = unsafe { mem::transmute(state) };
+    id * 1000 + *state as usize
+}
+
+#[test]
+fn test_side_exit() {
+    let mut ctx = Context::<()>::new();
+    let mut trace = Trace::new(&mut ctx, exit);
+    let value = trace.insn_load_relative(trace.state(), 0, &get::<i32>());
+    let small = trace.insn_lt(value, trace.insn_of(10i32));
+    assert_eq!(trace.guard(small), 0);
+    let trace = trace.compile();
+    assert_eq!(trace.get_exits(), 2);
+    let mut state = 3i32;
+    assert_eq!(trace.run(&mut state as *mut i32 as *mut u8), 1003);
+    let mut state = 12i32;
+    assert_eq!(trace.run(&mut state as *mut i32 as *mut u8), 12);
+}
+
+#[test]
+fn test_stitch() {
+    let mut ctx = Context::<()>::new();
+    let mut first = Trace::new(&mut ctx, exit);
+    let mut second = first.sibling(exit);
+    let value = first.insn_load_relative(first.state(), 0, &get::<i32>());
+    let positive = first.insn_gt(value, first.insn_of(0i32));
+    first.guard(positive);
+    first.exit();
+    let value = second.insn_load_relative(second.state(), 0, &get::<i32>());
+    second.insn_store_relative(second.state(), 0, second.insn_neg(value));
+    second.exit();
+    let first = first.compile();
+    let second = second.compile();
+    let mut state = -4i32;
+    assert_eq!(first.run(&mut state as *mut i32 as *mut u8), -4i32 as usize);
+    assert!(!first.is_stitched(0));
+    first.stitch(0, &second);
+    assert!(first.is_stitched(0));
+    let mut state = -4i32;
+    assert_eq!(first.run(&mut state as *mut i32 as *mut u8), 4);
+    first.unstitch(0);
+    assert!(!first.is_stitched(0));
+}
@@ -0,0 +1,57 @@
+extern crate jit;
+use jit::*;
+
+fn sum_below<'a>(func: &UncompiledFunction<'a>, start: &'a Val, end: &'a Val) -> &'a Val {
+    let sum_var = func.new_lvalue(&get::<i32>());
+    let sum = sum_var.get_local().unwrap();
+    func.insn_store(&sum_var, func.insn_of(0i32));
+    func.insn_for_range(start, end, |index| {
+        func.insn_store(&sum_var, func.insn_add(sum, index));
+    });
+    sum
+}
+
+#[test]
+fn test_unrolled_with_tail() {
+    let mut ctx = Context::<()>::new();
+    ctx.set_unroll_limits(UnrollLimits { factor: 3, max_full: 0 });
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+    let sum = sum_below(&func, func.insn_of(0i32), &func[0]);
+    func.insn_return(sum);
+    func.compile().with(|sum: extern fn(i32) -> i32| {
+        for n in 0..20 {
+            assert_eq!(sum(n), (0..n).fold(0, |sum, index| sum + index));
+        }
+    });
+}
+
+#[test]
+fn test_full_unroll() {
+    let mut ctx = Context::<()>::new();
+    ctx.set_unroll_limits(UnrollLimits { factor: 1, max_full: 8 });
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+    let sum = sum_below(&func, func.insn_of(2i32), func.insn_of(7i32));
+    func.insn_return(sum);
+    assert_eq!(func.blocks().count(), 1);
+    func.compile().with(|sum: extern fn(i32) -> i32| assert_eq!(sum(0), 2 + 3 + 4 + 5 + 6));
+}
+
+#[test]
+fn test_constant_trips_without_tail() {
+    let mut ctx = Context::<()>::new();
+    ctx.set_unroll_limits(UnrollLimits { factor: 4, max_full: 8 });
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+    let sum = sum_below(&func, func.insn_of(0i32), func.insn_of(100i32));
+    func.insn_return(sum);
+    func.compile().with(|sum: extern fn(i32) -> i32| assert_eq!(sum(0), 4950));
+}
+
+#[test]
+fn test_not_unrolled_by_default() {
+    let mut ctx = Context::<()>::new();
+    assert_eq!(ctx.get_unroll_limits(), UnrollLimits::default());
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+    let sum = sum_below(&func, func.insn_of(0i32), &func[0]);
+    func.insn_return(sum);
+    func.compile().with(|sum: extern fn(i32) -> i32| assert_eq!(sum(5), 10));
+}
@@ -0,0 +1,96 @@
+extern crate jit;
+use jit::*;
+
+#[test]
+fn test_missing_at_creation() {
+    let ctx = Context::<()>::new();
+    assert_eq!(ctx.get_unsupported(), missing_insns());
+    for function in missing_insns() {
+        assert!(!has_function(function));
+    }
+}
+
+#[test]
+fn test_falls_back_to_natives() {
+    let mut ctx = Context::<()>::new();
+    ctx.set_unsupported(&["jit_insn_rint", "jit_insn_round", "jit_insn_trunc"]);
+    assert_eq!(ctx.get_unsupported(), ["jit_insn_rint", "jit_insn_round", "jit_insn_trunc"]);
+    assert!(ctx.extern_snapshot().get("jit_math_trunc").is_some());
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(f32) -> f32>());
+    let sum = func.insn_add(func.insn_add(func.insn_rint(&func[0]), func.insn_round(&func[0])), func.insn_trunc(&func[0]));
+    func.insn_return(sum);
+    assert!(!func.dump_stable().contains("round"));
+    func.compile().with(|sum: extern fn(f32) -> f32| {
+        assert_eq!(sum(2.5), 2.0 + 3.0 + 2.0);
+        assert_eq!(sum(-1.5), -2.0 - 2.0 - 1.0);
+    });
+}
+
+#[test]
+fn test_require_insn() {
+    let mut ctx = Context::<()>::new();
+    ctx.set_unsupported(&["jit_insn_address_of_label"]);
+    assert_eq!(ctx.require_insn("jit_insn_address_of_label"), Err(Unsupported { function: "jit_insn_address_of_label" }));
+    assert_eq!(ctx.require_insn("jit_insn_round"), Ok(()));
+    ctx.set_unsupported(&[]);
+    assert_eq!(ctx.require_insn("jit_insn_address_of_label"), Ok(()));
+}
+
+#[test]
+fn test_poisons_without_fallback() {
+    let mut ctx = ContextBuilder::new()
+        .options(ContextOptions {
+            defer_errors: true,
+            .. ContextOptions::default()
+        })
+        .build()
+        .unwrap();
+    ctx.set_unsupported(&["jit_insn_address_of_label"]);
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn() -> usize>());
+    let mut label = Label::new(&func);
+    let address = func.insn_address_of_label(&mut label);
+    func.insn_label(&mut label);
+    func.insn_return(func.insn_convert(address, &get::<usize>(), false));
+    match func.try_compile() {
+        Err(CompileError::Poisoned(error)) => assert!(error.message.contains("jit_insn_address_of_label")),
+        _ => panic!("compiled a function using a missing instruction")
+    }
+}
+
+#[test]
+#[should_panic]
+fn test_unknown_function() {
+    let mut ctx = Context::<()>::new();
+    ctx.set_unsupported(&["jit_insn_add"]);
+}
+
+#[test]
+fn test_probe_matches_links() {
+    let missing = missing_insns();
+    for &function in &["jit_insn_address_of_label", "jit_insn_rint", "jit_insn_round", "jit_insn_trunc"] {
+        assert_eq!(has_function(function), !missing.contains(&function));
+    }
+    assert!(has_function("jit_context_create"));
+    if !is_static() {
+        assert!(!has_function("jit_insn_that_does_not_exist"));
+    }
+}
+
+#[test]
+fn test_rounding_whether_linked_or_not() {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(f64) -> f64>());
+    func.insn_return(func.insn_add(func.insn_round(&func[0]), func.insn_trunc(&func[0])));
+    func.compile().with(|f: extern fn(f64) -> f64| {
+        assert_eq!(f(2.5), 3.0 + 2.0);
+        assert_eq!(f(-2.5), -3.0 - 2.0);
+    });
+}
+
+#[test]
+fn test_linked_version() {
+    if let Some(version) = version() {
+        assert_eq!(Version::parse(&version.to_string()), Some(version));
+    }
+    assert_eq!(Version::parse("0.1.3-rc1"), Some(Version::new(0, 1, 3)));
+}
@@ -0,0 +1,36 @@
+extern crate jit;
+use jit::*;
+
+extern fn noisy(x: i32) -> i32 {
+    x
+}
+
+#[test]
+fn test_dropped_results_found() {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32, i32) -> i32>());
+    let sum = func.insn_add(&func[0], &func[1]);
+    let product = func.insn_mul(&func[0], &func[1]);
+    let used = func.insn_sub(&func[0], &func[1]);
+    func.insn_return(used);
+    assert_eq!(func.unused_values(), vec![sum, product]);
+}
+
+#[test]
+fn test_all_used() {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32, i32) -> i32>());
+    let sum = func.insn_add(&func[0], &func[1]);
+    func.insn_return(func.insn_mul(sum, &func[1]));
+    assert!(func.unused_values().is_empty());
+}
+
+#[test]
+fn test_call_results_skipped() {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+    func.insn_call_native1(Some("noisy"), noisy, &get::<fn(i32) -> i32>(),
+                           [&func[0]], flags::CallFlags::empty());
+    func.insn_return(&func[0]);
+    assert!(func.unused_values().is_empty());
+}
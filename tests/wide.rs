@@ -0,0 +1,75 @@
+extern crate jit;
+use jit::*;
+
+fn helpers_context() -> Context<()> {
+    let mut ctx = Context::<()>::new();
+    ctx.set_long_helpers(true);
+    ctx
+}
+
+#[test]
+fn test_registered() {
+    let ctx = helpers_context();
+    assert!(ctx.uses_long_helpers());
+    let snapshot = ctx.extern_snapshot();
+    for name in &["jit_long_div", "jit_ulong_rem", "jit_long_shl", "jit_ulong_shr", "jit_f64_to_long"] {
+        assert!(snapshot.get(name).is_some(), "{} wasn't registered", name);
+    }
+}
+
+#[test]
+fn test_div_rem() {
+    let mut ctx = helpers_context();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(i64) -> i64>());
+    let seven = func.insn_of(7i64);
+    func.insn_return(func.insn_add(func.insn_mul(func.insn_div(&func[0], seven), func.insn_of(1000i64)),
+                                   func.insn_rem(&func[0], seven)));
+    func.compile().with(|divmod: extern fn(i64) -> i64| {
+        assert_eq!(divmod(50_000_000_003), 7_142_857_143_000 + 2);
+        assert_eq!(divmod(-15), -2_000 - 1);
+    });
+}
+
+#[test]
+fn test_unsigned_div() {
+    let mut ctx = helpers_context();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(u64) -> u64>());
+    func.insn_return(func.insn_div(&func[0], func.insn_of(3u64)));
+    func.compile().with(|third: extern fn(u64) -> u64| {
+        assert_eq!(third(u64::max_value()), u64::max_value() / 3);
+    });
+}
+
+#[test]
+fn test_shifts() {
+    let mut ctx = helpers_context();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(i64) -> i64>());
+    let amount = func.insn_of(40i32);
+    func.insn_return(func.insn_shr(func.insn_shl(&func[0], amount), func.insn_of(8i32)));
+    func.compile().with(|shift: extern fn(i64) -> i64| {
+        assert_eq!(shift(1), 1 << 32);
+        assert_eq!(shift(-1), -1 << 32);
+    });
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(i64) -> i64>());
+    func.insn_return(func.insn_ushr(&func[0], func.insn_of(60i32)));
+    func.compile().with(|top: extern fn(i64) -> i64| {
+        assert_eq!(top(-1), 15);
+    });
+}
+
+#[test]
+fn test_float_conversions() {
+    let mut ctx = helpers_context();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(i64) -> f64>());
+    func.insn_return(func.insn_convert(&func[0], &get::<f64>(), false));
+    func.compile().with(|to_float: extern fn(i64) -> f64| {
+        assert_eq!(to_float(1 << 53), 9007199254740992.0);
+        assert_eq!(to_float(-3), -3.0);
+    });
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(f64) -> u64>());
+    func.insn_return(func.insn_convert(&func[0], &get::<u64>(), false));
+    func.compile().with(|to_int: extern fn(f64) -> u64| {
+        assert_eq!(to_int(1e19), 10_000_000_000_000_000_000);
+        assert_eq!(to_int(-1.0), 0);
+    });
+}